@@ -0,0 +1,528 @@
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format, Lock, Predicate,
+        SerializedComponent,
+    },
+    Entity,
+};
+use log::*;
+use std::time::SystemTime;
+
+use crate::{cache::Contents, CachedBackend};
+
+impl<F: Format, A: AccessBackend<F>> AccessBackend<F> for CachedBackend<F, A> {
+    fn supports_atomic_writes(&self) -> bool {
+        self.inner.supports_atomic_writes()
+    }
+
+    fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        let names = names(&components);
+        let written = self.inner.write_components(entity, components);
+        self.forget(entity, &names);
+        written
+    }
+
+    fn write_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        let names = names(&components);
+        let written = self.inner.write_components_locked(entity, components, lock);
+        self.forget(entity, &names);
+        written
+    }
+
+    fn write_components_batch(
+        &self,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        let written: Vec<(Entity, Vec<String>)> = batch
+            .iter()
+            .map(|(entity, components)| (*entity, names(components)))
+            .collect();
+
+        let result = self.inner.write_components_batch(batch);
+        for (entity, names) in written {
+            self.forget(entity, &names);
+        }
+        result
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        let names = names(&components);
+        let written = self.inner.write_components_if_absent(entity, components);
+        self.forget(entity, &names);
+        written
+    }
+
+    fn write_components_if_absent_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        let names = names(&components);
+        let written = self
+            .inner
+            .write_components_if_absent_locked(entity, components, lock);
+        self.forget(entity, &names);
+        written
+    }
+
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        let names = names(&components);
+        let written = self.inner.update_components(entity, components);
+        self.forget(entity, &names);
+        written
+    }
+
+    fn update_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        let names = names(&components);
+        let written = self
+            .inner
+            .update_components_locked(entity, components, lock);
+        self.forget(entity, &names);
+        written
+    }
+
+    fn update_component_if_unchanged(
+        &self,
+        entity: Entity,
+        component: SerializedComponent<F>,
+        revision: u64,
+    ) -> Result<(), AccessError> {
+        let name = component.name.clone();
+        let written = self
+            .inner
+            .update_component_if_unchanged(entity, component, revision);
+        self.forget(entity, &[name]);
+        written
+    }
+
+    /// Reads the components which are not cached from the inner backend in
+    /// one request, caching what it returns.
+    fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        let now = self.clock.now();
+        let (mut cached, generation) = {
+            let mut cache = self.cache.lock().unwrap();
+            let cached: Vec<Option<Contents>> = descriptors
+                .iter()
+                .map(|descriptor| cache.get(entity, &descriptor.name, now, self.ttl))
+                .collect();
+            (cached, cache.generation())
+        };
+
+        let missing: Vec<usize> = (0..descriptors.len())
+            .filter(|at| cached[*at].is_none())
+            .collect();
+
+        if missing.is_empty() {
+            trace!(
+                "read {} components of {entity} from the cache",
+                descriptors.len()
+            );
+        } else {
+            // Reading without versions caches components as they are stored,
+            // and leaves checking the versions to the loop below.
+            let read = self.inner.read_components(
+                entity,
+                missing
+                    .iter()
+                    .map(|at| ExtractionDescriptor {
+                        name: descriptors[*at].name.clone(),
+                        version: None,
+                        schema: None,
+                    })
+                    .collect(),
+            )?;
+
+            let mut cache = self.cache.lock().unwrap();
+            for (at, component) in missing.into_iter().zip(read) {
+                let contents: Contents = component.map(|component| {
+                    (
+                        component.version,
+                        component.contents.into(),
+                        component.metadata,
+                    )
+                });
+                cache.insert(
+                    generation,
+                    entity,
+                    &descriptors[at].name,
+                    contents.clone(),
+                    now,
+                );
+                cached[at] = Some(contents);
+            }
+        }
+
+        descriptors
+            .into_iter()
+            .zip(cached)
+            .map(|(descriptor, contents)| {
+                let Some((version, contents, metadata)) = contents.flatten() else {
+                    return Ok(None);
+                };
+
+                if let Some(expected) = descriptor.version.filter(|expected| *expected != version) {
+                    return Err(AccessError::VersionMismatch {
+                        component: descriptor.name,
+                        stored: version,
+                        expected,
+                    });
+                }
+
+                Ok(Some(SerializedComponent {
+                    contents: F::Data::from(contents),
+                    name: descriptor.name,
+                    version,
+                    schema: None,
+                    metadata,
+                }))
+            })
+            .collect()
+    }
+
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        let names: Vec<String> = descriptors.iter().map(|d| d.name.clone()).collect();
+        let removed = self.inner.remove_components(entity, descriptors);
+        self.forget(entity, &names);
+        removed
+    }
+
+    fn remove_components_locked(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        let names: Vec<String> = descriptors.iter().map(|d| d.name.clone()).collect();
+        let removed = self
+            .inner
+            .remove_components_locked(entity, descriptors, lock);
+        self.forget(entity, &names);
+        removed
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        self.inner.entities_with(component)
+    }
+
+    fn modified_since(
+        &self,
+        component: &str,
+        since: SystemTime,
+    ) -> Result<Vec<Entity>, AccessError> {
+        self.inner.modified_since(component, since)
+    }
+
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        self.inner.list_entities()
+    }
+
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        self.inner.list_components(entity)
+    }
+
+    fn contains(&self, entity: Entity, names: &[String]) -> Result<Vec<bool>, AccessError> {
+        self.inner.contains(entity, names)
+    }
+
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        self.inner.stats()
+    }
+
+    fn find_entities_where(&self, predicates: Vec<Predicate>) -> Result<Vec<Entity>, AccessError> {
+        self.inner.find_entities_where(predicates)
+    }
+}
+
+impl<F: Format, A: AccessBackend<F>> CachedBackend<F, A> {
+    /// Forgets the components once they have been written, whether or not
+    /// writing succeeded. Reads which were underway in the meantime do not
+    /// cache what they read, since the write may have happened after it.
+    fn forget(&self, entity: Entity, names: &[String]) {
+        let mut cache = self.cache.lock().unwrap();
+        for name in names {
+            cache.invalidate(entity, name);
+        }
+    }
+}
+
+fn names<F: Format>(components: &[SerializedComponent<F>]) -> Vec<String> {
+    components
+        .iter()
+        .map(|component| component.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use eci_backend_memory::MemoryBackend;
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format, ManualClock,
+            SerializedComponent,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::CachedBackend;
+
+    /// Counts the reads which make it through to the inner backend.
+    #[derive(Default)]
+    struct Spy {
+        inner: MemoryBackend,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl AccessBackend<Json> for Spy {
+        fn write_components(
+            &self,
+            entity: Entity,
+            components: Vec<SerializedComponent<Json>>,
+        ) -> Result<(), AccessError> {
+            self.inner.write_components(entity, components)
+        }
+
+        fn update_components(
+            &self,
+            entity: Entity,
+            components: Vec<SerializedComponent<Json>>,
+        ) -> Result<(), AccessError> {
+            self.inner.update_components(entity, components)
+        }
+
+        fn read_components(
+            &self,
+            entity: Entity,
+            descriptors: Vec<ExtractionDescriptor>,
+        ) -> Result<Vec<Option<SerializedComponent<Json>>>, AccessError> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.inner.read_components(entity, descriptors)
+        }
+
+        fn remove_components(
+            &self,
+            entity: Entity,
+            descriptors: Vec<ExtractionDescriptor>,
+        ) -> Result<Vec<Option<SerializedComponent<Json>>>, AccessError> {
+            self.inner.remove_components(entity, descriptors)
+        }
+
+        fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+            AccessBackend::<Json>::entities_with(&self.inner, component)
+        }
+
+        fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+            AccessBackend::<Json>::list_entities(&self.inner)
+        }
+
+        fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+            AccessBackend::<Json>::list_components(&self.inner, entity)
+        }
+
+        fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+            AccessBackend::<Json>::stats(&self.inner)
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    fn cached() -> (CachedBackend<Json, Spy>, Arc<AtomicUsize>) {
+        let spy = Spy::default();
+        let reads = spy.reads.clone();
+        (CachedBackend::new(spy, 16), reads)
+    }
+
+    fn component(name: &str, content: &str) -> SerializedComponent<Json> {
+        SerializedComponent::<Json> {
+            contents: Json::serialize(DebugComponentA {
+                content: content.to_string(),
+            })
+            .unwrap(),
+            name: name.to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        }
+    }
+
+    fn extract(name: &str) -> ExtractionDescriptor {
+        ExtractionDescriptor {
+            name: name.to_string(),
+            version: None,
+            schema: None,
+        }
+    }
+
+    fn read(conn: &CachedBackend<Json, Spy>, entity: Entity, names: &[&str]) -> Vec<String> {
+        conn.read_components(entity, names.iter().map(|name| extract(name)).collect())
+            .unwrap()
+            .iter()
+            .map(|component| match component {
+                Some(component) => {
+                    Json::deserialize::<DebugComponentA>(&component.contents)
+                        .unwrap()
+                        .content
+                }
+                None => "missing".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cached_backends_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CachedBackend<Json, MemoryBackend>>();
+    }
+
+    #[test]
+    fn serve_repeated_reads_from_the_cache() {
+        let (conn, reads) = cached();
+        let entity = Entity::new();
+        conn.write_components(entity, vec![component("CounterA", "a")])
+            .unwrap();
+
+        assert_eq!(
+            read(&conn, entity, &["CounterA", "CounterB"]),
+            ["a", "missing"]
+        );
+        assert_eq!(
+            read(&conn, entity, &["CounterA", "CounterB"]),
+            ["a", "missing"]
+        );
+        assert_eq!(read(&conn, entity, &["CounterB"]), ["missing"]);
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+
+        // Only the components which are not cached are read.
+        conn.write_components(Entity::new(), vec![component("CounterC", "c")])
+            .unwrap();
+        assert_eq!(
+            read(&conn, entity, &["CounterC", "CounterA"]),
+            ["missing", "a"]
+        );
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+        assert_eq!(conn.len(), 3);
+    }
+
+    #[test]
+    fn writes_invalidate_cached_components() {
+        let (conn, reads) = cached();
+        let entity = Entity::new();
+        conn.write_components(entity, vec![component("CounterA", "a")])
+            .unwrap();
+        assert_eq!(
+            read(&conn, entity, &["CounterA", "CounterB"]),
+            ["a", "missing"]
+        );
+
+        conn.update_components(entity, vec![component("CounterA", "updated")])
+            .unwrap();
+        assert_eq!(
+            read(&conn, entity, &["CounterA", "CounterB"]),
+            ["updated", "missing"]
+        );
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+
+        conn.write_components(entity, vec![component("CounterB", "b")])
+            .unwrap();
+        assert_eq!(read(&conn, entity, &["CounterB"]), ["b"]);
+
+        conn.remove_components(entity, vec![extract("CounterA")])
+            .unwrap();
+        assert_eq!(read(&conn, entity, &["CounterA"]), ["missing"]);
+        assert_eq!(reads.load(Ordering::SeqCst), 4);
+
+        conn.invalidate(entity);
+        assert!(conn.is_empty());
+    }
+
+    #[test]
+    fn entries_expire() {
+        let clock = ManualClock::default();
+        let (conn, reads) = cached();
+        let conn = conn
+            .with_ttl(Duration::from_secs(30))
+            .with_clock(clock.clone());
+
+        let entity = Entity::new();
+        conn.write_components(entity, vec![component("CounterA", "a")])
+            .unwrap();
+
+        read(&conn, entity, &["CounterA"]);
+        clock.advance(Duration::from_secs(20));
+        read(&conn, entity, &["CounterA"]);
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+
+        clock.advance(Duration::from_secs(10));
+        read(&conn, entity, &["CounterA"]);
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn check_versions_of_cached_components() {
+        let (conn, reads) = cached();
+        let entity = Entity::new();
+        conn.write_components(entity, vec![component("CounterA", "a")])
+            .unwrap();
+        read(&conn, entity, &["CounterA"]);
+
+        assert!(matches!(
+            conn.read_components(
+                entity,
+                vec![ExtractionDescriptor {
+                    name: "CounterA".to_string(),
+                    version: Some(Version::new(2, 0, 0)),
+                    schema: None,
+                }],
+            ),
+            Err(AccessError::VersionMismatch { stored, .. }) if stored == Version::default()
+        ));
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, SystemTime},
+};
+
+use eci_core::{backend::ComponentMetadata, Entity, Version};
+
+/// What the inner backend returned for a component: its version, contents
+/// and metadata, or `None` if the entity did not have it.
+pub(crate) type Contents = Option<(Version, Vec<u8>, Option<ComponentMetadata>)>;
+
+struct Entry {
+    contents: Contents,
+    cached_at: SystemTime,
+    /// When the entry was last used, as a position in `recency`.
+    used: u64,
+}
+
+/// Components by entity and name, evicting the least recently used once
+/// there are more than `max_entries` of them.
+pub(crate) struct Cache {
+    max_entries: usize,
+    entries: HashMap<Entity, HashMap<String, Entry>>,
+    recency: BTreeMap<u64, (Entity, String)>,
+    tick: u64,
+    /// Bumped whenever entries are invalidated, so reads which started
+    /// before then do not cache what they read.
+    generation: u64,
+}
+
+impl Cache {
+    pub fn new(max_entries: usize) -> Self {
+        Cache {
+            max_entries,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            generation: 0,
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Looks up the component, forgetting it if it was cached longer than
+    /// the ttl ago.
+    pub fn get(
+        &mut self,
+        entity: Entity,
+        name: &str,
+        now: SystemTime,
+        ttl: Option<Duration>,
+    ) -> Option<Contents> {
+        let entry = self.entries.get_mut(&entity)?.get_mut(name)?;
+
+        let expired = ttl.is_some_and(|ttl| {
+            now.duration_since(entry.cached_at)
+                .is_ok_and(|age| age >= ttl)
+        });
+        if expired {
+            self.remove(entity, name);
+            return None;
+        }
+
+        self.recency.remove(&entry.used);
+        self.tick += 1;
+        entry.used = self.tick;
+        self.recency.insert(self.tick, (entity, name.to_string()));
+
+        Some(entry.contents.clone())
+    }
+
+    /// Caches what was read, unless entries were invalidated since the read
+    /// started at the given generation, since it may be outdated already.
+    pub fn insert(
+        &mut self,
+        generation: u64,
+        entity: Entity,
+        name: &str,
+        contents: Contents,
+        now: SystemTime,
+    ) {
+        if generation != self.generation || self.max_entries == 0 {
+            return;
+        }
+
+        self.remove(entity, name);
+        self.tick += 1;
+        self.entries.entry(entity).or_default().insert(
+            name.to_string(),
+            Entry {
+                contents,
+                cached_at: now,
+                used: self.tick,
+            },
+        );
+        self.recency.insert(self.tick, (entity, name.to_string()));
+
+        while self.recency.len() > self.max_entries {
+            let Some((_, (entity, name))) = self.recency.pop_first() else {
+                break;
+            };
+            self.remove(entity, &name);
+        }
+    }
+
+    pub fn invalidate(&mut self, entity: Entity, name: &str) {
+        self.generation += 1;
+        self.remove(entity, name);
+    }
+
+    pub fn invalidate_entity(&mut self, entity: Entity) {
+        self.generation += 1;
+        if let Some(components) = self.entries.remove(&entity) {
+            for entry in components.values() {
+                self.recency.remove(&entry.used);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.recency.len()
+    }
+
+    fn remove(&mut self, entity: Entity, name: &str) {
+        let Some(components) = self.entries.get_mut(&entity) else {
+            return;
+        };
+
+        if let Some(entry) = components.remove(name) {
+            self.recency.remove(&entry.used);
+        }
+        if components.is_empty() {
+            self.entries.remove(&entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use eci_core::{Entity, Version};
+
+    use super::Cache;
+
+    #[test]
+    fn evict_least_recently_used() {
+        let mut cache = Cache::new(2);
+        let entity = Entity::new();
+        let now = SystemTime::now();
+        let contents = Some((Version::default(), b"{}".to_vec(), None));
+
+        cache.insert(0, entity, "CounterA", contents.clone(), now);
+        cache.insert(0, entity, "CounterB", None, now);
+        assert!(cache.get(entity, "CounterA", now, None).is_some());
+
+        cache.insert(0, entity, "CounterC", contents.clone(), now);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(entity, "CounterB", now, None).is_none());
+        assert_eq!(cache.get(entity, "CounterA", now, None), Some(contents));
+    }
+
+    #[test]
+    fn expire_and_invalidate() {
+        let mut cache = Cache::new(10);
+        let entity = Entity::new();
+        let now = SystemTime::now();
+        let ttl = Some(Duration::from_secs(10));
+
+        cache.insert(0, entity, "CounterA", None, now);
+        assert_eq!(cache.get(entity, "CounterA", now, ttl), Some(None));
+        assert_eq!(
+            cache.get(entity, "CounterA", now + Duration::from_secs(10), ttl),
+            None
+        );
+        assert_eq!(cache.len(), 0);
+
+        cache.insert(0, entity, "CounterA", None, now);
+        let generation = cache.generation();
+        cache.invalidate_entity(entity);
+        assert_eq!(cache.len(), 0);
+
+        // Reads which started before the invalidation are not cached.
+        cache.insert(generation, entity, "CounterA", None, now);
+        assert_eq!(cache.len(), 0);
+    }
+}
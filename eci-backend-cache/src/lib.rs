@@ -0,0 +1,104 @@
+//! Keeps recently read components in memory in front of another access
+//! backend, for worlds whose storage is slow to reach but where most reads
+//! go to a small set of hot components.
+//!
+//! [`CachedBackend`] remembers what the inner backend returned for each
+//! entity and component, including that the entity does not have it, up to
+//! a number of entries, evicting the least recently used. Writes and
+//! removals go straight through to the inner backend and forget the
+//! components they touch, whether or not they succeed.
+//!
+//! The cache only sees changes made through it. Entries written by other
+//! processes sharing the inner backend are not noticed until the entries
+//! expire, so give them a time to live with [`CachedBackend::with_ttl`]
+//! unless this process is the only writer.
+//!
+//! It wraps an [`AccessBackend`], and is paired with a locking backend
+//! through [`Backend::from_disjoint`]:
+//!
+//! ```ignore
+//! let backend: Backend<Json> = Backend::from_disjoint(
+//!     CachedBackend::new(SqliteBackend::open("world.db")?, 10_000),
+//!     RedisLockingBackend::open("redis://127.0.0.1/")?,
+//! );
+//! ```
+//!
+//! [`AccessBackend`]: eci_core::backend::AccessBackend
+//! [`Backend::from_disjoint`]: eci_core::backend::Backend::from_disjoint
+
+mod access;
+mod cache;
+
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use cache::Cache;
+use eci_core::{
+    backend::{AccessBackend, Clock, Format, SystemClock},
+    Entity,
+};
+
+/// Caches the components read through the inner backend.
+pub struct CachedBackend<F: Format, A: AccessBackend<F>> {
+    inner: A,
+    cache: Mutex<Cache>,
+    ttl: Option<Duration>,
+    clock: Arc<dyn Clock>,
+    format: PhantomData<fn() -> F>,
+}
+
+impl<F: Format, A: AccessBackend<F>> CachedBackend<F, A> {
+    /// Caches up to `max_entries` components read through the backend, each
+    /// of which is kept until it is evicted or written.
+    pub fn new(inner: A, max_entries: usize) -> Self {
+        CachedBackend {
+            inner,
+            cache: Mutex::new(Cache::new(max_entries)),
+            ttl: None,
+            clock: Arc::new(SystemClock),
+            format: PhantomData,
+        }
+    }
+
+    /// Reads components from the inner backend again once they have been
+    /// cached for longer than the ttl.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Replaces the clock used for expiring entries.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The backend being cached.
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    /// Forgets every cached component of the entity, such as after it was
+    /// changed by someone else.
+    pub fn invalidate(&self, entity: Entity) {
+        self.cache.lock().unwrap().invalidate_entity(entity);
+    }
+
+    /// Forgets every cached component.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Number of components currently cached, including expired ones which
+    /// have yet to be evicted.
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,189 @@
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format, Lock,
+        SerializedComponent,
+    },
+    Entity,
+};
+
+use crate::{
+    wire::{self, Component, Stats, WriteMode},
+    HttpBackend,
+};
+
+impl<F: Format> AccessBackend<F> for HttpBackend {
+    /// Whether the backend served on the other end writes atomically, as
+    /// reported when connecting.
+    fn supports_atomic_writes(&self) -> bool {
+        self.atomic_writes
+    }
+
+    fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.write(entity, WriteMode::Insert, None, components)
+    }
+
+    fn write_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        self.write(entity, WriteMode::Insert, Some(lock), components)
+    }
+
+    fn write_components_batch(
+        &self,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        let batch: Vec<(Entity, Vec<Component>)> = batch
+            .into_iter()
+            .map(|(entity, components)| {
+                (
+                    entity,
+                    components.into_iter().map(Component::from).collect(),
+                )
+            })
+            .collect();
+
+        Ok(self.send(self.request("PUT", "/entities"), &batch)?)
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.write(entity, WriteMode::IfAbsent, None, components)
+    }
+
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.write(entity, WriteMode::Update, None, components)
+    }
+
+    fn update_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        self.write(entity, WriteMode::Update, Some(lock), components)
+    }
+
+    fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        let components: Vec<Option<Component>> = self.fetch(
+            self.request("GET", &format!("/entities/{entity}/components"))
+                .query("names", &wire::describe(&descriptors)),
+        )?;
+
+        serialized(components)
+    }
+
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.remove(entity, None, descriptors)
+    }
+
+    fn remove_components_locked(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.remove(entity, Some(lock), descriptors)
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        Ok(self.fetch(self.request("GET", "/entities").query("with", component))?)
+    }
+
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        Ok(self.fetch(self.request("GET", "/entities"))?)
+    }
+
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        Ok(self.fetch(self.request("GET", &format!("/entities/{entity}/components")))?)
+    }
+
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        let stats: Vec<Stats> = self.fetch(self.request("GET", "/stats"))?;
+        Ok(stats.into_iter().map(ComponentStats::from).collect())
+    }
+}
+
+impl HttpBackend {
+    fn write<F: Format>(
+        &self,
+        entity: Entity,
+        mode: WriteMode,
+        lock: Option<&Lock>,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        let write = wire::Write {
+            mode,
+            lock: lock.map(Lock::id),
+            components: components.into_iter().map(Component::from).collect(),
+        };
+
+        Ok(self.send(
+            self.request("PUT", &format!("/entities/{entity}/components")),
+            &write,
+        )?)
+    }
+
+    fn remove<F: Format>(
+        &self,
+        entity: Entity,
+        lock: Option<&Lock>,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut request = self
+            .request("DELETE", &format!("/entities/{entity}/components"))
+            .query("names", &wire::describe(&descriptors));
+        if let Some(lock) = lock {
+            request = request.query("lock", &lock.id());
+        }
+
+        let components: Vec<Option<Component>> = self.fetch(request)?;
+        serialized(components)
+    }
+}
+
+fn serialized<F: Format>(
+    components: Vec<Option<Component>>,
+) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+    components
+        .into_iter()
+        .map(|component| {
+            component
+                .map(|component| {
+                    component
+                        .into_serialized()
+                        .map_err(|err| err.into_access(200))
+                })
+                .transpose()
+        })
+        .collect()
+}
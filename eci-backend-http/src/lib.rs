@@ -0,0 +1,163 @@
+//! Reaches a backend served over HTTP, so the state of a world can be kept
+//! behind a single service which other processes talk to.
+//!
+//! [`HttpBackend`] implements the backend traits by sending JSON requests
+//! to these routes, which the [`server`] module, behind the `server`
+//! feature, serves on top of any [`Backend`]:
+//!
+//! | Route | Operation |
+//! |-------|-----------|
+//! | `GET /` | Describes the backend |
+//! | `GET /entities` | Lists entities, or those with the component given as `with` |
+//! | `PUT /entities` | Writes components for many entities |
+//! | `PUT /entities/{id}/components` | Inserts, updates or writes missing components |
+//! | `GET /entities/{id}/components` | Reads the components given as `names`, or lists them all |
+//! | `DELETE /entities/{id}/components` | Removes the components given as `names` |
+//! | `GET /stats` | Counts components |
+//! | `POST /locks` | Acquires a lock on one or more entities |
+//! | `GET /locks` | Lists locks, on the entity given as `entity` if any |
+//! | `GET /locks/stats` | Counts locks |
+//! | `DELETE /locks/{id}` | Releases a lock |
+//! | `POST /locks/{id}/renew` | Renews a lock |
+//! | `POST /locks/{id}/upgrade` | Upgrades a read lock to a write lock |
+//! | `DELETE /admin/locks/{id}` | Forcibly releases a lock |
+//! | `DELETE /admin/entities/{id}/locks` | Forcibly releases every lock on an entity |
+//!
+//! Components are named in `names` as `Name`, or `Name:1.0.0` to read them
+//! as that version. Failed requests respond with the error the backend
+//! reported, such as a 409 carrying the entity and component which
+//! conflicted, so the client hands back the same error.
+//!
+//! [`Backend`]: eci_core::backend::Backend
+
+mod access;
+mod lock;
+#[cfg(feature = "server")]
+pub mod server;
+mod wire;
+
+use std::{error::Error, fmt::Display, time::Duration};
+
+use eci_core::backend::{AccessError, Format, JointBackend, LockingError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Talks to a backend served over HTTP.
+pub struct HttpBackend {
+    agent: ureq::Agent,
+    url: String,
+    atomic_writes: bool,
+}
+
+/// Body of `GET /`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Info {
+    pub atomic_writes: bool,
+}
+
+/// Reads through the default implementation, which takes the locks first
+/// and releases them again if reading fails.
+impl<F: Format> JointBackend<F> for HttpBackend {}
+
+impl HttpBackend {
+    /// Connects to the server at the url, such as `http://127.0.0.1:8080`,
+    /// asking it to describe the backend it serves.
+    pub fn connect(url: &str) -> Result<Self, AccessError> {
+        Self::connect_with(url, ureq::AgentBuilder::new().build())
+    }
+
+    /// Like [`HttpBackend::connect`], but gives up on requests which take
+    /// longer than the timeout.
+    pub fn connect_with_timeout(url: &str, timeout: Duration) -> Result<Self, AccessError> {
+        Self::connect_with(url, ureq::AgentBuilder::new().timeout(timeout).build())
+    }
+
+    fn connect_with(url: &str, agent: ureq::Agent) -> Result<Self, AccessError> {
+        let mut backend = HttpBackend {
+            agent,
+            url: url.trim_end_matches('/').to_string(),
+            atomic_writes: false,
+        };
+
+        let info: Info = backend.fetch(backend.request("GET", "/"))?;
+        backend.atomic_writes = info.atomic_writes;
+        Ok(backend)
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        self.agent.request(method, &format!("{}{path}", self.url))
+    }
+
+    fn fetch<T: DeserializeOwned>(&self, request: ureq::Request) -> Result<T, Failure> {
+        respond(request.call())
+    }
+
+    fn send<T: DeserializeOwned, B: Serialize>(
+        &self,
+        request: ureq::Request,
+        body: &B,
+    ) -> Result<T, Failure> {
+        respond(request.send_json(body))
+    }
+}
+
+fn respond<T: DeserializeOwned>(
+    response: Result<ureq::Response, ureq::Error>,
+) -> Result<T, Failure> {
+    match response {
+        Ok(response) => response
+            .into_json()
+            .map_err(|err| Failure::Transport(Box::new(err))),
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            Err(match serde_json::from_str(&body) {
+                Ok(err) => Failure::Remote(status, err),
+                Err(_) => Failure::Transport(Box::new(RemoteError {
+                    status,
+                    message: body,
+                })),
+            })
+        }
+        Err(err) => Err(Failure::Transport(Box::new(err))),
+    }
+}
+
+/// Why a request failed: either the backend on the other end reported an
+/// error, or the request never got an answer it could make sense of.
+pub(crate) enum Failure {
+    Remote(u16, wire::Error),
+    Transport(Box<dyn Error + Send + Sync>),
+}
+
+impl From<Failure> for AccessError {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Remote(status, err) => err.into_access(status),
+            Failure::Transport(err) => AccessError::Implementation(err),
+        }
+    }
+}
+
+impl From<Failure> for LockingError {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Remote(status, err) => err.into_locking(status),
+            Failure::Transport(err) => LockingError::Implementation(err),
+        }
+    }
+}
+
+/// An error response which does not correspond to any error of the backend
+/// traits, such as a malformed request or a failure within the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server responded with {}: {}", self.status, self.message)
+    }
+}
+
+impl Error for RemoteError {}
@@ -0,0 +1,128 @@
+use eci_core::{
+    backend::{
+        ActiveLock, Administrative, BulkLockResult, Expiry, Lock, LockDescriptor, LockStats,
+        LockingBackend, LockingError,
+    },
+    Entity,
+};
+use log::*;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    wire::{Acquire, Acquired, Held, LockCounts, Renew, Upgrade},
+    HttpBackend,
+};
+
+impl LockingBackend for HttpBackend {
+    fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        let expires_in = match expires {
+            Expiry::In(expires_in) => Some(expires_in),
+            Expiry::Never => None,
+        };
+
+        let acquired =
+            self.acquire(Acquire::new(vec![(entity, descriptors)], expires_in, false))?;
+        debug!("acquired lock {}", acquired.id);
+
+        lock(&acquired)
+    }
+
+    fn acquire_locks(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<Lock, LockingError> {
+        let acquired = self.acquire(Acquire::new(requests, Some(expires_in), false))?;
+        lock(&acquired)
+    }
+
+    fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        debug!("releasing lock {lock}");
+        Ok(self.fetch(self.request("DELETE", &format!("/locks/{lock}")))?)
+    }
+
+    fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        debug!("renewing lock {lock}");
+        Ok(self.send(
+            self.request("POST", &format!("/locks/{lock}/renew")),
+            &Renew { extend_by },
+        )?)
+    }
+
+    fn upgrade_lock(
+        &self,
+        lock: &Lock,
+        entity: Entity,
+        component: String,
+    ) -> Result<(), LockingError> {
+        Ok(self.send(
+            self.request("POST", &format!("/locks/{lock}/upgrade")),
+            &Upgrade { entity, component },
+        )?)
+    }
+
+    fn list_locks(&self, filter: Option<Entity>) -> Result<Vec<ActiveLock>, LockingError> {
+        let mut request = self.request("GET", "/locks");
+        if let Some(entity) = filter {
+            request = request.query("entity", &entity.to_string());
+        }
+
+        let locks: Vec<Held> = self.fetch(request)?;
+        Ok(locks.into_iter().map(ActiveLock::from).collect())
+    }
+
+    fn force_release(&self, lock_id: Uuid, _: Administrative) -> Result<usize, LockingError> {
+        warn!("forcibly releasing lock {lock_id}");
+        Ok(self.fetch(self.request("DELETE", &format!("/admin/locks/{lock_id}")))?)
+    }
+
+    fn force_release_entity(
+        &self,
+        entity: Entity,
+        _: Administrative,
+    ) -> Result<usize, LockingError> {
+        warn!("forcibly releasing all locks on {entity}");
+        Ok(self.fetch(self.request("DELETE", &format!("/admin/entities/{entity}/locks")))?)
+    }
+
+    fn acquire_locks_bulk(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        let acquired = self.acquire(Acquire::new(requests, Some(expires_in), true))?;
+
+        Ok(BulkLockResult {
+            lock: lock(&acquired)?,
+            granted: acquired.granted,
+            skipped: acquired.skipped,
+        })
+    }
+
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        let stats: LockCounts = self.fetch(self.request("GET", "/locks/stats"))?;
+        Ok(stats.into())
+    }
+}
+
+impl HttpBackend {
+    fn acquire(&self, acquire: Acquire) -> Result<Acquired, LockingError> {
+        Ok(self.send(self.request("POST", "/locks"), &acquire)?)
+    }
+}
+
+/// Refers to the lock acquired by the server, expiring when it said so.
+fn lock(acquired: &Acquired) -> Result<Lock, LockingError> {
+    let lock = Lock::from_id(Uuid::parse_str(&acquired.id).map_err(LockingError::implementation)?);
+
+    Ok(match acquired.expires {
+        Some(expires) => lock.with_expiry(expires),
+        None => lock,
+    })
+}
@@ -0,0 +1,341 @@
+//! Serves any [`Backend`] on the routes [`HttpBackend`] sends its requests
+//! to.
+//!
+//! Backends are not `Send`, so every connection is served on the thread the
+//! server runs on, and requests wait for each other while the backend works.
+//!
+//! [`HttpBackend`]: crate::HttpBackend
+
+use std::{convert::Infallible, net::TcpListener as StdTcpListener};
+
+use eci_core::{
+    backend::{AccessBackend, Backend, Expiry, Format, Lock, LockingBackend},
+    Entity,
+};
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::{Bytes, Incoming},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use log::*;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{net::TcpListener, task::LocalSet};
+use uuid::Uuid;
+
+use crate::{
+    wire::{
+        self, Acquire, Acquired, Component, Error, Held, LockCounts, Renew, Stats, Upgrade,
+        WriteMode,
+    },
+    Info,
+};
+
+pub struct Server<F: Format> {
+    backend: Backend<F>,
+}
+
+impl<F: Format> Server<F> {
+    pub fn new(backend: Backend<F>) -> Self {
+        Server { backend }
+    }
+
+    /// Serves connections from the listener on the current thread, until
+    /// accepting one fails.
+    pub fn run(self, listener: StdTcpListener) -> std::io::Result<()> {
+        listener.set_nonblocking(true)?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()?;
+        LocalSet::new().block_on(&runtime, async move {
+            self.serve(TcpListener::from_std(listener)?).await
+        })
+    }
+
+    /// Serves connections from the listener until accepting one fails. Must
+    /// be run within a [`LocalSet`], which the connections are spawned on.
+    pub async fn serve(self, listener: TcpListener) -> std::io::Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            debug!("accepted connection from {peer}");
+
+            let backend = self.backend.clone();
+            tokio::task::spawn_local(async move {
+                let service = service_fn(move |request| handle(backend.clone(), request));
+                if let Err(err) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    warn!("failed to serve connection from {peer}: {err}");
+                }
+            });
+        }
+    }
+}
+
+async fn handle<F: Format>(
+    backend: Backend<F>,
+    request: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let (parts, body) = request.into_parts();
+
+    let result = match body.collect().await {
+        Ok(body) => route(
+            &backend,
+            &parts.method,
+            parts.uri.path(),
+            parts.uri.query().unwrap_or_default(),
+            &body.to_bytes(),
+        ),
+        Err(err) => Err(Error::bad_request(err)),
+    };
+
+    let (status, body) = match result {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => {
+            debug!("{} {} failed: {}", parts.method, parts.uri, err.status());
+            let status = StatusCode::from_u16(err.status()).unwrap();
+            (status, serde_json::to_vec(&err).unwrap_or_default())
+        }
+    };
+
+    Ok(Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap())
+}
+
+/// Performs the request, returning the body of the response.
+fn route<F: Format>(
+    backend: &Backend<F>,
+    method: &Method,
+    path: &str,
+    query: &str,
+    body: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    match (method, segments.as_slice()) {
+        (&Method::GET, [""]) => reply(Info {
+            atomic_writes: backend.supports_atomic_writes(),
+        }),
+        (&Method::GET, ["entities"]) => match parameter(query, "with") {
+            Some(component) => reply(backend.entities_with(&component)?),
+            None => reply(backend.list_entities()?),
+        },
+        (&Method::PUT, ["entities"]) => {
+            let batch: Vec<(Entity, Vec<Component>)> = parse(body)?;
+            let batch = batch
+                .into_iter()
+                .map(|(entity, components)| Ok((entity, serialized(components)?)))
+                .collect::<Result<_, Error>>()?;
+
+            reply(backend.write_components_batch(batch)?)
+        }
+        (&Method::PUT, ["entities", entity, "components"]) => {
+            let entity = parse_entity(entity)?;
+            let write: wire::Write = parse(body)?;
+            let components = serialized(write.components)?;
+
+            match (write.mode, write.lock) {
+                (WriteMode::Insert, Some(lock)) => {
+                    backend.write_components_locked(entity, components, &parse_lock(&lock)?)
+                }
+                (WriteMode::Insert, None) => backend.write_components(entity, components),
+                (WriteMode::Update, Some(lock)) => {
+                    backend.update_components_locked(entity, components, &parse_lock(&lock)?)
+                }
+                (WriteMode::Update, None) => backend.update_components(entity, components),
+                (WriteMode::IfAbsent, _) => backend.write_components_if_absent(entity, components),
+            }?;
+
+            reply(())
+        }
+        (&Method::GET, ["entities", entity, "components"]) => {
+            let entity = parse_entity(entity)?;
+            match parameter(query, "names") {
+                Some(names) => {
+                    let read = backend.read_components(entity, wire::parse_descriptors(&names)?)?;
+                    reply(
+                        read.into_iter()
+                            .map(|c| c.map(Component::from))
+                            .collect::<Vec<_>>(),
+                    )
+                }
+                None => reply(AccessBackend::list_components(backend, entity)?),
+            }
+        }
+        (&Method::DELETE, ["entities", entity, "components"]) => {
+            let entity = parse_entity(entity)?;
+            let names = parameter(query, "names").unwrap_or_default();
+            let descriptors = wire::parse_descriptors(&names)?;
+            let removed = match parameter(query, "lock") {
+                Some(lock) => {
+                    backend.remove_components_locked(entity, descriptors, &parse_lock(&lock)?)?
+                }
+                None => backend.remove_components(entity, descriptors)?,
+            };
+            reply(
+                removed
+                    .into_iter()
+                    .map(|c| c.map(Component::from))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        (&Method::GET, ["stats"]) => {
+            let stats = AccessBackend::stats(backend)?;
+            reply(stats.into_iter().map(Stats::from).collect::<Vec<_>>())
+        }
+        (&Method::POST, ["locks"]) => acquire(backend, parse(body)?),
+        (&Method::GET, ["locks"]) => {
+            let filter = parameter(query, "entity")
+                .map(|entity| parse_entity(&entity))
+                .transpose()?;
+            let locks = backend.list_locks(filter)?;
+            reply(locks.into_iter().map(Held::from).collect::<Vec<_>>())
+        }
+        (&Method::GET, ["locks", "stats"]) => reply(LockCounts::from(backend.lock_stats()?)),
+        (&Method::DELETE, ["locks", lock]) => reply(backend.release_lock(parse_lock(lock)?)?),
+        (&Method::POST, ["locks", lock, "renew"]) => {
+            let renew: Renew = parse(body)?;
+            reply(backend.renew_lock(&parse_lock(lock)?, renew.extend_by)?)
+        }
+        (&Method::POST, ["locks", lock, "upgrade"]) => {
+            let upgrade: Upgrade = parse(body)?;
+            reply(backend.upgrade_lock(&parse_lock(lock)?, upgrade.entity, upgrade.component)?)
+        }
+        (&Method::DELETE, ["admin", "locks", lock]) => {
+            let lock = Uuid::parse_str(lock).map_err(Error::bad_request)?;
+            reply(backend.admin().force_release(lock)?)
+        }
+        (&Method::DELETE, ["admin", "entities", entity, "locks"]) => reply(
+            backend
+                .admin()
+                .force_release_entity(parse_entity(entity)?)?,
+        ),
+        _ => Err(Error::NotFound),
+    }
+}
+
+/// Locks a single entity with its own expiry, or several under one lock.
+fn acquire<F: Format>(backend: &Backend<F>, acquire: Acquire) -> Result<Vec<u8>, Error> {
+    let (expires_in, skip_conflicts) = (acquire.expires_in, acquire.skip_conflicts);
+    let mut requests = acquire.requests();
+
+    if skip_conflicts {
+        let expires_in = expires_in.ok_or_else(|| Error::bad_request("missing expiry"))?;
+        let bulk = backend.acquire_locks_bulk(requests, expires_in)?;
+        return reply(Acquired {
+            id: bulk.lock.id(),
+            expires: bulk.lock.expires_at(),
+            granted: bulk.granted,
+            skipped: bulk.skipped,
+        });
+    }
+
+    let lock = match (requests.len(), expires_in) {
+        (1, expires_in) => {
+            let (entity, descriptors) = requests.remove(0);
+            let expires = expires_in.map(Expiry::In).unwrap_or(Expiry::Never);
+            backend.acquire_lock(entity, descriptors, expires)?
+        }
+        (_, Some(expires_in)) => backend.acquire_locks(requests, expires_in)?,
+        (_, None) => return Err(Error::bad_request("missing expiry")),
+    };
+
+    reply(Acquired {
+        id: lock.id(),
+        expires: lock.expires_at(),
+        granted: Vec::new(),
+        skipped: Vec::new(),
+    })
+}
+
+fn reply<T: Serialize>(value: T) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(&value).map_err(|err| Error::Internal {
+        message: err.to_string(),
+    })
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    serde_json::from_slice(body).map_err(Error::bad_request)
+}
+
+fn parse_entity(entity: &str) -> Result<Entity, Error> {
+    Entity::parse(entity).map_err(Error::bad_request)
+}
+
+fn parse_lock(lock: &str) -> Result<Lock, Error> {
+    Uuid::parse_str(lock)
+        .map(Lock::from_id)
+        .map_err(Error::bad_request)
+}
+
+fn serialized<F: Format>(
+    components: Vec<Component>,
+) -> Result<Vec<eci_core::backend::SerializedComponent<F>>, Error> {
+    components
+        .into_iter()
+        .map(Component::into_serialized)
+        .collect()
+}
+
+/// Finds the parameter in the query, decoding it.
+fn parameter(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| decode(value))
+}
+
+/// Undoes percent-encoding, leaving malformed escapes as they are.
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, parameter};
+
+    #[test]
+    fn decode_parameters() {
+        let query = "names=Counter%3A1.0.0%2CPosition&with=Name+Tag&broken=100%zz";
+
+        assert_eq!(
+            parameter(query, "names").as_deref(),
+            Some("Counter:1.0.0,Position")
+        );
+        assert_eq!(parameter(query, "with").as_deref(), Some("Name Tag"));
+        assert_eq!(parameter(query, "broken").as_deref(), Some("100%zz"));
+        assert_eq!(parameter(query, "missing"), None);
+        assert_eq!(decode("%"), "%");
+    }
+}
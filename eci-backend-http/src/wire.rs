@@ -0,0 +1,728 @@
+//! Bodies of the requests and responses exchanged by the client and the
+//! server, which both sides convert to and from the backend traits' types.
+
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime},
+};
+
+use eci_core::{
+    backend::{
+        AccessError, ActiveLock, ComponentStats, ConflictingLock, ExtractionDescriptor, Format,
+        Limit, LockDescriptor, LockStats, LockingError, LockingMode, SerializedComponent,
+    },
+    Entity, Version,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::RemoteError;
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Component {
+    pub name: String,
+    pub version: String,
+    pub contents: Vec<u8>,
+}
+
+impl<F: Format> From<SerializedComponent<F>> for Component {
+    fn from(component: SerializedComponent<F>) -> Self {
+        Component {
+            name: component.name,
+            version: component.version.to_string(),
+            contents: component.contents.into(),
+        }
+    }
+}
+
+impl Component {
+    pub fn into_serialized<F: Format>(self) -> Result<SerializedComponent<F>, Error> {
+        Ok(SerializedComponent {
+            contents: F::Data::from(self.contents),
+            name: self.name,
+            version: parse_version(&self.version)?,
+            schema: None,
+            metadata: None,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum WriteMode {
+    Insert,
+    Update,
+    IfAbsent,
+}
+
+/// Body of `PUT /entities/{id}/components`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Write {
+    pub mode: WriteMode,
+    /// Id of the lock the components must be written under, if any.
+    pub lock: Option<String>,
+    pub components: Vec<Component>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Mode {
+    Read,
+    Write,
+    Exclusive,
+}
+
+impl From<LockingMode> for Mode {
+    fn from(mode: LockingMode) -> Self {
+        match mode {
+            LockingMode::Read => Mode::Read,
+            LockingMode::Write => Mode::Write,
+            LockingMode::Exclusive => Mode::Exclusive,
+        }
+    }
+}
+
+impl From<Mode> for LockingMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Read => LockingMode::Read,
+            Mode::Write => LockingMode::Write,
+            Mode::Exclusive => LockingMode::Exclusive,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Descriptor {
+    pub mode: Mode,
+    pub name: String,
+}
+
+/// Body of `POST /locks`. A single entity is locked with the given expiry,
+/// or never expiring without one, while several entities are locked under
+/// one lock, skipping those which conflict if `skip_conflicts` is set.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Acquire {
+    pub entities: Vec<(Entity, Vec<Descriptor>)>,
+    pub expires_in: Option<Duration>,
+    pub skip_conflicts: bool,
+}
+
+impl Acquire {
+    pub fn new(
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Option<Duration>,
+        skip_conflicts: bool,
+    ) -> Self {
+        Acquire {
+            entities: requests
+                .into_iter()
+                .map(|(entity, descriptors)| {
+                    let descriptors = descriptors
+                        .into_iter()
+                        .map(|descriptor| Descriptor {
+                            mode: descriptor.mode.into(),
+                            name: descriptor.name,
+                        })
+                        .collect();
+                    (entity, descriptors)
+                })
+                .collect(),
+            expires_in,
+            skip_conflicts,
+        }
+    }
+
+    #[cfg(feature = "server")]
+    pub fn requests(self) -> Vec<(Entity, Vec<LockDescriptor>)> {
+        self.entities
+            .into_iter()
+            .map(|(entity, descriptors)| {
+                let descriptors = descriptors
+                    .into_iter()
+                    .map(|descriptor| LockDescriptor {
+                        mode: descriptor.mode.into(),
+                        name: descriptor.name,
+                    })
+                    .collect();
+                (entity, descriptors)
+            })
+            .collect()
+    }
+}
+
+/// Response to `POST /locks`. Entities are only listed as granted or
+/// skipped when conflicts were skipped.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Acquired {
+    pub id: String,
+    pub expires: Option<SystemTime>,
+    pub granted: Vec<Entity>,
+    pub skipped: Vec<Entity>,
+}
+
+/// Body of `POST /locks/{id}/renew`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Renew {
+    pub extend_by: Duration,
+}
+
+/// Body of `POST /locks/{id}/upgrade`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Upgrade {
+    pub entity: Entity,
+    pub component: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Held {
+    pub id: String,
+    pub entity: Entity,
+    pub component: String,
+    pub mode: Mode,
+    pub expires: Option<SystemTime>,
+    pub owner: Option<String>,
+}
+
+impl From<ActiveLock> for Held {
+    fn from(lock: ActiveLock) -> Self {
+        Held {
+            id: lock.id,
+            entity: lock.entity,
+            component: lock.component,
+            mode: lock.mode.into(),
+            expires: lock.expires,
+            owner: lock.owner,
+        }
+    }
+}
+
+impl From<Held> for ActiveLock {
+    fn from(lock: Held) -> Self {
+        ActiveLock {
+            id: lock.id,
+            entity: lock.entity,
+            component: lock.component,
+            mode: lock.mode.into(),
+            expires: lock.expires,
+            owner: lock.owner,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Stats {
+    pub name: String,
+    pub entity_count: u64,
+    pub total_bytes: u64,
+}
+
+impl From<ComponentStats> for Stats {
+    fn from(stats: ComponentStats) -> Self {
+        Stats {
+            name: stats.name,
+            entity_count: stats.entity_count,
+            total_bytes: stats.total_bytes,
+        }
+    }
+}
+
+impl From<Stats> for ComponentStats {
+    fn from(stats: Stats) -> Self {
+        ComponentStats {
+            name: stats.name,
+            entity_count: stats.entity_count,
+            total_bytes: stats.total_bytes,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LockCounts {
+    pub active: u64,
+    pub expired: u64,
+}
+
+impl From<LockStats> for LockCounts {
+    fn from(stats: LockStats) -> Self {
+        LockCounts {
+            active: stats.active,
+            expired: stats.expired,
+        }
+    }
+}
+
+impl From<LockCounts> for LockStats {
+    fn from(stats: LockCounts) -> Self {
+        LockStats {
+            active: stats.active,
+            expired: stats.expired,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Holder {
+    pub id: String,
+    pub mode: Mode,
+    pub owner: Option<String>,
+    pub expires: Option<SystemTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Bound {
+    Size(usize),
+    Depth(usize),
+}
+
+/// Body of every failed response, carrying everything the backend reported
+/// so the client can hand back the same error.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum Error {
+    Conflict {
+        entity: Entity,
+        component: String,
+    },
+    LockConflict {
+        entity: Entity,
+        component: String,
+        mode: Mode,
+        holder: Option<Holder>,
+    },
+    LockRequired {
+        entity: Entity,
+        component: String,
+    },
+    LimitExceeded {
+        component: String,
+        limit: Bound,
+    },
+    UnknownComponent {
+        component: String,
+    },
+    EmptyRequest,
+    InvalidComponentName {
+        component: String,
+    },
+    VersionMismatch {
+        component: String,
+        stored: String,
+        expected: String,
+    },
+    FormatMismatch {
+        component: String,
+        stored: String,
+        expected: String,
+    },
+    SchemaMismatch {
+        component: String,
+        stored: u64,
+        expected: u64,
+    },
+    StaleWrite {
+        entity: Entity,
+        component: String,
+        current: Option<u64>,
+    },
+    ComponentNotFound {
+        entity: Entity,
+        component: String,
+    },
+    EntityNotFound {
+        entity: Entity,
+    },
+    Busy {
+        attempts: u32,
+    },
+    Expired {
+        lock: String,
+    },
+    TimedOut {
+        entity: Entity,
+        component: String,
+        waited: Duration,
+    },
+    ExpiryOutOfRange {
+        requested: Duration,
+    },
+    /// The request could not be understood, or named no known route.
+    BadRequest {
+        message: String,
+    },
+    NotFound,
+    /// Anything else which went wrong with the backend, only described.
+    Internal {
+        message: String,
+    },
+}
+
+impl Error {
+    pub fn bad_request<E: Display>(err: E) -> Self {
+        Error::BadRequest {
+            message: err.to_string(),
+        }
+    }
+
+    #[cfg(feature = "server")]
+    /// Status code of the response carrying the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Error::Conflict { .. }
+            | Error::LockConflict { .. }
+            | Error::VersionMismatch { .. }
+            | Error::FormatMismatch { .. }
+            | Error::SchemaMismatch { .. }
+            | Error::StaleWrite { .. } => 409,
+            Error::LockRequired { .. } => 423,
+            Error::LimitExceeded { .. } => 413,
+            Error::UnknownComponent { .. }
+            | Error::ComponentNotFound { .. }
+            | Error::EntityNotFound { .. }
+            | Error::NotFound => 404,
+            Error::EmptyRequest
+            | Error::InvalidComponentName { .. }
+            | Error::ExpiryOutOfRange { .. }
+            | Error::BadRequest { .. } => 400,
+            Error::Busy { .. } => 503,
+            Error::Expired { .. } => 410,
+            Error::TimedOut { .. } => 408,
+            Error::Internal { .. } => 500,
+        }
+    }
+
+    pub fn into_access(self, status: u16) -> AccessError {
+        match self {
+            Error::Conflict { entity, component } => AccessError::Conflict(entity, component),
+            Error::LockRequired { entity, component } => {
+                AccessError::LockRequired(entity, component)
+            }
+            Error::LimitExceeded { component, limit } => AccessError::LimitExceeded {
+                component,
+                limit: match limit {
+                    Bound::Size(bytes) => Limit::Size(bytes),
+                    Bound::Depth(depth) => Limit::Depth(depth),
+                },
+            },
+            Error::UnknownComponent { component } => AccessError::UnknownComponent(component),
+            Error::EmptyRequest => AccessError::EmptyRequest,
+            Error::InvalidComponentName { component } => {
+                AccessError::InvalidComponentName(component)
+            }
+            Error::VersionMismatch {
+                component,
+                stored,
+                expected,
+            } => match (parse_version(&stored), parse_version(&expected)) {
+                (Ok(stored), Ok(expected)) => AccessError::VersionMismatch {
+                    component,
+                    stored,
+                    expected,
+                },
+                (Err(err), _) | (_, Err(err)) => err.into_access(status),
+            },
+            Error::FormatMismatch {
+                component,
+                stored,
+                expected,
+            } => AccessError::FormatMismatch {
+                component,
+                stored,
+                expected,
+            },
+            Error::SchemaMismatch {
+                component,
+                stored,
+                expected,
+            } => AccessError::SchemaMismatch {
+                component,
+                stored,
+                expected,
+            },
+            Error::StaleWrite {
+                entity,
+                component,
+                current,
+            } => AccessError::StaleWrite {
+                entity,
+                component,
+                current,
+            },
+            Error::ComponentNotFound { entity, component } => {
+                AccessError::ComponentNotFound { entity, component }
+            }
+            Error::EntityNotFound { entity } => AccessError::EntityNotFound(entity),
+            Error::Busy { attempts } => AccessError::Busy { attempts },
+            other => AccessError::implementation(other.into_remote(status)),
+        }
+    }
+
+    pub fn into_locking(self, status: u16) -> LockingError {
+        match self {
+            Error::LockConflict {
+                entity,
+                component,
+                mode,
+                holder,
+            } => LockingError::Conflict(
+                entity,
+                component,
+                mode.into(),
+                holder.map(|holder| ConflictingLock {
+                    id: holder.id,
+                    mode: holder.mode.into(),
+                    owner: holder.owner,
+                    expires: holder.expires,
+                }),
+            ),
+            Error::Expired { lock } => LockingError::Expired(lock),
+            Error::TimedOut {
+                entity,
+                component,
+                waited,
+            } => LockingError::TimedOut {
+                entity,
+                component,
+                waited,
+            },
+            Error::EmptyRequest => LockingError::EmptyRequest,
+            Error::ExpiryOutOfRange { requested } => LockingError::ExpiryOutOfRange(requested),
+            Error::Busy { attempts } => LockingError::Busy { attempts },
+            other => LockingError::implementation(other.into_remote(status)),
+        }
+    }
+
+    fn into_remote(self, status: u16) -> RemoteError {
+        RemoteError {
+            status,
+            message: match self {
+                Error::BadRequest { message } | Error::Internal { message } => message,
+                other => serde_json::to_string(&other).unwrap_or_default(),
+            },
+        }
+    }
+}
+
+impl From<AccessError> for Error {
+    fn from(err: AccessError) -> Self {
+        match err {
+            AccessError::Conflict(entity, component) => Error::Conflict { entity, component },
+            AccessError::LockRequired(entity, component) => {
+                Error::LockRequired { entity, component }
+            }
+            AccessError::LimitExceeded { component, limit } => Error::LimitExceeded {
+                component,
+                limit: match limit {
+                    Limit::Size(bytes) => Bound::Size(bytes),
+                    Limit::Depth(depth) => Bound::Depth(depth),
+                },
+            },
+            AccessError::UnknownComponent(component) => Error::UnknownComponent { component },
+            AccessError::EmptyRequest => Error::EmptyRequest,
+            AccessError::InvalidComponentName(component) => {
+                Error::InvalidComponentName { component }
+            }
+            AccessError::VersionMismatch {
+                component,
+                stored,
+                expected,
+            } => Error::VersionMismatch {
+                component,
+                stored: stored.to_string(),
+                expected: expected.to_string(),
+            },
+            AccessError::FormatMismatch {
+                component,
+                stored,
+                expected,
+            } => Error::FormatMismatch {
+                component,
+                stored,
+                expected,
+            },
+            AccessError::SchemaMismatch {
+                component,
+                stored,
+                expected,
+            } => Error::SchemaMismatch {
+                component,
+                stored,
+                expected,
+            },
+            AccessError::StaleWrite {
+                entity,
+                component,
+                current,
+            } => Error::StaleWrite {
+                entity,
+                component,
+                current,
+            },
+            AccessError::ComponentNotFound { entity, component } => {
+                Error::ComponentNotFound { entity, component }
+            }
+            AccessError::EntityNotFound(entity) => Error::EntityNotFound { entity },
+            AccessError::Busy { attempts } => Error::Busy { attempts },
+            err => Error::Internal {
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl From<LockingError> for Error {
+    fn from(err: LockingError) -> Self {
+        match err {
+            LockingError::Conflict(entity, component, mode, holder) => Error::LockConflict {
+                entity,
+                component,
+                mode: mode.into(),
+                holder: holder.map(|holder| Holder {
+                    id: holder.id,
+                    mode: holder.mode.into(),
+                    owner: holder.owner,
+                    expires: holder.expires,
+                }),
+            },
+            LockingError::Expired(lock) => Error::Expired { lock },
+            LockingError::TimedOut {
+                entity,
+                component,
+                waited,
+            } => Error::TimedOut {
+                entity,
+                component,
+                waited,
+            },
+            LockingError::EmptyRequest => Error::EmptyRequest,
+            LockingError::ExpiryOutOfRange(requested) => Error::ExpiryOutOfRange { requested },
+            LockingError::Busy { attempts } => Error::Busy { attempts },
+            err => Error::Internal {
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+/// Describes components as `Name` or `Name:major.minor.patch` in the
+/// `names` parameter, the latter to read them as that version.
+pub(crate) fn describe(descriptors: &[ExtractionDescriptor]) -> String {
+    descriptors
+        .iter()
+        .map(|descriptor| match descriptor.version {
+            Some(version) => format!("{}:{version}", descriptor.name),
+            None => descriptor.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn parse_descriptors(names: &str) -> Result<Vec<ExtractionDescriptor>, Error> {
+    names
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| match name.split_once(':') {
+            Some((name, version)) => Ok(ExtractionDescriptor {
+                name: name.to_string(),
+                version: Some(parse_version(version)?),
+                schema: None,
+            }),
+            None => Ok(ExtractionDescriptor {
+                name: name.to_string(),
+                version: None,
+                schema: None,
+            }),
+        })
+        .collect()
+}
+
+fn parse_version(version: &str) -> Result<Version, Error> {
+    version.parse().map_err(Error::bad_request)
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use std::time::SystemTime;
+
+    use eci_core::{
+        backend::{AccessError, ConflictingLock, ExtractionDescriptor, LockingError, LockingMode},
+        Entity, Version,
+    };
+
+    use super::{describe, parse_descriptors, Error};
+
+    fn round_trip(err: Error) -> (u16, Error) {
+        let status = err.status();
+        let body = serde_json::to_string(&err).unwrap();
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    #[test]
+    fn conflicts_keep_their_details() {
+        let entity = Entity::new();
+        let holder = ConflictingLock {
+            id: "held".to_string(),
+            mode: LockingMode::Write,
+            owner: Some("inventory-service".to_string()),
+            expires: Some(SystemTime::now()),
+        };
+
+        let (status, err) = round_trip(
+            LockingError::Conflict(
+                entity,
+                "Counter".to_string(),
+                LockingMode::Read,
+                Some(holder.clone()),
+            )
+            .into(),
+        );
+        assert_eq!(status, 409);
+        assert!(matches!(
+            err.into_locking(status),
+            LockingError::Conflict(conflicted, component, LockingMode::Read, Some(held))
+                if conflicted == entity && component == "Counter" && held == holder
+        ));
+
+        let (status, err) = round_trip(AccessError::Conflict(entity, "Counter".to_string()).into());
+        assert_eq!(status, 409);
+        assert!(matches!(
+            err.into_access(status),
+            AccessError::Conflict(conflicted, component)
+                if conflicted == entity && component == "Counter"
+        ));
+    }
+
+    #[test]
+    fn unexpected_errors_are_described() {
+        let (status, err) = round_trip(LockingError::Expired("lock".to_string()).into());
+        assert_eq!(status, 410);
+        assert!(matches!(
+            err.into_access(status),
+            AccessError::Implementation(inner) if inner.to_string().starts_with("server responded with 410")
+        ));
+    }
+
+    #[test]
+    fn describe_components() {
+        let descriptors = vec![
+            ExtractionDescriptor {
+                name: "CounterA".to_string(),
+                version: None,
+                schema: None,
+            },
+            ExtractionDescriptor {
+                name: "CounterB".to_string(),
+                version: Some(Version::new(1, 2, 3)),
+                schema: None,
+            },
+        ];
+
+        let described = describe(&descriptors);
+        assert_eq!(described, "CounterA,CounterB:1.2.3");
+
+        let parsed = parse_descriptors(&described).unwrap();
+        assert_eq!(parsed[1].name, "CounterB");
+        assert_eq!(parsed[1].version, Some(Version::new(1, 2, 3)));
+    }
+}
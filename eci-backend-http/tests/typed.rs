@@ -0,0 +1,192 @@
+use eci_backend_http::{server::Server, HttpBackend};
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, Backend, BackendError, ExtractionDescriptor, Format,
+        LockingBackend, LockingError, SerializedComponent,
+    },
+    Component, Entity, Version,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+use std::{net::TcpListener, time::Duration};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterA(pub usize);
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterB(pub usize);
+
+/// Serves an in-memory sqlite backend on an ephemeral port, returning a
+/// backend which talks to it.
+fn backend() -> Backend<Json> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        Server::new(Backend::<Json>::from_joint(
+            SqliteBackend::memory().unwrap(),
+        ))
+        .run(listener)
+        .unwrap();
+    });
+
+    Backend::from_joint(HttpBackend::connect(&url).unwrap())
+}
+
+fn is_conflict(result: Result<impl Sized, BackendError>) -> bool {
+    matches!(
+        result.as_ref().map_err(BackendError::root),
+        Err(BackendError::Locking(LockingError::Conflict(..)))
+    )
+}
+
+#[test]
+fn put_get_and_remove() {
+    let backend = backend();
+    assert!(backend.supports_atomic_writes());
+
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(1), CounterB(2)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend.get::<&mut CounterA>(entity).unwrap().unwrap();
+    locked.deref().0 += 1;
+    locked.commit().unwrap();
+
+    assert_eq!(
+        backend.peek::<&CounterA>(entity).unwrap(),
+        Some(CounterA(2))
+    );
+    assert_eq!(backend.list_entities().unwrap(), vec![entity]);
+    assert_eq!(
+        backend.list_components(entity).unwrap(),
+        vec!["CounterA", "CounterB"]
+    );
+
+    backend.remove::<(CounterA, CounterB)>(entity).unwrap();
+    assert_eq!(backend.peek::<&CounterA>(entity).unwrap(), None);
+    assert!(backend.list_entities().unwrap().is_empty());
+}
+
+#[test]
+fn conflicts_carry_their_details() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(0),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let again = AccessBackend::<Json>::write_components(
+        &backend,
+        entity,
+        vec![SerializedComponent {
+            contents: Json::serialize(CounterA(0)).unwrap(),
+            name: "CounterA".to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        }],
+    );
+    match again {
+        Err(AccessError::Conflict(conflicted, component)) => {
+            assert_eq!(conflicted, entity);
+            assert_eq!(component, "CounterA");
+        }
+        other => panic!("expected a conflict, got {other:?}"),
+    }
+
+    let held = backend.get::<&mut CounterA>(entity).unwrap().unwrap();
+    match backend
+        .get::<&CounterA>(entity)
+        .as_ref()
+        .map_err(BackendError::root)
+    {
+        Err(BackendError::Locking(LockingError::Conflict(
+            conflicted,
+            component,
+            _,
+            Some(holder),
+        ))) => {
+            assert_eq!(*conflicted, entity);
+            assert_eq!(component, "CounterA");
+            assert_eq!(holder.id, held.lock_id());
+        }
+        other => panic!("expected a conflict, got {other:?}"),
+    }
+}
+
+#[test]
+fn conflicting_locks() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(0), CounterB(0)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let _read = backend.get::<&CounterA>(entity).unwrap().unwrap();
+    let _also_read = backend.get::<&CounterA>(entity).unwrap().unwrap();
+    assert!(is_conflict(backend.get::<&mut CounterA>(entity)));
+
+    // Conflicts on one entity of a pair leave the other unlocked.
+    let other = Entity::new();
+    backend
+        .put(other, (CounterB(0),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    assert!(is_conflict(
+        backend.get_pair::<&mut CounterB, &mut CounterA>(other, entity)
+    ));
+    backend.get::<&mut CounterB>(other).unwrap().unwrap();
+}
+
+#[test]
+fn locks_expire() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(0),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let held = backend
+        .lock_entity(entity, Duration::from_millis(100))
+        .unwrap();
+    assert!(is_conflict(backend.get::<&CounterA>(entity)));
+
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(backend.list_locks(Some(entity)).unwrap().is_empty());
+    backend.get::<&mut CounterA>(entity).unwrap().unwrap();
+    drop(held);
+}
+
+#[test]
+fn version_mismatch() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(0),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let read = AccessBackend::<Json>::read_components(
+        &backend,
+        entity,
+        vec![ExtractionDescriptor {
+            name: "CounterA".to_string(),
+            version: Some(Version::new(2, 0, 0)),
+            schema: None,
+        }],
+    );
+    assert!(matches!(
+        read,
+        Err(AccessError::VersionMismatch { stored, expected, .. })
+            if stored == Version::default() && expected == Version::new(2, 0, 0)
+    ));
+}
@@ -0,0 +1,406 @@
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format, Lock,
+        SerializedComponent,
+    },
+    is_valid_component_name, Entity,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{lock, MemoryBackend, Stored};
+
+type Components = HashMap<Entity, HashMap<String, Stored>>;
+
+impl<F: Format> AccessBackend<F> for MemoryBackend {
+    fn supports_atomic_writes(&self) -> bool {
+        true
+    }
+
+    fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        let mut stored = self.state.components.write().unwrap();
+        insert(&mut stored, vec![(entity, components)])
+    }
+
+    fn write_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        // Holding on to the lock table keeps the locks from being released
+        // or expiring unnoticed until the components are written.
+        let rows = self.state.locks.lock().unwrap();
+        check_lock(&rows, Some(lock), entity, names(&components))?;
+
+        let mut stored = self.state.components.write().unwrap();
+        insert(&mut stored, vec![(entity, components)])
+    }
+
+    fn write_components_batch(
+        &self,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        let mut stored = self.state.components.write().unwrap();
+        insert(&mut stored, batch)
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.insert_absent(entity, components, None)
+    }
+
+    fn write_components_if_absent_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        self.insert_absent(entity, components, Some(lock))
+    }
+
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.update(entity, components, None)
+    }
+
+    fn update_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        self.update(entity, components, Some(lock))
+    }
+
+    fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        check_names(
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str()),
+        )?;
+        read(&self.state.components.read().unwrap(), entity, &descriptors)
+    }
+
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.remove(entity, descriptors, None)
+    }
+
+    fn remove_components_locked(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.remove(entity, descriptors, Some(lock))
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        check_names([component])?;
+
+        let mut entities: Vec<Entity> = self
+            .state
+            .components
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, components)| components.contains_key(component))
+            .map(|(entity, _)| *entity)
+            .collect();
+
+        entities.sort();
+        Ok(entities)
+    }
+
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        let mut entities: Vec<Entity> = self
+            .state
+            .components
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, components)| !components.is_empty())
+            .map(|(entity, _)| *entity)
+            .collect();
+
+        entities.sort();
+        Ok(entities)
+    }
+
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        let mut names: Vec<String> = self
+            .state
+            .components
+            .read()
+            .unwrap()
+            .get(&entity)
+            .map(|components| components.keys().cloned().collect())
+            .unwrap_or_default();
+
+        names.sort();
+        Ok(names)
+    }
+
+    /// Only reports components which at least one entity has, since unlike
+    /// tables, nothing is left behind once the last of them is removed.
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        let mut stats = BTreeMap::<&str, (u64, u64)>::new();
+
+        let stored = self.state.components.read().unwrap();
+        for (name, component) in stored.values().flatten() {
+            let (entity_count, total_bytes) = stats.entry(name).or_default();
+            *entity_count += 1;
+            *total_bytes += component.contents.len() as u64;
+        }
+
+        Ok(stats
+            .into_iter()
+            .map(|(name, (entity_count, total_bytes))| ComponentStats {
+                name: name.to_string(),
+                entity_count,
+                total_bytes,
+            })
+            .collect())
+    }
+}
+
+impl MemoryBackend {
+    /// Writes the components the entity does not already have. Given a
+    /// lock, it must hold write locks on all of them.
+    fn insert_absent<F: Format>(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: Option<&Lock>,
+    ) -> Result<(), AccessError> {
+        check_names(names(&components))?;
+
+        let rows = self.state.locks.lock().unwrap();
+        check_lock(&rows, lock, entity, names(&components))?;
+
+        let mut stored = self.state.components.write().unwrap();
+        for component in components {
+            stored
+                .entry(entity)
+                .or_default()
+                .entry(component.name)
+                .or_insert_with(|| Stored {
+                    contents: component.contents.into(),
+                    version: component.version,
+                });
+        }
+
+        Ok(())
+    }
+
+    /// Writes the components over any stored ones. Given a lock, it must
+    /// hold write locks on all of them.
+    fn update<F: Format>(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: Option<&Lock>,
+    ) -> Result<(), AccessError> {
+        check_names(names(&components))?;
+
+        let rows = self.state.locks.lock().unwrap();
+        check_lock(&rows, lock, entity, names(&components))?;
+
+        let mut stored = self.state.components.write().unwrap();
+        for component in components {
+            stored.entry(entity).or_default().insert(
+                component.name,
+                Stored {
+                    contents: component.contents.into(),
+                    version: component.version,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Removes the components, returning the removed values. Given a lock,
+    /// it must hold write locks on all of them.
+    fn remove<F: Format>(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: Option<&Lock>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        let names = || {
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str())
+        };
+        check_names(names())?;
+
+        let rows = self.state.locks.lock().unwrap();
+        check_lock(&rows, lock, entity, names())?;
+
+        // Reading everything first leaves the components in place if any of
+        // them was stored with the wrong version.
+        let mut stored = self.state.components.write().unwrap();
+        let removed = read(&stored, entity, &descriptors)?;
+
+        if let Some(components) = stored.get_mut(&entity) {
+            for descriptor in &descriptors {
+                components.remove(&descriptor.name);
+            }
+
+            if components.is_empty() {
+                stored.remove(&entity);
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+fn names<F: Format>(components: &[SerializedComponent<F>]) -> impl Iterator<Item = &str> {
+    components.iter().map(|component| component.name.as_str())
+}
+
+/// Fails with [`AccessError::LockRequired`] if given a lock which does not
+/// hold write locks on all the named components of the entity.
+fn check_lock<'a>(
+    rows: &[lock::LockRow],
+    lock: Option<&Lock>,
+    entity: Entity,
+    mut names: impl Iterator<Item = &'a str>,
+) -> Result<(), AccessError> {
+    let Some(lock) = lock else {
+        return Ok(());
+    };
+
+    match names.find(|name| !lock::holds_write_lock(rows, lock, entity, name)) {
+        Some(name) => Err(AccessError::LockRequired(entity, name.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Rejects names which are not valid component names, so the memory backend
+/// accepts exactly the components the others do.
+fn check_names<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Result<(), AccessError> {
+    match names
+        .into_iter()
+        .find(|name| !is_valid_component_name(name))
+    {
+        Some(name) => Err(AccessError::InvalidComponentName(name.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Inserts the components of every entity, failing with
+/// [`AccessError::Conflict`] without inserting any of them if one of the
+/// entities already has, or is given twice, one of its components.
+fn insert<F: Format>(
+    stored: &mut Components,
+    batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+) -> Result<(), AccessError> {
+    let mut seen = HashSet::new();
+    for (entity, components) in &batch {
+        check_names(components.iter().map(|component| component.name.as_str()))?;
+
+        for component in components {
+            let exists = stored
+                .get(entity)
+                .is_some_and(|existing| existing.contains_key(&component.name));
+
+            if exists || !seen.insert((*entity, component.name.as_str())) {
+                return Err(AccessError::Conflict(*entity, component.name.clone()));
+            }
+        }
+    }
+
+    for (entity, components) in batch {
+        if components.is_empty() {
+            continue;
+        }
+
+        let existing = stored.entry(entity).or_default();
+        for component in components {
+            existing.insert(
+                component.name,
+                Stored {
+                    contents: component.contents.into(),
+                    version: component.version,
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the described components of the entity out of the store, failing
+/// if any of them was stored with another version of its layout than the
+/// one it is being read as.
+pub(crate) fn read<F: Format>(
+    stored: &Components,
+    entity: Entity,
+    descriptors: &[ExtractionDescriptor],
+) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+    let components = stored.get(&entity);
+
+    descriptors
+        .iter()
+        .map(|descriptor| {
+            components
+                .and_then(|components| components.get(&descriptor.name))
+                .map(|component| match descriptor.version {
+                    Some(expected) if expected != component.version => {
+                        Err(AccessError::VersionMismatch {
+                            component: descriptor.name.clone(),
+                            stored: component.version,
+                            expected,
+                        })
+                    }
+                    _ => Ok(SerializedComponent::<F> {
+                        contents: F::Data::from(component.contents.clone()),
+                        name: descriptor.name.clone(),
+                        version: component.version,
+                        schema: None,
+                        metadata: None,
+                    }),
+                })
+                .transpose()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MemoryBackend;
+
+    eci_testing::access_conformance!(MemoryBackend::new());
+}
@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use eci_core::{
+    backend::{
+        AsyncLockingBackend, Expiry, Lock, LockDescriptor, LockingBackend, LockingError,
+        MAX_POLL_INTERVAL,
+    },
+    Entity,
+};
+
+use crate::MemoryBackend;
+
+/// Locks are taken and released without blocking, so there is no need to
+/// hand them to the blocking thread pool.
+#[async_trait]
+impl AsyncLockingBackend for MemoryBackend {
+    async fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        LockingBackend::acquire_lock(self, entity, descriptors, expires)
+    }
+
+    async fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        LockingBackend::release_lock(self, lock)
+    }
+
+    async fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        LockingBackend::renew_lock(self, lock, extend_by)
+    }
+
+    /// Waits to be woken by the release of any lock, rather than polling.
+    /// Locks which expire instead of being released are noticed within
+    /// [`MAX_POLL_INTERVAL`].
+    async fn acquire_lock_waiting(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+        deadline: Instant,
+    ) -> Result<Lock, LockingError> {
+        let start = Instant::now();
+
+        loop {
+            // Waiting on releases starts before trying to lock, so releases
+            // in between are not missed.
+            let released = self.state.released.notified();
+
+            match LockingBackend::acquire_lock(self, entity, descriptors.clone(), expires) {
+                Err(LockingError::Conflict(entity, component, ..)) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(LockingError::TimedOut {
+                            entity,
+                            component,
+                            waited: now - start,
+                        });
+                    }
+
+                    let wait = MAX_POLL_INTERVAL.min(deadline - now);
+                    let _ = tokio::time::timeout(wait, released).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use eci_core::{
+    backend::{
+        AccessError, BackendError, Expiry, ExtractionDescriptor, Format, JointBackend, Lock,
+        LockDescriptor, LockedRead, LockingError,
+    },
+    is_valid_component_name, Entity,
+};
+use log::*;
+
+use crate::{access, lock, MemoryBackend};
+
+impl<F: Format> JointBackend<F> for MemoryBackend {
+    /// Holds on to both the lock table and the components while taking the
+    /// locks and reading, so the components read are exactly those locked.
+    fn read_and_lock(
+        &self,
+        entity: Entity,
+        locks: Vec<LockDescriptor>,
+        components: Vec<ExtractionDescriptor>,
+        expires: Expiry,
+    ) -> Result<LockedRead<F>, BackendError> {
+        if locks.is_empty() {
+            return Err(LockingError::EmptyRequest.into());
+        }
+
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest.into());
+        }
+
+        if let Some(invalid) = components
+            .iter()
+            .find(|descriptor| !is_valid_component_name(&descriptor.name))
+        {
+            return Err(AccessError::InvalidComponentName(invalid.name.clone()).into());
+        }
+
+        let lock = Lock::new();
+        let expires = lock::expiry(expires)?;
+
+        let mut rows = self.state.locks.lock().unwrap();
+        let before = rows.len();
+        self.insert_locks(&mut rows, &lock, entity, &locks, expires)?;
+
+        // Components stored with the wrong version take the locks back.
+        let read = access::read(&self.state.components.read().unwrap(), entity, &components)
+            .inspect_err(|_| rows.truncate(before))?;
+
+        debug!("acquired lock {lock} and read {} components", read.len());
+        Ok((lock::with_expiry(lock, expires), read))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, BackendError, ExtractionDescriptor, Format, JointBackend,
+            LockDescriptor, LockingBackend, LockingError, LockingMode, SerializedComponent,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::MemoryBackend;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    fn lock(mode: LockingMode) -> Vec<LockDescriptor> {
+        vec![LockDescriptor {
+            mode,
+            name: "DebugComponentA".to_string(),
+        }]
+    }
+
+    fn extract(version: Option<Version>) -> Vec<ExtractionDescriptor> {
+        vec![ExtractionDescriptor {
+            name: "DebugComponentA".to_string(),
+            version,
+            schema: None,
+        }]
+    }
+
+    #[test]
+    fn read_and_lock_atomically() {
+        let conn = MemoryBackend::new();
+        let entity = Entity::new();
+        AccessBackend::<Json>::write_components(
+            &conn,
+            entity,
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(DebugComponentA {
+                    content: "Hello".to_string(),
+                })
+                .unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+
+        let (held, components) = JointBackend::<Json>::read_and_lock(
+            &conn,
+            entity,
+            lock(LockingMode::Write),
+            extract(Some(Version::default())),
+            Duration::from_secs(60).into(),
+        )
+        .unwrap();
+
+        let component = components.into_iter().next().flatten().unwrap();
+        assert_eq!(
+            Json::deserialize::<DebugComponentA>(&component.contents).unwrap(),
+            DebugComponentA {
+                content: "Hello".to_string()
+            }
+        );
+
+        // Conflicting locks fail without reading.
+        assert!(matches!(
+            JointBackend::<Json>::read_and_lock(
+                &conn,
+                entity,
+                lock(LockingMode::Read),
+                extract(None),
+                Duration::from_secs(60).into(),
+            ),
+            Err(BackendError::Locking(LockingError::Conflict(..)))
+        ));
+        conn.release_lock(held).unwrap();
+
+        // Failing to read takes back the lock taken along with it.
+        assert!(matches!(
+            JointBackend::<Json>::read_and_lock(
+                &conn,
+                entity,
+                lock(LockingMode::Write),
+                extract(Some(Version::new(2, 0, 0))),
+                Duration::from_secs(60).into(),
+            ),
+            Err(BackendError::Access(AccessError::VersionMismatch { .. }))
+        ));
+        assert!(conn.list_locks(Some(entity)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+mod access;
+#[cfg(feature = "async")]
+mod asynchronous;
+mod joint;
+mod lock;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use eci_core::{Entity, Version};
+use lock::LockRow;
+
+/// Keeps components and locks in memory, for tests and prototypes which
+/// should not need a database. Otherwise it behaves like the sqlite
+/// backend: writes are atomic, and locks conflict and expire the same way.
+///
+/// Clones share the same components and locks, much like several backends
+/// opening the same database would.
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    state: Arc<State>,
+    owner: Option<String>,
+}
+
+/// The lock table is always locked before the components, so operations
+/// touching both cannot deadlock each other.
+#[derive(Default)]
+struct State {
+    locks: Mutex<Vec<LockRow>>,
+    components: RwLock<HashMap<Entity, HashMap<String, Stored>>>,
+    /// Wakes tasks waiting for locks whenever some are released.
+    #[cfg(feature = "async")]
+    released: tokio::sync::Notify,
+}
+
+/// Contents of a component, along with the version of its layout they were
+/// written with.
+struct Stored {
+    contents: Vec<u8>,
+    version: Version,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the owner, such as a test or service name, with every lock
+    /// acquired through this backend, so others running into the locks can
+    /// tell who holds them.
+    pub fn with_owner<O: Into<String>>(mut self, owner: O) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+}
@@ -0,0 +1,439 @@
+use eci_core::{
+    backend::{
+        ActiveLock, Administrative, BulkLockResult, ConflictingLock, Expiry, Lock, LockDescriptor,
+        LockStats, LockingBackend, LockingError, LockingMode,
+    },
+    Entity,
+};
+use log::*;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+use crate::MemoryBackend;
+
+/// A lock held on a single component, or on the entity as a whole.
+pub(crate) struct LockRow {
+    id: String,
+    entity: Entity,
+    component: String,
+    mode: LockingMode,
+    /// `None` for locks which never expire.
+    expires: Option<SystemTime>,
+    owner: Option<String>,
+}
+
+impl LockRow {
+    fn is_active(&self, now: SystemTime) -> bool {
+        self.expires.is_none_or(|expires| now < expires)
+    }
+
+    /// Whether this lock keeps a lock in the given mode from being taken on
+    /// the entity's component, following the same rules as the sqlite
+    /// backend.
+    fn conflicts(
+        &self,
+        entity: Entity,
+        component: &str,
+        mode: LockingMode,
+        now: SystemTime,
+    ) -> bool {
+        self.entity == entity
+            && self.is_active(now)
+            && (self.mode == LockingMode::Exclusive
+                || mode == LockingMode::Exclusive
+                || (self.component == component
+                    && (self.mode == LockingMode::Write || mode == LockingMode::Write)))
+    }
+}
+
+impl LockingBackend for MemoryBackend {
+    fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        if descriptors.is_empty() {
+            return Err(LockingError::EmptyRequest);
+        }
+
+        let lock = Lock::new();
+        let expires = expiry(expires)?;
+
+        let mut rows = self.state.locks.lock().unwrap();
+        self.insert_locks(&mut rows, &lock, entity, &descriptors, expires)?;
+        debug!("acquired lock {lock}");
+
+        Ok(with_expiry(lock, expires))
+    }
+
+    fn acquire_locks(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: std::time::Duration,
+    ) -> Result<Lock, LockingError> {
+        if requests.is_empty()
+            || requests
+                .iter()
+                .any(|(_, descriptors)| descriptors.is_empty())
+        {
+            return Err(LockingError::EmptyRequest);
+        }
+
+        let lock = Lock::new();
+        let expires = expiry(Expiry::In(expires_in))?;
+
+        let mut rows = self.state.locks.lock().unwrap();
+        let before = rows.len();
+        for (entity, descriptors) in &requests {
+            if let Err(err) = self.insert_locks(&mut rows, &lock, *entity, descriptors, expires) {
+                // Nothing is locked unless everything is.
+                rows.truncate(before);
+                return Err(err);
+            }
+        }
+
+        debug!("acquired lock {lock} on {} entities", requests.len());
+        Ok(with_expiry(lock, expires))
+    }
+
+    fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        debug!("releasing lock {lock}");
+
+        let mut rows = self.state.locks.lock().unwrap();
+        let before = rows.len();
+        rows.retain(|row| row.id != lock.id());
+
+        debug!(
+            "deleted locks on {} resources by releasing {lock}",
+            before - rows.len()
+        );
+        self.notify_released();
+        Ok(())
+    }
+
+    fn renew_lock(&self, lock: &Lock, extend_by: std::time::Duration) -> Result<(), LockingError> {
+        debug!("renewing lock {lock}");
+        let expires = expiry(Expiry::In(extend_by))?;
+        let now = SystemTime::now();
+
+        // Renewing a lock which never expires leaves it that way.
+        let mut renewed = 0;
+        for row in self.state.locks.lock().unwrap().iter_mut() {
+            if row.id == lock.id() && row.is_active(now) {
+                if row.expires.is_some() {
+                    row.expires = expires;
+                }
+                renewed += 1;
+            }
+        }
+
+        if renewed == 0 {
+            return Err(LockingError::Expired(lock.id()));
+        }
+
+        debug!("renewed locks on {renewed} resources held by {lock}");
+        Ok(())
+    }
+
+    fn upgrade_lock(
+        &self,
+        lock: &Lock,
+        entity: Entity,
+        component: String,
+    ) -> Result<(), LockingError> {
+        let mut rows = self.state.locks.lock().unwrap();
+        let now = SystemTime::now();
+
+        let on_component = |row: &&LockRow| {
+            row.entity == entity && row.component == component && row.is_active(now)
+        };
+
+        if !rows
+            .iter()
+            .filter(on_component)
+            .any(|row| row.id == lock.id())
+        {
+            return Err(LockingError::Expired(lock.id()));
+        }
+
+        if rows
+            .iter()
+            .filter(on_component)
+            .any(|row| row.id != lock.id())
+        {
+            let holder = find_conflict(&rows, lock, entity, &component, LockingMode::Write, now);
+            return Err(LockingError::Conflict(
+                entity,
+                component,
+                LockingMode::Write,
+                holder,
+            ));
+        }
+
+        for row in rows.iter_mut() {
+            if row.id == lock.id() && row.entity == entity && row.component == component {
+                row.mode = LockingMode::Write;
+            }
+        }
+
+        debug!("upgraded lock {lock} on {entity}'s {component} to a write lock");
+        Ok(())
+    }
+
+    fn force_release(&self, lock_id: Uuid, _: Administrative) -> Result<usize, LockingError> {
+        warn!("forcibly releasing lock {lock_id}");
+
+        let mut rows = self.state.locks.lock().unwrap();
+        let before = rows.len();
+        rows.retain(|row| row.id != lock_id.to_string());
+        self.notify_released();
+        Ok(before - rows.len())
+    }
+
+    fn force_release_entity(
+        &self,
+        entity: Entity,
+        _: Administrative,
+    ) -> Result<usize, LockingError> {
+        warn!("forcibly releasing all locks on {entity}");
+
+        let mut rows = self.state.locks.lock().unwrap();
+        let before = rows.len();
+        rows.retain(|row| row.entity != entity);
+        self.notify_released();
+        Ok(before - rows.len())
+    }
+
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        let now = SystemTime::now();
+
+        let mut stats = LockStats::default();
+        for row in self.state.locks.lock().unwrap().iter() {
+            if row.is_active(now) {
+                stats.active += 1;
+            } else {
+                stats.expired += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn list_locks(&self, filter: Option<Entity>) -> Result<Vec<ActiveLock>, LockingError> {
+        let now = SystemTime::now();
+
+        let mut locks: Vec<ActiveLock> = self
+            .state
+            .locks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|row| filter.is_none_or(|entity| row.entity == entity))
+            .filter(|row| row.is_active(now))
+            .map(|row| ActiveLock {
+                id: row.id.clone(),
+                entity: row.entity,
+                component: row.component.clone(),
+                mode: row.mode,
+                expires: row.expires,
+                owner: row.owner.clone(),
+            })
+            .collect();
+
+        locks.sort_by(|a, b| (a.entity, &a.component, &a.id).cmp(&(b.entity, &b.component, &b.id)));
+        Ok(locks)
+    }
+
+    fn acquire_locks_bulk(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: std::time::Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        let lock = Lock::new();
+        let expires = expiry(Expiry::In(expires_in))?;
+
+        let mut rows = self.state.locks.lock().unwrap();
+        let (mut granted, mut skipped) = (Vec::new(), Vec::new());
+        for (entity, descriptors) in &requests {
+            match self.insert_locks(&mut rows, &lock, *entity, descriptors, expires) {
+                Ok(()) => granted.push(*entity),
+                Err(LockingError::Conflict(..)) => {
+                    debug!("skipping {entity} due to conflicting locks");
+                    skipped.push(*entity);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        debug!(
+            "bulk lock {lock} acquired, locked {} entities",
+            granted.len()
+        );
+        Ok(BulkLockResult {
+            lock: with_expiry(lock, expires),
+            granted,
+            skipped,
+        })
+    }
+}
+
+/// Determines when a lock expires, failing for expiries too far into the
+/// future to represent.
+pub(crate) fn expiry(expires: Expiry) -> Result<Option<SystemTime>, LockingError> {
+    match expires {
+        Expiry::In(expires_in) => SystemTime::now()
+            .checked_add(expires_in)
+            .map(Some)
+            .ok_or(LockingError::ExpiryOutOfRange(expires_in)),
+        Expiry::Never => Ok(None),
+    }
+}
+
+pub(crate) fn with_expiry(lock: Lock, expires: Option<SystemTime>) -> Lock {
+    match expires {
+        Some(expires) => lock.with_expiry(expires),
+        None => lock,
+    }
+}
+
+/// Finds the lock held by someone else which conflicts with the requested
+/// one, preferring the one which expires first.
+fn find_conflict(
+    rows: &[LockRow],
+    lock: &Lock,
+    entity: Entity,
+    component: &str,
+    mode: LockingMode,
+    now: SystemTime,
+) -> Option<ConflictingLock> {
+    rows.iter()
+        .filter(|row| row.id != lock.id() && row.conflicts(entity, component, mode, now))
+        .min_by_key(|row| (row.expires.is_none(), row.expires))
+        .map(|row| ConflictingLock {
+            id: row.id.clone(),
+            mode: row.mode,
+            owner: row.owner.clone(),
+            expires: row.expires,
+        })
+}
+
+/// Whether the lock holds an unexpired write lock on the entity's component,
+/// either on the component itself or on the entity as a whole.
+pub(crate) fn holds_write_lock(
+    rows: &[LockRow],
+    lock: &Lock,
+    entity: Entity,
+    component: &str,
+) -> bool {
+    let now = SystemTime::now();
+    rows.iter().any(|row| {
+        row.id == lock.id()
+            && row.entity == entity
+            && ((row.mode == LockingMode::Write && row.component == component)
+                || row.mode == LockingMode::Exclusive)
+            && row.is_active(now)
+    })
+}
+
+impl MemoryBackend {
+    /// Wakes the tasks waiting for conflicting locks to be released, see
+    /// [`eci_core::backend::AsyncLockingBackend::acquire_lock_waiting`].
+    fn notify_released(&self) {
+        #[cfg(feature = "async")]
+        self.state.released.notify_waiters();
+    }
+
+    /// Adds a lock row for each of the descriptors, failing with
+    /// [`LockingError::Conflict`] on the first one which conflicts with an
+    /// existing lock, in which case the rows added before it are removed
+    /// again.
+    pub(crate) fn insert_locks(
+        &self,
+        rows: &mut Vec<LockRow>,
+        lock: &Lock,
+        entity: Entity,
+        descriptors: &[LockDescriptor],
+        expires: Option<SystemTime>,
+    ) -> Result<(), LockingError> {
+        let before = rows.len();
+        let now = SystemTime::now();
+
+        for descriptor in descriptors {
+            debug!("acquiring {}-lock for {}", descriptor.mode, descriptor.name);
+
+            // Like in the sqlite backend, the lock's own rows count too.
+            if rows
+                .iter()
+                .any(|row| row.conflicts(entity, &descriptor.name, descriptor.mode, now))
+            {
+                let holder =
+                    find_conflict(rows, lock, entity, &descriptor.name, descriptor.mode, now);
+                rows.truncate(before);
+                return Err(LockingError::Conflict(
+                    entity,
+                    descriptor.name.clone(),
+                    descriptor.mode,
+                    holder,
+                ));
+            }
+
+            rows.push(LockRow {
+                id: lock.id(),
+                entity,
+                component: descriptor.name.clone(),
+                mode: descriptor.mode,
+                expires,
+                owner: self.owner.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_core::{
+        backend::{LockDescriptor, LockingBackend, LockingError, LockingMode},
+        Entity,
+    };
+
+    use crate::MemoryBackend;
+
+    eci_testing::locking_conformance!(MemoryBackend::new());
+
+    const LOCK_TIME: Duration = Duration::from_secs(60);
+
+    fn write_lock() -> Vec<LockDescriptor> {
+        vec![LockDescriptor {
+            mode: LockingMode::Write,
+            name: "DebugComponentA".to_string(),
+        }]
+    }
+
+    #[test]
+    fn clones_share_locks() {
+        let holder = MemoryBackend::new().with_owner("inventory-service");
+        let contender = holder.clone().with_owner("billing-service");
+
+        let entity = Entity::new();
+        let held = holder
+            .acquire_lock(entity, write_lock(), LOCK_TIME.into())
+            .unwrap();
+
+        match contender.acquire_lock(entity, write_lock(), LOCK_TIME.into()) {
+            Err(LockingError::Conflict(_, _, _, Some(conflict))) => {
+                assert_eq!(conflict.id, held.id());
+                assert_eq!(conflict.owner.as_deref(), Some("inventory-service"));
+            }
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+
+        let listed = contender.list_locks(None).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].expires, held.expires_at());
+    }
+}
@@ -0,0 +1,364 @@
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format, Lock,
+        SerializedComponent,
+    },
+    is_valid_component_name, Entity, Version,
+};
+use uuid::Uuid;
+
+use crate::RedisBackend;
+
+impl<F: Format> AccessBackend<F> for RedisBackend {
+    fn supports_atomic_writes(&self) -> bool {
+        true
+    }
+
+    fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        self.write("insert", None, vec![(entity, components)])
+    }
+
+    fn write_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        self.write("insert", Some(lock), vec![(entity, components)])
+    }
+
+    fn write_components_batch(
+        &self,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        self.write("insert", None, batch)
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.write("absent", None, vec![(entity, components)])
+    }
+
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.write("update", None, vec![(entity, components)])
+    }
+
+    fn update_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        self.write("update", Some(lock), vec![(entity, components)])
+    }
+
+    fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        check_names(
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str()),
+        )?;
+        let names: Vec<&str> = descriptors
+            .iter()
+            .map(|descriptor| descriptor.name.as_str())
+            .collect();
+
+        let key = self.key("entity", entity);
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        let (contents, versions): (Vec<Option<Vec<u8>>>, Vec<Option<String>>) = redis::pipe()
+            .atomic()
+            .cmd("HMGET")
+            .arg(&key)
+            .arg(&names)
+            .cmd("HMGET")
+            .arg(format!("{key}:versions"))
+            .arg(&names)
+            .query(&mut *conn)
+            .map_err(AccessError::implementation)?;
+
+        descriptors
+            .iter()
+            .zip(contents.into_iter().zip(versions))
+            .map(|(descriptor, (contents, version))| {
+                let (Some(contents), Some(version)) = (contents, version) else {
+                    return Ok(None);
+                };
+
+                let version = parse_version(&version)?;
+                check_version(descriptor, version)?;
+                Ok(Some(SerializedComponent::<F> {
+                    contents: F::Data::from(contents),
+                    name: descriptor.name.clone(),
+                    version,
+                    schema: None,
+                    metadata: None,
+                }))
+            })
+            .collect()
+    }
+
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.remove(entity, None, descriptors)
+    }
+
+    fn remove_components_locked(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.remove(entity, Some(lock), descriptors)
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        check_names([component])?;
+        self.entities(&self.key("component", component))
+    }
+
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        self.entities(&format!("{}:entities", self.namespace))
+    }
+
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        let mut names: Vec<String> = redis::cmd("HKEYS")
+            .arg(self.key("entity", entity))
+            .query(&mut *conn)
+            .map_err(AccessError::implementation)?;
+
+        names.sort();
+        Ok(names)
+    }
+
+    /// Only reports components which at least one entity has, since nothing
+    /// is left behind in Redis once the last of them is removed.
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        let reply: Vec<String> = self
+            .scripts
+            .stats
+            .arg(&self.namespace)
+            .invoke(&mut *conn)
+            .map_err(AccessError::implementation)?;
+
+        reply
+            .chunks(3)
+            .map(|stats| {
+                Ok(ComponentStats {
+                    name: stats[0].clone(),
+                    entity_count: stats[1].parse().map_err(AccessError::implementation)?,
+                    total_bytes: stats[2].parse().map_err(AccessError::implementation)?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl RedisBackend {
+    /// Removes the components through the remove script, failing with the
+    /// first missing write lock or version mismatch without removing any.
+    fn remove<F: Format>(
+        &self,
+        entity: Entity,
+        lock: Option<&Lock>,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        check_names(
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str()),
+        )?;
+        if descriptors.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut invocation = self.scripts.remove.prepare_invoke();
+        invocation
+            .arg(&self.namespace)
+            .arg(entity.to_string())
+            .arg(lock.map(Lock::id).unwrap_or_default());
+        for descriptor in &descriptors {
+            invocation.arg(&descriptor.name).arg(
+                descriptor
+                    .version
+                    .map(|version| version.to_string())
+                    .unwrap_or_default(),
+            );
+        }
+
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        let reply: Vec<Vec<u8>> = invocation
+            .invoke(&mut *conn)
+            .map_err(AccessError::implementation)?;
+
+        if reply[0] == b"unlocked" {
+            return Err(AccessError::LockRequired(entity, text(&reply[1])?));
+        }
+
+        if reply[0] == b"mismatch" {
+            return Err(AccessError::VersionMismatch {
+                component: text(&reply[1])?,
+                stored: parse_version(&text(&reply[2])?)?,
+                expected: descriptors
+                    .iter()
+                    .find(|descriptor| descriptor.name.as_bytes() == reply[1])
+                    .and_then(|descriptor| descriptor.version)
+                    .unwrap_or_default(),
+            });
+        }
+
+        descriptors
+            .iter()
+            .zip(reply[1..].chunks(3))
+            .map(|(descriptor, removed)| {
+                if removed[0] != b"1" {
+                    return Ok(None);
+                }
+
+                Ok(Some(SerializedComponent::<F> {
+                    contents: F::Data::from(removed[1].clone()),
+                    name: descriptor.name.clone(),
+                    version: parse_version(&text(&removed[2])?)?,
+                    schema: None,
+                    metadata: None,
+                }))
+            })
+            .collect()
+    }
+
+    /// Writes the components through the write script, failing with the
+    /// first conflict or missing write lock without writing any of them.
+    fn write<F: Format>(
+        &self,
+        mode: &str,
+        lock: Option<&Lock>,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        for (_, components) in &batch {
+            check_names(components.iter().map(|component| component.name.as_str()))?;
+        }
+
+        if batch.iter().all(|(_, components)| components.is_empty()) {
+            return Ok(());
+        }
+
+        let mut invocation = self.scripts.write.prepare_invoke();
+        invocation
+            .arg(&self.namespace)
+            .arg(mode)
+            .arg(lock.map(Lock::id).unwrap_or_default());
+        for (entity, components) in &batch {
+            for component in components {
+                invocation
+                    .arg(entity.to_string())
+                    .arg(&component.name)
+                    .arg(component.version.to_string())
+                    .arg(component.contents.as_ref());
+            }
+        }
+
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        let reply: Vec<String> = invocation
+            .invoke(&mut *conn)
+            .map_err(AccessError::implementation)?;
+
+        match reply.first().map(String::as_str) {
+            None => Ok(()),
+            Some("unlocked") => Err(AccessError::LockRequired(
+                parse_entity(&reply[1])?,
+                reply[2].clone(),
+            )),
+            Some(_) => Err(AccessError::Conflict(
+                parse_entity(&reply[1])?,
+                reply[2].clone(),
+            )),
+        }
+    }
+
+    /// Lists the members of a set of entities, in order.
+    fn entities(&self, key: &str) -> Result<Vec<Entity>, AccessError> {
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        let members: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(key)
+            .query(&mut *conn)
+            .map_err(AccessError::implementation)?;
+
+        let mut entities = members
+            .iter()
+            .map(|entity| parse_entity(entity))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        entities.sort();
+        Ok(entities)
+    }
+}
+
+/// Rejects names which are not valid component names, so the Redis backend
+/// accepts exactly the components the others do.
+fn check_names<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Result<(), AccessError> {
+    match names
+        .into_iter()
+        .find(|name| !is_valid_component_name(name))
+    {
+        Some(name) => Err(AccessError::InvalidComponentName(name.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Fails if the component was stored with another version of its layout
+/// than the one it is being read as.
+fn check_version(descriptor: &ExtractionDescriptor, stored: Version) -> Result<(), AccessError> {
+    match descriptor.version {
+        Some(expected) if expected != stored => Err(AccessError::VersionMismatch {
+            component: descriptor.name.clone(),
+            stored,
+            expected,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version, AccessError> {
+    version.parse().map_err(AccessError::implementation)
+}
+
+fn parse_entity(entity: &str) -> Result<Entity, AccessError> {
+    Ok(Entity(
+        Uuid::parse_str(entity).map_err(AccessError::implementation)?,
+    ))
+}
+
+fn text(bytes: &[u8]) -> Result<String, AccessError> {
+    String::from_utf8(bytes.to_vec()).map_err(AccessError::implementation)
+}
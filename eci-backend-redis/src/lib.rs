@@ -0,0 +1,80 @@
+//! Stores components and locks in Redis, for state which is shared between
+//! processes but need not outlive the Redis instance, such as game sessions.
+//!
+//! Each entity's components are stored as fields of an `entity:{uuid}`
+//! hash. Locks are kept by a [`RedisLockingBackend`] under the same
+//! namespace, as keys which expire along with them, so the locks of holders
+//! which crashed are released by Redis itself. Every operation runs as a
+//! single command or Lua script, so writes and lock acquisitions are
+//! all-or-nothing like the transactions of the sqlite backend, and writes
+//! requiring a lock check it within the same script.
+//!
+//! Consistency differs from the sqlite backend in a few ways:
+//!
+//! * Reading and locking are not one transaction. Components are read once
+//!   the locks are taken, so the outcome is the same, but a failed read
+//!   releases the locks through a separate request.
+//! * Writes are only as durable as the Redis persistence configuration.
+//!   Acknowledged writes may be lost on a restart without an append-only
+//!   file, and on failover to a replica which had not caught up.
+//! * Locks expire on the Redis server's clock, rather than the clock of
+//!   whichever process checks them.
+//! * Scripts compute their keys from their arguments, so every key must live
+//!   on the same node. Redis Cluster is not supported.
+
+mod access;
+mod lock;
+mod scripts;
+
+use eci_core::backend::{AccessError, Format, JointBackend};
+use eci_lock_redis::RedisLockingBackend;
+use r2d2::Pool;
+use scripts::Scripts;
+
+pub struct RedisBackend {
+    pool: Pool<redis::Client>,
+    /// Prefixed to every key, so several backends can share a database.
+    namespace: String,
+    scripts: Scripts,
+    locking: RedisLockingBackend,
+}
+
+/// Reads through the default implementation, which takes the locks first
+/// and releases them again if reading fails.
+impl<F: Format> JointBackend<F> for RedisBackend {}
+
+impl RedisBackend {
+    /// Connects to the Redis instance at the url, such as
+    /// `redis://127.0.0.1/`, keeping keys under the `eci` namespace.
+    pub fn open(url: &str) -> Result<Self, AccessError> {
+        let client = redis::Client::open(url).map_err(AccessError::implementation)?;
+        let pool = Pool::new(client).map_err(AccessError::implementation)?;
+
+        Ok(RedisBackend {
+            locking: RedisLockingBackend::from_pool(pool.clone()),
+            pool,
+            namespace: "eci".to_string(),
+            scripts: Scripts::new(),
+        })
+    }
+
+    /// Keeps keys under another namespace, so the components and locks of
+    /// one backend are invisible to backends using other namespaces.
+    pub fn with_namespace<N: Into<String>>(mut self, namespace: N) -> Self {
+        self.namespace = namespace.into();
+        self.locking = self.locking.with_namespace(self.namespace.clone());
+        self
+    }
+
+    /// Records the owner, such as a hostname, process id or service name,
+    /// with every lock acquired through this backend, so others running
+    /// into the locks can tell who holds them.
+    pub fn with_owner<O: Into<String>>(mut self, owner: O) -> Self {
+        self.locking = self.locking.with_owner(owner);
+        self
+    }
+
+    fn key(&self, kind: &str, name: impl std::fmt::Display) -> String {
+        format!("{}:{kind}:{name}", self.namespace)
+    }
+}
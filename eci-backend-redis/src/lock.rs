@@ -0,0 +1,77 @@
+use eci_core::{
+    backend::{
+        ActiveLock, Administrative, BulkLockResult, Expiry, Lock, LockDescriptor, LockStats,
+        LockingBackend, LockingError,
+    },
+    Entity,
+};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::RedisBackend;
+
+/// Locks through the [`eci_lock_redis::RedisLockingBackend`] sharing the
+/// backend's connections and namespace.
+impl LockingBackend for RedisBackend {
+    fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        self.locking.acquire_lock(entity, descriptors, expires)
+    }
+
+    fn acquire_locks(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<Lock, LockingError> {
+        self.locking.acquire_locks(requests, expires_in)
+    }
+
+    fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        self.locking.release_lock(lock)
+    }
+
+    fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        self.locking.renew_lock(lock, extend_by)
+    }
+
+    fn upgrade_lock(
+        &self,
+        lock: &Lock,
+        entity: Entity,
+        component: String,
+    ) -> Result<(), LockingError> {
+        self.locking.upgrade_lock(lock, entity, component)
+    }
+
+    fn force_release(&self, lock_id: Uuid, admin: Administrative) -> Result<usize, LockingError> {
+        self.locking.force_release(lock_id, admin)
+    }
+
+    fn force_release_entity(
+        &self,
+        entity: Entity,
+        admin: Administrative,
+    ) -> Result<usize, LockingError> {
+        self.locking.force_release_entity(entity, admin)
+    }
+
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        self.locking.lock_stats()
+    }
+
+    fn list_locks(&self, filter: Option<Entity>) -> Result<Vec<ActiveLock>, LockingError> {
+        self.locking.list_locks(filter)
+    }
+
+    fn acquire_locks_bulk(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        self.locking.acquire_locks_bulk(requests, expires_in)
+    }
+}
@@ -0,0 +1,132 @@
+use eci_lock_redis::HOLDS_WRITE_LOCK;
+use redis::Script;
+
+/// Shared by every script. The first argument is always the namespace all
+/// keys are prefixed with. Locks are kept under the same namespace by
+/// [`eci_lock_redis::RedisLockingBackend`], whose keys the scripts check
+/// writes against through [`HOLDS_WRITE_LOCK`].
+const PRELUDE: &str = r#"
+local ns = ARGV[1]
+
+local function entity_key(entity) return ns .. ':entity:' .. entity end
+local function versions_key(entity) return entity_key(entity) .. ':versions' end
+local function component_key(component) return ns .. ':component:' .. component end
+"#;
+
+/// Arguments: mode (`insert`, `update` or `absent`), the lock id required
+/// to hold write locks on the components or an empty string, then entity,
+/// component, version and contents of each component.
+const WRITE: &str = r#"
+local mode, lockid = ARGV[2], ARGV[3]
+if lockid ~= '' then
+  for i = 4, #ARGV, 4 do
+    local entity, component = ARGV[i], ARGV[i + 1]
+    if not holds_write_lock(entity, component, lockid) then
+      return {'unlocked', entity, component}
+    end
+  end
+end
+
+if mode == 'insert' then
+  local seen = {}
+  for i = 4, #ARGV, 4 do
+    local entity, component = ARGV[i], ARGV[i + 1]
+    local written = entity .. ':' .. component
+    if seen[written] or redis.call('HEXISTS', entity_key(entity), component) == 1 then
+      return {'conflict', entity, component}
+    end
+    seen[written] = true
+  end
+end
+
+for i = 4, #ARGV, 4 do
+  local entity, component, version, contents = ARGV[i], ARGV[i + 1], ARGV[i + 2], ARGV[i + 3]
+  if mode ~= 'absent' or redis.call('HEXISTS', entity_key(entity), component) == 0 then
+    redis.call('HSET', entity_key(entity), component, contents)
+    redis.call('HSET', versions_key(entity), component, version)
+    redis.call('SADD', component_key(component), entity)
+    redis.call('SADD', ns .. ':components', component)
+    redis.call('SADD', ns .. ':entities', entity)
+  end
+end
+return {}
+"#;
+
+/// Arguments: the entity, the lock id required to hold write locks on the
+/// components or an empty string, then each component along with the
+/// version it is expected to have, or an empty string for any version.
+const REMOVE: &str = r#"
+local entity, lockid = ARGV[2], ARGV[3]
+for i = 4, #ARGV, 2 do
+  if lockid ~= '' and not holds_write_lock(entity, ARGV[i], lockid) then
+    return {'unlocked', ARGV[i]}
+  end
+
+  local stored = redis.call('HGET', versions_key(entity), ARGV[i])
+  if stored and ARGV[i + 1] ~= '' and stored ~= ARGV[i + 1] then
+    return {'mismatch', ARGV[i], stored}
+  end
+end
+
+local removed = {'ok'}
+for i = 4, #ARGV, 2 do
+  local component = ARGV[i]
+  local contents = redis.call('HGET', entity_key(entity), component)
+  if contents then
+    table.insert(removed, '1')
+    table.insert(removed, contents)
+    table.insert(removed, redis.call('HGET', versions_key(entity), component))
+    redis.call('HDEL', entity_key(entity), component)
+    redis.call('HDEL', versions_key(entity), component)
+    redis.call('SREM', component_key(component), entity)
+    if redis.call('SCARD', component_key(component)) == 0 then
+      redis.call('SREM', ns .. ':components', component)
+    end
+  else
+    table.insert(removed, '0')
+    table.insert(removed, '')
+    table.insert(removed, '')
+  end
+end
+
+if redis.call('EXISTS', entity_key(entity)) == 0 then
+  redis.call('SREM', ns .. ':entities', entity)
+end
+return removed
+"#;
+
+/// Returns the name, entity count and total size of each component.
+const STATS: &str = r#"
+local stats = {}
+for _, component in ipairs(redis.call('SORT', ns .. ':components', 'ALPHA')) do
+  local entities = redis.call('SMEMBERS', component_key(component))
+  local bytes = 0
+  for _, entity in ipairs(entities) do
+    bytes = bytes + redis.call('HSTRLEN', entity_key(entity), component)
+  end
+  table.insert(stats, component)
+  table.insert(stats, tostring(#entities))
+  table.insert(stats, tostring(bytes))
+end
+return stats
+"#;
+
+pub(crate) struct Scripts {
+    pub write: Script,
+    pub remove: Script,
+    pub stats: Script,
+}
+
+fn script(body: &str) -> Script {
+    Script::new(&format!("{PRELUDE}{HOLDS_WRITE_LOCK}{body}"))
+}
+
+impl Scripts {
+    pub fn new() -> Self {
+        Scripts {
+            write: script(WRITE),
+            remove: script(REMOVE),
+            stats: script(STATS),
+        }
+    }
+}
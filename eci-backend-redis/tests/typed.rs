@@ -0,0 +1,114 @@
+//! Runs against the Redis instance at `ECI_REDIS_URL`, or a local one, with
+//! `cargo test -p eci-backend-redis -- --ignored`. Each test keeps to its own
+//! namespace.
+
+use eci_backend_redis::RedisBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, BackendError, LockingBackend, LockingError},
+    Component, Entity,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterA(pub usize);
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterB(pub usize);
+
+fn backend() -> Backend<Json> {
+    let url = std::env::var("ECI_REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+    Backend::from_joint(
+        RedisBackend::open(&url)
+            .unwrap()
+            .with_namespace(format!("eci-test-{}", Entity::new())),
+    )
+}
+
+fn is_conflict(result: Result<impl Sized, BackendError>) -> bool {
+    matches!(
+        result.as_ref().map_err(BackendError::root),
+        Err(BackendError::Locking(LockingError::Conflict(..)))
+    )
+}
+
+#[test]
+#[ignore = "needs a Redis instance"]
+fn put_get_and_remove() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(1), CounterB(2)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend.get::<&mut CounterA>(entity).unwrap().unwrap();
+    locked.deref().0 += 1;
+    locked.commit().unwrap();
+
+    assert_eq!(
+        backend.peek::<&CounterA>(entity).unwrap(),
+        Some(CounterA(2))
+    );
+    assert_eq!(backend.list_entities().unwrap(), vec![entity]);
+
+    backend.remove::<(CounterA, CounterB)>(entity).unwrap();
+    assert_eq!(backend.peek::<&CounterA>(entity).unwrap(), None);
+    assert!(backend.list_entities().unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "needs a Redis instance"]
+fn conflicting_locks() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(0), CounterB(0)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let _read = backend.get::<&CounterA>(entity).unwrap().unwrap();
+    let _also_read = backend.get::<&CounterA>(entity).unwrap().unwrap();
+    assert!(is_conflict(backend.get::<&mut CounterA>(entity)));
+
+    let lock = backend
+        .lock_entity(entity, Duration::from_secs(60))
+        .map(|_| ())
+        .unwrap_err();
+    assert!(is_conflict(Err::<(), _>(lock)));
+
+    // Conflicts on one entity of a pair leave the other unlocked.
+    let other = Entity::new();
+    backend
+        .put(other, (CounterB(0),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    assert!(is_conflict(
+        backend.get_pair::<&mut CounterB, &mut CounterA>(other, entity)
+    ));
+    backend.get::<&mut CounterB>(other).unwrap().unwrap();
+}
+
+#[test]
+#[ignore = "needs a Redis instance"]
+fn locks_expire() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(0),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let held = backend
+        .lock_entity(entity, Duration::from_millis(100))
+        .unwrap();
+    assert!(is_conflict(backend.get::<&CounterA>(entity)));
+
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(backend.list_locks(Some(entity)).unwrap().is_empty());
+    backend.get::<&mut CounterA>(entity).unwrap().unwrap();
+    drop(held);
+}
@@ -0,0 +1,549 @@
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format,
+        SerializedComponent,
+    },
+    is_valid_component_name, Entity, Version,
+};
+use log::*;
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+
+use crate::{store::failed, Corrupted, ObjectStore, S3Backend};
+
+impl<F: Format, S: ObjectStore> AccessBackend<F> for S3Backend<S> {
+    /// Inserts each component with a conditional put, deleting the ones
+    /// already written if a later one conflicts.
+    fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        check_names(components.iter().map(|component| component.name.as_str()))?;
+
+        let mut seen = HashSet::new();
+        if let Some(component) = components
+            .iter()
+            .find(|component| !seen.insert(component.name.as_str()))
+        {
+            return Err(AccessError::Conflict(entity, component.name.clone()));
+        }
+
+        let mut written = Vec::with_capacity(components.len());
+        for component in &components {
+            let key = self.key(entity, &component.name);
+            let inserted = self
+                .store
+                .put_if_absent(&key, encode(component.version, component.contents.as_ref()));
+
+            match inserted {
+                Ok(true) => written.push(key),
+                Ok(false) => {
+                    self.undo(&written);
+                    return Err(AccessError::Conflict(entity, component.name.clone()));
+                }
+                Err(err) => {
+                    self.undo(&written);
+                    return Err(failed(err));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        check_names(components.iter().map(|component| component.name.as_str()))?;
+
+        for component in components {
+            self.store
+                .put_if_absent(
+                    &self.key(entity, &component.name),
+                    encode(component.version, component.contents.as_ref()),
+                )
+                .map_err(failed)?;
+        }
+
+        Ok(())
+    }
+
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        check_names(components.iter().map(|component| component.name.as_str()))?;
+
+        for component in components {
+            self.store
+                .put(
+                    &self.key(entity, &component.name),
+                    encode(component.version, component.contents.as_ref()),
+                )
+                .map_err(failed)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        check_names(
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str()),
+        )?;
+        self.read(entity, &descriptors)
+    }
+
+    /// Reads the components before deleting them, so none of them are
+    /// deleted if any was stored with the wrong version.
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        check_names(
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str()),
+        )?;
+
+        let removed = self.read(entity, &descriptors)?;
+        for (descriptor, component) in descriptors.iter().zip(&removed) {
+            if component.is_some() {
+                self.store
+                    .delete(&self.key(entity, &descriptor.name))
+                    .map_err(failed)?;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        check_names([component])?;
+
+        self.objects()?
+            .into_iter()
+            .filter(|(_, name, _)| name == component)
+            .map(|(entity, _, _)| Ok(entity))
+            .collect()
+    }
+
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        let mut entities: Vec<Entity> = self
+            .objects()?
+            .into_iter()
+            .map(|(entity, _, _)| entity)
+            .collect();
+
+        // Keys start with the entity, so each entity's are adjacent.
+        entities.dedup();
+        Ok(entities)
+    }
+
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        let prefix = format!("{}{entity}/", self.root());
+        Ok(self
+            .store
+            .list(&prefix)
+            .map_err(failed)?
+            .into_iter()
+            .map(|listed| listed.key[prefix.len()..].to_string())
+            .collect())
+    }
+
+    /// Sizes come from the listing, so no objects are fetched.
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        let mut stats = BTreeMap::<String, (u64, u64)>::new();
+
+        for (_, name, size) in self.objects()? {
+            let (entity_count, total_bytes) = stats.entry(name).or_default();
+            *entity_count += 1;
+            *total_bytes += size.saturating_sub(VERSION_LENGTH as u64);
+        }
+
+        Ok(stats
+            .into_iter()
+            .map(|(name, (entity_count, total_bytes))| ComponentStats {
+                name,
+                entity_count,
+                total_bytes,
+            })
+            .collect())
+    }
+}
+
+impl<S: ObjectStore> S3Backend<S> {
+    /// Fetches the described components in parallel, failing if any of them
+    /// was stored with another version of its layout than the one it is
+    /// being read as.
+    fn read<F: Format>(
+        &self,
+        entity: Entity,
+        descriptors: &[ExtractionDescriptor],
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        let keys: Vec<String> = descriptors
+            .iter()
+            .map(|descriptor| self.key(entity, &descriptor.name))
+            .collect();
+
+        let fetched: Vec<_> = std::thread::scope(|scope| {
+            let fetches: Vec<_> = keys
+                .iter()
+                .map(|key| scope.spawn(move || self.store.get(key)))
+                .collect();
+
+            fetches
+                .into_iter()
+                .map(|fetch| fetch.join().expect("fetching an object panicked"))
+                .collect()
+        });
+
+        let mut components = Vec::with_capacity(descriptors.len());
+        for (descriptor, object) in descriptors.iter().zip(fetched) {
+            let Some(object) = object.map_err(failed)? else {
+                components.push(None);
+                continue;
+            };
+
+            let (version, contents) = decode(&object).map_err(AccessError::implementation)?;
+            if let Some(expected) = descriptor.version.filter(|expected| *expected != version) {
+                return Err(AccessError::VersionMismatch {
+                    component: descriptor.name.clone(),
+                    stored: version,
+                    expected,
+                });
+            }
+
+            components.push(Some(SerializedComponent::<F> {
+                contents: F::Data::from(contents.to_vec()),
+                name: descriptor.name.clone(),
+                version,
+                schema: None,
+                metadata: None,
+            }));
+        }
+
+        Ok(components)
+    }
+
+    /// Lists the entity, component name and size of every object under the
+    /// prefix, ordered by entity.
+    fn objects(&self) -> Result<Vec<(Entity, String, u64)>, AccessError> {
+        let root = self.root();
+        self.store
+            .list(&root)
+            .map_err(failed)?
+            .into_iter()
+            .map(|listed| {
+                let (entity, name) = listed.key[root.len()..]
+                    .split_once('/')
+                    .ok_or(Corrupted("key"))
+                    .map_err(AccessError::implementation)?;
+                let entity = Uuid::parse_str(entity)
+                    .map(Entity)
+                    .map_err(|_| AccessError::implementation(Corrupted("key")))?;
+
+                Ok((entity, name.to_string(), listed.size))
+            })
+            .collect()
+    }
+
+    /// Deletes the objects written by an insert which failed partway.
+    fn undo(&self, written: &[String]) {
+        for key in written {
+            if let Err(err) = self.store.delete(key) {
+                error!("failed to delete {key} after a failed insert: {err}");
+            }
+        }
+    }
+}
+
+/// Rejects names which are not valid component names, so the backend
+/// accepts exactly the components the others do, and names never contain
+/// characters with a meaning in keys.
+fn check_names<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Result<(), AccessError> {
+    match names
+        .into_iter()
+        .find(|name| !is_valid_component_name(name))
+    {
+        Some(name) => Err(AccessError::InvalidComponentName(name.to_string())),
+        None => Ok(()),
+    }
+}
+
+const VERSION_LENGTH: usize = 24;
+
+/// Stores the version of the component's layout ahead of its contents.
+fn encode(version: Version, contents: &[u8]) -> Vec<u8> {
+    [
+        version.major.to_be_bytes().as_slice(),
+        &version.minor.to_be_bytes(),
+        &version.patch.to_be_bytes(),
+        contents,
+    ]
+    .concat()
+}
+
+fn decode(object: &[u8]) -> Result<(Version, &[u8]), Corrupted> {
+    if object.len() < VERSION_LENGTH {
+        return Err(Corrupted("component"));
+    }
+
+    let part = |at: usize| u64::from_be_bytes(object[at..at + 8].try_into().unwrap());
+    Ok((
+        Version::new(part(0), part(8), part(16)),
+        &object[VERSION_LENGTH..],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format,
+            SerializedComponent,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{MemoryStore, ObjectStore, S3Backend};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    fn component(name: &str, content: &str) -> SerializedComponent<Json> {
+        SerializedComponent::<Json> {
+            contents: Json::serialize(DebugComponentA {
+                content: content.to_string(),
+            })
+            .unwrap(),
+            name: name.to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        }
+    }
+
+    fn extract(name: &str) -> ExtractionDescriptor {
+        ExtractionDescriptor {
+            name: name.to_string(),
+            version: None,
+            schema: None,
+        }
+    }
+
+    fn content(component: &Option<SerializedComponent<Json>>) -> String {
+        Json::deserialize::<DebugComponentA>(&component.as_ref().unwrap().contents)
+            .unwrap()
+            .content
+    }
+
+    #[test]
+    fn write_and_read_components() {
+        let store = MemoryStore::new();
+        let conn = S3Backend::new(store.clone());
+        let entity = Entity::new();
+
+        conn.write_components(
+            entity,
+            vec![component("CounterA", "a"), component("CounterB", "b")],
+        )
+        .unwrap();
+
+        let read = AccessBackend::<Json>::read_components(
+            &conn,
+            entity,
+            vec![extract("CounterB"), extract("Missing"), extract("CounterA")],
+        )
+        .unwrap();
+        assert_eq!(content(&read[0]), "b");
+        assert!(read[1].is_none());
+        assert_eq!(content(&read[2]), "a");
+
+        let keys: Vec<String> = store
+            .list("")
+            .unwrap()
+            .into_iter()
+            .map(|listed| listed.key)
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                format!("eci/{entity}/CounterA"),
+                format!("eci/{entity}/CounterB")
+            ]
+        );
+    }
+
+    #[test]
+    fn conflicting_inserts_are_undone() {
+        let store = MemoryStore::new();
+        let conn = S3Backend::new(store.clone());
+        let entity = Entity::new();
+
+        conn.write_components(entity, vec![component("CounterB", "b")])
+            .unwrap();
+
+        assert!(matches!(
+            conn.write_components(
+                entity,
+                vec![component("CounterA", "a"), component("CounterB", "new")],
+            ),
+            Err(AccessError::Conflict(conflicted, name)) if conflicted == entity && name == "CounterB"
+        ));
+        assert_eq!(
+            AccessBackend::<Json>::list_components(&conn, entity).unwrap(),
+            vec!["CounterB".to_string()]
+        );
+
+        assert!(matches!(
+            conn.write_components(
+                entity,
+                vec![component("CounterC", "c"), component("CounterC", "c")],
+            ),
+            Err(AccessError::Conflict(_, name)) if name == "CounterC"
+        ));
+        assert_eq!(store.list("eci/").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn update_and_write_if_absent() {
+        let conn = S3Backend::new(MemoryStore::new());
+        let entity = Entity::new();
+
+        conn.write_components(entity, vec![component("CounterA", "a")])
+            .unwrap();
+        conn.write_components_if_absent(
+            entity,
+            vec![component("CounterA", "ignored"), component("CounterB", "b")],
+        )
+        .unwrap();
+        conn.update_components(entity, vec![component("CounterA", "updated")])
+            .unwrap();
+
+        let read = AccessBackend::<Json>::read_components(
+            &conn,
+            entity,
+            vec![extract("CounterA"), extract("CounterB")],
+        )
+        .unwrap();
+        assert_eq!(content(&read[0]), "updated");
+        assert_eq!(content(&read[1]), "b");
+    }
+
+    #[test]
+    fn remove_checks_versions() {
+        let conn = S3Backend::new(MemoryStore::new());
+        let entity = Entity::new();
+
+        conn.write_components(entity, vec![component("CounterA", "a")])
+            .unwrap();
+
+        assert!(matches!(
+            AccessBackend::<Json>::remove_components(
+                &conn,
+                entity,
+                vec![ExtractionDescriptor {
+                    name: "CounterA".to_string(),
+                    version: Some(Version::new(2, 0, 0)),
+                    schema: None,
+                }],
+            ),
+            Err(AccessError::VersionMismatch { stored, .. }) if stored == Version::default()
+        ));
+
+        let removed = AccessBackend::<Json>::remove_components(
+            &conn,
+            entity,
+            vec![extract("CounterA"), extract("CounterB")],
+        )
+        .unwrap();
+        assert_eq!(content(&removed[0]), "a");
+        assert!(removed[1].is_none());
+        assert!(AccessBackend::<Json>::list_entities(&conn)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn list_entities_and_stats() {
+        let conn = S3Backend::new(MemoryStore::new()).with_prefix("/worlds/test/");
+        let (first, second) = (Entity::new(), Entity::new());
+
+        conn.write_components(
+            first,
+            vec![component("CounterA", "a"), component("CounterB", "b")],
+        )
+        .unwrap();
+        conn.write_components(second, vec![component("CounterA", "aa")])
+            .unwrap();
+
+        let mut entities = AccessBackend::<Json>::list_entities(&conn).unwrap();
+        entities.sort();
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(entities, expected);
+
+        assert_eq!(
+            AccessBackend::<Json>::entities_with(&conn, "CounterB").unwrap(),
+            vec![first]
+        );
+
+        let size = |content: &str| component("CounterA", content).contents.len() as u64;
+        assert_eq!(
+            AccessBackend::<Json>::stats(&conn).unwrap(),
+            vec![
+                ComponentStats {
+                    name: "CounterA".to_string(),
+                    entity_count: 2,
+                    total_bytes: size("a") + size("aa"),
+                },
+                ComponentStats {
+                    name: "CounterB".to_string(),
+                    entity_count: 1,
+                    total_bytes: size("b"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_invalid_names() {
+        let conn = S3Backend::new(MemoryStore::new());
+
+        assert!(matches!(
+            conn.write_components(Entity::new(), vec![component("../CounterA", "a")]),
+            Err(AccessError::InvalidComponentName(_))
+        ));
+        assert!(matches!(
+            AccessBackend::<Json>::read_components(&conn, Entity::new(), vec![]),
+            Err(AccessError::EmptyRequest)
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+//! Stores components as objects in S3 or any other object storage, for
+//! deployments without a database of their own, such as serverless ones.
+//!
+//! Each component is an object keyed `{prefix}/{entity}/{component}`,
+//! holding the version of the component's layout followed by its contents.
+//! Storage is reached through the [`ObjectStore`] trait, implemented by
+//! [`S3Store`] behind the `s3` feature for S3 and compatible services such
+//! as minio, and by [`MemoryStore`] for tests.
+//!
+//! Object storage offers no way of locking, so [`S3Backend`] only
+//! implements [`AccessBackend`], and is paired with a separate locking
+//! backend, such as the sqlite or redis ones:
+//!
+//! ```ignore
+//! let backend: Backend<Json> = Backend::from_disjoint(
+//!     S3Backend::new(S3Store::new(endpoint, region, bucket, credentials)),
+//!     RedisLockingBackend::open("redis://127.0.0.1/")?,
+//! );
+//! ```
+//!
+//! Inserts are conditional puts, which fail if the object already exists,
+//! so inserting a component the entity has fails with
+//! [`AccessError::Conflict`] like it does for other backends. Writes of
+//! several components are not atomic however: an insert which conflicts
+//! deletes the components it already wrote, but others may see them in the
+//! meantime, and a failed update may leave some components updated.
+//! Listing entities and components goes through every object under the
+//! prefix, so it gets slower as the world grows.
+//!
+//! [`AccessBackend`]: eci_core::backend::AccessBackend
+//! [`AccessError::Conflict`]: eci_core::backend::AccessError::Conflict
+//! [`S3Store`]: s3::S3Store
+
+mod access;
+#[cfg(feature = "s3")]
+pub mod s3;
+mod store;
+
+use std::{error::Error, fmt::Display};
+
+use eci_core::Entity;
+pub use store::{Listed, MemoryStore, ObjectStore, StoreError};
+
+pub struct S3Backend<S: ObjectStore> {
+    store: S,
+    prefix: String,
+}
+
+impl<S: ObjectStore> S3Backend<S> {
+    /// Keeps components in the store under the `eci` prefix.
+    pub fn new(store: S) -> Self {
+        S3Backend {
+            store,
+            prefix: "eci".to_string(),
+        }
+    }
+
+    /// Keeps components under another prefix, so several worlds can share
+    /// a bucket. An empty prefix keeps them at the root of the bucket.
+    pub fn with_prefix<P: Into<String>>(mut self, prefix: P) -> Self {
+        self.prefix = prefix.into().trim_matches('/').to_string();
+        self
+    }
+
+    /// Prefix of every key, ending in a slash unless it is empty.
+    fn root(&self) -> String {
+        match self.prefix.as_str() {
+            "" => String::new(),
+            prefix => format!("{prefix}/"),
+        }
+    }
+
+    fn key(&self, entity: Entity, component: &str) -> String {
+        format!("{}{entity}/{component}", self.root())
+    }
+}
+
+/// An object in the store which could not be decoded, most likely because
+/// it was not written by this backend.
+#[derive(Debug)]
+pub(crate) struct Corrupted(&'static str);
+
+impl Display for Corrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "corrupted {} in object store", self.0)
+    }
+}
+
+impl Error for Corrupted {}
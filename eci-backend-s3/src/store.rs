@@ -0,0 +1,95 @@
+use eci_core::backend::AccessError;
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+/// Errors of an object store, which must be sendable so objects can be
+/// fetched from several threads at once.
+pub type StoreError = Box<dyn Error + Send + Sync>;
+
+pub(crate) fn failed(err: StoreError) -> AccessError {
+    AccessError::Implementation(err)
+}
+
+/// An object found by [`ObjectStore::list`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listed {
+    pub key: String,
+    pub size: u64,
+}
+
+/// The few operations the backend needs from object storage, so it can be
+/// used with any service which offers them.
+pub trait ObjectStore: Sync {
+    /// Fetches the object, or `None` if there is no object with the key.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError>;
+
+    /// Stores the object, replacing any existing object with the key.
+    fn put(&self, key: &str, body: Vec<u8>) -> Result<(), StoreError>;
+
+    /// Stores the object only if there is no object with the key yet,
+    /// returning whether it was stored. This must be a single conditional
+    /// request, so concurrent puts of the same key cannot both succeed.
+    fn put_if_absent(&self, key: &str, body: Vec<u8>) -> Result<bool, StoreError>;
+
+    /// Deletes the object, if there is one.
+    fn delete(&self, key: &str) -> Result<(), StoreError>;
+
+    /// Lists every object whose key starts with the prefix, ordered by key.
+    fn list(&self, prefix: &str) -> Result<Vec<Listed>, StoreError>;
+}
+
+/// Keeps objects in memory, standing in for object storage in tests.
+/// Clones share the same objects.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    objects: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ObjectStore for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        Ok(self.objects.lock().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &str, body: Vec<u8>) -> Result<(), StoreError> {
+        self.objects.lock().unwrap().insert(key.to_string(), body);
+        Ok(())
+    }
+
+    fn put_if_absent(&self, key: &str, body: Vec<u8>) -> Result<bool, StoreError> {
+        match self.objects.lock().unwrap().entry(key.to_string()) {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(entry) => {
+                entry.insert(body);
+                Ok(true)
+            }
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StoreError> {
+        self.objects.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<Listed>, StoreError> {
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, body)| Listed {
+                key: key.clone(),
+                size: body.len() as u64,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,113 @@
+//! Keeps components in the bucket named by `ECI_S3_BUCKET` of the
+//! S3-compatible service at `ECI_S3_ENDPOINT`, or a local minio, with
+//! `cargo test -p eci-backend-s3 -- --ignored`. Locks are kept by an
+//! in-memory sqlite backend, and each test keeps to its own prefix.
+
+use eci_backend_s3::{
+    s3::{Credentials, S3Store},
+    S3Backend,
+};
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{AccessBackend, AccessError, Backend, Format, SerializedComponent},
+    Component, Entity, Version,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterA(pub usize);
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterB(pub usize);
+
+fn backend() -> Backend<Json> {
+    let var =
+        |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+
+    let store = S3Store::new(
+        &var("ECI_S3_ENDPOINT", "http://127.0.0.1:9000"),
+        &var("ECI_S3_REGION", "us-east-1"),
+        &var("ECI_S3_BUCKET", "eci-test"),
+        Credentials::from_env().unwrap_or_else(|| Credentials::new("minioadmin", "minioadmin")),
+    );
+
+    Backend::from_disjoint(
+        S3Backend::new(store).with_prefix(format!("eci-test-{}", Entity::new())),
+        SqliteBackend::memory().unwrap(),
+    )
+}
+
+#[test]
+#[ignore = "needs an S3-compatible service"]
+fn put_get_and_remove() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(1), CounterB(2)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend.get::<&mut CounterA>(entity).unwrap().unwrap();
+    locked.deref().0 += 1;
+    locked.commit().unwrap();
+
+    assert_eq!(
+        backend.peek::<&CounterA>(entity).unwrap(),
+        Some(CounterA(2))
+    );
+    assert_eq!(backend.list_entities().unwrap(), vec![entity]);
+    assert_eq!(
+        backend.list_components(entity).unwrap(),
+        vec!["CounterA", "CounterB"]
+    );
+
+    backend.remove::<(CounterA, CounterB)>(entity).unwrap();
+    assert_eq!(backend.peek::<&CounterA>(entity).unwrap(), None);
+    assert!(backend.list_entities().unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "needs an S3-compatible service"]
+fn conditional_puts_conflict() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(0),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let again = AccessBackend::<Json>::write_components(
+        &backend,
+        entity,
+        vec![
+            SerializedComponent {
+                contents: Json::serialize(CounterB(0)).unwrap(),
+                name: "CounterB".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            },
+            SerializedComponent {
+                contents: Json::serialize(CounterA(1)).unwrap(),
+                name: "CounterA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            },
+        ],
+    );
+    assert!(matches!(
+        again,
+        Err(AccessError::Conflict(conflicted, component))
+            if conflicted == entity && component == "CounterA"
+    ));
+
+    assert_eq!(
+        backend.peek::<&CounterA>(entity).unwrap(),
+        Some(CounterA(0))
+    );
+    assert_eq!(backend.peek::<&CounterB>(entity).unwrap(), None);
+}
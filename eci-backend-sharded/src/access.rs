@@ -0,0 +1,314 @@
+use std::{collections::BTreeMap, time::SystemTime};
+
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format, Lock, Predicate,
+        SerializedComponent,
+    },
+    Entity,
+};
+
+use crate::ShardedBackend;
+
+impl<F: Format> AccessBackend<F> for ShardedBackend<F> {
+    /// Each write only touches a single entity, and so a single shard, so
+    /// writes are atomic as long as every shard's are.
+    fn supports_atomic_writes(&self) -> bool {
+        self.shards
+            .iter()
+            .all(|shard| shard.supports_atomic_writes())
+    }
+
+    fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.route(entity).write_components(entity, components)
+    }
+
+    fn write_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        let shard = self.shard_of(entity);
+        self.shards[shard].write_components_locked(entity, components, &self.part(lock, shard))
+    }
+
+    /// Writes each shard's part of the batch in turn, so the batch is only
+    /// atomic if it falls on a single shard which writes batches atomically.
+    fn write_components_batch(
+        &self,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        for (shard, batch) in self.split(batch) {
+            self.shards[shard].write_components_batch(batch)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.route(entity)
+            .write_components_if_absent(entity, components)
+    }
+
+    fn write_components_if_absent_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        let shard = self.shard_of(entity);
+        self.shards[shard].write_components_if_absent_locked(
+            entity,
+            components,
+            &self.part(lock, shard),
+        )
+    }
+
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.route(entity).update_components(entity, components)
+    }
+
+    fn update_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        let shard = self.shard_of(entity);
+        self.shards[shard].update_components_locked(entity, components, &self.part(lock, shard))
+    }
+
+    fn update_component_if_unchanged(
+        &self,
+        entity: Entity,
+        component: SerializedComponent<F>,
+        revision: u64,
+    ) -> Result<(), AccessError> {
+        self.route(entity)
+            .update_component_if_unchanged(entity, component, revision)
+    }
+
+    fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.route(entity).read_components(entity, descriptors)
+    }
+
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.route(entity).remove_components(entity, descriptors)
+    }
+
+    fn remove_components_locked(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        let shard = self.shard_of(entity);
+        self.shards[shard].remove_components_locked(entity, descriptors, &self.part(lock, shard))
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        let mut entities = Vec::new();
+        for shard in &self.shards {
+            entities.extend(shard.entities_with(component)?);
+        }
+
+        Ok(entities)
+    }
+
+    fn modified_since(
+        &self,
+        component: &str,
+        since: SystemTime,
+    ) -> Result<Vec<Entity>, AccessError> {
+        let mut entities = Vec::new();
+        for shard in &self.shards {
+            entities.extend(shard.modified_since(component, since)?);
+        }
+
+        Ok(entities)
+    }
+
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        let mut entities = Vec::new();
+        for shard in &self.shards {
+            entities.extend(shard.list_entities()?);
+        }
+
+        Ok(entities)
+    }
+
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        self.route(entity).list_components(entity)
+    }
+
+    fn contains(&self, entity: Entity, names: &[String]) -> Result<Vec<bool>, AccessError> {
+        self.route(entity).contains(entity, names)
+    }
+
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        let mut merged = BTreeMap::<String, ComponentStats>::new();
+        for shard in &self.shards {
+            for stats in shard.stats()? {
+                let entry = merged
+                    .entry(stats.name.clone())
+                    .or_insert_with(|| ComponentStats {
+                        name: stats.name,
+                        entity_count: 0,
+                        total_bytes: 0,
+                    });
+                entry.entity_count += stats.entity_count;
+                entry.total_bytes += stats.total_bytes;
+            }
+        }
+
+        Ok(merged.into_values().collect())
+    }
+
+    fn find_entities_where(&self, predicates: Vec<Predicate>) -> Result<Vec<Entity>, AccessError> {
+        let mut entities = Vec::new();
+        for shard in &self.shards {
+            entities.extend(shard.find_entities_where(predicates.clone())?);
+        }
+
+        Ok(entities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_core::{
+        backend::{
+            AccessBackend, ComponentStats, ExtractionDescriptor, Format, SerializedComponent,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::tests::sharded;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    fn component(name: &str) -> SerializedComponent<Json> {
+        SerializedComponent::<Json> {
+            contents: Json::serialize(DebugComponentA {
+                content: "Hello world".to_string(),
+            })
+            .unwrap(),
+            name: name.to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        }
+    }
+
+    fn extract(name: &str) -> Vec<ExtractionDescriptor> {
+        vec![ExtractionDescriptor {
+            name: name.to_string(),
+            version: None,
+            schema: None,
+        }]
+    }
+
+    #[test]
+    fn entities_land_on_their_shard() {
+        let (conn, shards) = sharded();
+        let entities: Vec<_> = (0..32).map(|_| Entity::new()).collect();
+
+        for entity in &entities {
+            conn.write_components(*entity, vec![component("DebugComponentA")])
+                .unwrap();
+        }
+        conn.write_components_batch(
+            entities
+                .iter()
+                .map(|entity| (*entity, vec![component("DebugComponentB")]))
+                .collect(),
+        )
+        .unwrap();
+
+        for entity in &entities {
+            let home = conn.shard_of(*entity);
+            for (index, shard) in shards.iter().enumerate() {
+                let components = AccessBackend::<Json>::list_components(shard, *entity).unwrap();
+                if index == home {
+                    assert_eq!(components, ["DebugComponentA", "DebugComponentB"]);
+                } else {
+                    assert!(components.is_empty());
+                }
+            }
+
+            assert!(conn
+                .read_components(*entity, extract("DebugComponentA"))
+                .unwrap()[0]
+                .is_some());
+        }
+
+        // With 32 entities, every shard should have gotten some.
+        for shard in &shards {
+            assert!(!AccessBackend::<Json>::list_entities(shard)
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn merge_listings() {
+        let (conn, _) = sharded();
+        let mut entities: Vec<_> = (0..16).map(|_| Entity::new()).collect();
+        for entity in &entities {
+            conn.write_components(*entity, vec![component("DebugComponentA")])
+                .unwrap();
+        }
+        conn.write_components(entities[0], vec![component("DebugComponentB")])
+            .unwrap();
+
+        let mut listed = conn.list_entities().unwrap();
+        listed.sort();
+        entities.sort();
+        assert_eq!(listed, entities);
+        assert_eq!(conn.entities_with("DebugComponentA").unwrap().len(), 16);
+        assert_eq!(conn.entities_with("DebugComponentB").unwrap().len(), 1);
+
+        let size = component("DebugComponentA").contents.len() as u64;
+        assert_eq!(
+            conn.stats().unwrap(),
+            [
+                ComponentStats {
+                    name: "DebugComponentA".to_string(),
+                    entity_count: 16,
+                    total_bytes: 16 * size,
+                },
+                ComponentStats {
+                    name: "DebugComponentB".to_string(),
+                    entity_count: 1,
+                    total_bytes: size,
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Spreads entities across several backends, for worlds with more entities
+//! than a single database holds comfortably.
+//!
+//! [`ShardedBackend`] sends everything concerning an entity, its components
+//! as well as its locks, to the one shard picked for it by [`shard_index`].
+//! Operations spanning entities, like listing them or locking several at
+//! once, are split up between the shards and their results merged.
+//!
+//! Shards are picked by hashing the entity, so the same entities only end up
+//! on the same shards again if the shards are given in the same order.
+//! Moving entities between shards when adding or removing some is left to
+//! the caller.
+//!
+//! ```ignore
+//! let backend: Backend<Json> = Backend::from_joint(ShardedBackend::new(vec![
+//!     Arc::new(SqliteBackend::open("world-0.db")?),
+//!     Arc::new(SqliteBackend::open("world-1.db")?),
+//!     Arc::new(SqliteBackend::open("world-2.db")?),
+//! ])?);
+//! ```
+
+mod access;
+mod lock;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use eci_core::{
+    backend::{Format, JointBackend},
+    Entity,
+};
+use uuid::Uuid;
+
+/// Offset basis and prime of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Picks which of `shards` shards the entity belongs on, by hashing the 16
+/// bytes of its UUID with 64-bit FNV-1a and taking the remainder of dividing
+/// the hash by the number of shards.
+///
+/// The result only depends on the entity and the number of shards, and will
+/// stay the same across versions, so shards can be recreated from it.
+///
+/// # Panics
+///
+/// If `shards` is zero.
+pub fn shard_index(entity: Entity, shards: usize) -> usize {
+    let hash = entity
+        .0
+        .as_bytes()
+        .iter()
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        });
+
+    (hash % shards as u64) as usize
+}
+
+/// Returned when creating a [`ShardedBackend`] without any shards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoShards;
+
+impl Display for NoShards {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "a sharded backend needs at least one shard")
+    }
+}
+
+impl Error for NoShards {}
+
+/// Routes each entity to one of several backends.
+pub struct ShardedBackend<F: Format> {
+    shards: Vec<Arc<dyn JointBackend<F>>>,
+    /// Locks handed out by this backend, by the id they were handed out
+    /// with.
+    locks: Mutex<HashMap<Uuid, Held>>,
+}
+
+/// Where a lock handed out by the sharded backend is actually held.
+#[derive(Clone)]
+struct Held {
+    /// The shards holding part of the lock, along with the id of the lock
+    /// on each of them.
+    parts: Vec<(usize, Uuid)>,
+    expires: Option<SystemTime>,
+}
+
+impl<F: Format> ShardedBackend<F> {
+    /// Spreads entities across the shards, failing if there are none.
+    pub fn new(shards: Vec<Arc<dyn JointBackend<F>>>) -> Result<Self, NoShards> {
+        if shards.is_empty() {
+            return Err(NoShards);
+        }
+
+        Ok(ShardedBackend {
+            shards,
+            locks: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn shards(&self) -> &[Arc<dyn JointBackend<F>>] {
+        &self.shards
+    }
+
+    /// Index of the shard the entity belongs on, see [`shard_index`].
+    pub fn shard_of(&self, entity: Entity) -> usize {
+        shard_index(entity, self.shards.len())
+    }
+
+    fn route(&self, entity: Entity) -> &dyn JointBackend<F> {
+        self.shards[self.shard_of(entity)].as_ref()
+    }
+
+    /// Splits requests concerning several entities up by shard, keeping
+    /// the order of each shard's requests.
+    fn split<T>(&self, requests: Vec<(Entity, T)>) -> BTreeMap<usize, Vec<(Entity, T)>> {
+        let mut split = BTreeMap::<usize, Vec<_>>::new();
+        for (entity, request) in requests {
+            split
+                .entry(self.shard_of(entity))
+                .or_default()
+                .push((entity, request));
+        }
+
+        split
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use eci_backend_memory::MemoryBackend;
+    use eci_core::Entity;
+    use eci_format_json::Json;
+    use uuid::Uuid;
+
+    use crate::{shard_index, NoShards, ShardedBackend};
+
+    /// A backend sharded across three in-memory backends, along with clones
+    /// of them to check which one things ended up on.
+    pub(crate) fn sharded() -> (ShardedBackend<Json>, Vec<MemoryBackend>) {
+        let shards: Vec<_> = (0..3).map(|_| MemoryBackend::new()).collect();
+        let backend = ShardedBackend::new(
+            shards
+                .iter()
+                .map(|shard| Arc::new(shard.clone()) as _)
+                .collect(),
+        )
+        .unwrap();
+
+        (backend, shards)
+    }
+
+    #[test]
+    fn stable_shard_index() {
+        let nil = Entity(Uuid::nil());
+        assert_eq!(shard_index(nil, 3), 0);
+        assert_eq!(shard_index(nil, 16), 5);
+
+        let entity = Entity(Uuid::from_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap());
+        assert_eq!(shard_index(entity, 1), 0);
+        assert_eq!(shard_index(entity, 3), 1);
+        assert_eq!(shard_index(entity, 16), 2);
+    }
+
+    #[test]
+    fn reject_no_shards() {
+        assert_eq!(
+            ShardedBackend::<Json>::new(Vec::new()).err(),
+            Some(NoShards)
+        );
+    }
+}
@@ -0,0 +1,513 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use eci_core::{
+    backend::{
+        ActiveLock, Administrative, BackendError, BulkLockResult, Expiry, ExtractionDescriptor,
+        Format, JointBackend, Lock, LockDescriptor, LockStats, LockedRead, LockingBackend,
+        LockingError,
+    },
+    Entity,
+};
+use log::debug;
+use uuid::Uuid;
+
+use crate::{Held, ShardedBackend};
+
+/// Lock ids are always UUIDs, even though they are handed around as strings.
+fn id_of(lock: &Lock) -> Uuid {
+    Uuid::parse_str(&lock.id()).expect("lock ids are uuids")
+}
+
+impl<F: Format> ShardedBackend<F> {
+    /// Hands out a lock acquired from one or more shards. A lock held by a
+    /// single shard is handed out as is, while locks spanning several get an
+    /// id of their own, expiring when the first of their parts does.
+    fn combine(&self, mut parts: Vec<(usize, Lock)>) -> Lock {
+        let expires = parts.iter().filter_map(|(_, lock)| lock.expires_at()).min();
+        let ids = parts
+            .iter()
+            .map(|(shard, lock)| (*shard, id_of(lock)))
+            .collect();
+
+        let lock = if parts.len() == 1 {
+            parts.remove(0).1
+        } else {
+            match expires {
+                Some(expires) => Lock::new().with_expiry(expires),
+                None => Lock::new(),
+            }
+        };
+
+        let now = SystemTime::now();
+        let mut locks = self.locks.lock().unwrap();
+        locks.retain(|_, held| !matches!(held.expires, Some(expires) if expires <= now));
+        locks.insert(
+            id_of(&lock),
+            Held {
+                parts: ids,
+                expires,
+            },
+        );
+
+        lock
+    }
+
+    /// Releases the parts of a lock which could not be acquired in full.
+    fn abandon(&self, parts: Vec<(usize, Lock)>) {
+        for (shard, lock) in parts {
+            // Failing to release a part only leaves it to expire, which
+            // matters less than the reason the lock could not be acquired.
+            let _ = self.shards[shard].release_lock(lock);
+        }
+    }
+
+    /// The shards holding the lock, along with the id of the lock on each.
+    /// Locks this backend did not hand out, or has since forgotten, are
+    /// looked for on every shard under their own id.
+    fn parts(&self, id: Uuid, forget: bool) -> Vec<(usize, Uuid)> {
+        let mut locks = self.locks.lock().unwrap();
+        let held = if forget {
+            locks.remove(&id)
+        } else {
+            locks.get(&id).cloned()
+        };
+
+        match held {
+            Some(held) => held.parts,
+            None => (0..self.shards.len()).map(|shard| (shard, id)).collect(),
+        }
+    }
+
+    /// The part of the lock held by the shard, to pass on to it in place of
+    /// the lock itself.
+    pub(crate) fn part(&self, lock: &Lock, shard: usize) -> Lock {
+        let id = id_of(lock);
+        let part = self
+            .locks
+            .lock()
+            .unwrap()
+            .get(&id)
+            .and_then(|held| held.parts.iter().find(|(held, _)| *held == shard))
+            .map_or(id, |(_, part)| *part);
+
+        Lock::from_id(part)
+    }
+
+    /// Acquires locks on entities spread across shards, one lock per shard,
+    /// releasing those already acquired if any of them fails.
+    fn acquire_split<T>(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        mut acquire: impl FnMut(
+            &dyn JointBackend<F>,
+            Vec<(Entity, Vec<LockDescriptor>)>,
+        ) -> Result<(Lock, T), LockingError>,
+    ) -> Result<(Lock, Vec<T>), LockingError> {
+        let mut split = self.split(requests);
+        if split.is_empty() {
+            // Leave rejecting the empty request to the shard.
+            split.insert(0, Vec::new());
+        }
+
+        let mut parts = Vec::new();
+        let mut results = Vec::new();
+        for (shard, requests) in split {
+            match acquire(self.shards[shard].as_ref(), requests) {
+                Ok((lock, result)) => {
+                    parts.push((shard, lock));
+                    results.push(result);
+                }
+                Err(err) => {
+                    self.abandon(parts);
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok((self.combine(parts), results))
+    }
+}
+
+impl<F: Format> LockingBackend for ShardedBackend<F> {
+    fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        let shard = self.shard_of(entity);
+        let lock = self.shards[shard].acquire_lock(entity, descriptors, expires)?;
+        Ok(self.combine(vec![(shard, lock)]))
+    }
+
+    fn acquire_lock_blocking(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+        wait_up_to: Duration,
+    ) -> Result<Lock, LockingError> {
+        let shard = self.shard_of(entity);
+        let lock =
+            self.shards[shard].acquire_lock_blocking(entity, descriptors, expires, wait_up_to)?;
+        Ok(self.combine(vec![(shard, lock)]))
+    }
+
+    fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        let mut result = Ok(());
+        for (shard, part) in self.parts(id_of(&lock), true) {
+            let released = self.shards[shard].release_lock(Lock::from_id(part));
+            if result.is_ok() {
+                result = released;
+            }
+        }
+
+        result
+    }
+
+    fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        let id = id_of(lock);
+        let known = self.locks.lock().unwrap().contains_key(&id);
+
+        // Locks this backend does not know of are only held by some of the
+        // shards, which the others will claim has expired.
+        let mut renewed = false;
+        for (shard, part) in self.parts(id, false) {
+            match self.shards[shard].renew_lock(&Lock::from_id(part), extend_by) {
+                Ok(()) => renewed = true,
+                Err(LockingError::Expired(_)) if !known => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        if !renewed {
+            return Err(LockingError::Expired(lock.id()));
+        }
+
+        if let Some(held) = self.locks.lock().unwrap().get_mut(&id) {
+            if held.expires.is_some() {
+                held.expires = SystemTime::now().checked_add(extend_by);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn upgrade_lock(
+        &self,
+        lock: &Lock,
+        entity: Entity,
+        component: String,
+    ) -> Result<(), LockingError> {
+        let shard = self.shard_of(entity);
+        self.shards[shard].upgrade_lock(&self.part(lock, shard), entity, component)
+    }
+
+    /// Locks spanning several shards are listed under the id they were
+    /// handed out with, rather than that of their part on each shard.
+    fn list_locks(&self, filter: Option<Entity>) -> Result<Vec<ActiveLock>, LockingError> {
+        let mut active = match filter {
+            Some(entity) => self.route(entity).list_locks(filter)?,
+            None => {
+                let mut active = Vec::new();
+                for shard in &self.shards {
+                    active.extend(shard.list_locks(None)?);
+                }
+                active
+            }
+        };
+
+        let handed_out: HashMap<String, String> = self
+            .locks
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|(id, held)| {
+                held.parts
+                    .iter()
+                    .map(move |(_, part)| (part.to_string(), id.to_string()))
+            })
+            .collect();
+
+        for lock in &mut active {
+            if let Some(id) = handed_out.get(&lock.id) {
+                lock.id = id.clone();
+            }
+        }
+
+        Ok(active)
+    }
+
+    fn force_release(&self, lock_id: Uuid, admin: Administrative) -> Result<usize, LockingError> {
+        let mut released = 0;
+        for (shard, part) in self.parts(lock_id, true) {
+            released += self.shards[shard].force_release(part, admin.clone())?;
+        }
+
+        Ok(released)
+    }
+
+    fn force_release_entity(
+        &self,
+        entity: Entity,
+        admin: Administrative,
+    ) -> Result<usize, LockingError> {
+        self.route(entity).force_release_entity(entity, admin)
+    }
+
+    /// Entities on different shards are locked one shard at a time, so
+    /// conflicts on a later shard release the locks already acquired on
+    /// earlier ones.
+    fn acquire_locks(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<Lock, LockingError> {
+        let (lock, _) = self.acquire_split(requests, |shard, requests| {
+            Ok((shard.acquire_locks(requests, expires_in)?, ()))
+        })?;
+
+        debug!("acquired lock {lock} across shards");
+        Ok(lock)
+    }
+
+    fn acquire_locks_bulk(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        let (lock, results) = self.acquire_split(requests, |shard, requests| {
+            let result = shard.acquire_locks_bulk(requests, expires_in)?;
+            Ok((result.lock, (result.granted, result.skipped)))
+        })?;
+
+        let (granted, skipped) = results.into_iter().fold(
+            (Vec::new(), Vec::new()),
+            |(mut granted, mut skipped), result| {
+                granted.extend(result.0);
+                skipped.extend(result.1);
+                (granted, skipped)
+            },
+        );
+
+        Ok(BulkLockResult {
+            lock,
+            granted,
+            skipped,
+        })
+    }
+
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        let mut stats = LockStats::default();
+        for shard in &self.shards {
+            let shard = shard.lock_stats()?;
+            stats.active += shard.active;
+            stats.expired += shard.expired;
+        }
+
+        Ok(stats)
+    }
+}
+
+impl<F: Format> JointBackend<F> for ShardedBackend<F> {
+    fn read_and_lock(
+        &self,
+        entity: Entity,
+        locks: Vec<LockDescriptor>,
+        components: Vec<ExtractionDescriptor>,
+        expires: Expiry,
+    ) -> Result<LockedRead<F>, BackendError> {
+        let shard = self.shard_of(entity);
+        let (lock, components) =
+            self.shards[shard].read_and_lock(entity, locks, components, expires)?;
+        Ok((self.combine(vec![(shard, lock)]), components))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, Expiry, Format, LockDescriptor, LockingBackend,
+            LockingError, LockingMode, SerializedComponent,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+
+    use crate::{shard_index, tests::sharded};
+
+    const LOCK_TIME: Duration = Duration::from_secs(10);
+
+    fn write_lock() -> Vec<LockDescriptor> {
+        vec![LockDescriptor {
+            mode: LockingMode::Write,
+            name: "DebugComponentA".to_string(),
+        }]
+    }
+
+    /// Entities which land on different shards.
+    fn spread(count: usize) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = Vec::new();
+        while entities.len() < count {
+            let entity = Entity::new();
+            if entities
+                .iter()
+                .all(|other| shard_index(*other, 3) != shard_index(entity, 3))
+            {
+                entities.push(entity);
+            }
+        }
+
+        entities
+    }
+
+    #[test]
+    fn locks_follow_their_entity() {
+        let (conn, shards) = sharded();
+        let entity = Entity::new();
+        let home = conn.shard_of(entity);
+
+        let lock = conn
+            .acquire_lock(entity, write_lock(), LOCK_TIME.into())
+            .unwrap();
+
+        for (index, shard) in shards.iter().enumerate() {
+            let held = shard.list_locks(None).unwrap();
+            if index == home {
+                assert_eq!(held.len(), 1);
+                assert_eq!(held[0].id, lock.id());
+            } else {
+                assert!(held.is_empty());
+            }
+        }
+
+        assert!(matches!(
+            conn.acquire_lock(entity, write_lock(), LOCK_TIME.into()),
+            Err(LockingError::Conflict(..))
+        ));
+
+        conn.renew_lock(&lock, LOCK_TIME).unwrap();
+        conn.release_lock(lock).unwrap();
+        assert!(shards[home].list_locks(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn lock_entities_across_shards() {
+        let (conn, shards) = sharded();
+        let entities = spread(3);
+
+        let lock = conn
+            .acquire_locks(
+                entities
+                    .iter()
+                    .map(|entity| (*entity, write_lock()))
+                    .collect(),
+                LOCK_TIME,
+            )
+            .unwrap();
+
+        // Each shard holds its own part of the lock, but it is listed under
+        // the id it was handed out with.
+        for entity in &entities {
+            let shard = &shards[conn.shard_of(*entity)];
+            let held = conn.list_locks(Some(*entity)).unwrap();
+            assert_eq!(held.len(), 1);
+            assert_eq!(held[0].id, lock.id());
+            assert_eq!(shard.list_locks(None).unwrap().len(), 1);
+
+            let contents = Json::serialize("Hello world").unwrap();
+            conn.write_components_locked(
+                *entity,
+                vec![SerializedComponent::<Json> {
+                    contents,
+                    name: "DebugComponentA".to_string(),
+                    version: Version::default(),
+                    schema: None,
+                    metadata: None,
+                }],
+                &lock,
+            )
+            .unwrap();
+        }
+        assert_eq!(conn.lock_stats().unwrap().active, 3);
+
+        conn.release_lock(lock).unwrap();
+        for shard in &shards {
+            assert!(shard.list_locks(None).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn conflicts_release_other_shards() {
+        let (conn, shards) = sharded();
+        let entities = spread(3);
+        let last = *entities
+            .iter()
+            .max_by_key(|entity| conn.shard_of(**entity))
+            .unwrap();
+
+        conn.acquire_lock(last, write_lock(), Expiry::Never)
+            .unwrap();
+
+        assert!(matches!(
+            conn.acquire_locks(
+                entities
+                    .iter()
+                    .map(|entity| (*entity, write_lock()))
+                    .collect(),
+                LOCK_TIME,
+            ),
+            Err(LockingError::Conflict(entity, ..)) if entity == last
+        ));
+
+        // Only the lock which was already held is left.
+        let held: usize = shards
+            .iter()
+            .map(|shard| shard.list_locks(None).unwrap().len())
+            .sum();
+        assert_eq!(held, 1);
+
+        let result = conn
+            .acquire_locks_bulk(
+                entities
+                    .iter()
+                    .map(|entity| (*entity, write_lock()))
+                    .collect(),
+                LOCK_TIME,
+            )
+            .unwrap();
+        assert_eq!(result.granted.len(), 2);
+        assert_eq!(result.skipped, [last]);
+    }
+
+    #[test]
+    fn locked_writes_need_the_right_lock() {
+        let (conn, _) = sharded();
+        let entities = spread(2);
+
+        let lock = conn
+            .acquire_lock(entities[0], write_lock(), LOCK_TIME.into())
+            .unwrap();
+
+        let component = || SerializedComponent::<Json> {
+            contents: Json::serialize("Hello world").unwrap(),
+            name: "DebugComponentA".to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        };
+
+        conn.write_components_locked(entities[0], vec![component()], &lock)
+            .unwrap();
+        assert!(matches!(
+            conn.write_components_locked(entities[1], vec![component()], &lock),
+            Err(AccessError::LockRequired(..))
+        ));
+    }
+}
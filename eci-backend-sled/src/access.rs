@@ -0,0 +1,518 @@
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format, Lock,
+        SerializedComponent,
+    },
+    is_valid_component_name, Entity, Version,
+};
+use sled::{
+    transaction::{ConflictableTransactionResult, TransactionalTree},
+    Transactional,
+};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{abort, finish, lock, parse_entity, Corrupted, SledBackend};
+
+impl<F: Format> AccessBackend<F> for SledBackend {
+    fn supports_atomic_writes(&self) -> bool {
+        true
+    }
+
+    fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        self.insert(None, vec![(entity, components)])
+    }
+
+    fn write_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        self.insert(Some(lock), vec![(entity, components)])
+    }
+
+    fn write_components_batch(
+        &self,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        self.insert(None, batch)
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        check_names(components.iter().map(|component| component.name.as_str()))?;
+        let entries = entries(entity, &components);
+
+        finish(
+            self.components.transaction(|stored| {
+                for (key, value) in &entries {
+                    if stored.get(key)?.is_none() {
+                        stored.insert(key.as_slice(), value.as_slice())?;
+                    }
+                }
+                Ok(())
+            }),
+            AccessError::implementation,
+        )
+    }
+
+    fn write_components_if_absent_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        check_names(components.iter().map(|component| component.name.as_str()))?;
+        let entries = entries(entity, &components);
+
+        finish(
+            (&self.components, &self.locks).transaction(|(stored, locks)| {
+                check_lock(
+                    locks,
+                    lock,
+                    entity,
+                    components.iter().map(|c| c.name.as_str()),
+                )?;
+                for (key, value) in &entries {
+                    if stored.get(key)?.is_none() {
+                        stored.insert(key.as_slice(), value.as_slice())?;
+                    }
+                }
+                Ok(())
+            }),
+            AccessError::implementation,
+        )
+    }
+
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        check_names(components.iter().map(|component| component.name.as_str()))?;
+
+        let mut batch = sled::Batch::default();
+        for (key, value) in entries(entity, &components) {
+            batch.insert(key, value);
+        }
+
+        self.components
+            .apply_batch(batch)
+            .map_err(AccessError::implementation)
+    }
+
+    fn update_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        check_names(components.iter().map(|component| component.name.as_str()))?;
+        let entries = entries(entity, &components);
+
+        finish(
+            (&self.components, &self.locks).transaction(|(stored, locks)| {
+                check_lock(
+                    locks,
+                    lock,
+                    entity,
+                    components.iter().map(|c| c.name.as_str()),
+                )?;
+                for (key, value) in &entries {
+                    stored.insert(key.as_slice(), value.as_slice())?;
+                }
+                Ok(())
+            }),
+            AccessError::implementation,
+        )
+    }
+
+    fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        check_names(
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str()),
+        )?;
+        finish(
+            self.components
+                .transaction(|stored| read(stored, entity, &descriptors)),
+            AccessError::implementation,
+        )
+    }
+
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        check_names(
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str()),
+        )?;
+
+        // Reading everything first leaves the components in place if any of
+        // them was stored with the wrong version.
+        finish(
+            self.components.transaction(|stored| {
+                let removed = read(stored, entity, &descriptors)?;
+                for descriptor in &descriptors {
+                    stored.remove(key(entity, &descriptor.name))?;
+                }
+                Ok(removed)
+            }),
+            AccessError::implementation,
+        )
+    }
+
+    fn remove_components_locked(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        let names = || {
+            descriptors
+                .iter()
+                .map(|descriptor| descriptor.name.as_str())
+        };
+        check_names(names())?;
+
+        finish(
+            (&self.components, &self.locks).transaction(|(stored, locks)| {
+                check_lock(locks, lock, entity, names())?;
+                let removed = read(stored, entity, &descriptors)?;
+                for descriptor in &descriptors {
+                    stored.remove(key(entity, &descriptor.name))?;
+                }
+                Ok(removed)
+            }),
+            AccessError::implementation,
+        )
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        check_names([component])?;
+
+        let mut entities = Vec::new();
+        for key in self.components.iter().keys() {
+            let key = key.map_err(AccessError::implementation)?;
+            if &key[16..] == component.as_bytes() {
+                entities
+                    .push(parse_entity(&key, "component").map_err(AccessError::implementation)?);
+            }
+        }
+
+        Ok(entities)
+    }
+
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        let mut entities = Vec::new();
+        for key in self.components.iter().keys() {
+            let key = key.map_err(AccessError::implementation)?;
+            entities.push(parse_entity(&key, "component").map_err(AccessError::implementation)?);
+        }
+
+        // Keys are ordered by entity first, so each entity's are adjacent.
+        entities.dedup();
+        Ok(entities)
+    }
+
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        self.components
+            .scan_prefix(entity.0.as_bytes())
+            .keys()
+            .map(|key| {
+                let key = key.map_err(AccessError::implementation)?;
+                String::from_utf8(key[16..].to_vec()).map_err(AccessError::implementation)
+            })
+            .collect()
+    }
+
+    /// Only reports components which at least one entity has, since unlike
+    /// tables, nothing is left behind once the last of them is removed.
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        let mut stats = BTreeMap::<String, (u64, u64)>::new();
+
+        for entry in self.components.iter() {
+            let (key, value) = entry.map_err(AccessError::implementation)?;
+            let name =
+                String::from_utf8(key[16..].to_vec()).map_err(AccessError::implementation)?;
+            let (_, contents) = decode(&value).map_err(AccessError::implementation)?;
+
+            let (entity_count, total_bytes) = stats.entry(name).or_default();
+            *entity_count += 1;
+            *total_bytes += contents.len() as u64;
+        }
+
+        Ok(stats
+            .into_iter()
+            .map(|(name, (entity_count, total_bytes))| ComponentStats {
+                name,
+                entity_count,
+                total_bytes,
+            })
+            .collect())
+    }
+}
+
+impl SledBackend {
+    /// Inserts the components of every entity in one transaction, failing
+    /// with [`AccessError::Conflict`] without inserting any of them if one
+    /// of the entities already has, or is given twice, one of its
+    /// components. Given a lock, it must hold write locks on all of them.
+    fn insert<F: Format>(
+        &self,
+        lock: Option<&Lock>,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        let mut seen = HashSet::new();
+        for (entity, components) in &batch {
+            check_names(components.iter().map(|component| component.name.as_str()))?;
+
+            for component in components {
+                if !seen.insert((*entity, component.name.as_str())) {
+                    return Err(AccessError::Conflict(*entity, component.name.clone()));
+                }
+            }
+        }
+
+        finish(
+            (&self.components, &self.locks).transaction(|(stored, locks)| {
+                for (entity, components) in &batch {
+                    if let Some(lock) = lock {
+                        check_lock(
+                            locks,
+                            lock,
+                            *entity,
+                            components.iter().map(|c| c.name.as_str()),
+                        )?;
+                    }
+
+                    for (component, (key, value)) in
+                        components.iter().zip(entries(*entity, components))
+                    {
+                        if stored.insert(key, value)?.is_some() {
+                            return abort(AccessError::Conflict(*entity, component.name.clone()));
+                        }
+                    }
+                }
+                Ok(())
+            }),
+            AccessError::implementation,
+        )
+    }
+}
+
+/// Aborts with [`AccessError::LockRequired`] unless the lock holds write
+/// locks on all the named components of the entity. Checking the locks
+/// within the transaction keeps them from being released unnoticed until
+/// the components are written or removed.
+fn check_lock<'a>(
+    locks: &TransactionalTree,
+    lock: &Lock,
+    entity: Entity,
+    mut names: impl Iterator<Item = &'a str>,
+) -> ConflictableTransactionResult<(), AccessError> {
+    let rows = lock::load(locks, entity, AccessError::implementation)?;
+    match names.find(|name| !lock::holds_write_lock(&rows, lock, name)) {
+        Some(name) => abort(AccessError::LockRequired(entity, name.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Rejects names which are not valid component names, so the sled backend
+/// accepts exactly the components the others do.
+fn check_names<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Result<(), AccessError> {
+    match names
+        .into_iter()
+        .find(|name| !is_valid_component_name(name))
+    {
+        Some(name) => Err(AccessError::InvalidComponentName(name.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Reads the described components of the entity within a transaction,
+/// aborting if any of them was stored with another version of its layout
+/// than the one it is being read as.
+pub(crate) fn read<F: Format>(
+    stored: &TransactionalTree,
+    entity: Entity,
+    descriptors: &[ExtractionDescriptor],
+) -> ConflictableTransactionResult<Vec<Option<SerializedComponent<F>>>, AccessError> {
+    let mut components = Vec::with_capacity(descriptors.len());
+    for descriptor in descriptors {
+        let Some(value) = stored.get(key(entity, &descriptor.name))? else {
+            components.push(None);
+            continue;
+        };
+
+        let (version, contents) = match decode(&value) {
+            Ok(decoded) => decoded,
+            Err(err) => return abort(AccessError::implementation(err)),
+        };
+
+        if let Some(expected) = descriptor.version.filter(|expected| *expected != version) {
+            return abort(AccessError::VersionMismatch {
+                component: descriptor.name.clone(),
+                stored: version,
+                expected,
+            });
+        }
+
+        components.push(Some(SerializedComponent::<F> {
+            contents: F::Data::from(contents.to_vec()),
+            name: descriptor.name.clone(),
+            version,
+            schema: None,
+            metadata: None,
+        }));
+    }
+
+    Ok(components)
+}
+
+/// Components are keyed by the entity's bytes followed by their name, so
+/// an entity's components are next to each other.
+fn key(entity: Entity, name: &str) -> Vec<u8> {
+    [entity.0.as_bytes().as_slice(), name.as_bytes()].concat()
+}
+
+fn entries<F: Format>(
+    entity: Entity,
+    components: &[SerializedComponent<F>],
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+    components
+        .iter()
+        .map(|component| {
+            (
+                key(entity, &component.name),
+                encode(component.version, component.contents.as_ref()),
+            )
+        })
+        .collect()
+}
+
+/// Stores the version of the component's layout ahead of its contents.
+fn encode(version: Version, contents: &[u8]) -> Vec<u8> {
+    [
+        version.major.to_be_bytes().as_slice(),
+        &version.minor.to_be_bytes(),
+        &version.patch.to_be_bytes(),
+        contents,
+    ]
+    .concat()
+}
+
+fn decode(value: &[u8]) -> Result<(Version, &[u8]), Corrupted> {
+    if value.len() < 24 {
+        return Err(Corrupted("component"));
+    }
+
+    let part = |at: usize| u64::from_be_bytes(value[at..at + 8].try_into().unwrap());
+    Ok((Version::new(part(0), part(8), part(16)), &value[24..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_core::{
+        backend::{
+            AccessBackend, ExtractionDescriptor, Format, LockDescriptor, LockingBackend,
+            SerializedComponent,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::SledBackend;
+
+    eci_testing::access_conformance!(SledBackend::temporary().unwrap());
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    fn component(name: &str, content: &str) -> SerializedComponent<Json> {
+        SerializedComponent::<Json> {
+            contents: Json::serialize(DebugComponentA {
+                content: content.to_string(),
+            })
+            .unwrap(),
+            name: name.to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        }
+    }
+
+    fn extract(name: &str) -> ExtractionDescriptor {
+        ExtractionDescriptor {
+            name: name.to_string(),
+            version: None,
+            schema: None,
+        }
+    }
+
+    fn content(component: &Option<SerializedComponent<Json>>) -> String {
+        Json::deserialize::<DebugComponentA>(&component.as_ref().unwrap().contents)
+            .unwrap()
+            .content
+    }
+
+    #[test]
+    fn persist_across_reopening() {
+        let path = std::env::temp_dir().join(format!("eci-sled-{}", Entity::new()));
+        let entity = Entity::new();
+
+        {
+            let conn = SledBackend::open(&path).unwrap();
+            conn.write_components(entity, vec![component("DebugComponentA", "Hello")])
+                .unwrap();
+            conn.acquire_lock(
+                entity,
+                vec![LockDescriptor::entity()],
+                Duration::from_secs(60).into(),
+            )
+            .unwrap();
+            conn.flush().unwrap();
+        }
+
+        let conn = SledBackend::open(&path).unwrap();
+        let comps: Vec<Option<SerializedComponent<Json>>> = conn
+            .read_components(entity, vec![extract("DebugComponentA")])
+            .unwrap();
+        assert_eq!(content(&comps[0]), "Hello");
+        assert_eq!(conn.list_locks(Some(entity)).unwrap().len(), 1);
+
+        drop(conn);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+//! Stores components and locks in a [sled](https://docs.rs/sled) database,
+//! for targets where the C dependency of the sqlite backend is a problem.
+//!
+//! Components are kept in one tree, keyed by the entity's bytes followed by
+//! the component name. Locks are kept in another, with all of an entity's
+//! locks in the entry for the entity, so checking for conflicts and taking
+//! the locks can happen in one sled transaction. A third tree indexes the
+//! entities each lock is held on, for releasing and renewing it.
+//!
+//! Like the sqlite backend, writes are atomic, and locks conflict and
+//! expire the same way. A sled database can only be opened by one process
+//! at a time, so clones of the backend are the way to share it.
+
+mod access;
+mod joint;
+mod lock;
+use std::{error::Error, fmt::Display, path::Path};
+
+use eci_core::Entity;
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+    TransactionResult,
+};
+
+#[derive(Clone)]
+pub struct SledBackend {
+    db: sled::Db,
+    components: sled::Tree,
+    locks: sled::Tree,
+    /// Keyed by lock id followed by entity, for finding the entities a lock
+    /// is held on without going through every entity's locks.
+    held: sled::Tree,
+    owner: Option<String>,
+}
+
+impl TryFrom<sled::Db> for SledBackend {
+    type Error = sled::Error;
+    fn try_from(db: sled::Db) -> Result<Self, Self::Error> {
+        Ok(SledBackend {
+            components: db.open_tree("components")?,
+            locks: db.open_tree("locks")?,
+            held: db.open_tree("held")?,
+            db,
+            owner: None,
+        })
+    }
+}
+
+impl SledBackend {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, sled::Error> {
+        SledBackend::try_from(sled::open(path)?)
+    }
+
+    /// Opens a database which is removed again once the backend and all of
+    /// its clones are dropped, for tests and prototypes.
+    pub fn temporary() -> Result<Self, sled::Error> {
+        SledBackend::try_from(sled::Config::new().temporary(true).open()?)
+    }
+
+    /// Records the owner, such as a hostname, process id or service name,
+    /// with every lock acquired through this backend, so others running
+    /// into the locks can tell who holds them.
+    pub fn with_owner<O: Into<String>>(mut self, owner: O) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    /// Writes everything to disk, returning the number of bytes flushed.
+    /// Sled otherwise flushes periodically in the background.
+    pub fn flush(&self) -> Result<usize, sled::Error> {
+        self.db.flush()
+    }
+}
+
+/// An entry in the database which could not be decoded, most likely
+/// because it was not written by this backend.
+#[derive(Debug)]
+pub(crate) struct Corrupted(&'static str);
+
+impl Display for Corrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "corrupted {} in sled database", self.0)
+    }
+}
+
+impl Error for Corrupted {}
+
+/// Reads the entity from the first 16 bytes of a key.
+pub(crate) fn parse_entity(key: &[u8], what: &'static str) -> Result<Entity, Corrupted> {
+    key.get(..16)
+        .and_then(|bytes| uuid::Uuid::from_slice(bytes).ok())
+        .map(Entity)
+        .ok_or(Corrupted(what))
+}
+
+/// Aborts the transaction, undoing everything written within it.
+pub(crate) fn abort<T, E>(err: E) -> ConflictableTransactionResult<T, E> {
+    Err(ConflictableTransactionError::Abort(err))
+}
+
+/// Turns the outcome of a transaction into the error it was aborted with,
+/// or the error made by `storage` if sled itself failed.
+pub(crate) fn finish<T, E>(
+    result: TransactionResult<T, E>,
+    storage: fn(sled::Error) -> E,
+) -> Result<T, E> {
+    result.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(err) => storage(err),
+    })
+}
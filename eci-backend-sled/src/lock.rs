@@ -0,0 +1,637 @@
+use eci_core::{
+    backend::{
+        ActiveLock, Administrative, BulkLockResult, ConflictingLock, Expiry, Lock, LockDescriptor,
+        LockStats, LockingBackend, LockingError, LockingMode,
+    },
+    Entity,
+};
+use log::*;
+use sled::{
+    transaction::{ConflictableTransactionResult, TransactionalTree},
+    Transactional,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+use crate::{abort, finish, parse_entity, Corrupted, SledBackend};
+
+/// Stored in place of the expiry of locks which never expire.
+const NEVER: u64 = u64::MAX;
+
+/// A lock held on a single component of an entity, or on the entity as a
+/// whole. The entity is the key the lock is stored under.
+pub(crate) struct LockRow {
+    id: Uuid,
+    component: String,
+    mode: LockingMode,
+    /// `None` for locks which never expire.
+    expires: Option<SystemTime>,
+    owner: Option<String>,
+}
+
+impl LockRow {
+    fn is_active(&self, now: SystemTime) -> bool {
+        self.expires.is_none_or(|expires| now < expires)
+    }
+
+    /// Whether this lock keeps a lock in the given mode from being taken on
+    /// the component, following the same rules as the sqlite backend.
+    fn conflicts(&self, component: &str, mode: LockingMode, now: SystemTime) -> bool {
+        self.is_active(now)
+            && (self.mode == LockingMode::Exclusive
+                || mode == LockingMode::Exclusive
+                || (self.component == component
+                    && (self.mode == LockingMode::Write || mode == LockingMode::Write)))
+    }
+}
+
+impl LockingBackend for SledBackend {
+    fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        if descriptors.is_empty() {
+            return Err(LockingError::EmptyRequest);
+        }
+
+        let lock = Lock::new();
+        let id = lock_id(&lock);
+        let expires = expiry(expires)?;
+
+        finish(
+            (&self.locks, &self.held).transaction(|(locks, held)| {
+                self.insert_locks(locks, held, id, entity, &descriptors, expires)
+            }),
+            LockingError::implementation,
+        )?;
+        debug!("acquired lock {lock}");
+
+        Ok(with_expiry(lock, expires))
+    }
+
+    fn acquire_locks(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<Lock, LockingError> {
+        if requests.is_empty()
+            || requests
+                .iter()
+                .any(|(_, descriptors)| descriptors.is_empty())
+        {
+            return Err(LockingError::EmptyRequest);
+        }
+
+        let lock = Lock::new();
+        let id = lock_id(&lock);
+        let expires = expiry(Expiry::In(expires_in))?;
+
+        // Nothing is locked unless everything is, since a conflict aborts
+        // the whole transaction.
+        finish(
+            (&self.locks, &self.held).transaction(|(locks, held)| {
+                for (entity, descriptors) in &requests {
+                    self.insert_locks(locks, held, id, *entity, descriptors, expires)?;
+                }
+                Ok(())
+            }),
+            LockingError::implementation,
+        )?;
+
+        debug!("acquired lock {lock} on {} entities", requests.len());
+        Ok(with_expiry(lock, expires))
+    }
+
+    fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        debug!("releasing lock {lock}");
+        let released = self.release(lock_id(&lock))?;
+
+        debug!("deleted locks on {released} resources by releasing {lock}");
+        Ok(())
+    }
+
+    fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        debug!("renewing lock {lock}");
+        let id = lock_id(lock);
+        let expires = expiry(Expiry::In(extend_by))?;
+        let entities = self.held_on(id)?;
+
+        let renewed = finish(
+            self.locks.transaction(|locks| {
+                let now = SystemTime::now();
+
+                // Renewing a lock which never expires leaves it that way.
+                let mut renewed = 0;
+                for entity in &entities {
+                    let mut rows = load(locks, *entity, LockingError::implementation)?;
+                    for row in rows.iter_mut() {
+                        if row.id == id && row.is_active(now) {
+                            if row.expires.is_some() {
+                                row.expires = expires;
+                            }
+                            renewed += 1;
+                        }
+                    }
+                    store(locks, *entity, &rows)?;
+                }
+
+                if renewed == 0 {
+                    return abort(LockingError::Expired(lock.id()));
+                }
+
+                Ok(renewed)
+            }),
+            LockingError::implementation,
+        )?;
+
+        debug!("renewed locks on {renewed} resources held by {lock}");
+        Ok(())
+    }
+
+    fn upgrade_lock(
+        &self,
+        lock: &Lock,
+        entity: Entity,
+        component: String,
+    ) -> Result<(), LockingError> {
+        let id = lock_id(lock);
+
+        finish(
+            self.locks.transaction(|locks| {
+                let now = SystemTime::now();
+                let mut rows = load(locks, entity, LockingError::implementation)?;
+
+                let on_component =
+                    |row: &&LockRow| row.component == component && row.is_active(now);
+
+                if !rows.iter().filter(on_component).any(|row| row.id == id) {
+                    return abort(LockingError::Expired(lock.id()));
+                }
+
+                if rows.iter().filter(on_component).any(|row| row.id != id) {
+                    let holder = find_conflict(&rows, id, &component, LockingMode::Write, now);
+                    return abort(LockingError::Conflict(
+                        entity,
+                        component.clone(),
+                        LockingMode::Write,
+                        holder,
+                    ));
+                }
+
+                for row in rows.iter_mut() {
+                    if row.id == id && row.component == component {
+                        row.mode = LockingMode::Write;
+                    }
+                }
+
+                store(locks, entity, &rows)
+            }),
+            LockingError::implementation,
+        )?;
+
+        debug!("upgraded lock {lock} on {entity}'s {component} to a write lock");
+        Ok(())
+    }
+
+    fn force_release(&self, lock_id: Uuid, _: Administrative) -> Result<usize, LockingError> {
+        warn!("forcibly releasing lock {lock_id}");
+        self.release(lock_id)
+    }
+
+    fn force_release_entity(
+        &self,
+        entity: Entity,
+        _: Administrative,
+    ) -> Result<usize, LockingError> {
+        warn!("forcibly releasing all locks on {entity}");
+
+        finish(
+            (&self.locks, &self.held).transaction(|(locks, held)| {
+                let rows = load(locks, entity, LockingError::implementation)?;
+                for row in &rows {
+                    held.remove(held_key(row.id, entity))?;
+                }
+
+                locks.remove(entity.0.as_bytes())?;
+                Ok(rows.len())
+            }),
+            LockingError::implementation,
+        )
+    }
+
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        let now = SystemTime::now();
+
+        let mut stats = LockStats::default();
+        for entry in self.locks.iter() {
+            let (_, rows) = entry.map_err(LockingError::implementation)?;
+            for row in decode(&rows).map_err(LockingError::implementation)? {
+                if row.is_active(now) {
+                    stats.active += 1;
+                } else {
+                    stats.expired += 1;
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn list_locks(&self, filter: Option<Entity>) -> Result<Vec<ActiveLock>, LockingError> {
+        let now = SystemTime::now();
+
+        let entries = match filter {
+            Some(entity) => self
+                .locks
+                .get(entity.0.as_bytes())
+                .map_err(LockingError::implementation)?
+                .map(|rows| (entity, rows))
+                .into_iter()
+                .collect(),
+            None => self
+                .locks
+                .iter()
+                .map(|entry| {
+                    let (key, rows) = entry.map_err(LockingError::implementation)?;
+                    let entity =
+                        parse_entity(&key, "lock").map_err(LockingError::implementation)?;
+                    Ok((entity, rows))
+                })
+                .collect::<Result<Vec<_>, LockingError>>()?,
+        };
+
+        let mut locks = Vec::new();
+        for (entity, rows) in entries {
+            for row in decode(&rows).map_err(LockingError::implementation)? {
+                if row.is_active(now) {
+                    locks.push(ActiveLock {
+                        id: row.id.to_string(),
+                        entity,
+                        component: row.component,
+                        mode: row.mode,
+                        expires: row.expires,
+                        owner: row.owner,
+                    });
+                }
+            }
+        }
+
+        locks.sort_by(|a, b| (a.entity, &a.component, &a.id).cmp(&(b.entity, &b.component, &b.id)));
+        Ok(locks)
+    }
+
+    fn acquire_locks_bulk(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        let lock = Lock::new();
+        let id = lock_id(&lock);
+        let expires = expiry(Expiry::In(expires_in))?;
+
+        let (granted, skipped) = finish(
+            (&self.locks, &self.held).transaction(|(locks, held)| {
+                let (mut granted, mut skipped) = (Vec::new(), Vec::new());
+                for (entity, descriptors) in &requests {
+                    match self.insert_locks(locks, held, id, *entity, descriptors, expires) {
+                        Ok(()) => granted.push(*entity),
+                        Err(sled::transaction::ConflictableTransactionError::Abort(
+                            LockingError::Conflict(..),
+                        )) => {
+                            debug!("skipping {entity} due to conflicting locks");
+                            skipped.push(*entity);
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok((granted, skipped))
+            }),
+            LockingError::implementation,
+        )?;
+
+        debug!(
+            "bulk lock {lock} acquired, locked {} entities",
+            granted.len()
+        );
+        Ok(BulkLockResult {
+            lock: with_expiry(lock, expires),
+            granted,
+            skipped,
+        })
+    }
+}
+
+impl SledBackend {
+    /// Adds a lock on each of the descriptors to the entity's locks, aborting
+    /// with [`LockingError::Conflict`] on the first one which conflicts with
+    /// an existing lock. Nothing is written unless all of them are added.
+    pub(crate) fn insert_locks(
+        &self,
+        locks: &TransactionalTree,
+        held: &TransactionalTree,
+        id: Uuid,
+        entity: Entity,
+        descriptors: &[LockDescriptor],
+        expires: Option<SystemTime>,
+    ) -> ConflictableTransactionResult<(), LockingError> {
+        let now = SystemTime::now();
+        let mut rows = load(locks, entity, LockingError::implementation)?;
+
+        for descriptor in descriptors {
+            debug!("acquiring {}-lock for {}", descriptor.mode, descriptor.name);
+
+            // Like in the sqlite backend, the lock's own rows count too.
+            if rows
+                .iter()
+                .any(|row| row.conflicts(&descriptor.name, descriptor.mode, now))
+            {
+                let holder = find_conflict(&rows, id, &descriptor.name, descriptor.mode, now);
+                return abort(LockingError::Conflict(
+                    entity,
+                    descriptor.name.clone(),
+                    descriptor.mode,
+                    holder,
+                ));
+            }
+
+            rows.push(LockRow {
+                id,
+                component: descriptor.name.clone(),
+                mode: descriptor.mode,
+                expires,
+                owner: self.owner.clone(),
+            });
+        }
+
+        store(locks, entity, &rows)?;
+        held.insert(held_key(id, entity), Vec::new())?;
+        Ok(())
+    }
+
+    /// Removes the lock from every entity it is held on, returning the
+    /// number of components it was held on.
+    fn release(&self, id: Uuid) -> Result<usize, LockingError> {
+        let entities = self.held_on(id)?;
+
+        finish(
+            (&self.locks, &self.held).transaction(|(locks, held)| {
+                let mut released = 0;
+                for entity in &entities {
+                    let mut rows = load(locks, *entity, LockingError::implementation)?;
+                    let before = rows.len();
+                    rows.retain(|row| row.id != id);
+                    released += before - rows.len();
+
+                    store(locks, *entity, &rows)?;
+                    held.remove(held_key(id, *entity))?;
+                }
+                Ok(released)
+            }),
+            LockingError::implementation,
+        )
+    }
+
+    /// Lists the entities the lock is held on, expired or not.
+    fn held_on(&self, id: Uuid) -> Result<Vec<Entity>, LockingError> {
+        self.held
+            .scan_prefix(id.as_bytes())
+            .keys()
+            .map(|key| {
+                let key = key.map_err(LockingError::implementation)?;
+                parse_entity(&key[16..], "lock index").map_err(LockingError::implementation)
+            })
+            .collect()
+    }
+}
+
+/// Determines when a lock expires, failing for expiries too far into the
+/// future to store.
+pub(crate) fn expiry(expires: Expiry) -> Result<Option<SystemTime>, LockingError> {
+    match expires {
+        Expiry::In(expires_in) => SystemTime::now()
+            .checked_add(expires_in)
+            .filter(|expires| nanos(*expires) < NEVER)
+            .map(Some)
+            .ok_or(LockingError::ExpiryOutOfRange(expires_in)),
+        Expiry::Never => Ok(None),
+    }
+}
+
+pub(crate) fn with_expiry(lock: Lock, expires: Option<SystemTime>) -> Lock {
+    match expires {
+        Some(expires) => lock.with_expiry(expires),
+        None => lock,
+    }
+}
+
+pub(crate) fn lock_id(lock: &Lock) -> Uuid {
+    Uuid::parse_str(&lock.id()).expect("lock ids are uuids")
+}
+
+/// Finds the lock held by someone else which conflicts with the requested
+/// one, preferring the one which expires first.
+fn find_conflict(
+    rows: &[LockRow],
+    id: Uuid,
+    component: &str,
+    mode: LockingMode,
+    now: SystemTime,
+) -> Option<ConflictingLock> {
+    rows.iter()
+        .filter(|row| row.id != id && row.conflicts(component, mode, now))
+        .min_by_key(|row| (row.expires.is_none(), row.expires))
+        .map(|row| ConflictingLock {
+            id: row.id.to_string(),
+            mode: row.mode,
+            owner: row.owner.clone(),
+            expires: row.expires,
+        })
+}
+
+/// Whether the lock holds an unexpired write lock on the component, either
+/// on the component itself or on the entity as a whole.
+pub(crate) fn holds_write_lock(rows: &[LockRow], lock: &Lock, component: &str) -> bool {
+    let (id, now) = (lock_id(lock), SystemTime::now());
+    rows.iter().any(|row| {
+        row.id == id
+            && ((row.mode == LockingMode::Write && row.component == component)
+                || row.mode == LockingMode::Exclusive)
+            && row.is_active(now)
+    })
+}
+
+/// Reads the locks held on the entity within a transaction, aborting with
+/// the error made by `corrupted` if they cannot be decoded.
+pub(crate) fn load<E>(
+    locks: &TransactionalTree,
+    entity: Entity,
+    corrupted: fn(Corrupted) -> E,
+) -> ConflictableTransactionResult<Vec<LockRow>, E> {
+    match locks.get(entity.0.as_bytes())? {
+        Some(rows) => decode(&rows).or_else(|err| abort(corrupted(err))),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Writes the locks held on the entity within a transaction, removing its
+/// entry altogether once none are left.
+fn store<E>(
+    locks: &TransactionalTree,
+    entity: Entity,
+    rows: &[LockRow],
+) -> ConflictableTransactionResult<(), E> {
+    if rows.is_empty() {
+        locks.remove(entity.0.as_bytes())?;
+    } else {
+        locks.insert(entity.0.as_bytes(), encode(rows))?;
+    }
+
+    Ok(())
+}
+
+fn held_key(id: Uuid, entity: Entity) -> Vec<u8> {
+    [id.as_bytes().as_slice(), entity.0.as_bytes()].concat()
+}
+
+fn nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|since| u64::try_from(since.as_nanos()).ok())
+        .unwrap_or(NEVER)
+}
+
+/// Encodes each lock as its id, mode and expiry in nanoseconds, followed by
+/// its component and owner prefixed by their lengths.
+fn encode(rows: &[LockRow]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for row in rows {
+        bytes.extend_from_slice(row.id.as_bytes());
+        bytes.push(match row.mode {
+            LockingMode::Read => b'r',
+            LockingMode::Write => b'w',
+            LockingMode::Exclusive => b'x',
+        });
+        bytes.extend_from_slice(&row.expires.map(nanos).unwrap_or(NEVER).to_be_bytes());
+
+        for text in [Some(row.component.as_str()), row.owner.as_deref()] {
+            match text {
+                Some(text) => {
+                    bytes.extend_from_slice(&(text.len() as u32).to_be_bytes());
+                    bytes.extend_from_slice(text.as_bytes());
+                }
+                None => bytes.extend_from_slice(&u32::MAX.to_be_bytes()),
+            }
+        }
+    }
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Result<Vec<LockRow>, Corrupted> {
+    let mut reader = Reader(bytes);
+
+    let mut rows = Vec::new();
+    while !reader.0.is_empty() {
+        let id = Uuid::from_slice(reader.take(16)?).map_err(|_| Corrupted("lock"))?;
+        let mode = match reader.take(1)? {
+            b"r" => LockingMode::Read,
+            b"w" => LockingMode::Write,
+            b"x" => LockingMode::Exclusive,
+            _ => return Err(Corrupted("lock")),
+        };
+        let expires = match reader.u64()? {
+            NEVER => None,
+            nanos => Some(UNIX_EPOCH + Duration::from_nanos(nanos)),
+        };
+
+        rows.push(LockRow {
+            id,
+            component: reader.text()?.ok_or(Corrupted("lock"))?,
+            mode,
+            expires,
+            owner: reader.text()?,
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Reads the fields of encoded locks one after another.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], Corrupted> {
+        if self.0.len() < count {
+            return Err(Corrupted("lock"));
+        }
+
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u64(&mut self) -> Result<u64, Corrupted> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn text(&mut self) -> Result<Option<String>, Corrupted> {
+        let length = u32::from_be_bytes(self.take(4)?.try_into().unwrap());
+        if length == u32::MAX {
+            return Ok(None);
+        }
+
+        let text = self.take(length as usize)?;
+        String::from_utf8(text.to_vec())
+            .map(Some)
+            .map_err(|_| Corrupted("lock"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_core::{
+        backend::{LockDescriptor, LockingBackend, LockingError, LockingMode},
+        Entity,
+    };
+
+    use crate::SledBackend;
+
+    eci_testing::locking_conformance!(SledBackend::temporary().unwrap());
+
+    const LOCK_TIME: Duration = Duration::from_secs(60);
+
+    fn write_lock() -> Vec<LockDescriptor> {
+        vec![LockDescriptor {
+            mode: LockingMode::Write,
+            name: "DebugComponentA".to_string(),
+        }]
+    }
+
+    #[test]
+    fn clones_share_locks() {
+        let holder = SledBackend::temporary()
+            .unwrap()
+            .with_owner("inventory-service");
+        let contender = holder.clone().with_owner("billing-service");
+
+        let entity = Entity::new();
+        let held = holder
+            .acquire_lock(entity, write_lock(), LOCK_TIME.into())
+            .unwrap();
+
+        match contender.acquire_lock(entity, write_lock(), LOCK_TIME.into()) {
+            Err(LockingError::Conflict(_, _, _, Some(conflict))) => {
+                assert_eq!(conflict.id, held.id());
+                assert_eq!(conflict.owner.as_deref(), Some("inventory-service"));
+            }
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+
+        let listed = contender.list_locks(None).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].expires, held.expires_at());
+    }
+}
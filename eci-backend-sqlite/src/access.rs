@@ -1,261 +1,2359 @@
-use eci_core::backend::{
-    AccessBackend, AccessError, ExtractionDescriptor, Format, SerializedComponent,
+use eci_core::{
+    backend::{
+        scan_entities_where, AccessBackend, AccessError, Comparison, ComponentMetadata,
+        ComponentStats, ContextError, ExtractionDescriptor, Format, Lock, Predicate,
+        PredicateValue, SerializedComponent,
+    },
+    is_valid_component_name, Version,
 };
-use rusqlite::named_params;
+use rusqlite::{
+    named_params, params_from_iter, Connection, ErrorCode, OptionalExtension, ToSql, Transaction,
+};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+
+use crate::{
+    layout::{self, Table},
+    lock, metadata, registry,
+    retry::write_transaction,
+    statements::CachedStatements,
+    SqliteBackend,
+};
+
+impl<F: Format> AccessBackend<F> for SqliteBackend {
+    fn supports_atomic_writes(&self) -> bool {
+        true
+    }
+
+    fn write_components(
+        &self,
+        entity: eci_core::Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = write_transaction(&mut conn).map_err(AccessError::implementation)?;
+            metadata::record_naming(&tx, self.naming.as_ref())?;
+
+            for descriptor in &components {
+                let table = self.table(&descriptor.name)?;
+                self.prepare_table(&tx, &table, &descriptor.name)?;
+                insert_component(&tx, &table, entity, descriptor)?;
+            }
+
+            tx.commit().map_err(AccessError::implementation)
+        })
+    }
+
+    fn write_components_locked(
+        &self,
+        entity: eci_core::Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = write_transaction(&mut conn).map_err(AccessError::implementation)?;
+            metadata::record_naming(&tx, self.naming.as_ref())?;
+
+            for descriptor in &components {
+                check_lock(&tx, Some(lock), entity, &descriptor.name)?;
+
+                let table = self.table(&descriptor.name)?;
+                self.prepare_table(&tx, &table, &descriptor.name)?;
+                insert_component(&tx, &table, entity, descriptor)?;
+            }
+
+            tx.commit().map_err(AccessError::implementation)
+        })
+    }
+
+    fn write_components_batch(
+        &self,
+        batch: Vec<(eci_core::Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = write_transaction(&mut conn).map_err(AccessError::implementation)?;
+            metadata::record_naming(&tx, self.naming.as_ref())?;
+
+            let mut created = HashSet::new();
+            for (entity, components) in &batch {
+                for descriptor in components {
+                    let table = self.table(&descriptor.name)?;
+                    if !created.contains(&table) {
+                        self.prepare_table(&tx, &table, &descriptor.name)?;
+                        created.insert(table.clone());
+                    }
+
+                    insert_component(&tx, &table, *entity, descriptor)?;
+                }
+            }
+
+            tx.commit().map_err(AccessError::implementation)
+        })
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: eci_core::Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.insert_absent(entity, components, None)
+    }
+
+    fn write_components_if_absent_locked(
+        &self,
+        entity: eci_core::Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        self.insert_absent(entity, components, Some(lock))
+    }
+
+    fn update_components(
+        &self,
+        entity: eci_core::Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.update(entity, components, None)
+    }
+
+    fn update_components_locked(
+        &self,
+        entity: eci_core::Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        self.update(entity, components, Some(lock))
+    }
+
+    fn update_component_if_unchanged(
+        &self,
+        entity: eci_core::Entity,
+        component: SerializedComponent<F>,
+        revision: u64,
+    ) -> Result<(), AccessError> {
+        let name = &component.name;
+        let table = self.table(name)?;
+
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = write_transaction(&mut conn).map_err(AccessError::implementation)?;
+            metadata::check_naming(&tx, self.naming.as_ref())?;
+
+            // Components which were never stored have no revision to match.
+            if !table.exists(&tx)? {
+                return Err(AccessError::StaleWrite {
+                    entity,
+                    component: name.clone(),
+                    current: None,
+                });
+            }
+
+            let updated = tx
+                .execute_cached(
+                    &table.update_if_unchanged(),
+                    named_params! {
+                        ":entity": entity.to_string(),
+                        ":contents": component.contents.as_ref(),
+                        ":version": component.version.to_string(),
+                        ":updated_at": timestamp(SystemTime::now()),
+                        ":revision": i64::try_from(revision).unwrap_or(-1),
+                    },
+                )
+                .map_err(|err| {
+                    AccessError::implementation(ContextError::new(
+                        format!("updating {name} of {entity}"),
+                        err,
+                    ))
+                })?;
+
+            if updated == 0 {
+                let current = tx
+                    .query_row_cached(
+                        &table.revision(),
+                        named_params! { ":entity": entity.to_string() },
+                        |row| row.get::<_, i64>(0),
+                    )
+                    .optional()
+                    .map_err(AccessError::implementation)?;
+
+                return Err(AccessError::StaleWrite {
+                    entity,
+                    component: name.clone(),
+                    current: current.map(|current| current as u64),
+                });
+            }
+
+            tx.commit().map_err(AccessError::implementation)
+        })
+    }
+
+    fn read_components(
+        &self,
+        entity: eci_core::Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if descriptors.is_empty() {
+            return Err(AccessError::EmptyRequest);
+        }
+
+        let tables = descriptors
+            .iter()
+            .map(|descriptor| self.table(&descriptor.name))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let contents = self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = conn.transaction().map_err(AccessError::implementation)?;
+            metadata::check_naming(&tx, self.naming.as_ref())?;
+            self.read_contents(&tx, entity, &tables)
+        })?;
+
+        serialized(&descriptors, &tables, &contents)
+    }
+
+    fn remove_components(
+        &self,
+        entity: eci_core::Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.remove(entity, descriptors, None)
+    }
+
+    fn remove_components_locked(
+        &self,
+        entity: eci_core::Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.remove(entity, descriptors, Some(lock))
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<eci_core::Entity>, AccessError> {
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        metadata::check_naming(&conn, self.naming.as_ref())?;
+
+        let table = self.table(component)?;
+        if !table.exists(&conn)? {
+            return Ok(Vec::new());
+        }
+
+        let mut statement = conn
+            .prepare_cached(&table.entities())
+            .map_err(AccessError::implementation)?;
+
+        let entities = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(AccessError::implementation)?
+            .map(|entity| {
+                let entity = entity.map_err(AccessError::implementation)?;
+                Ok(eci_core::Entity(
+                    Uuid::parse_str(&entity).map_err(AccessError::implementation)?,
+                ))
+            })
+            .collect();
+        entities
+    }
+
+    fn modified_since(
+        &self,
+        component: &str,
+        since: SystemTime,
+    ) -> Result<Vec<eci_core::Entity>, AccessError> {
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        metadata::check_naming(&conn, self.naming.as_ref())?;
+
+        let table = self.table(component)?;
+        if !table.exists(&conn)? {
+            return Ok(Vec::new());
+        }
+
+        let mut statement = conn
+            .prepare_cached(&table.modified_since())
+            .map_err(AccessError::implementation)?;
+
+        let entities = statement
+            .query_map(named_params! { ":since": timestamp(since) }, |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(AccessError::implementation)?
+            .map(|entity| {
+                let entity = entity.map_err(AccessError::implementation)?;
+                Ok(eci_core::Entity(
+                    Uuid::parse_str(&entity).map_err(AccessError::implementation)?,
+                ))
+            })
+            .collect();
+        entities
+    }
+
+    fn list_entities(&self) -> Result<Vec<eci_core::Entity>, AccessError> {
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        metadata::check_naming(&conn, self.naming.as_ref())?;
+
+        let tables: Vec<Table> = registry::components(&conn)?
+            .into_iter()
+            .map(|(_, table)| Table::new(self.layout, table))
+            .collect();
+
+        // Entities may show up in more than one batch of tables.
+        let mut entities = BTreeSet::new();
+        for batch in tables.chunks(layout::MAX_COMPOUND_SELECT) {
+            let mut statement = conn
+                .prepare_cached(&layout::select_entities(batch))
+                .map_err(AccessError::implementation)?;
+
+            let rows = statement
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(AccessError::implementation)?;
+
+            for entity in rows {
+                entities.insert(entity.map_err(AccessError::implementation)?);
+            }
+        }
+
+        entities
+            .into_iter()
+            .map(|entity| {
+                Ok(eci_core::Entity(
+                    Uuid::parse_str(&entity).map_err(AccessError::implementation)?,
+                ))
+            })
+            .collect()
+    }
+
+    fn list_components(&self, entity: eci_core::Entity) -> Result<Vec<String>, AccessError> {
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        metadata::check_naming(&conn, self.naming.as_ref())?;
+
+        let components: Vec<(String, Table)> = registry::components(&conn)?
+            .into_iter()
+            .map(|(component, table)| (component, Table::new(self.layout, table)))
+            .collect();
+
+        // Components are recorded in order, so the batches are too.
+        let mut names = Vec::new();
+        for batch in components.chunks(layout::MAX_COMPOUND_SELECT) {
+            let mut statement = conn
+                .prepare_cached(&layout::select_components(batch))
+                .map_err(AccessError::implementation)?;
+
+            let rows = statement
+                .query_map(named_params! { ":entity": entity.to_string() }, |row| {
+                    row.get::<_, String>(0)
+                })
+                .map_err(AccessError::implementation)?;
+
+            for name in rows {
+                names.push(name.map_err(AccessError::implementation)?);
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Checks every table in a single query, without reading any contents.
+    fn contains(
+        &self,
+        entity: eci_core::Entity,
+        names: &[String],
+    ) -> Result<Vec<bool>, AccessError> {
+        let tables = names
+            .iter()
+            .map(|name| self.table(name))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        metadata::check_naming(&conn, self.naming.as_ref())?;
+
+        let unique = self.readable_tables(&conn, &tables)?;
+        if unique.is_empty() {
+            return Ok(vec![false; names.len()]);
+        }
+
+        let mut statement = conn
+            .prepare_cached(&layout::select_present(&unique))
+            .map_err(AccessError::implementation)?;
+
+        let present = statement
+            .query_map(named_params! { ":entity": entity.to_string() }, |row| {
+                row.get::<_, usize>(0)
+            })
+            .map_err(AccessError::implementation)?
+            .map(|index| Ok(unique[index.map_err(AccessError::implementation)?]))
+            .collect::<Result<Vec<&Table>, AccessError>>()?;
+
+        Ok(tables
+            .iter()
+            .map(|table| present.contains(&table))
+            .collect())
+    }
+
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        metadata::check_naming(&conn, self.naming.as_ref())?;
+
+        let components: Vec<(String, Table)> = registry::components(&conn)?
+            .into_iter()
+            .map(|(component, table)| (component, Table::new(self.layout, table)))
+            .collect();
+
+        let mut stats = Vec::new();
+        for batch in components.chunks(layout::MAX_COMPOUND_SELECT) {
+            let mut statement = conn
+                .prepare_cached(&layout::select_stats(batch))
+                .map_err(AccessError::implementation)?;
+
+            let rows = statement
+                .query_map([], |row| {
+                    Ok(ComponentStats {
+                        name: row.get(0)?,
+                        entity_count: row.get(1)?,
+                        total_bytes: row.get(2)?,
+                    })
+                })
+                .map_err(AccessError::implementation)?;
+
+            for row in rows {
+                stats.push(row.map_err(AccessError::implementation)?);
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Evaluates the predicates with sqlite's json functions when the
+    /// components are stored as JSON, and falls back to deserializing each
+    /// candidate component otherwise.
+    fn find_entities_where(
+        &self,
+        predicates: Vec<Predicate>,
+    ) -> Result<Vec<eci_core::Entity>, AccessError> {
+        if !F::IS_JSON {
+            return scan_entities_where::<F, _>(self, &predicates);
+        }
+
+        if predicates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        metadata::check_naming(&conn, self.naming.as_ref())?;
+
+        let mut joins = Vec::new();
+        let mut conditions = Vec::new();
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+        for (i, predicate) in predicates.iter().enumerate() {
+            let table = self.table(&predicate.component)?;
+            if !table.exists(&conn)? {
+                return Ok(Vec::new());
+            }
+
+            let (rows, condition) = table.rows(&format!("t{i}"));
+            joins.push(if i == 0 {
+                rows
+            } else {
+                format!("join {rows} on t{i}.entity = t0.entity")
+            });
+            conditions.extend(condition);
+
+            let (kinds, value): (_, Box<dyn ToSql>) = match &predicate.value {
+                PredicateValue::Integer(value) => ("'integer', 'real'", Box::new(*value)),
+                PredicateValue::Float(value) => ("'integer', 'real'", Box::new(*value)),
+                PredicateValue::String(value) => ("'text'", Box::new(value.clone())),
+                PredicateValue::Bool(value) => ("'true', 'false'", Box::new(*value)),
+            };
+
+            let operator = match predicate.comparison {
+                Comparison::Eq => "=",
+                Comparison::Ne => "!=",
+                Comparison::Lt => "<",
+                Comparison::Le => "<=",
+                Comparison::Gt => ">",
+                Comparison::Ge => ">=",
+            };
+
+            // Components are stored as blobs, which newer versions of sqlite
+            // would interpret as binary json.
+            let field = format!("cast(t{i}.contents as text), ?");
+            conditions.push(format!(
+                "json_type({field}) in ({kinds}) and json_extract({field}) {operator} ?"
+            ));
+
+            let path = json_path(&predicate.path);
+            params.push(Box::new(path.clone()));
+            params.push(Box::new(path));
+            params.push(value);
+        }
+
+        let mut statement = conn
+            .prepare_cached(&format!(
+                "select t0.entity from {} where {} order by t0.entity",
+                joins.join(" "),
+                conditions.join(" and ")
+            ))
+            .map_err(AccessError::implementation)?;
+
+        let entities = statement
+            .query_map(params_from_iter(params.iter()), |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(AccessError::implementation)?
+            .map(|entity| {
+                let entity = entity.map_err(AccessError::implementation)?;
+                Ok(eci_core::Entity(
+                    Uuid::parse_str(&entity).map_err(AccessError::implementation)?,
+                ))
+            })
+            .collect();
+        entities
+    }
+}
+
+/// Converts a predicate's field path to a json path, treating numeric
+/// names as array indices.
+fn json_path(path: &[String]) -> String {
+    let mut json_path = "$".to_string();
+    for name in path {
+        if name.parse::<usize>().is_ok() {
+            json_path.push_str(&format!("[{name}]"));
+        } else {
+            json_path.push_str(&format!(".\"{}\"", name.replace('"', "\\\"")));
+        }
+    }
+
+    json_path
+}
+
+/// Inserts a single component, failing with a conflict if the entity
+/// already has it.
+fn insert_component<F: Format>(
+    tx: &Transaction,
+    table: &Table,
+    entity: eci_core::Entity,
+    component: &SerializedComponent<F>,
+) -> Result<(), AccessError> {
+    let name = &component.name;
+
+    let mut statement = tx
+        .prepare_cached(&table.insert(None))
+        .map_err(AccessError::implementation)?;
+
+    match statement.execute(named_params! {
+        ":entity": entity.to_string(),
+        ":contents": component.contents.as_ref(),
+        ":version": component.version.to_string(),
+        ":updated_at": timestamp(SystemTime::now()),
+    }) {
+        Ok(1) => Ok(()),
+        Ok(_) => Err(AccessError::Conflict(entity, name.clone())),
+        Err(rusqlite::Error::SqliteFailure(err, _))
+            if err.code == ErrorCode::ConstraintViolation =>
+        {
+            Err(AccessError::Conflict(entity, name.clone()))
+        }
+        Err(err) => Err(AccessError::implementation(ContextError::new(
+            format!("writing {name} of {entity}"),
+            err,
+        ))),
+    }
+}
+
+/// Times are stored as microseconds since the epoch.
+/// Fails with [`AccessError::LockRequired`] if given a lock which does not
+/// hold a write lock on the entity's component.
+fn check_lock(
+    tx: &Connection,
+    lock: Option<&Lock>,
+    entity: eci_core::Entity,
+    component: &str,
+) -> Result<(), AccessError> {
+    match lock {
+        Some(lock)
+            if !lock::holds_write_lock(tx, lock, entity, component)
+                .map_err(AccessError::implementation)? =>
+        {
+            Err(AccessError::LockRequired(entity, component.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn timestamp(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| {
+        elapsed.as_micros().try_into().unwrap_or(i64::MAX)
+    })
+}
+
+/// Components stored before the time of their last write or their
+/// revision was recorded are taken to have been written at the epoch, and
+/// never since.
+fn stored_metadata(updated_at: i64, revision: i64) -> ComponentMetadata {
+    ComponentMetadata {
+        updated_at: UNIX_EPOCH + Duration::from_micros(updated_at.max(0) as u64),
+        revision: revision.max(0) as u64,
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version, AccessError> {
+    version.parse().map_err(AccessError::implementation)
+}
+
+/// Fails if the component was stored with another version of its layout
+/// than the one it is being read as.
+fn check_version(descriptor: &ExtractionDescriptor, stored: Version) -> Result<(), AccessError> {
+    match descriptor.version {
+        Some(expected) if expected != stored => Err(AccessError::VersionMismatch {
+            component: descriptor.name.clone(),
+            stored,
+            expected,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Contents, version and metadata of a component as read from its table.
+pub(crate) type StoredContents = (Vec<u8>, Version, ComponentMetadata);
+
+/// Pairs the described components up with the contents read from their
+/// tables. The same component may be requested more than once, so contents
+/// are cloned rather than moved out of the map.
+pub(crate) fn serialized<F: Format>(
+    descriptors: &[ExtractionDescriptor],
+    tables: &[Table],
+    contents: &HashMap<Table, StoredContents>,
+) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+    descriptors
+        .iter()
+        .zip(tables)
+        .map(|(descriptor, table)| {
+            contents
+                .get(table)
+                .map(|(contents, version, metadata)| {
+                    check_version(descriptor, *version)?;
+                    Ok(SerializedComponent::<F> {
+                        contents: F::Data::from(contents.clone()),
+                        name: descriptor.name.clone(),
+                        version: *version,
+                        schema: None,
+                        metadata: Some(*metadata),
+                    })
+                })
+                .transpose()
+        })
+        .collect()
+}
+
+impl SqliteBackend {
+    /// Writes the components the entity does not already have in one
+    /// transaction. Given a lock, it must hold write locks on all of them.
+    fn insert_absent<F: Format>(
+        &self,
+        entity: eci_core::Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: Option<&Lock>,
+    ) -> Result<(), AccessError> {
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = write_transaction(&mut conn).map_err(AccessError::implementation)?;
+            metadata::record_naming(&tx, self.naming.as_ref())?;
+
+            for descriptor in &components {
+                let name = &descriptor.name;
+                check_lock(&tx, lock, entity, name)?;
+                let table = self.table(name)?;
+
+                self.prepare_table(&tx, &table, name)?;
+
+                tx.execute_cached(
+                    &table.insert(Some("do nothing")),
+                    named_params! {
+                        ":entity": entity.to_string(),
+                        ":contents": descriptor.contents.as_ref(),
+                        ":version": descriptor.version.to_string(),
+                        ":updated_at": timestamp(SystemTime::now()),
+                    },
+                )
+                .map_err(|err| {
+                    AccessError::implementation(ContextError::new(
+                        format!("writing {name} of {entity}"),
+                        err,
+                    ))
+                })?;
+            }
+
+            tx.commit().map_err(AccessError::implementation)
+        })
+    }
+
+    /// Writes the components over any stored ones in one transaction. Given
+    /// a lock, it must hold write locks on all of them.
+    fn update<F: Format>(
+        &self,
+        entity: eci_core::Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: Option<&Lock>,
+    ) -> Result<(), AccessError> {
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = write_transaction(&mut conn).map_err(AccessError::implementation)?;
+            metadata::record_naming(&tx, self.naming.as_ref())?;
+
+            for descriptor in &components {
+                let name = &descriptor.name;
+                check_lock(&tx, lock, entity, name)?;
+                let table = self.table(name)?;
+
+                let params = named_params! {
+                    ":entity": entity.to_string(),
+                    ":contents": descriptor.contents.as_ref(),
+                    ":version": descriptor.version.to_string(),
+                    ":updated_at": timestamp(SystemTime::now()),
+                };
+
+                self.prepare_table(&tx, &table, name)?;
+
+                tx.execute_cached(
+                    &table.insert(Some(
+                        "do update set contents = excluded.contents, version = excluded.version,
+                        updated_at = excluded.updated_at, revision = revision + 1",
+                    )),
+                    params,
+                )
+                .map_err(|err| {
+                    AccessError::implementation(ContextError::new(
+                        format!("updating {name} of {entity}"),
+                        err,
+                    ))
+                })?;
+            }
+
+            tx.commit().map_err(AccessError::implementation)
+        })
+    }
+
+    /// Removes the components in one transaction, returning the removed
+    /// values. Given a lock, it must hold write locks on all of them.
+    fn remove<F: Format>(
+        &self,
+        entity: eci_core::Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: Option<&Lock>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = write_transaction(&mut conn).map_err(AccessError::implementation)?;
+            metadata::check_naming(&tx, self.naming.as_ref())?;
+
+            let mut components = Vec::new();
+            for descriptor in &descriptors {
+                let name = &descriptor.name;
+                check_lock(&tx, lock, entity, name)?;
+                let table = self.table(name)?;
+
+                let params = named_params! {
+                    ":entity": entity.to_string(),
+                };
+
+                let component = match tx
+                    .query_row_cached(&table.select(), params, |row| {
+                        Ok((
+                            row.get::<_, Vec<u8>>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, i64>(2)?,
+                            row.get::<_, i64>(3)?,
+                        ))
+                    })
+                    .ok()
+                {
+                    Some((contents, version, updated_at, revision)) => {
+                        let version = parse_version(&version)?;
+                        check_version(descriptor, version)?;
+                        Some(SerializedComponent::<F> {
+                            contents: F::Data::from(contents),
+                            name: name.clone(),
+                            version,
+                            schema: None,
+                            metadata: Some(stored_metadata(updated_at, revision)),
+                        })
+                    }
+                    None => None,
+                };
+
+                if component.is_some() {
+                    tx.execute_cached(&table.delete(), params).map_err(|err| {
+                        AccessError::implementation(ContextError::new(
+                            format!("removing {name} of {entity}"),
+                            err,
+                        ))
+                    })?;
+                }
+
+                components.push(component);
+            }
+
+            tx.commit().map_err(AccessError::implementation)?;
+            Ok(components)
+        })
+    }
+
+    /// Looks up where the component is stored, rejecting names which are
+    /// not valid component names before they get anywhere near SQL.
+    pub(crate) fn table(&self, component: &str) -> Result<Table, AccessError> {
+        if !is_valid_component_name(component) {
+            return Err(AccessError::InvalidComponentName(component.to_string()));
+        }
+
+        Ok(Table::new(self.layout, self.naming.table_name(component)))
+    }
+
+    /// The distinct tables among the given ones which exist, and so can be
+    /// read from.
+    fn readable_tables<'a>(
+        &self,
+        conn: &Connection,
+        tables: &'a [Table],
+    ) -> Result<Vec<&'a Table>, AccessError> {
+        let mut unique: Vec<&Table> = Vec::new();
+        for table in tables {
+            if !unique.contains(&table) {
+                unique.push(table);
+            }
+        }
+
+        let unknown: Vec<&Table> = {
+            let registered = self.registered.read().unwrap();
+            unique
+                .iter()
+                .copied()
+                .filter(|table| !registered.contains(table))
+                .collect()
+        };
+        let existing = layout::existing_tables(conn, &unknown)?;
+        unique.retain(|table| !unknown.contains(table) || existing.contains(table));
+
+        Ok(unique)
+    }
+
+    /// Reads the entity's components from all of the tables in a single
+    /// statement, skipping tables which have not been created yet.
+    pub(crate) fn read_contents(
+        &self,
+        conn: &Connection,
+        entity: eci_core::Entity,
+        tables: &[Table],
+    ) -> Result<HashMap<Table, StoredContents>, AccessError> {
+        let unique = self.readable_tables(conn, tables)?;
+        if unique.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut statement = conn
+            .prepare_cached(&layout::select_each(&unique))
+            .map_err(AccessError::implementation)?;
+
+        let rows = statement
+            .query_map(named_params! { ":entity": entity.to_string() }, |row| {
+                Ok((
+                    row.get::<_, usize>(0)?,
+                    row.get::<_, Vec<u8>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })
+            .map_err(AccessError::implementation)?;
+
+        rows.map(|row| {
+            let (index, contents, version, updated_at, revision) =
+                row.map_err(AccessError::implementation)?;
+            Ok((
+                unique[index].clone(),
+                (
+                    contents,
+                    parse_version(&version)?,
+                    stored_metadata(updated_at, revision),
+                ),
+            ))
+        })
+        .collect()
+    }
+
+    /// Makes sure the component's table exists before writing to it.
+    /// Registered components already have one, while unregistered ones get
+    /// theirs created on the fly, unless registration is required.
+    fn prepare_table(
+        &self,
+        tx: &Transaction,
+        table: &Table,
+        component: &str,
+    ) -> Result<(), AccessError> {
+        if self.registered.read().unwrap().contains(table) {
+            return Ok(());
+        }
+
+        if self.require_registration {
+            return Err(AccessError::UnknownComponent(component.to_string()));
+        }
+
+        table.create(tx, component)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, Backend, ComponentStats, Compressed, ExtractionDescriptor,
+            Field, Format, Lock, LockDescriptor, LockingBackend, LockingMode, NamingStrategy,
+            SerializedComponent, SnakeCasePrefixed,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use rusqlite::{ffi, named_params};
+    use serde::{Deserialize, Serialize};
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        ffi::CStr,
+        time::{Duration, SystemTime},
+    };
+
+    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentB {
+        content: String,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentC {
+        content: String,
+    }
+
+    use crate::SqliteBackend;
+
+    /// Runs the tests against both the table-per-component and the
+    /// single-table layout.
+    macro_rules! layouts {
+        ($($test:ident),* $(,)?) => {
+            mod table_per_component {
+                use crate::SqliteBackend;
+
+                $(
+                    #[test]
+                    fn $test() {
+                        super::$test(|| SqliteBackend::memory().unwrap())
+                    }
+                )*
+
+                mod conformance {
+                    use crate::SqliteBackend;
+
+                    eci_testing::access_conformance!(SqliteBackend::memory().unwrap());
+                }
+            }
+
+            mod single_table {
+                use crate::SqliteBackend;
+
+                $(
+                    #[test]
+                    fn $test() {
+                        super::$test(|| SqliteBackend::memory_single_table().unwrap())
+                    }
+                )*
+
+                mod conformance {
+                    use crate::SqliteBackend;
+
+                    eci_testing::access_conformance!(SqliteBackend::memory_single_table().unwrap());
+                }
+            }
+        };
+    }
+
+    layouts!(
+        insert_disparate_components,
+        read_components,
+        read_same_component_twice,
+        update_existing_component,
+        remove_components,
+        list_entities_and_components,
+        contains_components,
+        modified_since,
+        update_if_unchanged,
+        component_stats,
+        compressed_and_legacy_contents,
+        reject_version_mismatch,
+        reject_hostile_component_names,
+        reject_empty_requests,
+        locked_write_requires_write_lock,
+        mandatory_locking,
+        mandatory_locking_of_updates_and_removals,
+        require_registration,
+        read_in_one_statement,
+        reuse_prepared_statements,
+    );
+
+    fn insert_disparate_components(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+
+        conn.write_components(
+            entity,
+            vec![
+                SerializedComponent::<Json> {
+                    contents: Json::serialize(DebugComponentA {
+                        content: "Hello".to_string(),
+                    })
+                    .unwrap(),
+                    name: "DebugComponentA".to_string(),
+                    version: Version::default(),
+                    schema: None,
+                    metadata: None,
+                },
+                SerializedComponent::<Json> {
+                    contents: Json::serialize(DebugComponentB {
+                        content: "Hello".to_string(),
+                    })
+                    .unwrap(),
+                    name: "DebugComponentB".to_string(),
+                    version: Version::default(),
+                    schema: None,
+                    metadata: None,
+                },
+            ],
+        )
+        .unwrap();
+    }
+
+    fn read_components(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+
+        let a = DebugComponentA {
+            content: "Hello".to_string(),
+        };
+
+        let b = DebugComponentB {
+            content: "World".to_string(),
+        };
+
+        conn.write_components(
+            entity,
+            vec![
+                SerializedComponent::<Json> {
+                    contents: Json::serialize(&a).unwrap(),
+                    name: "DebugComponentA".to_string(),
+                    version: Version::default(),
+                    schema: None,
+                    metadata: None,
+                },
+                SerializedComponent::<Json> {
+                    contents: Json::serialize(&b).unwrap(),
+                    name: "DebugComponentB".to_string(),
+                    version: Version::default(),
+                    schema: None,
+                    metadata: None,
+                },
+            ],
+        )
+        .unwrap();
+
+        let comps: Vec<Option<SerializedComponent<Json>>> = conn
+            .read_components(
+                entity,
+                vec![
+                    ExtractionDescriptor {
+                        name: "DebugComponentA".to_string(),
+                        version: None,
+                        schema: None,
+                    },
+                    ExtractionDescriptor {
+                        name: "DebugComponentB".to_string(),
+                        version: None,
+                        schema: None,
+                    },
+                ],
+            )
+            .unwrap();
+
+        let ax = Json::deserialize(&comps[0].as_ref().unwrap().contents).unwrap();
+        let bx = Json::deserialize(&comps[1].as_ref().unwrap().contents).unwrap();
+
+        assert_eq!(a, ax);
+        assert_eq!(b, bx);
+    }
+
+    fn read_same_component_twice(memory: fn() -> SqliteBackend) {
+        // While not exactly useful, there's no real reason why you shouldn't be allowed to
+        // read the same component twice within a single query
+        let conn = memory();
+        let entity = Entity::new();
+
+        let a = DebugComponentA {
+            content: "Hello".to_string(),
+        };
+
+        conn.write_components(
+            entity,
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(&a).unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+
+        let comps: Vec<Option<SerializedComponent<Json>>> = conn
+            .read_components(
+                entity,
+                vec![
+                    ExtractionDescriptor {
+                        name: "DebugComponentA".to_string(),
+                        version: None,
+                        schema: None,
+                    },
+                    ExtractionDescriptor {
+                        name: "DebugComponentA".to_string(),
+                        version: None,
+                        schema: None,
+                    },
+                ],
+            )
+            .unwrap();
+
+        let ax = Json::deserialize(&comps[0].as_ref().unwrap().contents).unwrap();
+        let bx = Json::deserialize(&comps[1].as_ref().unwrap().contents).unwrap();
+
+        assert_eq!(&a, &ax);
+        assert_eq!(&a, &bx);
+    }
+
+    fn update_existing_component(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+
+        let c = DebugComponentC {
+            content: "Untouched".to_string(),
+        };
+
+        conn.write_components(
+            entity,
+            vec![
+                SerializedComponent::<Json> {
+                    contents: Json::serialize(DebugComponentA {
+                        content: "Hello".to_string(),
+                    })
+                    .unwrap(),
+                    name: "DebugComponentA".to_string(),
+                    version: Version::default(),
+                    schema: None,
+                    metadata: None,
+                },
+                SerializedComponent::<Json> {
+                    contents: Json::serialize(&c).unwrap(),
+                    name: "DebugComponentC".to_string(),
+                    version: Version::default(),
+                    schema: None,
+                    metadata: None,
+                },
+            ],
+        )
+        .unwrap();
+
+        let a = DebugComponentA {
+            content: "World".to_string(),
+        };
+
+        conn.update_components(
+            entity,
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(&a).unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+
+        let comps: Vec<Option<SerializedComponent<Json>>> = conn
+            .read_components(
+                entity,
+                vec![
+                    ExtractionDescriptor {
+                        name: "DebugComponentA".to_string(),
+                        version: None,
+                        schema: None,
+                    },
+                    ExtractionDescriptor {
+                        name: "DebugComponentC".to_string(),
+                        version: None,
+                        schema: None,
+                    },
+                ],
+            )
+            .unwrap();
+
+        let ax = Json::deserialize(&comps[0].as_ref().unwrap().contents).unwrap();
+        let cx = Json::deserialize(&comps[1].as_ref().unwrap().contents).unwrap();
+        assert_eq!(a, ax);
+        assert_eq!(c, cx);
+    }
+
+    fn remove_components(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+
+        let a = DebugComponentA {
+            content: "Hello".to_string(),
+        };
+
+        conn.write_components(
+            entity,
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(&a).unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+
+        let descriptors = || {
+            vec![
+                ExtractionDescriptor {
+                    name: "DebugComponentA".to_string(),
+                    version: None,
+                    schema: None,
+                },
+                ExtractionDescriptor {
+                    name: "DebugComponentB".to_string(),
+                    version: None,
+                    schema: None,
+                },
+            ]
+        };
+
+        let removed: Vec<Option<SerializedComponent<Json>>> =
+            conn.remove_components(entity, descriptors()).unwrap();
+
+        let ax = Json::deserialize(&removed[0].as_ref().unwrap().contents).unwrap();
+        assert_eq!(a, ax);
+        assert!(removed[1].is_none());
+
+        let removed: Vec<Option<SerializedComponent<Json>>> =
+            conn.remove_components(entity, descriptors()).unwrap();
+        assert!(removed.iter().all(Option::is_none));
+    }
+
+    fn list_entities_and_components(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let component = |name: &str| SerializedComponent::<Json> {
+            contents: Json::serialize(DebugComponentA {
+                content: name.to_string(),
+            })
+            .unwrap(),
+            name: name.to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        };
+
+        // Each entity has a different set of components, some of which
+        // overlap, and one of which is registered without being written.
+        conn.register_components(&["DebugComponentC"]).unwrap();
+        let (first, second, third) = (Entity::new(), Entity::new(), Entity::new());
+        let sets = [
+            (first, vec!["DebugComponentB", "DebugComponentA"]),
+            (second, vec!["DebugComponentB"]),
+            (third, vec!["DebugComponentD"]),
+        ];
+
+        for (entity, components) in &sets {
+            conn.write_components(
+                *entity,
+                components.iter().map(|name| component(name)).collect(),
+            )
+            .unwrap();
+        }
+
+        let mut expected = vec![first, second, third];
+        expected.sort();
+        assert_eq!(
+            AccessBackend::<Json>::list_entities(&conn).unwrap(),
+            expected
+        );
+
+        assert_eq!(
+            AccessBackend::<Json>::list_components(&conn, first).unwrap(),
+            vec!["DebugComponentA", "DebugComponentB"]
+        );
+        assert_eq!(
+            AccessBackend::<Json>::list_components(&conn, second).unwrap(),
+            vec!["DebugComponentB"]
+        );
+        assert_eq!(
+            AccessBackend::<Json>::list_components(&conn, third).unwrap(),
+            vec!["DebugComponentD"]
+        );
+        assert!(AccessBackend::<Json>::list_components(&conn, Entity::new())
+            .unwrap()
+            .is_empty());
+
+        // Entities without components are gone.
+        AccessBackend::<Json>::remove_components(
+            &conn,
+            third,
+            vec![ExtractionDescriptor {
+                name: "DebugComponentD".to_string(),
+                version: None,
+                schema: None,
+            }],
+        )
+        .unwrap();
+
+        expected.retain(|entity| *entity != third);
+        let backend = Backend::<Json>::from_joint(conn);
+        assert_eq!(backend.list_entities().unwrap(), expected);
+        assert!(backend.list_components(third).unwrap().is_empty());
+    }
+
+    fn contains_components(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+        conn.register_components(&["DebugComponentC"]).unwrap();
+
+        // Contents are never read, so they need not deserialize.
+        conn.write_components(
+            entity,
+            vec![SerializedComponent::<Json> {
+                contents: b"not json".to_vec(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+
+        let names =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+
+        assert_eq!(
+            AccessBackend::<Json>::contains(
+                &conn,
+                entity,
+                &names(&[
+                    "DebugComponentC",
+                    "DebugComponentA",
+                    "DebugComponentD",
+                    "DebugComponentA"
+                ])
+            )
+            .unwrap(),
+            vec![false, true, false, true]
+        );
+        assert_eq!(
+            AccessBackend::<Json>::contains(&conn, Entity::new(), &names(&["DebugComponentA"]))
+                .unwrap(),
+            vec![false]
+        );
+        assert_eq!(
+            AccessBackend::<Json>::contains(&conn, entity, &names(&["DebugComponentD"])).unwrap(),
+            vec![false]
+        );
+        assert!(AccessBackend::<Json>::contains(&conn, entity, &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    fn modified_since(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let (changed, unchanged) = (Entity::new(), Entity::new());
+        conn.write_components(changed, component_a("Hello"))
+            .unwrap();
+        conn.write_components(unchanged, component_a("Hello"))
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(2));
+        let since = SystemTime::now();
+        std::thread::sleep(Duration::from_millis(2));
+        conn.update_components(changed, component_a("World"))
+            .unwrap();
+
+        assert_eq!(
+            AccessBackend::<Json>::modified_since(&conn, "DebugComponentA", since).unwrap(),
+            vec![changed]
+        );
+        assert!(
+            AccessBackend::<Json>::modified_since(&conn, "DebugComponentB", since)
+                .unwrap()
+                .is_empty()
+        );
+
+        // The time of the last write is read back along with the contents.
+        let read = AccessBackend::<Json>::read_components(
+            &conn,
+            changed,
+            vec![ExtractionDescriptor {
+                name: "DebugComponentA".to_string(),
+                version: None,
+                schema: None,
+            }],
+        )
+        .unwrap();
+        let updated_at = read[0].as_ref().unwrap().metadata.unwrap().updated_at;
+        assert!(updated_at >= since);
+
+        // Changes are found through the index rather than by scanning.
+        let table = conn.table("DebugComponentA").unwrap();
+        let plan = conn
+            .with_connection(|raw| {
+                raw.prepare(&format!("explain query plan {}", table.modified_since()))?
+                    .query_map(named_params! { ":since": 0 }, |row| row.get::<_, String>(3))?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .unwrap();
+        assert!(
+            plan.iter().any(|step| step.contains("USING INDEX")),
+            "{plan:?}"
+        );
+    }
+
+    fn update_if_unchanged(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+        let revision = |conn: &SqliteBackend| {
+            AccessBackend::<Json>::read_components(
+                conn,
+                entity,
+                vec![ExtractionDescriptor {
+                    name: "DebugComponentA".to_string(),
+                    version: None,
+                    schema: None,
+                }],
+            )
+            .unwrap()
+            .remove(0)
+            .map(|component| component.metadata.unwrap().revision)
+        };
+
+        let stale = |result: Result<(), AccessError>| match result {
+            Err(AccessError::StaleWrite { current, .. }) => current,
+            other => panic!("expected a stale write, got {other:?}"),
+        };
+
+        // Components which were never written have no revision to match.
+        assert_eq!(
+            stale(conn.update_component_if_unchanged(entity, component_a("Hello").remove(0), 0)),
+            None
+        );
+
+        conn.write_components(entity, component_a("Hello")).unwrap();
+        assert_eq!(revision(&conn), Some(1));
+        conn.update_components(entity, component_a("World"))
+            .unwrap();
+        assert_eq!(revision(&conn), Some(2));
+
+        assert_eq!(
+            stale(conn.update_component_if_unchanged(entity, component_a("Stale").remove(0), 1)),
+            Some(2)
+        );
+        conn.update_component_if_unchanged(entity, component_a("Fresh").remove(0), 2)
+            .unwrap();
+        assert_eq!(revision(&conn), Some(3));
+    }
+
+    fn component_stats(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let component = |name: &str, content: &str| SerializedComponent::<Json> {
+            contents: Json::serialize(DebugComponentA {
+                content: content.to_string(),
+            })
+            .unwrap(),
+            name: name.to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        };
+
+        conn.register_components(&["DebugComponentC"]).unwrap();
+        for content in ["a", "bb", "ccc"] {
+            conn.write_components(
+                Entity::new(),
+                vec![
+                    component("DebugComponentA", content),
+                    component("DebugComponentB", "b"),
+                ],
+            )
+            .unwrap();
+        }
+
+        // {"content":"a"} and so on.
+        let bytes = |content: &str| 14 + content.len() as u64;
+        let backend = Backend::<Json>::from_joint(conn);
+        assert_eq!(
+            backend.stats().unwrap(),
+            vec![
+                ComponentStats {
+                    name: "DebugComponentA".to_string(),
+                    entity_count: 3,
+                    total_bytes: bytes("a") + bytes("bb") + bytes("ccc"),
+                },
+                ComponentStats {
+                    name: "DebugComponentB".to_string(),
+                    entity_count: 3,
+                    total_bytes: 3 * bytes("b"),
+                },
+                ComponentStats {
+                    name: "DebugComponentC".to_string(),
+                    entity_count: 0,
+                    total_bytes: 0,
+                },
+            ]
+        );
+    }
+
+    fn compressed_and_legacy_contents(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let (legacy, packed) = (Entity::new(), Entity::new());
+        let payload = DebugComponentA {
+            content: "entity component interface ".repeat(100),
+        };
+
+        // Written before compression was enabled.
+        conn.write_components(
+            legacy,
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(&payload).unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+
+        conn.write_components(
+            packed,
+            vec![SerializedComponent::<Compressed<Json>> {
+                contents: Compressed::<Json>::serialize(&payload).unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+
+        let descriptors = || {
+            vec![ExtractionDescriptor {
+                name: "DebugComponentA".to_string(),
+                version: None,
+                schema: None,
+            }]
+        };
+
+        for entity in [legacy, packed] {
+            let component =
+                AccessBackend::<Compressed<Json>>::read_components(&conn, entity, descriptors())
+                    .unwrap()
+                    .pop()
+                    .flatten()
+                    .unwrap();
+
+            assert_eq!(
+                Compressed::<Json>::deserialize::<DebugComponentA>(&component.contents).unwrap(),
+                payload
+            );
+        }
+
+        let stored_size = |entity| {
+            AccessBackend::<Json>::read_components(&conn, entity, descriptors())
+                .unwrap()
+                .pop()
+                .flatten()
+                .unwrap()
+                .contents
+                .len()
+        };
+        assert!(stored_size(packed) * 10 < stored_size(legacy));
+    }
+
+    #[test]
+    fn inspect_rows_through_connection() {
+        let entity = Entity::new();
+        let component = || {
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(DebugComponentA {
+                    content: "Hello".to_string(),
+                })
+                .unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }]
+        };
+
+        let layouts = [
+            (
+                SqliteBackend::memory().unwrap(),
+                "select entity, contents from DebugComponentA",
+            ),
+            (
+                SqliteBackend::memory_single_table().unwrap(),
+                "select entity, contents from components where name = 'DebugComponentA'",
+            ),
+        ];
+
+        for (conn, query) in layouts {
+            conn.write_components(entity, component()).unwrap();
+
+            let rows = conn
+                .with_connection(|raw| {
+                    raw.prepare(query)?
+                        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                        .collect::<Result<Vec<(String, Vec<u8>)>, _>>()
+                })
+                .unwrap();
+
+            assert_eq!(
+                rows,
+                vec![(entity.to_string(), br#"{"content":"Hello"}"#.to_vec())]
+            );
+
+            // Errors from the closure are passed on.
+            assert!(matches!(
+                conn.with_connection(|raw| raw.execute("select * from missing", [])),
+                Err(AccessError::Implementation(_))
+            ));
+        }
+    }
+
+    fn reject_version_mismatch(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+        let (old, new) = (Version::new(1, 0, 0), Version::new(2, 0, 0));
+
+        let mut components = component_a("Hello");
+        components[0].version = old;
+        conn.write_components(entity, components).unwrap();
+
+        let descriptor = |version| {
+            vec![ExtractionDescriptor {
+                name: "DebugComponentA".to_string(),
+                version,
+                schema: None,
+            }]
+        };
+
+        let read = |version| {
+            AccessBackend::<Json>::read_components(&conn, entity, descriptor(version))
+                .map(|mut components| components.remove(0).map(|component| component.version))
+        };
+
+        assert_eq!(read(None).unwrap(), Some(old));
+        assert_eq!(read(Some(old)).unwrap(), Some(old));
+        match read(Some(new)) {
+            Err(AccessError::VersionMismatch {
+                component,
+                stored,
+                expected,
+            }) => {
+                assert_eq!(component, "DebugComponentA");
+                assert_eq!((stored, expected), (old, new));
+            }
+            other => panic!("expected a version mismatch, got {other:?}"),
+        }
+
+        // Removing the component as the wrong version leaves it in place.
+        assert!(matches!(
+            AccessBackend::<Json>::remove_components(&conn, entity, descriptor(Some(new))),
+            Err(AccessError::VersionMismatch { .. })
+        ));
+        assert_eq!(read(None).unwrap(), Some(old));
+
+        let mut components = component_a("World");
+        components[0].version = new;
+        conn.update_components(entity, components).unwrap();
+        assert_eq!(read(Some(new)).unwrap(), Some(new));
+    }
+
+    #[test]
+    fn add_version_to_existing_tables() {
+        let path = std::env::temp_dir().join(format!("eci-version-{}.db", Entity::new()));
+        let entity = Entity::new();
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(&format!(
+                "
+                create table DebugComponentA (
+                    entity   text not null unique,
+                    contents blob not null
+                );
+
+                insert into DebugComponentA (entity, contents)
+                values ('{entity}', cast('{{\"content\":\"Hello\"}}' as blob));
+            "
+            ))
+            .unwrap();
+
+        let conn = SqliteBackend::file(&path).unwrap();
+        let read = AccessBackend::<Json>::read_components(
+            &conn,
+            entity,
+            vec![ExtractionDescriptor {
+                name: "DebugComponentA".to_string(),
+                version: Some(Version::default()),
+                schema: None,
+            }],
+        )
+        .unwrap();
+        assert_eq!(read[0].as_ref().unwrap().version, Version::default());
+
+        drop(conn);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn table_names(conn: &SqliteBackend) -> Vec<String> {
+        let conn = conn.pool.get().unwrap();
+        let mut statement = conn
+            .prepare("select name from sqlite_master where type = 'table' order by name")
+            .unwrap();
 
-use crate::SqliteBackend;
+        let names = statement
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        names
+    }
 
-impl<F: Format> AccessBackend<F> for SqliteBackend {
-    fn write_components(
-        &self,
-        entity: eci_core::Entity,
-        components: Vec<SerializedComponent<F>>,
-    ) -> Result<(), AccessError> {
-        let mut conn = self.0.get().map_err(AccessError::implementation)?;
-        let tx = conn.transaction().map_err(AccessError::implementation)?;
+    #[test]
+    fn snake_case_prefixed_table_names() {
+        let conn = SqliteBackend::memory()
+            .unwrap()
+            .with_naming(SnakeCasePrefixed::new("eci_"))
+            .unwrap();
+        let entity = Entity::new();
 
-        for descriptor in components {
-            let name = descriptor.name;
-            let serialized_contents: Vec<u8> = descriptor.contents.into();
+        let a = DebugComponentA {
+            content: "Hello".to_string(),
+        };
 
-            let params = named_params! {
-                ":entity": entity.to_string(),
-                ":contents": serialized_contents,
-            };
+        conn.write_components(
+            entity,
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(&a).unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
 
-            // TODO: Should not be creating the table at this point in time but whatever.
-            tx.execute_batch(&format!(
-                "
-            create table if not exists {name} (
-                entity   text not null unique,
-                contents blob not null
-            );"
-            ))
-            .map_err(AccessError::implementation)?;
+        assert!(table_names(&conn).contains(&"eci_debug_component_a".to_string()));
+        assert!(!table_names(&conn).contains(&"DebugComponentA".to_string()));
 
-            if tx
-                .execute(
-                    &format!("insert into {name} (entity, contents) values(:entity, :contents)"),
-                    params,
-                )
-                .map_err(AccessError::implementation)?
-                != 1
-            {
-                return Err(AccessError::Conflict(entity, name.to_string()));
-            };
-        }
+        let components: Vec<Option<SerializedComponent<Json>>> = conn
+            .read_components(
+                entity,
+                vec![ExtractionDescriptor {
+                    name: "DebugComponentA".to_string(),
+                    version: None,
+                    schema: None,
+                }],
+            )
+            .unwrap();
+
+        let component = components[0].as_ref().unwrap();
+        assert_eq!(component.name, "DebugComponentA");
+        assert_eq!(a, Json::deserialize(&component.contents).unwrap());
 
-        tx.commit().map_err(AccessError::implementation)?;
-        Ok(())
+        let naming = SnakeCasePrefixed::new("eci_");
+        assert_eq!(
+            naming.table_name("HTTPServer2Config"),
+            "eci_http_server2_config"
+        );
+        assert_eq!(naming.table_name("Health"), "eci_health");
     }
 
-    fn read_components(
-        &self,
-        entity: eci_core::Entity,
-        descriptors: Vec<ExtractionDescriptor>,
-    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
-        let mut conn = self.0.get().map_err(AccessError::implementation)?;
-        let tx = conn.transaction().map_err(AccessError::implementation)?;
+    #[test]
+    fn reject_changed_naming() {
+        let path = std::env::temp_dir().join(format!("eci-naming-{}.db", Entity::new()));
 
-        let mut components = Vec::new();
-        for descriptor in descriptors {
-            let name = descriptor.name;
+        let conn = SqliteBackend::file(&path).unwrap();
+        conn.write_components(
+            Entity::new(),
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(DebugComponentA {
+                    content: "Hello".to_string(),
+                })
+                .unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+        drop(conn);
 
-            let params = named_params! {
-                ":entity": entity.to_string(),
-            };
+        let result = SqliteBackend::file(&path)
+            .unwrap()
+            .with_naming(SnakeCasePrefixed::new("eci_"));
+        std::fs::remove_file(&path).unwrap();
 
-            components.push(
-                tx.query_row(
-                    &format!(
-                        "
-                    select contents from {name} 
-                    where entity = :entity
-                "
-                    ),
-                    params,
-                    |row| {
-                        Ok(SerializedComponent::<F> {
-                            contents: F::Data::from(row.get(0)?),
-                            name,
-                        })
-                    },
-                )
-                .ok(),
-            );
+        match result {
+            Err(AccessError::Implementation(err)) => assert_eq!(
+                err.to_string(),
+                "database uses the verbatim naming strategy, but snake_case_prefixed:eci_ was configured"
+            ),
+            _ => panic!("expected the naming strategy change to be rejected"),
         }
-
-        Ok(components)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use eci_core::{
-        backend::{AccessBackend, ExtractionDescriptor, Format, SerializedComponent},
-        Entity,
-    };
-    use eci_format_json::Json;
-    use serde::{Deserialize, Serialize};
+    fn reject_hostile_component_names(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+        conn.write_components(entity, component_a("Hello")).unwrap();
 
-    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
-    struct DebugComponentA {
-        content: String,
-    }
+        fn invalid<T>(result: Result<T, AccessError>, name: &str) -> bool {
+            matches!(result, Err(AccessError::InvalidComponentName(invalid)) if invalid == name)
+        }
 
-    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
-    struct DebugComponentB {
-        content: String,
-    }
+        for name in [
+            "DebugComponentA; drop table locks;--",
+            "DebugComponentA where 1 = 1",
+            "Debug Component",
+            "\"DebugComponentA\"",
+            "1DebugComponent",
+            "",
+        ] {
+            let mut component = component_a("Hello");
+            component[0].name = name.to_string();
+            let descriptors = || {
+                vec![ExtractionDescriptor {
+                    name: name.to_string(),
+                    version: None,
+                    schema: None,
+                }]
+            };
 
-    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
-    struct DebugComponentC {
-        content: String,
-    }
+            assert!(invalid(conn.write_components(entity, component), name));
+            assert!(invalid(
+                AccessBackend::<Json>::read_components(&conn, entity, descriptors()),
+                name
+            ));
+            assert!(invalid(
+                AccessBackend::<Json>::remove_components(&conn, entity, descriptors()),
+                name
+            ));
+            assert!(invalid(
+                AccessBackend::<Json>::entities_with(&conn, name),
+                name
+            ));
+        }
 
-    use crate::SqliteBackend;
+        assert!(table_names(&conn).contains(&"locks".to_string()));
+        assert_eq!(
+            AccessBackend::<Json>::entities_with(&conn, "DebugComponentA").unwrap(),
+            vec![entity]
+        );
+    }
 
     #[test]
-    fn insert_disparate_components() {
-        let conn = SqliteBackend::memory().unwrap();
+    fn quote_table_names() {
+        let conn = SqliteBackend::memory()
+            .unwrap()
+            .with_naming(SnakeCasePrefixed::new("eci \"quoted\" "))
+            .unwrap();
         let entity = Entity::new();
 
-        conn.write_components(
-            entity,
-            vec![
-                SerializedComponent::<Json> {
-                    contents: Json::serialize(DebugComponentA {
-                        content: "Hello".to_string(),
-                    })
-                    .unwrap(),
-                    name: "DebugComponentA".to_string(),
-                },
-                SerializedComponent::<Json> {
-                    contents: Json::serialize(DebugComponentB {
-                        content: "Hello".to_string(),
-                    })
-                    .unwrap(),
-                    name: "DebugComponentB".to_string(),
-                },
-            ],
-        )
-        .unwrap();
+        conn.write_components(entity, component_a("Hello")).unwrap();
+        assert!(table_names(&conn).contains(&"eci \"quoted\" debug_component_a".to_string()));
+        assert_eq!(
+            AccessBackend::<Json>::entities_with(&conn, "DebugComponentA").unwrap(),
+            vec![entity]
+        );
     }
 
     #[test]
-    fn fail_on_duplicate_components() {
-        let conn = SqliteBackend::memory().unwrap();
-        let entity = Entity::new();
+    fn single_table_layout() {
+        let conn = SqliteBackend::memory_single_table()
+            .unwrap()
+            .with_naming(SnakeCasePrefixed::new("eci_"))
+            .unwrap();
+        let (a, b) = (Entity::new(), Entity::new());
 
-        conn.write_components(
-            entity,
-            vec![
-                SerializedComponent::<Json> {
-                    contents: Json::serialize(DebugComponentA {
-                        content: "Hello".to_string(),
-                    })
-                    .unwrap(),
-                    name: "DebugComponentA".to_string(),
-                },
-                SerializedComponent::<Json> {
-                    contents: Json::serialize(DebugComponentA {
-                        content: "Hello".to_string(),
-                    })
-                    .unwrap(),
-                    name: "DebugComponentA".to_string(),
-                },
-            ],
-        )
-        .unwrap_err();
+        conn.write_components(a, component_a("Hello")).unwrap();
+        conn.write_components(b, component_a("World")).unwrap();
+
+        assert_eq!(
+            table_names(&conn),
+            vec!["component_tables", "components", "locks", "metadata"]
+        );
+        assert_eq!(
+            AccessBackend::<Json>::entities_with(&conn, "DebugComponentA").unwrap(),
+            {
+                let mut entities = vec![a, b];
+                entities.sort();
+                entities
+            }
+        );
+        assert_eq!(
+            AccessBackend::<Json>::find_entities_where(
+                &conn,
+                vec![Field::new("DebugComponentA", "content").eq("World")]
+            )
+            .unwrap(),
+            vec![b]
+        );
     }
 
     #[test]
-    fn read_components() {
+    fn migrate_into_single_table() {
         let conn = SqliteBackend::memory().unwrap();
         let entity = Entity::new();
 
-        let a = DebugComponentA {
-            content: "Hello".to_string(),
-        };
-
         let b = DebugComponentB {
             content: "World".to_string(),
         };
-
+        conn.write_components(entity, component_a("Hello")).unwrap();
         conn.write_components(
             entity,
-            vec![
-                SerializedComponent::<Json> {
-                    contents: Json::serialize(&a).unwrap(),
-                    name: "DebugComponentA".to_string(),
-                },
-                SerializedComponent::<Json> {
-                    contents: Json::serialize(&b).unwrap(),
-                    name: "DebugComponentB".to_string(),
-                },
-            ],
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(&b).unwrap(),
+                name: "DebugComponentB".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
         )
         .unwrap();
 
-        let comps: Vec<Option<SerializedComponent<Json>>> = conn
+        let conn = conn.into_single_table().unwrap();
+        let components: Vec<Option<SerializedComponent<Json>>> = conn
             .read_components(
                 entity,
                 vec![
                     ExtractionDescriptor {
                         name: "DebugComponentA".to_string(),
+                        version: None,
+                        schema: None,
                     },
                     ExtractionDescriptor {
                         name: "DebugComponentB".to_string(),
+                        version: None,
+                        schema: None,
                     },
                 ],
             )
             .unwrap();
 
-        let ax = Json::deserialize(&comps[0].as_ref().unwrap().contents).unwrap();
-        let bx = Json::deserialize(&comps[1].as_ref().unwrap().contents).unwrap();
+        let a: DebugComponentA =
+            Json::deserialize(&components[0].as_ref().unwrap().contents).unwrap();
+        assert_eq!(a.content, "Hello");
+        assert_eq!(
+            b,
+            Json::deserialize(&components[1].as_ref().unwrap().contents).unwrap()
+        );
 
-        assert_eq!(a, ax);
-        assert_eq!(b, bx);
+        // Writes now go to the single table, leaving the old tables as they were.
+        conn.update_components(entity, component_a("Updated"))
+            .unwrap();
+        let count: i64 = conn
+            .pool
+            .get()
+            .unwrap()
+            .query_row(
+                "select count(*) from \"DebugComponentA\" where cast(contents as text) like '%Hello%'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
     }
 
-    #[test]
-    fn read_same_component_twice() {
-        // While not exactly useful, there's no real reason why you shouldn't be allowed to
-        // read the same component twice within a single query
-        let conn = SqliteBackend::memory().unwrap();
+    fn require_registration(memory: fn() -> SqliteBackend) {
+        let conn = memory().require_registration();
         let entity = Entity::new();
+        let tables = table_names(&conn);
 
-        let a = DebugComponentA {
-            content: "Hello".to_string(),
+        let unknown = |result: Result<(), AccessError>| matches!(result, Err(AccessError::UnknownComponent(component)) if component == "DebugComponentA");
+        assert!(unknown(conn.write_components(entity, component_a("Hello"))));
+        assert!(unknown(
+            conn.update_components(entity, component_a("Hello"))
+        ));
+        assert_eq!(table_names(&conn), tables);
+
+        conn.register_components(&["DebugComponentA"]).unwrap();
+        conn.write_components(entity, component_a("Hello")).unwrap();
+        conn.update_components(entity, component_a("World"))
+            .unwrap();
+    }
+
+    #[test]
+    fn registered_writes_skip_table_creation() {
+        let path = std::env::temp_dir().join(format!("eci-register-{}.db", Entity::new()));
+        let conn = SqliteBackend::file(&path).unwrap().require_registration();
+        conn.register_components(&["DebugComponentA"]).unwrap();
+
+        let schema = |conn: &SqliteBackend| -> i64 {
+            conn.pool
+                .get()
+                .unwrap()
+                .query_row("select count(*) from sqlite_master", [], |row| row.get(0))
+                .unwrap()
         };
+        let before = schema(&conn);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        conn.write_components(Entity::new(), component_a("Hello"))
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(schema(&conn), before);
+        assert_eq!(
+            AccessBackend::<Json>::entities_with(&conn, "DebugComponentA")
+                .unwrap()
+                .len(),
+            40
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    thread_local! {
+        static STATEMENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn trace(statement: &str) {
+        STATEMENTS.with(|statements| statements.borrow_mut().push(statement.to_string()));
+    }
+
+    fn read_in_one_statement(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
 
+        let b = DebugComponentB {
+            content: "World".to_string(),
+        };
+        conn.write_components(entity, component_a("Hello")).unwrap();
         conn.write_components(
             entity,
             vec![SerializedComponent::<Json> {
-                contents: Json::serialize(&a).unwrap(),
-                name: "DebugComponentA".to_string(),
+                contents: Json::serialize(&b).unwrap(),
+                name: "DebugComponentB".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
             }],
         )
         .unwrap();
 
-        let comps: Vec<Option<SerializedComponent<Json>>> = conn
+        // Pooled connections are reused most recently returned first, so
+        // the read runs on the traced connection.
+        conn.pool.get().unwrap().trace(Some(trace));
+
+        let descriptor = |name: &str| ExtractionDescriptor {
+            name: name.to_string(),
+            version: None,
+            schema: None,
+        };
+        let components: Vec<Option<SerializedComponent<Json>>> = conn
             .read_components(
                 entity,
                 vec![
-                    ExtractionDescriptor {
-                        name: "DebugComponentA".to_string(),
-                    },
-                    ExtractionDescriptor {
-                        name: "DebugComponentA".to_string(),
-                    },
+                    descriptor("DebugComponentB"),
+                    descriptor("DebugComponentC"),
+                    descriptor("DebugComponentA"),
+                    descriptor("DebugComponentB"),
                 ],
             )
             .unwrap();
 
-        let ax = Json::deserialize(&comps[0].as_ref().unwrap().contents).unwrap();
-        let bx = Json::deserialize(&comps[1].as_ref().unwrap().contents).unwrap();
+        conn.pool.get().unwrap().trace(None);
+        let reads = STATEMENTS.with(|statements| {
+            statements
+                .borrow()
+                .iter()
+                .filter(|statement| statement.contains("contents"))
+                .count()
+        });
+        assert_eq!(reads, 1);
 
-        assert_eq!(&a, &ax);
-        assert_eq!(&a, &bx);
+        let names: Vec<_> = components
+            .iter()
+            .map(|component| component.as_ref().map(|component| component.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                Some("DebugComponentB"),
+                None,
+                Some("DebugComponentA"),
+                Some("DebugComponentB")
+            ]
+        );
+        assert_eq!(
+            b,
+            Json::deserialize(&components[3].as_ref().unwrap().contents).unwrap()
+        );
+    }
+
+    /// The statements currently prepared on the pooled connection, by their
+    /// SQL, identified by their address.
+    fn prepared_statements(conn: &SqliteBackend) -> HashMap<String, usize> {
+        let conn = conn.pool.get().unwrap();
+        let mut statements = HashMap::new();
+
+        // Only the connection's own statements are visited, while it is
+        // checked out of the pool.
+        unsafe {
+            let db = conn.handle();
+            let mut statement = ffi::sqlite3_next_stmt(db, std::ptr::null_mut());
+            while !statement.is_null() {
+                let sql = CStr::from_ptr(ffi::sqlite3_sql(statement));
+                statements.insert(sql.to_string_lossy().into_owned(), statement as usize);
+                statement = ffi::sqlite3_next_stmt(db, statement);
+            }
+        }
+
+        statements
+    }
+
+    fn reuse_prepared_statements(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let descriptors = || {
+            vec![ExtractionDescriptor {
+                name: "DebugComponentA".to_string(),
+                version: None,
+                schema: None,
+            }]
+        };
+
+        let roundtrip = || {
+            let entity = Entity::new();
+            conn.write_components(entity, component_a("Hello")).unwrap();
+            conn.update_components(entity, component_a("World"))
+                .unwrap();
+            AccessBackend::<Json>::read_components(&conn, entity, descriptors()).unwrap();
+            let lock = lock_a(&conn, entity, LockingMode::Write);
+            conn.release_lock(lock).unwrap();
+            AccessBackend::<Json>::remove_components(&conn, entity, descriptors()).unwrap();
+
+            prepared_statements(&conn)
+        };
+
+        let first = roundtrip();
+        assert!(first.keys().any(|sql| sql.starts_with("insert into")));
+        assert!(first
+            .keys()
+            .any(|sql| sql.starts_with("select 0, contents")));
+
+        // The same statements are used again, rather than new ones prepared.
+        assert_eq!(roundtrip(), first);
+    }
+
+    fn component_a(content: &str) -> Vec<SerializedComponent<Json>> {
+        vec![SerializedComponent::<Json> {
+            contents: Json::serialize(DebugComponentA {
+                content: content.to_string(),
+            })
+            .unwrap(),
+            name: "DebugComponentA".to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        }]
+    }
+
+    fn lock_a<L: LockingBackend>(conn: &L, entity: Entity, mode: LockingMode) -> Lock {
+        conn.acquire_lock(
+            entity,
+            vec![LockDescriptor {
+                mode,
+                name: "DebugComponentA".to_string(),
+            }],
+            std::time::Duration::from_secs(60).into(),
+        )
+        .unwrap()
+    }
+
+    fn is_lock_required(result: Result<(), AccessError>) -> bool {
+        matches!(result, Err(AccessError::LockRequired(_, component)) if component == "DebugComponentA")
+    }
+
+    fn reject_empty_requests(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let entity = Entity::new();
+
+        assert!(matches!(
+            conn.write_components(entity, Vec::<SerializedComponent<Json>>::new()),
+            Err(AccessError::EmptyRequest)
+        ));
+        assert!(matches!(
+            AccessBackend::<Json>::read_components(&conn, entity, vec![]),
+            Err(AccessError::EmptyRequest)
+        ));
+
+        let lock = lock_a(&conn, entity, LockingMode::Write);
+        assert!(matches!(
+            conn.write_components_locked(entity, Vec::<SerializedComponent<Json>>::new(), &lock),
+            Err(AccessError::EmptyRequest)
+        ));
+    }
+
+    fn locked_write_requires_write_lock(memory: fn() -> SqliteBackend) {
+        let conn = memory();
+        let (a, b) = (Entity::new(), Entity::new());
+
+        // Locks which are unrelated, read-only or expired do not count.
+        let read = lock_a(&conn, a, LockingMode::Read);
+        assert!(is_lock_required(conn.write_components_locked(
+            a,
+            component_a("Hello"),
+            &read
+        )));
+        let other = lock_a(&conn, b, LockingMode::Write);
+        assert!(is_lock_required(conn.write_components_locked(
+            a,
+            component_a("Hello"),
+            &other
+        )));
+        assert!(is_lock_required(conn.write_components_locked(
+            a,
+            component_a("Hello"),
+            &Lock::new()
+        )));
+        conn.release_lock(read).unwrap();
+
+        let write = lock_a(&conn, a, LockingMode::Write);
+        conn.write_components_locked(a, component_a("Hello"), &write)
+            .unwrap();
+        conn.release_lock(write).unwrap();
+
+        // Entity-wide locks cover every component.
+        conn.release_lock(other).unwrap();
+        let entity_lock = conn
+            .acquire_lock(
+                b,
+                vec![LockDescriptor::entity()],
+                std::time::Duration::from_secs(60).into(),
+            )
+            .unwrap();
+        conn.write_components_locked(b, component_a("Hello"), &entity_lock)
+            .unwrap();
+        conn.release_lock(entity_lock).unwrap();
+    }
+
+    fn mandatory_locking(memory: fn() -> SqliteBackend) {
+        let backend = Backend::<Json>::from_joint(memory()).with_mandatory_locking();
+        let entity = Entity::new();
+
+        assert!(is_lock_required(
+            backend.write_components(entity, component_a("Hello"))
+        ));
+
+        let lock = backend
+            .acquire_lock(
+                entity,
+                vec![LockDescriptor {
+                    mode: LockingMode::Write,
+                    name: "DebugComponentA".to_string(),
+                }],
+                std::time::Duration::from_secs(60).into(),
+            )
+            .unwrap();
+        backend
+            .write_components_locked(entity, component_a("Hello"), &lock)
+            .unwrap();
+
+        // Locking stays advisory unless mandatory locking is enabled.
+        let advisory = Backend::<Json>::from_joint(memory());
+        advisory
+            .write_components(Entity::new(), component_a("Hello"))
+            .unwrap();
+    }
+
+    fn descriptor_a() -> Vec<ExtractionDescriptor> {
+        vec![ExtractionDescriptor {
+            name: "DebugComponentA".to_string(),
+            version: None,
+            schema: None,
+        }]
+    }
+
+    fn mandatory_locking_of_updates_and_removals(memory: fn() -> SqliteBackend) {
+        let joint = Backend::<Json>::from_joint(memory()).with_mandatory_locking();
+        // Separate locking backends have the locks looked up before writing.
+        let disjoint = Backend::<Json>::from_disjoint(memory(), memory()).with_mandatory_locking();
+
+        for backend in [joint, disjoint] {
+            let entity = Entity::new();
+            assert!(is_lock_required(
+                backend.update_components(entity, component_a("Hello"))
+            ));
+
+            let read = lock_a(&backend, entity, LockingMode::Read);
+            assert!(is_lock_required(backend.update_components_locked(
+                entity,
+                component_a("Hello"),
+                &read
+            )));
+            backend.release_lock(read).unwrap();
+
+            let write = lock_a(&backend, entity, LockingMode::Write);
+            backend
+                .update_components_locked(entity, component_a("Hello"), &write)
+                .unwrap();
+
+            assert!(matches!(
+                backend.remove_components(entity, descriptor_a()),
+                Err(AccessError::LockRequired(_, component)) if component == "DebugComponentA"
+            ));
+            assert!(matches!(
+                backend.remove_components_locked(entity, descriptor_a(), &Lock::new()),
+                Err(AccessError::LockRequired(_, component)) if component == "DebugComponentA"
+            ));
+
+            let removed = backend
+                .remove_components_locked(entity, descriptor_a(), &write)
+                .unwrap();
+            assert!(removed[0].is_some());
+            backend.release_lock(write).unwrap();
+        }
     }
 }
@@ -0,0 +1,248 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use eci_core::backend::AccessError;
+use rusqlite::{backup::Backup, Connection, OpenFlags};
+
+use crate::SqliteBackend;
+
+/// Number of pages copied at a time. The database is only locked while
+/// copying a step, so writers get a chance to proceed in between.
+const PAGES_PER_STEP: i32 = 64;
+
+/// Pause between two steps, during which writers may proceed.
+const PAUSE_BETWEEN_STEPS: Duration = Duration::from_millis(5);
+
+/// Columns every lock table has.
+const LOCK_COLUMNS: &[&str] = &[
+    "lockid",
+    "entity",
+    "component",
+    "locktype",
+    "expires",
+    "owner",
+];
+
+/// Backing up or restoring the database failed.
+#[derive(Debug)]
+pub enum BackupError {
+    /// Copying the database to or from the file failed.
+    Copy {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+    /// The restored database lacks columns of the lock table, so it was
+    /// most likely not written by this backend.
+    InvalidLockTable { path: PathBuf, missing: Vec<String> },
+}
+
+impl Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupError::Copy { path, source } => {
+                write!(f, "copying database with {}: {source}", path.display())
+            }
+            BackupError::InvalidLockTable { path, missing } => write!(
+                f,
+                "database restored from {} lacks lock table columns {}",
+                path.display(),
+                missing.join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for BackupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BackupError::Copy { source, .. } => Some(source),
+            BackupError::InvalidLockTable { .. } => None,
+        }
+    }
+}
+
+fn copy(from: &Connection, to: &mut Connection, path: &Path) -> Result<(), AccessError> {
+    let copy_error = |source| {
+        AccessError::implementation(BackupError::Copy {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+
+    Backup::new(from, to)
+        .map_err(copy_error)?
+        .run_to_completion(PAGES_PER_STEP, PAUSE_BETWEEN_STEPS, None)
+        .map_err(copy_error)
+}
+
+fn check_lock_table(conn: &Connection, path: &Path) -> Result<(), AccessError> {
+    let mut statement = conn
+        .prepare("select name from pragma_table_info('locks')")
+        .map_err(AccessError::implementation)?;
+
+    let columns = statement
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(AccessError::implementation)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(AccessError::implementation)?;
+
+    let missing: Vec<String> = LOCK_COLUMNS
+        .iter()
+        .filter(|column| !columns.iter().any(|existing| existing == *column))
+        .map(|column| column.to_string())
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(AccessError::implementation(BackupError::InvalidLockTable {
+            path: path.to_path_buf(),
+            missing,
+        }))
+    }
+}
+
+impl SqliteBackend {
+    /// Copies the database to the file at `path`, replacing its contents.
+    /// The copy is made a few pages at a time, so writers are only held up
+    /// briefly, and is consistent as of when it completes.
+    pub fn backup_to<P: AsRef<Path>>(&self, path: P) -> Result<(), AccessError> {
+        let path = path.as_ref();
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        let mut backup = Connection::open(path).map_err(|source| {
+            AccessError::implementation(BackupError::Copy {
+                path: path.to_path_buf(),
+                source,
+            })
+        })?;
+
+        copy(&conn, &mut backup, path)
+    }
+
+    /// Replaces the contents of the database with the backup at `path`,
+    /// failing with [`BackupError::InvalidLockTable`] if the restored
+    /// database does not have the lock table of this backend. The restored
+    /// contents are left in place even then.
+    pub fn restore_from<P: AsRef<Path>>(&self, path: P) -> Result<(), AccessError> {
+        let path = path.as_ref();
+        let backup = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(
+            |source| {
+                AccessError::implementation(BackupError::Copy {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            },
+        )?;
+
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        copy(&backup, &mut conn, path)?;
+
+        // Tables registered before restoring may no longer exist.
+        self.registered.write().unwrap().clear();
+
+        check_lock_table(&conn, path)
+    }
+
+    /// Rebuilds the database file, reclaiming the space left behind by
+    /// removed components and locks.
+    pub fn vacuum(&self) -> Result<(), AccessError> {
+        self.retry(|| {
+            self.pool
+                .get()
+                .map_err(AccessError::implementation)?
+                .execute_batch("vacuum")
+                .map_err(AccessError::implementation)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_core::{
+        backend::{AccessBackend, AccessError, Format, SerializedComponent},
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::SqliteBackend;
+
+    use super::BackupError;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    fn write(backend: &SqliteBackend, entity: Entity) {
+        AccessBackend::<Json>::write_components(
+            backend,
+            entity,
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(DebugComponentA {
+                    content: entity.to_string(),
+                })
+                .unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+    }
+
+    fn entities(backend: &SqliteBackend) -> Vec<Entity> {
+        let mut entities = AccessBackend::<Json>::list_entities(backend).unwrap();
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn restore_entities_written_before_backup() {
+        let path = std::env::temp_dir().join(format!("eci-backup-{}.db", Entity::new()));
+        let backend = SqliteBackend::memory().unwrap();
+
+        let mut before = vec![Entity::new(), Entity::new()];
+        before.sort();
+        for entity in &before {
+            write(&backend, *entity);
+        }
+
+        backend.backup_to(&path).unwrap();
+        write(&backend, Entity::new());
+        assert_eq!(entities(&backend).len(), 3);
+
+        backend.restore_from(&path).unwrap();
+        assert_eq!(entities(&backend), before);
+
+        // The restored database can be written to as before.
+        write(&backend, Entity::new());
+        backend.vacuum().unwrap();
+        assert_eq!(entities(&backend).len(), 3);
+    }
+
+    #[test]
+    fn reject_restoring_foreign_databases() {
+        let path = std::env::temp_dir().join(format!("eci-backup-{}.db", Entity::new()));
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch("create table unrelated (value text)")
+            .unwrap();
+
+        let backend = SqliteBackend::memory().unwrap();
+        match backend.restore_from(&path) {
+            Err(AccessError::Implementation(err)) => match err.downcast_ref::<BackupError>() {
+                Some(BackupError::InvalidLockTable { missing, .. }) => {
+                    assert_eq!(missing.len(), 6)
+                }
+                other => panic!("expected an invalid lock table, got {other:?}"),
+            },
+            other => panic!("expected an invalid lock table, got {other:?}"),
+        }
+    }
+}
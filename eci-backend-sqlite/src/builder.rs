@@ -0,0 +1,348 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::Connection;
+use uuid::Uuid;
+
+use crate::{layout::Layout, statements, BusyRetry, SqliteBackend};
+
+/// Sqlite's `journal_mode` pragma.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    /// Lets readers proceed while a write is in progress, which suits
+    /// multi-threaded workloads. Only applies to file-backed databases.
+    Wal,
+    Off,
+}
+
+impl Display for JournalMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JournalMode::Delete => write!(f, "delete"),
+            JournalMode::Truncate => write!(f, "truncate"),
+            JournalMode::Persist => write!(f, "persist"),
+            JournalMode::Memory => write!(f, "memory"),
+            JournalMode::Wal => write!(f, "wal"),
+            JournalMode::Off => write!(f, "off"),
+        }
+    }
+}
+
+/// Sqlite's `synchronous` pragma.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl Display for Synchronous {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Synchronous::Off => write!(f, "off"),
+            Synchronous::Normal => write!(f, "normal"),
+            Synchronous::Full => write!(f, "full"),
+            Synchronous::Extra => write!(f, "extra"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Location {
+    Memory,
+    File(PathBuf),
+}
+
+/// Settings applied to every pooled connection as it is opened.
+#[derive(Debug, Clone, Copy, Default)]
+struct Pragmas {
+    busy_timeout: Option<Duration>,
+    journal_mode: Option<JournalMode>,
+    synchronous: Option<Synchronous>,
+}
+
+impl Pragmas {
+    fn apply(&self, conn: &mut Connection) -> Result<(), rusqlite::Error> {
+        statements::init(conn)?;
+
+        if let Some(timeout) = self.busy_timeout {
+            conn.busy_timeout(timeout)?;
+        }
+
+        // Setting the journal mode reports the resulting mode back.
+        if let Some(mode) = self.journal_mode {
+            conn.pragma_update_and_check(None, "journal_mode", mode.to_string(), |_| Ok(()))?;
+        }
+
+        if let Some(synchronous) = self.synchronous {
+            conn.pragma_update(None, "synchronous", synchronous.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Configures the connection pool and pragmas of a [`SqliteBackend`]. Those
+/// building their own pool can convert it with [`SqliteBackend::try_from`]
+/// instead.
+#[derive(Debug, Clone)]
+pub struct SqliteBackendBuilder {
+    location: Location,
+    layout: Layout,
+    max_connections: Option<u32>,
+    pragmas: Pragmas,
+    retry: BusyRetry,
+}
+
+impl SqliteBackendBuilder {
+    /// An in-memory database shared by all pooled connections, which is
+    /// gone once the backend is dropped, so these are mostly useful for
+    /// tests. Every backend built gets a database of its own.
+    pub fn memory() -> Self {
+        Self::new(Location::Memory)
+    }
+
+    pub fn file<P: AsRef<Path>>(path: P) -> Self {
+        Self::new(Location::File(path.as_ref().to_path_buf()))
+    }
+
+    fn new(location: Location) -> Self {
+        SqliteBackendBuilder {
+            location,
+            layout: Layout::TablePerComponent,
+            max_connections: None,
+            pragmas: Pragmas::default(),
+            retry: BusyRetry::default(),
+        }
+    }
+
+    /// Stores all components in a single table rather than one table per
+    /// component type.
+    pub fn single_table(mut self) -> Self {
+        self.layout = Layout::SingleTable;
+        self
+    }
+
+    pub fn max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections = Some(max_connections);
+        self
+    }
+
+    /// How long to wait on other connections holding the database locked
+    /// before giving up.
+    pub fn busy_timeout(mut self, timeout: Duration) -> Self {
+        self.pragmas.busy_timeout = Some(timeout);
+        self
+    }
+
+    pub fn journal_mode(mut self, mode: JournalMode) -> Self {
+        self.pragmas.journal_mode = Some(mode);
+        self
+    }
+
+    pub fn synchronous(mut self, synchronous: Synchronous) -> Self {
+        self.pragmas.synchronous = Some(synchronous);
+        self
+    }
+
+    /// How transactions are retried when the database is busy, on top of
+    /// the busy timeout. Use [`BusyRetry::never`] to fail right away.
+    pub fn retry(mut self, policy: BusyRetry) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    pub fn build(self) -> Result<SqliteBackend, r2d2::Error> {
+        // A plain in-memory connection opens a private database, so
+        // every pooled connection would see a different one.
+        self.build_with(format!(
+            "file:eci-{}?mode=memory&cache=shared",
+            Uuid::new_v4()
+        ))
+    }
+
+    /// Builds the backend, opening the shared in-memory database at `uri`
+    /// if it is kept in memory.
+    fn build_with(self, uri: String) -> Result<SqliteBackend, r2d2::Error> {
+        let pragmas = self.pragmas;
+        let manager = match &self.location {
+            Location::Memory => SqliteConnectionManager::file(&uri),
+            Location::File(path) => SqliteConnectionManager::file(path),
+        }
+        .with_init(move |conn| pragmas.apply(conn));
+
+        // Pooled connections are never closed for being idle or old, though
+        // the in-memory database outlives them regardless.
+        let mut pool = Pool::builder().idle_timeout(None).max_lifetime(None);
+        if let Some(max_connections) = self.max_connections {
+            pool = pool.max_size(max_connections);
+        }
+        let pool = pool.build(manager)?;
+
+        // Sqlite discards a shared in-memory database along with its last
+        // connection, so one is kept open for as long as the backend lives.
+        // The pool has just opened the same database, so this does too.
+        let memory = match self.location {
+            Location::Memory => Some(Mutex::new(Connection::open(&uri).unwrap())),
+            Location::File(_) => None,
+        };
+
+        Ok(SqliteBackend {
+            retry: self.retry,
+            _memory: memory,
+            ..SqliteBackend::with_layout(pool, self.layout)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_core::{
+        backend::{AccessBackend, ExtractionDescriptor, SerializedComponent},
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use r2d2::Pool;
+    use r2d2_sqlite::SqliteConnectionManager;
+
+    use super::{JournalMode, SqliteBackendBuilder, Synchronous};
+
+    #[test]
+    fn apply_pragmas_to_pooled_connections() {
+        let path = std::env::temp_dir().join(format!("eci-builder-{}.db", Entity::new()));
+        let backend = SqliteBackendBuilder::file(&path)
+            .max_connections(4)
+            .busy_timeout(Duration::from_secs(2))
+            .journal_mode(JournalMode::Wal)
+            .synchronous(Synchronous::Normal)
+            .build()
+            .unwrap();
+
+        assert_eq!(backend.pool.max_size(), 4);
+
+        // Hold on to every connection, so each of them is checked.
+        let connections: Vec<_> = (0..4).map(|_| backend.pool.get().unwrap()).collect();
+        for conn in &connections {
+            let journal_mode: String = conn
+                .query_row("pragma journal_mode", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(journal_mode, "wal");
+
+            let synchronous: i64 = conn
+                .query_row("pragma synchronous", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(synchronous, 1);
+
+            let busy_timeout: i64 = conn
+                .query_row("pragma busy_timeout", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(busy_timeout, 2000);
+        }
+    }
+
+    #[test]
+    fn share_memory_between_pooled_connections() {
+        let backend = SqliteBackendBuilder::memory()
+            .max_connections(2)
+            .build()
+            .unwrap();
+
+        let first = backend.pool.get().unwrap();
+        let second = backend.pool.get().unwrap();
+
+        first
+            .execute_batch("create table shared (value text); insert into shared values ('seen')")
+            .unwrap();
+
+        let value: String = second
+            .query_row("select value from shared", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, "seen");
+
+        // Separately built backends are kept apart.
+        let other = SqliteBackendBuilder::memory().build().unwrap();
+        let tables: i64 = other
+            .pool
+            .get()
+            .unwrap()
+            .query_row(
+                "select count(*) from sqlite_master where name = 'shared'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    #[test]
+    fn keep_memory_without_pooled_connections() {
+        let uri = format!("file:eci-{}?mode=memory&cache=shared", Entity::new());
+        let mut backend = SqliteBackendBuilder::memory()
+            .build_with(uri.clone())
+            .unwrap();
+
+        let entity = Entity::new();
+        backend
+            .write_components(
+                entity,
+                vec![SerializedComponent::<Json> {
+                    contents: br#""Hello""#.to_vec(),
+                    name: "DebugComponentA".to_string(),
+                    version: Version::default(),
+                    schema: None,
+                    metadata: None,
+                }],
+            )
+            .unwrap();
+
+        // Swapping out the pool closes every connection it had open, as the
+        // pool would itself if it were to close idle connections.
+        backend.pool = Pool::new(SqliteConnectionManager::memory()).unwrap();
+        backend.pool = Pool::new(SqliteConnectionManager::file(&uri)).unwrap();
+
+        let read: Vec<Option<SerializedComponent<Json>>> = backend
+            .read_components(
+                entity,
+                vec![ExtractionDescriptor {
+                    name: "DebugComponentA".to_string(),
+                    version: None,
+                    schema: None,
+                }],
+            )
+            .unwrap();
+        assert_eq!(read[0].as_ref().unwrap().contents, br#""Hello""#);
+    }
+
+    #[test]
+    fn build_single_table_backend() {
+        let backend = SqliteBackendBuilder::memory()
+            .single_table()
+            .build()
+            .unwrap();
+
+        let tables: i64 = backend
+            .pool
+            .get()
+            .unwrap()
+            .query_row(
+                "select count(*) from sqlite_master where type = 'table' and name = 'components'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 1);
+    }
+}
@@ -0,0 +1,160 @@
+use eci_core::{
+    backend::{
+        AccessError, BackendError, Expiry, ExtractionDescriptor, Format, JointBackend, Lock,
+        LockDescriptor, LockedRead, LockingError,
+    },
+    Entity,
+};
+use log::*;
+
+use crate::{access, lock, metadata, retry::write_transaction, SqliteBackend};
+
+impl<F: Format> JointBackend<F> for SqliteBackend {
+    /// Takes the locks and reads the components in the same transaction,
+    /// so the components read are exactly those locked.
+    fn read_and_lock(
+        &self,
+        entity: Entity,
+        locks: Vec<LockDescriptor>,
+        components: Vec<ExtractionDescriptor>,
+        expires: Expiry,
+    ) -> Result<LockedRead<F>, BackendError> {
+        if locks.is_empty() {
+            return Err(LockingError::EmptyRequest.into());
+        }
+
+        if components.is_empty() {
+            return Err(AccessError::EmptyRequest.into());
+        }
+
+        let tables = components
+            .iter()
+            .map(|descriptor| self.table(&descriptor.name))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let lock = Lock::new();
+        let expires = lock::expiry(expires)?;
+
+        let read = self.retry(|| -> Result<_, BackendError> {
+            let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+
+            debug!("starting read and lock transaction for lock {lock}");
+            let tx = write_transaction(&mut conn).map_err(LockingError::implementation)?;
+            metadata::check_naming(&tx, self.naming.as_ref())?;
+
+            self.insert_locks(&tx, &lock, entity, &locks, expires)?;
+            let contents = self.read_contents(&tx, entity, &tables)?;
+
+            // Components stored with the wrong version roll back the locks
+            // along with the rest of the transaction.
+            let read = access::serialized(&components, &tables, &contents)?;
+
+            tx.commit().map_err(LockingError::implementation)?;
+            debug!("lock {lock} transaction committed");
+            Ok(read)
+        })?;
+
+        Ok((lock::with_expiry(lock, expires), read))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, BackendError, ExtractionDescriptor, Format, JointBackend,
+            LockDescriptor, LockingBackend, LockingError, LockingMode, SerializedComponent,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::SqliteBackend;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    fn lock(mode: LockingMode) -> Vec<LockDescriptor> {
+        vec![LockDescriptor {
+            mode,
+            name: "DebugComponentA".to_string(),
+        }]
+    }
+
+    fn extract(version: Option<Version>) -> Vec<ExtractionDescriptor> {
+        vec![ExtractionDescriptor {
+            name: "DebugComponentA".to_string(),
+            version,
+            schema: None,
+        }]
+    }
+
+    #[test]
+    fn read_and_lock_in_one_transaction() {
+        let conn = SqliteBackend::memory().unwrap();
+        let entity = Entity::new();
+        AccessBackend::<Json>::write_components(
+            &conn,
+            entity,
+            vec![SerializedComponent::<Json> {
+                contents: Json::serialize(DebugComponentA {
+                    content: "Hello".to_string(),
+                })
+                .unwrap(),
+                name: "DebugComponentA".to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+
+        let (held, components) = JointBackend::<Json>::read_and_lock(
+            &conn,
+            entity,
+            lock(LockingMode::Write),
+            extract(Some(Version::default())),
+            Duration::from_secs(60).into(),
+        )
+        .unwrap();
+
+        let component = components.into_iter().next().flatten().unwrap();
+        assert_eq!(
+            Json::deserialize::<DebugComponentA>(&component.contents).unwrap(),
+            DebugComponentA {
+                content: "Hello".to_string()
+            }
+        );
+
+        // Conflicting locks fail without reading.
+        assert!(matches!(
+            JointBackend::<Json>::read_and_lock(
+                &conn,
+                entity,
+                lock(LockingMode::Read),
+                extract(None),
+                Duration::from_secs(60).into(),
+            ),
+            Err(BackendError::Locking(LockingError::Conflict(..)))
+        ));
+        conn.release_lock(held).unwrap();
+
+        // Failing to read rolls back the lock taken along with it.
+        assert!(matches!(
+            JointBackend::<Json>::read_and_lock(
+                &conn,
+                entity,
+                lock(LockingMode::Write),
+                extract(Some(Version::new(2, 0, 0))),
+                Duration::from_secs(60).into(),
+            ),
+            Err(BackendError::Access(AccessError::VersionMismatch { .. }))
+        ));
+        assert!(conn.list_locks(Some(entity)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,520 @@
+use std::sync::RwLock;
+
+use eci_core::{backend::AccessError, Version};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{named_params, params_from_iter, Connection};
+
+use crate::{registry, retry::write_transaction, statements::CachedStatements, SqliteBackend};
+
+/// Tables used by the backend itself, which never hold components.
+const INTERNAL_TABLES: &[&str] = &["locks", "metadata", "components", "component_tables"];
+
+/// Sqlite's default limit on the number of selects in a compound statement.
+pub(crate) const MAX_COMPOUND_SELECT: usize = 500;
+
+/// How components are laid out in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Layout {
+    /// Every component type gets a table of its own, created on first write.
+    TablePerComponent,
+    /// All components share the `components` table, keyed by entity and name.
+    SingleTable,
+}
+
+/// Where a component is stored: either its own table, or its rows of the
+/// shared `components` table. Names are quoted when formatted into SQL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Table {
+    Dedicated(String),
+    Shared(String),
+}
+
+impl Table {
+    pub fn new(layout: Layout, name: String) -> Self {
+        match layout {
+            Layout::TablePerComponent => Table::Dedicated(name),
+            Layout::SingleTable => Table::Shared(name),
+        }
+    }
+
+    /// The name of the component's table, or its name within the shared
+    /// table.
+    pub fn name(&self) -> &str {
+        match self {
+            Table::Dedicated(name) | Table::Shared(name) => name,
+        }
+    }
+
+    /// Creates the component's table, if it has one of its own, and records
+    /// the component in the registry.
+    pub fn create(&self, conn: &Connection, component: &str) -> Result<(), AccessError> {
+        if let Table::Dedicated(name) = self {
+            conn.execute_batch(&format!(
+                "
+                create table if not exists {} (
+                    entity     text not null unique,
+                    contents   blob not null,
+                    version    text not null,
+                    updated_at integer not null default 0,
+                    revision   integer not null default 0
+                );
+
+                {}",
+                identifier(name),
+                updated_at_index(name)
+            ))
+            .map_err(AccessError::implementation)?;
+        }
+
+        registry::record(conn, component, self)
+    }
+
+    pub fn exists(&self, conn: &Connection) -> Result<bool, AccessError> {
+        match self {
+            Table::Dedicated(name) => conn
+                .query_row_cached(
+                    "select count(*) from sqlite_master where type = 'table' and name = :table",
+                    named_params! { ":table": name },
+                    |row| row.get::<_, i64>(0),
+                )
+                .map(|count| count > 0)
+                .map_err(AccessError::implementation),
+            Table::Shared(_) => Ok(true),
+        }
+    }
+
+    /// Inserts `:entity`, `:contents`, `:version` and `:updated_at` as the
+    /// component's first revision, resolving conflicts with the given
+    /// `on conflict` action, if any.
+    pub fn insert(&self, on_conflict: Option<&str>) -> String {
+        let (statement, key) = match self {
+            Table::Dedicated(name) => (
+                format!(
+                    "insert into {} (entity, contents, version, updated_at, revision)
+                    values(:entity, :contents, :version, :updated_at, 1)",
+                    identifier(name)
+                ),
+                "entity",
+            ),
+            Table::Shared(name) => (
+                format!(
+                    "insert into components (entity, name, contents, version, updated_at, revision)
+                    values(:entity, {}, :contents, :version, :updated_at, 1)",
+                    literal(name)
+                ),
+                "entity, name",
+            ),
+        };
+
+        match on_conflict {
+            Some(action) => format!("{statement} on conflict({key}) {action}"),
+            None => statement,
+        }
+    }
+
+    /// Replaces the `:contents`, `:version` and `:updated_at` of the
+    /// component for `:entity`, if it is still at `:revision`.
+    pub fn update_if_unchanged(&self) -> String {
+        let (table, condition) = match self {
+            Table::Dedicated(name) => (identifier(name), String::new()),
+            Table::Shared(name) => (
+                "components".to_string(),
+                format!("name = {} and", literal(name)),
+            ),
+        };
+
+        format!(
+            "update {table}
+            set contents = :contents, version = :version, updated_at = :updated_at,
+                revision = revision + 1
+            where {condition} entity = :entity and revision = :revision"
+        )
+    }
+
+    /// Selects the revision of the component for `:entity`.
+    pub fn revision(&self) -> String {
+        format!(
+            "select revision from {} entity = :entity",
+            self.rows_where()
+        )
+    }
+
+    /// Selects the contents, version, time of the last write and revision
+    /// of the component for `:entity`.
+    pub fn select(&self) -> String {
+        format!(
+            "select contents, version, updated_at, revision from {} entity = :entity",
+            self.rows_where()
+        )
+    }
+
+    /// Deletes the component of `:entity`.
+    pub fn delete(&self) -> String {
+        format!("delete from {} entity = :entity", self.rows_where())
+    }
+
+    /// Selects every entity whose component was written at or after
+    /// `:since`, in order.
+    pub fn modified_since(&self) -> String {
+        format!(
+            "select entity from {} updated_at >= :since order by entity",
+            self.rows_where()
+        )
+    }
+
+    /// Selects every entity with the component, in order.
+    pub fn entities(&self) -> String {
+        match self {
+            Table::Dedicated(name) => {
+                format!("select entity from {} order by entity", identifier(name))
+            }
+            Table::Shared(name) => format!(
+                "select entity from components where name = {} order by entity",
+                literal(name)
+            ),
+        }
+    }
+
+    /// The component's rows under the given alias, along with the condition
+    /// restricting them to the component, if needed, for queries joining
+    /// several components.
+    pub fn rows(&self, alias: &str) -> (String, Option<String>) {
+        match self {
+            Table::Dedicated(name) => (format!("{} {alias}", identifier(name)), None),
+            Table::Shared(name) => (
+                format!("components {alias}"),
+                Some(format!("{alias}.name = {}", literal(name))),
+            ),
+        }
+    }
+
+    /// The table to read from, followed by an open `where` clause.
+    fn rows_where(&self) -> String {
+        match self {
+            Table::Dedicated(name) => format!("{} where", identifier(name)),
+            Table::Shared(name) => format!("components where name = {} and", literal(name)),
+        }
+    }
+}
+
+/// Selects the position of each table along with the contents, version,
+/// time of the last write and revision of `:entity`'s component in it, for
+/// all of the tables at once.
+pub(crate) fn select_each(tables: &[&Table]) -> String {
+    tables
+        .iter()
+        .enumerate()
+        .map(|(i, table)| {
+            format!(
+                "select {i}, contents, version, updated_at, revision from {} entity = :entity",
+                table.rows_where()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" union all ")
+}
+
+/// Selects the position of each table which has a row for `:entity`, for
+/// all of the tables at once.
+pub(crate) fn select_present(tables: &[&Table]) -> String {
+    tables
+        .iter()
+        .enumerate()
+        .map(|(i, table)| {
+            format!(
+                "select {i} where exists (select 1 from {} entity = :entity)",
+                table.rows_where()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" union all ")
+}
+
+/// Selects every entity with a component in any of the tables, in order.
+pub(crate) fn select_entities(tables: &[Table]) -> String {
+    let selects: Vec<String> = tables
+        .iter()
+        .map(|table| match table {
+            Table::Dedicated(name) => format!("select entity from {}", identifier(name)),
+            Table::Shared(name) => format!(
+                "select entity from components where name = {}",
+                literal(name)
+            ),
+        })
+        .collect();
+
+    format!("{} order by entity", selects.join(" union "))
+}
+
+/// Selects the name of each of the components which `:entity` has, in
+/// order, given the tables the components are stored in.
+pub(crate) fn select_components(components: &[(String, Table)]) -> String {
+    let selects: Vec<String> = components
+        .iter()
+        .map(|(component, table)| {
+            format!(
+                "select {} from {} entity = :entity",
+                literal(component),
+                table.rows_where()
+            )
+        })
+        .collect();
+
+    format!("{} order by 1", selects.join(" union all "))
+}
+
+/// Selects the name of each of the components along with the number of
+/// entities with it and the total length of their contents, in order.
+pub(crate) fn select_stats(components: &[(String, Table)]) -> String {
+    let selects: Vec<String> = components
+        .iter()
+        .map(|(component, table)| {
+            let (rows, condition) = table.rows("c");
+            format!(
+                "select {}, count(*), coalesce(sum(length(c.contents)), 0) from {rows}{}",
+                literal(component),
+                condition
+                    .map(|condition| format!(" where {condition}"))
+                    .unwrap_or_default()
+            )
+        })
+        .collect();
+
+    format!("{} order by 1", selects.join(" union all "))
+}
+
+/// Those of the tables which exist. Components in the shared table always
+/// have somewhere to be read from.
+pub(crate) fn existing_tables<'a>(
+    conn: &Connection,
+    tables: &[&'a Table],
+) -> Result<Vec<&'a Table>, AccessError> {
+    let dedicated: Vec<&str> = tables
+        .iter()
+        .filter_map(|table| match table {
+            Table::Dedicated(name) => Some(name.as_str()),
+            Table::Shared(_) => None,
+        })
+        .collect();
+
+    let created: Vec<String> = if dedicated.is_empty() {
+        Vec::new()
+    } else {
+        let mut statement = conn
+            .prepare_cached(&format!(
+                "select name from sqlite_master where type = 'table' and name in ({})",
+                vec!["?"; dedicated.len()].join(", ")
+            ))
+            .map_err(AccessError::implementation)?;
+
+        let created = statement
+            .query_map(params_from_iter(&dedicated), |row| row.get(0))
+            .map_err(AccessError::implementation)?
+            .collect::<Result<_, _>>()
+            .map_err(AccessError::implementation)?;
+        created
+    };
+
+    Ok(tables
+        .iter()
+        .copied()
+        .filter(|table| match table {
+            Table::Dedicated(name) => created.contains(name),
+            Table::Shared(_) => true,
+        })
+        .collect())
+}
+
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn literal(name: &str) -> String {
+    format!("'{}'", name.replace('\'', "''"))
+}
+
+pub(crate) fn create_components_table(
+    conn: &Pool<SqliteConnectionManager>,
+) -> Result<(), rusqlite::Error> {
+    conn.get().unwrap().execute_batch(
+        "
+        create table if not exists components (
+            entity     text not null,
+            name       text not null,
+            contents   blob not null,
+            version    text not null,
+            updated_at integer not null default 0,
+            revision   integer not null default 0
+        );
+
+        create unique index if not exists components_entity_name
+        on components (entity, name);
+    ",
+    )
+}
+
+/// Every table holding components of a single type, in order.
+fn component_tables(conn: &Connection) -> Result<Vec<String>, rusqlite::Error> {
+    let mut statement = conn.prepare(
+        "select name from sqlite_master
+        where type = 'table' and name not like 'sqlite_%'
+        order by name",
+    )?;
+
+    let tables = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter(|table| {
+            table
+                .as_ref()
+                .map_or(true, |table| !INTERNAL_TABLES.contains(&table.as_str()))
+        })
+        .collect();
+    tables
+}
+
+/// Components stored before versions were recorded lack the column, and
+/// are taken to be of the default version.
+pub(crate) fn add_version_columns(
+    conn: &Pool<SqliteConnectionManager>,
+) -> Result<(), rusqlite::Error> {
+    let conn = conn.get().unwrap();
+
+    let mut tables = component_tables(&conn)?;
+    tables.push("components".to_string());
+
+    for table in tables {
+        let (exists, has_version) = conn.query_row(
+            "select count(*) > 0, count(case when name = 'version' then 1 end) > 0
+            from pragma_table_info(:table)",
+            named_params! { ":table": table },
+            |row| Ok((row.get::<_, bool>(0)?, row.get::<_, bool>(1)?)),
+        )?;
+
+        if exists && !has_version {
+            conn.execute_batch(&format!(
+                "alter table {} add column version text not null default {}",
+                identifier(&table),
+                literal(&Version::default().to_string())
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Components stored before the time of their last write was recorded lack
+/// the column, and are taken to have been written at the epoch. Indexes the
+/// column of every table, so entities modified since a given time are found
+/// without scanning the whole table.
+pub(crate) fn add_updated_at_columns(
+    conn: &Pool<SqliteConnectionManager>,
+) -> Result<(), rusqlite::Error> {
+    let conn = conn.get().unwrap();
+
+    let mut tables = component_tables(&conn)?;
+    tables.push("components".to_string());
+
+    for table in tables {
+        let (exists, has_updated_at) = conn.query_row(
+            "select count(*) > 0, count(case when name = 'updated_at' then 1 end) > 0
+            from pragma_table_info(:table)",
+            named_params! { ":table": table },
+            |row| Ok((row.get::<_, bool>(0)?, row.get::<_, bool>(1)?)),
+        )?;
+
+        if !exists {
+            continue;
+        }
+
+        if !has_updated_at {
+            conn.execute_batch(&format!(
+                "alter table {} add column updated_at integer not null default 0",
+                identifier(&table)
+            ))?;
+        }
+
+        if table == "components" {
+            conn.execute_batch(
+                "create index if not exists components_name_updated_at
+                on components (name, updated_at)",
+            )?;
+        } else {
+            conn.execute_batch(&updated_at_index(&table))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Components stored before revisions were recorded lack the column, and
+/// are taken to have never been written since.
+pub(crate) fn add_revision_columns(
+    conn: &Pool<SqliteConnectionManager>,
+) -> Result<(), rusqlite::Error> {
+    let conn = conn.get().unwrap();
+
+    let mut tables = component_tables(&conn)?;
+    tables.push("components".to_string());
+
+    for table in tables {
+        let (exists, has_revision) = conn.query_row(
+            "select count(*) > 0, count(case when name = 'revision' then 1 end) > 0
+            from pragma_table_info(:table)",
+            named_params! { ":table": table },
+            |row| Ok((row.get::<_, bool>(0)?, row.get::<_, bool>(1)?)),
+        )?;
+
+        if exists && !has_revision {
+            conn.execute_batch(&format!(
+                "alter table {} add column revision integer not null default 0",
+                identifier(&table)
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the index on the time of the last write of a component with a
+/// table of its own.
+fn updated_at_index(table: &str) -> String {
+    format!(
+        "create index if not exists {} on {} (updated_at);",
+        identifier(&format!("{table}_updated_at")),
+        identifier(table)
+    )
+}
+
+impl SqliteBackend {
+    /// Switches the backend to the single-table layout, copying every
+    /// component stored in a table of its own into the shared table first.
+    /// The per-component tables are left in place, and can be dropped once
+    /// the copy has been verified.
+    pub fn into_single_table(mut self) -> Result<Self, AccessError> {
+        create_components_table(&self.pool).map_err(AccessError::implementation)?;
+
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        let tx = write_transaction(&mut conn).map_err(AccessError::implementation)?;
+
+        for table in component_tables(&tx).map_err(AccessError::implementation)? {
+            tx.execute(
+                &format!(
+                    "insert into components (entity, name, contents, version, updated_at, revision)
+                    select entity, {}, contents, version, updated_at, revision from {}",
+                    literal(&table),
+                    identifier(&table)
+                ),
+                [],
+            )
+            .map_err(AccessError::implementation)?;
+        }
+
+        tx.commit().map_err(AccessError::implementation)?;
+        drop(conn);
+
+        self.layout = Layout::SingleTable;
+        self.registered = RwLock::default();
+        Ok(self)
+    }
+}
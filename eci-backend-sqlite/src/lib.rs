@@ -1,34 +1,187 @@
 mod access;
+mod backup;
+mod builder;
+mod joint;
+mod layout;
 mod lock;
-use std::path::Path;
+mod metadata;
+mod registry;
+mod retry;
+mod statements;
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
+};
 
+use eci_core::{
+    backend::{AccessError, NamingStrategy, Verbatim},
+    Component,
+};
+use layout::{Layout, Table};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 
+pub use backup::BackupError;
+pub use builder::{JournalMode, SqliteBackendBuilder, Synchronous};
 pub use lock::SqliteLock;
+pub use metadata::NamingMismatch;
+pub use retry::BusyRetry;
 
-pub struct SqliteBackend(Pool<SqliteConnectionManager>);
+pub struct SqliteBackend {
+    pool: Pool<SqliteConnectionManager>,
+    naming: Arc<dyn NamingStrategy>,
+    layout: Layout,
+    /// Components whose tables were created through registration.
+    registered: RwLock<HashSet<Table>>,
+    /// Whether writing unregistered components is an error, rather than
+    /// creating their tables on the fly.
+    require_registration: bool,
+    owner: Option<String>,
+    retry: BusyRetry,
+    /// Keeps an in-memory database alive while no pooled connection is open.
+    _memory: Option<Mutex<rusqlite::Connection>>,
+}
 
 impl TryFrom<Pool<SqliteConnectionManager>> for SqliteBackend {
     type Error = rusqlite::Error;
     fn try_from(pool: Pool<SqliteConnectionManager>) -> Result<Self, Self::Error> {
         lock::create_lock_table(&pool)?;
-        Ok(SqliteBackend(pool))
+        metadata::create_metadata_table(&pool)?;
+        registry::create_registry_table(&pool)?;
+        layout::add_version_columns(&pool)?;
+        layout::add_updated_at_columns(&pool)?;
+        layout::add_revision_columns(&pool)?;
+        Ok(SqliteBackend::new(pool))
     }
 }
 
 impl SqliteBackend {
-    pub fn memory() -> Result<Self, r2d2::Error> {
-        let pool = r2d2::Pool::new(SqliteConnectionManager::memory())?;
+    fn new(pool: Pool<SqliteConnectionManager>) -> Self {
+        SqliteBackend {
+            pool,
+            naming: Arc::new(Verbatim),
+            layout: Layout::TablePerComponent,
+            registered: RwLock::default(),
+            require_registration: false,
+            owner: None,
+            retry: BusyRetry::default(),
+            _memory: None,
+        }
+    }
 
-        lock::create_lock_table(&pool).unwrap();
-        Ok(SqliteBackend(pool))
+    pub fn memory() -> Result<Self, r2d2::Error> {
+        SqliteBackendBuilder::memory().build()
     }
 
     pub fn file<P: AsRef<Path>>(path: P) -> Result<Self, r2d2::Error> {
-        let pool = r2d2::Pool::new(SqliteConnectionManager::file(path))?;
+        SqliteBackendBuilder::file(path).build()
+    }
+
+    /// Like [`SqliteBackend::memory`], but stores all components in a single
+    /// table rather than one table per component type.
+    pub fn memory_single_table() -> Result<Self, r2d2::Error> {
+        SqliteBackendBuilder::memory().single_table().build()
+    }
 
+    /// Like [`SqliteBackend::file`], but stores all components in a single
+    /// table rather than one table per component type.
+    pub fn file_single_table<P: AsRef<Path>>(path: P) -> Result<Self, r2d2::Error> {
+        SqliteBackendBuilder::file(path).single_table().build()
+    }
+
+    fn with_layout(
+        pool: Pool<SqliteConnectionManager>,
+        layout: Layout,
+    ) -> Result<Self, r2d2::Error> {
         lock::create_lock_table(&pool).unwrap();
-        Ok(SqliteBackend(pool))
+        metadata::create_metadata_table(&pool).unwrap();
+        registry::create_registry_table(&pool).unwrap();
+        if layout == Layout::SingleTable {
+            layout::create_components_table(&pool).unwrap();
+        }
+        layout::add_version_columns(&pool).unwrap();
+        layout::add_updated_at_columns(&pool).unwrap();
+        layout::add_revision_columns(&pool).unwrap();
+
+        Ok(SqliteBackend {
+            layout,
+            ..SqliteBackend::new(pool)
+        })
     }
-}
\ No newline at end of file
+
+    /// Sets the strategy used for deriving table names from component names.
+    /// Fails if the database has already been written using another strategy.
+    pub fn with_naming<N: NamingStrategy + 'static>(
+        mut self,
+        naming: N,
+    ) -> Result<Self, AccessError> {
+        let conn = self.pool.get().map_err(AccessError::implementation)?;
+        metadata::check_naming(&conn, &naming)?;
+        drop(conn);
+
+        self.naming = Arc::new(naming);
+        self.registered = RwLock::default();
+        Ok(self)
+    }
+
+    /// Creates the tables of the given components up front, so writing them
+    /// no longer involves creating tables inside the write transaction.
+    pub fn register_components(&self, components: &[&str]) -> Result<(), AccessError> {
+        let tables = components
+            .iter()
+            .map(|component| self.table(component))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+            let tx = retry::write_transaction(&mut conn).map_err(AccessError::implementation)?;
+            metadata::record_naming(&tx, self.naming.as_ref())?;
+
+            for (component, table) in components.iter().zip(&tables) {
+                table.create(&tx, component)?;
+            }
+
+            tx.commit().map_err(AccessError::implementation)
+        })?;
+
+        self.registered.write().unwrap().extend(tables);
+        Ok(())
+    }
+
+    pub fn register<T: Component>(&self) -> Result<(), AccessError> {
+        self.register_components(&[T::COMPONENT_TYPE])
+    }
+
+    /// Rejects writes of components which have not been registered through
+    /// [`SqliteBackend::register_components`] with
+    /// [`AccessError::UnknownComponent`], instead of creating their tables
+    /// on the fly.
+    pub fn require_registration(mut self) -> Self {
+        self.require_registration = true;
+        self
+    }
+
+    /// Records the owner, such as a hostname, process id or service name,
+    /// with every lock acquired through this backend, so others running
+    /// into the locks can tell who holds them.
+    pub fn with_owner<O: Into<String>>(mut self, owner: O) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    /// Hands a connection from the backend's pool to the closure, for
+    /// queries the backend has no methods for. The connection has the same
+    /// pragmas applied as any other.
+    ///
+    /// Nothing is locked on the closure's behalf: writing to component
+    /// tables directly bypasses the locks held by others, and skips the
+    /// bookkeeping of which components exist.
+    pub fn with_connection<R, F>(&self, f: F) -> Result<R, AccessError>
+    where
+        F: FnOnce(&mut rusqlite::Connection) -> rusqlite::Result<R>,
+    {
+        let mut conn = self.pool.get().map_err(AccessError::implementation)?;
+        f(&mut conn).map_err(AccessError::implementation)
+    }
+}
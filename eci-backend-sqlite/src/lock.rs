@@ -1,43 +1,65 @@
-use chrono::{Duration, Utc};
-use eci_core::backend::{Lock, LockDescriptor, LockingBackend, LockingError, LockingMode};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use eci_core::backend::{
+    ActiveLock, Administrative, BulkLockResult, ConflictingLock, Expiry, Lock, LockDescriptor,
+    LockStats, LockingBackend, LockingError, LockingMode, MAX_POLL_INTERVAL,
+};
 use log::*;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::named_params;
+use rusqlite::{named_params, Connection, OptionalExtension};
+use std::time::{Instant, SystemTime};
 use uuid::Uuid;
 
-use crate::SqliteBackend;
+use crate::{retry::write_transaction, statements::CachedStatements, SqliteBackend};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct SqliteLock(Uuid);
 
-const WRITE_LOCK: &'static str = "insert into locks
+const WRITE_LOCK: &str = "insert into locks (lockid, entity, component, locktype, expires, owner)
 select
     :lockid    as lockid,
     :entity    as entity,
     :component as component,
     'write'    as locktype,
-    :expires   as expires
+    :expires   as expires,
+    :owner     as owner
 where not exists(
     select entity from locks
     where entity   = :entity
-    and component  = :component
-    and datetime(current_timestamp) < datetime(expires)
+    and (component = :component or locktype = 'exclusive')
+    and julianday('now') < julianday(expires)
 );";
 
-const READ_LOCK: &'static str = "insert into locks
+const READ_LOCK: &str = "insert into locks (lockid, entity, component, locktype, expires, owner)
 select
     :lockid    as lockid,
     :entity    as entity,
     :component as component,
     'read'     as locktype,
-    :expires   as expires
+    :expires   as expires,
+    :owner     as owner
 where not exists(
     select entity from locks
-    where locktype = 'write'
-    and entity     = :entity
-    and component  = :component
-    and datetime(current_timestamp) < datetime(expires)
+    where entity   = :entity
+    and ((locktype = 'write' and component = :component) or locktype = 'exclusive')
+    and julianday('now') < julianday(expires)
+);";
+
+/// Entity-wide locks conflict with any lock on the entity, whatever the
+/// component.
+const EXCLUSIVE_LOCK: &str =
+    "insert into locks (lockid, entity, component, locktype, expires, owner)
+select
+    :lockid      as lockid,
+    :entity      as entity,
+    :component   as component,
+    'exclusive'  as locktype,
+    :expires     as expires,
+    :owner       as owner
+where not exists(
+    select entity from locks
+    where entity = :entity
+    and julianday('now') < julianday(expires)
 );";
 
 impl LockingBackend for SqliteBackend {
@@ -45,92 +67,606 @@ impl LockingBackend for SqliteBackend {
         &self,
         entity: eci_core::Entity,
         descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        if descriptors.is_empty() {
+            return Err(LockingError::EmptyRequest);
+        }
+
+        let lock = Lock::new();
+        let expires = expiry(expires)?;
+
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+
+            debug!("starting lock transaction for lock {lock}");
+            let tx = write_transaction(&mut conn).map_err(LockingError::implementation)?;
+
+            self.insert_locks(&tx, &lock, entity, &descriptors, expires)?;
+            tx.commit().map_err(LockingError::implementation)?;
+            debug!("lock {lock} transaction committed");
+            Ok(())
+        })?;
+
+        Ok(with_expiry(lock, expires))
+    }
+
+    fn acquire_locks(
+        &self,
+        requests: Vec<(eci_core::Entity, Vec<LockDescriptor>)>,
         expires_in: std::time::Duration,
     ) -> Result<Lock, LockingError> {
+        if requests.is_empty()
+            || requests
+                .iter()
+                .any(|(_, descriptors)| descriptors.is_empty())
+        {
+            return Err(LockingError::EmptyRequest);
+        }
+
         let lock = Lock::new();
+        let expires = expiry(Expiry::In(expires_in))?;
 
-        let mut conn = self.0.get().map_err(LockingError::implementation)?;
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(LockingError::implementation)?;
 
-        debug!("starting lock transaction for lock {lock}");
-        let tx = conn.transaction().map_err(LockingError::implementation)?;
+            debug!("starting multi-entity lock transaction for lock {lock}");
+            let tx = write_transaction(&mut conn).map_err(LockingError::implementation)?;
+
+            // Returning early drops the transaction, rolling back every lock
+            // acquired on the preceding entities.
+            for (entity, descriptors) in &requests {
+                self.insert_locks(&tx, &lock, *entity, descriptors, expires)?;
+            }
+
+            tx.commit().map_err(LockingError::implementation)?;
+            debug!("lock {lock} transaction committed");
+            Ok(())
+        })?;
+
+        Ok(with_expiry(lock, expires))
+    }
+
+    fn release_lock(&self, lock: Lock) -> Result<(), eci_core::backend::LockingError> {
+        debug!("releasing lock {lock}");
+
+        let locks_deleted = self.retry(|| {
+            let conn = self.pool.get().map_err(LockingError::implementation)?;
+            conn.execute_cached(
+                "delete from locks where lockid = :lockid",
+                named_params! { ":lockid": lock.id()},
+            )
+            .map_err(LockingError::implementation)
+        })?;
+
+        debug!("deleted locks on {locks_deleted} resources by releasing {lock}",);
+        Ok(())
+    }
+
+    /// Sleeps until the earliest conflicting lock expires rather than
+    /// polling at fixed intervals, though never longer than
+    /// [`MAX_POLL_INTERVAL`] at a time, so early releases are noticed.
+    fn acquire_lock_blocking(
+        &self,
+        entity: eci_core::Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+        wait_up_to: std::time::Duration,
+    ) -> Result<Lock, LockingError> {
+        let start = Instant::now();
+
+        loop {
+            let (component, holder) = match self.acquire_lock(entity, descriptors.clone(), expires)
+            {
+                Err(LockingError::Conflict(_, component, _, holder)) => (component, holder),
+                result => return result,
+            };
+
+            let waited = start.elapsed();
+            if waited >= wait_up_to {
+                return Err(LockingError::TimedOut {
+                    entity,
+                    component,
+                    waited,
+                });
+            }
+
+            // Locks which never expire can still be released early, so keep
+            // polling for that.
+            let until_expiry = match holder {
+                Some(ConflictingLock { expires: None, .. }) => MAX_POLL_INTERVAL,
+                Some(ConflictingLock {
+                    expires: Some(expires),
+                    ..
+                }) => expires
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default(),
+                None => std::time::Duration::ZERO,
+            };
+
+            debug!("waiting {until_expiry:?} for conflicting lock on {entity}'s {component}");
+            std::thread::sleep(until_expiry.min(MAX_POLL_INTERVAL).min(wait_up_to - waited));
+        }
+    }
+
+    fn renew_lock(&self, lock: &Lock, extend_by: std::time::Duration) -> Result<(), LockingError> {
+        debug!("renewing lock {lock}");
+        let expires = expiry(Expiry::In(extend_by))?;
+
+        // Every row of a lock shares the same expiry, so either all of them
+        // are renewed, or none are. Renewing a lock which never expires
+        // leaves it that way.
+        let renewed = self.retry(|| {
+            let conn = self.pool.get().map_err(LockingError::implementation)?;
+            conn.execute_cached(
+                "update locks set expires = case when expires = :never then expires else :expires end
+                where lockid = :lockid
+                and julianday('now') < julianday(expires)",
+                named_params! {
+                    ":lockid": lock.id(),
+                    ":expires": expires,
+                    ":never": never(),
+                },
+            )
+            .map_err(LockingError::implementation)
+        })?;
+
+        if renewed == 0 {
+            return Err(LockingError::Expired(lock.id()));
+        }
+
+        debug!("renewed locks on {renewed} resources held by {lock}");
+        Ok(())
+    }
+
+    fn upgrade_lock(
+        &self,
+        lock: &Lock,
+        entity: eci_core::Entity,
+        component: String,
+    ) -> Result<(), LockingError> {
+        self.retry(|| {
+            let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+
+            // Taking the write lock on the database up front keeps new locks
+            // from being granted between counting the competing locks and
+            // upgrading.
+            let tx = write_transaction(&mut conn).map_err(LockingError::implementation)?;
 
-        for descriptor in descriptors {
             let params = named_params! {
                 ":lockid": lock.id(),
                 ":entity": entity.to_string(),
-                ":component": descriptor.name,
-                ":expires": Utc::now() + Duration::from_std(expires_in).map_err(LockingError::implementation)?,
+                ":component": &component,
             };
 
-            debug!("acquiring {}-lock for {}", descriptor.mode, descriptor.name);
-
-            if tx
-                .execute(
-                    match descriptor.mode {
-                        LockingMode::Read => READ_LOCK,
-                        LockingMode::Write => WRITE_LOCK,
-                    },
+            let (held, competing): (i64, i64) = tx
+                .query_row_cached(
+                    "select
+                        count(case when lockid  = :lockid then 1 end),
+                        count(case when lockid != :lockid then 1 end)
+                    from locks
+                    where entity  = :entity
+                    and component = :component
+                    and julianday('now') < julianday(expires)",
                     params,
+                    |row| Ok((row.get(0)?, row.get(1)?)),
                 )
-                .map_err(LockingError::implementation)?
-                != 1
-            {
+                .map_err(LockingError::implementation)?;
+
+            if held == 0 {
+                return Err(LockingError::Expired(lock.id()));
+            }
+
+            if competing > 0 {
+                let holder = find_conflict(&tx, lock, entity, &component, LockingMode::Write)?;
                 return Err(LockingError::Conflict(
                     entity,
-                    descriptor.name,
-                    descriptor.mode,
+                    component.clone(),
+                    LockingMode::Write,
+                    holder,
                 ));
-            };
-        }
+            }
+
+            tx.execute_cached(
+                "update locks set locktype = 'write'
+                where lockid  = :lockid
+                and entity    = :entity
+                and component = :component",
+                params,
+            )
+            .map_err(LockingError::implementation)?;
 
-        tx.commit().map_err(LockingError::implementation)?;
-        debug!("lock {lock} transaction committed");
+            tx.commit().map_err(LockingError::implementation)
+        })?;
 
-        Ok(lock)
+        debug!("upgraded lock {lock} on {entity}'s {component} to a write lock");
+        Ok(())
     }
 
-    fn release_lock(&self, lock: Lock) -> Result<(), eci_core::backend::LockingError> {
-        let conn = self.0.get().map_err(LockingError::implementation)?;
-        debug!("releasing lock {lock}");
+    fn force_release(&self, lock_id: Uuid, _: Administrative) -> Result<usize, LockingError> {
+        warn!("forcibly releasing lock {lock_id}");
 
-        let locks_deleted = conn
-            .execute(
+        self.retry(|| {
+            let conn = self.pool.get().map_err(LockingError::implementation)?;
+            conn.execute_cached(
                 "delete from locks where lockid = :lockid",
-                named_params! { ":lockid": lock.id()},
+                named_params! { ":lockid": lock_id.to_string() },
+            )
+            .map_err(LockingError::implementation)
+        })
+    }
+
+    fn force_release_entity(
+        &self,
+        entity: eci_core::Entity,
+        _: Administrative,
+    ) -> Result<usize, LockingError> {
+        warn!("forcibly releasing all locks on {entity}");
+
+        self.retry(|| {
+            let conn = self.pool.get().map_err(LockingError::implementation)?;
+            conn.execute_cached(
+                "delete from locks where entity = :entity",
+                named_params! { ":entity": entity.to_string() },
+            )
+            .map_err(LockingError::implementation)
+        })
+    }
+
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        let conn = self.pool.get().map_err(LockingError::implementation)?;
+        conn.query_row_cached(
+            "select
+                count(*) filter (where julianday('now') < julianday(expires)),
+                count(*) filter (where julianday('now') >= julianday(expires))
+            from locks",
+            [],
+            |row| {
+                Ok(LockStats {
+                    active: row.get(0)?,
+                    expired: row.get(1)?,
+                })
+            },
+        )
+        .map_err(LockingError::implementation)
+    }
+
+    fn list_locks(
+        &self,
+        filter: Option<eci_core::Entity>,
+    ) -> Result<Vec<ActiveLock>, LockingError> {
+        let conn = self.pool.get().map_err(LockingError::implementation)?;
+
+        let mut statement = conn
+            .prepare_cached(
+                "select lockid, entity, component, locktype, expires, owner from locks
+                where (:entity is null or entity = :entity)
+                and julianday('now') < julianday(expires)
+                order by entity, component, lockid",
             )
             .map_err(LockingError::implementation)?;
 
-        debug!("deleted locks on {locks_deleted} resources by releasing {lock}",);
+        let locks = statement
+            .query_map(
+                named_params! { ":entity": filter.map(|entity| entity.to_string()) },
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, DateTime<Utc>>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                    ))
+                },
+            )
+            .map_err(LockingError::implementation)?
+            .map(|row| {
+                let (id, entity, component, mode, expires, owner) =
+                    row.map_err(LockingError::implementation)?;
+
+                Ok(ActiveLock {
+                    id,
+                    entity: eci_core::Entity(
+                        Uuid::parse_str(&entity).map_err(LockingError::implementation)?,
+                    ),
+                    component,
+                    mode: locking_mode(&mode),
+                    expires: expires_at(expires),
+                    owner,
+                })
+            })
+            .collect();
+        locks
+    }
+
+    fn acquire_locks_bulk(
+        &self,
+        requests: Vec<(eci_core::Entity, Vec<LockDescriptor>)>,
+        expires_in: std::time::Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        let lock = Lock::new();
+        let expires = expiry(Expiry::In(expires_in))?;
+
+        let (granted, skipped) = self.retry(|| {
+            let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+
+            debug!("starting bulk lock transaction for lock {lock}");
+            let mut tx = write_transaction(&mut conn).map_err(LockingError::implementation)?;
+
+            let (mut granted, mut skipped) = (Vec::new(), Vec::new());
+            for (entity, descriptors) in &requests {
+                let savepoint = tx.savepoint().map_err(LockingError::implementation)?;
+
+                let mut acquired = true;
+                for descriptor in descriptors {
+                    if !self.insert_lock(&savepoint, &lock, *entity, descriptor, expires)? {
+                        acquired = false;
+                        break;
+                    }
+                }
+
+                if acquired {
+                    savepoint.commit().map_err(LockingError::implementation)?;
+                    granted.push(*entity);
+                } else {
+                    debug!("skipping {entity} due to conflicting locks");
+                    savepoint.finish().map_err(LockingError::implementation)?;
+                    skipped.push(*entity);
+                }
+            }
+
+            tx.commit().map_err(LockingError::implementation)?;
+            debug!(
+                "bulk lock {lock} transaction committed, locked {} entities",
+                granted.len()
+            );
+            Ok((granted, skipped))
+        })?;
+
+        Ok(BulkLockResult {
+            lock: with_expiry(lock, expires),
+            granted,
+            skipped,
+        })
+    }
+}
+
+fn locking_mode(locktype: &str) -> LockingMode {
+    match locktype {
+        "write" => LockingMode::Write,
+        "exclusive" => LockingMode::Exclusive,
+        _ => LockingMode::Read,
+    }
+}
+
+/// Locks which never expire are stored as expiring at the end of year 9999,
+/// so the expiry checks in the lock queries need no special cases for them.
+fn never() -> DateTime<Utc> {
+    DateTime::from_utc(
+        NaiveDate::from_ymd_opt(9999, 12, 31)
+            .and_then(|date| date.and_hms_opt(23, 59, 59))
+            .expect("end of year 9999 is a valid date"),
+        Utc,
+    )
+}
+
+pub(crate) fn expiry(expires: Expiry) -> Result<DateTime<Utc>, LockingError> {
+    let expires_in = match expires {
+        Expiry::In(expires_in) => expires_in,
+        Expiry::Never => return Ok(never()),
+    };
+
+    // Finite expiries must fall before the sentinel, or they would be
+    // mistaken for locks which never expire.
+    Duration::from_std(expires_in)
+        .ok()
+        .and_then(|duration| Utc::now().checked_add_signed(duration))
+        .filter(|expires| *expires < never())
+        .ok_or(LockingError::ExpiryOutOfRange(expires_in))
+}
+
+fn expires_at(expires: DateTime<Utc>) -> Option<SystemTime> {
+    (expires != never()).then(|| expires.into())
+}
+
+pub(crate) fn with_expiry(lock: Lock, expires: DateTime<Utc>) -> Lock {
+    match expires_at(expires) {
+        Some(expires) => lock.with_expiry(expires),
+        None => lock,
+    }
+}
+
+/// Finds the lock held by someone else which conflicts with the requested
+/// one, preferring the one which expires first.
+fn find_conflict(
+    conn: &Connection,
+    lock: &Lock,
+    entity: eci_core::Entity,
+    component: &str,
+    mode: LockingMode,
+) -> Result<Option<ConflictingLock>, LockingError> {
+    conn.query_row_cached(
+        "select lockid, locktype, owner, expires from locks
+        where entity   = :entity
+        and lockid    != :lockid
+        and (
+            locktype = 'exclusive' or :mode = 'exclusive'
+            or (component = :component and (locktype = 'write' or :mode = 'write'))
+        )
+        and julianday('now') < julianday(expires)
+        order by julianday(expires)
+        limit 1",
+        named_params! {
+            ":lockid": lock.id(),
+            ":entity": entity.to_string(),
+            ":component": component,
+            ":mode": mode.to_string(),
+        },
+        |row| {
+            Ok(ConflictingLock {
+                id: row.get(0)?,
+                mode: locking_mode(&row.get::<_, String>(1)?),
+                owner: row.get(2)?,
+                expires: expires_at(row.get(3)?),
+            })
+        },
+    )
+    .optional()
+    .map_err(LockingError::implementation)
+}
+
+/// Whether the lock holds an unexpired write lock on the entity's component,
+/// either on the component itself or on the entity as a whole.
+pub(crate) fn holds_write_lock(
+    conn: &Connection,
+    lock: &Lock,
+    entity: eci_core::Entity,
+    component: &str,
+) -> Result<bool, rusqlite::Error> {
+    conn.prepare_cached(
+        "select 1 from locks
+        where lockid = :lockid
+        and entity   = :entity
+        and ((locktype = 'write' and component = :component) or locktype = 'exclusive')
+        and julianday('now') < julianday(expires)",
+    )?
+    .exists(named_params! {
+        ":lockid": lock.id(),
+        ":entity": entity.to_string(),
+        ":component": component,
+    })
+}
+
+impl SqliteBackend {
+    /// Inserts a lock row for each of the descriptors, failing with
+    /// [`LockingError::Conflict`] on the first one which conflicts with an
+    /// existing lock. Rows inserted before the conflict are left for the
+    /// caller to roll back.
+    pub(crate) fn insert_locks(
+        &self,
+        conn: &Connection,
+        lock: &Lock,
+        entity: eci_core::Entity,
+        descriptors: &[LockDescriptor],
+        expires: DateTime<Utc>,
+    ) -> Result<(), LockingError> {
+        for descriptor in descriptors {
+            if !self.insert_lock(conn, lock, entity, descriptor, expires)? {
+                let holder = find_conflict(conn, lock, entity, &descriptor.name, descriptor.mode)?;
+                return Err(LockingError::Conflict(
+                    entity,
+                    descriptor.name.clone(),
+                    descriptor.mode,
+                    holder,
+                ));
+            }
+        }
+
         Ok(())
     }
+
+    /// Attempts to insert a single lock row, returning false if it conflicts
+    /// with an existing lock.
+    fn insert_lock(
+        &self,
+        conn: &Connection,
+        lock: &Lock,
+        entity: eci_core::Entity,
+        descriptor: &LockDescriptor,
+        expires: DateTime<Utc>,
+    ) -> Result<bool, LockingError> {
+        let params = named_params! {
+            ":lockid": lock.id(),
+            ":entity": entity.to_string(),
+            ":component": descriptor.name,
+            ":expires": expires,
+            ":owner": self.owner,
+        };
+
+        debug!("acquiring {}-lock for {}", descriptor.mode, descriptor.name);
+
+        Ok(conn
+            .execute_cached(
+                match descriptor.mode {
+                    LockingMode::Read => READ_LOCK,
+                    LockingMode::Write => WRITE_LOCK,
+                    LockingMode::Exclusive => EXCLUSIVE_LOCK,
+                },
+                params,
+            )
+            .map_err(LockingError::implementation)?
+            == 1)
+    }
 }
 
 pub(crate) fn create_lock_table(
     conn: &Pool<SqliteConnectionManager>,
 ) -> Result<(), rusqlite::Error> {
-    conn.get().unwrap().execute_batch(
+    let conn = conn.get().unwrap();
+    conn.execute_batch(
         "
         create table if not exists locks (
             lockid    text not null,
             entity    text not null,
             component text not null,
             locktype  text not null,
-            expires   text not null
+            expires   text not null,
+            owner     text
         ) strict;
     ",
-    )
+    )?;
+
+    // Lock tables created before owners were recorded lack the column.
+    let has_owner = conn
+        .prepare("select 1 from pragma_table_info('locks') where name = 'owner'")?
+        .exists([])?;
+
+    if !has_owner {
+        conn.execute_batch("alter table locks add column owner text")?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
     use eci_core::{
-        backend::{LockDescriptor, LockingBackend, LockingError, LockingMode},
+        backend::{
+            AtomicLockMetrics, Backend, ConflictingLock, Expiry, Lock, LockCounts, LockDescriptor,
+            LockStats, LockingBackend, LockingError, LockingMode, SnakeCasePrefixed,
+        },
         Entity,
     };
 
+    use eci_format_json::Json;
+    use uuid::Uuid;
+
     use crate::SqliteBackend;
+
+    mod conformance {
+        use crate::SqliteBackend;
+
+        eci_testing::locking_conformance!(SqliteBackend::memory().unwrap());
+    }
+
     const LOCK_TIME: std::time::Duration = std::time::Duration::from_secs(60);
 
+    /// The conflict expected when running into the given lock.
+    fn held_by(lock: &Lock, mode: LockingMode) -> Option<ConflictingLock> {
+        Some(ConflictingLock {
+            id: lock.id(),
+            mode,
+            owner: None,
+            expires: lock.expires_at(),
+        })
+    }
+
     #[test]
     fn test_acquire_locking() {
         let conn = SqliteBackend::memory().unwrap();
@@ -149,7 +685,7 @@ mod tests {
                     name: "DebugComponentA".to_string(),
                 },
             ],
-            LOCK_TIME,
+            LOCK_TIME.into(),
         )
         .unwrap();
     }
@@ -173,7 +709,7 @@ mod tests {
                         name: "DebugComponentA".to_string(),
                     },
                 ],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap();
 
@@ -190,7 +726,7 @@ mod tests {
                         name: "DebugComponentA".to_string(),
                     },
                 ],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap();
     }
@@ -208,7 +744,7 @@ mod tests {
                     mode: LockingMode::Write,
                     name: "DebugComponentA".to_string(),
                 }],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap();
 
@@ -219,12 +755,17 @@ mod tests {
                     mode: LockingMode::Write,
                     name: "DebugComponentA".to_string(),
                 },],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap_err()
             .to_string(),
-            LockingError::Conflict(entity, "DebugComponentA".to_string(), LockingMode::Write)
-                .to_string()
+            LockingError::Conflict(
+                entity,
+                "DebugComponentA".to_string(),
+                LockingMode::Write,
+                held_by(&_a, LockingMode::Write)
+            )
+            .to_string()
         );
     }
 
@@ -247,7 +788,7 @@ mod tests {
                         name: "DebugComponentB".to_string(),
                     },
                 ],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap();
 
@@ -258,7 +799,7 @@ mod tests {
                     mode: LockingMode::Write,
                     name: "DebugComponentC".to_string(),
                 }],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap();
     }
@@ -276,7 +817,7 @@ mod tests {
                     mode: LockingMode::Read,
                     name: "DebugComponentA".to_string(),
                 }],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap();
 
@@ -287,12 +828,17 @@ mod tests {
                     mode: LockingMode::Write,
                     name: "DebugComponentA".to_string(),
                 },],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap_err()
             .to_string(),
-            LockingError::Conflict(entity, "DebugComponentA".to_string(), LockingMode::Write)
-                .to_string()
+            LockingError::Conflict(
+                entity,
+                "DebugComponentA".to_string(),
+                LockingMode::Write,
+                held_by(&_a, LockingMode::Read)
+            )
+            .to_string()
         );
     }
 
@@ -309,7 +855,7 @@ mod tests {
                     mode: LockingMode::Write,
                     name: "DebugComponentA".to_string(),
                 }],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap();
 
@@ -320,12 +866,725 @@ mod tests {
                     mode: LockingMode::Read,
                     name: "DebugComponentA".to_string(),
                 },],
-                LOCK_TIME,
+                LOCK_TIME.into(),
             )
             .unwrap_err()
             .to_string(),
-            LockingError::Conflict(entity, "DebugComponentA".to_string(), LockingMode::Read)
-                .to_string()
+            LockingError::Conflict(
+                entity,
+                "DebugComponentA".to_string(),
+                LockingMode::Read,
+                held_by(&_a, LockingMode::Write)
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn bulk_lock_skips_conflicts() {
+        let conn = SqliteBackend::memory().unwrap();
+
+        let (a, b, c) = (Entity::new(), Entity::new(), Entity::new());
+        let descriptors = || {
+            vec![
+                LockDescriptor {
+                    mode: LockingMode::Write,
+                    name: "DebugComponentA".to_string(),
+                },
+                LockDescriptor {
+                    mode: LockingMode::Read,
+                    name: "DebugComponentB".to_string(),
+                },
+            ]
+        };
+
+        let _b = conn
+            .acquire_lock(
+                b,
+                vec![LockDescriptor {
+                    mode: LockingMode::Write,
+                    name: "DebugComponentB".to_string(),
+                }],
+                LOCK_TIME.into(),
+            )
+            .unwrap();
+
+        let bulk = conn
+            .acquire_locks_bulk(
+                vec![(a, descriptors()), (b, descriptors()), (c, descriptors())],
+                LOCK_TIME,
+            )
+            .unwrap();
+
+        assert_eq!(bulk.granted, vec![a, c]);
+        assert_eq!(bulk.skipped, vec![b]);
+
+        // The conflicting entity must not have been left partially locked.
+        conn.acquire_lock(b, descriptors(), LOCK_TIME.into())
+            .unwrap_err();
+        conn.acquire_lock(
+            b,
+            vec![LockDescriptor {
+                mode: LockingMode::Write,
+                name: "DebugComponentA".to_string(),
+            }],
+            LOCK_TIME.into(),
+        )
+        .unwrap();
+
+        conn.acquire_lock(a, descriptors(), LOCK_TIME.into())
+            .unwrap_err();
+        conn.release_lock(bulk.lock).unwrap();
+        conn.acquire_lock(a, descriptors(), LOCK_TIME.into())
+            .unwrap();
+        conn.acquire_lock(c, descriptors(), LOCK_TIME.into())
+            .unwrap();
+    }
+
+    #[test]
+    fn reject_empty_lock_requests() {
+        let conn = SqliteBackend::memory().unwrap();
+
+        assert!(matches!(
+            conn.acquire_lock(Entity::new(), vec![], LOCK_TIME.into()),
+            Err(LockingError::EmptyRequest)
+        ));
+        assert!(matches!(
+            conn.acquire_locks(vec![], LOCK_TIME),
+            Err(LockingError::EmptyRequest)
+        ));
+        assert!(matches!(
+            conn.acquire_locks(
+                vec![(Entity::new(), write_lock()), (Entity::new(), vec![])],
+                LOCK_TIME
+            ),
+            Err(LockingError::EmptyRequest)
+        ));
+        assert!(conn.list_locks(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn duplicate_descriptors_lock_once() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let descriptor = |mode, name: &str| LockDescriptor {
+            mode,
+            name: name.to_string(),
+        };
+
+        let entity = Entity::new();
+        backend
+            .acquire_lock(
+                entity,
+                vec![
+                    descriptor(LockingMode::Read, "DebugComponentA"),
+                    descriptor(LockingMode::Read, "DebugComponentA"),
+                    descriptor(LockingMode::Read, "DebugComponentB"),
+                    descriptor(LockingMode::Write, "DebugComponentB"),
+                    descriptor(LockingMode::Read, "DebugComponentB"),
+                ],
+                LOCK_TIME.into(),
+            )
+            .unwrap();
+
+        let listed: Vec<_> = backend
+            .list_locks(Some(entity))
+            .unwrap()
+            .into_iter()
+            .map(|lock| (lock.component, lock.mode))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("DebugComponentA".to_string(), LockingMode::Read),
+                ("DebugComponentB".to_string(), LockingMode::Write),
+            ]
+        );
+    }
+
+    #[test]
+    fn naming_does_not_affect_locks() {
+        let conn = SqliteBackend::memory()
+            .unwrap()
+            .with_naming(SnakeCasePrefixed::new("eci_"))
+            .unwrap();
+
+        let entity = Entity::new();
+        let descriptor = |mode| LockDescriptor {
+            mode,
+            name: "DebugComponentA".to_string(),
+        };
+
+        let _a = conn
+            .acquire_lock(
+                entity,
+                vec![descriptor(LockingMode::Write)],
+                LOCK_TIME.into(),
+            )
+            .unwrap();
+
+        assert!(matches!(
+            conn.acquire_lock(entity, vec![descriptor(LockingMode::Read)], LOCK_TIME.into()),
+            Err(LockingError::Conflict(_, name, LockingMode::Read, _)) if name == "DebugComponentA"
+        ));
+    }
+
+    #[test]
+    fn renew_lock() {
+        let conn = SqliteBackend::memory().unwrap();
+
+        let entity = Entity::new();
+        let descriptor = || {
+            vec![LockDescriptor {
+                mode: LockingMode::Write,
+                name: "DebugComponentA".to_string(),
+            }]
+        };
+        let short = std::time::Duration::from_millis(300);
+
+        let lock = conn
+            .acquire_lock(entity, descriptor(), short.into())
+            .unwrap();
+        conn.renew_lock(&lock, std::time::Duration::from_secs(1))
+            .unwrap();
+
+        // The lock would have expired by now, had it not been renewed.
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        assert!(matches!(
+            conn.acquire_lock(entity, descriptor(), LOCK_TIME.into()),
+            Err(LockingError::Conflict(..))
+        ));
+
+        conn.renew_lock(&lock, short).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(500));
+
+        assert!(matches!(
+            conn.renew_lock(&lock, LOCK_TIME),
+            Err(LockingError::Expired(id)) if id == lock.id()
+        ));
+
+        let _b = conn
+            .acquire_lock(entity, descriptor(), LOCK_TIME.into())
+            .unwrap();
+    }
+
+    #[test]
+    fn conflict_names_owner() {
+        let path = std::env::temp_dir().join(format!("eci-owner-{}.db", Entity::new()));
+        let holder = SqliteBackend::file(&path)
+            .unwrap()
+            .with_owner("inventory-service (pid 42)");
+        let contender = SqliteBackend::file(&path).unwrap();
+
+        let entity = Entity::new();
+        let held = holder
+            .acquire_lock(entity, write_lock(), LOCK_TIME.into())
+            .unwrap();
+
+        let err = contender
+            .acquire_lock(entity, write_lock(), LOCK_TIME.into())
+            .unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.starts_with(&format!(
+            "write lock requested on {entity}'s DebugComponentA but write lock {held} held until "
+        )));
+        assert!(message.ends_with(" by inventory-service (pid 42)"));
+
+        match err {
+            LockingError::Conflict(_, _, _, Some(holder)) => {
+                let remaining = holder
+                    .expires
+                    .unwrap()
+                    .duration_since(std::time::SystemTime::now())
+                    .unwrap();
+                assert!(remaining > LOCK_TIME - Duration::from_secs(5));
+                assert!(remaining <= LOCK_TIME);
+            }
+            other => panic!("expected the conflicting lock, got {other:?}"),
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn add_owner_to_existing_lock_table() {
+        let path = std::env::temp_dir().join(format!("eci-owner-{}.db", Entity::new()));
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "
+                create table locks (
+                    lockid    text not null,
+                    entity    text not null,
+                    component text not null,
+                    locktype  text not null,
+                    expires   text not null
+                ) strict;
+            ",
+            )
+            .unwrap();
+
+        let conn = SqliteBackend::file(&path).unwrap().with_owner("worker");
+        let entity = Entity::new();
+        conn.acquire_lock(entity, write_lock(), LOCK_TIME.into())
+            .unwrap();
+
+        assert!(matches!(
+            conn.acquire_lock(entity, write_lock(), LOCK_TIME.into()),
+            Err(LockingError::Conflict(_, _, _, Some(holder))) if holder.owner.as_deref() == Some("worker")
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn list_locks() {
+        let conn = SqliteBackend::memory().unwrap().with_owner("worker");
+        let (a, b) = (Entity::new(), Entity::new());
+
+        let read = LockDescriptor {
+            mode: LockingMode::Read,
+            name: "DebugComponentB".to_string(),
+        };
+
+        let first = conn
+            .acquire_lock(a, write_lock(), LOCK_TIME.into())
+            .unwrap();
+        let second = conn
+            .acquire_lock(a, vec![read.clone()], Duration::from_millis(200).into())
+            .unwrap();
+        let third = conn.acquire_lock(b, vec![read], LOCK_TIME.into()).unwrap();
+
+        let locks = conn.list_locks(Some(a)).unwrap();
+        assert_eq!(
+            locks
+                .iter()
+                .map(|lock| (
+                    lock.id.clone(),
+                    lock.entity,
+                    lock.component.as_str(),
+                    lock.mode
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (first.id(), a, "DebugComponentA", LockingMode::Write),
+                (second.id(), a, "DebugComponentB", LockingMode::Read),
+            ]
+        );
+        assert!(locks
+            .iter()
+            .all(|lock| lock.owner.as_deref() == Some("worker")));
+        assert_eq!(locks[0].expires, first.expires_at());
+
+        assert_eq!(conn.list_locks(None).unwrap().len(), 3);
+
+        // Released and expired locks are no longer listed.
+        conn.release_lock(first).unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+
+        assert!(conn.list_locks(Some(a)).unwrap().is_empty());
+        assert_eq!(
+            conn.list_locks(None)
+                .unwrap()
+                .into_iter()
+                .map(|lock| lock.id)
+                .collect::<Vec<_>>(),
+            vec![third.id()]
+        );
+    }
+
+    #[test]
+    fn lock_stats() {
+        let conn = SqliteBackend::memory().unwrap();
+        assert_eq!(conn.lock_stats().unwrap(), LockStats::default());
+
+        let entity = Entity::new();
+        conn.acquire_lock(entity, write_lock(), LOCK_TIME.into())
+            .unwrap();
+        conn.acquire_lock(
+            entity,
+            vec![LockDescriptor {
+                mode: LockingMode::Read,
+                name: "DebugComponentB".to_string(),
+            }],
+            Duration::from_millis(100).into(),
+        )
+        .unwrap();
+
+        std::thread::sleep(Duration::from_millis(200));
+        let backend = Backend::<Json>::from_joint(conn);
+        assert_eq!(
+            backend.lock_stats().unwrap(),
+            LockStats {
+                active: 1,
+                expired: 1
+            }
+        );
+    }
+
+    #[test]
+    fn force_release() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let (a, b) = (Entity::new(), Entity::new());
+        let crashed = backend.acquire_lock(a, write_lock(), day.into()).unwrap();
+        backend
+            .acquire_lock(a, write_lock(), LOCK_TIME.into())
+            .unwrap_err();
+
+        let id = Uuid::parse_str(&crashed.id()).unwrap();
+        assert_eq!(backend.admin().force_release(id).unwrap(), 1);
+        assert_eq!(backend.admin().force_release(id).unwrap(), 0);
+        let _a = backend
+            .acquire_lock(a, write_lock(), LOCK_TIME.into())
+            .unwrap();
+
+        let read = |name: &str| LockDescriptor {
+            mode: LockingMode::Read,
+            name: name.to_string(),
+        };
+        backend
+            .acquire_lock(
+                b,
+                vec![read("DebugComponentA"), read("DebugComponentB")],
+                day.into(),
+            )
+            .unwrap();
+        backend
+            .acquire_lock(b, vec![read("DebugComponentC")], day.into())
+            .unwrap();
+
+        assert_eq!(backend.admin().force_release_entity(b).unwrap(), 3);
+        assert_eq!(backend.list_locks(None).unwrap().len(), 1);
+        let _b = backend
+            .acquire_lock(b, write_lock(), LOCK_TIME.into())
+            .unwrap();
+    }
+
+    #[test]
+    fn never_expiring_lock() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+        let short = Duration::from_millis(100);
+
+        let other = vec![LockDescriptor {
+            mode: LockingMode::Write,
+            name: "DebugComponentB".to_string(),
+        }];
+
+        let forever = backend
+            .acquire_lock(entity, write_lock(), Expiry::Never)
+            .unwrap();
+        assert_eq!(forever.expires_at(), None);
+        backend
+            .acquire_lock(entity, other.clone(), short.into())
+            .unwrap();
+
+        // Once the finite lock has lapsed, only the never-expiring one remains.
+        std::thread::sleep(short + Duration::from_millis(50));
+        backend
+            .acquire_lock(entity, other, LOCK_TIME.into())
+            .unwrap();
+        assert!(matches!(
+            backend.acquire_lock(entity, write_lock(), short.into()),
+            Err(LockingError::Conflict(
+                _,
+                _,
+                _,
+                Some(ConflictingLock { expires: None, .. })
+            ))
+        ));
+
+        // Renewing does not put an expiry on it either.
+        backend.renew_lock(&forever, short).unwrap();
+        let listed = backend.list_locks(Some(entity)).unwrap();
+        assert_eq!(
+            listed
+                .iter()
+                .find(|lock| lock.id == forever.id())
+                .unwrap()
+                .expires,
+            None
         );
+
+        backend.release_lock(forever).unwrap();
+        let broken = backend
+            .acquire_lock(entity, write_lock(), Expiry::Never)
+            .unwrap();
+
+        let id = Uuid::parse_str(&broken.id()).unwrap();
+        assert_eq!(backend.admin().force_release(id).unwrap(), 1);
+        backend
+            .acquire_lock(entity, write_lock(), LOCK_TIME.into())
+            .unwrap();
+    }
+
+    #[test]
+    fn reject_out_of_range_expiry() {
+        let conn = SqliteBackend::memory().unwrap();
+
+        assert!(matches!(
+            conn.acquire_lock(Entity::new(), write_lock(), Duration::MAX.into()),
+            Err(LockingError::ExpiryOutOfRange(Duration::MAX))
+        ));
+
+        let lock = conn
+            .acquire_lock(Entity::new(), write_lock(), LOCK_TIME.into())
+            .unwrap();
+        assert!(matches!(
+            conn.renew_lock(&lock, Duration::MAX),
+            Err(LockingError::ExpiryOutOfRange(Duration::MAX))
+        ));
+    }
+
+    #[test]
+    fn lock_metrics() {
+        let metrics = AtomicLockMetrics::new();
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap())
+            .with_lock_metrics(metrics.clone());
+        let (a, b) = (Entity::new(), Entity::new());
+
+        let held = backend
+            .acquire_lock(a, write_lock(), LOCK_TIME.into())
+            .unwrap();
+        for _ in 0..3 {
+            backend
+                .acquire_lock(a, write_lock(), LOCK_TIME.into())
+                .unwrap_err();
+        }
+        backend.release_lock(held).unwrap();
+
+        let bulk = backend
+            .acquire_locks_bulk(vec![(a, write_lock()), (b, write_lock())], LOCK_TIME)
+            .unwrap();
+        backend
+            .acquire_locks_bulk(vec![(a, write_lock())], LOCK_TIME)
+            .unwrap();
+        backend.release_lock(bulk.lock).unwrap();
+
+        let short = backend
+            .acquire_lock(b, write_lock(), Duration::from_millis(50).into())
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        backend.release_lock(short).unwrap();
+
+        assert_eq!(
+            metrics.snapshot().get("DebugComponentA"),
+            Some(&LockCounts {
+                acquired: 4,
+                conflicted: 4,
+                released: 3,
+                expired: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn entity_lock_conflicts() {
+        let conn = SqliteBackend::memory().unwrap();
+        let component = |mode| LockDescriptor {
+            mode,
+            name: "DebugComponentA".to_string(),
+        };
+
+        // Entity-wide locks block reads and writes of any component.
+        for mode in [LockingMode::Read, LockingMode::Write] {
+            let entity = Entity::new();
+            let _entity = conn
+                .acquire_lock(entity, vec![LockDescriptor::entity()], LOCK_TIME.into())
+                .unwrap();
+
+            assert!(matches!(
+                conn.acquire_lock(entity, vec![component(mode)], LOCK_TIME.into()),
+                Err(LockingError::Conflict(_, name, requested, Some(_)))
+                    if name == "DebugComponentA" && requested == mode
+            ));
+        }
+
+        // Reads and writes of any component block entity-wide locks.
+        for mode in [LockingMode::Read, LockingMode::Write] {
+            let entity = Entity::new();
+            let _component = conn
+                .acquire_lock(entity, vec![component(mode)], LOCK_TIME.into())
+                .unwrap();
+
+            assert!(matches!(
+                conn.acquire_lock(entity, vec![LockDescriptor::entity()], LOCK_TIME.into()),
+                Err(LockingError::Conflict(_, name, LockingMode::Exclusive, Some(_)))
+                    if name == LockDescriptor::ENTITY
+            ));
+        }
+
+        // Entity-wide locks on different entities do not conflict.
+        let a = conn
+            .acquire_lock(
+                Entity::new(),
+                vec![LockDescriptor::entity()],
+                LOCK_TIME.into(),
+            )
+            .unwrap();
+        let entity = Entity::new();
+        let _b = conn
+            .acquire_lock(entity, vec![LockDescriptor::entity()], LOCK_TIME.into())
+            .unwrap();
+        conn.acquire_lock(entity, vec![LockDescriptor::entity()], LOCK_TIME.into())
+            .unwrap_err();
+
+        conn.release_lock(a).unwrap();
+        let listed = conn.list_locks(Some(entity)).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].mode, LockingMode::Exclusive);
+    }
+
+    #[test]
+    fn multi_entity_lock_is_atomic() {
+        let conn = SqliteBackend::memory().unwrap();
+        let (a, b) = (Entity::new(), Entity::new());
+
+        let held = conn
+            .acquire_lock(b, write_lock(), LOCK_TIME.into())
+            .unwrap();
+        let err = conn
+            .acquire_locks(vec![(a, write_lock()), (b, write_lock())], LOCK_TIME)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LockingError::Conflict(entity, ref name, LockingMode::Write, Some(_))
+                if entity == b && name == "DebugComponentA"
+        ));
+
+        // The lock on the first entity was rolled back along with the rest.
+        assert!(conn.list_locks(Some(a)).unwrap().is_empty());
+
+        conn.release_lock(held).unwrap();
+        let lock = conn
+            .acquire_locks(vec![(a, write_lock()), (b, write_lock())], LOCK_TIME)
+            .unwrap();
+        let listed = conn.list_locks(None).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().all(|listed| listed.id == lock.id()));
+    }
+
+    /// Locking backend which relies on the default blocking implementation.
+    struct Polling(SqliteBackend);
+
+    impl LockingBackend for Polling {
+        fn acquire_lock(
+            &self,
+            entity: Entity,
+            descriptors: Vec<LockDescriptor>,
+            expires: Expiry,
+        ) -> Result<Lock, LockingError> {
+            self.0.acquire_lock(entity, descriptors, expires)
+        }
+
+        fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+            self.0.release_lock(lock)
+        }
+    }
+
+    fn write_lock() -> Vec<LockDescriptor> {
+        vec![LockDescriptor {
+            mode: LockingMode::Write,
+            name: "DebugComponentA".to_string(),
+        }]
+    }
+
+    /// Holds a write lock from another thread, releasing it after `hold` or
+    /// leaving it to expire after `ttl` if `hold` is `None`.
+    fn contend<L: LockingBackend + Sync>(
+        contender: L,
+        ttl: Duration,
+        hold: Option<Duration>,
+        wait_up_to: Duration,
+    ) -> (Result<Lock, LockingError>, Duration) {
+        let entity = Entity::new();
+        let (locked, done) = (mpsc::channel(), mpsc::channel::<()>());
+
+        std::thread::scope(|scope| {
+            let (contender, locked_tx, done_rx) = (&contender, locked.0, done.1);
+            scope.spawn(move || {
+                let lock = contender
+                    .acquire_lock(entity, write_lock(), ttl.into())
+                    .unwrap();
+                locked_tx.send(()).unwrap();
+
+                if let Some(hold) = hold {
+                    std::thread::sleep(hold);
+                    contender.release_lock(lock).unwrap();
+                }
+
+                done_rx.recv().unwrap();
+            });
+
+            locked.1.recv().unwrap();
+            let start = Instant::now();
+            let result =
+                contender.acquire_lock_blocking(entity, write_lock(), LOCK_TIME.into(), wait_up_to);
+            let elapsed = start.elapsed();
+
+            done.0.send(()).unwrap();
+            (result, elapsed)
+        })
+    }
+
+    fn sqlite() -> SqliteBackend {
+        SqliteBackend::memory().unwrap()
+    }
+
+    fn polling() -> Polling {
+        Polling(SqliteBackend::memory().unwrap())
+    }
+
+    #[test]
+    fn blocking_waits_for_expiry() {
+        let ttl = Duration::from_millis(300);
+
+        let (result, elapsed) = contend(sqlite(), ttl, None, Duration::from_secs(5));
+        result.unwrap();
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_secs(2));
+
+        let (result, elapsed) = contend(polling(), ttl, None, Duration::from_secs(5));
+        result.unwrap();
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn blocking_waits_for_release() {
+        let hold = Some(Duration::from_millis(200));
+
+        let (result, elapsed) = contend(sqlite(), LOCK_TIME, hold, Duration::from_secs(5));
+        result.unwrap();
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed < Duration::from_secs(2));
+
+        let (result, elapsed) = contend(polling(), LOCK_TIME, hold, Duration::from_secs(5));
+        result.unwrap();
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn blocking_times_out() {
+        let wait_up_to = Duration::from_millis(200);
+
+        for (result, elapsed) in [
+            contend(sqlite(), LOCK_TIME, None, wait_up_to),
+            contend(polling(), LOCK_TIME, None, wait_up_to),
+        ] {
+            match result {
+                Err(LockingError::TimedOut {
+                    component, waited, ..
+                }) => {
+                    assert_eq!(component, "DebugComponentA");
+                    assert!(waited >= wait_up_to);
+                }
+                other => panic!("expected to time out, got {other:?}"),
+            }
+            assert!(elapsed < Duration::from_secs(2));
+        }
     }
 }
@@ -0,0 +1,88 @@
+use std::{error::Error, fmt::Display};
+
+use eci_core::backend::{AccessError, NamingStrategy};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{named_params, Connection, OptionalExtension};
+
+use crate::statements::CachedStatements;
+
+const NAMING_KEY: &str = "naming";
+
+/// The database was created with a different naming strategy than the one
+/// it is being accessed with.
+#[derive(Debug)]
+pub struct NamingMismatch {
+    pub stored: String,
+    pub configured: String,
+}
+
+impl Display for NamingMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "database uses the {} naming strategy, but {} was configured",
+            self.stored, self.configured
+        )
+    }
+}
+
+impl Error for NamingMismatch {}
+
+pub(crate) fn create_metadata_table(
+    conn: &Pool<SqliteConnectionManager>,
+) -> Result<(), rusqlite::Error> {
+    conn.get().unwrap().execute_batch(
+        "
+        create table if not exists metadata (
+            key   text not null primary key,
+            value text not null
+        ) strict;
+    ",
+    )
+}
+
+pub(crate) fn get(conn: &Connection, key: &str) -> Result<Option<String>, rusqlite::Error> {
+    conn.query_row_cached(
+        "select value from metadata where key = :key",
+        named_params! { ":key": key },
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Fails if the database was previously written using a different naming
+/// strategy.
+pub(crate) fn check_naming(
+    conn: &Connection,
+    naming: &dyn NamingStrategy,
+) -> Result<(), AccessError> {
+    match get(conn, NAMING_KEY).map_err(AccessError::implementation)? {
+        Some(stored) if stored != naming.describe() => {
+            Err(AccessError::implementation(NamingMismatch {
+                stored,
+                configured: naming.describe(),
+            }))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Records the naming strategy used for the database, failing if it was
+/// previously written using a different one.
+///
+/// Writes before reading, so a deferred transaction takes its write lock
+/// up front, where waiting on other writers is possible, rather than
+/// upgrading from a read lock, which fails immediately when contended.
+pub(crate) fn record_naming(
+    conn: &Connection,
+    naming: &dyn NamingStrategy,
+) -> Result<(), AccessError> {
+    conn.execute_cached(
+        "insert or ignore into metadata (key, value) values (:key, :value)",
+        named_params! { ":key": NAMING_KEY, ":value": naming.describe() },
+    )
+    .map_err(AccessError::implementation)?;
+
+    check_naming(conn, naming)
+}
@@ -0,0 +1,47 @@
+use eci_core::backend::AccessError;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{named_params, Connection};
+
+use crate::{layout::Table, statements::CachedStatements};
+
+/// Keeps track of every component written to the database along with the
+/// table it is stored in, so components can be enumerated without telling
+/// component tables apart from others by name.
+pub(crate) fn create_registry_table(
+    conn: &Pool<SqliteConnectionManager>,
+) -> Result<(), rusqlite::Error> {
+    conn.get().unwrap().execute_batch(
+        "
+        create table if not exists component_tables (
+            component text not null primary key,
+            tablename text not null
+        ) strict;
+    ",
+    )
+}
+
+/// Records where the component is stored, unless it already has been.
+pub(crate) fn record(conn: &Connection, component: &str, table: &Table) -> Result<(), AccessError> {
+    conn.execute_cached(
+        "insert or ignore into component_tables (component, tablename) values (:component, :table)",
+        named_params! { ":component": component, ":table": table.name() },
+    )
+    .map_err(AccessError::implementation)?;
+
+    Ok(())
+}
+
+/// Every recorded component, ordered by name, along with its table name.
+pub(crate) fn components(conn: &Connection) -> Result<Vec<(String, String)>, AccessError> {
+    let mut statement = conn
+        .prepare_cached("select component, tablename from component_tables order by component")
+        .map_err(AccessError::implementation)?;
+
+    let components = statement
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(AccessError::implementation)?
+        .collect::<Result<_, _>>()
+        .map_err(AccessError::implementation)?;
+    Ok(components)
+}
@@ -0,0 +1,286 @@
+use std::{error::Error, time::Duration};
+
+use eci_core::backend::{AccessError, BackendError, LockingError};
+use log::*;
+use rusqlite::{Connection, ErrorCode, Transaction, TransactionBehavior};
+
+use crate::SqliteBackend;
+
+/// The longest the backoff grows to between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How often, and how patiently, transactions are retried when the database
+/// is busy with other connections or processes. These retries come on top
+/// of sqlite's own busy timeout, which is waited out on every attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusyRetry {
+    /// Total number of attempts, including the first one.
+    pub attempts: u32,
+    /// Delay before the first retry, doubling with every retry after it, up
+    /// to a second.
+    pub backoff: Duration,
+}
+
+impl Default for BusyRetry {
+    fn default() -> Self {
+        BusyRetry {
+            attempts: 5,
+            backoff: Duration::from_millis(10),
+        }
+    }
+}
+
+impl BusyRetry {
+    /// Gives up as soon as the database is busy.
+    pub fn never() -> Self {
+        BusyRetry {
+            attempts: 1,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    fn delay(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(1 << retry.min(16))
+            .min(MAX_BACKOFF)
+    }
+}
+
+/// Errors which may be caused by the database being busy.
+pub(crate) trait Transient: Sized {
+    fn is_busy(&self) -> bool;
+
+    fn busy(attempts: u32) -> Self;
+}
+
+impl Transient for AccessError {
+    fn is_busy(&self) -> bool {
+        match self {
+            AccessError::Implementation(inner) => caused_by_busy(inner.as_ref()),
+            _ => false,
+        }
+    }
+
+    fn busy(attempts: u32) -> Self {
+        AccessError::Busy { attempts }
+    }
+}
+
+impl Transient for LockingError {
+    fn is_busy(&self) -> bool {
+        match self {
+            LockingError::Implementation(inner) => caused_by_busy(inner.as_ref()),
+            _ => false,
+        }
+    }
+
+    fn busy(attempts: u32) -> Self {
+        LockingError::Busy { attempts }
+    }
+}
+
+impl Transient for BackendError {
+    fn is_busy(&self) -> bool {
+        match self {
+            BackendError::Access(err) => err.is_busy(),
+            BackendError::Locking(err) => err.is_busy(),
+            BackendError::Context(_, inner) => inner.is_busy(),
+            _ => false,
+        }
+    }
+
+    /// Reported as the locks being busy, since those are taken first.
+    fn busy(attempts: u32) -> Self {
+        BackendError::Locking(LockingError::Busy { attempts })
+    }
+}
+
+/// Whether the error, or any error it wraps, is sqlite reporting the
+/// database as busy or locked.
+fn caused_by_busy(err: &(dyn Error + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(rusqlite::Error::SqliteFailure(failure, _)) =
+            err.downcast_ref::<rusqlite::Error>()
+        {
+            if matches!(
+                failure.code,
+                ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked
+            ) {
+                return true;
+            }
+        }
+
+        current = err.source();
+    }
+
+    false
+}
+
+/// Starts a transaction which takes the database's write lock up front, so
+/// contention with other writers shows up right away, where the busy
+/// timeout applies, instead of when upgrading from a read lock midway.
+pub(crate) fn write_transaction(conn: &mut Connection) -> rusqlite::Result<Transaction<'_>> {
+    conn.transaction_with_behavior(TransactionBehavior::Immediate)
+}
+
+impl SqliteBackend {
+    /// Runs the operation until it no longer fails due to the database
+    /// being busy, or the retry policy gives up. The operation must start
+    /// its transaction anew on every attempt.
+    pub(crate) fn retry<T, E, O>(&self, mut operation: O) -> Result<T, E>
+    where
+        E: Transient,
+        O: FnMut() -> Result<T, E>,
+    {
+        let attempts = self.retry.attempts.max(1);
+        for attempt in 1..=attempts {
+            match operation() {
+                Err(err) if err.is_busy() => {
+                    if attempt < attempts {
+                        let delay = self.retry.delay(attempt - 1);
+                        debug!("database busy on attempt {attempt}, retrying in {delay:?}");
+                        std::thread::sleep(delay);
+                    }
+                }
+                result => return result,
+            }
+        }
+
+        Err(E::busy(attempts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, ExtractionDescriptor, Format, LockDescriptor,
+            LockingBackend, LockingError, LockingMode, SerializedComponent,
+        },
+        Entity, Version,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{BusyRetry, SqliteBackend, SqliteBackendBuilder};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    struct DebugComponentA {
+        content: String,
+    }
+
+    fn component_a(content: &str) -> Vec<SerializedComponent<Json>> {
+        vec![SerializedComponent::<Json> {
+            contents: Json::serialize(DebugComponentA {
+                content: content.to_string(),
+            })
+            .unwrap(),
+            name: "DebugComponentA".to_string(),
+            version: Version::default(),
+            schema: None,
+            metadata: None,
+        }]
+    }
+
+    fn write_lock() -> Vec<LockDescriptor> {
+        vec![LockDescriptor {
+            mode: LockingMode::Write,
+            name: "DebugComponentA".to_string(),
+        }]
+    }
+
+    /// Without a busy timeout, sqlite reports contention right away, leaving
+    /// it all to the retry policy.
+    fn impatient(path: &Path, retry: BusyRetry) -> SqliteBackend {
+        SqliteBackendBuilder::file(path)
+            .busy_timeout(Duration::ZERO)
+            .retry(retry)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn retry_while_busy() {
+        let path = std::env::temp_dir().join(format!("eci-retry-{}.db", Entity::new()));
+        let retry = BusyRetry {
+            attempts: 1000,
+            backoff: Duration::from_millis(1),
+        };
+
+        // Separate backends have separate pools, much like separate
+        // processes sharing the database file.
+        let backends = [impatient(&path, retry), impatient(&path, retry)];
+        let workers: Vec<_> = backends
+            .into_iter()
+            .map(|backend| {
+                std::thread::spawn(move || {
+                    for i in 0..50 {
+                        let entity = Entity::new();
+                        let lock = backend
+                            .acquire_lock(entity, write_lock(), Duration::from_secs(60).into())
+                            .unwrap();
+
+                        AccessBackend::<Json>::write_components(
+                            &backend,
+                            entity,
+                            component_a(&i.to_string()),
+                        )
+                        .unwrap();
+
+                        let read = AccessBackend::<Json>::read_components(
+                            &backend,
+                            entity,
+                            vec![ExtractionDescriptor {
+                                name: "DebugComponentA".to_string(),
+                                version: None,
+                                schema: None,
+                            }],
+                        )
+                        .unwrap();
+                        assert!(read[0].is_some());
+
+                        backend.release_lock(lock).unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn busy_after_exhausting_retries() {
+        let path = std::env::temp_dir().join(format!("eci-retry-{}.db", Entity::new()));
+        let backend = impatient(
+            &path,
+            BusyRetry {
+                attempts: 3,
+                backoff: Duration::from_millis(1),
+            },
+        );
+
+        // Hold the database's write lock for the duration of the test.
+        let blocker = rusqlite::Connection::open(&path).unwrap();
+        blocker.execute_batch("begin immediate").unwrap();
+
+        let entity = Entity::new();
+        assert!(matches!(
+            AccessBackend::<Json>::write_components(&backend, entity, component_a("blocked")),
+            Err(AccessError::Busy { attempts: 3 })
+        ));
+
+        assert!(matches!(
+            backend.acquire_lock(entity, write_lock(), Duration::from_secs(60).into()),
+            Err(LockingError::Busy { attempts: 3 })
+        ));
+
+        blocker.execute_batch("rollback").unwrap();
+        AccessBackend::<Json>::write_components(&backend, entity, component_a("unblocked"))
+            .unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+use rusqlite::{Connection, Params, Row};
+
+/// Number of prepared statements kept around per pooled connection. Every
+/// component accounts for a handful of statements, on top of those used
+/// for locking.
+pub(crate) const STATEMENT_CACHE_CAPACITY: usize = 256;
+
+/// Prepares pooled connections for use by the backend.
+pub(crate) fn init(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    Ok(())
+}
+
+/// Counterparts to [`Connection::execute`] and [`Connection::query_row`]
+/// which reuse the connection's prepared statement for the same SQL,
+/// rather than compiling it anew on every call.
+///
+/// Statements are cached by their SQL, which includes the table names, so
+/// changing naming strategy or layout never reuses a statement meant for
+/// another table. Sqlite recompiles cached statements itself when the
+/// schema changes underneath them.
+pub(crate) trait CachedStatements {
+    fn execute_cached<P: Params>(&self, sql: &str, params: P) -> rusqlite::Result<usize>;
+
+    fn query_row_cached<T, P, F>(&self, sql: &str, params: P, f: F) -> rusqlite::Result<T>
+    where
+        P: Params,
+        F: FnOnce(&Row<'_>) -> rusqlite::Result<T>;
+}
+
+impl CachedStatements for Connection {
+    fn execute_cached<P: Params>(&self, sql: &str, params: P) -> rusqlite::Result<usize> {
+        self.prepare_cached(sql)?.execute(params)
+    }
+
+    fn query_row_cached<T, P, F>(&self, sql: &str, params: P, f: F) -> rusqlite::Result<T>
+    where
+        P: Params,
+        F: FnOnce(&Row<'_>) -> rusqlite::Result<T>,
+    {
+        self.prepare_cached(sql)?.query_row(params, f)
+    }
+}
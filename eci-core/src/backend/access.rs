@@ -1,69 +1,493 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, time::SystemTime};
 
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::Entity;
+use crate::{Component, Entity, Version};
 
-#[derive(Debug)]
-pub enum AccessError {
-    Implementation(Box<dyn Error>),
-    Serialization(Box<dyn Error>),
-    Conflict(Entity, String),
+use super::{envelope, scan_entities_where, Envelope, Lock, Predicate};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Size(usize),
+    Depth(usize),
 }
 
-impl Display for AccessError {
+impl Display for Limit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AccessError::Implementation(inner) => {
-                write!(f, "error during access: {}", inner)
-            }
-            AccessError::Serialization(inner) => {
-                write!(f, "error during serialization: {}", inner)
-            }
-            AccessError::Conflict(entity, component) => {
-                write!(f, "failed to insert {component} into {entity}'s table")
-            }
+            Limit::Size(bytes) => write!(f, "size limit of {bytes} bytes"),
+            Limit::Depth(depth) => write!(f, "nesting depth limit of {depth}"),
+        }
+    }
+}
+
+/// Bounds placed on components read from untrusted sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializationLimits {
+    pub max_size: usize,
+    pub max_depth: usize,
+}
+
+impl Default for DeserializationLimits {
+    fn default() -> Self {
+        DeserializationLimits {
+            max_size: 1024 * 1024,
+            max_depth: 64,
+        }
+    }
+}
+
+impl DeserializationLimits {
+    pub fn check_size(&self, component: &str, size: usize) -> Result<(), AccessError> {
+        if size > self.max_size {
+            Err(AccessError::LimitExceeded {
+                component: component.to_string(),
+                limit: Limit::Size(self.max_size),
+            })
+        } else {
+            Ok(())
         }
     }
 }
 
-impl Error for AccessError {}
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum AccessError {
+    #[error("error during access: {0}")]
+    Implementation(#[source] Box<dyn Error + Send + Sync>),
+    #[error("error during serialization: {0}")]
+    Serialization(#[source] Box<dyn Error + Send + Sync>),
+    #[error("failed to insert {1} into {0}'s table")]
+    Conflict(Entity, String),
+    #[error("{component} exceeds the {limit}")]
+    LimitExceeded { component: String, limit: Limit },
+    #[error("{0} is not a known component")]
+    UnknownComponent(String),
+    /// No components were given to read or write.
+    #[error("no components were given")]
+    EmptyRequest,
+    /// The component was written without holding a write lock on it, while
+    /// locking is mandatory.
+    #[error("writing {0}'s {1} requires a write lock on it")]
+    LockRequired(Entity, String),
+    /// The component name contains characters other than ASCII letters,
+    /// digits and underscores, starts with a digit or is too long. See
+    /// [`crate::validate_component_name`].
+    #[error("{0:?} is not a valid component name")]
+    InvalidComponentName(String),
+    /// The component was stored with a different version of its layout
+    /// than the one it was read as.
+    #[error("{component} was stored with version {stored}, but version {expected} was expected")]
+    VersionMismatch {
+        component: String,
+        stored: Version,
+        expected: Version,
+    },
+    /// The storage stayed busy with other writers through every attempt.
+    /// Unlike most errors, retrying the operation later may well succeed.
+    #[error("storage was still busy after {attempts} attempts")]
+    Busy { attempts: u32 },
+    /// The stored contents could not be decrypted, such as when they were
+    /// encrypted with a key which is not available, or have been tampered
+    /// with. Kept apart from [`AccessError::Serialization`] so missing keys
+    /// do not pass for corrupted contents.
+    #[error("error during decryption: {0}")]
+    Decryption(#[source] Box<dyn Error + Send + Sync>),
+    /// The component was stored in an envelope naming another format than
+    /// the one it was read with. See [`Envelope`].
+    #[error("{component} was stored as {stored}, but was read as {expected}")]
+    FormatMismatch {
+        component: String,
+        stored: String,
+        expected: String,
+    },
+    /// The component was stored with other fields than the ones it was read
+    /// with, while its version stayed the same. See
+    /// [`Component::SCHEMA_HASH`].
+    #[error(
+        "{component} was stored with fields hashing to {stored:016x}, \
+        but its fields hash to {expected:016x}"
+    )]
+    SchemaMismatch {
+        component: String,
+        stored: u64,
+        expected: u64,
+    },
+    /// The component was written or removed since it was read at the given
+    /// revision, so writing it back would overwrite someone else's change.
+    /// Holds the revision it is at now, or `None` if it has been removed.
+    /// See [`AccessBackend::update_component_if_unchanged`].
+    #[error("{entity}'s {component} was {}", stale_since(*.current))]
+    StaleWrite {
+        entity: Entity,
+        component: String,
+        current: Option<u64>,
+    },
+    /// The backend cannot carry out the named operation.
+    #[error("{0} is not supported by the backend")]
+    Unsupported(&'static str),
+    /// The entity does not have the component, which it was required to.
+    #[error("{entity} has no {component}")]
+    ComponentNotFound { entity: Entity, component: String },
+    /// The entity has no components at all, where it was required to have
+    /// some.
+    #[error("{0} has no components")]
+    EntityNotFound(Entity),
+}
+
+fn stale_since(current: Option<u64>) -> String {
+    match current {
+        Some(current) => format!("written since it was read, and is now at revision {current}"),
+        None => "removed since it was read".to_string(),
+    }
+}
 
 impl AccessError {
-    pub fn implementation<T: Error + 'static>(err: T) -> Self {
+    pub fn implementation<T: Error + Send + Sync + 'static>(err: T) -> Self {
         AccessError::Implementation(Box::new(err))
     }
 
-    pub fn serialization<T: Error + 'static>(err: T) -> Self {
+    pub fn serialization<T: Error + Send + Sync + 'static>(err: T) -> Self {
         AccessError::Serialization(Box::new(err))
     }
+
+    pub fn decryption<T: Error + Send + Sync + 'static>(err: T) -> Self {
+        AccessError::Decryption(Box::new(err))
+    }
+
+    /// Whether someone else got to the component first, by inserting it
+    /// or writing it since it was read.
+    pub fn is_conflict(&self) -> bool {
+        matches!(
+            self,
+            AccessError::Conflict(..) | AccessError::StaleWrite { .. }
+        )
+    }
+
+    /// Whether retrying the same operation later may well succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, AccessError::Busy { .. })
+    }
 }
 
 pub trait AccessBackend<F: Format> {
+    /// Whether [`AccessBackend::write_components`] writes either all of the
+    /// given components or none of them. Backends which may leave some of
+    /// the components written when another fails must return `false`.
+    fn supports_atomic_writes(&self) -> bool {
+        false
+    }
+
+    /// Inserts the components into the entity, failing with
+    /// [`AccessError::Conflict`] if it already has any of them, or
+    /// [`AccessError::EmptyRequest`] if no components are given.
     fn write_components(
         &self,
         entity: Entity,
         components: Vec<SerializedComponent<F>>,
     ) -> Result<(), AccessError>;
 
+    /// Like [`AccessBackend::write_components`], but fails with
+    /// [`AccessError::LockRequired`] unless the lock holds an unexpired
+    /// write lock on every component, checked as part of the write. The
+    /// default implementation cannot check this, so it rejects every write.
+    fn write_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        _lock: &Lock,
+    ) -> Result<(), AccessError> {
+        match components.into_iter().next() {
+            Some(component) => Err(AccessError::LockRequired(entity, component.name)),
+            None => Err(AccessError::EmptyRequest),
+        }
+    }
+
+    /// Writes components for many entities at once. The default
+    /// implementation writes each entity separately, so a failure may leave
+    /// the entities before it written. Backends which can should override
+    /// it to write the whole batch atomically.
+    fn write_components_batch(
+        &self,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        for (entity, components) in batch {
+            self.write_components(entity, components)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the components which the entity does not already have,
+    /// leaving existing ones untouched. The default implementation writes
+    /// each component separately.
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        for component in components {
+            match self.write_components(entity, vec![component]) {
+                Ok(()) | Err(AccessError::Conflict(..)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like [`AccessBackend::write_components_if_absent`], but fails with
+    /// [`AccessError::LockRequired`] unless the lock holds an unexpired
+    /// write lock on every component, checked as part of the write. The
+    /// default implementation writes each component separately through
+    /// [`AccessBackend::write_components_locked`].
+    fn write_components_if_absent_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        for component in components {
+            match self.write_components_locked(entity, vec![component], lock) {
+                Ok(()) | Err(AccessError::Conflict(..)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the given components, replacing any existing values
+    /// already stored for the entity.
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError>;
+
+    /// Like [`AccessBackend::update_components`], but fails with
+    /// [`AccessError::LockRequired`] unless the lock holds an unexpired
+    /// write lock on every component, checked as part of the write. The
+    /// default implementation cannot check this, so it rejects every write.
+    fn update_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        _lock: &Lock,
+    ) -> Result<(), AccessError> {
+        match components.into_iter().next() {
+            Some(component) => Err(AccessError::LockRequired(entity, component.name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the component only if it is still at the given revision,
+    /// see [`ComponentMetadata::revision`], failing with
+    /// [`AccessError::StaleWrite`] if it has been written or removed since.
+    /// Backends which do not keep track of revisions fail with
+    /// [`AccessError::Unsupported`].
+    fn update_component_if_unchanged(
+        &self,
+        entity: Entity,
+        component: SerializedComponent<F>,
+        revision: u64,
+    ) -> Result<(), AccessError> {
+        let _ = (entity, component, revision);
+        Err(AccessError::Unsupported("compare-and-swap writes"))
+    }
+
+    /// Reads the described components of the entity, in the order they were
+    /// described, with `None` for components the entity does not have.
+    /// Fails with [`AccessError::EmptyRequest`] if no components are
+    /// described.
     fn read_components(
         &self,
         entity: Entity,
         descriptors: Vec<ExtractionDescriptor>,
     ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError>;
+
+    /// Like [`AccessBackend::read_components`], but fails with
+    /// [`AccessError::ComponentNotFound`] naming the first of the described
+    /// components the entity does not have, or with
+    /// [`AccessError::EntityNotFound`] if it has no components at all,
+    /// rather than returning `None` for them.
+    fn expect_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<SerializedComponent<F>>, AccessError> {
+        let names: Vec<String> = descriptors.iter().map(|d| d.name.clone()).collect();
+        let components = self.read_components(entity, descriptors)?;
+
+        let Some(missing) = components.iter().position(Option::is_none) else {
+            return Ok(components.into_iter().flatten().collect());
+        };
+
+        if components.iter().all(Option::is_none) && self.list_components(entity)?.is_empty() {
+            return Err(AccessError::EntityNotFound(entity));
+        }
+
+        Err(AccessError::ComponentNotFound {
+            entity,
+            component: names[missing].clone(),
+        })
+    }
+
+    /// Removes the described components from the entity, returning the
+    /// removed values, or `None` for components the entity did not have.
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError>;
+
+    /// Like [`AccessBackend::remove_components`], but fails with
+    /// [`AccessError::LockRequired`] unless the lock holds an unexpired
+    /// write lock on every component, checked as part of the removal. The
+    /// default implementation cannot check this, so it rejects every
+    /// removal.
+    fn remove_components_locked(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        _lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        match descriptors.into_iter().next() {
+            Some(descriptor) => Err(AccessError::LockRequired(entity, descriptor.name)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Lists all entities which have the named component.
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError>;
+
+    /// Lists the entities whose component was written at or after the
+    /// given time, for picking up changes incrementally. The default
+    /// implementation knows nothing about when components were written,
+    /// and so lists every entity with the component.
+    fn modified_since(
+        &self,
+        component: &str,
+        since: SystemTime,
+    ) -> Result<Vec<Entity>, AccessError> {
+        let _ = since;
+        self.entities_with(component)
+    }
+
+    /// Lists every entity which has at least one component.
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError>;
+
+    /// Lists the names of the components the entity has.
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError>;
+
+    /// Whether the entity has each of the named components, in the order
+    /// they were named, without reading their contents. The default
+    /// implementation lists every component of the entity.
+    fn contains(&self, entity: Entity, names: &[String]) -> Result<Vec<bool>, AccessError> {
+        let components = self.list_components(entity)?;
+        Ok(names.iter().map(|name| components.contains(name)).collect())
+    }
+
+    /// Counts the entities with each component and the space taken up by
+    /// their contents, ordered by component name.
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError>;
+
+    /// Lists the entities whose components match every predicate, of which
+    /// there must be at least one. The default implementation deserializes
+    /// every candidate component, so backends which can evaluate predicates
+    /// natively should override it.
+    fn find_entities_where(&self, predicates: Vec<Predicate>) -> Result<Vec<Entity>, AccessError> {
+        scan_entities_where(self, &predicates)
+    }
 }
 
 pub trait Format: Display + Clone + 'static {
-    type Data: Into<Vec<u8>> + From<Vec<u8>>;
+    /// Whether serialized components are JSON text, which backends may
+    /// inspect directly instead of deserializing them.
+    const IS_JSON: bool = false;
+
+    type Data: Into<Vec<u8>> + From<Vec<u8>> + AsRef<[u8]> + Send + Sync;
     fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError>;
     fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError>;
+
+    /// Deserializes a component while enforcing the given limits. Formats
+    /// which cannot inspect their own structure only enforce the size limit.
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        limits.check_size(T::COMPONENT_TYPE, value.as_ref().len())?;
+        Self::deserialize(value)
+    }
+
+    /// Name of the format, recorded in the envelopes of components written
+    /// with it. Formats which read each other's contents should share a
+    /// name. Defaults to the name of the type, which formats should
+    /// override with one which does not change as the code is moved around.
+    fn name() -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+
+    /// Serializes the component, wrapping it in an [`Envelope`] recording
+    /// the format and the component's version.
+    fn serialize_envelope<T: Component + Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        let contents = Self::serialize(value)?;
+        let sealed = Envelope::new(&Self::name(), T::VERSION, contents.as_ref()).seal();
+        Ok(sealed.into())
+    }
+
+    /// Deserializes the component, whether or not it is wrapped in an
+    /// envelope, failing with [`AccessError::FormatMismatch`] if it was
+    /// written by another format.
+    fn deserialize_envelope<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+    ) -> Result<T, AccessError> {
+        let contents = envelope::unseal::<Self>(T::COMPONENT_TYPE, value.as_ref())?;
+        Self::deserialize(&contents.into())
+    }
 }
 
 pub struct SerializedComponent<F: Format> {
     pub contents: F::Data,
     pub name: String,
+    /// The version of the component's layout the contents were written
+    /// with, see [`Component::VERSION`].
+    pub version: Version,
+    /// Hash of the fields of the component the contents were written with,
+    /// see [`Component::SCHEMA_HASH`], if known.
+    pub schema: Option<u64>,
+    /// What the backend recorded about the stored component, for backends
+    /// which keep track of it. Ignored when writing.
+    pub metadata: Option<ComponentMetadata>,
+}
+
+/// What a backend records about a stored component besides its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentMetadata {
+    /// When the component was last written.
+    pub updated_at: SystemTime,
+    /// Number of times the component has been written, so a component
+    /// which is at the same revision as when it was read has not changed
+    /// since. See [`AccessBackend::update_component_if_unchanged`].
+    pub revision: u64,
+}
+
+/// How many entities have a component, and how many bytes their
+/// serialized contents take up, as reported by [`AccessBackend::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentStats {
+    pub name: String,
+    pub entity_count: u64,
+    pub total_bytes: u64,
 }
 
 pub struct ExtractionDescriptor {
     pub name: String,
+    /// The version of the component's layout expected when reading it,
+    /// or `None` to accept whichever version is stored.
+    pub version: Option<Version>,
+    /// Hash of the component's fields expected when reading it, or `None`
+    /// to accept whichever fields it was stored with. Only checked by
+    /// backends with schema checks enabled.
+    pub schema: Option<u64>,
 }
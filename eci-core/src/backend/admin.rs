@@ -0,0 +1,42 @@
+use uuid::Uuid;
+
+use crate::Entity;
+
+use super::{Backend, Format, LockingBackend, LockingError};
+
+/// Proof that an administrative operation was requested through
+/// [`Backend::admin`]. It cannot be constructed anywhere else, which keeps
+/// normal code paths from breaking locks by accident. Backends wrapping
+/// several others may clone it to pass the operation on to each of them.
+#[derive(Clone)]
+pub struct Administrative {
+    _private: (),
+}
+
+/// Administrative operations which bypass the usual locking rules, for
+/// recovering from crashed workers and the like.
+pub struct Admin<'a, F: Format> {
+    backend: &'a Backend<F>,
+}
+
+impl<F: Format> Backend<F> {
+    pub fn admin(&self) -> Admin<'_, F> {
+        Admin { backend: self }
+    }
+}
+
+impl<'a, F: Format> Admin<'a, F> {
+    /// Releases the lock regardless of who holds it, returning the number of
+    /// component locks which were removed.
+    pub fn force_release(&self, lock_id: Uuid) -> Result<usize, LockingError> {
+        self.backend
+            .force_release(lock_id, Administrative { _private: () })
+    }
+
+    /// Releases every lock on the entity, returning the number of component
+    /// locks which were removed.
+    pub fn force_release_entity(&self, entity: Entity) -> Result<usize, LockingError> {
+        self.backend
+            .force_release_entity(entity, Administrative { _private: () })
+    }
+}
@@ -0,0 +1,501 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use async_trait::async_trait;
+use log::*;
+use uuid::Uuid;
+
+use crate::Entity;
+
+use super::{
+    AccessBackend, AccessError, Clock, Expiry, ExtractionDescriptor, Format, Lock, LockDescriptor,
+    LockingBackend, LockingError, SerializedComponent, SystemClock, DEFAULT_LOCK_TTL,
+    MAX_POLL_INTERVAL, MIN_POLL_INTERVAL,
+};
+
+/// The counterpart of [`AccessBackend`] for async runtimes, whose calls
+/// yield rather than block the calling thread. Sync backends can be used
+/// through [`SyncAsAsync`].
+#[async_trait]
+pub trait AsyncAccessBackend<F: Format>: Send + Sync {
+    /// See [`AccessBackend::supports_atomic_writes`].
+    fn supports_atomic_writes(&self) -> bool {
+        false
+    }
+
+    async fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError>;
+
+    async fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError>;
+
+    async fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError>;
+
+    async fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError>;
+
+    async fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError>;
+
+    async fn list_entities(&self) -> Result<Vec<Entity>, AccessError>;
+
+    async fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError>;
+}
+
+/// The counterpart of [`LockingBackend`] for async runtimes.
+#[async_trait]
+pub trait AsyncLockingBackend: Send + Sync {
+    /// See [`LockingBackend::acquire_lock`].
+    async fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError>;
+
+    async fn release_lock(&self, lock: Lock) -> Result<(), LockingError>;
+
+    /// See [`LockingBackend::renew_lock`].
+    async fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError>;
+
+    /// Acquires the lock, waiting until the deadline for conflicting locks
+    /// to be released or expire. Fails with [`LockingError::TimedOut`] if
+    /// they are still held by then.
+    ///
+    /// By default the lock is retried with jittered, exponentially growing
+    /// delays, which backends that can tell when locks are released should
+    /// improve upon. Dropping the future before it completes leaves no part
+    /// of the lock held.
+    async fn acquire_lock_waiting(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+        deadline: Instant,
+    ) -> Result<Lock, LockingError> {
+        let start = Instant::now();
+        let mut interval = MIN_POLL_INTERVAL;
+
+        loop {
+            match self
+                .acquire_lock(entity, descriptors.clone(), expires)
+                .await
+            {
+                Err(LockingError::Conflict(entity, component, ..)) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(LockingError::TimedOut {
+                            entity,
+                            component,
+                            waited: now - start,
+                        });
+                    }
+
+                    tokio::time::sleep(jittered(interval).min(deadline - now)).await;
+                    interval = (interval * 2).min(MAX_POLL_INTERVAL);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Adds up to half the interval again, so waiters which conflicted at the
+/// same time do not all retry at the same time.
+fn jittered(interval: Duration) -> Duration {
+    let jitter = Uuid::new_v4().as_u128() % (interval.as_micros() / 2 + 1);
+    interval + Duration::from_micros(jitter as u64)
+}
+
+/// Backends which handle both storage and locking asynchronously.
+pub trait AsyncJointBackend<F: Format>: AsyncAccessBackend<F> + AsyncLockingBackend {}
+
+impl<F: Format, T: AsyncAccessBackend<F> + AsyncLockingBackend> AsyncJointBackend<F> for T {}
+
+/// Runs the calls of a sync backend on tokio's blocking thread pool, so
+/// backends such as sqlite can be used from async code without holding up
+/// the runtime's worker threads. Must be used from within a tokio runtime.
+pub struct SyncAsAsync<T>(Arc<T>);
+
+impl<T> SyncAsAsync<T> {
+    pub fn new(backend: T) -> Self {
+        SyncAsAsync(Arc::new(backend))
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Clone for SyncAsAsync<T> {
+    fn clone(&self) -> Self {
+        SyncAsAsync(self.0.clone())
+    }
+}
+
+/// Runs the call on the blocking thread pool, resuming any panic it raised
+/// on the calling task.
+async fn blocking<T, R, C>(backend: &Arc<T>, call: C) -> R
+where
+    T: Send + Sync + 'static,
+    R: Send + 'static,
+    C: FnOnce(&T) -> R + Send + 'static,
+{
+    let backend = backend.clone();
+    match tokio::task::spawn_blocking(move || call(&backend)).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => panic!("blocking backend call did not complete: {err}"),
+    }
+}
+
+#[async_trait]
+impl<F, T> AsyncAccessBackend<F> for SyncAsAsync<T>
+where
+    F: Format,
+    T: AccessBackend<F> + Send + Sync + 'static,
+{
+    fn supports_atomic_writes(&self) -> bool {
+        self.0.supports_atomic_writes()
+    }
+
+    async fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        blocking(&self.0, move |backend| {
+            backend.write_components(entity, components)
+        })
+        .await
+    }
+
+    async fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        blocking(&self.0, move |backend| {
+            backend.update_components(entity, components)
+        })
+        .await
+    }
+
+    async fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        blocking(&self.0, move |backend| {
+            backend.read_components(entity, descriptors)
+        })
+        .await
+    }
+
+    async fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        blocking(&self.0, move |backend| {
+            backend.remove_components(entity, descriptors)
+        })
+        .await
+    }
+
+    async fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        let component = component.to_string();
+        blocking(&self.0, move |backend| backend.entities_with(&component)).await
+    }
+
+    async fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        blocking(&self.0, |backend| backend.list_entities()).await
+    }
+
+    async fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        blocking(&self.0, move |backend| backend.list_components(entity)).await
+    }
+}
+
+/// A lock being acquired on the blocking thread pool, shared with the task
+/// awaiting it. Blocking calls run to completion even if the task is
+/// dropped, so whichever of the two finds the other gone releases the lock.
+#[derive(Default)]
+struct Acquisition {
+    abandoned: bool,
+    acquired: Option<Result<Lock, LockingError>>,
+}
+
+/// Abandons the acquisition when dropped, releasing the lock if it was
+/// acquired but never handed to the awaiting task.
+struct AbandonOnDrop<T: LockingBackend + Send + Sync + 'static> {
+    backend: Arc<T>,
+    acquisition: Arc<Mutex<Acquisition>>,
+}
+
+impl<T: LockingBackend + Send + Sync + 'static> Drop for AbandonOnDrop<T> {
+    fn drop(&mut self) {
+        let mut acquisition = self
+            .acquisition
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        acquisition.abandoned = true;
+
+        if let Some(Ok(lock)) = acquisition.acquired.take() {
+            let backend = self.backend.clone();
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => {
+                    runtime.spawn_blocking(move || release_abandoned(backend.as_ref(), lock));
+                }
+                Err(_) => release_abandoned(backend.as_ref(), lock),
+            }
+        }
+    }
+}
+
+fn release_abandoned<T: LockingBackend>(backend: &T, lock: Lock) {
+    let id = lock.id();
+    debug!("releasing lock {id} acquired after its acquisition was abandoned");
+    if let Err(err) = backend.release_lock(lock) {
+        error!("failed to release abandoned lock {id}: {err}");
+    }
+}
+
+#[async_trait]
+impl<T> AsyncLockingBackend for SyncAsAsync<T>
+where
+    T: LockingBackend + Send + Sync + 'static,
+{
+    async fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        let guard = AbandonOnDrop {
+            backend: self.0.clone(),
+            acquisition: Arc::default(),
+        };
+
+        let acquisition = guard.acquisition.clone();
+        blocking(&self.0, move |backend| {
+            let result = backend.acquire_lock(entity, descriptors, expires);
+            let mut acquisition = acquisition
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            match result {
+                Ok(lock) if acquisition.abandoned => release_abandoned(backend, lock),
+                result => acquisition.acquired = Some(result),
+            }
+        })
+        .await;
+
+        let acquired = guard
+            .acquisition
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .acquired
+            .take();
+        acquired.expect("the acquisition completed without being abandoned")
+    }
+
+    async fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        blocking(&self.0, move |backend| backend.release_lock(lock)).await
+    }
+
+    async fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        let lock = lock.duplicate();
+        blocking(&self.0, move |backend| backend.renew_lock(&lock, extend_by)).await
+    }
+}
+
+#[derive(Clone)]
+enum AsyncStorage<F: Format> {
+    Disjoint {
+        locking: Arc<dyn AsyncLockingBackend>,
+        access: Arc<dyn AsyncAccessBackend<F>>,
+    },
+    Joint {
+        backend: Arc<dyn AsyncJointBackend<F>>,
+    },
+}
+
+/// The counterpart of [`Backend`](super::Backend) for async runtimes,
+/// pairing storage with locking. Components are passed through as they
+/// are, without the envelopes, migrations, limits or observers which
+/// [`Backend`](super::Backend) can be configured with.
+#[derive(Clone)]
+pub struct AsyncBackend<F: Format> {
+    storage: AsyncStorage<F>,
+    lock_ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl<F: Format> AsyncBackend<F> {
+    pub fn from_joint<T: AsyncJointBackend<F> + 'static>(backend: T) -> Self {
+        AsyncBackend {
+            storage: AsyncStorage::Joint {
+                backend: Arc::new(backend),
+            },
+            lock_ttl: DEFAULT_LOCK_TTL,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn from_disjoint<A, L>(access: A, locking: L) -> Self
+    where
+        A: AsyncAccessBackend<F> + 'static,
+        L: AsyncLockingBackend + 'static,
+    {
+        AsyncBackend {
+            storage: AsyncStorage::Disjoint {
+                access: Arc::new(access),
+                locking: Arc::new(locking),
+            },
+            lock_ttl: DEFAULT_LOCK_TTL,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Runs a sync backend which handles both storage and locking, see
+    /// [`SyncAsAsync`].
+    pub fn from_sync<T>(backend: T) -> Self
+    where
+        T: AccessBackend<F> + LockingBackend + Send + Sync + 'static,
+    {
+        Self::from_joint(SyncAsAsync::new(backend))
+    }
+
+    /// Sets the duration of locks acquired through this backend.
+    pub fn with_lock_ttl(mut self, lock_ttl: Duration) -> Self {
+        self.lock_ttl = lock_ttl;
+        self
+    }
+
+    pub fn lock_ttl(&self) -> Duration {
+        self.lock_ttl
+    }
+
+    /// Replaces the clock used for tracking lock expiry.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    fn access(&self) -> &dyn AsyncAccessBackend<F> {
+        match &self.storage {
+            AsyncStorage::Disjoint { locking: _, access } => access.as_ref(),
+            AsyncStorage::Joint { backend } => backend.as_ref(),
+        }
+    }
+
+    fn locking(&self) -> &dyn AsyncLockingBackend {
+        match &self.storage {
+            AsyncStorage::Disjoint { locking, access: _ } => locking.as_ref(),
+            AsyncStorage::Joint { backend } => backend.as_ref(),
+        }
+    }
+}
+
+#[async_trait]
+impl<F: Format> AsyncAccessBackend<F> for AsyncBackend<F> {
+    fn supports_atomic_writes(&self) -> bool {
+        self.access().supports_atomic_writes()
+    }
+
+    async fn write_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.access().write_components(entity, components).await
+    }
+
+    async fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.access().update_components(entity, components).await
+    }
+
+    async fn read_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.access().read_components(entity, descriptors).await
+    }
+
+    async fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.access().remove_components(entity, descriptors).await
+    }
+
+    async fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        self.access().entities_with(component).await
+    }
+
+    async fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        self.access().list_entities().await
+    }
+
+    async fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        self.access().list_components(entity).await
+    }
+}
+
+#[async_trait]
+impl<F: Format> AsyncLockingBackend for AsyncBackend<F> {
+    async fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        self.locking()
+            .acquire_lock(entity, descriptors, expires)
+            .await
+    }
+
+    async fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        self.locking().release_lock(lock).await
+    }
+
+    async fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        self.locking().renew_lock(lock, extend_by).await
+    }
+
+    async fn acquire_lock_waiting(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+        deadline: Instant,
+    ) -> Result<Lock, LockingError> {
+        self.locking()
+            .acquire_lock_waiting(entity, descriptors, expires, deadline)
+            .await
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::Component;
+
+use super::{AccessError, DeserializationLimits, Format, Limit};
+
+/// Marks compressed contents, telling them apart from contents written
+/// before compression was enabled. Text formats never start with a null
+/// byte, so this only clashes with binary formats which happen to.
+const MAGIC: &[u8] = b"\0ecz";
+
+/// Compresses the contents serialized by another format, which pays off
+/// for large or repetitive components. Contents written by the inner format
+/// alone are still read as they are, so compression can be enabled on
+/// existing data, which is then compressed as it is written.
+///
+/// Backends can no longer inspect compressed contents, so predicates are
+/// evaluated by deserializing components, even if the inner format is JSON.
+#[derive(Clone)]
+pub struct Compressed<F: Format>(pub F);
+
+impl<F: Format> Format for Compressed<F> {
+    type Data = Vec<u8>;
+
+    fn name() -> String {
+        format!("compressed {}", F::name())
+    }
+
+    fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        let contents: Vec<u8> = F::serialize(value)?.into();
+
+        let mut encoder = ZlibEncoder::new(MAGIC.to_vec(), Compression::default());
+        encoder
+            .write_all(&contents)
+            .map_err(AccessError::serialization)?;
+        encoder.finish().map_err(AccessError::serialization)
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+        let contents = match value.strip_prefix(MAGIC) {
+            Some(compressed) => {
+                let mut contents = Vec::new();
+                ZlibDecoder::new(compressed)
+                    .read_to_end(&mut contents)
+                    .map_err(AccessError::serialization)?;
+                contents
+            }
+            None => value.clone(),
+        };
+
+        F::deserialize(&F::Data::from(contents))
+    }
+
+    /// Enforces the size limit on the decompressed contents as well, without
+    /// decompressing any more than that.
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        limits.check_size(T::COMPONENT_TYPE, value.len())?;
+
+        let contents = match value.strip_prefix(MAGIC) {
+            Some(compressed) => {
+                let mut contents = Vec::new();
+                ZlibDecoder::new(compressed)
+                    .take(limits.max_size as u64 + 1)
+                    .read_to_end(&mut contents)
+                    .map_err(AccessError::serialization)?;
+
+                if contents.len() > limits.max_size {
+                    return Err(AccessError::LimitExceeded {
+                        component: T::COMPONENT_TYPE.to_string(),
+                        limit: Limit::Size(limits.max_size),
+                    });
+                }
+                contents
+            }
+            None => value.clone(),
+        };
+
+        F::deserialize_bounded(&F::Data::from(contents), limits)
+    }
+}
+
+impl<F: Format> Display for Compressed<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "compressed {}", self.0)
+    }
+}
@@ -0,0 +1,45 @@
+use std::{error::Error, fmt::Display};
+
+use super::BackendError;
+
+/// Wraps an error with a description of what was being done when it occurred.
+#[derive(Debug)]
+pub struct ContextError {
+    context: String,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl ContextError {
+    pub fn new<C: Into<String>, E: Into<Box<dyn Error + Send + Sync>>>(
+        context: C,
+        source: E,
+    ) -> Self {
+        ContextError {
+            context: context.into(),
+            source: source.into(),
+        }
+    }
+}
+
+impl Display for ContextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.context, self.source)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// Attaches context to the errors of backend operations.
+pub trait ResultExt<T> {
+    fn ctx<C: Into<String>>(self, context: C) -> Result<T, BackendError>;
+}
+
+impl<T, E: Into<BackendError>> ResultExt<T> for Result<T, E> {
+    fn ctx<C: Into<String>>(self, context: C) -> Result<T, BackendError> {
+        self.map_err(|err| err.into().context(context))
+    }
+}
@@ -0,0 +1,80 @@
+use crate::Entity;
+
+use super::{
+    AccessBackend, Backend, BackendError, ExtractionDescriptor, Format, LockDescriptor,
+    LockingBackend, LockingMode, ResultExt,
+};
+
+impl<F: Format> Backend<F> {
+    /// Copies every component of the source to a new entity, returning the
+    /// new entity. See [`Backend::clone_entity_filtered`].
+    pub fn clone_entity(&self, source: Entity) -> Result<Entity, BackendError> {
+        self.clone_entity_filtered(source, |_| true)
+    }
+
+    /// Copies the components of the source for which `keep` returns `true`
+    /// to a new entity, such as to leave out per-instance state. Components
+    /// are copied as they are stored, so their types need not be known.
+    ///
+    /// The copied components are read locked while they are copied, so
+    /// they cannot change halfway through, and are written in a single
+    /// request. Fails with [`BackendError::EntityNotFound`] if the source
+    /// has no components.
+    pub fn clone_entity_filtered<K>(&self, source: Entity, keep: K) -> Result<Entity, BackendError>
+    where
+        K: Fn(&str) -> bool,
+    {
+        let components = self
+            .list_components(source)
+            .ctx(format!("listing components of {source} to clone"))?;
+        if components.is_empty() {
+            return Err(BackendError::EntityNotFound(source));
+        }
+
+        let clone = self.new_entity();
+        let names: Vec<String> = components.into_iter().filter(|name| keep(name)).collect();
+        if names.is_empty() {
+            return Ok(clone);
+        }
+
+        let locks: Vec<_> = names
+            .iter()
+            .map(|name| LockDescriptor {
+                mode: LockingMode::Read,
+                name: name.clone(),
+            })
+            .collect();
+        let ttl = self.lock_ttl_for(&locks);
+        let lock = self
+            .acquire_lock(source, locks, ttl.into())
+            .ctx(format!("locking components of {source} to clone"))?;
+
+        let descriptors = names
+            .into_iter()
+            .map(|name| ExtractionDescriptor {
+                name,
+                version: None,
+                schema: None,
+            })
+            .collect();
+
+        let copied = self.read_components(source, descriptors).and_then(|read| {
+            // Components removed since they were listed are left out.
+            let components: Vec<_> = read.into_iter().flatten().collect();
+            if components.is_empty() {
+                return Ok(());
+            }
+
+            self.write_components(clone, components)
+        });
+
+        if let Err(err) = copied {
+            let _ = self.release_lock(lock);
+            return Err(err).ctx(format!("copying components of {source} to {clone}"));
+        }
+
+        self.release_lock(lock)
+            .ctx(format!("releasing components of {source} after cloning"))?;
+        Ok(clone)
+    }
+}
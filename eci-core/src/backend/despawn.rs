@@ -0,0 +1,77 @@
+use crate::Entity;
+
+use super::{
+    AccessBackend, Backend, BackendError, ExtractionDescriptor, Format, LockDescriptor,
+    LockingBackend, ResultExt,
+};
+
+/// What was removed along with a despawned entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DespawnReport {
+    pub entity: Entity,
+    /// Names of the components which were removed.
+    pub components: Vec<String>,
+    /// Number of expired component locks which were still recorded for the
+    /// entity, and were released along with it.
+    pub expired_locks: usize,
+}
+
+impl<F: Format> Backend<F> {
+    /// Removes every component of the entity, and every lock recorded for
+    /// it. The entity is locked as a whole while doing so, so despawning
+    /// fails with [`super::LockingError::Conflict`] while anyone else holds
+    /// a lock on any of its components. Fails with
+    /// [`BackendError::EntityNotFound`] if the entity has no components.
+    ///
+    /// The components are removed in a single request, which backends
+    /// supporting transactions carry out in one.
+    pub fn despawn(&self, entity: Entity) -> Result<DespawnReport, BackendError> {
+        let locks = vec![LockDescriptor::entity()];
+        let ttl = self.lock_ttl_for(&locks);
+        let lock = self
+            .acquire_lock(entity, locks, ttl.into())
+            .ctx(format!("locking {entity} to despawn it"))?;
+
+        let removed = self.list_components(entity).and_then(|components| {
+            if components.is_empty() {
+                return Ok(components);
+            }
+
+            let descriptors = components
+                .iter()
+                .map(|name| ExtractionDescriptor {
+                    name: name.clone(),
+                    version: None,
+                    schema: None,
+                })
+                .collect();
+            self.remove_components_locked(entity, descriptors, &lock)?;
+            Ok(components)
+        });
+
+        let components = match removed {
+            Ok(components) if !components.is_empty() => components,
+            Ok(_) => {
+                let _ = self.release_lock(lock);
+                return Err(BackendError::EntityNotFound(entity));
+            }
+            Err(err) => {
+                let _ = self.release_lock(lock);
+                return Err(err).ctx(format!("removing the components of {entity}"));
+            }
+        };
+
+        // Clearing every lock of the entity releases the despawn's own lock
+        // too, leaving only the expired ones to report.
+        let released = self
+            .admin()
+            .force_release_entity(entity)
+            .ctx(format!("releasing the locks of {entity}"))?;
+
+        Ok(DespawnReport {
+            entity,
+            components,
+            expired_locks: released.saturating_sub(1),
+        })
+    }
+}
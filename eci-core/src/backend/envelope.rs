@@ -0,0 +1,136 @@
+use std::{error::Error, fmt::Display};
+
+use crate::Version;
+
+use super::{AccessError, Format};
+
+/// Marks contents wrapped in an envelope. Text formats never start with a
+/// null byte, so this only clashes with binary formats which happen to.
+const MAGIC: &[u8] = b"\0ecv";
+
+/// Marks envelopes which also record the schema hash of the component.
+const MAGIC_SCHEMA: &[u8] = b"\0ecs";
+
+/// Serialized contents along with the name of the format and the version
+/// of the component's layout they were written with, so stored components
+/// can be told apart without knowing how they were written.
+///
+/// Envelopes are laid out as the magic bytes `\0ecv`, the length of the
+/// format's name as two big-endian bytes, the name itself, the major, minor
+/// and patch versions as eight big-endian bytes each, and finally the
+/// contents. Envelopes recording the component's schema hash start with
+/// `\0ecs` instead, and have the hash as eight big-endian bytes between the
+/// versions and the contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Envelope<'a> {
+    pub format: &'a str,
+    pub version: Version,
+    /// See [`crate::Component::SCHEMA_HASH`].
+    pub schema: Option<u64>,
+    pub payload: &'a [u8],
+}
+
+/// The contents start like an envelope, but are cut short or name their
+/// format in something other than UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MalformedEnvelope;
+
+impl Display for MalformedEnvelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "contents start with an envelope, but it is malformed")
+    }
+}
+
+impl Error for MalformedEnvelope {}
+
+impl<'a> Envelope<'a> {
+    pub fn new(format: &'a str, version: Version, payload: &'a [u8]) -> Self {
+        Envelope {
+            format,
+            version,
+            schema: None,
+            payload,
+        }
+    }
+
+    pub fn with_schema(mut self, schema: u64) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Writes the envelope along with its contents.
+    pub fn seal(&self) -> Vec<u8> {
+        let name = self.format.as_bytes();
+        let length = u16::try_from(name.len()).expect("format names are short");
+
+        let mut sealed = Vec::with_capacity(MAGIC.len() + 2 + name.len() + 32 + self.payload.len());
+        sealed.extend(if self.schema.is_some() {
+            MAGIC_SCHEMA
+        } else {
+            MAGIC
+        });
+        sealed.extend(length.to_be_bytes());
+        sealed.extend(name);
+        for part in [self.version.major, self.version.minor, self.version.patch] {
+            sealed.extend(part.to_be_bytes());
+        }
+        if let Some(schema) = self.schema {
+            sealed.extend(schema.to_be_bytes());
+        }
+        sealed.extend(self.payload);
+        sealed
+    }
+
+    /// Reads the envelope the contents are wrapped in, or `None` if they are
+    /// not wrapped in one, as is the case for contents written without.
+    pub fn open(contents: &'a [u8]) -> Result<Option<Self>, AccessError> {
+        let (rest, has_schema) = match (
+            contents.strip_prefix(MAGIC),
+            contents.strip_prefix(MAGIC_SCHEMA),
+        ) {
+            (Some(rest), _) => (rest, false),
+            (_, Some(rest)) => (rest, true),
+            (None, None) => return Ok(None),
+        };
+
+        let malformed = || AccessError::serialization(MalformedEnvelope);
+        let (length, rest) = split(rest, 2).ok_or_else(malformed)?;
+        let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+        let (name, rest) = split(rest, length).ok_or_else(malformed)?;
+        let format = std::str::from_utf8(name).map_err(|_| malformed())?;
+
+        let mut parts = [0u64; 4];
+        let mut rest = rest;
+        for part in &mut parts[..if has_schema { 4 } else { 3 }] {
+            let (bytes, remainder) = split(rest, 8).ok_or_else(malformed)?;
+            *part = u64::from_be_bytes(bytes.try_into().unwrap());
+            rest = remainder;
+        }
+
+        Ok(Some(Envelope {
+            format,
+            version: Version::new(parts[0], parts[1], parts[2]),
+            schema: has_schema.then_some(parts[3]),
+            payload: rest,
+        }))
+    }
+}
+
+fn split(bytes: &[u8], at: usize) -> Option<(&[u8], &[u8])> {
+    (bytes.len() >= at).then(|| bytes.split_at(at))
+}
+
+/// Strips the envelope from contents written by the format, failing with
+/// [`AccessError::FormatMismatch`] if the envelope names another format.
+/// Contents without an envelope are returned as they are.
+pub fn unseal<F: Format>(component: &str, contents: &[u8]) -> Result<Vec<u8>, AccessError> {
+    match Envelope::open(contents)? {
+        Some(envelope) if envelope.format != F::name() => Err(AccessError::FormatMismatch {
+            component: component.to_string(),
+            stored: envelope.format.to_string(),
+            expected: F::name(),
+        }),
+        Some(envelope) => Ok(envelope.payload.to_vec()),
+        None => Ok(contents.to_vec()),
+    }
+}
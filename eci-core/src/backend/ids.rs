@@ -0,0 +1,31 @@
+use crate::Entity;
+
+/// Source of the entities created through a backend, such as by
+/// [`Backend::new_entity`](super::Backend::new_entity). Entities created by
+/// different generators work side by side, since they are all UUIDs.
+pub trait EntityIdGenerator: Send + Sync {
+    fn generate(&self) -> Entity;
+}
+
+/// Random v4 UUIDs, see [`Entity::new`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomIds;
+
+impl EntityIdGenerator for RandomIds {
+    fn generate(&self) -> Entity {
+        Entity::new()
+    }
+}
+
+/// Time-ordered v7 UUIDs, see [`Entity::new_v7`] for the ordering they
+/// guarantee.
+#[cfg(feature = "uuid-v7")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeOrderedIds;
+
+#[cfg(feature = "uuid-v7")]
+impl EntityIdGenerator for TimeOrderedIds {
+    fn generate(&self) -> Entity {
+        Entity::new_v7()
+    }
+}
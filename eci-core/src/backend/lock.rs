@@ -1,13 +1,31 @@
-use std::{error::Error, fmt::Display};
+use std::{
+    error::Error,
+    fmt::Display,
+    time::{Duration, Instant, SystemTime},
+};
 
+use chrono::{DateTime, SecondsFormat, Utc};
 use uuid::Uuid;
 
 use crate::Entity;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+use super::Administrative;
+
+/// Called with the lock's id when a lock released in the background, such
+/// as when its guard is dropped, fails to be released.
+pub type ReleaseErrorHook = std::sync::Arc<dyn Fn(&str, &LockingError) + Send + Sync>;
+
+/// Shortest and longest delays between attempts to acquire a contended lock.
+pub(crate) const MIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+pub const MAX_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LockingMode {
     Read,
     Write,
+    /// Locks the entity as a whole, conflicting with every other lock on
+    /// any of its components. See [`LockDescriptor::entity`].
+    Exclusive,
 }
 
 impl Display for LockingMode {
@@ -15,68 +33,374 @@ impl Display for LockingMode {
         match self {
             LockingMode::Read => write!(f, "read"),
             LockingMode::Write => write!(f, "write"),
+            LockingMode::Exclusive => write!(f, "exclusive"),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum LockingError {
-    Implementation(Box<dyn Error>),
-    Conflict(Entity, String, LockingMode),
+    #[error("error while acquiring lock: {0}")]
+    Implementation(#[source] Box<dyn Error + Send + Sync>),
+    /// The component is locked by someone else. Holds the requested mode,
+    /// and the conflicting lock if the backend could tell which it was.
+    #[error("{}", conflict(.0, .1, .2, .3.as_ref()))]
+    Conflict(Entity, String, LockingMode, Option<ConflictingLock>),
+    #[error("lock {0} has expired")]
+    Expired(String),
+    /// The lock was already released, so it no longer guards anything.
+    #[error("lock has already been released")]
+    Released,
+    /// The lock was still held by someone else once the wait was over.
+    #[error("timed out after {waited:?} waiting for lock on {entity}'s {component}")]
+    TimedOut {
+        entity: Entity,
+        component: String,
+        waited: Duration,
+    },
+    /// No components were given to lock, so the lock would guard nothing.
+    #[error("no components were given to lock")]
+    EmptyRequest,
+    /// The lock would expire too far into the future for the backend to
+    /// represent. Use [`Expiry::Never`] for locks which should not expire.
+    #[error("lock duration of {0:?} is out of range")]
+    ExpiryOutOfRange(Duration),
+    /// The storage stayed busy with other writers through every attempt.
+    /// Unlike most errors, retrying the operation later may well succeed.
+    #[error("storage was still busy after {attempts} attempts")]
+    Busy { attempts: u32 },
+    /// The backend cannot carry out the named operation.
+    #[error("{0} is not supported by the backend")]
+    Unsupported(&'static str),
 }
 
-impl Display for LockingError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            LockingError::Implementation(inner) => {
-                write!(f, "error while acquiring lock: {}", inner)
-            }
-            LockingError::Conflict(entity, component, mode) => write!(
-                f,
-                "conflicting lock for {entity}'s {component} while acquiring {mode} lock"
-            ),
-        }
-    }
-}
+fn conflict(
+    entity: &Entity,
+    component: &str,
+    mode: &LockingMode,
+    holder: Option<&ConflictingLock>,
+) -> String {
+    let Some(holder) = holder else {
+        return format!("conflicting lock for {entity}'s {component} while acquiring {mode} lock");
+    };
 
-impl Error for LockingError {}
+    let until = match holder.expires {
+        Some(expires) => format!(
+            "until {}",
+            DateTime::<Utc>::from(expires).to_rfc3339_opts(SecondsFormat::Millis, true)
+        ),
+        None => "indefinitely".to_string(),
+    };
+    let owner = match &holder.owner {
+        Some(owner) => format!(" by {owner}"),
+        None => String::new(),
+    };
+
+    format!(
+        "{mode} lock requested on {entity}'s {component} but {} lock {} held {until}{owner}",
+        holder.mode, holder.id
+    )
+}
 
 impl LockingError {
-    pub fn implementation<T: Error + 'static>(err: T) -> Self {
+    pub fn implementation<T: Error + Send + Sync + 'static>(err: T) -> Self {
         LockingError::Implementation(Box::new(err))
     }
+
+    /// Whether the lock is, or was until the wait was over, held by
+    /// someone else.
+    pub fn is_conflict(&self) -> bool {
+        matches!(
+            self,
+            LockingError::Conflict(..) | LockingError::TimedOut { .. }
+        )
+    }
+
+    /// Whether retrying the same operation later may well succeed, such as
+    /// once the conflicting lock has been released.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            LockingError::Conflict(..) | LockingError::TimedOut { .. } | LockingError::Busy { .. }
+        )
+    }
+}
+
+/// A lock held by someone else, which caused a conflict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictingLock {
+    /// Identifies the lock, as in [`Lock::id`].
+    pub id: String,
+    pub mode: LockingMode,
+    /// Whoever acquired the lock, if they identified themselves.
+    pub owner: Option<String>,
+    /// When the lock expires, or `None` if it never does.
+    pub expires: Option<SystemTime>,
+}
+
+/// How long a lock is held for, unless it is released before then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    In(Duration),
+    /// Held until released, or broken through [`Backend::admin`].
+    ///
+    /// [`Backend::admin`]: super::Backend::admin
+    Never,
+}
+
+impl From<Duration> for Expiry {
+    fn from(duration: Duration) -> Self {
+        Expiry::In(duration)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct Lock(Uuid);
+pub struct Lock {
+    id: Uuid,
+    expires: Option<SystemTime>,
+}
 
 impl Lock {
     pub fn new() -> Lock {
-        Lock(Uuid::new_v4())
+        Lock {
+            id: Uuid::new_v4(),
+            expires: None,
+        }
+    }
+
+    /// Refers to a lock acquired elsewhere, such as by a backend on the
+    /// other end of a network connection.
+    pub fn from_id(id: Uuid) -> Lock {
+        Lock { id, expires: None }
+    }
+
+    /// Records when the lock expires, as determined by the locking backend.
+    pub fn with_expiry(mut self, expires: SystemTime) -> Lock {
+        self.expires = Some(expires);
+        self
     }
 
     pub fn id(&self) -> String {
-        self.0.to_string()
+        self.id.to_string()
+    }
+
+    /// Point in time at which the lock expires, if reported by the backend.
+    /// Locks which never expire have no such point.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires
+    }
+
+    /// Copies the lock, for handing to a task which cannot borrow it.
+    #[cfg(feature = "async")]
+    pub(crate) fn duplicate(&self) -> Lock {
+        Lock {
+            id: self.id,
+            expires: self.expires,
+        }
+    }
+}
+
+impl Default for Lock {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Display for Lock {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.id)
     }
 }
 
 pub trait LockingBackend {
+    /// Locks the described components of the entity. Locks are held per
+    /// component, so the lock only guards the components it names, or every
+    /// component of the entity for [`LockingMode::Exclusive`]. Fails with
+    /// [`LockingError::EmptyRequest`] if no components are described.
     fn acquire_lock(
         &self,
         entity: Entity,
         descriptors: Vec<LockDescriptor>,
-        expires_in: std::time::Duration,
+        expires: Expiry,
     ) -> Result<Lock, LockingError>;
     fn release_lock(&self, lock: Lock) -> Result<(), LockingError>;
+
+    /// Acquires the lock, waiting up to `wait_up_to` for conflicting locks
+    /// to be released or expire. Fails with [`LockingError::TimedOut`] if
+    /// they are still held once the time is up. The default implementation
+    /// retries at increasing intervals.
+    fn acquire_lock_blocking(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+        wait_up_to: Duration,
+    ) -> Result<Lock, LockingError> {
+        let start = Instant::now();
+        let mut interval = MIN_POLL_INTERVAL;
+
+        loop {
+            match self.acquire_lock(entity, descriptors.clone(), expires) {
+                Err(LockingError::Conflict(entity, component, ..)) => {
+                    let waited = start.elapsed();
+                    if waited >= wait_up_to {
+                        return Err(LockingError::TimedOut {
+                            entity,
+                            component,
+                            waited,
+                        });
+                    }
+
+                    std::thread::sleep(interval.min(wait_up_to - waited));
+                    interval = (interval * 2).min(MAX_POLL_INTERVAL);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Pushes the expiry of every component held by the lock forward, so it
+    /// expires `extend_by` from now. Fails with [`LockingError::Expired`] if
+    /// the lock has already expired, since another lock may have been
+    /// granted on the same components in the meantime.
+    ///
+    /// Backends which cannot renew locks fail with
+    /// [`LockingError::Unsupported`], as do those which do not implement the
+    /// methods below.
+    fn renew_lock(&self, _lock: &Lock, _extend_by: Duration) -> Result<(), LockingError> {
+        Err(LockingError::Unsupported("renewing locks"))
+    }
+
+    /// Converts the read lock held on the entity's component into a write
+    /// lock, failing with [`LockingError::Conflict`] if anyone else holds a
+    /// lock on the component, or [`LockingError::Expired`] if the lock has
+    /// expired.
+    fn upgrade_lock(
+        &self,
+        _lock: &Lock,
+        _entity: Entity,
+        _component: String,
+    ) -> Result<(), LockingError> {
+        Err(LockingError::Unsupported("upgrading locks"))
+    }
+
+    /// Lists the locks which are currently held, either on a single entity
+    /// or all of them. Expired locks are left out.
+    fn list_locks(&self, _filter: Option<Entity>) -> Result<Vec<ActiveLock>, LockingError> {
+        Err(LockingError::Unsupported("listing locks"))
+    }
+
+    /// Releases the lock regardless of who holds it, returning the number
+    /// of component locks removed. Only reachable through [`Backend::admin`].
+    ///
+    /// [`Backend::admin`]: super::Backend::admin
+    fn force_release(&self, _lock_id: Uuid, _: Administrative) -> Result<usize, LockingError> {
+        Err(LockingError::Unsupported("force-releasing locks"))
+    }
+
+    /// Releases every lock on the entity, returning the number of component
+    /// locks removed. Only reachable through [`Backend::admin`].
+    ///
+    /// [`Backend::admin`]: super::Backend::admin
+    fn force_release_entity(
+        &self,
+        _entity: Entity,
+        _: Administrative,
+    ) -> Result<usize, LockingError> {
+        Err(LockingError::Unsupported("force-releasing locks"))
+    }
+
+    /// Acquires locks on several entities at once under a single lock. If
+    /// any of them conflict, nothing is locked, and the error names the
+    /// entity and component which conflicted.
+    fn acquire_locks(
+        &self,
+        _requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        _expires_in: Duration,
+    ) -> Result<Lock, LockingError> {
+        Err(LockingError::Unsupported(
+            "locking several entities at once",
+        ))
+    }
+
+    /// Acquires locks for many entities at once under a single lock. Entities
+    /// whose locks conflict are skipped rather than failing the whole request.
+    fn acquire_locks_bulk(
+        &self,
+        _requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        _expires_in: Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        Err(LockingError::Unsupported("locking entities in bulk"))
+    }
+
+    /// Counts the component locks stored, telling those still held apart
+    /// from expired ones which have yet to be cleaned up.
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        Err(LockingError::Unsupported("lock statistics"))
+    }
+}
+
+/// A lock held on a single component, as listed by
+/// [`LockingBackend::list_locks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveLock {
+    /// Identifies the lock, as in [`Lock::id`]. A lock on several
+    /// components is listed once for each of them.
+    pub id: String,
+    pub entity: Entity,
+    pub component: String,
+    pub mode: LockingMode,
+    /// When the lock expires, or `None` if it never does.
+    pub expires: Option<SystemTime>,
+    pub owner: Option<String>,
+}
+
+/// Component locks stored by a backend, as reported by
+/// [`LockingBackend::lock_stats`]. A lock on several components counts once
+/// for each of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockStats {
+    pub active: u64,
+    pub expired: u64,
 }
 
+#[derive(Debug)]
+pub struct BulkLockResult {
+    pub lock: Lock,
+    pub granted: Vec<Entity>,
+    pub skipped: Vec<Entity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LockDescriptor {
     pub mode: LockingMode,
     pub name: String,
 }
+
+impl LockDescriptor {
+    /// Component name used by entity-wide locks, which cover every
+    /// component of the entity.
+    pub const ENTITY: &'static str = "*";
+
+    /// Describes an exclusive lock on the entity as a whole.
+    pub fn entity() -> Self {
+        LockDescriptor {
+            mode: LockingMode::Exclusive,
+            name: Self::ENTITY.to_string(),
+        }
+    }
+}
+
+/// Sorts descriptors by component name, so every caller acquires the locks
+/// of an entity in the same order no matter how they were selected. Taking
+/// them in differing orders lets callers retrying each other's conflicts
+/// keep running into each other instead of one of them getting through.
+///
+/// Descriptors naming the same component are merged into one, in the
+/// strongest of their modes, so each component is only locked once.
+pub(crate) fn canonical_order(mut descriptors: Vec<LockDescriptor>) -> Vec<LockDescriptor> {
+    descriptors.sort_by(|a, b| a.name.cmp(&b.name).then(b.mode.cmp(&a.mode)));
+    descriptors.dedup_by(|duplicate, kept| duplicate.name == kept.name);
+    descriptors
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::SystemTime,
+};
+
+use crate::Entity;
+
+use super::{BulkLockResult, Lock, LockDescriptor, LockingError};
+
+/// Receives lock events from a [`super::Backend`], keyed by component name,
+/// for instance to find components which are frequently contended.
+pub trait LockMetrics: Send + Sync {
+    fn acquired(&self, component: &str);
+    /// A lock on the component could not be acquired, because someone else
+    /// held a conflicting one.
+    fn conflicted(&self, component: &str);
+    fn released(&self, component: &str);
+    /// A lock on the component expired before it was released or renewed,
+    /// leaving it free to be claimed by others.
+    fn expired(&self, component: &str);
+}
+
+/// Counters for a single component, as recorded by [`AtomicLockMetrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockCounts {
+    pub acquired: u64,
+    pub conflicted: u64,
+    pub released: u64,
+    pub expired: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    acquired: AtomicU64,
+    conflicted: AtomicU64,
+    released: AtomicU64,
+    expired: AtomicU64,
+}
+
+/// Counts lock events in memory. Clones share the same counters, so a clone
+/// can be kept around for taking snapshots.
+#[derive(Clone, Default)]
+pub struct AtomicLockMetrics(Arc<RwLock<HashMap<String, Arc<Counters>>>>);
+
+impl AtomicLockMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The counts recorded so far for every component seen.
+    pub fn snapshot(&self) -> HashMap<String, LockCounts> {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(component, counters)| {
+                (
+                    component.clone(),
+                    LockCounts {
+                        acquired: counters.acquired.load(Ordering::Relaxed),
+                        conflicted: counters.conflicted.load(Ordering::Relaxed),
+                        released: counters.released.load(Ordering::Relaxed),
+                        expired: counters.expired.load(Ordering::Relaxed),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn counters(&self, component: &str) -> Arc<Counters> {
+        if let Some(counters) = self.0.read().unwrap().get(component) {
+            return counters.clone();
+        }
+
+        self.0
+            .write()
+            .unwrap()
+            .entry(component.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+impl LockMetrics for AtomicLockMetrics {
+    fn acquired(&self, component: &str) {
+        self.counters(component)
+            .acquired
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn conflicted(&self, component: &str) {
+        self.counters(component)
+            .conflicted
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn released(&self, component: &str) {
+        self.counters(component)
+            .released
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn expired(&self, component: &str) {
+        self.counters(component)
+            .expired
+            .fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Names of the locked components, as reported to [`LockMetrics`].
+pub(crate) fn component_names<'a>(
+    descriptors: impl IntoIterator<Item = &'a LockDescriptor>,
+) -> Vec<String> {
+    descriptors
+        .into_iter()
+        .map(|descriptor| descriptor.name.clone())
+        .collect()
+}
+
+/// Reports lock events to the configured [`LockMetrics`], remembering which
+/// components each lock covers so its release can be attributed to them.
+pub(crate) struct Metering {
+    metrics: Arc<dyn LockMetrics>,
+    held: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl Metering {
+    pub fn new(metrics: Arc<dyn LockMetrics>) -> Self {
+        Metering {
+            metrics,
+            held: Mutex::default(),
+        }
+    }
+
+    pub fn acquired(&self, lock: &Lock, components: Vec<String>) {
+        for component in &components {
+            self.metrics.acquired(component);
+        }
+
+        self.held.lock().unwrap().insert(lock.id(), components);
+    }
+
+    /// Entities skipped by a bulk lock count as conflicts on every
+    /// component requested for them.
+    pub fn acquired_bulk(
+        &self,
+        result: &BulkLockResult,
+        mut components: HashMap<Entity, Vec<String>>,
+    ) {
+        for component in result
+            .skipped
+            .iter()
+            .flat_map(|entity| components.remove(entity).unwrap_or_default())
+        {
+            self.metrics.conflicted(&component);
+        }
+
+        let granted = result
+            .granted
+            .iter()
+            .flat_map(|entity| components.remove(entity).unwrap_or_default())
+            .collect();
+        self.acquired(&result.lock, granted);
+    }
+
+    pub fn failed(&self, err: &LockingError) {
+        match err {
+            LockingError::Conflict(_, component, _, _)
+            | LockingError::TimedOut { component, .. } => self.metrics.conflicted(component),
+            _ => {}
+        }
+    }
+
+    /// Locks released after their expiry are counted as expired instead.
+    pub fn released(&self, lock: &Lock, now: SystemTime) {
+        let expired = lock.expires_at().is_some_and(|expires| expires <= now);
+        if expired {
+            return self.expired(lock);
+        }
+
+        for component in self.forget(lock) {
+            self.metrics.released(&component);
+        }
+    }
+
+    pub fn expired(&self, lock: &Lock) {
+        for component in self.forget(lock) {
+            self.metrics.expired(&component);
+        }
+    }
+
+    fn forget(&self, lock: &Lock) -> Vec<String> {
+        self.held
+            .lock()
+            .unwrap()
+            .remove(&lock.id())
+            .unwrap_or_default()
+    }
+}
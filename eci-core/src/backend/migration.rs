@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Component, Version};
+
+use super::{AccessError, Format};
+
+type Upgrade = Box<dyn Fn(Value) -> Result<Value, AccessError> + Send + Sync>;
+
+/// Upgrades a component from one version of its layout to another.
+struct Migration {
+    to: Version,
+    upgrade: Upgrade,
+}
+
+/// Upgrades components stored by older versions of their layout to the
+/// version they are read as, keyed by component and the version they
+/// upgrade from. Upgrades are chained, so a component stored as `1.0.0`
+/// can be read as `3.0.0` given upgrades from `1.0.0` and from `2.0.0`.
+#[derive(Default)]
+pub struct MigrationRegistry {
+    migrations: HashMap<(String, Version), Migration>,
+    write_back: bool,
+}
+
+impl MigrationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an upgrade of `T` from the given version to
+    /// [`Component::VERSION`]. The upgrade is given the stored component as
+    /// a JSON value, whatever format it is stored in.
+    pub fn register<T, U>(mut self, from: Version, upgrade: U) -> Self
+    where
+        T: Component + Serialize,
+        U: Fn(Value) -> Result<T, AccessError> + Send + Sync + 'static,
+    {
+        self.migrations.insert(
+            (T::COMPONENT_TYPE.to_string(), from),
+            Migration {
+                to: T::VERSION,
+                upgrade: Box::new(move |value| {
+                    serde_json::to_value(upgrade(value)?).map_err(AccessError::serialization)
+                }),
+            },
+        );
+        self
+    }
+
+    /// Whether upgraded components are written back when read, so they
+    /// only need to be upgraded once. Off by default, leaving the stored
+    /// components untouched.
+    pub fn write_back(mut self, write_back: bool) -> Self {
+        self.write_back = write_back;
+        self
+    }
+
+    pub fn writes_back(&self) -> bool {
+        self.write_back
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty()
+    }
+
+    /// Upgrades the stored contents of the component from one version to
+    /// another, failing with [`AccessError::VersionMismatch`] if no chain of
+    /// upgrades leads there.
+    pub fn migrate<F: Format>(
+        &self,
+        component: &str,
+        contents: &F::Data,
+        from: Version,
+        to: Version,
+    ) -> Result<F::Data, AccessError> {
+        let mismatch = || AccessError::VersionMismatch {
+            component: component.to_string(),
+            stored: from,
+            expected: to,
+        };
+
+        let mut value = F::deserialize::<Value>(contents)?;
+        let mut version = from;
+
+        // Every upgrade is used at most once, so chains which loop around
+        // end up failing rather than upgrading forever.
+        let mut upgrades = 0;
+        while version != to {
+            let migration = self
+                .migrations
+                .get(&(component.to_string(), version))
+                .filter(|_| upgrades < self.migrations.len())
+                .ok_or_else(mismatch)?;
+
+            value = (migration.upgrade)(value)?;
+            version = migration.to;
+            upgrades += 1;
+        }
+
+        F::serialize(value)
+    }
+}
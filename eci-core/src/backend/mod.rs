@@ -1,23 +1,84 @@
 mod access;
+mod admin;
+#[cfg(feature = "async")]
+mod asynchronous;
+mod clock;
+mod compressed;
+mod context;
+mod copy;
+mod despawn;
+mod envelope;
+mod ids;
 mod lock;
-use std::{error::Error, fmt::Display, sync::Arc};
+mod metrics;
+mod migration;
+mod naming;
+mod observer;
+mod predicate;
+mod ttl;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 pub use access::*;
+pub use admin::*;
+#[cfg(feature = "async")]
+pub use asynchronous::*;
+pub use clock::*;
+pub use compressed::Compressed;
+pub use context::*;
+pub use despawn::DespawnReport;
+pub use envelope::{unseal, Envelope, MalformedEnvelope};
+pub use ids::*;
 pub use lock::*;
+pub use metrics::{AtomicLockMetrics, LockCounts, LockMetrics};
+pub use migration::MigrationRegistry;
+pub use naming::*;
+pub use observer::Observer;
+pub use predicate::*;
+pub use ttl::LockTtl;
 
-use crate::Entity;
+use metrics::Metering;
+use observer::Observers;
+use ttl::HoldTimes;
 
-pub trait JointBackend<F: Format>: AccessBackend<F> + LockingBackend {}
+use log::warn;
+use uuid::Uuid;
 
-impl<T, F> JointBackend<F> for T
-where
-    F: Format,
-    T: AccessBackend<F> + LockingBackend,
-{
+use crate::{Entity, Version};
+
+/// A lock along with the components read under it.
+pub type LockedRead<F> = (Lock, Vec<Option<SerializedComponent<F>>>);
+
+/// Backends which store both components and their locks.
+pub trait JointBackend<F: Format>: AccessBackend<F> + LockingBackend {
+    /// Locks the entity's components and reads them, so nobody gets to
+    /// write them in between. Backends which can do both in a single
+    /// transaction should, but by default the components are read right
+    /// after locking them, releasing the lock again if reading fails.
+    fn read_and_lock(
+        &self,
+        entity: Entity,
+        locks: Vec<LockDescriptor>,
+        components: Vec<ExtractionDescriptor>,
+        expires: Expiry,
+    ) -> Result<LockedRead<F>, BackendError> {
+        let lock = self.acquire_lock(entity, locks, expires)?;
+        match self.read_components(entity, components) {
+            Ok(components) => Ok((lock, components)),
+            Err(err) => {
+                // Failing to release the lock only leaves it to expire, which
+                // matters less than the reason reading failed.
+                let _ = self.release_lock(lock);
+                Err(err.into())
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
-pub enum Backend<F: Format> {
+enum Storage<F: Format> {
     Disjoint {
         locking: Arc<dyn LockingBackend>,
         access: Arc<dyn AccessBackend<F>>,
@@ -27,16 +88,230 @@ pub enum Backend<F: Format> {
     },
 }
 
+#[derive(Clone)]
+pub struct Backend<F: Format> {
+    storage: Storage<F>,
+    limits: Option<DeserializationLimits>,
+    lock_ttl: LockTtl,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn EntityIdGenerator>,
+    hold_times: Arc<HoldTimes>,
+    metering: Option<Arc<Metering>>,
+    /// Whether components may only be written under a lock.
+    mandatory_locking: bool,
+    on_release_error: Option<ReleaseErrorHook>,
+    migrations: Arc<MigrationRegistry>,
+    /// Whether components are written in an [`Envelope`].
+    envelopes: bool,
+    schema_checks: Option<SchemaCheck>,
+    observers: Arc<Observers>,
+}
+
+/// What to do when a component was stored with other fields than the ones
+/// it is read with, see [`Backend::with_schema_checks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaCheck {
+    /// Logs a warning, and reads the component regardless.
+    Warn,
+    /// Fails with [`AccessError::SchemaMismatch`].
+    Reject,
+}
+
+/// Lock duration used by backends unless configured otherwise.
+pub const DEFAULT_LOCK_TTL: Duration = Duration::from_secs(3600);
+
 impl<F: Format> AccessBackend<F> for Backend<F> {
+    fn supports_atomic_writes(&self) -> bool {
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.supports_atomic_writes(),
+            Storage::Joint { backend } => backend.supports_atomic_writes(),
+        }
+    }
+
     fn write_components(
         &self,
         entity: Entity,
         components: Vec<SerializedComponent<F>>,
     ) -> Result<(), AccessError> {
-        match self {
-            Backend::Disjoint { locking: _, access } => access.write_components(entity, components),
-            Backend::Joint { backend } => backend.write_components(entity, components),
+        self.check_unlocked_write(entity, names(&components))?;
+        let observed = self.observed_writes(&components);
+        let components = self.seal(components);
+
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.write_components(entity, components),
+            Storage::Joint { backend } => backend.write_components(entity, components),
+        }
+        .map(|()| self.observers.written(entity, &observed))
+    }
+
+    /// Separate locking backends cannot check the lock as part of the write,
+    /// so the lock is looked up before writing instead. This is only a best
+    /// effort, as the lock may expire or be force released in between.
+    fn write_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        let observed = self.observed_writes(&components);
+        let components = self.seal(components);
+
+        match &self.storage {
+            Storage::Disjoint { locking, access } => {
+                check_held(locking.as_ref(), entity, names(&components), lock)?;
+                access.write_components(entity, components)
+            }
+            Storage::Joint { backend } => backend.write_components_locked(entity, components, lock),
+        }
+        .map(|()| self.observers.written(entity, &observed))
+    }
+
+    fn write_components_batch(
+        &self,
+        batch: Vec<(Entity, Vec<SerializedComponent<F>>)>,
+    ) -> Result<(), AccessError> {
+        for (entity, components) in &batch {
+            self.check_unlocked_write(*entity, names(components))?;
+        }
+        let observed: Vec<_> = batch
+            .iter()
+            .map(|(entity, components)| (*entity, self.observed_writes(components)))
+            .collect();
+        let batch = batch
+            .into_iter()
+            .map(|(entity, components)| (entity, self.seal(components)))
+            .collect();
+
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.write_components_batch(batch),
+            Storage::Joint { backend } => backend.write_components_batch(batch),
+        }?;
+
+        for (entity, components) in observed {
+            self.observers.written(entity, &components);
+        }
+        Ok(())
+    }
+
+    fn write_components_if_absent(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.check_unlocked_write(entity, names(&components))?;
+
+        // Only the components which were absent beforehand are written, so
+        // only those are observed.
+        let mut observed = self.observed_writes(&components);
+        if !observed.is_empty() {
+            let names: Vec<_> = observed.iter().map(|c| c.name.clone()).collect();
+            let mut present = self.contains(entity, &names)?.into_iter();
+            observed.retain(|_| present.next() == Some(false));
+        }
+        let components = self.seal(components);
+
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => {
+                access.write_components_if_absent(entity, components)
+            }
+            Storage::Joint { backend } => backend.write_components_if_absent(entity, components),
+        }
+        .map(|()| self.observers.written(entity, &observed))
+    }
+
+    /// Checked like [`Backend::write_components_locked`].
+    fn write_components_if_absent_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        let mut observed = self.observed_writes(&components);
+        if !observed.is_empty() {
+            let names: Vec<_> = observed.iter().map(|c| c.name.clone()).collect();
+            let mut present = self.contains(entity, &names)?.into_iter();
+            observed.retain(|_| present.next() == Some(false));
+        }
+        let components = self.seal(components);
+
+        match &self.storage {
+            Storage::Disjoint { locking, access } => {
+                check_held(locking.as_ref(), entity, names(&components), lock)?;
+                access.write_components_if_absent(entity, components)
+            }
+            Storage::Joint { backend } => {
+                backend.write_components_if_absent_locked(entity, components, lock)
+            }
         }
+        .map(|()| self.observers.written(entity, &observed))
+    }
+
+    fn update_components(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+    ) -> Result<(), AccessError> {
+        self.check_unlocked_write(entity, names(&components))?;
+        let observed = self.observed_writes(&components);
+        let components = self.seal(components);
+
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => {
+                access.update_components(entity, components)
+            }
+            Storage::Joint { backend } => backend.update_components(entity, components),
+        }
+        .map(|()| self.observers.written(entity, &observed))
+    }
+
+    /// The lock is only checked when locking is mandatory, see
+    /// [`Backend::with_mandatory_locking`], and on separate locking backends
+    /// only as a best effort, like [`Backend::write_components_locked`].
+    fn update_components_locked(
+        &self,
+        entity: Entity,
+        components: Vec<SerializedComponent<F>>,
+        lock: &Lock,
+    ) -> Result<(), AccessError> {
+        if !self.mandatory_locking {
+            return self.update_components(entity, components);
+        }
+
+        let observed = self.observed_writes(&components);
+        let components = self.seal(components);
+
+        match &self.storage {
+            Storage::Disjoint { locking, access } => {
+                check_held(locking.as_ref(), entity, names(&components), lock)?;
+                access.update_components(entity, components)
+            }
+            Storage::Joint { backend } => {
+                backend.update_components_locked(entity, components, lock)
+            }
+        }
+        .map(|()| self.observers.written(entity, &observed))
+    }
+
+    fn update_component_if_unchanged(
+        &self,
+        entity: Entity,
+        component: SerializedComponent<F>,
+        revision: u64,
+    ) -> Result<(), AccessError> {
+        let component = vec![component];
+        self.check_unlocked_write(entity, names(&component))?;
+        let observed = self.observed_writes(&component);
+        let component = self.seal(component).remove(0);
+
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => {
+                access.update_component_if_unchanged(entity, component, revision)
+            }
+            Storage::Joint { backend } => {
+                backend.update_component_if_unchanged(entity, component, revision)
+            }
+        }
+        .map(|()| self.observers.written(entity, &observed))
     }
 
     fn read_components(
@@ -44,9 +319,217 @@ impl<F: Format> AccessBackend<F> for Backend<F> {
         entity: Entity,
         descriptors: Vec<ExtractionDescriptor>,
     ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
-        match self {
-            Backend::Disjoint { locking: _, access } => access.read_components(entity, descriptors),
-            Backend::Joint { backend } => backend.read_components(entity, descriptors),
+        let expected = expectations(&descriptors);
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.read_components(entity, descriptors),
+            Storage::Joint { backend } => backend.read_components(entity, descriptors),
+        }
+        .and_then(|components| self.check_sizes(components))
+        .and_then(|components| self.unseal_all(components, &expected))
+    }
+
+    fn remove_components(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        self.check_unlocked_write(entity, descriptors.iter().map(|d| d.name.as_str()))?;
+        let expected = expectations(&descriptors);
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => {
+                access.remove_components(entity, descriptors)
+            }
+            Storage::Joint { backend } => backend.remove_components(entity, descriptors),
+        }
+        .and_then(|components| {
+            self.observers.removed(entity, &components);
+            self.unseal_all(components, &expected)
+        })
+    }
+
+    /// The lock is only checked when locking is mandatory, see
+    /// [`Backend::update_components_locked`].
+    fn remove_components_locked(
+        &self,
+        entity: Entity,
+        descriptors: Vec<ExtractionDescriptor>,
+        lock: &Lock,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if !self.mandatory_locking {
+            return self.remove_components(entity, descriptors);
+        }
+
+        let expected = expectations(&descriptors);
+        match &self.storage {
+            Storage::Disjoint { locking, access } => {
+                check_held(
+                    locking.as_ref(),
+                    entity,
+                    descriptors.iter().map(|d| d.name.as_str()),
+                    lock,
+                )?;
+                access.remove_components(entity, descriptors)
+            }
+            Storage::Joint { backend } => {
+                backend.remove_components_locked(entity, descriptors, lock)
+            }
+        }
+        .and_then(|components| {
+            self.observers.removed(entity, &components);
+            self.unseal_all(components, &expected)
+        })
+    }
+
+    fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.entities_with(component),
+            Storage::Joint { backend } => backend.entities_with(component),
+        }
+        .map(without_resources)
+    }
+
+    fn modified_since(
+        &self,
+        component: &str,
+        since: SystemTime,
+    ) -> Result<Vec<Entity>, AccessError> {
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.modified_since(component, since),
+            Storage::Joint { backend } => backend.modified_since(component, since),
+        }
+        .map(without_resources)
+    }
+
+    fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.list_entities(),
+            Storage::Joint { backend } => backend.list_entities(),
+        }
+        .map(without_resources)
+    }
+
+    fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.list_components(entity),
+            Storage::Joint { backend } => backend.list_components(entity),
+        }
+    }
+
+    fn contains(&self, entity: Entity, names: &[String]) -> Result<Vec<bool>, AccessError> {
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.contains(entity, names),
+            Storage::Joint { backend } => backend.contains(entity, names),
+        }
+    }
+
+    fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.stats(),
+            Storage::Joint { backend } => backend.stats(),
+        }
+    }
+
+    /// Backends cannot see into enveloped contents, so predicates are
+    /// evaluated by reading components when envelopes are enabled.
+    fn find_entities_where(&self, predicates: Vec<Predicate>) -> Result<Vec<Entity>, AccessError> {
+        if self.envelopes {
+            return scan_entities_where(self, &predicates);
+        }
+
+        match &self.storage {
+            Storage::Disjoint { locking: _, access } => access.find_entities_where(predicates),
+            Storage::Joint { backend } => backend.find_entities_where(predicates),
+        }
+        .map(without_resources)
+    }
+}
+
+fn names<F: Format>(components: &[SerializedComponent<F>]) -> impl Iterator<Item = &str> {
+    components.iter().map(|component| component.name.as_str())
+}
+
+/// Fails with [`AccessError::LockRequired`] unless the lock is listed as
+/// holding a write lock on each of the named components of the entity.
+fn check_held<'a>(
+    locking: &dyn LockingBackend,
+    entity: Entity,
+    names: impl Iterator<Item = &'a str>,
+    lock: &Lock,
+) -> Result<(), AccessError> {
+    let held = locking
+        .list_locks(Some(entity))
+        .map_err(AccessError::implementation)?;
+
+    for name in names {
+        let covered = held.iter().any(|held| {
+            held.id == lock.id()
+                && (held.mode == LockingMode::Exclusive
+                    || (held.mode == LockingMode::Write && held.component == name))
+        });
+
+        if !covered {
+            return Err(AccessError::LockRequired(entity, name.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Leaves [`Entity::RESOURCES`] out of listed entities, since resources
+/// belong to no entity in particular.
+fn without_resources(mut entities: Vec<Entity>) -> Vec<Entity> {
+    entities.retain(|entity| *entity != Entity::RESOURCES);
+    entities
+}
+
+impl<F: Format> JointBackend<F> for Backend<F> {
+    fn read_and_lock(
+        &self,
+        entity: Entity,
+        locks: Vec<LockDescriptor>,
+        components: Vec<ExtractionDescriptor>,
+        expires: Expiry,
+    ) -> Result<LockedRead<F>, BackendError> {
+        let backend = match &self.storage {
+            Storage::Joint { backend } => backend,
+            Storage::Disjoint { .. } => {
+                let lock = self.acquire_lock(entity, locks, expires)?;
+                return match self.read_components(entity, components) {
+                    Ok(components) => Ok((lock, components)),
+                    Err(err) => {
+                        let _ = self.release_lock(lock);
+                        Err(err.into())
+                    }
+                };
+            }
+        };
+
+        let expected = expectations(&components);
+        let locks = lock::canonical_order(locks);
+        let held = ttl::component_set(&locks);
+        let names = metrics::component_names(&locks);
+        let (lock, components) = backend
+            .read_and_lock(entity, locks, components, expires)
+            .map_err(|err| match err {
+                BackendError::Locking(err) => BackendError::Locking(self.metered_failure(err)),
+                err => err,
+            })?;
+
+        self.hold_times
+            .acquired(&self.lock_ttl, &lock, held, self.now());
+        if let Some(metering) = &self.metering {
+            metering.acquired(&lock, names);
+        }
+
+        match self
+            .check_sizes(components)
+            .and_then(|components| self.unseal_all(components, &expected))
+        {
+            Ok(components) => Ok((lock, components)),
+            Err(err) => {
+                let _ = self.release_lock(lock);
+                Err(err.into())
+            }
         }
     }
 }
@@ -56,28 +539,209 @@ impl<F: Format> LockingBackend for Backend<F> {
         &self,
         entity: Entity,
         descriptors: Vec<LockDescriptor>,
-        expires_in: std::time::Duration,
+        expires: Expiry,
     ) -> Result<Lock, LockingError> {
-        match self {
-            Backend::Disjoint { locking, access: _ } => {
-                locking.acquire_lock(entity, descriptors, expires_in)
+        let descriptors = lock::canonical_order(descriptors);
+        let components = ttl::component_set(&descriptors);
+        let names = metrics::component_names(&descriptors);
+        let lock = match &self.storage {
+            Storage::Disjoint { locking, access: _ } => {
+                locking.acquire_lock(entity, descriptors, expires)
             }
-            Backend::Joint { backend } => backend.acquire_lock(entity, descriptors, expires_in),
+            Storage::Joint { backend } => backend.acquire_lock(entity, descriptors, expires),
+        }
+        .map_err(|err| self.metered_failure(err))?;
+
+        self.hold_times
+            .acquired(&self.lock_ttl, &lock, components, self.now());
+        if let Some(metering) = &self.metering {
+            metering.acquired(&lock, names);
+        }
+        Ok(lock)
+    }
+
+    fn acquire_lock_blocking(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+        wait_up_to: Duration,
+    ) -> Result<Lock, LockingError> {
+        let descriptors = lock::canonical_order(descriptors);
+        let components = ttl::component_set(&descriptors);
+        let names = metrics::component_names(&descriptors);
+        let lock = match &self.storage {
+            Storage::Disjoint { locking, access: _ } => {
+                locking.acquire_lock_blocking(entity, descriptors, expires, wait_up_to)
+            }
+            Storage::Joint { backend } => {
+                backend.acquire_lock_blocking(entity, descriptors, expires, wait_up_to)
+            }
+        }
+        .map_err(|err| self.metered_failure(err))?;
+
+        self.hold_times
+            .acquired(&self.lock_ttl, &lock, components, self.now());
+        if let Some(metering) = &self.metering {
+            metering.acquired(&lock, names);
+        }
+        Ok(lock)
+    }
+
+    fn acquire_locks(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<Lock, LockingError> {
+        let mut requests: Vec<_> = requests
+            .into_iter()
+            .map(|(entity, descriptors)| (entity, lock::canonical_order(descriptors)))
+            .collect();
+        requests.sort_by_key(|(entity, _)| *entity);
+
+        let components = ttl::component_set(
+            &requests
+                .iter()
+                .flat_map(|(_, descriptors)| descriptors.iter().cloned())
+                .collect::<Vec<_>>(),
+        );
+        let names =
+            metrics::component_names(requests.iter().flat_map(|(_, descriptors)| descriptors));
+        let lock = match &self.storage {
+            Storage::Disjoint { locking, access: _ } => locking.acquire_locks(requests, expires_in),
+            Storage::Joint { backend } => backend.acquire_locks(requests, expires_in),
+        }
+        .map_err(|err| self.metered_failure(err))?;
+
+        self.hold_times
+            .acquired(&self.lock_ttl, &lock, components, self.now());
+        if let Some(metering) = &self.metering {
+            metering.acquired(&lock, names);
         }
+        Ok(lock)
     }
 
     fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
-        match self {
-            Backend::Disjoint { locking, access: _ } => locking.release_lock(lock),
-            Backend::Joint { backend } => backend.release_lock(lock),
+        self.hold_times.released(&lock, self.now());
+        if let Some(metering) = &self.metering {
+            metering.released(&lock, self.now());
+        }
+
+        match &self.storage {
+            Storage::Disjoint { locking, access: _ } => locking.release_lock(lock),
+            Storage::Joint { backend } => backend.release_lock(lock),
         }
     }
+
+    fn upgrade_lock(
+        &self,
+        lock: &Lock,
+        entity: Entity,
+        component: String,
+    ) -> Result<(), LockingError> {
+        match &self.storage {
+            Storage::Disjoint { locking, access: _ } => {
+                locking.upgrade_lock(lock, entity, component)
+            }
+            Storage::Joint { backend } => backend.upgrade_lock(lock, entity, component),
+        }
+        .map_err(|err| self.metered_failure(err))
+    }
+
+    fn force_release(&self, lock_id: Uuid, admin: Administrative) -> Result<usize, LockingError> {
+        match &self.storage {
+            Storage::Disjoint { locking, access: _ } => locking.force_release(lock_id, admin),
+            Storage::Joint { backend } => backend.force_release(lock_id, admin),
+        }
+    }
+
+    fn force_release_entity(
+        &self,
+        entity: Entity,
+        admin: Administrative,
+    ) -> Result<usize, LockingError> {
+        match &self.storage {
+            Storage::Disjoint { locking, access: _ } => locking.force_release_entity(entity, admin),
+            Storage::Joint { backend } => backend.force_release_entity(entity, admin),
+        }
+    }
+
+    fn list_locks(&self, filter: Option<Entity>) -> Result<Vec<ActiveLock>, LockingError> {
+        match &self.storage {
+            Storage::Disjoint { locking, access: _ } => locking.list_locks(filter),
+            Storage::Joint { backend } => backend.list_locks(filter),
+        }
+    }
+
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        match &self.storage {
+            Storage::Disjoint { locking, access: _ } => locking.lock_stats(),
+            Storage::Joint { backend } => backend.lock_stats(),
+        }
+    }
+
+    fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        match &self.storage {
+            Storage::Disjoint { locking, access: _ } => locking.renew_lock(lock, extend_by),
+            Storage::Joint { backend } => backend.renew_lock(lock, extend_by),
+        }
+        .inspect(|()| {
+            self.hold_times
+                .renewed(lock, self.now().checked_add(extend_by));
+        })
+        .inspect_err(|err| {
+            if let (Some(metering), LockingError::Expired(_)) = (&self.metering, err) {
+                metering.expired(lock);
+            }
+        })
+    }
+
+    fn acquire_locks_bulk(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        let requests: Vec<_> = requests
+            .into_iter()
+            .map(|(entity, descriptors)| (entity, lock::canonical_order(descriptors)))
+            .collect();
+        let names = requests
+            .iter()
+            .map(|(entity, descriptors)| (*entity, metrics::component_names(descriptors)))
+            .collect();
+
+        let result = match &self.storage {
+            Storage::Disjoint { locking, access: _ } => {
+                locking.acquire_locks_bulk(requests, expires_in)
+            }
+            Storage::Joint { backend } => backend.acquire_locks_bulk(requests, expires_in),
+        }?;
+
+        if let Some(metering) = &self.metering {
+            metering.acquired_bulk(&result, names);
+        }
+        Ok(result)
+    }
 }
 
 impl<F: Format> Backend<F> {
     pub fn from_joint<T: JointBackend<F> + 'static>(backend: T) -> Self {
-        Backend::Joint {
-            backend: Arc::new(backend),
+        Backend {
+            storage: Storage::Joint {
+                backend: Arc::new(backend),
+            },
+            limits: None,
+            lock_ttl: LockTtl::Fixed(DEFAULT_LOCK_TTL),
+            clock: Arc::new(SystemClock),
+            ids: Arc::new(RandomIds),
+            hold_times: Arc::default(),
+            metering: None,
+            mandatory_locking: false,
+            on_release_error: None,
+            migrations: Arc::default(),
+            envelopes: false,
+            schema_checks: None,
+            observers: Arc::default(),
         }
     }
 
@@ -85,38 +749,373 @@ impl<F: Format> Backend<F> {
         access: A,
         locking: L,
     ) -> Self {
-        Backend::Disjoint {
-            access: Arc::new(access),
-            locking: Arc::new(locking),
+        Backend {
+            storage: Storage::Disjoint {
+                access: Arc::new(access),
+                locking: Arc::new(locking),
+            },
+            limits: None,
+            lock_ttl: LockTtl::Fixed(DEFAULT_LOCK_TTL),
+            clock: Arc::new(SystemClock),
+            ids: Arc::new(RandomIds),
+            hold_times: Arc::default(),
+            metering: None,
+            mandatory_locking: false,
+            on_release_error: None,
+            migrations: Arc::default(),
+            envelopes: false,
+            schema_checks: None,
+            observers: Arc::default(),
+        }
+    }
+
+    /// Treats all stored data as untrusted, enforcing the given limits on
+    /// the size and complexity of components read through this backend.
+    ///
+    /// Strings and enums need no stricter handling than usual: every format
+    /// already rejects invalid UTF-8 and unknown enum variants, rather than
+    /// replacing or skipping them.
+    pub fn untrusted_mode(mut self, limits: DeserializationLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    pub fn limits(&self) -> Option<&DeserializationLimits> {
+        self.limits.as_ref()
+    }
+
+    /// Sets the duration of locks acquired through this backend, when
+    /// no explicit duration is given.
+    pub fn with_lock_ttl<T: Into<LockTtl>>(mut self, lock_ttl: T) -> Self {
+        self.lock_ttl = lock_ttl.into();
+        self
+    }
+
+    pub fn lock_ttl(&self) -> &LockTtl {
+        &self.lock_ttl
+    }
+
+    /// Duration of a new lock on the described components, when no
+    /// explicit duration is given.
+    pub fn lock_ttl_for(&self, descriptors: &[LockDescriptor]) -> Duration {
+        self.hold_times.ttl(&self.lock_ttl, descriptors)
+    }
+
+    /// Replaces the clock used for tracking lock expiry and hold times.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    /// Replaces the generator of new entities, which creates random v4
+    /// UUIDs unless replaced.
+    pub fn with_entity_ids<G: EntityIdGenerator + 'static>(mut self, generator: G) -> Self {
+        self.ids = Arc::new(generator);
+        self
+    }
+
+    /// A new entity from the backend's generator, see
+    /// [`Backend::with_entity_ids`].
+    pub fn new_entity(&self) -> Entity {
+        self.ids.generate()
+    }
+
+    /// Requires components to be written, updated and removed through
+    /// [`AccessBackend::write_components_locked`],
+    /// [`AccessBackend::update_components_locked`] and
+    /// [`AccessBackend::remove_components_locked`], rejecting other writes
+    /// and removals of components with [`AccessError::LockRequired`].
+    /// Locking is advisory unless this is set.
+    pub fn with_mandatory_locking(mut self) -> Self {
+        self.mandatory_locking = true;
+        self
+    }
+
+    /// Calls the hook whenever a lock released in the background fails to
+    /// be released, so applications can raise alerts about locks which will
+    /// keep blocking others until they expire.
+    pub fn on_release_error<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&str, &LockingError) + Send + Sync + 'static,
+    {
+        self.on_release_error = Some(Arc::new(hook));
+        self
+    }
+
+    pub fn release_error_hook(&self) -> Option<&ReleaseErrorHook> {
+        self.on_release_error.as_ref()
+    }
+
+    /// Reports locks acquired, released and contended through this backend
+    /// to the given metrics, whichever backend is used underneath.
+    pub fn with_lock_metrics<M: LockMetrics + 'static>(mut self, metrics: M) -> Self {
+        self.metering = Some(Arc::new(Metering::new(Arc::new(metrics))));
+        self
+    }
+
+    /// Upgrades components stored by older versions of their layout when
+    /// they are read.
+    pub fn with_migrations(mut self, migrations: MigrationRegistry) -> Self {
+        self.migrations = Arc::new(migrations);
+        self
+    }
+
+    pub fn migrations(&self) -> &MigrationRegistry {
+        &self.migrations
+    }
+
+    /// Writes components in an [`Envelope`] naming the format and version
+    /// they were written with, so reading them with another format fails
+    /// with [`AccessError::FormatMismatch`] rather than garbled contents.
+    /// Components are read whether or not they are in an envelope, so this
+    /// can be enabled on existing data.
+    pub fn with_envelopes(mut self) -> Self {
+        self.envelopes = true;
+        self
+    }
+
+    /// Records the [`crate::Component::SCHEMA_HASH`] of components written
+    /// in their envelope, and compares it with the hash of the component
+    /// they are read as, which catches fields being added, removed or
+    /// changed without the component's version changing with them.
+    /// Components written without a hash, such as before this was enabled,
+    /// are read as they are.
+    ///
+    /// Schema hashes are kept in envelopes, so this also enables
+    /// [`Backend::with_envelopes`].
+    pub fn with_schema_checks(mut self, check: SchemaCheck) -> Self {
+        self.envelopes = true;
+        self.schema_checks = Some(check);
+        self
+    }
+
+    /// Copies of the components which are observed, taken before they are
+    /// sealed so observers get the contents as they were serialized.
+    fn observed_writes(
+        &self,
+        components: &[SerializedComponent<F>],
+    ) -> Vec<SerializedComponent<F>> {
+        components
+            .iter()
+            .filter(|component| self.observers.observes_writes(&component.name))
+            .map(|component| SerializedComponent {
+                contents: component.contents.as_ref().to_vec().into(),
+                name: component.name.clone(),
+                version: component.version,
+                schema: component.schema,
+                metadata: None,
+            })
+            .collect()
+    }
+
+    fn seal(&self, components: Vec<SerializedComponent<F>>) -> Vec<SerializedComponent<F>> {
+        if !self.envelopes {
+            return components;
+        }
+
+        let format = F::name();
+        components
+            .into_iter()
+            .map(|component| {
+                let mut envelope =
+                    Envelope::new(&format, component.version, component.contents.as_ref());
+                if let (Some(_), Some(schema)) = (self.schema_checks, component.schema) {
+                    envelope = envelope.with_schema(schema);
+                }
+
+                SerializedComponent {
+                    contents: envelope.seal().into(),
+                    ..component
+                }
+            })
+            .collect()
+    }
+
+    /// Compares the schema hash the component was stored with, if it was
+    /// stored with one, with the expected hash, warning about or rejecting
+    /// a mismatch as configured with [`Backend::with_schema_checks`].
+    pub fn check_schema(
+        &self,
+        component: &SerializedComponent<F>,
+        expected: u64,
+    ) -> Result<(), AccessError> {
+        let (Some(check), Some(stored)) = (self.schema_checks, component.schema) else {
+            return Ok(());
+        };
+
+        if stored == expected {
+            return Ok(());
+        }
+
+        let err = AccessError::SchemaMismatch {
+            component: component.name.clone(),
+            stored,
+            expected,
+        };
+        match check {
+            SchemaCheck::Warn => {
+                warn!("{err}");
+                Ok(())
+            }
+            SchemaCheck::Reject => Err(err),
+        }
+    }
+
+    /// Strips the envelopes from components read, if they have one, and
+    /// checks the schema hashes of components stored with the version
+    /// expected of them. Components stored with another version have
+    /// another layout, so their hashes are bound to differ.
+    fn unseal_all(
+        &self,
+        components: Vec<Option<SerializedComponent<F>>>,
+        expected: &[(Option<Version>, Option<u64>)],
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        components
+            .into_iter()
+            .zip(expected)
+            .map(|(component, expected)| {
+                let Some(component) = component else {
+                    return Ok(None);
+                };
+
+                let schema = Envelope::open(component.contents.as_ref())?
+                    .and_then(|envelope| envelope.schema);
+                let component = SerializedComponent {
+                    contents: unseal::<F>(&component.name, component.contents.as_ref())?.into(),
+                    schema: schema.or(component.schema),
+                    ..component
+                };
+
+                if let (Some(version), Some(schema)) = expected {
+                    if *version == component.version {
+                        self.check_schema(&component, *schema)?;
+                    }
+                }
+
+                Ok(Some(component))
+            })
+            .collect()
+    }
+
+    fn metered_failure(&self, err: LockingError) -> LockingError {
+        if let Some(metering) = &self.metering {
+            metering.failed(&err);
+        }
+        err
+    }
+
+    fn check_sizes(
+        &self,
+        components: Vec<Option<SerializedComponent<F>>>,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        if let Some(limits) = &self.limits {
+            for component in components.iter().flatten() {
+                limits.check_size(&component.name, component.contents.as_ref().len())?;
+            }
+        }
+
+        Ok(components)
+    }
+
+    fn check_unlocked_write<'a>(
+        &self,
+        entity: Entity,
+        mut names: impl Iterator<Item = &'a str>,
+    ) -> Result<(), AccessError> {
+        match names.next() {
+            Some(name) if self.mandatory_locking => {
+                Err(AccessError::LockRequired(entity, name.to_string()))
+            }
+            _ => Ok(()),
         }
     }
 }
 
-#[derive(Debug)]
+/// The versions and schema hashes expected of the described components.
+fn expectations(descriptors: &[ExtractionDescriptor]) -> Vec<(Option<Version>, Option<u64>)> {
+    descriptors
+        .iter()
+        .map(|descriptor| (descriptor.version, descriptor.schema))
+        .collect()
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum BackendError {
-    Access(AccessError),
-    Locking(LockingError),
+    #[error("access error {0}")]
+    Access(#[from] AccessError),
+    #[error("locking error {0}")]
+    Locking(#[from] LockingError),
+    #[error("{0}: {1}")]
+    Context(String, #[source] Box<BackendError>),
+    /// The same component was requested more than once, with modes that
+    /// would conflict with each other.
+    #[error(
+        "invalid selection: {component} requested with conflicting modes {}",
+        join_modes(.modes)
+    )]
+    InvalidSelection {
+        component: String,
+        modes: Vec<LockingMode>,
+    },
+    /// The entity has no components, such as when it was already despawned.
+    #[error("{0} has no components")]
+    EntityNotFound(Entity),
 }
 
-impl From<LockingError> for BackendError {
-    fn from(locking: LockingError) -> Self {
-        BackendError::Locking(locking)
-    }
+fn join_modes(modes: &[LockingMode]) -> String {
+    let modes: Vec<String> = modes.iter().map(ToString::to_string).collect();
+    modes.join(", ")
 }
 
-impl From<AccessError> for BackendError {
-    fn from(access: AccessError) -> Self {
-        BackendError::Access(access)
+impl BackendError {
+    /// Describes what was being done when the error occurred.
+    pub fn context<C: Into<String>>(self, context: C) -> Self {
+        BackendError::Context(context.into(), Box::new(self))
     }
-}
 
-impl Display for BackendError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// All context attached to the error, outermost first.
+    pub fn contexts(&self) -> Vec<&str> {
+        let mut contexts = Vec::new();
+        let mut err = self;
+        while let BackendError::Context(context, inner) = err {
+            contexts.push(context.as_str());
+            err = inner;
+        }
+
+        contexts
+    }
+
+    /// The underlying error, stripped of any context.
+    pub fn root(&self) -> &BackendError {
         match self {
-            BackendError::Access(access) => write!(f, "access error {}", access),
-            BackendError::Locking(locking) => write!(f, "locking error {}", locking),
+            BackendError::Context(_, inner) => inner.root(),
+            err => err,
         }
     }
-}
 
-impl Error for BackendError {}
+    /// Whether the underlying error was caused by someone else holding or
+    /// writing the components, see [`AccessError::is_conflict`] and
+    /// [`LockingError::is_conflict`].
+    pub fn is_conflict(&self) -> bool {
+        match self.root() {
+            BackendError::Access(access) => access.is_conflict(),
+            BackendError::Locking(locking) => locking.is_conflict(),
+            _ => false,
+        }
+    }
+
+    /// Whether retrying the same operation later may well succeed, see
+    /// [`AccessError::is_transient`] and [`LockingError::is_transient`].
+    pub fn is_transient(&self) -> bool {
+        match self.root() {
+            BackendError::Access(access) => access.is_transient(),
+            BackendError::Locking(locking) => locking.is_transient(),
+            _ => false,
+        }
+    }
+}
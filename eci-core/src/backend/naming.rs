@@ -0,0 +1,79 @@
+/// Maps logical component names to the names of the tables, keys or
+/// similar physical storage used by an access backend.
+///
+/// Only access backends apply the strategy. Lock descriptors always use the
+/// logical component name, so locking backends are unaffected by it.
+pub trait NamingStrategy: Send + Sync {
+    fn table_name(&self, component: &str) -> String;
+
+    /// Identifies the strategy and its configuration, so backends can
+    /// detect an existing store being opened with a different strategy.
+    fn describe(&self) -> String;
+}
+
+/// Uses component names as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Verbatim;
+
+impl NamingStrategy for Verbatim {
+    fn table_name(&self, component: &str) -> String {
+        component.to_string()
+    }
+
+    fn describe(&self) -> String {
+        "verbatim".to_string()
+    }
+}
+
+/// Converts component names to snake_case and prepends a common prefix,
+/// turning `PlayerHealth` into `eci_player_health` given the prefix `eci_`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnakeCasePrefixed {
+    pub prefix: String,
+}
+
+impl SnakeCasePrefixed {
+    pub fn new<P: Into<String>>(prefix: P) -> Self {
+        SnakeCasePrefixed {
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl NamingStrategy for SnakeCasePrefixed {
+    fn table_name(&self, component: &str) -> String {
+        let chars: Vec<char> = component.chars().collect();
+        let mut name = self.prefix.clone();
+
+        for (i, c) in chars.iter().enumerate() {
+            if c.is_uppercase() {
+                let previous = i.checked_sub(1).map(|i| chars[i]);
+                let next = chars.get(i + 1);
+
+                // Split "PlayerHealth" before the H and "HTTPServer" before the S.
+                let boundary = match previous {
+                    Some(previous) if previous.is_lowercase() || previous.is_numeric() => true,
+                    Some(previous) if previous.is_uppercase() => {
+                        next.map(|next| next.is_lowercase()).unwrap_or(false)
+                    }
+                    _ => false,
+                };
+
+                if boundary {
+                    name.push('_');
+                }
+                name.extend(c.to_lowercase());
+            } else if c.is_alphanumeric() {
+                name.push(*c);
+            } else {
+                name.push('_');
+            }
+        }
+
+        name
+    }
+
+    fn describe(&self) -> String {
+        format!("snake_case_prefixed:{}", self.prefix)
+    }
+}
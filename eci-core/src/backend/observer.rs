@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use log::{error, warn};
+use serde::de::DeserializeOwned;
+
+use crate::{Component, Entity};
+
+use super::{unseal, Backend, Format, SerializedComponent};
+
+/// Called with the entity and the serialized contents of a component which
+/// was written or removed, see [`Backend::on_write`].
+pub type Observer = Arc<dyn Fn(Entity, &[u8]) + Send + Sync>;
+
+/// Observers of writes and removals, by component name.
+#[derive(Clone, Default)]
+pub(crate) struct Observers {
+    writes: HashMap<String, Vec<Observer>>,
+    removals: HashMap<String, Vec<Observer>>,
+}
+
+impl Observers {
+    pub fn observes_writes(&self, component: &str) -> bool {
+        self.writes.contains_key(component)
+    }
+
+    pub fn observes_removals(&self, component: &str) -> bool {
+        self.removals.contains_key(component)
+    }
+
+    pub fn written<F: Format>(&self, entity: Entity, components: &[SerializedComponent<F>]) {
+        for component in components {
+            notify(
+                &self.writes,
+                entity,
+                &component.name,
+                component.contents.as_ref(),
+            );
+        }
+    }
+
+    /// Notifies observers of the removed components, which are still in
+    /// their envelopes if they were stored in one.
+    pub fn removed<F: Format>(
+        &self,
+        entity: Entity,
+        components: &[Option<SerializedComponent<F>>],
+    ) {
+        for component in components.iter().flatten() {
+            if !self.observes_removals(&component.name) {
+                continue;
+            }
+
+            match unseal::<F>(&component.name, component.contents.as_ref()) {
+                Ok(contents) => notify(&self.removals, entity, &component.name, &contents),
+                Err(err) => warn!(
+                    "not notifying observers of the removal of {} from {entity}: {err}",
+                    component.name
+                ),
+            }
+        }
+    }
+}
+
+/// Calls each observer of the component, catching and logging panics so a
+/// misbehaving observer cannot fail the write it observes.
+fn notify(
+    observers: &HashMap<String, Vec<Observer>>,
+    entity: Entity,
+    component: &str,
+    contents: &[u8],
+) {
+    for observer in observers.get(component).into_iter().flatten() {
+        if catch_unwind(AssertUnwindSafe(|| observer(entity, contents))).is_err() {
+            error!("observer of {component} panicked when notified about {entity}");
+        }
+    }
+}
+
+impl<F: Format> Backend<F> {
+    /// Calls the observer with the contents of the component whenever it
+    /// is written through this backend, once the write has succeeded.
+    /// Observers are called on the writing thread, so they should be quick,
+    /// and panics are caught and logged rather than failing the write.
+    ///
+    /// Only writes through this backend, or its clones, are observed.
+    pub fn on_write<N: Into<String>>(mut self, component: N, observer: Observer) -> Self {
+        Arc::make_mut(&mut self.observers)
+            .writes
+            .entry(component.into())
+            .or_default()
+            .push(observer);
+        self
+    }
+
+    /// Like [`Backend::on_write`], but for components removed through this
+    /// backend, called with the contents they had.
+    pub fn on_remove<N: Into<String>>(mut self, component: N, observer: Observer) -> Self {
+        Arc::make_mut(&mut self.observers)
+            .removals
+            .entry(component.into())
+            .or_default()
+            .push(observer);
+        self
+    }
+
+    /// Like [`Backend::on_write`], but deserializes the written component
+    /// first. Components which fail to deserialize are logged instead.
+    pub fn on_write_typed<T, O>(self, observer: O) -> Self
+    where
+        T: Component + DeserializeOwned,
+        O: Fn(Entity, T) + Send + Sync + 'static,
+    {
+        self.on_write(
+            T::COMPONENT_TYPE,
+            Arc::new(move |entity, contents| {
+                match F::deserialize::<T>(&contents.to_vec().into()) {
+                    Ok(value) => observer(entity, value),
+                    Err(err) => warn!(
+                        "not notifying observer of {} written to {entity}: {err}",
+                        T::COMPONENT_TYPE
+                    ),
+                }
+            }),
+        )
+    }
+}
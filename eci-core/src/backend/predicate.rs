@@ -0,0 +1,369 @@
+use std::{cmp::Ordering, fmt};
+
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::Entity;
+
+use super::{AccessBackend, AccessError, ExtractionDescriptor, Format};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    pub fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Eq => ordering == Ordering::Equal,
+            Comparison::Ne => ordering != Ordering::Equal,
+            Comparison::Lt => ordering == Ordering::Less,
+            Comparison::Le => ordering != Ordering::Greater,
+            Comparison::Gt => ordering == Ordering::Greater,
+            Comparison::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// A value fields are compared against. Fields only match values of the
+/// same kind, with integers and floats both counting as numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PredicateValue {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+}
+
+impl PredicateValue {
+    fn compare(&self, other: &PredicateValue) -> Option<Ordering> {
+        use PredicateValue::*;
+
+        match (self, other) {
+            (Integer(a), Integer(b)) => Some(a.cmp(b)),
+            (Integer(a), Float(b)) => (*a as f64).partial_cmp(b),
+            (Float(a), Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (Float(a), Float(b)) => a.partial_cmp(b),
+            (String(a), String(b)) => Some(a.cmp(b)),
+            (Bool(a), Bool(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($t:ty),+) => {
+        $(
+            impl From<$t> for PredicateValue {
+                fn from(value: $t) -> Self {
+                    PredicateValue::Integer(value.into())
+                }
+            }
+        )+
+    };
+}
+
+impl_from_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+/// Integers too large for an `i64` are compared as floats, the same way
+/// such fields are read by [`FieldValue`].
+macro_rules! impl_from_unsigned {
+    ($($t:ty),+) => {
+        $(
+            impl From<$t> for PredicateValue {
+                fn from(value: $t) -> Self {
+                    i64::try_from(value)
+                        .map(PredicateValue::Integer)
+                        .unwrap_or(PredicateValue::Float(value as f64))
+                }
+            }
+        )+
+    };
+}
+
+impl_from_unsigned!(u64, usize);
+
+impl From<f32> for PredicateValue {
+    fn from(value: f32) -> Self {
+        PredicateValue::Float(value.into())
+    }
+}
+
+impl From<f64> for PredicateValue {
+    fn from(value: f64) -> Self {
+        PredicateValue::Float(value)
+    }
+}
+
+impl From<&str> for PredicateValue {
+    fn from(value: &str) -> Self {
+        PredicateValue::String(value.to_string())
+    }
+}
+
+impl From<String> for PredicateValue {
+    fn from(value: String) -> Self {
+        PredicateValue::String(value)
+    }
+}
+
+impl From<bool> for PredicateValue {
+    fn from(value: bool) -> Self {
+        PredicateValue::Bool(value)
+    }
+}
+
+/// Compares a field of a component against a value. The path names the
+/// nested fields leading to the compared field, and is empty when the
+/// component itself is compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    pub component: String,
+    pub path: Vec<String>,
+    pub comparison: Comparison,
+    pub value: PredicateValue,
+}
+
+impl Predicate {
+    /// Evaluates the predicate against a deserialized component. Missing
+    /// fields and fields of another kind than the value never match.
+    pub fn matches(&self, component: &FieldValue) -> bool {
+        let mut field = component;
+        for name in &self.path {
+            match field.get(name) {
+                Some(inner) => field = inner,
+                None => return false,
+            }
+        }
+
+        field
+            .as_predicate_value()
+            .and_then(|value| value.compare(&self.value))
+            .map(|ordering| self.comparison.holds(ordering))
+            .unwrap_or(false)
+    }
+}
+
+/// A field of a component, from which predicates can be built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    component: &'static str,
+    path: Vec<String>,
+}
+
+impl Field {
+    /// Refers to the field at the dot-separated path within the component.
+    /// An empty path refers to the component itself.
+    pub fn new(component: &'static str, path: &str) -> Self {
+        Field {
+            component,
+            path: path
+                .split('.')
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    fn compare<V: Into<PredicateValue>>(self, comparison: Comparison, value: V) -> Predicate {
+        Predicate {
+            component: self.component.to_string(),
+            path: self.path,
+            comparison,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq<V: Into<PredicateValue>>(self, value: V) -> Predicate {
+        self.compare(Comparison::Eq, value)
+    }
+
+    pub fn ne<V: Into<PredicateValue>>(self, value: V) -> Predicate {
+        self.compare(Comparison::Ne, value)
+    }
+
+    pub fn lt<V: Into<PredicateValue>>(self, value: V) -> Predicate {
+        self.compare(Comparison::Lt, value)
+    }
+
+    pub fn le<V: Into<PredicateValue>>(self, value: V) -> Predicate {
+        self.compare(Comparison::Le, value)
+    }
+
+    pub fn gt<V: Into<PredicateValue>>(self, value: V) -> Predicate {
+        self.compare(Comparison::Gt, value)
+    }
+
+    pub fn ge<V: Into<PredicateValue>>(self, value: V) -> Predicate {
+        self.compare(Comparison::Ge, value)
+    }
+}
+
+/// A component deserialized without knowing its type, used to evaluate
+/// predicates in formats the backend cannot inspect by itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Seq(Vec<FieldValue>),
+    Map(Vec<(String, FieldValue)>),
+}
+
+impl FieldValue {
+    fn get(&self, name: &str) -> Option<&FieldValue> {
+        match self {
+            FieldValue::Map(fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            FieldValue::Seq(items) => items.get(name.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    fn as_predicate_value(&self) -> Option<PredicateValue> {
+        match self {
+            FieldValue::Bool(value) => Some(PredicateValue::Bool(*value)),
+            FieldValue::Integer(value) => Some(PredicateValue::Integer(*value)),
+            FieldValue::Float(value) => Some(PredicateValue::Float(*value)),
+            FieldValue::String(value) => Some(PredicateValue::String(value.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FieldValueVisitor)
+    }
+}
+
+struct FieldValueVisitor;
+
+impl<'de> Visitor<'de> for FieldValueVisitor {
+    type Value = FieldValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a self-describing value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<FieldValue, E> {
+        Ok(FieldValue::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<FieldValue, E> {
+        Ok(FieldValue::Integer(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<FieldValue, E> {
+        Ok(i64::try_from(value)
+            .map(FieldValue::Integer)
+            .unwrap_or(FieldValue::Float(value as f64)))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<FieldValue, E> {
+        Ok(FieldValue::Float(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<FieldValue, E> {
+        Ok(FieldValue::String(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<FieldValue, E> {
+        Ok(FieldValue::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<FieldValue, E> {
+        Ok(FieldValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<FieldValue, E> {
+        Ok(FieldValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<FieldValue, D::Error> {
+        FieldValue::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<FieldValue, D::Error> {
+        FieldValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FieldValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+
+        Ok(FieldValue::Seq(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldValue, A::Error> {
+        let mut fields = Vec::new();
+        while let Some(field) = map.next_entry()? {
+            fields.push(field);
+        }
+
+        Ok(FieldValue::Map(fields))
+    }
+}
+
+/// Finds the entities matching every predicate by reading and deserializing
+/// each candidate component. An empty list of predicates matches nothing.
+pub fn scan_entities_where<F, A>(
+    backend: &A,
+    predicates: &[Predicate],
+) -> Result<Vec<Entity>, AccessError>
+where
+    F: Format,
+    A: AccessBackend<F> + ?Sized,
+{
+    let first = match predicates.first() {
+        Some(first) => first,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut found = Vec::new();
+    for entity in backend.entities_with(&first.component)? {
+        let descriptors = predicates
+            .iter()
+            .map(|predicate| ExtractionDescriptor {
+                name: predicate.component.clone(),
+                version: None,
+                schema: None,
+            })
+            .collect();
+
+        let mut matches = true;
+        for (predicate, component) in predicates
+            .iter()
+            .zip(backend.read_components(entity, descriptors)?)
+        {
+            matches &= match component {
+                Some(component) => {
+                    predicate.matches(&F::deserialize::<FieldValue>(&component.contents)?)
+                }
+                None => false,
+            };
+        }
+
+        if matches {
+            found.push(entity);
+        }
+    }
+
+    Ok(found)
+}
@@ -0,0 +1,213 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use super::{Lock, LockDescriptor};
+
+/// Number of observed hold durations kept per set of components.
+const HOLD_WINDOW: usize = 128;
+
+/// Number of unreleased locks tracked at once. Locks which are never
+/// released and report no expiry are otherwise never forgotten.
+const HELD_LIMIT: usize = 4096;
+
+/// How long locks acquired through a backend are held for, unless an
+/// explicit duration is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockTtl {
+    Fixed(Duration),
+    /// Derives the duration from how long locks on the same set of
+    /// components were held in the past: `multiplier` times the 99th
+    /// percentile, clamped to `floor` and `ceiling`. Without any history,
+    /// `floor` is used.
+    Adaptive {
+        multiplier: f64,
+        floor: Duration,
+        ceiling: Duration,
+    },
+}
+
+impl From<Duration> for LockTtl {
+    fn from(ttl: Duration) -> Self {
+        LockTtl::Fixed(ttl)
+    }
+}
+
+/// Identifies a set of locked components, regardless of their order.
+pub(crate) fn component_set(descriptors: &[LockDescriptor]) -> String {
+    let mut components: Vec<_> = descriptors
+        .iter()
+        .map(|descriptor| format!("{}:{}", descriptor.name, descriptor.mode))
+        .collect();
+    components.sort();
+    components.join(",")
+}
+
+/// An unreleased lock, as tracked by [`HoldTimes`].
+struct Held {
+    components: String,
+    acquired: SystemTime,
+    expires: Option<SystemTime>,
+}
+
+/// Tracks how long locks are held between acquisition and release.
+#[derive(Default)]
+pub(crate) struct HoldTimes {
+    held: Mutex<HashMap<String, Held>>,
+    observed: Mutex<HashMap<String, VecDeque<Duration>>>,
+}
+
+impl HoldTimes {
+    /// Starts tracking the lock, if its hold time can affect later TTLs.
+    /// Locks which expired without being released are forgotten.
+    pub fn acquired(&self, lock_ttl: &LockTtl, lock: &Lock, components: String, now: SystemTime) {
+        if let LockTtl::Fixed(_) = lock_ttl {
+            return;
+        }
+
+        let mut held = self.held.lock().unwrap();
+        held.retain(|_, held| held.expires.is_none_or(|expires| expires > now));
+        if held.len() >= HELD_LIMIT {
+            let oldest = held
+                .iter()
+                .min_by_key(|(_, held)| held.acquired)
+                .map(|(id, _)| id.clone());
+            held.remove(&oldest.unwrap_or_default());
+        }
+
+        held.insert(
+            lock.id(),
+            Held {
+                components,
+                acquired: now,
+                expires: lock.expires_at(),
+            },
+        );
+    }
+
+    /// Moves the expiry of a tracked lock, unless it never expires.
+    pub fn renewed(&self, lock: &Lock, expires: Option<SystemTime>) {
+        if let Some(held) = self.held.lock().unwrap().get_mut(&lock.id()) {
+            if held.expires.is_some() {
+                held.expires = expires;
+            }
+        }
+    }
+
+    pub fn released(&self, lock: &Lock, now: SystemTime) {
+        let held = self.held.lock().unwrap().remove(&lock.id());
+
+        if let Some(Held {
+            components,
+            acquired,
+            ..
+        }) = held
+        {
+            let mut observed = self.observed.lock().unwrap();
+            let window = observed.entry(components).or_default();
+
+            if window.len() == HOLD_WINDOW {
+                window.pop_front();
+            }
+            window.push_back(now.duration_since(acquired).unwrap_or_default());
+        }
+    }
+
+    /// The 99th percentile of observed hold durations for the set of
+    /// components, if any have been observed.
+    pub fn p99(&self, components: &str) -> Option<Duration> {
+        let observed = self.observed.lock().unwrap();
+        let mut window: Vec<Duration> = observed.get(components)?.iter().copied().collect();
+        window.sort();
+
+        let rank = (window.len() * 99).div_ceil(100);
+        window.get(rank.saturating_sub(1)).copied()
+    }
+
+    pub fn ttl(&self, lock_ttl: &LockTtl, descriptors: &[LockDescriptor]) -> Duration {
+        match *lock_ttl {
+            LockTtl::Fixed(ttl) => ttl,
+            LockTtl::Adaptive {
+                multiplier,
+                floor,
+                ceiling,
+            } => self
+                .p99(&component_set(descriptors))
+                .map(|p99| p99.mul_f64(multiplier).max(floor).min(ceiling))
+                .unwrap_or(floor),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADAPTIVE: LockTtl = LockTtl::Adaptive {
+        multiplier: 2.0,
+        floor: Duration::from_secs(1),
+        ceiling: Duration::from_secs(60),
+    };
+
+    #[test]
+    fn fixed_ttl_tracks_nothing() {
+        let hold_times = HoldTimes::default();
+        let now = SystemTime::now();
+
+        let lock = Lock::new().with_expiry(now + Duration::from_secs(1));
+        hold_times.acquired(
+            &LockTtl::Fixed(Duration::from_secs(1)),
+            &lock,
+            "A:write".to_string(),
+            now,
+        );
+        assert!(hold_times.held.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn expired_locks_are_forgotten() {
+        let hold_times = HoldTimes::default();
+        let now = SystemTime::now();
+
+        let expired = Lock::new().with_expiry(now + Duration::from_secs(1));
+        hold_times.acquired(&ADAPTIVE, &expired, "A:write".to_string(), now);
+        let renewed = Lock::new().with_expiry(now + Duration::from_secs(1));
+        hold_times.acquired(&ADAPTIVE, &renewed, "A:write".to_string(), now);
+        hold_times.renewed(&renewed, Some(now + Duration::from_secs(10)));
+        let never = Lock::new();
+        hold_times.acquired(&ADAPTIVE, &never, "A:write".to_string(), now);
+
+        let later = now + Duration::from_secs(5);
+        let lock = Lock::new().with_expiry(later + Duration::from_secs(1));
+        hold_times.acquired(&ADAPTIVE, &lock, "A:write".to_string(), later);
+
+        let held = hold_times.held.lock().unwrap();
+        assert!(!held.contains_key(&expired.id()));
+        assert!(held.contains_key(&renewed.id()));
+        assert!(held.contains_key(&never.id()));
+        assert!(held.contains_key(&lock.id()));
+    }
+
+    #[test]
+    fn held_locks_are_bounded() {
+        let hold_times = HoldTimes::default();
+        let now = SystemTime::now();
+
+        let first = Lock::new();
+        hold_times.acquired(&ADAPTIVE, &first, "A:write".to_string(), now);
+        for offset in 1..=HELD_LIMIT as u64 {
+            hold_times.acquired(
+                &ADAPTIVE,
+                &Lock::new(),
+                "A:write".to_string(),
+                now + Duration::from_millis(offset),
+            );
+        }
+
+        let held = hold_times.held.lock().unwrap();
+        assert_eq!(held.len(), HELD_LIMIT);
+        assert!(!held.contains_key(&first.id()));
+    }
+}
@@ -1,3 +1,133 @@
+use std::{error::Error, fmt::Display};
+
+use crate::{backend::Field, Version};
+
 pub trait Component {
     const COMPONENT_TYPE: &'static str;
+
+    /// Version of the component's layout, stored along with it. Reading a
+    /// component stored with another version fails with
+    /// [`AccessError::VersionMismatch`].
+    ///
+    /// [`AccessError::VersionMismatch`]: crate::backend::AccessError::VersionMismatch
+    const VERSION: Version = Version::new(0, 1, 0);
+
+    /// Hash of the names and types of the component's fields, computed by
+    /// the derive. Backends with schema checks record it along with the
+    /// component and compare it when reading, catching fields which were
+    /// added or changed without a new [`Component::VERSION`]. `0` for
+    /// components implemented by hand, which are never checked.
+    const SCHEMA_HASH: u64 = 0;
+
+    /// The component's [`Component::SCHEMA_HASH`], or `None` if it does not
+    /// have one.
+    fn schema() -> Option<u64>
+    where
+        Self: Sized,
+    {
+        (Self::SCHEMA_HASH != 0).then_some(Self::SCHEMA_HASH)
+    }
+
+    /// Refers to a field of the component by its dot-separated path, for
+    /// use in predicates.
+    fn field(path: &str) -> Field
+    where
+        Self: Sized,
+    {
+        Field::new(Self::COMPONENT_TYPE, path)
+    }
+}
+
+/// Components whose [`Default`] stands in for them when an entity does not
+/// have them, implemented with `#[component(default)]`.
+pub trait DefaultComponent: Component + Default {}
+
+/// Longest name a component can have, in bytes, which keeps table names
+/// derived from component names within the limits of common databases.
+pub const MAX_COMPONENT_NAME_LENGTH: usize = 64;
+
+/// Whether the name can be used for a component: a non-empty sequence of
+/// at most [`MAX_COMPONENT_NAME_LENGTH`] ASCII letters, digits and
+/// underscores, not starting with a digit. Backends reject components with
+/// other names, since they may not be safe to use as table names and the
+/// like.
+pub const fn is_valid_component_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() || bytes.len() > MAX_COMPONENT_NAME_LENGTH || bytes[0].is_ascii_digit() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if !(bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+/// Why a name cannot be used for a component, see
+/// [`is_valid_component_name`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidName {
+    Empty,
+    /// The name is longer than [`MAX_COMPONENT_NAME_LENGTH`] bytes.
+    TooLong(usize),
+    LeadingDigit,
+    /// The characters which are not ASCII letters, digits or underscores,
+    /// in the order they first appear.
+    InvalidCharacters(Vec<char>),
+}
+
+impl Display for InvalidName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidName::Empty => write!(f, "component names cannot be empty"),
+            InvalidName::TooLong(length) => write!(
+                f,
+                "component name is {length} bytes long, \
+                but may be at most {MAX_COMPONENT_NAME_LENGTH}"
+            ),
+            InvalidName::LeadingDigit => write!(f, "component names cannot start with a digit"),
+            InvalidName::InvalidCharacters(chars) => {
+                let chars: Vec<_> = chars.iter().map(|c| format!("{c:?}")).collect();
+                write!(
+                    f,
+                    "component name contains {}, but may only contain ASCII letters, \
+                    digits and underscores",
+                    chars.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl Error for InvalidName {}
+
+/// Checks the name like [`is_valid_component_name`], telling why it cannot
+/// be used, for names which only become known at runtime.
+pub fn validate_component_name(name: &str) -> Result<(), InvalidName> {
+    let mut invalid = Vec::new();
+    for c in name.chars() {
+        if !(c.is_ascii_alphanumeric() || c == '_' || invalid.contains(&c)) {
+            invalid.push(c);
+        }
+    }
+
+    match name.chars().next() {
+        None => Err(InvalidName::Empty),
+        _ if !invalid.is_empty() => Err(InvalidName::InvalidCharacters(invalid)),
+        Some(first) if first.is_ascii_digit() => Err(InvalidName::LeadingDigit),
+        _ if name.len() > MAX_COMPONENT_NAME_LENGTH => Err(InvalidName::TooLong(name.len())),
+        _ => Ok(()),
+    }
+}
+
+/// References to components are stored as the component itself, so they
+/// can be inserted without giving up ownership.
+impl<T: Component + ?Sized> Component for &T {
+    const COMPONENT_TYPE: &'static str = T::COMPONENT_TYPE;
+    const VERSION: Version = T::VERSION;
 }
@@ -1,4 +1,7 @@
-use std::fmt::{Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -7,9 +10,57 @@ use uuid::Uuid;
 pub struct Entity(pub Uuid);
 
 impl Entity {
+    /// The entity resources, components which belong to no entity in
+    /// particular, are stored with. It is the v5 UUID of `eci:resources` in
+    /// the URL namespace, and [`crate::backend::Backend`] leaves it out
+    /// when listing entities.
+    pub const RESOURCES: Entity = Entity(Uuid::from_u128(0xdda1928c_6bb0_5443_abe5_8f5e18049392));
+
     pub fn new() -> Entity {
         Entity(Uuid::new_v4())
     }
+
+    /// A time-ordered v7 UUID, so entities created one after the other sort
+    /// next to each other, keeping inserts into the entity indexes of
+    /// storage backends close together.
+    ///
+    /// Within a process, every entity created this way sorts after the ones
+    /// created before it, even within the same millisecond or if the clock
+    /// steps backwards. Entities created by different processes are only
+    /// ordered as far as their clocks agree.
+    #[cfg(feature = "uuid-v7")]
+    pub fn new_v7() -> Entity {
+        Entity(v7::next())
+    }
+
+    pub const fn from_uuid(uuid: Uuid) -> Entity {
+        Entity(uuid)
+    }
+
+    /// Parses an entity in any of the forms [`Uuid::parse_str`] accepts,
+    /// including the hyphenated one entities are displayed as.
+    pub fn parse(input: &str) -> Result<Entity, EntityParseError> {
+        Uuid::parse_str(input)
+            .map(Entity)
+            .map_err(|source| EntityParseError {
+                input: input.to_string(),
+                source,
+            })
+    }
+
+    /// The v5 UUID of the name within the namespace, so the same external
+    /// key, such as a user id, always names the same entity. Keys from
+    /// different sources should be given different namespaces, which may
+    /// themselves be derived from a well-known one.
+    pub fn derived(namespace: &Entity, name: &str) -> Entity {
+        Entity(Uuid::new_v5(&namespace.0, name.as_bytes()))
+    }
+}
+
+impl Default for Entity {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Display for Entity {
@@ -17,3 +68,91 @@ impl Display for Entity {
         write!(f, "{}", self.0)
     }
 }
+
+impl From<Uuid> for Entity {
+    fn from(uuid: Uuid) -> Self {
+        Entity(uuid)
+    }
+}
+
+impl FromStr for Entity {
+    type Err = EntityParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Entity::parse(s)
+    }
+}
+
+impl TryFrom<&str> for Entity {
+    type Error = EntityParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Entity::parse(value)
+    }
+}
+
+/// The text is not an entity, see [`Entity::parse`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{input:?} is not a valid entity")]
+pub struct EntityParseError {
+    input: String,
+    source: uuid::Error,
+}
+
+impl EntityParseError {
+    /// The text which failed to parse.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+#[cfg(feature = "uuid-v7")]
+mod v7 {
+    use std::{
+        sync::Mutex,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use uuid::Uuid;
+
+    /// Millisecond and counter of the last uuid handed out.
+    static LAST: Mutex<(u64, u16)> = Mutex::new((0, 0));
+
+    /// The counter fills the 12 bits following the version, and starts at
+    /// a random value no higher than this each millisecond, leaving room
+    /// for at least 2048 more uuids before borrowing the next millisecond.
+    const MAX_COUNTER: u16 = 0xfff;
+    const COUNTER_SEED_MASK: u16 = 0x7ff;
+
+    pub fn next() -> Uuid {
+        // The random bits of a v4 uuid, so no other source is needed.
+        let random = *Uuid::new_v4().as_bytes();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or_default();
+
+        let (millis, counter) = {
+            let mut last = LAST.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            *last = match *last {
+                (millis, counter) if now <= millis && counter < MAX_COUNTER => {
+                    (millis, counter + 1)
+                }
+                (millis, _) if now <= millis => (millis + 1, 0),
+                _ => (
+                    now,
+                    u16::from_be_bytes([random[0], random[1]]) & COUNTER_SEED_MASK,
+                ),
+            };
+            *last
+        };
+
+        let mut bytes = [0; 16];
+        bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+        bytes[6] = 0x70 | (counter >> 8) as u8;
+        bytes[7] = counter as u8;
+        bytes[8] = 0x80 | (random[8] & 0x3f);
+        bytes[9..].copy_from_slice(&random[9..]);
+        Uuid::from_bytes(bytes)
+    }
+}
@@ -1,7 +1,12 @@
 pub mod backend;
 mod component;
 mod entity;
+mod version;
 
-pub use component::Component;
+pub use component::{
+    is_valid_component_name, validate_component_name, Component, DefaultComponent, InvalidName,
+    MAX_COMPONENT_NAME_LENGTH,
+};
 pub use eci_derive::Component;
-pub use entity::Entity;
+pub use entity::{Entity, EntityParseError};
+pub use version::{InvalidVersion, Version};
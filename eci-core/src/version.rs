@@ -0,0 +1,81 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Version of a component's layout, as `major.minor.patch`. Stored along
+/// with every component, so data written by an older layout is recognized
+/// as such rather than failing to deserialize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+/// The version of components which do not declare one, `0.1.0`.
+impl Default for Version {
+    fn default() -> Self {
+        Version::new(0, 1, 0)
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The text is not a version of the form `major.minor.patch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidVersion(pub String);
+
+impl Display for InvalidVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} is not a version of the form major.minor.patch",
+            self.0
+        )
+    }
+}
+
+impl Error for InvalidVersion {}
+
+impl FromStr for Version {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('.').map(|part| part.parse::<u64>());
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => {
+                Ok(Version::new(major, minor, patch))
+            }
+            _ => Err(InvalidVersion(s.to_string())),
+        }
+    }
+}
+
+/// Serialized as `major.minor.patch`, the same as it is displayed.
+impl Serialize for Version {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,115 @@
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, BackendError, LockingError},
+    Component, Entity,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+struct Position {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Name(pub String);
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Health(pub u32);
+
+fn template(backend: &Backend<Json>) -> Entity {
+    let template = Entity::new();
+    backend
+        .put(
+            template,
+            (
+                Position { x: 1.0, y: 2.0 },
+                Name("Goblin".to_string()),
+                Health(5),
+            ),
+        )
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    template
+}
+
+#[test]
+fn clone_entity() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let template = template(&backend);
+
+    let clone = backend.clone_entity(template).unwrap();
+    assert_ne!(clone, template);
+    assert_eq!(
+        backend.peek::<(&Position, &Name, &Health)>(clone).unwrap(),
+        Some((
+            Position { x: 1.0, y: 2.0 },
+            Name("Goblin".to_string()),
+            Health(5)
+        ))
+    );
+
+    let mut health = backend.get::<&mut Health>(clone).unwrap().unwrap();
+    health.deref().0 = 1;
+    health.commit().unwrap();
+    backend
+        .update(clone, (Name("Goblin Chief".to_string()),))
+        .unwrap();
+
+    assert_eq!(
+        backend
+            .peek::<(&Position, &Name, &Health)>(template)
+            .unwrap(),
+        Some((
+            Position { x: 1.0, y: 2.0 },
+            Name("Goblin".to_string()),
+            Health(5)
+        ))
+    );
+    assert_eq!(backend.peek::<&Health>(clone).unwrap(), Some(Health(1)));
+}
+
+#[test]
+fn clone_filtered_components() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let template = template(&backend);
+
+    let clone = backend
+        .clone_entity_filtered(template, |name| name != "Position")
+        .unwrap();
+    assert_eq!(backend.peek::<&Position>(clone).unwrap(), None);
+    assert_eq!(
+        backend.peek::<(&Name, &Health)>(clone).unwrap(),
+        Some((Name("Goblin".to_string()), Health(5)))
+    );
+
+    let empty = backend.clone_entity_filtered(template, |_| false).unwrap();
+    assert!(!backend.exists(empty).unwrap());
+}
+
+#[test]
+fn clone_conflicts_with_writers() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let template = template(&backend);
+
+    let writing = backend.get::<&mut Health>(template).unwrap().unwrap();
+    let err = backend.clone_entity(template).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        BackendError::Locking(LockingError::Conflict(..))
+    ));
+    assert_eq!(backend.list_entities().unwrap(), vec![template]);
+
+    // Readers do not get in the way.
+    drop(writing);
+    let _reading = backend.get::<&Health>(template).unwrap().unwrap();
+    backend.clone_entity(template).unwrap();
+
+    assert!(matches!(
+        backend.clone_entity(Entity::new()),
+        Err(BackendError::EntityNotFound(_))
+    ));
+}
@@ -0,0 +1,87 @@
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, BackendError, LockingError},
+    Component, Entity,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Name(pub String);
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Health(pub u32);
+
+#[test]
+fn despawn_entity() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let goblin = Entity::new();
+    let orc = Entity::new();
+    backend
+        .put(goblin, (Name("Goblin".to_string()), Health(5)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    backend
+        .put(orc, (Name("Orc".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let report = backend.despawn(goblin).unwrap();
+    let mut components = report.components.clone();
+    components.sort();
+    assert_eq!(report.entity, goblin);
+    assert_eq!(components, vec!["Health".to_string(), "Name".to_string()]);
+    assert_eq!(report.expired_locks, 0);
+
+    assert!(backend.get::<&Name>(goblin).unwrap().is_none());
+    assert_eq!(backend.peek::<&Health>(goblin).unwrap(), None);
+    assert!(backend.list_components(goblin).unwrap().is_empty());
+    assert_eq!(backend.list_entities().unwrap(), vec![orc]);
+
+    // The entity's locks are gone with it.
+    backend.get::<&mut Name>(goblin).unwrap();
+}
+
+#[test]
+fn despawn_conflicts_with_readers() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    backend
+        .put(entity, (Name("Probe".to_string()), Health(10)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let reading = backend.get::<&Name>(entity).unwrap().unwrap();
+    let err = backend.despawn(entity).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        BackendError::Locking(LockingError::Conflict(..))
+    ));
+    assert_eq!(backend.peek::<&Health>(entity).unwrap(), Some(Health(10)));
+    drop(reading);
+
+    assert_eq!(backend.despawn(entity).unwrap().components.len(), 2);
+}
+
+#[test]
+fn despawn_missing_entity() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    assert!(matches!(
+        backend.despawn(entity),
+        Err(BackendError::EntityNotFound(missing)) if missing == entity
+    ));
+
+    // Failing to find it does not leave it locked.
+    backend
+        .put(entity, (Health(1),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    assert_eq!(backend.despawn(entity).unwrap().components.len(), 1);
+    assert!(matches!(
+        backend.despawn(entity),
+        Err(BackendError::EntityNotFound(_))
+    ));
+}
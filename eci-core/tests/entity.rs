@@ -0,0 +1,152 @@
+use std::error::Error;
+
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, EntityIdGenerator, RandomIds, TimeOrderedIds},
+    Component, Entity, EntityParseError,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use eci_query::{InsertReport, TypedBackend, World};
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Name(pub String);
+
+#[test]
+fn parse_displayed_entities() {
+    let entity = Entity::new();
+    let displayed = entity.to_string();
+    assert_eq!(Entity::parse(&displayed), Ok(entity));
+    assert_eq!(displayed.parse::<Entity>(), Ok(entity));
+    assert_eq!(Entity::try_from(displayed.as_str()), Ok(entity));
+    assert_eq!(Entity::from_uuid(entity.0), entity);
+
+    // Other forms of the same uuid name the same entity.
+    assert_eq!(Entity::parse(&entity.0.to_simple().to_string()), Ok(entity));
+    assert_eq!(Entity::parse(&displayed.to_uppercase()), Ok(entity));
+}
+
+#[test]
+fn reject_malformed_entities() {
+    for input in [
+        "",
+        "goblin",
+        "dda1928c-6bb0-5443-abe5-8f5e1804939",
+        "dda1928c-6bb0-5443-abe5-8f5e180493921",
+        "gda1928c-6bb0-5443-abe5-8f5e18049392",
+    ] {
+        let err: EntityParseError = input.parse::<Entity>().unwrap_err();
+        assert_eq!(err.input(), input);
+        assert_eq!(err.to_string(), format!("{input:?} is not a valid entity"));
+        assert!(err.source().is_some());
+    }
+}
+
+#[test]
+fn derive_stable_entities() {
+    let url = Entity::from_uuid(Uuid::NAMESPACE_URL);
+    assert_eq!(Entity::derived(&url, "eci:resources"), Entity::RESOURCES);
+
+    let users = Entity::derived(&url, "https://example.com/users");
+    assert_eq!(users.to_string(), "580e7e6a-ba60-5993-be9c-2da0aee9a8bf");
+    assert_eq!(
+        Entity::derived(&users, "42").to_string(),
+        "7f4e1505-8135-5529-9508-a33c0e67eb23"
+    );
+    assert_ne!(Entity::derived(&users, "42"), Entity::derived(&users, "43"));
+    assert_ne!(Entity::derived(&users, "42"), Entity::derived(&url, "42"));
+
+    // Importing the same user twice writes the same entity, so the
+    // second import runs into the first.
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let imports: Vec<_> = (0..2)
+        .map(|_| {
+            backend
+                .put(Entity::derived(&users, "42"), (Name("Ada".to_string()),))
+                .unwrap()
+                .is_complete()
+        })
+        .collect();
+    assert_eq!(imports, vec![true, false]);
+    assert_eq!(
+        backend.list_entities().unwrap(),
+        vec![Entity::derived(&users, "42")]
+    );
+}
+
+/// Spawns 10k entities with each generator, checking they all come back.
+fn spawn_many<G: EntityIdGenerator + 'static>(generator: G) -> Vec<Entity> {
+    let world = World::new(
+        Backend::<Json>::from_joint(SqliteBackend::memory().unwrap()).with_entity_ids(generator),
+    );
+
+    let spawned: Vec<Entity> = (0..10_000)
+        .map(|index| {
+            world
+                .spawn()
+                .insert((Name(index.to_string()),))
+                .id()
+                .unwrap()
+        })
+        .collect();
+
+    let mut listed = world.backend().list_entities().unwrap();
+    listed.sort();
+    let mut expected = spawned.clone();
+    expected.sort();
+    expected.dedup();
+    assert_eq!(listed, expected);
+
+    for index in [0, 4_999, 9_999] {
+        assert_eq!(
+            world.peek::<&Name>(spawned[index]).unwrap(),
+            Some(Name(index.to_string()))
+        );
+    }
+
+    spawned
+}
+
+#[test]
+fn spawn_with_either_generator() {
+    let random = spawn_many(RandomIds);
+    assert!(random.iter().all(|entity| entity.0.get_version_num() == 4));
+
+    // Time-ordered entities sort in the order they were spawned.
+    let ordered = spawn_many(TimeOrderedIds);
+    assert!(ordered.iter().all(|entity| entity.0.get_version_num() == 7));
+    assert!(ordered.windows(2).all(|pair| pair[0] < pair[1]));
+
+    // Both kinds are stored and read alike.
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    for entity in [random[0], ordered[0], Entity::new_v7(), Entity::new()] {
+        backend
+            .put(entity, (Name(entity.to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        assert_eq!(
+            backend.peek::<&Name>(entity).unwrap(),
+            Some(Name(entity.to_string()))
+        );
+    }
+    assert_eq!(backend.list_entities().unwrap().len(), 4);
+}
+
+#[test]
+fn order_within_a_millisecond() {
+    let entities: Vec<Entity> = (0..100_000).map(|_| Entity::new_v7()).collect();
+    assert!(entities.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| (0..10_000).map(|_| Entity::new_v7()).collect::<Vec<_>>()))
+        .collect();
+    let mut all: Vec<Entity> = threads
+        .into_iter()
+        .flat_map(|thread| thread.join().unwrap())
+        .collect();
+    all.sort();
+    all.dedup();
+    assert_eq!(all.len(), 40_000);
+}
@@ -0,0 +1,121 @@
+use eci_backend_memory::MemoryBackend;
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, Backend, BackendError, Envelope, ExtractionDescriptor, Field,
+        Format,
+    },
+    Component, Entity,
+};
+use eci_format_bincode::Bincode;
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct Name(pub String);
+
+fn stored(backend: &MemoryBackend, entity: Entity) -> Vec<u8> {
+    AccessBackend::<Json>::read_components(
+        backend,
+        entity,
+        vec![ExtractionDescriptor {
+            name: "Name".to_string(),
+            version: None,
+            schema: None,
+        }],
+    )
+    .unwrap()
+    .remove(0)
+    .unwrap()
+    .contents
+}
+
+#[test]
+fn read_enveloped_and_legacy() {
+    let storage = MemoryBackend::default();
+    let legacy = Backend::<Json>::from_joint(storage.clone());
+    let enveloped = Backend::<Json>::from_joint(storage.clone()).with_envelopes();
+
+    let (old, new) = (Entity::new(), Entity::new());
+    legacy
+        .put(old, (Name("Old".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    enveloped
+        .put(new, (Name("New".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let envelope = stored(&storage, new);
+    let envelope = Envelope::open(&envelope).unwrap().unwrap();
+    assert_eq!(envelope.format, "json");
+    assert_eq!(envelope.version, Name::VERSION);
+    assert_eq!(envelope.payload, br#""New""#);
+    assert_eq!(Envelope::open(&stored(&storage, old)).unwrap(), None);
+
+    for backend in [&legacy, &enveloped] {
+        assert_eq!(
+            backend.peek::<&Name>(old).unwrap(),
+            Some(Name("Old".to_string()))
+        );
+        assert_eq!(
+            backend.peek::<&Name>(new).unwrap(),
+            Some(Name("New".to_string()))
+        );
+    }
+
+    assert_eq!(
+        enveloped
+            .find_entities_where(vec![Field::new("Name", "").eq("New")])
+            .unwrap(),
+        vec![new]
+    );
+
+    let mut name = enveloped.get::<&mut Name>(old).unwrap().unwrap();
+    name.deref().0.push('!');
+    name.commit().unwrap();
+    assert!(Envelope::open(&stored(&storage, old)).unwrap().is_some());
+
+    assert_eq!(
+        Json::deserialize_envelope::<Name>(
+            &Json::serialize_envelope(Name("Direct".to_string())).unwrap()
+        )
+        .unwrap(),
+        Name("Direct".to_string())
+    );
+}
+
+#[test]
+fn reject_other_format() {
+    let storage = MemoryBackend::default();
+    let entity = Entity::new();
+    Backend::<Json>::from_joint(storage.clone())
+        .with_envelopes()
+        .put(entity, (Name("Probe".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let assert_mismatch = |err: Option<BackendError>| match err.as_ref().map(BackendError::root) {
+        Some(BackendError::Access(AccessError::FormatMismatch {
+            component,
+            stored,
+            expected,
+        })) => {
+            assert_eq!(component, "Name");
+            assert_eq!(stored, "json");
+            assert_eq!(expected, "bincode");
+        }
+        other => panic!("expected a format mismatch, got {other:?}"),
+    };
+
+    let bincode = Backend::<Bincode>::from_joint(storage.clone());
+    assert_mismatch(bincode.peek::<&Name>(entity).err());
+    assert_mismatch(bincode.with_envelopes().get::<&Name>(entity).err());
+
+    let contents: Vec<u8> = Json::serialize_envelope(Name("Probe".to_string())).unwrap();
+    assert!(matches!(
+        Bincode::deserialize_envelope::<Name>(&contents),
+        Err(AccessError::FormatMismatch { .. })
+    ));
+}
@@ -0,0 +1,123 @@
+use std::{error::Error, time::Duration};
+
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{AccessError, Backend, BackendError, LockingError},
+    Component, Entity,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend, Versioned};
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Name(pub String);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NameAsNumber(pub usize);
+
+impl Component for NameAsNumber {
+    const COMPONENT_TYPE: &'static str = "Name";
+}
+
+/// Every error in the chain, starting with the error itself.
+fn chain<'a>(err: &'a (dyn Error + 'static)) -> Vec<&'a (dyn Error + 'static)> {
+    std::iter::successors(Some(err), |err: &&'a (dyn Error + 'static)| (*err).source()).collect()
+}
+
+#[test]
+fn reach_underlying_errors() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    backend
+        .put(entity, (Name("Probe".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let err = backend
+        .get::<&NameAsNumber>(entity)
+        .map(|_| ())
+        .unwrap_err()
+        .context("reading the probe");
+
+    let chain = chain(&err);
+    assert_eq!(
+        chain.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        vec![
+            format!(
+                "reading the probe: reading components of {entity}: access error error \
+                during serialization: deserializing Name: invalid type: string \"Probe\", \
+                expected usize at line 1 column 7"
+            ),
+            format!(
+                "reading components of {entity}: access error error during serialization: \
+                deserializing Name: invalid type: string \"Probe\", expected usize at line \
+                1 column 7"
+            ),
+            "access error error during serialization: deserializing Name: invalid type: \
+            string \"Probe\", expected usize at line 1 column 7"
+                .to_string(),
+            "error during serialization: deserializing Name: invalid type: string \"Probe\", \
+            expected usize at line 1 column 7"
+                .to_string(),
+            "deserializing Name: invalid type: string \"Probe\", expected usize at line 1 \
+            column 7"
+                .to_string(),
+            "invalid type: string \"Probe\", expected usize at line 1 column 7".to_string(),
+        ]
+    );
+    assert!(chain
+        .last()
+        .unwrap()
+        .downcast_ref::<serde_json::Error>()
+        .is_some());
+    assert!(!err.is_conflict());
+    assert!(!err.is_transient());
+}
+
+#[test]
+fn classify_conflicts() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    backend
+        .put(entity, (Name("Probe".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let lock = backend
+        .lock_entity(entity, Duration::from_secs(60))
+        .unwrap();
+    let err = backend.get::<&mut Name>(entity).map(|_| ()).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        BackendError::Locking(LockingError::Conflict(..))
+    ));
+    assert!(err.is_conflict());
+    assert!(err.is_transient());
+    assert!(chain(&err)
+        .iter()
+        .any(|err| err.downcast_ref::<LockingError>().is_some()));
+    drop(lock);
+
+    // Retrying a stale write would only go stale again.
+    let stale = backend
+        .put_if_unchanged(
+            entity,
+            Versioned {
+                value: Name("Stale".to_string()),
+                revision: 0,
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(stale, AccessError::StaleWrite { .. }));
+    assert!(stale.is_conflict());
+    assert!(!stale.is_transient());
+
+    let busy = BackendError::from(AccessError::Busy { attempts: 3 }).context("writing");
+    assert!(!busy.is_conflict());
+    assert!(busy.is_transient());
+    assert_eq!(
+        busy.to_string(),
+        "writing: access error storage was still busy after 3 attempts"
+    );
+}
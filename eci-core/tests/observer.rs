@@ -0,0 +1,130 @@
+use std::sync::{Arc, Mutex};
+
+use eci_backend_memory::MemoryBackend;
+use eci_core::{
+    backend::{AccessBackend, AccessError, Backend, ExtractionDescriptor, Format},
+    Component, Entity,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, Inserter, TypedBackend};
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Health(pub u32);
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Name(pub String);
+
+#[test]
+fn observe_committed_writes() {
+    let storage = MemoryBackend::new();
+    let observed = Arc::new(Mutex::new(Vec::new()));
+
+    let backend = Backend::<Json>::from_joint(storage.clone()).on_write("Health", {
+        let observed = observed.clone();
+        Arc::new(move |entity, contents: &[u8]| {
+            // The write is visible by the time observers are called.
+            let stored = AccessBackend::<Json>::read_components(
+                &storage,
+                entity,
+                vec![ExtractionDescriptor {
+                    name: "Health".to_string(),
+                    version: None,
+                    schema: None,
+                }],
+            )
+            .unwrap()
+            .remove(0)
+            .map(|component| component.contents);
+
+            observed.lock().unwrap().push((
+                entity,
+                Json::deserialize::<Health>(&contents.to_vec()).unwrap(),
+                stored.map(|stored| Json::deserialize::<Health>(&stored).unwrap()),
+            ));
+        })
+    });
+
+    let entity = Entity::new();
+    backend
+        .put(entity, (Health(10), Name("Probe".to_string())))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    assert_eq!(
+        observed.lock().unwrap().as_slice(),
+        &[(entity, Health(10), Some(Health(10)))]
+    );
+
+    // Failed writes are not observed.
+    assert!(matches!(
+        backend.write_components(entity, (Health(5),).insert::<Json>()),
+        Err(AccessError::Conflict(..))
+    ));
+    assert_eq!(observed.lock().unwrap().len(), 1);
+
+    let mut health = backend.get::<&mut Health>(entity).unwrap().unwrap();
+    health.deref().0 = 20;
+    health.commit().unwrap();
+    backend
+        .update(entity, (Name("Renamed".to_string()),))
+        .unwrap();
+    assert_eq!(
+        observed.lock().unwrap().last(),
+        Some(&(entity, Health(20), Some(Health(20))))
+    );
+    assert_eq!(observed.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn observe_typed_writes_and_removals() {
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let removed = Arc::new(Mutex::new(Vec::new()));
+
+    let backend = Backend::<Json>::from_joint(MemoryBackend::new())
+        .with_envelopes()
+        .on_write_typed::<Health, _>({
+            let written = written.clone();
+            move |entity, health| written.lock().unwrap().push((entity, health))
+        })
+        .on_remove("Health", {
+            let removed = removed.clone();
+            Arc::new(move |entity, contents: &[u8]| {
+                removed.lock().unwrap().push((
+                    entity,
+                    Json::deserialize::<Health>(&contents.to_vec()).unwrap(),
+                ))
+            })
+        });
+
+    let entity = Entity::new();
+    backend
+        .put(entity, (Health(3),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    assert_eq!(written.lock().unwrap().as_slice(), &[(entity, Health(3))]);
+    assert!(removed.lock().unwrap().is_empty());
+
+    backend.remove::<(Health,)>(entity).unwrap();
+    assert_eq!(removed.lock().unwrap().as_slice(), &[(entity, Health(3))]);
+
+    // Removing a component the entity does not have is not observed.
+    backend.remove::<(Health,)>(entity).unwrap();
+    assert_eq!(removed.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn panicking_observer() {
+    let calls = Arc::new(Mutex::new(0));
+    let backend = Backend::<Json>::from_joint(MemoryBackend::new())
+        .on_write("Health", Arc::new(|_, _| panic!("observer failed")))
+        .on_write("Health", {
+            let calls = calls.clone();
+            Arc::new(move |_, _| *calls.lock().unwrap() += 1)
+        });
+
+    let entity = Entity::new();
+    assert!(backend.put(entity, (Health(1),)).unwrap().is_complete());
+    assert_eq!(backend.peek::<&Health>(entity).unwrap(), Some(Health(1)));
+    assert_eq!(*calls.lock().unwrap(), 1);
+}
@@ -1,17 +1,574 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use proc_macro2::Span;
+use proc_macro_crate::{crate_name, FoundCrate};
+use quote::{quote, quote_spanned};
+use syn::{
+    ext::IdentExt, parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Field,
+    Fields, Ident, Index, Lit, Meta, NestedMeta,
+};
 
-#[proc_macro_derive(Component)]
+/// Resolves the path to eci-core from the perspective of the crate invoking
+/// the derive, taking renamed dependencies into account.
+fn core_path() -> proc_macro2::TokenStream {
+    crate_path("eci-core", quote!(::eci_core))
+}
+
+/// Resolves the path to eci-query, like [`core_path`].
+fn query_path() -> proc_macro2::TokenStream {
+    crate_path("eci-query", quote!(::eci_query))
+}
+
+fn crate_path(name: &str, fallback: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match crate_name(name) {
+        Ok(FoundCrate::Itself) => quote!(crate),
+        Ok(FoundCrate::Name(name)) => {
+            let ident = Ident::new(&name, Span::call_site());
+            quote!(::#ident)
+        }
+        Err(_) => fallback,
+    }
+}
+
+/// Settings given through `#[component(...)]` attributes.
+struct Attributes {
+    name: Option<String>,
+    version: (u64, u64, u64),
+    /// Whether the component's `Default` stands in for it when absent.
+    default: bool,
+    /// Path to eci-core to use instead of the one found in the manifest.
+    krate: Option<syn::Path>,
+}
+
+/// Mirrors `MAX_COMPONENT_NAME_LENGTH` in eci-core.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Mirrors `validate_component_name` in eci-core, so invalid names are
+/// pointed out where they are written, rather than failing once a backend
+/// gets to see them.
+fn validate_name(name: &str) -> Result<(), String> {
+    let mut invalid = Vec::new();
+    for c in name.chars() {
+        if !(c.is_ascii_alphanumeric() || c == '_' || invalid.contains(&c)) {
+            invalid.push(c);
+        }
+    }
+
+    if !invalid.is_empty() {
+        let chars: Vec<_> = invalid.iter().map(|c| format!("{c:?}")).collect();
+        Err(format!(
+            "component name `{name}` contains {}, but may only contain ASCII letters, \
+            digits and underscores",
+            chars.join(", ")
+        ))
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        Err(format!(
+            "component name `{name}` starts with a digit, which it may not"
+        ))
+    } else if name.len() > MAX_NAME_LENGTH {
+        Err(format!(
+            "component name `{name}` is {} bytes long, but may be at most {MAX_NAME_LENGTH}",
+            name.len()
+        ))
+    } else if name.is_empty() {
+        Err("component names cannot be empty".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn string(lit: Lit, what: &str) -> syn::Result<syn::LitStr> {
+    match lit {
+        Lit::Str(lit) => Ok(lit),
+        lit => Err(syn::Error::new_spanned(
+            lit,
+            format!("expected the {what} as a string"),
+        )),
+    }
+}
+
+/// Parses `#[component(name = "position", version = "1.2.0", default)]`,
+/// defaulting to the name of the type and version `0.1.0` when either is
+/// absent, along with `crate = "..."` naming the path to eci-core, for
+/// crates using it through another crate which re-exports it.
+fn attributes(attrs: &[Attribute]) -> syn::Result<Attributes> {
+    let mut attributes = Attributes {
+        name: None,
+        version: (0, 1, 0),
+        default: false,
+        krate: None,
+    };
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("component")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(
+                meta,
+                "expected #[component(name = \"...\", version = \"major.minor.patch\", default)]",
+            )),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("name") => {
+                    let lit = string(pair.lit, "name")?;
+
+                    validate_name(&lit.value())
+                        .map_err(|message| syn::Error::new_spanned(&lit, message))?;
+
+                    attributes.name = Some(lit.value());
+                }
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("version") => {
+                    let lit = string(pair.lit, "version")?;
+
+                    let parts: Vec<_> = lit
+                        .value()
+                        .split('.')
+                        .map(|part| part.parse::<u64>())
+                        .collect();
+
+                    attributes.version = match parts.as_slice() {
+                        [Ok(major), Ok(minor), Ok(patch)] => (*major, *minor, *patch),
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected a version of the form major.minor.patch",
+                            ))
+                        }
+                    };
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+                    attributes.default = true;
+                }
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("crate") => {
+                    let lit = string(pair.lit, "path to eci-core")?;
+                    attributes.krate = Some(lit.parse().map_err(|_| {
+                        syn::Error::new_spanned(&lit, "expected a path, such as `facade::eci`")
+                    })?);
+                }
+                nested => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "unknown component attribute, expected `name`, `version`, `default` \
+                        or `crate`",
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(attributes)
+}
+
+/// Whether the field is marked `#[component(skip)]`, the only attribute
+/// fields take.
+fn is_skipped(field: &Field) -> syn::Result<bool> {
+    let mut skipped = false;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("component"))
+    {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected #[component(skip)]")),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => skipped = true,
+                nested => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "unknown field attribute, expected `skip`",
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(skipped)
+}
+
+/// Checks that a field marked `#[component(skip)]` is skipped by serde in
+/// both directions, returning whether serde fills it in with a function
+/// rather than its `Default`.
+///
+/// Derives cannot change the input other derives see, so the serde
+/// attribute has to be written out. Skipping in only one direction is
+/// rejected, since formats which are not self-describing, such as bincode,
+/// would then read every field after it from the wrong position.
+fn check_serde_skip(field: &Field, component: &Attribute) -> syn::Result<bool> {
+    let mut skip = false;
+    let mut default_with = false;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde"))
+    {
+        let Ok(Meta::List(list)) = attr.parse_meta() else {
+            continue;
+        };
+
+        for nested in list.nested {
+            let path = match &nested {
+                NestedMeta::Meta(Meta::Path(path)) => path,
+                NestedMeta::Meta(Meta::NameValue(pair)) => &pair.path,
+                _ => continue,
+            };
+
+            if path.is_ident("skip") {
+                skip = true;
+            } else if path.is_ident("default") {
+                default_with = matches!(nested, NestedMeta::Meta(Meta::NameValue(_)));
+            } else if [
+                "skip_serializing",
+                "skip_deserializing",
+                "skip_serializing_if",
+            ]
+            .iter()
+            .any(|one_sided| path.is_ident(one_sided))
+            {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "skipped fields must be skipped in both directions with `#[serde(skip)]`, \
+                    or formats such as bincode read the fields after them from the wrong position",
+                ));
+            }
+        }
+    }
+
+    if !skip {
+        return Err(syn::Error::new_spanned(
+            component,
+            "skipped fields also need `#[serde(skip)]`, which the derive cannot add itself",
+        ));
+    }
+
+    Ok(default_with)
+}
+
+/// Checks every field marked `#[component(skip)]`, pointing out fields
+/// whose type does not implement `Default` where they are declared.
+fn skipped_fields(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields: Vec<&Field> = match &input.data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|variant| variant.fields.iter())
+            .collect(),
+        Data::Union(_) => Vec::new(),
+    };
+
+    let mut checks = Vec::new();
+    for field in fields {
+        if !is_skipped(field)? {
+            continue;
+        }
+
+        let component = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path.is_ident("component"))
+            .expect("skipped fields have a component attribute");
+
+        if !check_serde_skip(field, component)? {
+            let ty = &field.ty;
+            checks.push(quote_spanned! {ty.span()=>
+                skipped::<#ty>();
+            });
+        }
+    }
+
+    if checks.is_empty() {
+        return Ok(quote!());
+    }
+
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        const _: () = {
+            fn skipped<T: ::std::default::Default>() {}
+            fn check #impl_generics () #where_clause {
+                #(#checks)*
+            }
+        };
+    })
+}
+
+/// Offset basis and prime of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Describes the fields stored of each variant, or of the struct, as their
+/// names or positions along with their types as written, leaving out
+/// skipped fields.
+fn describe_fields(fields: &Fields, description: &mut String) -> syn::Result<()> {
+    description.push(match fields {
+        Fields::Named(_) => '{',
+        Fields::Unnamed(_) => '(',
+        Fields::Unit => ';',
+    });
+
+    for (index, field) in fields.iter().enumerate() {
+        if is_skipped(field)? {
+            continue;
+        }
+
+        let ty = &field.ty;
+        let name = match &field.ident {
+            Some(ident) => ident.unraw().to_string(),
+            None => index.to_string(),
+        };
+        description.push_str(&format!("{name}:{},", quote!(#ty)));
+    }
+
+    Ok(())
+}
+
+/// Hashes the names and types of the fields with 64-bit FNV-1a, which
+/// changes whenever fields are added, removed, renamed or change type,
+/// though not when a type they refer to changes. Never `0`, which is left
+/// for components without a hash.
+fn schema_hash(input: &DeriveInput) -> syn::Result<u64> {
+    let mut description = String::new();
+    match &input.data {
+        Data::Struct(data) => describe_fields(&data.fields, &mut description)?,
+        Data::Enum(data) => {
+            for variant in &data.variants {
+                description.push_str(&format!("|{}", variant.ident.unraw()));
+                describe_fields(&variant.fields, &mut description)?;
+            }
+        }
+        Data::Union(_) => {}
+    }
+
+    let hash = description.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    Ok(hash.max(1))
+}
+
+/// Implements `Component` for structs of any shape and enums, stored under
+/// the name of the type unless renamed with `#[component(name = "...")]`.
+/// Its `SCHEMA_HASH` is a hash of the names and types of its fields, see
+/// [`schema_hash`].
+///
+/// The generated code refers to eci-core by the name it has in the
+/// invoking crate's manifest, or through the path given with
+/// `#[component(crate = "facade::eci")]` for crates which only depend on a
+/// crate re-exporting it, so nothing has to be imported.
+///
+/// How the component is stored is up to its `Serialize` and `Deserialize`
+/// implementations. Unit structs are stored as serde's unit, which is `null`
+/// in Json and nothing at all in binary formats such as bincode, and read
+/// back from it by every format. Unions cannot be serialized by serde, so
+/// they cannot be components.
+#[proc_macro_derive(Component, attributes(component))]
 pub fn derive_answer_fn(item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as DeriveInput);
 
+    if let Data::Union(data) = &input.data {
+        return syn::Error::new_spanned(
+            data.union_token,
+            "unions cannot be components, since serde cannot serialize them",
+        )
+        .to_compile_error()
+        .into();
+    }
+
     let ident = &input.ident;
-    let name = ident.to_string();
+    let attributes = match attributes(&input.attrs) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let core = match &attributes.krate {
+        Some(path) => quote!(#path),
+        None => core_path(),
+    };
+    let (major, minor, patch) = attributes.version;
+
+    // Every instantiation of a generic type is stored under the same name,
+    // so it has to be chosen deliberately rather than taken from the type.
+    let name = match attributes.name {
+        Some(name) => name,
+        None if input.generics.params.is_empty() => {
+            let name = ident.unraw().to_string();
+            if let Err(message) = validate_name(&name) {
+                return syn::Error::new_spanned(ident, message)
+                    .to_compile_error()
+                    .into();
+            }
+            name
+        }
+        None => {
+            return syn::Error::new_spanned(
+                &input.generics,
+                "generic components need a name, which all their instantiations are \
+                stored under: #[component(name = \"...\")]",
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+    let skipped = match skipped_fields(&input) {
+        Ok(skipped) => skipped,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let schema = match schema_hash(&input) {
+        Ok(schema) => schema,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let default = attributes.default.then(|| {
+        quote! {
+            impl #impl_generics #core::DefaultComponent for #ident #type_generics #where_clause {}
+        }
+    });
 
     TokenStream::from(quote! {
-        impl eci_core::Component for #ident {
+        impl #impl_generics #core::Component for #ident #type_generics #where_clause {
             const COMPONENT_TYPE: &'static str = #name;
+            const VERSION: #core::Version = #core::Version::new(#major, #minor, #patch);
+            const SCHEMA_HASH: u64 = #schema;
         }
+
+        #default
+        #skipped
+    })
+}
+
+/// Most components a bundle can hold, matching the largest tuples
+/// implementing `Inserter` and `Extractor`.
+const MAX_BUNDLE_SIZE: usize = 16;
+
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    match bundle(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn bundle(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let core = core_path();
+    let query = query_path();
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "bundles cannot be generic",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "only structs can be bundles",
+            ))
+        }
+    };
+
+    if fields.is_empty() || fields.len() > MAX_BUNDLE_SIZE {
+        return Err(syn::Error::new_spanned(
+            fields,
+            format!("bundles hold between 1 and {MAX_BUNDLE_SIZE} components"),
+        ));
+    }
+
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let members: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(index);
+                quote!(#index)
+            }
+        })
+        .collect();
+    let bindings: Vec<_> = (0..fields.len())
+        .map(|index| Ident::new(&format!("field{index}"), Span::call_site()))
+        .collect();
+    let construct = match fields {
+        Fields::Named(_) => quote!(#ident { #(#members: #bindings),* }),
+        _ => quote!(#ident ( #(#bindings),* )),
+    };
+
+    // Points at the offending field when it is not a component, which is
+    // clearer than the errors about the tuples below.
+    let members_check = types.iter().map(|ty| {
+        quote_spanned! {ty.span()=>
+            member::<#ty>();
+        }
+    });
+
+    Ok(quote! {
+        const _: () = {
+            fn member<T: #query::bundle::BundleMember>() {}
+            let _ = || {
+                #(#members_check)*
+            };
+
+            impl #query::Bundle for #ident {
+                type Components = (#(#types,)*);
+                type Selection = (#(&'static #types,)*);
+
+                fn into_components(self) -> Self::Components {
+                    (#(self.#members,)*)
+                }
+
+                fn from_components((#(#bindings,)*): Self::Components) -> Self {
+                    #construct
+                }
+            }
+
+            // The bound only holds if every field is a component, and is
+            // deferred so a field which is not does not also fail here.
+            impl #query::Extractor for &#ident
+            where
+                for<'a> <#ident as #query::Bundle>::Selection:
+                    #query::Extractor<Owned = <#ident as #query::Bundle>::Components>,
+            {
+                type Owned = #ident;
+
+                fn describe() -> ::std::vec::Vec<#core::backend::LockDescriptor> {
+                    <<#ident as #query::Bundle>::Selection as #query::Extractor>::describe()
+                }
+
+                fn extract() -> ::std::vec::Vec<#core::backend::ExtractionDescriptor> {
+                    <<#ident as #query::Bundle>::Selection as #query::Extractor>::extract()
+                }
+
+                fn required() -> ::std::vec::Vec<::std::string::String> {
+                    <<#ident as #query::Bundle>::Selection as #query::Extractor>::required()
+                }
+
+                fn from<F: #core::backend::Format>(
+                    entity: #core::Entity,
+                    serialized: ::std::vec::Vec<::std::option::Option<#core::backend::SerializedComponent<F>>>,
+                    limits: ::std::option::Option<&#core::backend::DeserializationLimits>,
+                ) -> ::std::result::Result<::std::option::Option<Self::Owned>, #core::backend::AccessError> {
+                    ::std::result::Result::Ok(
+                        <<#ident as #query::Bundle>::Selection as #query::Extractor>::from(entity, serialized, limits)?
+                            .map(<#ident as #query::Bundle>::from_components),
+                    )
+                }
+
+                fn serialize<F: #core::backend::Format>(
+                    _owned: &Self::Owned,
+                ) -> ::std::result::Result<::std::vec::Vec<#core::backend::SerializedComponent<F>>, #core::backend::AccessError> {
+                    ::std::result::Result::Ok(::std::vec::Vec::new())
+                }
+            }
+        };
     })
 }
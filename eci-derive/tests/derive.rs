@@ -0,0 +1,26 @@
+#[test]
+fn derive() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/no_imports.rs");
+    t.pass("tests/ui/facade.rs");
+    t.pass("tests/ui/version.rs");
+    t.pass("tests/ui/rename.rs");
+    t.pass("tests/ui/generic.rs");
+    t.pass("tests/ui/raw_ident.rs");
+    t.pass("tests/ui/default.rs");
+    t.pass("tests/ui/skip.rs");
+    t.pass("tests/ui/shapes.rs");
+    t.pass("tests/ui/schema.rs");
+    t.pass("tests/ui/crate_path.rs");
+    t.compile_fail("tests/ui/non_ascii_name.rs");
+    t.compile_fail("tests/ui/invalid_version.rs");
+    t.compile_fail("tests/ui/invalid_name.rs");
+    t.compile_fail("tests/ui/unnamed_generic.rs");
+    t.compile_fail("tests/ui/nested_bundle.rs");
+    t.compile_fail("tests/ui/long_name.rs");
+    t.compile_fail("tests/ui/missing_default.rs");
+    t.compile_fail("tests/ui/invalid_skip.rs");
+    t.compile_fail("tests/ui/union.rs");
+    t.compile_fail("tests/ui/invalid_crate.rs");
+    t.compile_fail("tests/ui/missing_crate.rs");
+}
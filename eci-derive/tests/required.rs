@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use eci::{
+    backend::{AccessBackend, AccessError, Backend, BackendError, ExtractionDescriptor},
+    Component, Entity,
+};
+use eci_backend_sqlite::SqliteBackend;
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Name(pub String);
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Health(pub u32);
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Mana(pub u32);
+
+#[test]
+fn name_missing_components() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    backend
+        .put(entity, (Name("Probe".to_string()), Health(10)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend
+        .get_required::<(&Name, &mut Health, Option<&Mana>)>(entity)
+        .unwrap();
+    assert_eq!(locked.deref().2, None);
+    drop(locked);
+
+    let err = backend
+        .get_required::<(&Name, &Mana, &Health)>(entity)
+        .unwrap_err();
+    assert!(matches!(
+        err.root(),
+        BackendError::Access(AccessError::ComponentNotFound { entity: missing, component })
+            if *missing == entity && component == "Mana"
+    ));
+    assert!(err.to_string().ends_with(&format!("{entity} has no Mana")));
+
+    // The components are released again after failing.
+    backend
+        .lock_entity(entity, Duration::from_secs(60))
+        .unwrap();
+
+    // Plain gets keep returning nothing.
+    assert!(backend.get::<&Mana>(entity).unwrap().is_none());
+
+    let stranger = Entity::new();
+    let err = backend.get_required::<&Name>(stranger).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        BackendError::Access(AccessError::EntityNotFound(missing)) if *missing == stranger
+    ));
+    assert!(err
+        .to_string()
+        .ends_with(&format!("{stranger} has no components")));
+}
+
+#[test]
+fn expect_components() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    backend
+        .put(entity, (Health(10),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let describe = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| ExtractionDescriptor {
+                name: name.to_string(),
+                version: None,
+                schema: None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let read = backend
+        .expect_components(entity, describe(&["Health"]))
+        .unwrap();
+    assert_eq!(read.len(), 1);
+    assert_eq!(read[0].name, Health::COMPONENT_TYPE);
+
+    let Err(err) = backend.expect_components(entity, describe(&["Health", "Mana", "Name"])) else {
+        panic!("expected Mana to be missing");
+    };
+    assert_eq!(err.to_string(), format!("{entity} has no Mana"));
+
+    let stranger = Entity::new();
+    assert!(matches!(
+        backend.expect_components(stranger, describe(&["Health"])),
+        Err(AccessError::EntityNotFound(missing)) if missing == stranger
+    ));
+}
@@ -0,0 +1,155 @@
+use eci::{
+    backend::{AccessError, Backend, BackendError, MigrationRegistry, SchemaCheck},
+    Component, Entity,
+};
+use eci_backend_memory::MemoryBackend;
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+mod before {
+    use super::*;
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+    pub struct Position {
+        pub x: f32,
+        pub y: f32,
+    }
+}
+
+/// Someone added a field without changing the version, which reads
+/// stored positions without complaint since it has a default.
+mod after {
+    use super::*;
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+    pub struct Position {
+        pub x: f32,
+        pub y: f32,
+        #[serde(default)]
+        pub z: f32,
+    }
+}
+
+mod legacy {
+    use super::*;
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+    #[component(version = "0.0.1")]
+    pub struct Position(pub f32, pub f32);
+}
+
+fn assert_mismatch(err: Option<BackendError>) {
+    match err.as_ref().map(BackendError::root) {
+        Some(BackendError::Access(AccessError::SchemaMismatch {
+            component,
+            stored,
+            expected,
+        })) => {
+            assert_eq!(component, "Position");
+            assert_eq!(*stored, before::Position::SCHEMA_HASH);
+            assert_eq!(*expected, after::Position::SCHEMA_HASH);
+        }
+        other => panic!("expected a schema mismatch, got {other:?}"),
+    }
+}
+
+fn store_before(storage: &MemoryBackend) -> Entity {
+    let entity = Entity::new();
+    Backend::<Json>::from_joint(storage.clone())
+        .with_schema_checks(SchemaCheck::Reject)
+        .put(entity, (before::Position { x: 1.0, y: 2.0 },))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    entity
+}
+
+#[test]
+fn detect_added_field() {
+    assert_ne!(before::Position::SCHEMA_HASH, after::Position::SCHEMA_HASH);
+    assert_eq!(before::Position::VERSION, after::Position::VERSION);
+
+    let storage = MemoryBackend::default();
+    let entity = store_before(&storage);
+
+    let rejecting =
+        Backend::<Json>::from_joint(storage.clone()).with_schema_checks(SchemaCheck::Reject);
+    assert_mismatch(rejecting.peek::<&after::Position>(entity).err());
+    assert_mismatch(rejecting.get::<&mut after::Position>(entity).err());
+    assert_eq!(
+        rejecting.peek::<&before::Position>(entity).unwrap(),
+        Some(before::Position { x: 1.0, y: 2.0 })
+    );
+
+    let expected = Some(after::Position {
+        x: 1.0,
+        y: 2.0,
+        z: 0.0,
+    });
+    let warning =
+        Backend::<Json>::from_joint(storage.clone()).with_schema_checks(SchemaCheck::Warn);
+    assert_eq!(warning.peek::<&after::Position>(entity).unwrap(), expected);
+
+    let unchecked = Backend::<Json>::from_joint(storage.clone());
+    assert_eq!(
+        unchecked.peek::<&after::Position>(entity).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn accept_unrecorded_schema() {
+    let storage = MemoryBackend::default();
+    let entity = Entity::new();
+    Backend::<Json>::from_joint(storage.clone())
+        .put(entity, (before::Position { x: 1.0, y: 2.0 },))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let rejecting =
+        Backend::<Json>::from_joint(storage.clone()).with_schema_checks(SchemaCheck::Reject);
+    assert_eq!(
+        rejecting.peek::<&after::Position>(entity).unwrap(),
+        Some(after::Position {
+            x: 1.0,
+            y: 2.0,
+            z: 0.0,
+        })
+    );
+}
+
+/// Reading with migrations accepts any stored version, leaving the
+/// check of components stored with the current one to the query.
+#[test]
+fn detect_with_migrations() {
+    let storage = MemoryBackend::default();
+    let changed = store_before(&storage);
+
+    let upgraded = Entity::new();
+    Backend::<Json>::from_joint(storage.clone())
+        .with_schema_checks(SchemaCheck::Reject)
+        .put(upgraded, (legacy::Position(3.0, 4.0),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let migrations = MigrationRegistry::new().register(legacy::Position::VERSION, |value| {
+        let legacy::Position(x, y) =
+            serde_json::from_value(value).map_err(AccessError::serialization)?;
+        Ok(after::Position { x, y, z: 0.0 })
+    });
+    let backend = Backend::<Json>::from_joint(storage.clone())
+        .with_schema_checks(SchemaCheck::Reject)
+        .with_migrations(migrations);
+
+    assert_mismatch(backend.peek::<&after::Position>(changed).err());
+    assert_mismatch(backend.get::<&after::Position>(changed).err());
+    assert_eq!(
+        backend.peek::<&after::Position>(upgraded).unwrap(),
+        Some(after::Position {
+            x: 3.0,
+            y: 4.0,
+            z: 0.0,
+        })
+    );
+}
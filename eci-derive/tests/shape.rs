@@ -0,0 +1,108 @@
+use eci::{
+    backend::{Backend, Format},
+    Component, Entity,
+};
+use eci_backend_sqlite::SqliteBackend;
+use eci_format_bincode::Bincode;
+use eci_format_cbor::Cbor;
+use eci_format_json::Json;
+use eci_format_ron::Ron;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Clone, Component, Serialize, Deserialize, PartialEq)]
+enum Shape {
+    Point,
+    Circle(f32),
+    Segment(f32, f32),
+    Rectangle { width: f32, height: f32 },
+}
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+struct Velocity(f32, f32);
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+struct Dead;
+
+fn backend<F: Format>() -> Backend<F> {
+    Backend::<F>::from_joint(SqliteBackend::memory().unwrap())
+}
+
+#[test]
+fn store_enum_variants() {
+    let backend = backend::<Json>();
+    let shapes = [
+        Shape::Point,
+        Shape::Circle(2.0),
+        Shape::Segment(1.0, 3.0),
+        Shape::Rectangle {
+            width: 4.0,
+            height: 0.5,
+        },
+    ];
+
+    let entities: Vec<_> = shapes
+        .iter()
+        .map(|shape| {
+            let entity = Entity::new();
+            backend
+                .put(entity, (shape.clone(),))
+                .and_then(InsertReport::into_result)
+                .unwrap();
+            entity
+        })
+        .collect();
+
+    for (entity, shape) in entities.iter().zip(&shapes) {
+        assert_eq!(
+            backend.peek::<&Shape>(*entity).unwrap().as_ref(),
+            Some(shape)
+        );
+    }
+
+    // Changing the variant replaces the stored component entirely.
+    let mut locked = backend.get::<&mut Shape>(entities[0]).unwrap().unwrap();
+    *locked.deref() = Shape::Rectangle {
+        width: 1.0,
+        height: 1.0,
+    };
+    locked.commit().unwrap();
+    assert_eq!(
+        backend.peek::<&Shape>(entities[0]).unwrap(),
+        Some(Shape::Rectangle {
+            width: 1.0,
+            height: 1.0,
+        })
+    );
+}
+
+fn roundtrip<F: Format>() {
+    let backend = backend::<F>();
+    let entity = Entity::new();
+    backend
+        .put(
+            entity,
+            (Dead, Velocity(1.0, -1.0), Shape::Segment(0.0, 2.0)),
+        )
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    assert_eq!(
+        backend.peek::<(&Dead, &Velocity, &Shape)>(entity).unwrap(),
+        Some((Dead, Velocity(1.0, -1.0), Shape::Segment(0.0, 2.0)))
+    );
+
+    backend.remove::<(Dead,)>(entity).unwrap();
+    assert_eq!(backend.peek::<&Dead>(entity).unwrap(), None);
+}
+
+/// Unit structs serialize to nothing at all in some formats, which
+/// still has to be told apart from the component being absent.
+#[test]
+fn store_shapes_in_every_format() {
+    roundtrip::<Json>();
+    roundtrip::<Bincode>();
+    roundtrip::<Cbor>();
+    roundtrip::<Ron>();
+}
@@ -0,0 +1,67 @@
+use eci::{
+    backend::{Backend, Format},
+    Component, Entity,
+};
+use eci_backend_sqlite::SqliteBackend;
+use eci_format_bincode::Bincode;
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+/// Stands in for a texture handle, which cannot be serialized at all.
+#[derive(Default)]
+struct Handle(Option<fn() -> u32>);
+
+#[derive(Component, Serialize, Deserialize)]
+struct Sprite {
+    path: String,
+    #[component(skip)]
+    #[serde(skip)]
+    handle: Handle,
+    layer: u32,
+}
+
+fn sprite() -> Sprite {
+    Sprite {
+        path: "player.png".to_string(),
+        handle: Handle(Some(|| 7)),
+        layer: 3,
+    }
+}
+
+fn roundtrip<F: Format>() {
+    let backend = Backend::<F>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    backend
+        .put(entity, (sprite(),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend.get::<&mut Sprite>(entity).unwrap().unwrap();
+    let sprite = locked.deref();
+    assert!(sprite.handle.0.is_none());
+    assert_eq!(sprite.path, "player.png");
+    assert_eq!(sprite.layer, 3);
+
+    sprite.handle = Handle(Some(|| 8));
+    sprite.layer += 1;
+    locked.commit().unwrap();
+
+    let sprite = backend.peek::<&Sprite>(entity).unwrap().unwrap();
+    assert!(sprite.handle.0.is_none());
+    assert_eq!(sprite.path, "player.png");
+    assert_eq!(sprite.layer, 4);
+}
+
+#[test]
+fn skip_in_json() {
+    roundtrip::<Json>();
+}
+
+/// Bincode does not name fields, so the field after the skipped one
+/// is only read back correctly if it was skipped both ways.
+#[test]
+fn skip_in_bincode() {
+    roundtrip::<Bincode>();
+}
@@ -0,0 +1,28 @@
+// Stands in for a crate which re-exports eci-core, in a crate which only
+// depends on that one and so has no `eci_core` to refer to.
+mod facade {
+    pub use eci;
+}
+
+#[derive(eci::Component, Default)]
+#[component(crate = "facade::eci", default, version = "1.0.0")]
+struct Position {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(eci::Component)]
+#[component(crate = "::eci")]
+struct Velocity(f32, f32);
+
+fn main() {
+    assert_eq!(
+        <Position as facade::eci::Component>::COMPONENT_TYPE,
+        "Position"
+    );
+    assert_eq!(
+        <Position as facade::eci::Component>::VERSION,
+        facade::eci::Version::new(1, 0, 0)
+    );
+    assert_eq!(<Velocity as eci::Component>::COMPONENT_TYPE, "Velocity");
+}
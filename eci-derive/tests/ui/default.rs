@@ -0,0 +1,13 @@
+use eci::{Component, DefaultComponent};
+
+#[derive(Component, Default)]
+#[component(default)]
+struct Score(u32);
+
+fn defaulted<T: DefaultComponent>() -> T {
+    T::default()
+}
+
+fn main() {
+    assert_eq!(defaulted::<Score>().0, 0);
+}
@@ -0,0 +1,12 @@
+mod facade {
+    pub use eci::Component;
+}
+
+use facade::Component;
+
+#[derive(Component)]
+struct Velocity(f32, f32);
+
+fn main() {
+    assert_eq!(Velocity::COMPONENT_TYPE, "Velocity");
+}
@@ -0,0 +1,21 @@
+use eci::Component;
+
+#[derive(Component)]
+#[component(name = "Tagged")]
+struct Tagged<T>(T);
+
+#[derive(Component)]
+#[component(name = "Labelled", version = "1.0.0")]
+struct Labelled<'a, T: Clone, const N: usize>
+where
+    T: Default,
+{
+    _label: &'a str,
+    _values: [T; N],
+}
+
+fn main() {
+    assert_eq!(Tagged::<u32>::COMPONENT_TYPE, "Tagged");
+    assert_eq!(Tagged::<String>::COMPONENT_TYPE, "Tagged");
+    assert_eq!(<Labelled<'static, u8, 3>>::COMPONENT_TYPE, "Labelled");
+}
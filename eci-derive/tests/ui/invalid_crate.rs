@@ -0,0 +1,9 @@
+#[derive(eci::Component)]
+#[component(crate = "facade::")]
+struct Incomplete;
+
+#[derive(eci::Component)]
+#[component(crate = facade)]
+struct Unquoted;
+
+fn main() {}
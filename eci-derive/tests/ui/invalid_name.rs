@@ -0,0 +1,19 @@
+#[derive(eci::Component)]
+#[component(name = "physics.position")]
+struct Position {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(eci::Component)]
+#[component(name = "2d_position")]
+struct Position2d {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(eci::Component)]
+#[component(name = 5)]
+struct Velocity(f32, f32);
+
+fn main() {}
@@ -0,0 +1,40 @@
+use eci::Component;
+use serde::{Deserialize, Serialize};
+
+struct Handle(fn() -> u32);
+
+#[derive(Component)]
+struct MissingSerde {
+    #[component(skip)]
+    _cache: Vec<u32>,
+}
+
+#[derive(Component, Serialize, Deserialize)]
+struct OneSided {
+    #[component(skip)]
+    #[serde(skip_serializing)]
+    _cache: Vec<u32>,
+}
+
+#[derive(Component, Serialize, Deserialize)]
+struct Conditional {
+    #[component(skip)]
+    #[serde(skip, skip_serializing_if = "Vec::is_empty")]
+    _cache: Vec<u32>,
+}
+
+#[derive(Component)]
+struct UnknownField {
+    #[component(transient)]
+    _cache: Vec<u32>,
+}
+
+#[derive(Component, Serialize, Deserialize)]
+struct NoDefault {
+    _value: u32,
+    #[component(skip)]
+    #[serde(skip)]
+    _handle: Handle,
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+#[derive(eci::Component)]
+#[component(version = "1.2")]
+struct Position {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(eci::Component)]
+#[component(version = "1.2.0.1")]
+struct Velocity(f32, f32);
+
+#[derive(eci::Component)]
+#[component(version = "1.x.0")]
+struct Health(u32);
+
+#[derive(eci::Component)]
+#[component(version = "-1.0.0")]
+struct Mana(u32);
+
+#[derive(eci::Component)]
+#[component(version = 1)]
+struct Stamina(u32);
+
+#[derive(eci::Component)]
+#[component(revision = "1.0.0")]
+struct Armor(u32);
+
+fn main() {}
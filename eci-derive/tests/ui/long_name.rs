@@ -0,0 +1,19 @@
+#[derive(eci::Component)]
+struct PositionOfTheEntityRelativeToTheOriginOfTheWorldItIsLocatedWithin {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(eci::Component)]
+#[component(name = "position_of_the_entity_relative_to_the_origin_of_the_world_it_is_in")]
+struct Position {
+    _x: f32,
+    _y: f32,
+}
+
+// Exactly as long as names may be.
+#[derive(eci::Component)]
+#[component(name = "position_of_the_entity_relative_to_the_origin_of_the_world_x_y_z")]
+struct Velocity(f32, f32);
+
+fn main() {}
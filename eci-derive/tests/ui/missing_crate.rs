@@ -0,0 +1,5 @@
+#[derive(eci::Component)]
+#[component(crate = "umbrella::eci")]
+struct Position(f32, f32);
+
+fn main() {}
@@ -0,0 +1,7 @@
+use eci::Component;
+
+#[derive(Component)]
+#[component(default)]
+struct Score(u32);
+
+fn main() {}
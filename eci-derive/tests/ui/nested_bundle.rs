@@ -0,0 +1,24 @@
+use eci_query::Bundle;
+use serde::{Deserialize, Serialize};
+
+#[derive(eci::Component, Serialize, Deserialize)]
+struct Position(f32, f32);
+
+#[derive(eci::Component, Serialize, Deserialize)]
+struct Health(u32);
+
+#[derive(Bundle)]
+struct Body {
+    position: Position,
+}
+
+#[derive(Bundle)]
+struct Player {
+    body: Body,
+    health: Health,
+}
+
+#[derive(Bundle)]
+struct Empty {}
+
+fn main() {}
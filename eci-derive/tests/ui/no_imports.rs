@@ -0,0 +1,11 @@
+// eci-core is renamed to `eci` in this crate's dev-dependencies, and
+// neither the trait nor the derive macro are imported.
+#[derive(eci::Component)]
+struct Position {
+    _x: f32,
+    _y: f32,
+}
+
+fn main() {
+    assert_eq!(<Position as eci::Component>::COMPONENT_TYPE, "Position");
+}
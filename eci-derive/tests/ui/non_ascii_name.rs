@@ -0,0 +1,14 @@
+// Component names end up in table names and the like, so the derive
+// rejects names which backends would refuse anyway.
+#[derive(eci::Component)]
+struct Größe {
+    _value: f32,
+}
+
+#[derive(eci::Component)]
+#[component(name = "physics.größe-2")]
+struct Size {
+    _value: f32,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+// Raw identifiers are stored under the identifier without the `r#`.
+#[derive(eci::Component)]
+#[allow(non_camel_case_types)]
+struct r#type {
+    _value: f32,
+}
+
+fn main() {
+    assert_eq!(<r#type as eci::Component>::COMPONENT_TYPE, "type");
+}
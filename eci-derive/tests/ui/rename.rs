@@ -0,0 +1,19 @@
+use eci::{Component, Version};
+
+// Renamed from `Position`, keeping the name its components are stored by.
+#[derive(Component)]
+#[component(name = "Position")]
+struct Location {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(Component)]
+#[component(version = "1.2.0", name = "physics_velocity")]
+struct Velocity(f32, f32);
+
+fn main() {
+    assert_eq!(Location::COMPONENT_TYPE, "Position");
+    assert_eq!(Velocity::COMPONENT_TYPE, "physics_velocity");
+    assert_eq!(Velocity::VERSION, Version::new(1, 2, 0));
+}
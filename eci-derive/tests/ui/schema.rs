@@ -0,0 +1,69 @@
+use eci::Component;
+
+mod before {
+    #[derive(eci::Component)]
+    pub struct Position {
+        pub x: f32,
+        pub y: f32,
+    }
+}
+
+mod added {
+    #[derive(eci::Component)]
+    pub struct Position {
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+    }
+}
+
+mod renamed {
+    #[derive(eci::Component)]
+    pub struct Position {
+        pub left: f32,
+        pub y: f32,
+    }
+}
+
+mod retyped {
+    #[derive(eci::Component)]
+    pub struct Position {
+        pub x: f64,
+        pub y: f64,
+    }
+}
+
+// Only changes the types which are not stored.
+mod skipped {
+    #[derive(eci::Component, serde::Serialize, serde::Deserialize)]
+    pub struct Position {
+        pub x: f32,
+        pub y: f32,
+        #[component(skip)]
+        #[serde(skip)]
+        pub cache: Vec<u8>,
+    }
+}
+
+#[derive(Component)]
+enum Shape {
+    _Circle(f32),
+    _Square { _side: f32 },
+}
+
+#[derive(Component)]
+#[component(name = "Shape")]
+enum Reordered {
+    _Square { _side: f32 },
+    _Circle(f32),
+}
+
+fn main() {
+    let hash = before::Position::SCHEMA_HASH;
+    assert_ne!(hash, 0);
+    assert_ne!(hash, added::Position::SCHEMA_HASH);
+    assert_ne!(hash, renamed::Position::SCHEMA_HASH);
+    assert_ne!(hash, retyped::Position::SCHEMA_HASH);
+    assert_eq!(hash, skipped::Position::SCHEMA_HASH);
+    assert_ne!(Shape::SCHEMA_HASH, Reordered::SCHEMA_HASH);
+}
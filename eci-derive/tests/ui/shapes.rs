@@ -0,0 +1,39 @@
+use eci::Component;
+
+#[derive(Component)]
+struct Dead;
+
+#[derive(Component)]
+struct Velocity(f32, f32);
+
+#[derive(Component)]
+enum Shape {
+    Point,
+    Circle(f32),
+    Rectangle { _width: f32, _height: f32 },
+}
+
+#[derive(Component)]
+#[component(name = "Choice")]
+enum Choice<L, R> {
+    Left(L),
+    Right(R),
+}
+
+#[derive(Component)]
+enum Never {}
+
+fn main() {
+    assert_eq!(Dead::COMPONENT_TYPE, "Dead");
+    assert_eq!(Velocity::COMPONENT_TYPE, "Velocity");
+    assert_eq!(Shape::COMPONENT_TYPE, "Shape");
+    assert_eq!(<Choice<u8, String>>::COMPONENT_TYPE, "Choice");
+    assert_eq!(Never::COMPONENT_TYPE, "Never");
+
+    let _ = (Velocity(0.0, 0.0).0, Shape::Point, Shape::Circle(1.0));
+    let _ = Shape::Rectangle {
+        _width: 1.0,
+        _height: 2.0,
+    };
+    let _ = (Choice::<u8, u8>::Left(1), Choice::<u8, u8>::Right(2));
+}
@@ -0,0 +1,33 @@
+#![deny(warnings)]
+
+use eci::Component;
+use serde::{Deserialize, Serialize};
+
+#[derive(Default)]
+struct Handle(Option<fn() -> u32>);
+
+fn fallback() -> Handle {
+    Handle(Some(|| 1))
+}
+
+#[derive(Component, Serialize, Deserialize)]
+struct Sprite {
+    _path: String,
+    #[component(skip)]
+    #[serde(skip)]
+    _handle: Handle,
+    #[component(skip)]
+    #[serde(skip, default = "fallback")]
+    _fallback: Handle,
+}
+
+#[derive(Component, Serialize, Deserialize)]
+#[component(name = "Cached")]
+struct Cached<T> {
+    _value: T,
+    #[component(skip)]
+    #[serde(skip)]
+    _cache: Vec<T>,
+}
+
+fn main() {}
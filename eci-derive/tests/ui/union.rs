@@ -0,0 +1,9 @@
+use eci::Component;
+
+#[derive(Component)]
+union Bits {
+    _float: f32,
+    _int: u32,
+}
+
+fn main() {}
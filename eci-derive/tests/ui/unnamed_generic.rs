@@ -0,0 +1,4 @@
+#[derive(eci::Component)]
+struct Tagged<T>(T);
+
+fn main() {}
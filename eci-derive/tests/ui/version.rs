@@ -0,0 +1,16 @@
+use eci::{Component, Version};
+
+#[derive(Component)]
+#[component(version = "1.2.0")]
+struct Position {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(Component)]
+struct Velocity(f32, f32);
+
+fn main() {
+    assert_eq!(Position::VERSION, Version::new(1, 2, 0));
+    assert_eq!(Velocity::VERSION, Version::new(0, 1, 0));
+}
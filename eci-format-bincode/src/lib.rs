@@ -0,0 +1,215 @@
+//! Stores components in bincode's compact binary encoding, which takes up
+//! far less space than JSON for numeric components and is quicker to
+//! serialize.
+//!
+//! Unlike JSON, bincode is not self-describing: the encoding holds the
+//! values of fields but not their names or types, so it can only be read
+//! back as exactly the layout it was written with. Adding, removing or
+//! reordering fields of a component makes components written before then
+//! unreadable, or worse, read back as garbage, so remember to bump
+//! [`Component::VERSION`] with every change to a component's layout.
+//! For the same reason, predicates cannot be evaluated on components
+//! stored as bincode, and types which rely on inspecting the encoding, like
+//! untagged enums or `serde_json::Value`, cannot be stored with it.
+//!
+//! [`Component::VERSION`]: eci_core::Component::VERSION
+
+use std::fmt::Display;
+
+use bincode::Options;
+use eci_core::{
+    backend::{AccessError, DeserializationLimits, Format, Limit},
+    Component,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+mod depth;
+
+#[derive(Clone)]
+pub struct Bincode;
+
+impl Format for Bincode {
+    type Data = Vec<u8>;
+
+    fn name() -> String {
+        "bincode".to_string()
+    }
+
+    fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        bincode::serialize(&value).map_err(AccessError::serialization)
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+        bincode::deserialize(value).map_err(AccessError::serialization)
+    }
+
+    /// Also keeps lengths stored within the contents from making bincode
+    /// allocate more than the size limit while deserializing. Components of
+    /// recursive types can nest as deeply as their contents say, so the
+    /// depth limit is enforced while deserializing as well.
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        limits.check_size(T::COMPONENT_TYPE, value.len())?;
+
+        // The same options as bincode::deserialize, apart from the limit.
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(limits.max_size as u64);
+
+        let depth = depth::Depth::new(limits.max_depth);
+        let mut deserializer = bincode::Deserializer::from_slice(value, options);
+        T::deserialize(depth::Limited::new(&mut deserializer, &depth)).map_err(|err| {
+            if depth.exceeded() {
+                AccessError::LimitExceeded {
+                    component: T::COMPONENT_TYPE.to_string(),
+                    limit: Limit::Depth(limits.max_depth),
+                }
+            } else {
+                AccessError::serialization(err)
+            }
+        })
+    }
+}
+
+impl Display for Bincode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bincode")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_core::{
+        backend::{AccessError, DeserializationLimits, Format, Limit},
+        Component,
+    };
+    use serde::{Deserialize, Serialize};
+
+    use crate::Bincode;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Component)]
+    struct TestStruct {
+        content: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Component)]
+    struct Numbers(Vec<u64>);
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    enum Tree {
+        Leaf,
+        Node(Box<Tree>),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Component)]
+    struct Nested(Tree);
+
+    fn nested(depth: usize) -> Nested {
+        Nested((0..depth).fold(Tree::Leaf, |tree, _| Tree::Node(Box::new(tree))))
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let component = TestStruct {
+            content: "Hello world!".to_string(),
+        };
+
+        let serialized = Bincode::serialize(component.clone()).unwrap();
+        let deserialized: TestStruct = Bincode::deserialize(&serialized).unwrap();
+
+        assert_eq!(deserialized, component);
+    }
+
+    #[test]
+    fn test_bounded_roundtrip() {
+        let component = TestStruct {
+            content: "Hello [{ world!".to_string(),
+        };
+
+        let serialized = Bincode::serialize(component.clone()).unwrap();
+        let deserialized: TestStruct =
+            Bincode::deserialize_bounded(&serialized, &DeserializationLimits::default()).unwrap();
+
+        assert_eq!(deserialized, component);
+    }
+
+    #[test]
+    fn reject_oversized_payload() {
+        let limits = DeserializationLimits {
+            max_size: 1024,
+            ..Default::default()
+        };
+
+        let payload = Bincode::serialize(vec![0u64; 4096]).unwrap();
+
+        match Bincode::deserialize_bounded::<Numbers>(&payload, &limits) {
+            Err(AccessError::LimitExceeded { component, limit }) => {
+                assert_eq!(component, "Numbers");
+                assert_eq!(limit, Limit::Size(1024));
+            }
+            other => panic!("expected size limit to be exceeded, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bounded_nesting_roundtrip() {
+        let limits = DeserializationLimits::default();
+        let serialized = Bincode::serialize(nested(limits.max_depth - 1)).unwrap();
+
+        assert_eq!(
+            Bincode::deserialize_bounded::<Nested>(&serialized, &limits).unwrap(),
+            nested(limits.max_depth - 1)
+        );
+    }
+
+    #[test]
+    fn reject_deep_nesting() {
+        // A million nodes, each a variant index of 1, ending in a leaf.
+        let mut payload = 1u32.to_le_bytes().repeat(1_000_000);
+        payload.extend(0u32.to_le_bytes());
+
+        let limits = DeserializationLimits {
+            max_size: payload.len(),
+            ..Default::default()
+        };
+        match Bincode::deserialize_bounded::<Nested>(&payload, &limits) {
+            Err(AccessError::LimitExceeded { component, limit }) => {
+                assert_eq!(component, "Nested");
+                assert_eq!(limit, Limit::Depth(limits.max_depth));
+            }
+            other => panic!("expected depth limit to be exceeded, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reject_invalid_strings_and_variants() {
+        let limits = DeserializationLimits::default();
+
+        let mut payload = 2u64.to_le_bytes().to_vec();
+        payload.extend([0xc3, 0x28]);
+        assert!(matches!(
+            Bincode::deserialize_bounded::<TestStruct>(&payload, &limits),
+            Err(AccessError::Serialization(_))
+        ));
+
+        let payload = 2u32.to_le_bytes().to_vec();
+        assert!(matches!(
+            Bincode::deserialize_bounded::<Nested>(&payload, &limits),
+            Err(AccessError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn reject_oversized_lengths() {
+        // A short payload claiming to hold far more numbers than it does.
+        let payload = u64::MAX.to_le_bytes().to_vec();
+
+        assert!(matches!(
+            Bincode::deserialize_bounded::<Numbers>(&payload, &DeserializationLimits::default()),
+            Err(AccessError::Serialization(_))
+        ));
+    }
+}
@@ -0,0 +1,93 @@
+use eci_backend_memory::MemoryBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, ExtractionDescriptor, Format},
+    Component, Entity,
+};
+use eci_format_bincode::Bincode;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+struct Position {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct Name(pub String);
+
+#[test]
+fn put_get_and_remove() {
+    let backend = Backend::<Bincode>::from_joint(MemoryBackend::new());
+    let entity = Entity::new();
+    let position = Position {
+        x: 1.0,
+        y: -2.5,
+        z: 1e9,
+    };
+    backend
+        .put(entity, (position, Name("Probe".to_string())))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend.get::<&mut Position>(entity).unwrap().unwrap();
+    locked.deref().x += 1.0;
+    locked.commit().unwrap();
+
+    assert_eq!(
+        backend.peek::<(&Position, &Name)>(entity).unwrap(),
+        Some((
+            Position {
+                x: 2.0,
+                y: -2.5,
+                z: 1e9,
+            },
+            Name("Probe".to_string())
+        ))
+    );
+
+    backend.remove::<(Position, Name)>(entity).unwrap();
+    assert_eq!(backend.peek::<&Position>(entity).unwrap(), None);
+    assert!(backend.list_entities().unwrap().is_empty());
+}
+
+#[test]
+fn stored_as_bincode() {
+    let backend = Backend::<Bincode>::from_joint(MemoryBackend::new());
+    let entity = Entity::new();
+    let position = Position {
+        x: 0.5,
+        y: 0.25,
+        z: 0.125,
+    };
+    backend
+        .put(entity, (position,))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let stored = AccessBackend::<Bincode>::read_components(
+        &backend,
+        entity,
+        vec![ExtractionDescriptor {
+            name: "Position".to_string(),
+            version: None,
+            schema: None,
+        }],
+    )
+    .unwrap()
+    .remove(0)
+    .unwrap();
+
+    // Three floats, without any field names.
+    assert_eq!(stored.contents.len(), 24);
+    assert_eq!(
+        Bincode::deserialize::<Position>(&stored.contents).unwrap(),
+        Position {
+            x: 0.5,
+            y: 0.25,
+            z: 0.125,
+        }
+    );
+}
@@ -0,0 +1,272 @@
+//! Stores components as CBOR, a compact binary format which, like JSON,
+//! describes its own structure. Components stored as CBOR can therefore
+//! still be matched against predicates, and read back after fields with
+//! defaults were added to them.
+//!
+//! [`Cbor`] encodes maps in the order they are iterated, which for a
+//! `HashMap` differs from one map to the next. [`CanonicalCbor`] sorts the
+//! keys of every map as in the core deterministic encoding of RFC 8949, so
+//! equal components always encode to the same bytes, and their contents
+//! can be hashed to detect changes. Both formats read each other's
+//! contents.
+
+use std::fmt::Display;
+
+use ciborium::value::{CanonicalValue, Value};
+use eci_core::{
+    backend::{AccessError, DeserializationLimits, Format, Limit},
+    Component,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+#[derive(Clone)]
+pub struct Cbor;
+
+/// Like [`Cbor`], but encodes equal components to identical bytes.
+#[derive(Clone)]
+pub struct CanonicalCbor;
+
+fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, AccessError> {
+    let mut contents = Vec::new();
+    ciborium::into_writer(value, &mut contents).map_err(AccessError::serialization)?;
+    Ok(contents)
+}
+
+fn decode<T: DeserializeOwned>(value: &[u8]) -> Result<T, AccessError> {
+    ciborium::from_reader(value).map_err(AccessError::serialization)
+}
+
+fn decode_bounded<T: Component + DeserializeOwned>(
+    value: &[u8],
+    limits: &DeserializationLimits,
+) -> Result<T, AccessError> {
+    limits.check_size(T::COMPONENT_TYPE, value.len())?;
+
+    match ciborium::de::from_reader_with_recursion_limit(value, limits.max_depth) {
+        Ok(value) => Ok(value),
+        Err(ciborium::de::Error::RecursionLimitExceeded) => Err(AccessError::LimitExceeded {
+            component: T::COMPONENT_TYPE.to_string(),
+            limit: Limit::Depth(limits.max_depth),
+        }),
+        Err(err) => Err(AccessError::serialization(err)),
+    }
+}
+
+/// Sorts the keys of every map within the value by their canonical order:
+/// shorter encodings first, and equally long ones byte by byte.
+fn canonicalize(value: &mut Value) {
+    match value {
+        Value::Map(entries) => {
+            for (key, value) in entries.iter_mut() {
+                canonicalize(key);
+                canonicalize(value);
+            }
+            entries.sort_by_cached_key(|(key, _)| CanonicalValue::from(key.clone()));
+        }
+        Value::Array(values) => values.iter_mut().for_each(canonicalize),
+        Value::Tag(_, value) => canonicalize(value),
+        _ => {}
+    }
+}
+
+impl Format for Cbor {
+    type Data = Vec<u8>;
+
+    fn name() -> String {
+        "cbor".to_string()
+    }
+
+    fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        encode(&value)
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+        decode(value)
+    }
+
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        decode_bounded(value, limits)
+    }
+}
+
+impl Format for CanonicalCbor {
+    type Data = Vec<u8>;
+
+    fn name() -> String {
+        "cbor".to_string()
+    }
+
+    /// Goes through ciborium's [`Value`] to sort map keys before encoding,
+    /// which costs an extra copy of the component.
+    fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        let mut value = Value::serialized(&value).map_err(AccessError::serialization)?;
+        canonicalize(&mut value);
+        encode(&value)
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+        decode(value)
+    }
+
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        decode_bounded(value, limits)
+    }
+}
+
+impl Display for Cbor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cbor")
+    }
+}
+
+impl Display for CanonicalCbor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "canonical cbor")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use eci_core::{
+        backend::{AccessError, DeserializationLimits, Format, Limit},
+        Component,
+    };
+    use serde::{Deserialize, Serialize};
+
+    use crate::{CanonicalCbor, Cbor};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Component)]
+    struct TestStruct {
+        content: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Component)]
+    struct Inventory {
+        owner: String,
+        items: HashMap<String, u32>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Component)]
+    struct Nested(ciborium::value::Value);
+
+    fn inventory(items: &[(&str, u32)]) -> Inventory {
+        Inventory {
+            owner: "Hello world!".to_string(),
+            items: items
+                .iter()
+                .map(|(name, count)| (name.to_string(), *count))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let component = TestStruct {
+            content: "Hello world!".to_string(),
+        };
+
+        let serialized = Cbor::serialize(component.clone()).unwrap();
+        let deserialized: TestStruct = Cbor::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized, component);
+
+        let serialized = CanonicalCbor::serialize(component.clone()).unwrap();
+        let deserialized: TestStruct = CanonicalCbor::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized, component);
+    }
+
+    #[test]
+    fn test_bounded_roundtrip() {
+        let component = TestStruct {
+            content: "Hello [{ world!".to_string(),
+        };
+
+        let serialized = Cbor::serialize(component.clone()).unwrap();
+        let deserialized: TestStruct =
+            Cbor::deserialize_bounded(&serialized, &DeserializationLimits::default()).unwrap();
+
+        assert_eq!(deserialized, component);
+    }
+
+    #[test]
+    fn canonical_encoding_is_deterministic() {
+        let names: Vec<_> = (0..64).map(|index| format!("item{index}")).collect();
+        let items: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.as_str(), index as u32))
+            .collect();
+        let reversed: Vec<_> = items.iter().rev().copied().collect();
+
+        let first = CanonicalCbor::serialize(inventory(&items)).unwrap();
+        let second = CanonicalCbor::serialize(inventory(&items)).unwrap();
+        let third = CanonicalCbor::serialize(inventory(&reversed)).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, third);
+
+        // Either format reads the other's contents.
+        let plain = Cbor::serialize(inventory(&items)).unwrap();
+        assert_eq!(
+            Cbor::deserialize::<Inventory>(&first).unwrap(),
+            CanonicalCbor::deserialize::<Inventory>(&plain).unwrap(),
+        );
+    }
+
+    #[test]
+    fn canonical_keys_are_sorted() {
+        let serialized = CanonicalCbor::serialize(inventory(&[("bb", 1), ("a", 2), ("c", 3)]));
+
+        // Shorter keys first, so "items" comes before "owner", and "a" and
+        // "c" before "bb".
+        let mut expected = vec![0xa2, 0x65];
+        expected.extend(b"items");
+        expected.extend([
+            0xa3, 0x61, b'a', 0x02, 0x61, b'c', 0x03, 0x62, b'b', b'b', 0x01,
+        ]);
+        expected.push(0x65);
+        expected.extend(b"owner");
+        expected.push(0x6c);
+        expected.extend(b"Hello world!");
+
+        assert_eq!(serialized.unwrap(), expected);
+    }
+
+    #[test]
+    fn reject_deep_nesting() {
+        let mut payload = vec![0x81; 100_000];
+        payload.push(0x80);
+
+        match Cbor::deserialize_bounded::<Nested>(&payload, &DeserializationLimits::default()) {
+            Err(AccessError::LimitExceeded { component, limit }) => {
+                assert_eq!(component, "Nested");
+                assert_eq!(limit, Limit::Depth(64));
+            }
+            other => panic!("expected depth limit to be exceeded, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reject_oversized_payload() {
+        let limits = DeserializationLimits {
+            max_size: 1024,
+            ..Default::default()
+        };
+
+        let payload = Cbor::serialize(vec![0u8; 4096]).unwrap();
+
+        match Cbor::deserialize_bounded::<Nested>(&payload, &limits) {
+            Err(AccessError::LimitExceeded { component, limit }) => {
+                assert_eq!(component, "Nested");
+                assert_eq!(limit, Limit::Size(1024));
+            }
+            other => panic!("expected size limit to be exceeded, got {other:?}"),
+        }
+    }
+}
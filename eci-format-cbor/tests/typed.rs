@@ -0,0 +1,90 @@
+use eci_backend_memory::MemoryBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, ExtractionDescriptor, Format},
+    Component, Entity,
+};
+use eci_format_cbor::{CanonicalCbor, Cbor};
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct Score {
+    points: u32,
+    /// Raw bytes, which are not valid UTF-8 once encoded.
+    digest: Vec<u8>,
+}
+
+fn score(points: u32) -> Score {
+    Score {
+        points,
+        digest: vec![0xff, 0xfe, 0x00, 0x80],
+    }
+}
+
+fn read_raw<F: Format>(backend: &Backend<F>, entity: Entity) -> Vec<u8> {
+    AccessBackend::<F>::read_components(
+        backend,
+        entity,
+        vec![ExtractionDescriptor {
+            name: "Score".to_string(),
+            version: None,
+            schema: None,
+        }],
+    )
+    .unwrap()
+    .remove(0)
+    .unwrap()
+    .contents
+    .into()
+}
+
+#[test]
+fn put_get_and_remove() {
+    let backend = Backend::<Cbor>::from_joint(MemoryBackend::new());
+    let entity = Entity::new();
+    backend
+        .put(entity, (score(1),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend.get::<&mut Score>(entity).unwrap().unwrap();
+    locked.deref().points += 1;
+    locked.commit().unwrap();
+
+    assert_eq!(backend.peek::<&Score>(entity).unwrap(), Some(score(2)));
+    assert_eq!(
+        read_raw(&backend, entity),
+        Cbor::serialize(score(2)).unwrap()
+    );
+
+    backend.remove::<(Score,)>(entity).unwrap();
+    assert_eq!(backend.peek::<&Score>(entity).unwrap(), None);
+}
+
+#[test]
+fn match_predicates() {
+    let backend = Backend::<CanonicalCbor>::from_joint(MemoryBackend::new());
+    let (low, high) = (Entity::new(), Entity::new());
+    backend
+        .put(low, (score(5),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    backend
+        .put(high, (score(50),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    assert_eq!(
+        AccessBackend::<CanonicalCbor>::find_entities_where(
+            &backend,
+            vec![Score::field("points").gt(10)]
+        )
+        .unwrap(),
+        vec![high]
+    );
+    assert_eq!(
+        read_raw(&backend, high),
+        CanonicalCbor::serialize(score(50)).unwrap()
+    );
+}
@@ -0,0 +1,77 @@
+use std::{collections::BTreeMap, sync::RwLock};
+
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+
+/// The keys components are encrypted and decrypted with, each identified by
+/// a number stored along with the contents it encrypted.
+///
+/// Keyrings are meant to be kept in a `static`, which is how
+/// [`KeySource`](crate::KeySource) hands them to the format.
+pub struct Keyring {
+    keys: RwLock<Keys>,
+}
+
+struct Keys {
+    /// The key new contents are encrypted with.
+    current: Option<u32>,
+    ciphers: BTreeMap<u32, XChaCha20Poly1305>,
+}
+
+impl Keyring {
+    pub const fn new() -> Self {
+        Keyring {
+            keys: RwLock::new(Keys {
+                current: None,
+                ciphers: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Encrypts everything from now on with the key. Keys added before are
+    /// kept, so contents encrypted with them can still be read.
+    pub fn rotate(&self, id: u32, key: [u8; 32]) {
+        let mut keys = self.keys.write().unwrap();
+        keys.ciphers.insert(id, XChaCha20Poly1305::new(&key.into()));
+        keys.current = Some(id);
+    }
+
+    /// Adds a key for reading contents encrypted with it, without
+    /// encrypting anything new with it. Replaces any key with the same id.
+    pub fn add(&self, id: u32, key: [u8; 32]) {
+        let mut keys = self.keys.write().unwrap();
+        keys.ciphers.insert(id, XChaCha20Poly1305::new(&key.into()));
+    }
+
+    /// Forgets the key, once everything encrypted with it has been written
+    /// again with another. Nothing is encrypted after removing the current
+    /// key, until another is rotated in.
+    pub fn remove(&self, id: u32) {
+        let mut keys = self.keys.write().unwrap();
+        keys.ciphers.remove(&id);
+        if keys.current == Some(id) {
+            keys.current = None;
+        }
+    }
+
+    /// The id of the key new contents are encrypted with, if any.
+    pub fn current(&self) -> Option<u32> {
+        self.keys.read().unwrap().current
+    }
+
+    /// The current key, along with its id.
+    pub(crate) fn encrypting(&self) -> Option<(u32, XChaCha20Poly1305)> {
+        let keys = self.keys.read().unwrap();
+        let id = keys.current?;
+        Some((id, keys.ciphers.get(&id)?.clone()))
+    }
+
+    pub(crate) fn decrypting(&self, id: u32) -> Option<XChaCha20Poly1305> {
+        self.keys.read().unwrap().ciphers.get(&id).cloned()
+    }
+}
+
+impl Default for Keyring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,314 @@
+//! Encrypts components before they are stored, so their contents stay
+//! confidential wherever the storage ends up, using XChaCha20-Poly1305 with
+//! a random nonce for every component written.
+//!
+//! Formats only consist of associated functions, which backends call
+//! without ever constructing the format, so there is no instance to hand a
+//! key to. Instead, [`Encrypted`] looks the key up through a [`KeySource`],
+//! a type naming the [`Keyring`] to use. Making keys an argument of every
+//! format would have meant changing every backend for the sake of this one,
+//! while a single keyring for the whole process would keep programs from
+//! using different keys for different backends, and tests from using
+//! different keys at the same time.
+//!
+//! ```ignore
+//! #[derive(Clone)]
+//! struct WorldKeys;
+//!
+//! static WORLD_KEYS: Keyring = Keyring::new();
+//!
+//! impl KeySource for WorldKeys {
+//!     fn keyring() -> &'static Keyring {
+//!         &WORLD_KEYS
+//!     }
+//! }
+//!
+//! WORLD_KEYS.rotate(1, key_from_secret_store()?);
+//! let backend = Backend::<Encrypted<Json, WorldKeys>>::from_joint(
+//!     SqliteBackend::open("world.db")?,
+//! );
+//! ```
+//!
+//! Each encrypted component records the id of the key it was encrypted
+//! with, so keys can be rotated while components encrypted with older ones
+//! are still read with those. Contents which cannot be decrypted fail with
+//! [`AccessError::Decryption`], telling why in a [`CryptoError`].
+
+mod keyring;
+
+pub use keyring::Keyring;
+
+use std::{error::Error, fmt::Display};
+
+use chacha20poly1305::{
+    aead::{Aead, OsRng, Payload},
+    AeadCore, XChaCha20Poly1305, XNonce,
+};
+use eci_core::{
+    backend::{AccessError, DeserializationLimits, Format},
+    Component,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Marks encrypted contents, ahead of the id of the key, the nonce and the
+/// ciphertext. Both the marker and key id are authenticated along with the
+/// contents.
+const MAGIC: &[u8] = b"\0ece";
+const HEADER: usize = MAGIC.len() + 4;
+const NONCE: usize = 24;
+
+/// Names the keyring an [`Encrypted`] format uses.
+pub trait KeySource: Clone + 'static {
+    fn keyring() -> &'static Keyring;
+}
+
+/// Why contents could not be encrypted or decrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptoError {
+    /// There is no current key to encrypt with.
+    NoKey,
+    /// The contents were not encrypted, or were cut short.
+    NotEncrypted,
+    /// The contents were encrypted with a key which is not in the keyring.
+    UnknownKey(u32),
+    /// The key with the id could not decrypt the contents, because it is
+    /// not the key they were encrypted with, or they were tampered with.
+    Rejected(u32),
+    /// The contents are too large to be encrypted.
+    TooLarge,
+}
+
+impl Display for CryptoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CryptoError::NoKey => write!(f, "no key to encrypt with"),
+            CryptoError::NotEncrypted => write!(f, "contents are not encrypted"),
+            CryptoError::UnknownKey(id) => write!(f, "key {id} is not in the keyring"),
+            CryptoError::Rejected(id) => write!(
+                f,
+                "key {id} does not match the contents, or they were tampered with"
+            ),
+            CryptoError::TooLarge => write!(f, "contents are too large to encrypt"),
+        }
+    }
+}
+
+impl Error for CryptoError {}
+
+/// Encrypts the contents serialized by another format with the current key
+/// of the key source's keyring.
+#[derive(Clone)]
+pub struct Encrypted<F: Format, K: KeySource>(pub F, pub K);
+
+impl<F: Format, K: KeySource> Encrypted<F, K> {
+    fn decrypt(value: &[u8]) -> Result<Vec<u8>, AccessError> {
+        if value.len() < HEADER + NONCE || !value.starts_with(MAGIC) {
+            return Err(AccessError::decryption(CryptoError::NotEncrypted));
+        }
+
+        let (header, rest) = value.split_at(HEADER);
+        let (nonce, ciphertext) = rest.split_at(NONCE);
+        let id = u32::from_be_bytes(header[MAGIC.len()..].try_into().unwrap());
+
+        let cipher = K::keyring()
+            .decrypting(id)
+            .ok_or_else(|| AccessError::decryption(CryptoError::UnknownKey(id)))?;
+
+        cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| AccessError::decryption(CryptoError::Rejected(id)))
+    }
+}
+
+impl<F: Format, K: KeySource> Format for Encrypted<F, K> {
+    type Data = Vec<u8>;
+
+    fn name() -> String {
+        format!("encrypted {}", F::name())
+    }
+
+    fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        let (id, cipher) = K::keyring()
+            .encrypting()
+            .ok_or_else(|| AccessError::serialization(CryptoError::NoKey))?;
+
+        let contents: Vec<u8> = F::serialize(value)?.into();
+
+        let mut header = MAGIC.to_vec();
+        header.extend(id.to_be_bytes());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &contents,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| AccessError::serialization(CryptoError::TooLarge))?;
+
+        let mut encrypted = header;
+        encrypted.extend(nonce);
+        encrypted.extend(ciphertext);
+        Ok(encrypted)
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+        F::deserialize(&F::Data::from(Self::decrypt(value)?))
+    }
+
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        limits.check_size(T::COMPONENT_TYPE, value.len())?;
+        F::deserialize_bounded(&F::Data::from(Self::decrypt(value)?), limits)
+    }
+}
+
+impl<F: Format, K: KeySource> Display for Encrypted<F, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "encrypted {}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use eci_core::backend::{AccessError, Format};
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{CryptoError, Encrypted, KeySource, Keyring, HEADER, NONCE};
+
+    /// Declares a key source with a keyring of its own, so tests running at
+    /// the same time do not trample each other's keys.
+    macro_rules! key_source {
+        ($name:ident) => {
+            #[derive(Clone)]
+            struct $name;
+
+            impl KeySource for $name {
+                fn keyring() -> &'static Keyring {
+                    static KEYRING: Keyring = Keyring::new();
+                    &KEYRING
+                }
+            }
+        };
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    struct TestStruct {
+        content: String,
+    }
+
+    fn component() -> TestStruct {
+        TestStruct {
+            content: "Hello world!".to_string(),
+        }
+    }
+
+    fn decryption_error(result: Result<TestStruct, AccessError>) -> CryptoError {
+        match result {
+            Err(AccessError::Decryption(err)) => *err.downcast_ref::<CryptoError>().unwrap(),
+            other => panic!("expected decryption to fail, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        key_source!(Keys);
+        Keys::keyring().rotate(1, [7; 32]);
+
+        let serialized = Encrypted::<Json, Keys>::serialize(component()).unwrap();
+        assert!(!serialized
+            .windows(b"Hello".len())
+            .any(|window| window == b"Hello"));
+
+        let deserialized: TestStruct = Encrypted::<Json, Keys>::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized, component());
+    }
+
+    #[test]
+    fn reject_wrong_key() {
+        key_source!(Keys);
+        Keys::keyring().rotate(1, [7; 32]);
+        let serialized = Encrypted::<Json, Keys>::serialize(component()).unwrap();
+
+        Keys::keyring().add(1, [8; 32]);
+        assert_eq!(
+            decryption_error(Encrypted::<Json, Keys>::deserialize(&serialized)),
+            CryptoError::Rejected(1)
+        );
+
+        // Tampering with the key id is caught as well, rather than trying
+        // another key.
+        Keys::keyring().add(1, [7; 32]);
+        Keys::keyring().add(2, [7; 32]);
+        let mut tampered = serialized.clone();
+        tampered[HEADER - 1] = 2;
+        assert_eq!(
+            decryption_error(Encrypted::<Json, Keys>::deserialize(&tampered)),
+            CryptoError::Rejected(2)
+        );
+
+        assert_eq!(
+            decryption_error(Encrypted::<Json, Keys>::deserialize(
+                &Json::serialize(component()).unwrap()
+            )),
+            CryptoError::NotEncrypted
+        );
+    }
+
+    #[test]
+    fn rotate_keys() {
+        key_source!(Keys);
+        assert!(matches!(
+            Encrypted::<Json, Keys>::serialize(component()),
+            Err(AccessError::Serialization(_))
+        ));
+
+        Keys::keyring().rotate(1, [1; 32]);
+        let old = Encrypted::<Json, Keys>::serialize(component()).unwrap();
+        Keys::keyring().rotate(2, [2; 32]);
+        let new = Encrypted::<Json, Keys>::serialize(component()).unwrap();
+        assert_eq!(Keys::keyring().current(), Some(2));
+
+        assert_eq!(
+            Encrypted::<Json, Keys>::deserialize::<TestStruct>(&old).unwrap(),
+            component()
+        );
+        assert_eq!(
+            Encrypted::<Json, Keys>::deserialize::<TestStruct>(&new).unwrap(),
+            component()
+        );
+
+        Keys::keyring().remove(1);
+        assert_eq!(
+            decryption_error(Encrypted::<Json, Keys>::deserialize(&old)),
+            CryptoError::UnknownKey(1)
+        );
+    }
+
+    #[test]
+    fn unique_nonces() {
+        key_source!(Keys);
+        Keys::keyring().rotate(1, [7; 32]);
+
+        let nonces: HashSet<Vec<u8>> = (0..10_000)
+            .map(|_| {
+                let serialized = Encrypted::<Json, Keys>::serialize(component()).unwrap();
+                serialized[HEADER..HEADER + NONCE].to_vec()
+            })
+            .collect();
+
+        assert_eq!(nonces.len(), 10_000);
+    }
+}
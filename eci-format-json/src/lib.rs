@@ -1,14 +1,31 @@
 use std::fmt::Display;
 
-use eci_core::backend::{AccessError, Format};
+use eci_core::{
+    backend::{AccessError, DeserializationLimits, Format, Limit},
+    Component,
+};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Clone)]
 pub struct Json;
 
+/// Stores components as pretty-printed JSON with the keys of every object
+/// sorted, so equal components are always written the same way, and stored
+/// components are easy to read and diff. Reads compact JSON written by
+/// [`Json`] just as well, and vice versa.
+#[derive(Clone)]
+pub struct JsonPretty;
+
 impl Format for Json {
+    const IS_JSON: bool = true;
+
     type Data = Vec<u8>;
 
+    fn name() -> String {
+        "json".to_string()
+    }
+
     fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
         Ok(serde_json::to_string(&value)
             .map_err(AccessError::serialization)?
@@ -19,6 +36,102 @@ impl Format for Json {
         let source = String::from_utf8(value.to_vec()).map_err(AccessError::serialization)?;
         serde_json::from_str(&source).map_err(AccessError::serialization)
     }
+
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        limits.check_size(T::COMPONENT_TYPE, value.len())?;
+
+        if nesting_depth(value) > limits.max_depth {
+            return Err(AccessError::LimitExceeded {
+                component: T::COMPONENT_TYPE.to_string(),
+                limit: Limit::Depth(limits.max_depth),
+            });
+        }
+
+        Self::deserialize(value)
+    }
+}
+
+impl Format for JsonPretty {
+    const IS_JSON: bool = true;
+
+    type Data = Vec<u8>;
+
+    fn name() -> String {
+        "json".to_string()
+    }
+
+    fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        let value = serde_json::to_value(&value).map_err(AccessError::serialization)?;
+
+        Ok(serde_json::to_string_pretty(&sort_keys(value))
+            .map_err(AccessError::serialization)?
+            .into())
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+        Json::deserialize(value)
+    }
+
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        Json::deserialize_bounded(value, limits)
+    }
+}
+
+/// Sorts the keys of every object within the value. Objects are already
+/// sorted unless serde_json preserves the order keys were inserted in,
+/// which another crate may have enabled.
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<_> = object.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+/// Determines the maximum nesting depth of arrays and objects within the
+/// document, without allocating anything on behalf of its contents.
+fn nesting_depth(value: &[u8]) -> usize {
+    let (mut depth, mut max_depth) = (0usize, 0usize);
+    let (mut in_string, mut escaped) = (false, false);
+
+    for byte in value {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+        } else {
+            match byte {
+                b'"' => in_string = true,
+                b'[' | b'{' => {
+                    depth += 1;
+                    max_depth = max_depth.max(depth);
+                }
+                b']' | b'}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    max_depth
 }
 
 impl Display for Json {
@@ -27,18 +140,47 @@ impl Display for Json {
     }
 }
 
+impl Display for JsonPretty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "pretty json")
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use eci_core::backend::Format;
+    use eci_core::{
+        backend::{AccessError, DeserializationLimits, Format, Limit},
+        Component,
+    };
     use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
 
-    use crate::Json;
+    use crate::{Json, JsonPretty};
 
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Component)]
     struct TestStruct {
         content: String,
     }
 
+    #[derive(Debug, Serialize, Deserialize, Component)]
+    struct Nested(serde_json::Value);
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Component)]
+    struct Inventory {
+        owner: String,
+        items: HashMap<String, u32>,
+    }
+
+    fn inventory(items: &[(&str, u32)]) -> Inventory {
+        Inventory {
+            owner: "Hello world!".to_string(),
+            items: items
+                .iter()
+                .map(|(name, count)| (name.to_string(), *count))
+                .collect(),
+        }
+    }
+
     #[test]
     fn test_roundtrip() {
         let component = TestStruct {
@@ -50,4 +192,90 @@ mod tests {
 
         assert_eq!(deserialized, component);
     }
+
+    #[test]
+    fn test_bounded_roundtrip() {
+        let component = TestStruct {
+            content: "Hello [{ world!".to_string(),
+        };
+
+        let serialized = Json::serialize(component.clone()).unwrap();
+        let deserialized: TestStruct =
+            Json::deserialize_bounded(&serialized, &DeserializationLimits::default()).unwrap();
+
+        assert_eq!(deserialized, component);
+    }
+
+    #[test]
+    fn reject_deep_nesting() {
+        let payload = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000)).into_bytes();
+
+        match Json::deserialize_bounded::<Nested>(&payload, &DeserializationLimits::default()) {
+            Err(AccessError::LimitExceeded { component, limit }) => {
+                assert_eq!(component, "Nested");
+                assert_eq!(limit, Limit::Depth(64));
+            }
+            other => panic!("expected depth limit to be exceeded, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reject_oversized_payload() {
+        let limits = DeserializationLimits {
+            max_size: 1024,
+            ..Default::default()
+        };
+
+        let payload = Json::serialize(vec![0u8; 4096]).unwrap();
+
+        match Json::deserialize_bounded::<Nested>(&payload, &limits) {
+            Err(AccessError::LimitExceeded { component, limit }) => {
+                assert_eq!(component, "Nested");
+                assert_eq!(limit, Limit::Size(1024));
+            }
+            other => panic!("expected size limit to be exceeded, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pretty_output_is_stable() {
+        let names: Vec<_> = (0..64).map(|index| format!("item{index}")).collect();
+        let items: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.as_str(), index as u32))
+            .collect();
+        let reversed: Vec<_> = items.iter().rev().copied().collect();
+
+        let first = JsonPretty::serialize(inventory(&items)).unwrap();
+        assert_eq!(first, JsonPretty::serialize(inventory(&items)).unwrap());
+        assert_eq!(first, JsonPretty::serialize(inventory(&reversed)).unwrap());
+
+        assert_eq!(
+            String::from_utf8(JsonPretty::serialize(inventory(&[("b", 1), ("a", 2)])).unwrap())
+                .unwrap(),
+            "{\n  \"items\": {\n    \"a\": 2,\n    \"b\": 1\n  },\n  \"owner\": \"Hello world!\"\n}"
+        );
+    }
+
+    #[test]
+    fn read_either_layout() {
+        let component = inventory(&[("b", 1), ("a", 2)]);
+        let compact = Json::serialize(component.clone()).unwrap();
+        let pretty = JsonPretty::serialize(component.clone()).unwrap();
+
+        assert_eq!(
+            JsonPretty::deserialize::<Inventory>(&compact).unwrap(),
+            component
+        );
+        assert_eq!(Json::deserialize::<Inventory>(&pretty).unwrap(), component);
+        assert_eq!(
+            JsonPretty::deserialize_bounded::<Inventory>(
+                &pretty,
+                &DeserializationLimits::default()
+            )
+            .unwrap(),
+            component
+        );
+    }
 }
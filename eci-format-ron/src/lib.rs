@@ -0,0 +1,226 @@
+//! Stores components as RON, Rust's own object notation, in which tuples,
+//! newtypes and enum variants look the way they do in Rust code. This makes
+//! components easy to read and edit by hand, at the cost of space.
+//!
+//! [`Ron`] pretty-prints components over several lines, naming structs
+//! and newtypes, while [`CompactRon`] writes them on a single line. Both
+//! formats read each other's contents.
+
+use std::fmt::Display;
+
+use eci_core::{
+    backend::{AccessError, DeserializationLimits, Format, Limit},
+    Component,
+};
+use ron::{ser::PrettyConfig, Options};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Stores components as pretty-printed RON.
+#[derive(Clone)]
+pub struct Ron;
+
+/// Stores components as RON on a single line.
+#[derive(Clone)]
+pub struct CompactRon;
+
+fn decode<T: DeserializeOwned>(value: &[u8]) -> Result<T, AccessError> {
+    ron::de::from_bytes(value).map_err(AccessError::serialization)
+}
+
+fn decode_bounded<T: Component + DeserializeOwned>(
+    value: &[u8],
+    limits: &DeserializationLimits,
+) -> Result<T, AccessError> {
+    limits.check_size(T::COMPONENT_TYPE, value.len())?;
+
+    match Options::default()
+        .with_recursion_limit(limits.max_depth)
+        .from_bytes(value)
+    {
+        Ok(value) => Ok(value),
+        Err(err) if err.code == ron::Error::ExceededRecursionLimit => {
+            Err(AccessError::LimitExceeded {
+                component: T::COMPONENT_TYPE.to_string(),
+                limit: Limit::Depth(limits.max_depth),
+            })
+        }
+        Err(err) => Err(AccessError::serialization(err)),
+    }
+}
+
+impl Format for Ron {
+    type Data = Vec<u8>;
+
+    fn name() -> String {
+        "ron".to_string()
+    }
+
+    fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        let config = PrettyConfig::new().struct_names(true);
+
+        Ok(ron::ser::to_string_pretty(&value, config)
+            .map_err(AccessError::serialization)?
+            .into())
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+        decode(value)
+    }
+
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        decode_bounded(value, limits)
+    }
+}
+
+impl Format for CompactRon {
+    type Data = Vec<u8>;
+
+    fn name() -> String {
+        "ron".to_string()
+    }
+
+    fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+        Ok(ron::ser::to_string(&value)
+            .map_err(AccessError::serialization)?
+            .into())
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+        decode(value)
+    }
+
+    fn deserialize_bounded<T: Component + DeserializeOwned>(
+        value: &Self::Data,
+        limits: &DeserializationLimits,
+    ) -> Result<T, AccessError> {
+        decode_bounded(value, limits)
+    }
+}
+
+impl Display for Ron {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ron")
+    }
+}
+
+impl Display for CompactRon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "compact ron")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_core::{
+        backend::{AccessError, DeserializationLimits, Format, Limit},
+        Component,
+    };
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use crate::{CompactRon, Ron};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Component)]
+    struct TestStruct {
+        content: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Component)]
+    struct CounterA(usize);
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Component)]
+    enum Shape {
+        Point,
+        Circle { radius: u32 },
+        Line(i32, i32),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Component)]
+    struct Nested(Vec<Nested>);
+
+    fn roundtrip<F: Format, T: Serialize + DeserializeOwned + Clone>(value: T) -> T {
+        F::deserialize(&F::serialize(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let component = TestStruct {
+            content: "Hello world!".to_string(),
+        };
+
+        assert_eq!(roundtrip::<Ron, _>(component.clone()), component);
+        assert_eq!(roundtrip::<CompactRon, _>(component.clone()), component);
+    }
+
+    #[test]
+    fn test_bounded_roundtrip() {
+        let component = TestStruct {
+            content: "Hello [{ world!".to_string(),
+        };
+
+        let serialized = Ron::serialize(component.clone()).unwrap();
+        let deserialized: TestStruct =
+            Ron::deserialize_bounded(&serialized, &DeserializationLimits::default()).unwrap();
+
+        assert_eq!(deserialized, component);
+    }
+
+    #[test]
+    fn newtypes_and_enums() {
+        assert_eq!(Ron::serialize(CounterA(5)).unwrap(), b"CounterA(5)");
+        assert_eq!(CompactRon::serialize(CounterA(5)).unwrap(), b"(5)");
+        assert_eq!(
+            Ron::serialize(Shape::Circle { radius: 2 }).unwrap(),
+            b"Circle(\n    radius: 2,\n)"
+        );
+
+        for shape in [
+            Shape::Point,
+            Shape::Circle { radius: 2 },
+            Shape::Line(-1, 1),
+        ] {
+            assert_eq!(roundtrip::<Ron, _>(shape.clone()), shape);
+            assert_eq!(roundtrip::<CompactRon, _>(shape.clone()), shape);
+        }
+
+        // Hand-edited contents need not follow either layout.
+        let edited = b"  Circle ( radius : 7 ) ".to_vec();
+        assert_eq!(
+            Ron::deserialize::<Shape>(&edited).unwrap(),
+            Shape::Circle { radius: 7 }
+        );
+    }
+
+    #[test]
+    fn reject_deep_nesting() {
+        let payload = format!("{}{}", "([".repeat(1_000), "])".repeat(1_000)).into_bytes();
+
+        match Ron::deserialize_bounded::<Nested>(&payload, &DeserializationLimits::default()) {
+            Err(AccessError::LimitExceeded { component, limit }) => {
+                assert_eq!(component, "Nested");
+                assert_eq!(limit, Limit::Depth(64));
+            }
+            other => panic!("expected depth limit to be exceeded, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reject_oversized_payload() {
+        let limits = DeserializationLimits {
+            max_size: 1024,
+            ..Default::default()
+        };
+
+        let payload = Ron::serialize(vec![0u8; 4096]).unwrap();
+
+        match Ron::deserialize_bounded::<Nested>(&payload, &limits) {
+            Err(AccessError::LimitExceeded { component, limit }) => {
+                assert_eq!(component, "Nested");
+                assert_eq!(limit, Limit::Size(1024));
+            }
+            other => panic!("expected size limit to be exceeded, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use eci_backend_memory::MemoryBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, ExtractionDescriptor},
+    Component, Entity,
+};
+use eci_format_ron::{CompactRon, Ron};
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterA(pub usize);
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+enum Shape {
+    Point,
+    Circle { radius: u32 },
+}
+
+#[test]
+fn put_get_and_remove() {
+    let backend = Backend::<Ron>::from_joint(MemoryBackend::new());
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(1), Shape::Circle { radius: 2 }))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend.get::<&mut Shape>(entity).unwrap().unwrap();
+    *locked.deref() = Shape::Point;
+    locked.commit().unwrap();
+
+    assert_eq!(
+        backend.peek::<(&CounterA, &Shape)>(entity).unwrap(),
+        Some((CounterA(1), Shape::Point))
+    );
+
+    let stored = AccessBackend::<Ron>::read_components(
+        &backend,
+        entity,
+        vec![ExtractionDescriptor {
+            name: "CounterA".to_string(),
+            version: None,
+            schema: None,
+        }],
+    )
+    .unwrap()
+    .remove(0)
+    .unwrap();
+    assert_eq!(stored.contents, b"CounterA(1)");
+
+    backend.remove::<(CounterA, Shape)>(entity).unwrap();
+    assert_eq!(backend.peek::<&CounterA>(entity).unwrap(), None);
+}
+
+#[test]
+fn compact_roundtrip() {
+    let backend = Backend::<CompactRon>::from_joint(MemoryBackend::new());
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(3), Shape::Circle { radius: 4 }))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    assert_eq!(
+        backend.peek::<(&CounterA, &Shape)>(entity).unwrap(),
+        Some((CounterA(3), Shape::Circle { radius: 4 }))
+    );
+}
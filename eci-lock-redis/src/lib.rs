@@ -0,0 +1,89 @@
+//! Keeps locks in Redis, for pairing with an access backend which cannot
+//! lock by itself, or whose locks are not visible to other processes:
+//!
+//! ```ignore
+//! let backend: Backend<Json> = Backend::from_disjoint(
+//!     SqliteBackend::open("world.db")?,
+//!     RedisLockingBackend::open("redis://127.0.0.1/")?.with_owner("worker-1"),
+//! );
+//! ```
+//!
+//! Each locked component maps to its own key. A write lock is a
+//! `write:{entity}:{component}` key set only if absent, holding the id of
+//! the lock and expiring along with it. Read locks are counted by a
+//! `readers:{entity}:{component}` sorted set of the readers' lock ids, scored
+//! by when they expire, so readers which never release stop counting once
+//! they expire. An exclusive lock is an `exclusive:{entity}` key like the
+//! write keys. Conflicts follow the rules of the sqlite backend: writes
+//! conflict with any lock on the component, reads with writes, and
+//! exclusive locks with every lock on the entity.
+//!
+//! Every descriptor of a request is taken by a single Lua script, so locks
+//! are acquired all-or-nothing, and releasing deletes a key only while it
+//! still holds the id of the lock being released.
+//!
+//! Locks expire on the Redis server's clock, which requires Redis 5 or later
+//! for scripts to read it. As with the redis backend, every key must live on
+//! the same node, so Redis Cluster is not supported.
+//!
+//! Since the locks live apart from the components, [`Backend`] checks that
+//! a lock is held before writing with it rather than within the write, and
+//! reads happen once the locks are taken rather than along with them.
+//! Backends storing their components in the same Redis instance can check
+//! within their own scripts instead, see [`HOLDS_WRITE_LOCK`].
+//!
+//! [`Backend`]: eci_core::backend::Backend
+
+mod lock;
+mod scripts;
+
+use eci_core::backend::LockingError;
+use r2d2::Pool;
+use scripts::Scripts;
+
+pub use scripts::HOLDS_WRITE_LOCK;
+
+pub struct RedisLockingBackend {
+    pool: Pool<redis::Client>,
+    /// Prefixed to every key, so several backends can share a database.
+    namespace: String,
+    owner: Option<String>,
+    scripts: Scripts,
+}
+
+impl RedisLockingBackend {
+    /// Connects to the Redis instance at the url, such as
+    /// `redis://127.0.0.1/`, keeping keys under the `eci` namespace.
+    pub fn open(url: &str) -> Result<Self, LockingError> {
+        let client = redis::Client::open(url).map_err(LockingError::implementation)?;
+        let pool = Pool::new(client).map_err(LockingError::implementation)?;
+
+        Ok(Self::from_pool(pool))
+    }
+
+    /// Keeps locks in the Redis instance behind an existing pool, such as
+    /// one shared with a backend storing components there.
+    pub fn from_pool(pool: Pool<redis::Client>) -> Self {
+        RedisLockingBackend {
+            pool,
+            namespace: "eci".to_string(),
+            owner: None,
+            scripts: Scripts::new(),
+        }
+    }
+
+    /// Keeps keys under another namespace, so the locks of one backend are
+    /// invisible to backends using other namespaces.
+    pub fn with_namespace<N: Into<String>>(mut self, namespace: N) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Records the owner, such as a hostname, process id or service name,
+    /// with every lock acquired through this backend, so others running
+    /// into the locks can tell who holds them.
+    pub fn with_owner<O: Into<String>>(mut self, owner: O) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+}
@@ -0,0 +1,395 @@
+use eci_core::{
+    backend::{
+        ActiveLock, Administrative, BulkLockResult, ConflictingLock, Expiry, Lock, LockDescriptor,
+        LockStats, LockingBackend, LockingError, LockingMode,
+    },
+    Entity,
+};
+use log::*;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+use crate::RedisLockingBackend;
+
+impl LockingBackend for RedisLockingBackend {
+    fn acquire_lock(
+        &self,
+        entity: Entity,
+        descriptors: Vec<LockDescriptor>,
+        expires: Expiry,
+    ) -> Result<Lock, LockingError> {
+        if descriptors.is_empty() {
+            return Err(LockingError::EmptyRequest);
+        }
+
+        let lock = Lock::new();
+        let ttl = ttl(expires)?;
+
+        debug!("acquiring lock {lock}");
+        let reply = self.acquire(&lock, ttl, false, &[(entity, descriptors)])?;
+        if !reply.is_empty() {
+            return Err(conflict(&reply)?);
+        }
+
+        Ok(with_expiry(lock, ttl))
+    }
+
+    fn acquire_locks(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<Lock, LockingError> {
+        if requests.is_empty()
+            || requests
+                .iter()
+                .any(|(_, descriptors)| descriptors.is_empty())
+        {
+            return Err(LockingError::EmptyRequest);
+        }
+
+        let lock = Lock::new();
+        let ttl = ttl(Expiry::In(expires_in))?;
+
+        debug!("acquiring multi-entity lock {lock}");
+        let reply = self.acquire(&lock, ttl, false, &requests)?;
+        if !reply.is_empty() {
+            return Err(conflict(&reply)?);
+        }
+
+        Ok(with_expiry(lock, ttl))
+    }
+
+    fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+        debug!("releasing lock {lock}");
+
+        let released = self.release(&lock.id())?;
+        debug!("deleted locks on {released} resources by releasing {lock}");
+        Ok(())
+    }
+
+    fn renew_lock(&self, lock: &Lock, extend_by: Duration) -> Result<(), LockingError> {
+        debug!("renewing lock {lock}");
+        let ttl = ttl(Expiry::In(extend_by))?;
+
+        let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+        let renewed: usize = self
+            .scripts
+            .renew
+            .arg(&self.namespace)
+            .arg(lock.id())
+            .arg(ttl)
+            .invoke(&mut *conn)
+            .map_err(LockingError::implementation)?;
+
+        if renewed == 0 {
+            return Err(LockingError::Expired(lock.id()));
+        }
+
+        debug!("renewed locks on {renewed} resources held by {lock}");
+        Ok(())
+    }
+
+    fn upgrade_lock(
+        &self,
+        lock: &Lock,
+        entity: Entity,
+        component: String,
+    ) -> Result<(), LockingError> {
+        let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+        let reply: Vec<String> = self
+            .scripts
+            .upgrade
+            .arg(&self.namespace)
+            .arg(lock.id())
+            .arg(entity.to_string())
+            .arg(&component)
+            .invoke(&mut *conn)
+            .map_err(LockingError::implementation)?;
+
+        match reply.first().map(String::as_str) {
+            None => {
+                debug!("upgraded lock {lock} on {entity}'s {component} to a write lock");
+                Ok(())
+            }
+            Some("expired") => Err(LockingError::Expired(lock.id())),
+            Some(_) => Err(conflict(&reply[1..])?),
+        }
+    }
+
+    fn force_release(&self, lock_id: Uuid, _: Administrative) -> Result<usize, LockingError> {
+        warn!("forcibly releasing lock {lock_id}");
+        self.release(&lock_id.to_string())
+    }
+
+    fn force_release_entity(
+        &self,
+        entity: Entity,
+        _: Administrative,
+    ) -> Result<usize, LockingError> {
+        warn!("forcibly releasing all locks on {entity}");
+
+        let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+        self.scripts
+            .release_entity
+            .arg(&self.namespace)
+            .arg(entity.to_string())
+            .invoke(&mut *conn)
+            .map_err(LockingError::implementation)
+    }
+
+    fn lock_stats(&self) -> Result<LockStats, LockingError> {
+        let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+        let (active, expired) = self
+            .scripts
+            .lock_stats
+            .arg(&self.namespace)
+            .invoke(&mut *conn)
+            .map_err(LockingError::implementation)?;
+
+        Ok(LockStats { active, expired })
+    }
+
+    fn list_locks(&self, filter: Option<Entity>) -> Result<Vec<ActiveLock>, LockingError> {
+        let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+        let reply: Vec<String> = self
+            .scripts
+            .list_locks
+            .arg(&self.namespace)
+            .arg(filter.map(|entity| entity.to_string()).unwrap_or_default())
+            .invoke(&mut *conn)
+            .map_err(LockingError::implementation)?;
+
+        let now = SystemTime::now();
+        let mut locks = reply
+            .chunks(6)
+            .map(|lock| {
+                Ok(ActiveLock {
+                    id: lock[3].clone(),
+                    entity: parse_entity(&lock[0])?,
+                    component: lock[1].clone(),
+                    mode: locking_mode(&lock[2]),
+                    expires: expires_at(now, &lock[5])?,
+                    owner: owner(&lock[4]),
+                })
+            })
+            .collect::<Result<Vec<_>, LockingError>>()?;
+
+        locks.sort_by(|a, b| (a.entity, &a.component, &a.id).cmp(&(b.entity, &b.component, &b.id)));
+        Ok(locks)
+    }
+
+    fn acquire_locks_bulk(
+        &self,
+        requests: Vec<(Entity, Vec<LockDescriptor>)>,
+        expires_in: Duration,
+    ) -> Result<BulkLockResult, LockingError> {
+        let lock = Lock::new();
+        let ttl = ttl(Expiry::In(expires_in))?;
+
+        debug!("acquiring bulk lock {lock}");
+        let statuses = self.acquire(&lock, ttl, true, &requests)?;
+
+        let (mut granted, mut skipped) = (Vec::new(), Vec::new());
+        for ((entity, _), status) in requests.iter().zip(statuses) {
+            if status == "1" {
+                granted.push(*entity);
+            } else {
+                debug!("skipping {entity} due to conflicting locks");
+                skipped.push(*entity);
+            }
+        }
+
+        debug!("bulk lock {lock} locked {} entities", granted.len());
+        Ok(BulkLockResult {
+            lock: with_expiry(lock, ttl),
+            granted,
+            skipped,
+        })
+    }
+}
+
+impl RedisLockingBackend {
+    /// Runs the acquisition script, which takes every lock or none of them,
+    /// or with `skip`, every lock of each entity or none of that entity's.
+    fn acquire(
+        &self,
+        lock: &Lock,
+        ttl: u64,
+        skip: bool,
+        requests: &[(Entity, Vec<LockDescriptor>)],
+    ) -> Result<Vec<String>, LockingError> {
+        let mut invocation = self.scripts.acquire.prepare_invoke();
+        invocation
+            .arg(&self.namespace)
+            .arg(lock.id())
+            .arg(self.owner.as_deref().unwrap_or_default())
+            .arg(ttl)
+            .arg(if skip { "1" } else { "0" });
+
+        for (entity, descriptors) in requests {
+            invocation.arg(entity.to_string()).arg(descriptors.len());
+            for descriptor in descriptors {
+                invocation
+                    .arg(&descriptor.name)
+                    .arg(descriptor.mode.to_string());
+            }
+        }
+
+        let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+        invocation
+            .invoke(&mut *conn)
+            .map_err(LockingError::implementation)
+    }
+
+    fn release(&self, lock_id: &str) -> Result<usize, LockingError> {
+        let mut conn = self.pool.get().map_err(LockingError::implementation)?;
+        self.scripts
+            .release
+            .arg(&self.namespace)
+            .arg(lock_id)
+            .invoke(&mut *conn)
+            .map_err(LockingError::implementation)
+    }
+}
+
+/// Converts the expiry into milliseconds for Redis, where 0 means never.
+/// Finite expiries are at least a millisecond, since Redis rejects 0.
+fn ttl(expires: Expiry) -> Result<u64, LockingError> {
+    let expires_in = match expires {
+        Expiry::In(expires_in) => expires_in,
+        Expiry::Never => return Ok(0),
+    };
+
+    // Redis adds the ttl to its own clock in milliseconds, which must not
+    // overflow, and scores locks by it as a double, which must stay exact.
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    let millis = expires_in.as_millis();
+    if millis + now > (1 << f64::MANTISSA_DIGITS) {
+        return Err(LockingError::ExpiryOutOfRange(expires_in));
+    }
+
+    Ok((millis as u64).max(1))
+}
+
+fn with_expiry(lock: Lock, ttl: u64) -> Lock {
+    match ttl {
+        0 => lock,
+        ttl => lock.with_expiry(SystemTime::now() + Duration::from_millis(ttl)),
+    }
+}
+
+fn parse_entity(entity: &str) -> Result<Entity, LockingError> {
+    Uuid::parse_str(entity)
+        .map(Entity)
+        .map_err(LockingError::implementation)
+}
+
+fn locking_mode(mode: &str) -> LockingMode {
+    match mode {
+        "write" => LockingMode::Write,
+        "exclusive" => LockingMode::Exclusive,
+        _ => LockingMode::Read,
+    }
+}
+
+fn owner(owner: &str) -> Option<String> {
+    (!owner.is_empty()).then(|| owner.to_string())
+}
+
+/// Turns the remaining time to live reported by a script into a point in
+/// time, or `None` for locks which never expire.
+fn expires_at(now: SystemTime, ttl: &str) -> Result<Option<SystemTime>, LockingError> {
+    let ttl: i64 = ttl.parse().map_err(LockingError::implementation)?;
+    Ok(u64::try_from(ttl)
+        .ok()
+        .map(|ttl| now + Duration::from_millis(ttl)))
+}
+
+/// Builds the conflict described by a script: the entity, component and mode
+/// requested, followed by the id, mode, owner and ttl of the conflicting
+/// lock, if it is known.
+fn conflict(reply: &[String]) -> Result<LockingError, LockingError> {
+    let entity = parse_entity(&reply[0])?;
+
+    let holder = match reply.get(3..7) {
+        Some([id, mode, holder, ttl]) => Some(ConflictingLock {
+            id: id.clone(),
+            mode: locking_mode(mode),
+            owner: owner(holder),
+            expires: expires_at(SystemTime::now(), ttl)?,
+        }),
+        _ => None,
+    };
+
+    Ok(LockingError::Conflict(
+        entity,
+        reply[1].clone(),
+        locking_mode(&reply[2]),
+        holder,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use eci_core::{
+        backend::{Expiry, LockingError, LockingMode},
+        Entity,
+    };
+
+    use super::{conflict, expires_at, ttl};
+
+    #[test]
+    fn convert_expiries() {
+        assert_eq!(ttl(Expiry::Never).unwrap(), 0);
+        assert_eq!(ttl(Duration::from_secs(2).into()).unwrap(), 2000);
+        assert_eq!(ttl(Duration::from_micros(10).into()).unwrap(), 1);
+        assert!(matches!(
+            ttl(Duration::from_secs(1 << 50).into()),
+            Err(LockingError::ExpiryOutOfRange(_))
+        ));
+
+        let now = SystemTime::now();
+        assert_eq!(expires_at(now, "-1").unwrap(), None);
+        assert_eq!(
+            expires_at(now, "1500").unwrap(),
+            Some(now + Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn describe_conflicts() {
+        let entity = Entity::new();
+        let reply = [
+            entity.to_string(),
+            "DebugComponentA".to_string(),
+            "read".to_string(),
+            "held".to_string(),
+            "write".to_string(),
+            "worker".to_string(),
+            "-1".to_string(),
+        ];
+
+        match conflict(&reply).unwrap() {
+            LockingError::Conflict(conflicting, name, LockingMode::Read, Some(holder)) => {
+                assert_eq!(conflicting, entity);
+                assert_eq!(name, "DebugComponentA");
+                assert_eq!(holder.id, "held");
+                assert_eq!(holder.mode, LockingMode::Write);
+                assert_eq!(holder.owner.as_deref(), Some("worker"));
+                assert_eq!(holder.expires, None);
+            }
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+
+        assert!(matches!(
+            conflict(&reply[..3]).unwrap(),
+            LockingError::Conflict(_, _, _, None)
+        ));
+    }
+}
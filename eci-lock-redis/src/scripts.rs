@@ -0,0 +1,427 @@
+use redis::Script;
+
+/// Shared by every script. The first argument is always the namespace all
+/// keys are prefixed with.
+///
+/// Besides the keys each lock maps to, every entity has a `held` sorted set
+/// of its component locks scored by when they expire, and every lock a set
+/// of the component locks it holds, for looking them up without scanning.
+/// Scores are milliseconds on the Redis clock, or `+inf` for locks which
+/// never expire.
+const PRELUDE: &str = r#"
+local ns = ARGV[1]
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local function write_key(entity, component) return ns .. ':write:' .. entity .. ':' .. component end
+local function readers_key(entity, component) return ns .. ':readers:' .. entity .. ':' .. component end
+local function exclusive_key(entity) return ns .. ':exclusive:' .. entity end
+local function held_key(entity) return ns .. ':held:' .. entity end
+local function lock_set(lockid) return ns .. ':lockid:' .. lockid end
+local function owner_key(lockid) return ns .. ':owner:' .. lockid end
+
+-- Splits an entry of the held set into the component, mode and lock id.
+local function parse(held)
+  return string.match(held, '^(.*):(%a+):([^:]+)$')
+end
+
+-- Splits an entry of a lock's set into the entity, component and mode.
+local function parse_member(member)
+  return string.match(member, '^([^:]+):(.*):(%a+)$')
+end
+
+-- Converts a score into a point in time, or nil for never.
+local function expiry_of(score)
+  if score == 'inf' or score == '+inf' then
+    return nil
+  end
+  return tonumber(score)
+end
+
+-- Milliseconds until the score, or -1 if it never comes.
+local function ttl_of(score)
+  local expiry = expiry_of(score)
+  if expiry == nil then
+    return -1
+  end
+  return expiry - now
+end
+
+local function is_live(score)
+  local expiry = expiry_of(score)
+  return score and (expiry == nil or expiry > now)
+end
+
+-- The key a write or exclusive lock is stored under.
+local function exclusive_or_write_key(entity, component, mode)
+  if mode == 'exclusive' then
+    return exclusive_key(entity)
+  end
+  return write_key(entity, component)
+end
+
+-- Lets a sorted set of locks expire along with the last of them, so sets
+-- left behind by locks which expired go away by themselves.
+local function expire_with_last(key)
+  local last = redis.call('ZRANGE', key, -1, -1, 'WITHSCORES')
+  if #last == 0 then
+    return
+  end
+  local expiry = expiry_of(last[2])
+  if expiry == nil then
+    redis.call('PERSIST', key)
+  else
+    redis.call('PEXPIREAT', key, expiry)
+  end
+end
+
+-- Drops the entity from the set of locked entities once it has no locks.
+local function forget_if_unlocked(entity)
+  if redis.call('ZCARD', held_key(entity)) == 0 then
+    redis.call('SREM', ns .. ':locked', entity)
+  end
+end
+
+-- Whether a lock held in one mode on a component keeps a lock in another
+-- mode from being taken, following the rules of the sqlite backend.
+local function conflicts(held_component, held_mode, component, mode)
+  return held_mode == 'exclusive' or mode == 'exclusive'
+    or (held_component == component and (held_mode == 'write' or mode == 'write'))
+end
+
+-- Whether the lock can be taken, going by the keys it maps to. Expired
+-- readers are forgotten along the way.
+local function is_free(entity, component, mode)
+  if redis.call('EXISTS', exclusive_key(entity)) == 1 then
+    return false
+  end
+  if mode == 'exclusive' then
+    return redis.call('ZCOUNT', held_key(entity), '(' .. now, '+inf') == 0
+  end
+  if redis.call('EXISTS', write_key(entity, component)) == 1 then
+    return false
+  end
+
+  local readers = readers_key(entity, component)
+  redis.call('ZREMRANGEBYSCORE', readers, '-inf', now)
+  return mode ~= 'write' or redis.call('ZCARD', readers) == 0
+end
+
+-- Finds the live lock held by others which conflicts with the requested
+-- one and expires first, returning its entry and score.
+local function find_holder(entity, component, mode, lockid)
+  local holder, holder_score = nil, nil
+  local live = redis.call('ZRANGEBYSCORE', held_key(entity), '(' .. now, '+inf', 'WITHSCORES')
+  for i = 1, #live, 2 do
+    local held_component, held_mode, held_id = parse(live[i])
+    local expiry = expiry_of(live[i + 1])
+    if held_id ~= lockid and conflicts(held_component, held_mode, component, mode)
+      and (holder == nil or (expiry ~= nil and (expiry_of(holder_score) == nil or expiry < expiry_of(holder_score)))) then
+      holder, holder_score = live[i], live[i + 1]
+    end
+  end
+  return holder, holder_score
+end
+
+-- Describes a conflict, and the lock it is with if it is known.
+local function describe(entity, component, mode, lockid)
+  local holder, score = find_holder(entity, component, mode, lockid)
+  if holder == nil then
+    return {entity, component, mode}
+  end
+  local _, held_mode, held_id = parse(holder)
+  local owner = redis.call('GET', owner_key(held_id)) or ''
+  return {entity, component, mode, held_id, held_mode, owner, string.format('%d', ttl_of(score))}
+end
+
+-- Records the lock in the entity's and the lock's own sets.
+local function index(entity, component, mode, lockid, score)
+  redis.call('ZADD', held_key(entity), score, component .. ':' .. mode .. ':' .. lockid)
+  expire_with_last(held_key(entity))
+  redis.call('SADD', lock_set(lockid), entity .. ':' .. component .. ':' .. mode)
+  redis.call('SADD', ns .. ':locked', entity)
+end
+
+-- Takes a lock, which never expires if the ttl is not positive. Returns
+-- whether the key could be set.
+local function take(entity, component, mode, lockid, ttl)
+  local score = '+inf'
+  if ttl > 0 then
+    score = now + ttl
+  end
+
+  if mode == 'read' then
+    redis.call('ZADD', readers_key(entity, component), score, lockid)
+    expire_with_last(readers_key(entity, component))
+  else
+    local key = exclusive_or_write_key(entity, component, mode)
+    local set
+    if ttl > 0 then
+      set = redis.call('SET', key, lockid, 'NX', 'PX', ttl)
+    else
+      set = redis.call('SET', key, lockid, 'NX')
+    end
+    if not set then
+      return false
+    end
+  end
+
+  index(entity, component, mode, lockid, score)
+  return true
+end
+
+-- Gives back a single component lock, deleting its key only if the lock
+-- still holds it. Returns the number of component locks released.
+local function give_back(entity, component, mode, lockid)
+  if mode == 'read' then
+    redis.call('ZREM', readers_key(entity, component), lockid)
+  else
+    local key = exclusive_or_write_key(entity, component, mode)
+    if redis.call('GET', key) == lockid then
+      redis.call('DEL', key)
+    end
+  end
+
+  local released = redis.call('ZREM', held_key(entity), component .. ':' .. mode .. ':' .. lockid)
+  redis.call('SREM', lock_set(lockid), entity .. ':' .. component .. ':' .. mode)
+  if redis.call('EXISTS', lock_set(lockid)) == 0 then
+    redis.call('DEL', owner_key(lockid))
+  end
+  forget_if_unlocked(entity)
+  return released
+end
+"#;
+
+/// Defines `holds_write_lock(entity, component, lockid)`, telling whether
+/// the lock holds an unexpired write lock on the component, or an exclusive
+/// lock on the whole entity. For scripts which keep other keys under the
+/// same namespace, which they must have stored in `ns` beforehand.
+pub const HOLDS_WRITE_LOCK: &str = r#"
+local function holds_write_lock(entity, component, lockid)
+  return redis.call('GET', ns .. ':write:' .. entity .. ':' .. component) == lockid
+    or redis.call('GET', ns .. ':exclusive:' .. entity) == lockid
+end
+"#;
+
+/// Arguments: lock id, owner, ttl in milliseconds or 0 to never expire,
+/// whether to skip conflicting entities, then for each entity its id, the
+/// number of components to lock, and the component and mode of each.
+///
+/// Returns nothing on success, the conflict if not skipping conflicting
+/// entities, and whether each entity was locked otherwise.
+const ACQUIRE: &str = r#"
+local lockid, owner, ttl, skip = ARGV[2], ARGV[3], tonumber(ARGV[4]), ARGV[5] == '1'
+local taken, statuses = {}, {}
+
+local function untake(from)
+  for i = #taken, from, -1 do
+    give_back(taken[i][1], taken[i][2], taken[i][3], lockid)
+    taken[i] = nil
+  end
+end
+
+local i = 6
+while i <= #ARGV do
+  local entity, count = ARGV[i], tonumber(ARGV[i + 1])
+  i = i + 2
+
+  redis.call('ZREMRANGEBYSCORE', held_key(entity), '-inf', now)
+  forget_if_unlocked(entity)
+
+  local from, failed = #taken + 1, nil
+  for j = i, i + 2 * (count - 1), 2 do
+    local component, mode = ARGV[j], ARGV[j + 1]
+    if not (is_free(entity, component, mode) and take(entity, component, mode, lockid, ttl)) then
+      failed = describe(entity, component, mode, lockid)
+      break
+    end
+    table.insert(taken, {entity, component, mode})
+  end
+  i = i + 2 * count
+
+  if failed then
+    untake(from)
+    if not skip then
+      untake(1)
+      return failed
+    end
+    table.insert(statuses, '0')
+  else
+    table.insert(statuses, '1')
+  end
+end
+
+if #taken > 0 then
+  if owner ~= '' then
+    redis.call('SET', owner_key(lockid), owner)
+  end
+  if ttl > 0 then
+    redis.call('PEXPIRE', lock_set(lockid), ttl)
+    redis.call('PEXPIRE', owner_key(lockid), ttl)
+  end
+end
+if skip then
+  return statuses
+end
+return {}
+"#;
+
+/// Arguments: lock id. Returns the number of component locks released.
+const RELEASE: &str = r#"
+local lockid = ARGV[2]
+local released = 0
+for _, member in ipairs(redis.call('SMEMBERS', lock_set(lockid))) do
+  local entity, component, mode = parse_member(member)
+  released = released + give_back(entity, component, mode, lockid)
+end
+redis.call('DEL', lock_set(lockid), owner_key(lockid))
+return released
+"#;
+
+/// Arguments: entity. Returns the number of component locks released.
+const RELEASE_ENTITY: &str = r#"
+local entity = ARGV[2]
+local released = 0
+for _, held in ipairs(redis.call('ZRANGE', held_key(entity), 0, -1)) do
+  local component, mode, lockid = parse(held)
+  released = released + give_back(entity, component, mode, lockid)
+end
+return released
+"#;
+
+/// Arguments: lock id, ttl in milliseconds. Returns the number of component
+/// locks renewed. Locks which never expire are left that way.
+const RENEW: &str = r#"
+local lockid, ttl = ARGV[2], tonumber(ARGV[3])
+local renewed, finite = 0, false
+for _, member in ipairs(redis.call('SMEMBERS', lock_set(lockid))) do
+  local entity, component, mode = parse_member(member)
+  local held = component .. ':' .. mode .. ':' .. lockid
+  local score = redis.call('ZSCORE', held_key(entity), held)
+  if is_live(score) then
+    renewed = renewed + 1
+    if expiry_of(score) ~= nil then
+      finite = true
+      redis.call('ZADD', held_key(entity), 'XX', now + ttl, held)
+      expire_with_last(held_key(entity))
+      if mode == 'read' then
+        redis.call('ZADD', readers_key(entity, component), 'XX', now + ttl, lockid)
+        expire_with_last(readers_key(entity, component))
+      else
+        redis.call('PEXPIRE', exclusive_or_write_key(entity, component, mode), ttl)
+      end
+    end
+  end
+end
+if finite then
+  redis.call('PEXPIRE', lock_set(lockid), ttl)
+  redis.call('PEXPIRE', owner_key(lockid), ttl)
+end
+return renewed
+"#;
+
+/// Arguments: lock id, entity, component. Returns nothing on success,
+/// `expired` if the lock is not held, and the conflict otherwise.
+const UPGRADE: &str = r#"
+local lockid, entity, component = ARGV[2], ARGV[3], ARGV[4]
+local read = component .. ':read:' .. lockid
+
+local score = redis.call('ZSCORE', held_key(entity), read)
+if not is_live(score) then
+  if redis.call('GET', write_key(entity, component)) == lockid then
+    return {}
+  end
+  return {'expired'}
+end
+
+local readers = readers_key(entity, component)
+redis.call('ZREMRANGEBYSCORE', readers, '-inf', now)
+if redis.call('ZCARD', readers) > 1 or redis.call('EXISTS', write_key(entity, component)) == 1 then
+  local conflict = describe(entity, component, 'write', lockid)
+  table.insert(conflict, 1, 'conflict')
+  return conflict
+end
+
+-- Giving back the last lock of the set drops the owner and the set's own
+-- expiry, so both are restored once the write lock is taken.
+local owner = redis.call('GET', owner_key(lockid))
+give_back(entity, component, 'read', lockid)
+index(entity, component, 'write', lockid, score)
+
+local ttl = ttl_of(score)
+if owner then
+  redis.call('SET', owner_key(lockid), owner)
+end
+if ttl > 0 then
+  redis.call('SET', write_key(entity, component), lockid, 'PX', ttl)
+  redis.call('PEXPIRE', lock_set(lockid), ttl)
+  redis.call('PEXPIRE', owner_key(lockid), ttl)
+else
+  redis.call('SET', write_key(entity, component), lockid)
+end
+return {}
+"#;
+
+/// Arguments: the entity, or an empty string for all of them. Returns the
+/// entity, component, mode, id, owner and ttl of each live lock.
+const LIST_LOCKS: &str = r#"
+local entities = {ARGV[2]}
+if ARGV[2] == '' then
+  entities = redis.call('SMEMBERS', ns .. ':locked')
+end
+
+local listed = {}
+for _, entity in ipairs(entities) do
+  local live = redis.call('ZRANGEBYSCORE', held_key(entity), '(' .. now, '+inf', 'WITHSCORES')
+  for i = 1, #live, 2 do
+    local component, mode, lockid = parse(live[i])
+    table.insert(listed, entity)
+    table.insert(listed, component)
+    table.insert(listed, mode)
+    table.insert(listed, lockid)
+    table.insert(listed, redis.call('GET', owner_key(lockid)) or '')
+    table.insert(listed, string.format('%d', ttl_of(live[i + 1])))
+  end
+end
+return listed
+"#;
+
+/// Returns the number of live and expired component locks, the latter being
+/// those still indexed after they expired.
+const LOCK_STATS: &str = r#"
+local active, expired = 0, 0
+for _, entity in ipairs(redis.call('SMEMBERS', ns .. ':locked')) do
+  active = active + redis.call('ZCOUNT', held_key(entity), '(' .. now, '+inf')
+  expired = expired + redis.call('ZCOUNT', held_key(entity), '-inf', now)
+end
+return {active, expired}
+"#;
+
+pub(crate) struct Scripts {
+    pub acquire: Script,
+    pub release: Script,
+    pub release_entity: Script,
+    pub renew: Script,
+    pub upgrade: Script,
+    pub list_locks: Script,
+    pub lock_stats: Script,
+}
+
+fn script(body: &str) -> Script {
+    Script::new(&format!("{PRELUDE}{body}"))
+}
+
+impl Scripts {
+    pub fn new() -> Self {
+        Scripts {
+            acquire: script(ACQUIRE),
+            release: script(RELEASE),
+            release_entity: script(RELEASE_ENTITY),
+            renew: script(RENEW),
+            upgrade: script(UPGRADE),
+            list_locks: script(LIST_LOCKS),
+            lock_stats: script(LOCK_STATS),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Pairs the sqlite backend's storage with locks kept in the Redis instance
+//! at `ECI_REDIS_URL`, or a local one, with
+//! `cargo test -p eci-lock-redis -- --ignored`.
+
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{
+        Backend, BackendError, Expiry, LockDescriptor, LockingBackend, LockingError, LockingMode,
+    },
+    Component, Entity,
+};
+use eci_format_json::Json;
+use eci_lock_redis::RedisLockingBackend;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterA(pub usize);
+
+#[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+struct CounterB(pub usize);
+
+fn locking() -> RedisLockingBackend {
+    let url = std::env::var("ECI_REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+    RedisLockingBackend::open(&url)
+        .unwrap()
+        .with_namespace(format!("eci-test-{}", Entity::new()))
+        .with_owner("eci-test")
+}
+
+fn backend() -> Backend<Json> {
+    Backend::from_disjoint(SqliteBackend::memory().unwrap(), locking())
+}
+
+fn descriptor(name: &str, mode: LockingMode) -> Vec<LockDescriptor> {
+    vec![LockDescriptor {
+        mode,
+        name: name.to_string(),
+    }]
+}
+
+#[test]
+#[ignore = "needs a Redis instance"]
+fn put_get_and_commit() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(1), CounterB(2)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let mut locked = backend.get::<&mut CounterA>(entity).unwrap().unwrap();
+    locked.deref().0 += 1;
+    locked.commit().unwrap();
+
+    assert_eq!(
+        backend.peek::<&CounterA>(entity).unwrap(),
+        Some(CounterA(2))
+    );
+    assert!(backend.list_locks(Some(entity)).unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "needs a Redis instance"]
+fn conflicting_locks() {
+    let backend = backend();
+    let entity = Entity::new();
+    backend
+        .put(entity, (CounterA(0), CounterB(0)))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let _read = backend.get::<&CounterA>(entity).unwrap().unwrap();
+    let _also_read = backend.get::<&CounterA>(entity).unwrap().unwrap();
+    let _other = backend.get::<&mut CounterB>(entity).unwrap().unwrap();
+
+    match backend.get::<&mut CounterA>(entity).map(|_| ()) {
+        Err(err) => match err.root() {
+            BackendError::Locking(LockingError::Conflict(_, component, _, Some(holder))) => {
+                assert_eq!(component, "CounterA");
+                assert_eq!(holder.mode, LockingMode::Read);
+                assert_eq!(holder.owner.as_deref(), Some("eci-test"));
+            }
+            other => panic!("expected a conflict, got {other:?}"),
+        },
+        Ok(()) => panic!("expected a conflict"),
+    }
+
+    assert!(backend
+        .lock_entity(entity, Duration::from_secs(60))
+        .is_err());
+    assert_eq!(backend.list_locks(Some(entity)).unwrap().len(), 3);
+}
+
+#[test]
+#[ignore = "needs a Redis instance"]
+fn renew_upgrade_and_release() {
+    let locking = locking();
+    let entity = Entity::new();
+
+    let read = locking
+        .acquire_lock(
+            entity,
+            descriptor("CounterA", LockingMode::Read),
+            Duration::from_millis(200).into(),
+        )
+        .unwrap();
+    locking.renew_lock(&read, Duration::from_secs(60)).unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+
+    locking
+        .upgrade_lock(&read, entity, "CounterA".to_string())
+        .unwrap();
+    let held = locking.list_locks(Some(entity)).unwrap();
+    assert_eq!(held.len(), 1);
+    assert_eq!(held[0].mode, LockingMode::Write);
+
+    assert!(matches!(
+        locking.acquire_lock(
+            entity,
+            descriptor("CounterA", LockingMode::Read),
+            Expiry::Never,
+        ),
+        Err(LockingError::Conflict(..))
+    ));
+
+    locking.release_lock(read).unwrap();
+    let exclusive = locking
+        .acquire_lock(entity, vec![LockDescriptor::entity()], Expiry::Never)
+        .unwrap();
+    assert_eq!(locking.lock_stats().unwrap().active, 1);
+    locking.release_lock(exclusive).unwrap();
+    assert!(locking.list_locks(None).unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "needs a Redis instance"]
+fn locks_expire() {
+    let locking = locking();
+    let entity = Entity::new();
+
+    let held = locking
+        .acquire_lock(
+            entity,
+            descriptor("CounterA", LockingMode::Write),
+            Duration::from_millis(100).into(),
+        )
+        .unwrap();
+
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(locking.list_locks(Some(entity)).unwrap().is_empty());
+    assert!(matches!(
+        locking.renew_lock(&held, Duration::from_secs(1)),
+        Err(LockingError::Expired(_))
+    ));
+
+    locking
+        .acquire_lock(
+            entity,
+            descriptor("CounterA", LockingMode::Write),
+            Expiry::Never,
+        )
+        .unwrap();
+}
@@ -0,0 +1,792 @@
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use eci_core::{
+    backend::{
+        AccessError, AsyncAccessBackend, AsyncBackend, AsyncLockingBackend, BackendError, Format,
+        Lock, LockingError, ResultExt,
+    },
+    Entity,
+};
+use log::*;
+
+use crate::{
+    validate_selection, Extractor, InsertOutcome, InsertReport, Inserter, ReadOnly, RefCast,
+    Remover,
+};
+
+/// The counterpart of [`crate::DropLock`] for async backends. Locks which
+/// are dropped without being released are released by a task spawned onto
+/// the current tokio runtime, so dropping never blocks the runtime.
+pub struct AsyncDropLock {
+    lock: Option<Lock>,
+    backend: Arc<dyn AsyncLockingBackend>,
+    /// Entities covered by the lock, for reporting failed releases.
+    entities: Vec<Entity>,
+}
+
+impl AsyncDropLock {
+    pub fn new<L: AsyncLockingBackend + 'static>(lock: Lock, backend: L) -> Self {
+        AsyncDropLock {
+            lock: Some(lock),
+            backend: Arc::new(backend),
+            entities: Vec::new(),
+        }
+    }
+
+    fn from_backend<F: Format>(lock: Lock, backend: &AsyncBackend<F>, entities: &[Entity]) -> Self {
+        AsyncDropLock {
+            lock: Some(lock),
+            backend: Arc::new(backend.clone()),
+            entities: entities.to_vec(),
+        }
+    }
+
+    pub fn id(&self) -> Option<String> {
+        self.lock.as_ref().map(Lock::id)
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.lock.as_ref().and_then(Lock::expires_at)
+    }
+
+    pub async fn renew(&self, extend_by: Duration) -> Result<(), LockingError> {
+        match &self.lock {
+            Some(lock) => self.backend.renew_lock(lock, extend_by).await,
+            None => Err(LockingError::Released),
+        }
+    }
+
+    pub async fn unlock(mut self) -> Result<(), LockingError> {
+        match self.lock.take() {
+            Some(lock) => self.backend.release_lock(lock).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for AsyncDropLock {
+    fn drop(&mut self) {
+        let Some(lock) = self.lock.take() else {
+            return;
+        };
+
+        let id = lock.id();
+        let entities: Vec<_> = self.entities.iter().map(Entity::to_string).collect();
+        let entities = entities.join(", ");
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            error!("no runtime to release dropped lock {id} on {entities}, leaving it to expire");
+            return;
+        };
+
+        debug!("releasing dropped lock {id}");
+        let backend = self.backend.clone();
+        runtime.spawn(async move {
+            if let Err(err) = backend.release_lock(lock).await {
+                error!("failed to release dropped lock {id} on {entities}: {err}");
+            }
+        });
+    }
+}
+
+impl Debug for AsyncDropLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncDropLock")
+            .field("lock", &self.lock)
+            .finish()
+    }
+}
+
+/// The counterpart of [`crate::Locked`] for async backends.
+pub struct AsyncLocked<T, F>
+where
+    T: Extractor,
+    F: Format,
+{
+    entity: Entity,
+    lock: AsyncDropLock,
+    backend: AsyncBackend<F>,
+    expires: SystemTime,
+    inner: <T as Extractor>::Owned,
+}
+
+impl<T, F> AsyncLocked<T, F>
+where
+    T: Extractor,
+    T: RefCast<Owned = <T as Extractor>::Owned>,
+    F: Format,
+{
+    pub async fn unlock(self) -> Result<(), LockingError> {
+        self.lock.unlock().await
+    }
+
+    /// The entity whose components are locked.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Identifies the lock, for instance in logs.
+    pub fn lock_id(&self) -> String {
+        self.lock.id().unwrap_or_default()
+    }
+
+    /// Point in time at which the lock expires, at the earliest.
+    pub fn expires_at(&self) -> SystemTime {
+        self.expires
+    }
+
+    /// Writes the mutably locked components back to the backend and
+    /// releases the lock. Fails without writing if the lock has expired.
+    pub async fn commit(self) -> Result<(), BackendError> {
+        let entity = self.entity;
+        if self.backend.now() >= self.expires {
+            return Err(LockingError::Expired(self.lock.id().unwrap_or_default()))
+                .ctx(format!("committing components of {entity}"));
+        }
+
+        let components =
+            T::serialize::<F>(&self.inner).ctx(format!("serializing components of {entity}"))?;
+        if !components.is_empty() {
+            self.backend
+                .update_components(entity, components)
+                .await
+                .ctx(format!("committing components of {entity}"))?;
+        }
+
+        self.lock
+            .unlock()
+            .await
+            .ctx(format!("releasing components of {entity} after commit"))
+    }
+
+    pub fn deref(&mut self) -> T::Ref<'_> {
+        <T as RefCast>::refcast(&mut self.inner)
+    }
+}
+
+impl<T, F> Debug for AsyncLocked<T, F>
+where
+    T: Extractor,
+    <T as Extractor>::Owned: Debug,
+    F: Format,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncLocked")
+            .field("entity", &self.entity)
+            .field("lock", &self.lock)
+            .field("expires", &self.expires)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// The counterpart of [`crate::TypedBackend`] for async backends. Unlike
+/// [`eci_core::backend::Backend`], [`AsyncBackend`] does not migrate,
+/// unseal or limit the components it reads.
+#[async_trait]
+pub trait TypedBackendAsync<F: Format> {
+    /// See [`crate::TypedBackend::get`].
+    async fn get<Select>(
+        &self,
+        entity: Entity,
+    ) -> Result<Option<AsyncLocked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>;
+
+    /// See [`crate::TypedBackend::peek`].
+    async fn peek<Select>(&self, entity: Entity) -> Result<Option<Select::Owned>, BackendError>
+    where
+        Select: Extractor + ReadOnly;
+
+    /// See [`crate::TypedBackend::put`].
+    async fn put<T>(&self, entity: Entity, components: T) -> Result<InsertReport, AccessError>
+    where
+        T: Inserter + Send;
+
+    /// See [`crate::TypedBackend::remove`].
+    async fn remove<T>(&self, entity: Entity) -> Result<T::Removed, BackendError>
+    where
+        T: Remover,
+        T::Removed: Send;
+}
+
+#[async_trait]
+impl<F: Format> TypedBackendAsync<F> for AsyncBackend<F> {
+    async fn get<Select>(
+        &self,
+        entity: Entity,
+    ) -> Result<Option<AsyncLocked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+    {
+        let descriptors = Select::describe();
+        validate_selection(&descriptors)?;
+
+        // Locking before reading, rather than after finding the components,
+        // makes sure nobody writes them in between.
+        let expires = crate::lock::expiry(self.now(), self.lock_ttl())
+            .ctx(format!("locking components of {entity}"))?;
+        let lock = self
+            .acquire_lock(entity, descriptors, self.lock_ttl().into())
+            .await
+            .ctx(format!("locking components of {entity}"))?;
+        let lock = AsyncDropLock::from_backend(lock, self, &[entity]);
+
+        let components = self
+            .read_components(entity, Select::extract())
+            .await
+            .and_then(|components| Select::from(entity, components, None))
+            .ctx(format!("reading components of {entity}"))?;
+
+        // Dropping the lock releases it again if the components are absent.
+        Ok(components.map(|components| AsyncLocked {
+            entity,
+            expires: lock.expires_at().unwrap_or(expires),
+            lock,
+            backend: self.clone(),
+            inner: components,
+        }))
+    }
+
+    async fn peek<Select>(&self, entity: Entity) -> Result<Option<Select::Owned>, BackendError>
+    where
+        Select: Extractor + ReadOnly,
+    {
+        if Select::extract().is_empty() {
+            return Err(AccessError::EmptyRequest).ctx(format!("reading components of {entity}"));
+        }
+
+        self.read_components(entity, Select::extract())
+            .await
+            .and_then(|components| Select::from(entity, components, None))
+            .ctx(format!("reading components of {entity}"))
+    }
+
+    async fn put<T>(&self, entity: Entity, components: T) -> Result<InsertReport, AccessError>
+    where
+        T: Inserter + Send,
+    {
+        let serialized = components.insert::<F>();
+        let mut report = InsertReport {
+            entity,
+            components: Vec::with_capacity(serialized.len()),
+        };
+
+        if self.supports_atomic_writes() {
+            let names: Vec<_> = serialized.iter().map(|c| c.name.clone()).collect();
+
+            let conflict = match self.write_components(entity, serialized).await {
+                Ok(()) => None,
+                Err(AccessError::Conflict(_, name)) => Some(name),
+                Err(err) => return Err(err),
+            };
+
+            for name in names {
+                let outcome = match &conflict {
+                    None => InsertOutcome::Inserted,
+                    Some(conflict) if *conflict == name => InsertOutcome::Conflict,
+                    Some(_) => InsertOutcome::RolledBack,
+                };
+                report.components.push((name, outcome));
+            }
+        } else {
+            for component in serialized {
+                let name = component.name.clone();
+                let outcome = match self.write_components(entity, vec![component]).await {
+                    Ok(()) => InsertOutcome::Inserted,
+                    Err(AccessError::Conflict(..)) => InsertOutcome::Conflict,
+                    Err(err) => return Err(err),
+                };
+                report.components.push((name, outcome));
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn remove<T>(&self, entity: Entity) -> Result<T::Removed, BackendError>
+    where
+        T: Remover,
+        T::Removed: Send,
+    {
+        let lock = AsyncDropLock::from_backend(
+            self.acquire_lock(entity, T::describe(), self.lock_ttl().into())
+                .await
+                .ctx(format!("locking components of {entity} for removal"))?,
+            self,
+            &[entity],
+        );
+
+        let removed = self
+            .remove_components(entity, T::extract())
+            .await
+            .and_then(|removed| T::from(removed, None))
+            .ctx(format!("removing components of {entity}"))?;
+
+        lock.unlock()
+            .await
+            .ctx(format!("releasing components of {entity} after removal"))?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use eci_backend_memory::MemoryBackend;
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{
+            AccessError, AsyncAccessBackend, AsyncBackend, AsyncLockingBackend, BackendError,
+            Expiry, LockDescriptor, LockingBackend, LockingError, ManualClock, SyncAsAsync,
+        },
+        Component, Entity,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{tests::NonAtomic, InsertOutcome, InsertReport};
+
+    use super::TypedBackendAsync;
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+    struct CounterA(pub usize);
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+    struct CounterB(pub usize);
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq)]
+    struct CounterC(pub usize);
+
+    fn backend() -> AsyncBackend<Json> {
+        AsyncBackend::from_sync(SqliteBackend::memory().unwrap())
+    }
+
+    #[tokio::test]
+    async fn get_components() {
+        let backend = backend();
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(10),))
+            .await
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(a, (CounterB(20),))
+            .await
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend
+            .get::<(&CounterB, &CounterA)>(a)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lock.deref(), (&CounterB(20), &CounterA(10)));
+        lock.unlock().await.unwrap();
+
+        assert!(backend
+            .get::<(&CounterA, &CounterC)>(a)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            backend.peek::<Option<&CounterC>>(a).await.unwrap(),
+            Some(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn expiry_follows_backend_clock() {
+        let clock = ManualClock::default();
+        let backend = backend().with_clock(clock.clone());
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .await
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert!(matches!(
+            backend
+                .clone()
+                .with_lock_ttl(Duration::MAX)
+                .get::<&mut CounterA>(a)
+                .await
+                .as_ref()
+                .map_err(BackendError::root),
+            Err(BackendError::Locking(LockingError::ExpiryOutOfRange(_)))
+        ));
+
+        let mut lock = backend.get::<&mut CounterA>(a).await.unwrap().unwrap();
+        lock.deref().0 = 2;
+        clock.advance(backend.lock_ttl() + Duration::from_secs(1));
+        assert!(matches!(
+            lock.commit().await.as_ref().map_err(BackendError::root),
+            Err(BackendError::Locking(LockingError::Expired(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn commit_mutable_components() {
+        let backend = backend();
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(2)))
+            .await
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend
+            .get::<(&mut CounterA, &CounterB)>(a)
+            .await
+            .unwrap()
+            .unwrap();
+        {
+            let (counter_a, counter_b) = lock.deref();
+            counter_a.0 += counter_b.0;
+        }
+        lock.commit().await.unwrap();
+
+        assert_eq!(
+            backend.peek::<(&CounterA, &CounterB)>(a).await.unwrap(),
+            Some((CounterA(3), CounterB(2)))
+        );
+    }
+
+    #[tokio::test]
+    async fn reject_empty_selection() {
+        let backend = backend();
+        let entity = Entity::new();
+
+        assert!(matches!(
+            backend.get::<Entity>(entity).await,
+            Err(BackendError::Locking(LockingError::EmptyRequest))
+        ));
+        assert!(matches!(
+            backend
+                .peek::<Entity>(entity)
+                .await
+                .as_ref()
+                .map_err(BackendError::root),
+            Err(BackendError::Access(AccessError::EmptyRequest))
+        ));
+    }
+
+    #[tokio::test]
+    async fn insert_report() {
+        let backend = backend();
+        assert!(backend.supports_atomic_writes());
+
+        let a = Entity::new();
+        assert!(backend.put(a, (CounterB(1),)).await.unwrap().is_complete());
+
+        let report = backend
+            .put(a, (CounterA(1), CounterB(2), CounterC(3)))
+            .await
+            .unwrap();
+        assert_eq!(report.conflicts().collect::<Vec<_>>(), vec!["CounterB"]);
+        assert_eq!(
+            report.rolled_back().collect::<Vec<_>>(),
+            vec!["CounterA", "CounterC"]
+        );
+        assert_eq!(backend.peek::<&CounterA>(a).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn insert_report_non_atomic() {
+        let backend = AsyncBackend::<Json>::from_disjoint(
+            SyncAsAsync::new(NonAtomic(SqliteBackend::memory().unwrap())),
+            SyncAsAsync::new(SqliteBackend::memory().unwrap()),
+        );
+        assert!(!backend.supports_atomic_writes());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterB(1),))
+            .await
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let report = backend
+            .put(a, (CounterA(1), CounterB(2), CounterC(3)))
+            .await
+            .unwrap();
+        assert_eq!(
+            report.components,
+            vec![
+                ("CounterA".to_string(), InsertOutcome::Inserted),
+                ("CounterB".to_string(), InsertOutcome::Conflict),
+                ("CounterC".to_string(), InsertOutcome::Inserted),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn spawned_tasks() {
+        let backend = backend();
+        let tasks: Vec<_> = (0..10)
+            .map(|count| {
+                let backend = backend.clone();
+                tokio::spawn(async move {
+                    let entity = Entity::new();
+                    backend
+                        .put(entity, (CounterA(count),))
+                        .await
+                        .and_then(InsertReport::into_result)?;
+                    Ok::<_, AccessError>(entity)
+                })
+            })
+            .collect();
+
+        for (count, task) in tasks.into_iter().enumerate() {
+            let entity = task.await.unwrap().unwrap();
+            assert_eq!(
+                backend.peek::<&CounterA>(entity).await.unwrap(),
+                Some(CounterA(count))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn remove_components() {
+        let backend = backend();
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(2)))
+            .await
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend.remove::<(CounterA, CounterC)>(a).await.unwrap(),
+            (Some(CounterA(1)), None)
+        );
+        assert_eq!(backend.list_components(a).await.unwrap(), vec!["CounterB"]);
+    }
+
+    #[tokio::test]
+    async fn lock_contention() {
+        let sqlite = SyncAsAsync::new(SqliteBackend::memory().unwrap());
+        let backend = AsyncBackend::<Json>::from_joint(sqlite.clone());
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(0),))
+            .await
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let held = backend.get::<&mut CounterA>(a).await.unwrap().unwrap();
+        let err = backend.get::<&CounterA>(a).await.unwrap_err();
+        assert!(matches!(
+            err.root(),
+            BackendError::Locking(LockingError::Conflict(..))
+        ));
+        assert!(err.is_conflict());
+
+        // Dropping the lock releases it from a spawned task.
+        drop(held);
+        for _ in 0..100 {
+            if sqlite.inner().list_locks(Some(a)).unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert!(sqlite.inner().list_locks(Some(a)).unwrap().is_empty());
+        backend.get::<&CounterA>(a).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn disjoint_backends() {
+        let backend = AsyncBackend::<Json>::from_disjoint(
+            SyncAsAsync::new(SqliteBackend::memory().unwrap()),
+            SyncAsAsync::new(MemoryBackend::default()),
+        );
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(5),))
+            .await
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend.get::<&mut CounterA>(a).await.unwrap().unwrap();
+        lock.deref().0 += 1;
+        assert!(backend.get::<&CounterA>(a).await.unwrap_err().is_conflict());
+        lock.commit().await.unwrap();
+
+        assert_eq!(
+            backend.peek::<&CounterA>(a).await.unwrap(),
+            Some(CounterA(6))
+        );
+    }
+
+    #[test]
+    fn drop_outside_runtime() {
+        let sqlite = SyncAsAsync::new(SqliteBackend::memory().unwrap());
+        let backend = AsyncBackend::<Json>::from_joint(sqlite.clone());
+        let a = Entity::new();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let held = runtime.block_on(async {
+            backend
+                .put(a, (CounterA(0),))
+                .await
+                .and_then(InsertReport::into_result)
+                .unwrap();
+            backend.get::<&CounterA>(a).await.unwrap().unwrap()
+        });
+
+        // With no runtime to release it on, the lock is left to expire.
+        drop(held);
+        assert_eq!(sqlite.inner().list_locks(Some(a)).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn wake_waiters_on_release() {
+        let memory = Arc::new(MemoryBackend::default());
+        let a = Entity::new();
+        let held = LockingBackend::acquire_lock(
+            memory.as_ref(),
+            a,
+            vec![LockDescriptor::entity()],
+            Expiry::Never,
+        )
+        .unwrap();
+
+        let waiter = tokio::spawn({
+            let memory = memory.clone();
+            async move {
+                let deadline = Instant::now() + Duration::from_secs(10);
+                AsyncLockingBackend::acquire_lock_waiting(
+                    memory.as_ref(),
+                    a,
+                    vec![LockDescriptor::entity()],
+                    Expiry::Never,
+                    deadline,
+                )
+                .await
+            }
+        });
+
+        // Long enough for polling to have backed off all the way.
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!waiter.is_finished());
+
+        let released = Instant::now();
+        LockingBackend::release_lock(memory.as_ref(), held).unwrap();
+        let lock = waiter.await.unwrap().unwrap();
+        assert!(released.elapsed() < Duration::from_millis(50));
+        assert_eq!(memory.list_locks(Some(a)).unwrap()[0].id, lock.id());
+    }
+
+    #[tokio::test]
+    async fn poll_until_deadline() {
+        let sqlite = SyncAsAsync::new(SqliteBackend::memory().unwrap());
+        let a = Entity::new();
+        let held = LockingBackend::acquire_lock(
+            sqlite.inner(),
+            a,
+            vec![LockDescriptor::entity()],
+            Expiry::Never,
+        )
+        .unwrap();
+
+        let deadline = Instant::now() + Duration::from_millis(100);
+        let err = sqlite
+            .acquire_lock_waiting(a, vec![LockDescriptor::entity()], Expiry::Never, deadline)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LockingError::TimedOut { entity, .. } if entity == a));
+        assert!(Instant::now() >= deadline);
+
+        let waiter = tokio::spawn({
+            let sqlite = sqlite.clone();
+            async move {
+                let deadline = Instant::now() + Duration::from_secs(10);
+                sqlite
+                    .acquire_lock_waiting(
+                        a,
+                        vec![LockDescriptor::entity()],
+                        Expiry::Never,
+                        deadline,
+                    )
+                    .await
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        LockingBackend::release_lock(sqlite.inner(), held).unwrap();
+        waiter.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancel_acquisition() {
+        let sqlite = SyncAsAsync::new(SqliteBackend::memory().unwrap());
+        let a = Entity::new();
+
+        // Cancelling at different points either abandons the acquisition
+        // before it completes, or drops the lock it acquired. An abandoned
+        // acquisition may still take the lock after being cancelled, before
+        // releasing it again, so each attempt waits for that to happen.
+        for micros in 0..20 {
+            let acquire = sqlite.acquire_lock_waiting(
+                a,
+                vec![LockDescriptor::entity()],
+                Expiry::Never,
+                Instant::now() + Duration::from_secs(5),
+            );
+            if let Ok(lock) =
+                tokio::time::timeout(Duration::from_micros(micros * 50), acquire).await
+            {
+                AsyncLockingBackend::release_lock(&sqlite, lock.unwrap())
+                    .await
+                    .unwrap();
+            }
+
+            // Abandoned locks are released in the background.
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !sqlite.inner().list_locks(Some(a)).unwrap().is_empty() {
+                assert!(
+                    Instant::now() < deadline,
+                    "abandoned lock was never released"
+                );
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel_waiting() {
+        let memory = MemoryBackend::default();
+        let a = Entity::new();
+        let held =
+            LockingBackend::acquire_lock(&memory, a, vec![LockDescriptor::entity()], Expiry::Never)
+                .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let waiting = AsyncLockingBackend::acquire_lock_waiting(
+            &memory,
+            a,
+            vec![LockDescriptor::entity()],
+            Expiry::Never,
+            deadline,
+        );
+        assert!(tokio::time::timeout(Duration::from_millis(20), waiting)
+            .await
+            .is_err());
+
+        LockingBackend::release_lock(&memory, held).unwrap();
+        assert!(memory.list_locks(Some(a)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+use eci_core::{
+    backend::{AccessBackend, Backend, BackendError, Format, LockingBackend, ResultExt},
+    Entity,
+};
+
+use crate::{lock::DropLock, refcast::RefCast, Extractor};
+
+/// Number of entities locked together by [`crate::TypedBackend::for_each`].
+pub const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// Outcome of visiting a set of entities in batches.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    /// Entities which had all the requested components and were visited.
+    pub visited: Vec<Entity>,
+    /// Entities which remained locked by someone else, even after retrying.
+    pub skipped: Vec<Entity>,
+}
+
+/// Locks the given entities under a single lock, visits each of them which
+/// have the selected components, writes back mutable components and releases
+/// the lock. Returns the entities which could not be locked.
+pub(crate) fn visit_batch<F, Select, Func>(
+    backend: &Backend<F>,
+    entities: &[Entity],
+    visited: &mut Vec<Entity>,
+    func: &mut Func,
+) -> Result<Vec<Entity>, BackendError>
+where
+    F: Format,
+    Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+    Func: FnMut(Entity, Select::Ref<'_>),
+{
+    crate::validate_selection(&Select::describe())?;
+
+    let bulk = backend
+        .acquire_locks_bulk(
+            entities
+                .iter()
+                .map(|entity| (*entity, Select::describe()))
+                .collect(),
+            backend.lock_ttl_for(&Select::describe()),
+        )
+        .ctx(format!("locking a batch of {} entities", entities.len()))?;
+
+    let lock = DropLock::from_backend(bulk.lock, backend, &bulk.granted);
+    let held = lock.held().ctx("locking a batch of entities")?;
+
+    for entity in bulk.granted {
+        let components = crate::migrate::read_components(backend, entity, Select::extract())
+            .and_then(|components| Select::from(entity, components, backend.limits()))
+            .ctx(format!("reading components of {entity}"))?;
+
+        if let Some(mut components) = components {
+            func(entity, Select::refcast(&mut components));
+
+            Select::serialize::<F>(&components)
+                .and_then(|changed| {
+                    if changed.is_empty() {
+                        Ok(())
+                    } else {
+                        backend.update_components_locked(entity, changed, held)
+                    }
+                })
+                .ctx(format!("committing components of {entity}"))?;
+
+            visited.push(entity);
+        }
+    }
+
+    lock.unlock().ctx("releasing batch lock")?;
+    Ok(bulk.skipped)
+}
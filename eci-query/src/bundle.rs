@@ -0,0 +1,143 @@
+use eci_core::{
+    backend::{Format, SerializedComponent},
+    Component,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{sealed, Inserter};
+
+/// A struct of components which are inserted and read together, such as
+/// everything a player is spawned with. Implemented with
+/// `#[derive(Bundle)]`, which also makes `&Bundle` an
+/// [`crate::Extractor`] reading every component of the bundle:
+///
+/// ```ignore
+/// #[derive(Bundle)]
+/// struct PlayerBundle {
+///     position: Position,
+///     health: Health,
+///     name: Name,
+/// }
+///
+/// backend.put(entity, player)?;
+/// let player = backend.peek::<&PlayerBundle>(entity)?;
+/// ```
+///
+/// Bundles hold up to 16 components, and cannot contain other bundles.
+pub trait Bundle: Sized {
+    /// The components of the bundle as a tuple, in the order of its fields.
+    type Components;
+
+    /// Reads every component of the bundle, as references to the
+    /// components.
+    type Selection;
+
+    fn into_components(self) -> Self::Components;
+
+    fn from_components(components: Self::Components) -> Self;
+}
+
+/// Implemented by everything which can be a field of a [`Bundle`].
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a component, so it cannot be part of a bundle",
+    note = "bundles cannot contain other bundles, list the components of `{Self}` instead"
+)]
+pub trait BundleMember: Component + Serialize + DeserializeOwned + 'static {}
+
+impl<T: Component + Serialize + DeserializeOwned + 'static> BundleMember for T {}
+
+impl<B: Bundle> sealed::Insertion for B {}
+
+impl<B: Bundle> Inserter for B
+where
+    B::Components: Inserter,
+{
+    fn insert<F: Format>(self) -> Vec<SerializedComponent<F>> {
+        self.into_components().insert()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{backend::Backend, Component, Entity};
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Bundle, InsertReport, TypedBackend};
+
+    #[derive(Debug, Clone, Component, Serialize, Deserialize, PartialEq)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Debug, Clone, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Health(pub u32);
+
+    #[derive(Debug, Clone, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Name(pub String);
+
+    #[derive(Debug, Clone, PartialEq, Bundle)]
+    struct PlayerBundle {
+        position: Position,
+        health: Health,
+        name: Name,
+    }
+
+    #[derive(Debug, PartialEq, Bundle)]
+    struct Marker(Health);
+
+    fn player() -> PlayerBundle {
+        PlayerBundle {
+            position: Position { x: 1.0, y: 2.0 },
+            health: Health(100),
+            name: Name("Probe".to_string()),
+        }
+    }
+
+    #[test]
+    fn insert_and_read_bundle() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+
+        let report = backend.put(entity, player()).unwrap();
+        assert!(report.is_complete());
+        assert_eq!(report.components.len(), 3);
+
+        assert_eq!(
+            backend.peek::<(&Position, &Health, &Name)>(entity).unwrap(),
+            Some((
+                Position { x: 1.0, y: 2.0 },
+                Health(100),
+                Name("Probe".to_string())
+            ))
+        );
+
+        assert_eq!(
+            backend.peek::<&PlayerBundle>(entity).unwrap(),
+            Some(player())
+        );
+        let mut locked = backend.get::<&PlayerBundle>(entity).unwrap().unwrap();
+        assert_eq!(locked.deref(), &player());
+        drop(locked);
+
+        assert_eq!(
+            backend.peek::<&Marker>(entity).unwrap(),
+            Some(Marker(Health(100)))
+        );
+    }
+
+    #[test]
+    fn incomplete_bundle_is_absent() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+        backend
+            .put(entity, Marker(Health(5)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(backend.peek::<&PlayerBundle>(entity).unwrap(), None);
+        assert_eq!(backend.peek::<&Health>(entity).unwrap(), Some(Health(5)));
+    }
+}
@@ -0,0 +1,202 @@
+use std::marker::PhantomData;
+
+use eci_core::{
+    backend::{
+        AccessError, DeserializationLimits, ExtractionDescriptor, Format, LockDescriptor,
+        SerializedComponent,
+    },
+    DefaultComponent, Entity,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{refcast::RefCast, sealed, LockableComponent, ReadOnly};
+
+/// Extracts the component, or its [`Default`] if the entity does not have
+/// it, for components marked with `#[component(default)]`. Unlike
+/// `Option<&T>`, extractions with defaulted components are never `None`
+/// because of them.
+///
+/// `OrDefault<&mut T>` locks the component even if the entity does not have
+/// it yet, so committing inserts the default, or whatever it was changed
+/// to, without anyone else inserting it in the meantime.
+pub struct OrDefault<T>(PhantomData<T>);
+
+impl<T> sealed::Selection for OrDefault<&T> {}
+
+impl<T> LockableComponent for OrDefault<&T>
+where
+    T: DefaultComponent + DeserializeOwned,
+{
+    type Inner = T;
+    fn as_lock() -> Option<LockDescriptor> {
+        <&T as LockableComponent>::as_lock()
+    }
+
+    fn as_extraction() -> Option<ExtractionDescriptor> {
+        <&T as LockableComponent>::as_extraction()
+    }
+
+    fn is_required() -> bool {
+        false
+    }
+
+    fn deserialize<F: Format>(
+        entity: Entity,
+        serialized: Option<SerializedComponent<F>>,
+        limits: Option<&DeserializationLimits>,
+    ) -> Result<Option<Self::Inner>, AccessError> {
+        Ok(Some(
+            <&T as LockableComponent>::deserialize(entity, serialized, limits)?.unwrap_or_default(),
+        ))
+    }
+
+    fn serialize<F: Format>(
+        _inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(None)
+    }
+}
+
+impl<T> sealed::Selection for OrDefault<&mut T> {}
+
+impl<T> LockableComponent for OrDefault<&mut T>
+where
+    T: DefaultComponent + DeserializeOwned + Serialize,
+{
+    type Inner = T;
+    fn as_lock() -> Option<LockDescriptor> {
+        <&mut T as LockableComponent>::as_lock()
+    }
+
+    fn as_extraction() -> Option<ExtractionDescriptor> {
+        <&mut T as LockableComponent>::as_extraction()
+    }
+
+    fn is_required() -> bool {
+        false
+    }
+
+    fn deserialize<F: Format>(
+        entity: Entity,
+        serialized: Option<SerializedComponent<F>>,
+        limits: Option<&DeserializationLimits>,
+    ) -> Result<Option<Self::Inner>, AccessError> {
+        Ok(Some(
+            <&mut T as LockableComponent>::deserialize(entity, serialized, limits)?
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn serialize<F: Format>(
+        inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        <&mut T as LockableComponent>::serialize(inner)
+    }
+}
+
+impl<T> ReadOnly for OrDefault<&T> {}
+
+impl<A> RefCast for OrDefault<&A> {
+    type Owned = A;
+    type Ref<'a>
+        = &'a A
+    where
+        Self: 'a;
+
+    fn refcast<'a>(a: &'a mut Self::Owned) -> Self::Ref<'a>
+    where
+        Self: 'a,
+    {
+        &*a
+    }
+}
+
+impl<A> RefCast for OrDefault<&mut A> {
+    type Owned = A;
+    type Ref<'a>
+        = &'a mut A
+    where
+        Self: 'a;
+
+    fn refcast<'a>(a: &'a mut Self::Owned) -> Self::Ref<'a>
+    where
+        Self: 'a,
+    {
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{backend::Backend, Component, Entity};
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{InsertReport, OrDefault, TypedBackend};
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Name(pub String);
+
+    #[derive(Debug, Default, Component, Serialize, Deserialize, PartialEq, Eq)]
+    #[component(default)]
+    struct Score(pub u32);
+
+    #[test]
+    fn read_missing_default() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+        backend
+            .put(entity, (Name("Probe".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend.peek::<(&Name, OrDefault<&Score>)>(entity).unwrap(),
+            Some((Name("Probe".to_string()), Score(0)))
+        );
+        assert_eq!(backend.peek::<(&Name, &Score)>(entity).unwrap(), None);
+
+        // Reading the default does not store it.
+        let mut locked = backend
+            .get::<(&Name, OrDefault<&Score>)>(entity)
+            .unwrap()
+            .unwrap();
+        assert_eq!(locked.deref().1, &Score(0));
+        locked.commit().unwrap();
+        assert_eq!(backend.peek::<&Score>(entity).unwrap(), None);
+
+        backend
+            .put(entity, (Score(7),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        assert_eq!(
+            backend.peek::<OrDefault<&Score>>(entity).unwrap(),
+            Some(Score(7))
+        );
+    }
+
+    #[test]
+    fn commit_missing_default() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+        backend
+            .put(entity, (Name("Probe".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut locked = backend
+            .get::<(&Name, OrDefault<&mut Score>)>(entity)
+            .unwrap()
+            .unwrap();
+
+        // The missing component is locked all the same, so nobody else gets
+        // to insert it before the commit.
+        assert!(backend.get::<&mut Score>(entity).is_err());
+
+        locked.deref().1 .0 += 5;
+        locked.commit().unwrap();
+
+        assert_eq!(backend.peek::<&Score>(entity).unwrap(), Some(Score(5)));
+    }
+}
@@ -1,47 +1,120 @@
-use crate::LockableComponent;
-use eci_core::backend::{
-    AccessError, ExtractionDescriptor, Format, LockDescriptor, SerializedComponent,
+use crate::{sealed, LockableComponent, ReadOnly};
+use eci_core::{
+    backend::{
+        AccessError, DeserializationLimits, ExtractionDescriptor, Format, LockDescriptor,
+        SerializedComponent,
+    },
+    Entity,
 };
-use eci_core::Component;
 
 /// Implements mapping from tuples of immutable/mutable references to an
 /// extraction descriptor which can be passed to a backend to retrieve
 /// and lock components.
-pub trait Extractor {
+///
+/// The trait is sealed: it is implemented for every [`LockableComponent`]
+/// and tuples of up to 16 of them, and can be named in bounds, but not
+/// implemented outside of this crate. Only the trait itself and `Owned` are
+/// covered by semver, the methods may change between minor versions.
+pub trait Extractor: sealed::Selection {
     type Owned;
     fn describe() -> Vec<LockDescriptor>;
     fn extract() -> Vec<ExtractionDescriptor>;
 
+    /// Names of the components the entity must have for the extraction to
+    /// succeed.
+    fn required() -> Vec<String>;
+
     fn from<F: Format>(
+        entity: Entity,
         serialized: Vec<Option<SerializedComponent<F>>>,
+        limits: Option<&DeserializationLimits>,
     ) -> Result<Option<Self::Owned>, AccessError>;
+
+    /// Serializes the mutably requested members of the extraction, so they
+    /// can be written back to the backend.
+    fn serialize<F: Format>(
+        owned: &Self::Owned,
+    ) -> Result<Vec<SerializedComponent<F>>, AccessError>;
+}
+
+/// Takes the serialized component belonging to `T` from the backend's
+/// response, if `T` was read from the backend at all.
+fn next<T: LockableComponent, F: Format>(
+    iter: &mut impl Iterator<Item = Option<SerializedComponent<F>>>,
+) -> Option<SerializedComponent<F>> {
+    T::as_extraction().and_then(|_| iter.next().unwrap())
 }
 
 macro_rules! impl_extractor {
-    ($head:ident) => {
+    ($vh:ident: $head:ident) => {
         impl<$head> Extractor for $head where
             $head: LockableComponent,
         {
             type Owned = $head::Inner;
 
             fn describe() -> Vec<LockDescriptor> {
-                vec![
-                    $head::as_lock(),
-                ]
+                $head::as_lock().into_iter().collect()
+            }
+
+            fn extract() -> Vec<ExtractionDescriptor> {
+                $head::as_extraction().into_iter().collect()
+            }
+
+            fn required() -> Vec<String> {
+                $head::as_extraction()
+                    .filter(|_| $head::is_required())
+                    .map(|descriptor| descriptor.name)
+                    .into_iter()
+                    .collect()
+            }
+
+            fn from<F: Format>(entity: Entity, serialized: Vec<Option<SerializedComponent<F>>>, limits: Option<&DeserializationLimits>) -> Result<Option<Self::Owned>, AccessError> {
+                let mut iter = serialized.into_iter();
+                <$head as LockableComponent>::deserialize(entity, next::<$head, F>(&mut iter), limits)
+            }
+
+            fn serialize<F: Format>(owned: &Self::Owned) -> Result<Vec<SerializedComponent<F>>, AccessError> {
+                Ok(<$head as LockableComponent>::serialize(owned)?.into_iter().collect())
+            }
+        }
+
+        impl<$head> sealed::Selection for ($head,) where $head: LockableComponent {}
+
+        impl<$head> Extractor for ($head,) where
+            $head: LockableComponent,
+        {
+            type Owned = ($head::Inner,);
+
+            fn describe() -> Vec<LockDescriptor> {
+                <$head as Extractor>::describe()
             }
 
             fn extract() -> Vec<ExtractionDescriptor> {
-                vec![
-                    ExtractionDescriptor { name: $head::Inner::COMPONENT_TYPE.to_string() },
-                ]
+                <$head as Extractor>::extract()
+            }
+
+            fn required() -> Vec<String> {
+                <$head as Extractor>::required()
+            }
+
+            fn from<F: Format>(entity: Entity, serialized: Vec<Option<SerializedComponent<F>>>, limits: Option<&DeserializationLimits>) -> Result<Option<Self::Owned>, AccessError> {
+                Ok(<$head as Extractor>::from(entity, serialized, limits)?.map(|inner| (inner,)))
             }
 
-            fn from<F: Format>(serialized: Vec<Option<SerializedComponent<F>>>) -> Result<Option<Self::Owned>, AccessError> {
-                Ok(<$head as LockableComponent>::deserialize(serialized.into_iter().next().unwrap())?)
+            fn serialize<F: Format>(($vh,): &Self::Owned) -> Result<Vec<SerializedComponent<F>>, AccessError> {
+                <$head as Extractor>::serialize($vh)
             }
         }
+
+        impl<$head> ReadOnly for ($head,) where $head: ReadOnly {}
     };
-    ($head:ident, $($rest:ident),* ) => {
+    ($vh:ident: $head:ident, $($v:ident: $rest:ident),* ) => {
+        impl<$head, $( $rest ),*> sealed::Selection for ($head, $( $rest ),*) where
+            $head: LockableComponent,
+            $( $rest: LockableComponent),*
+        {
+        }
+
         impl<$head, $( $rest ),*> Extractor for ($head, $( $rest ),*) where
             $head: LockableComponent,
             $( $rest: LockableComponent),*
@@ -49,39 +122,110 @@ macro_rules! impl_extractor {
             type Owned = ($head::Inner, $( $rest::Inner ),*);
 
             fn describe() -> Vec<LockDescriptor> {
-                vec![
+                [
                     $head::as_lock(),
                     $( $rest::as_lock() ),*
-                ]
+                ].into_iter().flatten().collect()
             }
 
             fn extract() -> Vec<ExtractionDescriptor> {
-                vec![
-                    ExtractionDescriptor { name: $head::Inner::COMPONENT_TYPE.to_string() },
-                    $( ExtractionDescriptor { name: $rest::Inner::COMPONENT_TYPE.to_string() } ),*
-                ]
+                [
+                    $head::as_extraction(),
+                    $( $rest::as_extraction() ),*
+                ].into_iter().flatten().collect()
             }
 
-            fn from<F: Format>(serialized: Vec<Option<SerializedComponent<F>>>) -> Result<Option<Self::Owned>, AccessError> {
+            fn required() -> Vec<String> {
+                [
+                    <$head as Extractor>::required(),
+                    $( <$rest as Extractor>::required() ),*
+                ].into_iter().flatten().collect()
+            }
+
+            fn from<F: Format>(entity: Entity, serialized: Vec<Option<SerializedComponent<F>>>, limits: Option<&DeserializationLimits>) -> Result<Option<Self::Owned>, AccessError> {
                 let mut iter = serialized.into_iter();
                 Ok(Some((
-                    if let Some(inner) = <$head as LockableComponent>::deserialize(iter.next().unwrap())? {
+                    if let Some(inner) = <$head as LockableComponent>::deserialize(entity, next::<$head, F>(&mut iter), limits)? {
                         inner
                     } else {
                         return Ok(None)
                     },
                     $(
-                    if let Some(inner) = <$rest as LockableComponent>::deserialize(iter.next().unwrap())? {
+                    if let Some(inner) = <$rest as LockableComponent>::deserialize(entity, next::<$rest, F>(&mut iter), limits)? {
                         inner
                     } else {
                         return Ok(None)
                     } ),*
                 )))
             }
+
+            fn serialize<F: Format>(($vh, $( $v ),*): &Self::Owned) -> Result<Vec<SerializedComponent<F>>, AccessError> {
+                Ok([
+                    <$head as LockableComponent>::serialize($vh)?,
+                    $( <$rest as LockableComponent>::serialize($v)? ),*
+                ].into_iter().flatten().collect())
+            }
+        }
+
+        impl<$head, $( $rest ),*> ReadOnly for ($head, $( $rest ),*) where
+            $head: ReadOnly,
+            $( $rest: ReadOnly),*
+        {
         }
 
-        impl_extractor!( $( $rest ),* );
+        impl_extractor!( $( $v: $rest ),* );
     };
 }
 
-impl_extractor!(T1, T2, T3, T4, T5, T6, T8, T9, T10, T11, T12, T13, T14, T15, T16);
+impl_extractor!(
+    t1: T1,
+    t2: T2,
+    t3: T3,
+    t4: T4,
+    t5: T5,
+    t6: T6,
+    t7: T7,
+    t8: T8,
+    t9: T9,
+    t10: T10,
+    t11: T11,
+    t12: T12,
+    t13: T13,
+    t14: T14,
+    t15: T15,
+    t16: T16
+);
+
+#[cfg(test)]
+mod tests {
+    use eci_core::Component;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Extractor, Inserter, RefCast};
+
+    #[derive(Component, Serialize, Deserialize)]
+    struct A;
+
+    type R = &'static A;
+    type W = &'static mut A;
+
+    fn extractor<T: Extractor + RefCast<Owned = <T as Extractor>::Owned>>() {}
+    fn inserter<T: Inserter>() {}
+
+    /// Checks every arity from the number of given types down to one.
+    macro_rules! arities {
+        ($head:ty $(, $rest:ty)*) => {
+            extractor::<($head, $( $rest ),*)>();
+            extractor::<(W, $( $rest ),*)>();
+            inserter::<(A, $( arities!(@owned $rest) ),*)>();
+            arities!($( $rest ),*);
+        };
+        (@owned $t:ty) => { A };
+        () => {};
+    }
+
+    #[test]
+    fn all_arities() {
+        arities!(R, R, R, R, R, R, R, R, R, R, R, R, R, R, R, R);
+    }
+}
@@ -0,0 +1,336 @@
+use eci_core::{
+    backend::{AccessError, Format, SerializedComponent},
+    Component, Entity,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Extractor, LockableComponent};
+
+/// A member of an extraction which can be given an initial value, see
+/// [`crate::TypedBackend::get_or_insert_with`]. Sealed like [`Extractor`].
+pub trait InitializableComponent: LockableComponent {
+    /// Serializes the initial value of the component, if it must be present
+    /// for the extraction to succeed.
+    fn initialize<F: Format>(
+        inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError>;
+}
+
+fn initialize<T: Component + Serialize, F: Format>(
+    inner: &T,
+) -> Result<Option<SerializedComponent<F>>, AccessError> {
+    Ok(Some(SerializedComponent {
+        contents: F::serialize(inner)?,
+        name: T::COMPONENT_TYPE.to_string(),
+        version: T::VERSION,
+        schema: T::schema(),
+        metadata: None,
+    }))
+}
+
+impl<T> InitializableComponent for &T
+where
+    T: Component + DeserializeOwned + Serialize,
+{
+    fn initialize<F: Format>(
+        inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        initialize(inner)
+    }
+}
+
+impl<T> InitializableComponent for &mut T
+where
+    T: Component + DeserializeOwned + Serialize,
+{
+    fn initialize<F: Format>(
+        inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        initialize(inner)
+    }
+}
+
+/// Optional components are left absent.
+impl<T> InitializableComponent for Option<&T>
+where
+    T: Component + DeserializeOwned,
+{
+    fn initialize<F: Format>(
+        _inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(None)
+    }
+}
+
+impl<T> InitializableComponent for Option<&mut T>
+where
+    T: Component + DeserializeOwned + Serialize,
+{
+    fn initialize<F: Format>(
+        _inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(None)
+    }
+}
+
+impl InitializableComponent for Entity {
+    fn initialize<F: Format>(
+        _inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(None)
+    }
+}
+
+/// Extractions whose required components can be given initial values.
+/// Sealed like [`Extractor`], and its methods are not covered by semver.
+pub trait Initializer: Extractor {
+    /// Serializes the initial values of the extracted components, in the
+    /// order of [`Extractor::extract`], with `None` for optional ones.
+    fn initialize<F: Format>(
+        owned: &Self::Owned,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError>;
+}
+
+/// Initial value of a single member, if it is read from the backend at all.
+fn slot<T: InitializableComponent, F: Format>(
+    inner: &T::Inner,
+) -> Result<Option<Option<SerializedComponent<F>>>, AccessError> {
+    T::as_extraction().map(|_| T::initialize(inner)).transpose()
+}
+
+impl<T: InitializableComponent> Initializer for T {
+    fn initialize<F: Format>(
+        owned: &Self::Owned,
+    ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+        Ok(slot::<T, F>(owned)?.into_iter().collect())
+    }
+}
+
+macro_rules! impl_initializer {
+    ($($v:ident: $T:ident),+) => {
+        impl<$($T: InitializableComponent),+> Initializer for ($($T,)+) {
+            fn initialize<F: Format>(
+                ($($v,)+): &Self::Owned,
+            ) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+                Ok([$(slot::<$T, F>($v)?),+].into_iter().flatten().collect())
+            }
+        }
+    }
+}
+
+macro_rules! impl_all_initializer {
+    ($v:ident: $t:ident) => {
+        impl_initializer!($v: $t);
+    };
+    ($vh:ident: $th:ident, $($vr:ident: $tr:ident),*) => {
+        impl_initializer!($vh: $th, $($vr: $tr),+);
+        impl_all_initializer!($($vr: $tr),+);
+    };
+}
+
+impl_all_initializer!(
+    t1: T1,
+    t2: T2,
+    t3: T3,
+    t4: T4,
+    t5: T5,
+    t6: T6,
+    t7: T7,
+    t8: T8,
+    t9: T9,
+    t10: T10,
+    t11: T11,
+    t12: T12,
+    t13: T13,
+    t14: T14,
+    t15: T15,
+    t16: T16
+);
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Barrier,
+        },
+        time::Duration,
+    };
+
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, Backend, BackendError, ComponentStats,
+            ExtractionDescriptor, SerializedComponent,
+        },
+        Component, Entity,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{retry::retry_conflicts, InsertReport, RetryPolicy, TypedBackend};
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Cooldown(pub usize);
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Charges(pub usize);
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Name(pub String);
+
+    #[test]
+    fn insert_missing_components() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (Charges(3),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend
+            .get_or_insert_with::<(&mut Cooldown, &Charges, Option<&Name>), _>(a, || {
+                (Cooldown(10), Charges(0), Some(Name("unused".to_string())))
+            })
+            .unwrap();
+
+        let (cooldown, charges, name) = lock.deref();
+        assert_eq!(
+            (&*cooldown, charges, name),
+            (&Cooldown(10), &Charges(3), None)
+        );
+        cooldown.0 -= 1;
+        lock.commit().unwrap();
+
+        let lock = backend
+            .get_or_insert_with::<(&Cooldown, &Charges), _>(a, || unreachable!())
+            .unwrap();
+        assert_eq!(lock.entity(), a);
+        drop(lock);
+
+        assert_eq!(
+            backend
+                .peek::<(&Cooldown, &Charges, Option<&Name>)>(a)
+                .unwrap(),
+            Some((Cooldown(9), Charges(3), None))
+        );
+    }
+
+    /// Wraps sqlite, but quietly drops every new component written.
+    struct DroppingWrites(SqliteBackend);
+
+    impl AccessBackend<Json> for DroppingWrites {
+        fn write_components(
+            &self,
+            _entity: Entity,
+            _components: Vec<SerializedComponent<Json>>,
+        ) -> Result<(), AccessError> {
+            Ok(())
+        }
+
+        fn update_components(
+            &self,
+            entity: Entity,
+            components: Vec<SerializedComponent<Json>>,
+        ) -> Result<(), AccessError> {
+            self.0.update_components(entity, components)
+        }
+
+        fn read_components(
+            &self,
+            entity: Entity,
+            descriptors: Vec<ExtractionDescriptor>,
+        ) -> Result<Vec<Option<SerializedComponent<Json>>>, AccessError> {
+            self.0.read_components(entity, descriptors)
+        }
+
+        fn remove_components(
+            &self,
+            entity: Entity,
+            descriptors: Vec<ExtractionDescriptor>,
+        ) -> Result<Vec<Option<SerializedComponent<Json>>>, AccessError> {
+            self.0.remove_components(entity, descriptors)
+        }
+
+        fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+            AccessBackend::<Json>::entities_with(&self.0, component)
+        }
+
+        fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+            AccessBackend::<Json>::list_entities(&self.0)
+        }
+
+        fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+            AccessBackend::<Json>::list_components(&self.0, entity)
+        }
+
+        fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+            AccessBackend::<Json>::stats(&self.0)
+        }
+    }
+
+    #[test]
+    fn initial_components_missing_after_insert() {
+        let backend = Backend::<Json>::from_disjoint(
+            DroppingWrites(SqliteBackend::memory().unwrap()),
+            SqliteBackend::memory().unwrap(),
+        );
+
+        let a = Entity::new();
+        let err = backend
+            .get_or_insert_with::<&mut Cooldown, _>(a, || Cooldown(10))
+            .unwrap_err();
+        assert!(matches!(
+            err.root(),
+            BackendError::Access(AccessError::ComponentNotFound { entity, component })
+                if *entity == a && component == "Cooldown"
+        ));
+    }
+
+    #[test]
+    fn single_initializer_wins() {
+        // A `Backend` cannot be sent to another thread, so each thread opens
+        // its own on a shared file.
+        let path = std::env::temp_dir().join(format!("eci-init-{}.db", Entity::new()));
+        let a = Entity::new();
+
+        let inits = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+
+        let workers: Vec<_> = (0..8)
+            .map(|i| {
+                let (path, inits, barrier) = (path.clone(), inits.clone(), barrier.clone());
+                std::thread::spawn(move || {
+                    let backend = Backend::<Json>::from_joint(SqliteBackend::file(path).unwrap());
+                    let policy = RetryPolicy {
+                        max_attempts: 1000,
+                        deadline: Some(Duration::from_secs(10)),
+                        ..Default::default()
+                    };
+
+                    barrier.wait();
+                    let mut lock = retry_conflicts(&policy, || {
+                        backend.get_or_insert_with::<&mut Cooldown, _>(a, || {
+                            inits.fetch_add(1, Ordering::SeqCst);
+                            Cooldown(i)
+                        })
+                    })
+                    .unwrap();
+
+                    lock.deref().0
+                })
+            })
+            .collect();
+
+        let seen: Vec<usize> = workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect();
+
+        assert_eq!(inits.load(Ordering::SeqCst), 1);
+        assert!(seen.iter().all(|value| *value == seen[0]));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
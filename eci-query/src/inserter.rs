@@ -1,13 +1,90 @@
-use eci_core::backend::{Format, SerializedComponent};
-use eci_core::Component;
+use eci_core::backend::{AccessError, Format, SerializedComponent};
+use eci_core::{Component, Entity};
 use serde::Serialize;
 
-pub trait Inserter {
+use crate::sealed;
+
+/// Serializes tuples of components for insertion into a backend.
+///
+/// The trait is sealed: it is implemented for tuples of up to 16
+/// components, and can be named in bounds, but not implemented outside of
+/// this crate. Its methods are not covered by semver.
+pub trait Inserter: sealed::Insertion {
     fn insert<F: Format>(self) -> Vec<SerializedComponent<F>>;
 }
 
+/// What happened to a single component passed to
+/// [`crate::TypedBackend::put`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertOutcome {
+    /// The component was written.
+    Inserted,
+    /// The entity already had the component, so it was left untouched.
+    Conflict,
+    /// The component was not written, because another component in the
+    /// same atomic write conflicted.
+    RolledBack,
+}
+
+/// Outcome of inserting components into an entity, in the order the
+/// components were given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use = "components which conflicted were not written"]
+pub struct InsertReport {
+    pub entity: Entity,
+    pub components: Vec<(String, InsertOutcome)>,
+}
+
+impl InsertReport {
+    /// Whether every component was written.
+    pub fn is_complete(&self) -> bool {
+        self.components
+            .iter()
+            .all(|(_, outcome)| *outcome == InsertOutcome::Inserted)
+    }
+
+    /// Fails with [`AccessError::Conflict`] naming the first component
+    /// which was not written, unless every component was.
+    pub fn into_result(self) -> Result<(), AccessError> {
+        let conflict = self
+            .components
+            .iter()
+            .find(|(_, outcome)| *outcome == InsertOutcome::Conflict)
+            .or_else(|| {
+                self.components
+                    .iter()
+                    .find(|(_, outcome)| *outcome != InsertOutcome::Inserted)
+            });
+
+        match conflict {
+            Some((name, _)) => Err(AccessError::Conflict(self.entity, name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Components which the entity already had.
+    pub fn conflicts(&self) -> impl Iterator<Item = &str> {
+        self.with_outcome(InsertOutcome::Conflict)
+    }
+
+    /// Components which were not written, and can be retried once the
+    /// conflicts have been dealt with.
+    pub fn rolled_back(&self) -> impl Iterator<Item = &str> {
+        self.with_outcome(InsertOutcome::RolledBack)
+    }
+
+    fn with_outcome(&self, wanted: InsertOutcome) -> impl Iterator<Item = &str> {
+        self.components
+            .iter()
+            .filter(move |(_, outcome)| *outcome == wanted)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
 macro_rules! impl_inserter{
     ($($v:ident: $T:ident),+) => {
+        impl<$($T: Component + Serialize),+> sealed::Insertion for ($($T,)+) {}
+
         impl<$($T: Component + Serialize),+> Inserter for ($($T,)+) {
             fn insert<F: Format>(self) -> Vec<SerializedComponent<F>> {
                 let ($($v,)+) = self;
@@ -17,6 +94,9 @@ macro_rules! impl_inserter{
                         SerializedComponent {
                             contents: F::serialize($v).unwrap(),
                             name: $T::COMPONENT_TYPE.to_string(),
+                            version: $T::VERSION,
+                            schema: $T::schema(),
+                            metadata: None,
                         },
                     )+
                 ]
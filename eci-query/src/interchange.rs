@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, Backend, BackendError, ExtractionDescriptor, Format, ResultExt,
+        SerializedComponent,
+    },
+    Entity,
+};
+
+use crate::registry::ComponentRegistry;
+
+/// Translates component names used by external tools to the
+/// `COMPONENT_TYPE`s of our own components. Names without an explicit
+/// mapping are used as-is.
+#[derive(Debug, Clone, Default)]
+pub struct NameMapping {
+    to_internal: HashMap<String, String>,
+    to_external: HashMap<String, String>,
+}
+
+impl NameMapping {
+    pub fn identity() -> Self {
+        Self::default()
+    }
+
+    pub fn map<E: Into<String>, I: Into<String>>(mut self, external: E, internal: I) -> Self {
+        let (external, internal) = (external.into(), internal.into());
+        self.to_external.insert(internal.clone(), external.clone());
+        self.to_internal.insert(external, internal);
+        self
+    }
+
+    pub fn internal<'a>(&'a self, external: &'a str) -> &'a str {
+        self.to_internal
+            .get(external)
+            .map(String::as_str)
+            .unwrap_or(external)
+    }
+
+    pub fn external<'a>(&'a self, internal: &'a str) -> &'a str {
+        self.to_external
+            .get(internal)
+            .map(String::as_str)
+            .unwrap_or(internal)
+    }
+}
+
+/// What to do with components in an imported document which are not in
+/// the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownComponents {
+    /// Import the known components, listing the rest in the report.
+    Skip,
+    /// Fail the import without writing anything.
+    Reject,
+}
+
+/// Outcome of importing a single entity document.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImportReport {
+    pub entity: Entity,
+    /// External names of the components which were skipped as unknown.
+    pub skipped: Vec<String>,
+}
+
+/// Import and export of entities as documents mapping component names to
+/// values, as emitted by scene formats of other ECS tools.
+pub trait InterchangeBackend<F: Format> {
+    /// Imports the document as a new entity. Every component is validated
+    /// before anything is written, and the components are written together.
+    fn import_entity_doc(
+        &self,
+        doc: serde_json::Value,
+        registry: &ComponentRegistry<F>,
+        mapping: &NameMapping,
+        unknown: UnknownComponents,
+    ) -> Result<ImportReport, BackendError>;
+
+    /// Exports all registered components of the entity as a document.
+    fn export_entity_doc(
+        &self,
+        entity: Entity,
+        registry: &ComponentRegistry<F>,
+        mapping: &NameMapping,
+    ) -> Result<serde_json::Value, BackendError>;
+}
+
+impl<F: Format> InterchangeBackend<F> for Backend<F> {
+    fn import_entity_doc(
+        &self,
+        doc: serde_json::Value,
+        registry: &ComponentRegistry<F>,
+        mapping: &NameMapping,
+        unknown: UnknownComponents,
+    ) -> Result<ImportReport, BackendError> {
+        let doc = match doc {
+            serde_json::Value::Object(doc) => doc,
+            other => {
+                return Err(AccessError::Serialization(
+                    format!("expected a map of components, found {other}").into(),
+                ))
+                .ctx("importing entity document")
+            }
+        };
+
+        let entity = Entity::new();
+        let mut components = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (external, value) in doc {
+            let name = mapping.internal(&external).to_string();
+
+            if !registry.contains(&name) && unknown == UnknownComponents::Skip {
+                skipped.push(external);
+                continue;
+            }
+
+            let (contents, version) = registry
+                .import(&name, value)
+                .ctx(format!("importing {external} as {name}"))?;
+
+            components.push(SerializedComponent {
+                contents,
+                name,
+                version,
+                schema: None,
+                metadata: None,
+            });
+        }
+
+        if !components.is_empty() {
+            self.write_components(entity, components)
+                .ctx(format!("writing imported components of {entity}"))?;
+        }
+
+        Ok(ImportReport { entity, skipped })
+    }
+
+    fn export_entity_doc(
+        &self,
+        entity: Entity,
+        registry: &ComponentRegistry<F>,
+        mapping: &NameMapping,
+    ) -> Result<serde_json::Value, BackendError> {
+        let descriptors = registry
+            .versions()
+            .map(|(name, version)| ExtractionDescriptor {
+                name: name.to_string(),
+                version: Some(version),
+                schema: None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut doc = serde_json::Map::new();
+        if descriptors.is_empty() {
+            return Ok(serde_json::Value::Object(doc));
+        }
+
+        for component in crate::migrate::read_components(self, entity, descriptors)
+            .ctx(format!("reading components of {entity}"))?
+            .into_iter()
+            .flatten()
+        {
+            let value = registry
+                .export(&component.name, &component.contents)
+                .ctx(format!("exporting {} of {entity}", component.name))?;
+
+            doc.insert(mapping.external(&component.name).to_string(), value);
+        }
+
+        Ok(serde_json::Value::Object(doc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{AccessError, Backend, BackendError},
+        Component,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use crate::{registry::ComponentRegistry, TypedBackend};
+
+    use super::{ImportReport, InterchangeBackend, NameMapping, UnknownComponents};
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Health(pub u32);
+
+    fn registry() -> ComponentRegistry<Json> {
+        ComponentRegistry::new()
+            .register::<Position>()
+            .register::<Health>()
+    }
+
+    fn mapping() -> NameMapping {
+        NameMapping::identity().map("game::Transform", "Position")
+    }
+
+    fn fixture() -> serde_json::Value {
+        json!({
+            "game::Transform": { "x": 1.0, "y": 2.0 },
+            "Health": "full",
+            "bevy::Visibility": true,
+        })
+    }
+
+    #[test]
+    fn roundtrip() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let doc = json!({
+            "game::Transform": { "x": 1.0, "y": 2.0 },
+            "Health": 10,
+            "bevy::Visibility": true,
+        });
+
+        let ImportReport { entity, skipped } = backend
+            .import_entity_doc(doc, &registry(), &mapping(), UnknownComponents::Skip)
+            .unwrap();
+
+        assert_eq!(skipped, vec!["bevy::Visibility".to_string()]);
+        assert_eq!(
+            backend
+                .get::<(&Position, &Health)>(entity)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (&Position { x: 1.0, y: 2.0 }, &Health(10))
+        );
+
+        assert_eq!(
+            backend
+                .export_entity_doc(entity, &registry(), &mapping())
+                .unwrap(),
+            json!({
+                "game::Transform": { "x": 1.0, "y": 2.0 },
+                "Health": 10,
+            })
+        );
+    }
+
+    #[test]
+    fn skip_unknown_rejects_misshapen() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let err = backend
+            .import_entity_doc(fixture(), &registry(), &mapping(), UnknownComponents::Skip)
+            .unwrap_err();
+
+        assert_eq!(err.contexts(), vec!["importing Health as Health"]);
+        assert!(matches!(
+            err.root(),
+            BackendError::Access(AccessError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn reject_unknown() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let mut doc = fixture();
+        doc["Health"] = json!(10);
+
+        let err = backend
+            .import_entity_doc(doc, &registry(), &mapping(), UnknownComponents::Reject)
+            .unwrap_err();
+
+        assert!(matches!(
+            err.root(),
+            BackendError::Access(AccessError::UnknownComponent(name)) if name == "bevy::Visibility"
+        ));
+
+        // Components are validated in document order, which is sorted.
+        let err = backend
+            .import_entity_doc(
+                fixture(),
+                &registry(),
+                &mapping(),
+                UnknownComponents::Reject,
+            )
+            .unwrap_err();
+
+        assert_eq!(err.contexts(), vec!["importing Health as Health"]);
+        assert!(matches!(
+            err.root(),
+            BackendError::Access(AccessError::Serialization(_))
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use eci_core::backend::{Lock, LockingBackend, LockingError};
+use log::*;
+
+/// Keeps a lock alive from a background thread, renewing it so it expires
+/// `ttl` from each renewal, every `interval`. Renewal stops once the keeper
+/// is unlocked or dropped, both of which release the lock.
+///
+/// If a renewal fails, the error is passed to the failure callback and no
+/// further renewals are attempted, since the lock may have been lost.
+pub struct LockKeeper<L: LockingBackend + Send + 'static> {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<(Lock, L)>>,
+}
+
+impl<L: LockingBackend + Send + 'static> LockKeeper<L> {
+    pub fn spawn<E>(
+        backend: L,
+        lock: Lock,
+        ttl: Duration,
+        interval: Duration,
+        on_failure: E,
+    ) -> Self
+    where
+        E: FnOnce(LockingError) + Send + 'static,
+    {
+        let (stop, stopped) = mpsc::channel();
+
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                debug!("renewing kept lock {lock}");
+                if let Err(err) = backend.renew_lock(&lock, ttl) {
+                    warn!("failed to renew kept lock {lock}: {err}");
+                    on_failure(err);
+                    break;
+                }
+            }
+
+            (lock, backend)
+        });
+
+        LockKeeper {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Stops renewing the lock and releases it.
+    pub fn unlock(mut self) -> Result<(), LockingError> {
+        match self.stop() {
+            Some((lock, backend)) => backend.release_lock(lock),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops the renewing thread, handing back the lock and its backend.
+    fn stop(&mut self) -> Option<(Lock, L)> {
+        // Dropping the sender wakes the thread up, even mid-interval.
+        self.stop.take();
+        self.thread.take().and_then(|thread| thread.join().ok())
+    }
+}
+
+impl<L: LockingBackend + Send + 'static> Drop for LockKeeper<L> {
+    fn drop(&mut self) {
+        if let Some((lock, backend)) = self.stop() {
+            backend.release_lock(lock).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{Backend, LockDescriptor, LockingBackend, LockingError, LockingMode},
+        Entity,
+    };
+    use eci_format_json::Json;
+
+    use super::LockKeeper;
+
+    // Leaves plenty of room for renewals to run late on a busy machine.
+    const TTL: Duration = Duration::from_millis(500);
+    const HEARTBEAT: Duration = Duration::from_millis(100);
+
+    fn write_lock() -> Vec<LockDescriptor> {
+        vec![LockDescriptor {
+            mode: LockingMode::Write,
+            name: "CounterA".to_string(),
+        }]
+    }
+
+    #[test]
+    fn keeps_lock_alive() {
+        // The keeper takes its backend along to its own thread, so the
+        // contender opens another on the same file.
+        let path = std::env::temp_dir().join(format!("eci-keeper-{}.db", Entity::new()));
+        let entity = Entity::new();
+
+        let backend = SqliteBackend::file(&path).unwrap();
+        let lock = backend
+            .acquire_lock(entity, write_lock(), TTL.into())
+            .unwrap();
+        let keeper = LockKeeper::spawn(backend, lock, TTL, HEARTBEAT, |err| {
+            panic!("renewal failed: {err}")
+        });
+
+        let contender = SqliteBackend::file(&path).unwrap();
+        let start = Instant::now();
+        while start.elapsed() < TTL * 2 {
+            assert!(matches!(
+                contender.acquire_lock(entity, write_lock(), TTL.into()),
+                Err(LockingError::Conflict(..))
+            ));
+            std::thread::sleep(HEARTBEAT / 2);
+        }
+
+        keeper.unlock().unwrap();
+        contender
+            .acquire_lock(entity, write_lock(), TTL.into())
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reports_lost_lock() {
+        let path = std::env::temp_dir().join(format!("eci-keeper-{}.db", Entity::new()));
+        let entity = Entity::new();
+
+        let backend = SqliteBackend::file(&path).unwrap();
+        let lock = backend
+            .acquire_lock(entity, write_lock(), TTL.into())
+            .unwrap();
+        let id = lock.id();
+
+        let (failed, failure) = mpsc::channel();
+        let _keeper = LockKeeper::spawn(backend, lock, TTL, HEARTBEAT, move |err| {
+            failed.send(err.to_string()).unwrap()
+        });
+
+        let admin = Backend::<Json>::from_joint(SqliteBackend::file(&path).unwrap());
+        assert_eq!(admin.admin().force_release_entity(entity).unwrap(), 1);
+
+        let failure = failure.recv_timeout(TTL).unwrap();
+        assert_eq!(failure, LockingError::Expired(id).to_string());
+        std::fs::remove_file(path).unwrap();
+    }
+}
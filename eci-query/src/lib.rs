@@ -1,240 +1,2885 @@
+//! Typed access to components stored in a [`Backend`].
+//!
+//! The public API consists of [`TypedBackend`], the [`Locked`] guard it
+//! hands out, and the [`Extractor`], [`Inserter`], [`Remover`] and
+//! [`LockableComponent`] traits describing which components to operate on.
+//! Those traits are sealed, so they can be used as bounds in generic code,
+//! but are only implemented by this crate.
+//!
+//! `TypedBackend` and the public methods of `Locked` are semver-stable. The
+//! methods of the sealed traits are implementation details, and may change
+//! in any release.
+
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod batch;
+pub mod bundle;
+pub mod defaulted;
 pub mod extractor;
+pub mod initializer;
 pub mod inserter;
+pub mod interchange;
+pub mod keeper;
 pub mod lock;
+pub mod merge;
+mod migrate;
+pub mod pair;
+pub mod query;
 pub mod refcast;
+pub mod registry;
+pub mod remover;
+pub mod retry;
+pub mod schedule;
+pub mod snapshot;
+pub mod transcode;
+pub mod versioned;
+pub mod world;
 
 use eci_core::{
     backend::{
-        AccessBackend, AccessError, Backend, BackendError, Format, LockDescriptor, LockingBackend,
-        LockingMode, SerializedComponent,
+        AccessBackend, AccessError, Backend, BackendError, ContextError, DeserializationLimits,
+        ExtractionDescriptor, Format, JointBackend, LockDescriptor, LockingBackend, LockingError,
+        LockingMode, ResultExt, SerializedComponent,
     },
     Component, Entity,
 };
 
-use extractor::Extractor;
-use inserter::Inserter;
-use lock::{DropLock, Locked};
-use refcast::RefCast;
-use serde::de::DeserializeOwned;
+#[cfg(feature = "async")]
+pub use asynchronous::{AsyncDropLock, AsyncLocked, TypedBackendAsync};
+pub use batch::{BatchReport, DEFAULT_BATCH_SIZE};
+pub use bundle::Bundle;
+pub use defaulted::OrDefault;
+pub use eci_derive::Bundle;
+pub use extractor::Extractor;
+pub use initializer::Initializer;
+pub use inserter::{InsertOutcome, InsertReport, Inserter};
+pub use keeper::LockKeeper;
+pub use lock::{DropLock, Locked};
+pub use pair::LockedPair;
+pub use query::Query;
+pub use refcast::RefCast;
+pub use remover::Remover;
+pub use retry::RetryPolicy;
+pub use schedule::{Schedule, ScheduleReport};
+pub use snapshot::{ImportMode, WorldSnapshot};
+pub use transcode::{migrate_format, DecoderRegistry, MigrationReport};
+pub use versioned::{Versioned, VersionedSelection};
+pub use world::{EntityBuilder, World};
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+
+pub(crate) mod sealed {
+    /// Implemented by everything which can be extracted from a backend.
+    pub trait Selection {}
+
+    /// Implemented by everything which can be inserted into a backend.
+    pub trait Insertion {}
+
+    /// Implemented by everything which can be removed from a backend.
+    pub trait Removal {}
+}
+
+/// A single member of an extraction, such as `&T`, `Option<&mut T>` or
+/// [`Entity`]. Sealed like [`Extractor`].
+pub trait LockableComponent: sealed::Selection {
+    type Inner;
+
+    /// Lock required on the component, if it is stored in the backend at all.
+    fn as_lock() -> Option<LockDescriptor>;
+
+    /// Component to read from the backend, if it is stored there at all.
+    fn as_extraction() -> Option<ExtractionDescriptor>;
+
+    /// Whether the extraction fails if the entity lacks the component.
+    fn is_required() -> bool;
 
-pub trait LockableComponent {
-    type Inner: Component + DeserializeOwned;
-    fn as_lock() -> LockDescriptor;
     fn deserialize<F: Format>(
+        entity: Entity,
         serialized: Option<SerializedComponent<F>>,
+        limits: Option<&DeserializationLimits>,
     ) -> Result<Option<Self::Inner>, AccessError>;
+
+    /// Serializes the component for writing back to the backend, if it
+    /// was requested mutably.
+    fn serialize<F: Format>(
+        inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError>;
+}
+
+/// Marks extractions which only read components, and so can be performed
+/// without taking any locks.
+pub trait ReadOnly {}
+
+impl<T> ReadOnly for &T {}
+impl<T> ReadOnly for Option<&T> {}
+impl ReadOnly for Entity {}
+
+/// Rejects selections which request the same component more than once,
+/// unless every request is for reading, since they would conflict with
+/// each other. Selections without any components, such as only [`Entity`],
+/// are rejected as well, since there would be nothing to lock.
+pub(crate) fn validate_selection(descriptors: &[LockDescriptor]) -> Result<(), BackendError> {
+    if descriptors.is_empty() {
+        return Err(LockingError::EmptyRequest.into());
+    }
+
+    for (i, descriptor) in descriptors.iter().enumerate() {
+        let modes: Vec<LockingMode> = descriptors[i..]
+            .iter()
+            .filter(|other| other.name == descriptor.name)
+            .map(|other| other.mode)
+            .collect();
+
+        if modes.len() > 1 && modes.contains(&LockingMode::Write) {
+            return Err(BackendError::InvalidSelection {
+                component: descriptor.name.clone(),
+                modes,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Deserializes a single component, naming it in any serialization or
+/// decryption error.
+pub(crate) fn deserialize_component<F: Format, T: Component + DeserializeOwned>(
+    component: SerializedComponent<F>,
+    limits: Option<&DeserializationLimits>,
+) -> Result<T, AccessError> {
+    match limits {
+        Some(limits) => F::deserialize_bounded::<T>(&component.contents, limits),
+        None => F::deserialize::<T>(&component.contents),
+    }
+    .map_err(|err| match err {
+        AccessError::Serialization(inner) => AccessError::Serialization(Box::new(
+            ContextError::new(format!("deserializing {}", component.name), inner),
+        )),
+        AccessError::Decryption(inner) => AccessError::Decryption(Box::new(ContextError::new(
+            format!("decrypting {}", component.name),
+            inner,
+        ))),
+        err => err,
+    })
 }
 
+impl<T> sealed::Selection for &T {}
+
 impl<T> LockableComponent for &T
 where
     T: Component + DeserializeOwned,
 {
     type Inner = T;
-    fn as_lock() -> LockDescriptor {
-        LockDescriptor {
+    fn as_lock() -> Option<LockDescriptor> {
+        Some(LockDescriptor {
             mode: LockingMode::Read,
             name: T::COMPONENT_TYPE.to_string(),
-        }
+        })
+    }
+
+    fn as_extraction() -> Option<ExtractionDescriptor> {
+        Some(ExtractionDescriptor {
+            name: T::COMPONENT_TYPE.to_string(),
+            version: Some(T::VERSION),
+            schema: T::schema(),
+        })
+    }
+
+    fn is_required() -> bool {
+        true
     }
 
     fn deserialize<F: Format>(
+        _entity: Entity,
         serialized: Option<SerializedComponent<F>>,
+        limits: Option<&DeserializationLimits>,
     ) -> Result<Option<Self::Inner>, AccessError> {
         serialized
-            .map(|component| {
-                let data = F::Data::from(component.contents.into());
-                F::deserialize::<Self::Inner>(&data)
-            })
+            .map(|component| deserialize_component::<F, T>(component, limits))
             .transpose()
     }
+
+    fn serialize<F: Format>(
+        _inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(None)
+    }
 }
 
+impl<T> sealed::Selection for &mut T {}
+
 impl<T> LockableComponent for &mut T
 where
-    T: Component + DeserializeOwned,
+    T: Component + DeserializeOwned + Serialize,
 {
     type Inner = T;
-    fn as_lock() -> LockDescriptor {
-        LockDescriptor {
+    fn as_lock() -> Option<LockDescriptor> {
+        Some(LockDescriptor {
             mode: LockingMode::Write,
             name: T::COMPONENT_TYPE.to_string(),
-        }
+        })
+    }
+
+    fn as_extraction() -> Option<ExtractionDescriptor> {
+        Some(ExtractionDescriptor {
+            name: T::COMPONENT_TYPE.to_string(),
+            version: Some(T::VERSION),
+            schema: T::schema(),
+        })
+    }
+
+    fn is_required() -> bool {
+        true
     }
 
     fn deserialize<F: Format>(
+        _entity: Entity,
         serialized: Option<SerializedComponent<F>>,
+        limits: Option<&DeserializationLimits>,
     ) -> Result<Option<Self::Inner>, AccessError> {
         serialized
-            .map(|component| {
-                let data = F::Data::from(component.contents.into());
-                F::deserialize::<Self::Inner>(&data)
-            })
+            .map(|component| deserialize_component::<F, T>(component, limits))
             .transpose()
     }
+
+    fn serialize<F: Format>(
+        inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(Some(SerializedComponent {
+            contents: F::serialize(inner)?,
+            name: T::COMPONENT_TYPE.to_string(),
+            version: T::VERSION,
+            schema: T::schema(),
+            metadata: None,
+        }))
+    }
+}
+
+/// Optional components do not abort the extraction if they are absent,
+/// but are still locked so they cannot be inserted concurrently.
+impl<T> sealed::Selection for Option<&T> {}
+
+impl<T> LockableComponent for Option<&T>
+where
+    T: Component + DeserializeOwned,
+{
+    type Inner = Option<T>;
+    fn as_lock() -> Option<LockDescriptor> {
+        <&T as LockableComponent>::as_lock()
+    }
+
+    fn as_extraction() -> Option<ExtractionDescriptor> {
+        <&T as LockableComponent>::as_extraction()
+    }
+
+    fn is_required() -> bool {
+        false
+    }
+
+    fn deserialize<F: Format>(
+        entity: Entity,
+        serialized: Option<SerializedComponent<F>>,
+        limits: Option<&DeserializationLimits>,
+    ) -> Result<Option<Self::Inner>, AccessError> {
+        Ok(Some(<&T as LockableComponent>::deserialize(
+            entity, serialized, limits,
+        )?))
+    }
+
+    fn serialize<F: Format>(
+        _inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(None)
+    }
+}
+
+impl<T> sealed::Selection for Option<&mut T> {}
+
+impl<T> LockableComponent for Option<&mut T>
+where
+    T: Component + DeserializeOwned + Serialize,
+{
+    type Inner = Option<T>;
+    fn as_lock() -> Option<LockDescriptor> {
+        <&mut T as LockableComponent>::as_lock()
+    }
+
+    fn as_extraction() -> Option<ExtractionDescriptor> {
+        <&mut T as LockableComponent>::as_extraction()
+    }
+
+    fn is_required() -> bool {
+        false
+    }
+
+    fn deserialize<F: Format>(
+        entity: Entity,
+        serialized: Option<SerializedComponent<F>>,
+        limits: Option<&DeserializationLimits>,
+    ) -> Result<Option<Self::Inner>, AccessError> {
+        Ok(Some(<&mut T as LockableComponent>::deserialize(
+            entity, serialized, limits,
+        )?))
+    }
+
+    fn serialize<F: Format>(
+        inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(inner
+            .as_ref()
+            .map(<&mut T as LockableComponent>::serialize)
+            .transpose()?
+            .flatten())
+    }
+}
+
+/// The entity being queried, which is neither locked nor read from the
+/// backend.
+impl sealed::Selection for Entity {}
+
+impl LockableComponent for Entity {
+    type Inner = Entity;
+    fn as_lock() -> Option<LockDescriptor> {
+        None
+    }
+
+    fn as_extraction() -> Option<ExtractionDescriptor> {
+        None
+    }
+
+    fn is_required() -> bool {
+        false
+    }
+
+    fn deserialize<F: Format>(
+        entity: Entity,
+        _serialized: Option<SerializedComponent<F>>,
+        _limits: Option<&DeserializationLimits>,
+    ) -> Result<Option<Self::Inner>, AccessError> {
+        Ok(Some(entity))
+    }
+
+    fn serialize<F: Format>(
+        _inner: &Self::Inner,
+    ) -> Result<Option<SerializedComponent<F>>, AccessError> {
+        Ok(None)
+    }
 }
 
 pub trait TypedBackend<F: Format> {
-    fn get<'a, Select>(&self, entity: Entity) -> Result<Option<Locked<Select>>, BackendError>
+    fn get<Select>(&self, entity: Entity) -> Result<Option<Locked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>;
+
+    /// Like [`TypedBackend::get`], but fails with
+    /// [`AccessError::ComponentNotFound`] naming the first selected component
+    /// the entity lacks, or with [`AccessError::EntityNotFound`] if it has
+    /// no components at all, rather than returning `None`. Optional
+    /// components may still be absent.
+    fn get_required<Select>(&self, entity: Entity) -> Result<Locked<Select, F>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>;
+
+    /// Like [`TypedBackend::get`], but holds the lock for the given duration
+    /// instead of the backend's default.
+    fn get_with_ttl<Select>(
+        &self,
+        entity: Entity,
+        ttl: Duration,
+    ) -> Result<Option<Locked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>;
+
+    /// Like [`TypedBackend::get`], but retries according to the policy while
+    /// the components are locked by someone else.
+    fn get_blocking<Select>(
+        &self,
+        entity: Entity,
+        policy: &RetryPolicy,
+    ) -> Result<Option<Locked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>;
+
+    /// Locks the selected components of two entities under a single lock,
+    /// such as when moving something from one entity to another. Either
+    /// both are locked, or neither is, and the components are read under
+    /// the lock. Returns `None` if either entity lacks any of its selected
+    /// components.
+    fn get_pair<SelA, SelB>(
+        &self,
+        a: Entity,
+        b: Entity,
+    ) -> Result<Option<LockedPair<SelA, SelB, F>>, BackendError>
+    where
+        SelA: Extractor + RefCast<Owned = <SelA as Extractor>::Owned>,
+        SelB: Extractor + RefCast<Owned = <SelB as Extractor>::Owned>;
+
+    /// Locks the selected components, inserting the values returned by
+    /// `init` for any required components the entity does not have yet.
+    ///
+    /// The locks are acquired before anything is read, so concurrent
+    /// callers cannot both insert their initial values. Optional components
+    /// are left absent, and `init` is only called if something is missing.
+    fn get_or_insert_with<Select, Init>(
+        &self,
+        entity: Entity,
+        init: Init,
+    ) -> Result<Locked<Select, F>, BackendError>
+    where
+        Select: Initializer + RefCast<Owned = <Select as Extractor>::Owned>,
+        Init: FnOnce() -> <Select as Extractor>::Owned;
+
+    /// Locks the selected components, passes them to the closure and commits
+    /// any mutably selected components once it returns. Returns `None`
+    /// without calling the closure if the entity lacks any of the components.
+    /// The lock is released even if the closure panics.
+    fn modify<Select, R>(
+        &self,
+        entity: Entity,
+        func: impl FnOnce(Select::Ref<'_>) -> R,
+    ) -> Result<Option<R>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>;
+
+    /// Reads the selected components without locking them, so the values
+    /// may be modified by others at any time. Only read-only selections are
+    /// allowed:
+    ///
+    /// ```compile_fail
+    /// # use eci_core::{backend::Backend, Component, Entity};
+    /// # use eci_format_json::Json;
+    /// # use eci_query::TypedBackend;
+    /// # #[derive(Component, serde::Serialize, serde::Deserialize)]
+    /// # struct Counter(usize);
+    /// # fn peek(backend: &Backend<Json>, entity: Entity) {
+    /// backend.peek::<&mut Counter>(entity);
+    /// # }
+    /// ```
+    fn peek<Select>(&self, entity: Entity) -> Result<Option<Select::Owned>, BackendError>
+    where
+        Select: Extractor + ReadOnly;
+
+    /// Reads a single component without locking it, along with its
+    /// revision, so it can be written back with
+    /// [`TypedBackend::put_if_unchanged`] unless someone else has written
+    /// it in the meantime. Fails with [`AccessError::Unsupported`] if the
+    /// backend does not keep track of revisions.
+    fn get_versioned<Select>(
+        &self,
+        entity: Entity,
+    ) -> Result<Option<Versioned<Select::Component>>, BackendError>
+    where
+        Select: VersionedSelection;
+
+    /// Whether the entity has the component, without reading it or taking
+    /// any locks.
+    fn has<T: Component>(&self, entity: Entity) -> Result<bool, AccessError>;
+
+    /// Whether the entity has any components at all, without reading them
+    /// or taking any locks.
+    fn exists(&self, entity: Entity) -> Result<bool, AccessError>;
+
+    /// Stores a resource, a component which belongs to no entity in
+    /// particular such as global settings, replacing the one already
+    /// stored. Fails if the resource is currently locked by someone else.
+    ///
+    /// Resources are components of [`Entity::RESOURCES`], which is left out
+    /// when listing entities, and are locked just like other components.
+    fn put_resource<T>(&self, value: T) -> Result<(), BackendError>
+    where
+        T: Component + Serialize;
+
+    /// Like [`TypedBackend::get`], but for resources stored with
+    /// [`TypedBackend::put_resource`].
+    fn get_resource<Select>(&self) -> Result<Option<Locked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>;
+
+    /// Like [`TypedBackend::peek`], but for resources stored with
+    /// [`TypedBackend::put_resource`].
+    fn peek_resource<Select>(&self) -> Result<Option<Select::Owned>, BackendError>
+    where
+        Select: Extractor + ReadOnly;
+
+    /// Starts a query for entities with the selected components, which can
+    /// be narrowed down by the values of their fields:
+    ///
+    /// ```
+    /// # use eci_core::{backend::Backend, Component};
+    /// # use eci_format_json::Json;
+    /// # use eci_query::TypedBackend;
+    /// # use serde::{Deserialize, Serialize};
+    /// #[derive(Component, Serialize, Deserialize)]
+    /// struct Health {
+    ///     current: u32,
+    /// }
+    ///
+    /// # fn wounded(backend: &Backend<Json>) {
+    /// let wounded = backend
+    ///     .query::<&Health>()
+    ///     .filter(Health::field("current").lt(10))
+    ///     .peek();
+    /// # }
+    /// ```
+    fn query<Select>(&self) -> Query<'_, F, Select>
+    where
+        Select: Extractor;
+
+    /// Locks the entity as a whole, keeping anyone else from locking any of
+    /// its components until the returned lock is released or expires.
+    /// Useful when restructuring an entity without knowing which
+    /// components it has.
+    fn lock_entity(&self, entity: Entity, ttl: Duration) -> Result<DropLock, BackendError>;
+
+    /// Inserts components into the entity. Components the entity already
+    /// has are reported as conflicts rather than returned as errors, which
+    /// [`InsertReport::into_result`] turns them into.
+    ///
+    /// If the backend [supports atomic writes], a conflict leaves the
+    /// other components unwritten, and they are reported as rolled back.
+    /// Otherwise each component is written separately, so only the
+    /// conflicting components are left out.
+    ///
+    /// [supports atomic writes]: AccessBackend::supports_atomic_writes
+    fn put<T>(&self, entity: Entity, components: T) -> Result<InsertReport, AccessError>
     where
-        Select: Extractor + RefCast<'a, Owned = <Select as Extractor>::Owned>;
+        T: Inserter;
+
+    /// Inserts components for many entities at once. If any of them
+    /// conflict with existing components, the backend decides whether the
+    /// rest are written, see [`AccessBackend::write_components_batch`].
+    fn put_many<I, T>(&self, items: I) -> Result<(), AccessError>
+    where
+        I: IntoIterator<Item = (Entity, T)>,
+        T: Inserter;
 
-    fn put<T>(&self, entity: Entity, components: T) -> Result<(), AccessError>
+    /// Overwrites the given components, inserting any which do not yet exist.
+    /// Fails if any of the components are currently locked by someone else.
+    fn update<T>(&self, entity: Entity, components: T) -> Result<(), BackendError>
     where
         T: Inserter;
+
+    /// Overwrites the component read with [`TypedBackend::get_versioned`],
+    /// failing with [`AccessError::StaleWrite`] if it has been written or
+    /// removed since it was read. Locks are neither taken nor checked, so
+    /// components written this way are best left unlocked everywhere.
+    fn put_if_unchanged<T>(
+        &self,
+        entity: Entity,
+        versioned: Versioned<T>,
+    ) -> Result<(), AccessError>
+    where
+        T: Component + Serialize;
+
+    /// Removes the given components from the entity, returning the removed
+    /// values or `None` for each component the entity did not have. Fails
+    /// if any of the components are currently locked by someone else.
+    fn remove<T>(&self, entity: Entity) -> Result<T::Removed, BackendError>
+    where
+        T: Remover;
+
+    /// Visits every entity which has the selected components, locking them
+    /// in batches of [`DEFAULT_BATCH_SIZE`]. Mutably selected components
+    /// are written back after each visit.
+    fn for_each<Select, Func>(
+        &self,
+        entities: Vec<Entity>,
+        func: Func,
+    ) -> Result<BatchReport, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+        Func: FnMut(Entity, Select::Ref<'_>);
+
+    /// Like [`TypedBackend::for_each`], but with a configurable batch size.
+    /// Entities which are locked by someone else are retried once all
+    /// batches have been visited, and reported as skipped if they still are.
+    fn for_each_batched<Select, Func>(
+        &self,
+        entities: Vec<Entity>,
+        batch_size: usize,
+        func: Func,
+    ) -> Result<BatchReport, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+        Func: FnMut(Entity, Select::Ref<'_>);
+}
+
+/// Locks and reads the selected components, failing rather than returning
+/// `None` if `required` and the entity lacks any of them.
+fn lock_selection<F, Select>(
+    backend: &Backend<F>,
+    entity: Entity,
+    ttl: Duration,
+    required: bool,
+) -> Result<Option<Locked<Select, F>>, BackendError>
+where
+    F: Format,
+    Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+{
+    let descriptors = Select::describe();
+    validate_selection(&descriptors)?;
+
+    // Locking before reading, rather than after finding the components,
+    // makes sure nobody writes them in between.
+    let expires =
+        lock::expiry(backend.now(), ttl).ctx(format!("locking components of {entity}"))?;
+    let (expected, extract) = migrate::expecting(backend, Select::extract());
+    let (lock, components) = backend
+        .read_and_lock(entity, descriptors, extract, ttl.into())
+        .ctx(format!("locking components of {entity}"))?;
+    let lock = DropLock::from_backend(lock, backend, &[entity]);
+
+    let components = migrate::upgrade(backend, entity, expected, components)
+        .and_then(|components| {
+            if required {
+                check_required::<F, Select>(backend, entity, &components)?;
+            }
+            Select::from(entity, components, backend.limits())
+        })
+        .ctx(format!("reading components of {entity}"))?;
+
+    // Dropping the lock releases it again if the components are absent.
+    Ok(components
+        .map(|components| Locked::new(entity, lock, backend.clone(), expires, ttl, components)))
+}
+
+/// Fails naming the first required component which was not read, or the
+/// entity itself if it has no components at all.
+fn check_required<F: Format, Select: Extractor>(
+    backend: &Backend<F>,
+    entity: Entity,
+    components: &[Option<SerializedComponent<F>>],
+) -> Result<(), AccessError> {
+    let required = Select::required();
+    let Some(missing) = Select::extract()
+        .into_iter()
+        .zip(components)
+        .find(|(descriptor, component)| component.is_none() && required.contains(&descriptor.name))
+        .map(|(descriptor, _)| descriptor.name)
+    else {
+        return Ok(());
+    };
+
+    if components.iter().all(Option::is_none) && backend.list_components(entity)?.is_empty() {
+        return Err(AccessError::EntityNotFound(entity));
+    }
+
+    Err(AccessError::ComponentNotFound {
+        entity,
+        component: missing,
+    })
 }
 
 impl<F: Format> TypedBackend<F> for Backend<F> {
-    fn get<'a, Select>(&self, entity: Entity) -> Result<Option<Locked<Select>>, BackendError>
+    fn get<Select>(&self, entity: Entity) -> Result<Option<Locked<Select, F>>, BackendError>
     where
-        Select: Extractor + RefCast<'a, Owned = <Select as Extractor>::Owned>,
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
     {
-        let components = Select::from(self.read_components(entity, Select::extract())?)?;
+        self.get_with_ttl(entity, self.lock_ttl_for(&Select::describe()))
+    }
 
-        if let Some(components) = components {
-            let lock = self.acquire_lock(
-                entity,
-                Select::describe(),
-                std::time::Duration::from_secs(3600),
-            )?;
-
-            Ok(Some(Locked::new(
-                DropLock::new(lock, Box::new((*self).clone())),
-                components,
-            )))
-        } else {
-            Ok(None)
+    fn get_required<Select>(&self, entity: Entity) -> Result<Locked<Select, F>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+    {
+        let ttl = self.lock_ttl_for(&Select::describe());
+        match lock_selection(self, entity, ttl, true)? {
+            Some(locked) => Ok(locked),
+            None => Err(AccessError::EntityNotFound(entity))
+                .ctx(format!("reading components of {entity}")),
         }
     }
 
-    fn put<T>(&self, entity: Entity, components: T) -> Result<(), AccessError>
+    fn get_with_ttl<Select>(
+        &self,
+        entity: Entity,
+        ttl: Duration,
+    ) -> Result<Option<Locked<Select, F>>, BackendError>
     where
-        T: Inserter,
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
     {
-        let serialized = components.insert::<F>();
-        self.write_components(entity, serialized)
+        lock_selection(self, entity, ttl, false)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use eci_backend_sqlite::SqliteBackend;
-    use eci_core::{backend::Backend, Component, Entity};
-    use eci_format_json::Json;
-    use serde::{Deserialize, Serialize};
+    fn get_blocking<Select>(
+        &self,
+        entity: Entity,
+        policy: &RetryPolicy,
+    ) -> Result<Option<Locked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+    {
+        retry::retry_conflicts(policy, || self.get(entity))
+    }
 
-    use crate::TypedBackend;
+    fn get_pair<SelA, SelB>(
+        &self,
+        a: Entity,
+        b: Entity,
+    ) -> Result<Option<LockedPair<SelA, SelB, F>>, BackendError>
+    where
+        SelA: Extractor + RefCast<Owned = <SelA as Extractor>::Owned>,
+        SelB: Extractor + RefCast<Owned = <SelB as Extractor>::Owned>,
+    {
+        let (first, second) = (SelA::describe(), SelB::describe());
+        validate_selection(&first)?;
+        validate_selection(&second)?;
+        if a == b {
+            validate_selection(&[first.clone(), second.clone()].concat())?;
+        }
 
-    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
-    struct CounterA(pub usize);
+        // Locking before reading, as in `get`, makes sure nobody writes the
+        // components in between.
+        let ttl = self.lock_ttl_for(&[first.clone(), second.clone()].concat());
+        let expires =
+            lock::expiry(self.now(), ttl).ctx(format!("locking components of {a} and {b}"))?;
+        let lock = DropLock::from_backend(
+            self.acquire_locks(vec![(a, first), (b, second)], ttl)
+                .ctx(format!("locking components of {a} and {b}"))?,
+            self,
+            &[a, b],
+        );
 
-    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
-    struct CounterB(pub usize);
+        let components = migrate::read_components(self, a, SelA::extract())
+            .and_then(|components| SelA::from(a, components, self.limits()))
+            .ctx(format!("reading components of {a}"))?
+            .zip(
+                migrate::read_components(self, b, SelB::extract())
+                    .and_then(|components| SelB::from(b, components, self.limits()))
+                    .ctx(format!("reading components of {b}"))?,
+            );
 
-    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
-    struct CounterC(pub usize);
+        // Dropping the lock releases it again if either entity lacks any of
+        // the components.
+        Ok(components
+            .map(|components| LockedPair::new((a, b), lock, (*self).clone(), expires, components)))
+    }
 
-    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
-    struct StringComponent(pub String);
+    fn get_or_insert_with<Select, Init>(
+        &self,
+        entity: Entity,
+        init: Init,
+    ) -> Result<Locked<Select, F>, BackendError>
+    where
+        Select: Initializer + RefCast<Owned = <Select as Extractor>::Owned>,
+        Init: FnOnce() -> <Select as Extractor>::Owned,
+    {
+        let descriptors = Select::describe();
+        validate_selection(&descriptors)?;
 
-    #[test]
-    fn it_works() {
-        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let ttl = self.lock_ttl_for(&descriptors);
+        let expires =
+            lock::expiry(self.now(), ttl).ctx(format!("locking components of {entity}"))?;
+        let lock = DropLock::from_backend(
+            self.acquire_lock(entity, descriptors, ttl.into())
+                .ctx(format!("locking components of {entity}"))?,
+            self,
+            &[entity],
+        );
 
-        let entity = Entity::new();
+        let read = || {
+            migrate::read_components(self, entity, Select::extract())
+                .and_then(|components| Select::from(entity, components, self.limits()))
+                .ctx(format!("reading components of {entity}"))
+        };
 
-        let inserts = (CounterA(0),);
+        let components = match read()? {
+            Some(components) => components,
+            None => {
+                let initial = Select::initialize::<F>(&init())
+                    .ctx(format!("serializing initial components of {entity}"))?;
 
-        backend.put(entity, inserts).unwrap();
+                let missing = migrate::read_components(self, entity, Select::extract())
+                    .ctx(format!("reading components of {entity}"))?
+                    .into_iter()
+                    .zip(initial)
+                    .filter_map(|(stored, initial)| match stored {
+                        Some(_) => None,
+                        None => initial,
+                    })
+                    .collect();
 
-        let components = backend
-            .get::<(&mut CounterA, &CounterA)>(entity)
-            .unwrap_err();
+                let held = lock.held().ctx(format!("locking components of {entity}"))?;
+                self.write_components_if_absent_locked(entity, missing, held)
+                    .ctx(format!("inserting initial components of {entity}"))?;
 
-        println!("{:?}", components);
+                // The lock keeps anyone else from removing the components
+                // again, but the backend may still have dropped them.
+                match read()? {
+                    Some(components) => components,
+                    None => {
+                        let stored = migrate::read_components(self, entity, Select::extract())
+                            .ctx(format!("reading components of {entity}"))?;
+                        let component = Select::extract()
+                            .into_iter()
+                            .zip(stored)
+                            .find(|(_, stored)| stored.is_none())
+                            .map(|(descriptor, _)| descriptor.name)
+                            .unwrap_or_default();
+                        return Err(AccessError::ComponentNotFound { entity, component })
+                            .ctx(format!("inserting initial components of {entity}"));
+                    }
+                }
+            }
+        };
+
+        Ok(Locked::new(
+            entity,
+            lock,
+            (*self).clone(),
+            expires,
+            ttl,
+            components,
+        ))
     }
 
-    #[test]
-    fn insert_component() {
-        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    fn modify<Select, R>(
+        &self,
+        entity: Entity,
+        func: impl FnOnce(Select::Ref<'_>) -> R,
+    ) -> Result<Option<R>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+    {
+        // Should the closure panic, unwinding drops the lock which releases it.
+        let Some(mut locked) = self.get::<Select>(entity)? else {
+            return Ok(None);
+        };
 
-        // Insert separately
-        let a = Entity::new();
-        backend.put(a, (CounterA(10),)).unwrap();
-        backend
-            .put(a, (StringComponent("Hello".to_string()),))
-            .unwrap();
+        let result = func(locked.deref());
+        locked.commit()?;
+        Ok(Some(result))
+    }
 
-        // Insert collectively
-        let b = Entity::new();
-        backend
-            .put(b, (CounterA(30), StringComponent("Hello".to_string())))
-            .unwrap();
+    fn peek<Select>(&self, entity: Entity) -> Result<Option<Select::Owned>, BackendError>
+    where
+        Select: Extractor + ReadOnly,
+    {
+        if Select::extract().is_empty() {
+            return Err(AccessError::EmptyRequest).ctx(format!("reading components of {entity}"));
+        }
+
+        migrate::read_components(self, entity, Select::extract())
+            .and_then(|components| Select::from(entity, components, self.limits()))
+            .ctx(format!("reading components of {entity}"))
     }
 
-    #[test]
-    fn get_components() {
-        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    fn get_versioned<Select>(
+        &self,
+        entity: Entity,
+    ) -> Result<Option<Versioned<Select::Component>>, BackendError>
+    where
+        Select: VersionedSelection,
+    {
+        let name = Select::Component::COMPONENT_TYPE;
+        let descriptor = ExtractionDescriptor {
+            name: name.to_string(),
+            version: Some(Select::Component::VERSION),
+            schema: Select::Component::schema(),
+        };
 
-        // Insert separately
-        let a = Entity::new();
-        backend.put(a, (CounterA(10),)).unwrap();
-        backend
-            .put(a, (StringComponent("Hello".to_string()),))
-            .unwrap();
+        let context = || format!("reading {name} of {entity} with its revision");
+        let Some(component) = migrate::read_components(self, entity, vec![descriptor])
+            .ctx(context())?
+            .pop()
+            .flatten()
+        else {
+            return Ok(None);
+        };
 
-        {
-            let mut lock = backend.get::<&CounterA>(a).unwrap().unwrap();
-            println!("locked: {:?}", lock.deref());
-        }
+        let Some(metadata) = component.metadata else {
+            return Err(AccessError::Unsupported("reading revisions")).ctx(context());
+        };
 
-        {
-            let mut lock = backend.get::<&CounterA>(a).unwrap().unwrap();
-            println!("locked: {:?}", lock.deref());
-        }
+        let value = deserialize_component::<F, Select::Component>(component, self.limits())
+            .ctx(context())?;
+        Ok(Some(Versioned {
+            value,
+            revision: metadata.revision,
+        }))
+    }
 
-        let mut lock = backend
-            .get::<(&CounterA, &StringComponent)>(a)
-            .unwrap()
-            .unwrap();
+    fn has<T: Component>(&self, entity: Entity) -> Result<bool, AccessError> {
+        let present = self.contains(entity, &[T::COMPONENT_TYPE.to_string()])?;
+        Ok(present.first() == Some(&true))
+    }
 
-        {
-            let reference = &mut lock;
-            assert_eq!(
-                reference.deref(),
-                (&CounterA(10), &StringComponent("Hello".to_string()))
-            );
-        };
+    fn exists(&self, entity: Entity) -> Result<bool, AccessError> {
+        Ok(!self.list_components(entity)?.is_empty())
+    }
 
-        // Insert collectively
-        let b = Entity::new();
-        backend
-            .put(b, (CounterA(30), StringComponent("Hello".to_string())))
-            .unwrap();
-        assert_eq!(
-            backend
+    fn put_resource<T>(&self, value: T) -> Result<(), BackendError>
+    where
+        T: Component + Serialize,
+    {
+        self.update(Entity::RESOURCES, (value,))
+    }
+
+    fn get_resource<Select>(&self) -> Result<Option<Locked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+    {
+        self.get(Entity::RESOURCES)
+    }
+
+    fn peek_resource<Select>(&self) -> Result<Option<Select::Owned>, BackendError>
+    where
+        Select: Extractor + ReadOnly,
+    {
+        self.peek::<Select>(Entity::RESOURCES)
+    }
+
+    fn query<Select>(&self) -> Query<'_, F, Select>
+    where
+        Select: Extractor,
+    {
+        Query::new(self)
+    }
+
+    fn lock_entity(&self, entity: Entity, ttl: Duration) -> Result<DropLock, BackendError> {
+        Ok(DropLock::from_backend(
+            self.acquire_lock(entity, vec![LockDescriptor::entity()], ttl.into())
+                .ctx(format!("locking {entity}"))?,
+            self,
+            &[entity],
+        ))
+    }
+
+    fn put<T>(&self, entity: Entity, components: T) -> Result<InsertReport, AccessError>
+    where
+        T: Inserter,
+    {
+        let serialized = components.insert::<F>();
+        let mut report = InsertReport {
+            entity,
+            components: Vec::with_capacity(serialized.len()),
+        };
+
+        if self.supports_atomic_writes() {
+            let names: Vec<_> = serialized.iter().map(|c| c.name.clone()).collect();
+
+            let conflict = match self.write_components(entity, serialized) {
+                Ok(()) => None,
+                Err(AccessError::Conflict(_, name)) => Some(name),
+                Err(err) => return Err(err),
+            };
+
+            for name in names {
+                let outcome = match &conflict {
+                    None => InsertOutcome::Inserted,
+                    Some(conflict) if *conflict == name => InsertOutcome::Conflict,
+                    Some(_) => InsertOutcome::RolledBack,
+                };
+                report.components.push((name, outcome));
+            }
+        } else {
+            for component in serialized {
+                let name = component.name.clone();
+                let outcome = match self.write_components(entity, vec![component]) {
+                    Ok(()) => InsertOutcome::Inserted,
+                    Err(AccessError::Conflict(..)) => InsertOutcome::Conflict,
+                    Err(err) => return Err(err),
+                };
+                report.components.push((name, outcome));
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn put_many<I, T>(&self, items: I) -> Result<(), AccessError>
+    where
+        I: IntoIterator<Item = (Entity, T)>,
+        T: Inserter,
+    {
+        self.write_components_batch(
+            items
+                .into_iter()
+                .map(|(entity, components)| (entity, components.insert::<F>()))
+                .collect(),
+        )
+    }
+
+    fn put_if_unchanged<T>(
+        &self,
+        entity: Entity,
+        versioned: Versioned<T>,
+    ) -> Result<(), AccessError>
+    where
+        T: Component + Serialize,
+    {
+        let component = SerializedComponent {
+            contents: F::serialize(&versioned.value)?,
+            name: T::COMPONENT_TYPE.to_string(),
+            version: T::VERSION,
+            schema: T::schema(),
+            metadata: None,
+        };
+
+        self.update_component_if_unchanged(entity, component, versioned.revision)
+    }
+
+    fn update<T>(&self, entity: Entity, components: T) -> Result<(), BackendError>
+    where
+        T: Inserter,
+    {
+        let serialized = components.insert::<F>();
+        let descriptors = serialized
+            .iter()
+            .map(|component| LockDescriptor {
+                mode: LockingMode::Write,
+                name: component.name.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let ttl = self.lock_ttl_for(&descriptors);
+        let lock = DropLock::from_backend(
+            self.acquire_lock(entity, descriptors, ttl.into())
+                .ctx(format!("locking components of {entity} for update"))?,
+            self,
+            &[entity],
+        );
+
+        let context = format!("updating components of {entity}");
+        let held = lock.held().ctx(context.clone())?;
+        self.update_components_locked(entity, serialized, held)
+            .ctx(context)?;
+        lock.unlock()
+            .ctx(format!("releasing components of {entity} after update"))
+    }
+
+    fn remove<T>(&self, entity: Entity) -> Result<T::Removed, BackendError>
+    where
+        T: Remover,
+    {
+        let lock = DropLock::from_backend(
+            self.acquire_lock(
+                entity,
+                T::describe(),
+                self.lock_ttl_for(&T::describe()).into(),
+            )
+            .ctx(format!("locking components of {entity} for removal"))?,
+            self,
+            &[entity],
+        );
+
+        let context = format!("removing components of {entity}");
+        let held = lock.held().ctx(context.clone())?;
+        let removed = self
+            .remove_components_locked(entity, T::extract(), held)
+            .and_then(|removed| T::from(removed, self.limits()))
+            .ctx(context)?;
+
+        lock.unlock()
+            .ctx(format!("releasing components of {entity} after removal"))?;
+        Ok(removed)
+    }
+
+    fn for_each<Select, Func>(
+        &self,
+        entities: Vec<Entity>,
+        func: Func,
+    ) -> Result<BatchReport, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+        Func: FnMut(Entity, Select::Ref<'_>),
+    {
+        self.for_each_batched::<Select, Func>(entities, DEFAULT_BATCH_SIZE, func)
+    }
+
+    fn for_each_batched<Select, Func>(
+        &self,
+        entities: Vec<Entity>,
+        batch_size: usize,
+        mut func: Func,
+    ) -> Result<BatchReport, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+        Func: FnMut(Entity, Select::Ref<'_>),
+    {
+        let mut report = BatchReport::default();
+
+        let mut conflicted = Vec::new();
+        for batch in entities.chunks(batch_size.max(1)) {
+            conflicted.extend(batch::visit_batch::<F, Select, Func>(
+                self,
+                batch,
+                &mut report.visited,
+                &mut func,
+            )?);
+        }
+
+        for batch in conflicted.chunks(batch_size.max(1)) {
+            report.skipped.extend(batch::visit_batch::<F, Select, Func>(
+                self,
+                batch,
+                &mut report.visited,
+                &mut func,
+            )?);
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{
+            AccessBackend, AccessError, Backend, BackendError, ComponentStats,
+            DeserializationLimits, Expiry, ExtractionDescriptor, Format, Limit, Lock,
+            LockDescriptor, LockTtl, LockingBackend, LockingError, LockingMode, ManualClock,
+            MigrationRegistry, SerializedComponent,
+        },
+        Component, Entity,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        Extractor, InsertOutcome, InsertReport, Inserter, Locked, ReadOnly, RetryPolicy,
+        TypedBackend,
+    };
+    use eci_core::backend::ResultExt;
+    use std::time::{Duration, SystemTime};
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+    struct CounterA(pub usize);
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+    struct CounterB(pub usize);
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+    struct CounterC(pub usize);
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+    struct StringComponent(pub String);
+
+    #[test]
+    fn it_works() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let entity = Entity::new();
+
+        let inserts = (CounterA(0),);
+
+        backend
+            .put(entity, inserts)
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let components = backend
+            .get::<(&mut CounterA, &CounterA)>(entity)
+            .unwrap_err();
+
+        println!("{:?}", components);
+    }
+
+    #[test]
+    fn reject_conflicting_selection() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(0), CounterB(0)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        match backend.get::<(&CounterB, &mut CounterA, Entity, Option<&CounterA>)>(a) {
+            Err(BackendError::InvalidSelection { component, modes }) => {
+                assert_eq!(component, "CounterA");
+                assert_eq!(modes, vec![LockingMode::Write, LockingMode::Read]);
+            }
+            other => panic!("expected an invalid selection, got {other:?}"),
+        }
+
+        assert!(matches!(
+            backend.get::<(&mut CounterA, &mut CounterA)>(a),
+            Err(BackendError::InvalidSelection { .. })
+        ));
+
+        // Nothing was locked by the rejected selections.
+        backend.get::<&mut CounterA>(a).unwrap().unwrap();
+        backend.get::<(&CounterA, &CounterA)>(a).unwrap().unwrap();
+    }
+
+    #[test]
+    fn lock_entity() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let entity = Entity::new();
+        backend
+            .put(entity, (CounterA(0), CounterB(0)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let lock = backend
+            .lock_entity(entity, Duration::from_secs(60))
+            .unwrap();
+        assert!(matches!(
+            backend
+                .get::<&CounterA>(entity)
+                .as_ref()
+                .map_err(BackendError::root),
+            Err(BackendError::Locking(LockingError::Conflict(..)))
+        ));
+        assert!(matches!(
+            backend
+                .get::<&mut CounterB>(entity)
+                .as_ref()
+                .map_err(BackendError::root),
+            Err(BackendError::Locking(LockingError::Conflict(..)))
+        ));
+
+        lock.unlock().unwrap();
+        let _counter = backend.get::<&CounterA>(entity).unwrap().unwrap();
+        assert!(backend
+            .lock_entity(entity, Duration::from_secs(60))
+            .is_err());
+    }
+
+    #[test]
+    fn permuted_selections_make_progress() {
+        // A `Backend` cannot be sent to another thread, so each thread opens
+        // its own on a shared file.
+        let path = std::env::temp_dir().join(format!("eci-ordering-{}.db", Entity::new()));
+        let entity = Entity::new();
+        Backend::<Json>::from_joint(SqliteBackend::file(&path).unwrap())
+            .put(entity, (CounterA(0), CounterB(0)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let policy = RetryPolicy {
+            max_attempts: 1000,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            jitter: Duration::from_millis(1),
+            deadline: Some(Duration::from_secs(30)),
+        };
+
+        let workers: Vec<_> = (0..2)
+            .map(|worker| {
+                let (path, policy) = (path.clone(), policy.clone());
+                std::thread::spawn(move || {
+                    let backend = Backend::<Json>::from_joint(SqliteBackend::file(path).unwrap());
+                    for _ in 0..50 {
+                        if worker == 0 {
+                            let locked = backend
+                                .get_blocking::<(&mut CounterA, &mut CounterB)>(entity, &policy)
+                                .unwrap();
+                            assert!(locked.is_some());
+                        } else {
+                            let locked = backend
+                                .get_blocking::<(&mut CounterB, &mut CounterA)>(entity, &policy)
+                                .unwrap();
+                            assert!(locked.is_some());
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn get_pair() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let (a, b) = (Entity::new(), Entity::new());
+        backend
+            .put(a, (CounterA(10),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(b, (CounterA(0), CounterB(0)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut pair = backend
+            .get_pair::<&mut CounterA, (&mut CounterA, &CounterB)>(a, b)
+            .unwrap()
+            .unwrap();
+        {
+            let (from, (to, _)) = pair.deref();
+            from.0 -= 3;
+            to.0 += 3;
+        }
+
+        // Both entities are locked until the pair is committed.
+        for entity in [a, b] {
+            match backend
+                .get::<&CounterA>(entity)
+                .as_ref()
+                .map_err(BackendError::root)
+            {
+                Err(BackendError::Locking(LockingError::Conflict(conflicted, ..))) => {
+                    assert_eq!(*conflicted, entity)
+                }
+                other => panic!("expected a conflict, got {other:?}"),
+            }
+        }
+
+        pair.commit().unwrap();
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), Some(CounterA(7)));
+        assert_eq!(backend.peek::<&CounterA>(b).unwrap(), Some(CounterA(3)));
+
+        // Neither entity is locked if one of them conflicts.
+        let held_b = backend.get::<&mut CounterB>(b).unwrap().unwrap();
+        match backend
+            .get_pair::<&mut CounterA, &mut CounterB>(a, b)
+            .as_ref()
+            .map_err(BackendError::root)
+        {
+            Err(BackendError::Locking(LockingError::Conflict(entity, component, ..))) => {
+                assert_eq!((*entity, component.as_str()), (b, "CounterB"));
+            }
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+        let held_a = backend.get::<&mut CounterA>(a).unwrap().unwrap();
+        drop((held_a, held_b));
+
+        // The components are read under the lock, which is released again
+        // if either entity lacks any of them.
+        assert!(backend
+            .get_pair::<&CounterA, &CounterB>(a, a)
+            .unwrap()
+            .is_none());
+        assert!(backend.list_locks(Some(a)).unwrap().is_empty());
+        assert!(matches!(
+            backend.get_pair::<&mut CounterA, &CounterA>(b, b),
+            Err(BackendError::InvalidSelection { .. })
+        ));
+    }
+
+    #[test]
+    fn reject_empty_selection() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+
+        assert!(matches!(
+            backend.get::<Entity>(entity),
+            Err(BackendError::Locking(LockingError::EmptyRequest))
+        ));
+        assert!(matches!(
+            backend.for_each::<Entity, _>(vec![entity], |_, _| {}),
+            Err(BackendError::Locking(LockingError::EmptyRequest))
+        ));
+        assert!(matches!(
+            backend
+                .peek::<Entity>(entity)
+                .as_ref()
+                .map_err(BackendError::root),
+            Err(BackendError::Access(AccessError::EmptyRequest))
+        ));
+    }
+
+    #[test]
+    fn insert_component() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        // Insert separately
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(10),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(a, (StringComponent("Hello".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        // Insert collectively
+        let b = Entity::new();
+        backend
+            .put(b, (CounterA(30), StringComponent("Hello".to_string())))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+    }
+
+    #[test]
+    fn insert_references() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let (a, b) = (Entity::new(), Entity::new());
+        let shared = StringComponent("Shared".to_string());
+
+        backend
+            .put(a, (&shared, CounterA(1)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(b, (&shared,))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put_many([Entity::new(), Entity::new()].map(|entity| (entity, (&shared,))))
+            .unwrap();
+
+        let by_value = Entity::new();
+        backend
+            .put(by_value, (StringComponent("Shared".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let stored = |entity| {
+            backend
+                .read_components(
+                    entity,
+                    vec![ExtractionDescriptor {
+                        name: "StringComponent".to_string(),
+                        version: None,
+                        schema: None,
+                    }],
+                )
+                .unwrap()
+                .pop()
+                .flatten()
+                .unwrap()
+                .contents
+        };
+
+        assert_eq!(stored(a), stored(by_value));
+        assert_eq!(stored(b), stored(by_value));
+        assert_eq!(
+            backend.peek::<(&StringComponent, &CounterA)>(a).unwrap(),
+            Some((shared, CounterA(1)))
+        );
+    }
+
+    /// Wraps sqlite, but writes components one at a time.
+    pub(crate) struct NonAtomic(pub SqliteBackend);
+
+    impl AccessBackend<Json> for NonAtomic {
+        fn write_components(
+            &self,
+            entity: Entity,
+            components: Vec<SerializedComponent<Json>>,
+        ) -> Result<(), AccessError> {
+            for component in components {
+                self.0.write_components(entity, vec![component])?;
+            }
+            Ok(())
+        }
+
+        fn update_components(
+            &self,
+            entity: Entity,
+            components: Vec<SerializedComponent<Json>>,
+        ) -> Result<(), AccessError> {
+            self.0.update_components(entity, components)
+        }
+
+        fn read_components(
+            &self,
+            entity: Entity,
+            descriptors: Vec<ExtractionDescriptor>,
+        ) -> Result<Vec<Option<SerializedComponent<Json>>>, AccessError> {
+            self.0.read_components(entity, descriptors)
+        }
+
+        fn remove_components(
+            &self,
+            entity: Entity,
+            descriptors: Vec<ExtractionDescriptor>,
+        ) -> Result<Vec<Option<SerializedComponent<Json>>>, AccessError> {
+            self.0.remove_components(entity, descriptors)
+        }
+
+        fn entities_with(&self, component: &str) -> Result<Vec<Entity>, AccessError> {
+            AccessBackend::<Json>::entities_with(&self.0, component)
+        }
+
+        fn list_entities(&self) -> Result<Vec<Entity>, AccessError> {
+            AccessBackend::<Json>::list_entities(&self.0)
+        }
+
+        fn list_components(&self, entity: Entity) -> Result<Vec<String>, AccessError> {
+            AccessBackend::<Json>::list_components(&self.0, entity)
+        }
+
+        fn stats(&self) -> Result<Vec<ComponentStats>, AccessError> {
+            AccessBackend::<Json>::stats(&self.0)
+        }
+    }
+
+    /// Wraps sqlite, but fails to release any lock.
+    struct FailingRelease(SqliteBackend);
+
+    impl LockingBackend for FailingRelease {
+        fn acquire_lock(
+            &self,
+            entity: Entity,
+            descriptors: Vec<LockDescriptor>,
+            expires: Expiry,
+        ) -> Result<Lock, LockingError> {
+            self.0.acquire_lock(entity, descriptors, expires)
+        }
+
+        fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+            Err(LockingError::Expired(lock.id()))
+        }
+    }
+
+    /// Wraps sqlite, but lets another thread write to the entity right
+    /// before the first lock is acquired.
+    struct WriteBeforeLocking {
+        locking: SqliteBackend,
+        writer: Mutex<Option<Box<dyn FnOnce() + Send>>>,
+    }
+
+    impl LockingBackend for WriteBeforeLocking {
+        fn acquire_lock(
+            &self,
+            entity: Entity,
+            descriptors: Vec<LockDescriptor>,
+            expires: Expiry,
+        ) -> Result<Lock, LockingError> {
+            if let Some(writer) = self.writer.lock().unwrap().take() {
+                std::thread::spawn(writer).join().unwrap();
+            }
+
+            self.locking.acquire_lock(entity, descriptors, expires)
+        }
+
+        fn release_lock(&self, lock: Lock) -> Result<(), LockingError> {
+            self.locking.release_lock(lock)
+        }
+    }
+
+    #[test]
+    fn lock_before_reading() {
+        let path = std::env::temp_dir().join(format!("eci-race-{}.db", Entity::new()));
+        let entity = Entity::new();
+        let writer = {
+            let path = path.clone();
+            Box::new(move || {
+                let other = Backend::<Json>::from_joint(SqliteBackend::file(path).unwrap());
+                other
+                    .modify::<&mut CounterA, _>(entity, |counter| counter.0 += 1)
+                    .unwrap();
+            })
+        };
+
+        let backend = Backend::<Json>::from_disjoint(
+            SqliteBackend::file(&path).unwrap(),
+            WriteBeforeLocking {
+                locking: SqliteBackend::memory().unwrap(),
+                writer: Mutex::new(Some(writer)),
+            },
+        );
+        backend
+            .put(entity, (CounterA(0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        // Reading before locking would miss the other thread's write, and
+        // committing would then overwrite it.
+        let mut locked = backend.get::<&mut CounterA>(entity).unwrap().unwrap();
+        assert_eq!(locked.deref(), &CounterA(1));
+        locked.deref().0 += 1;
+        locked.commit().unwrap();
+
+        assert_eq!(
+            backend.peek::<&CounterA>(entity).unwrap(),
+            Some(CounterA(2))
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn release_lock_when_components_are_absent() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+
+        assert!(backend.get::<&mut CounterA>(entity).unwrap().is_none());
+        assert!(backend.list_locks(Some(entity)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn release_error_hook() {
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let backend = {
+            let failures = failures.clone();
+            Backend::<Json>::from_disjoint(
+                SqliteBackend::memory().unwrap(),
+                FailingRelease(SqliteBackend::memory().unwrap()),
+            )
+            .on_release_error(move |lock, err| {
+                failures
+                    .lock()
+                    .unwrap()
+                    .push((lock.to_string(), err.to_string()))
+            })
+        };
+
+        let entity = Entity::new();
+        backend
+            .put(entity, (CounterA(0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let locked = backend.get::<&CounterA>(entity).unwrap().unwrap();
+        let id = locked.lock_id();
+        drop(locked);
+
+        assert_eq!(
+            *failures.lock().unwrap(),
+            vec![(id.clone(), LockingError::Expired(id).to_string())]
+        );
+    }
+
+    #[test]
+    fn insert_report() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        assert!(backend.supports_atomic_writes());
+
+        let a = Entity::new();
+        let report = backend.put(a, (CounterB(1),)).unwrap();
+        assert!(report.is_complete());
+
+        let report = backend
+            .put(a, (CounterA(1), CounterB(2), CounterC(3)))
+            .unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(report.conflicts().collect::<Vec<_>>(), vec!["CounterB"]);
+        assert_eq!(
+            report.rolled_back().collect::<Vec<_>>(),
+            vec!["CounterA", "CounterC"]
+        );
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), None);
+
+        // Retry only the components which were rolled back.
+        let report = backend.put(a, (CounterA(1), CounterC(3))).unwrap();
+        assert!(report.is_complete());
+        assert_eq!(
+            backend
+                .peek::<(&CounterA, &CounterB, &CounterC)>(a)
+                .unwrap(),
+            Some((CounterA(1), CounterB(1), CounterC(3)))
+        );
+    }
+
+    #[test]
+    fn insert_report_non_atomic() {
+        let backend = Backend::<Json>::from_disjoint(
+            NonAtomic(SqliteBackend::memory().unwrap()),
+            SqliteBackend::memory().unwrap(),
+        );
+        assert!(!backend.supports_atomic_writes());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterB(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let report = backend
+            .put(a, (CounterA(1), CounterB(2), CounterC(3)))
+            .unwrap();
+        assert_eq!(
+            report.components,
+            vec![
+                ("CounterA".to_string(), InsertOutcome::Inserted),
+                ("CounterB".to_string(), InsertOutcome::Conflict),
+                ("CounterC".to_string(), InsertOutcome::Inserted),
+            ]
+        );
+        assert_eq!(
+            backend
+                .peek::<(&CounterA, &CounterB, &CounterC)>(a)
+                .unwrap(),
+            Some((CounterA(1), CounterB(1), CounterC(3)))
+        );
+    }
+
+    #[test]
+    fn put_many() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let entities: Vec<Entity> = (0..100).map(|_| Entity::new()).collect();
+        backend
+            .put_many(
+                entities
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entity)| (*entity, (CounterA(i), CounterB(i * 2)))),
+            )
+            .unwrap();
+
+        assert_eq!(
+            backend
+                .peek::<(&CounterA, &CounterB)>(entities[10])
+                .unwrap(),
+            Some((CounterA(10), CounterB(20)))
+        );
+
+        // A conflict rolls back the whole batch.
+        let (a, b) = (Entity::new(), Entity::new());
+        match backend.put_many([
+            (a, (CounterA(1),)),
+            (entities[0], (CounterA(1),)),
+            (b, (CounterA(1),)),
+        ]) {
+            Err(AccessError::Conflict(entity, component)) => {
+                assert_eq!(entity, entities[0]);
+                assert_eq!(component, "CounterA");
+            }
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), None);
+        assert_eq!(
+            backend.peek::<&CounterA>(entities[0]).unwrap(),
+            Some(CounterA(0))
+        );
+    }
+
+    #[test]
+    fn get_components() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        // Insert separately
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(10),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(a, (StringComponent("Hello".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        {
+            let mut lock = backend.get::<&CounterA>(a).unwrap().unwrap();
+            println!("locked: {:?}", lock.deref());
+        }
+
+        {
+            let mut lock = backend.get::<&CounterA>(a).unwrap().unwrap();
+            println!("locked: {:?}", lock.deref());
+        }
+
+        let mut lock = backend
+            .get::<(&CounterA, &StringComponent)>(a)
+            .unwrap()
+            .unwrap();
+
+        {
+            let reference = &mut lock;
+            assert_eq!(
+                reference.deref(),
+                (&CounterA(10), &StringComponent("Hello".to_string()))
+            );
+        };
+
+        // Insert collectively
+        let b = Entity::new();
+        backend
+            .put(b, (CounterA(30), StringComponent("Hello".to_string())))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        assert_eq!(
+            backend
                 .get::<(&CounterA, &StringComponent)>(b)
                 .unwrap()
                 .unwrap()
                 .deref(),
-            (&CounterA(30), &StringComponent("Hello".to_string()))
+            (&CounterA(30), &StringComponent("Hello".to_string()))
+        );
+    }
+
+    #[test]
+    fn component_ordering() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(a, (CounterB(2),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(a, (CounterC(3),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        assert_eq!(
+            backend
+                .get::<(&CounterA, &CounterC, &CounterB)>(a)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (&CounterA(1), &CounterC(3), &CounterB(2))
+        );
+    }
+
+    #[test]
+    fn commit_mutable_components() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(2)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend
+            .get::<(&mut CounterA, &CounterB)>(a)
+            .unwrap()
+            .unwrap();
+
+        {
+            let (counter_a, counter_b) = lock.deref();
+            counter_a.0 += counter_b.0;
+        }
+
+        lock.commit().unwrap();
+
+        assert_eq!(
+            backend
+                .get::<(&CounterA, &CounterB)>(a)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (&CounterA(3), &CounterB(2))
+        );
+    }
+
+    #[test]
+    fn write_under_mandatory_locking() {
+        let advisory = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let backend = advisory.clone().with_mandatory_locking();
+
+        let a = Entity::new();
+        advisory
+            .put(a, (CounterA(1), CounterB(2)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        // Writes made under the locks taken for them are let through.
+        backend.update(a, (CounterA(2),)).unwrap();
+        let mut lock = backend.get::<&mut CounterA>(a).unwrap().unwrap();
+        lock.deref().0 += 1;
+        lock.commit().unwrap();
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), Some(CounterA(3)));
+        backend.remove::<(CounterB,)>(a).unwrap();
+
+        let b = Entity::new();
+        let mut lock = backend
+            .get_or_insert_with::<&mut CounterA, _>(b, || CounterA(5))
+            .unwrap();
+        assert_eq!(lock.deref(), &CounterA(5));
+        drop(lock);
+        assert_eq!(backend.peek::<&CounterA>(b).unwrap(), Some(CounterA(5)));
+
+        // Writes made without them are not.
+        assert!(matches!(
+            backend.update_components(a, (CounterA(0),).insert::<Json>()),
+            Err(AccessError::LockRequired(..))
+        ));
+        assert!(matches!(
+            backend.remove_components(a, <(&CounterA,)>::extract()),
+            Err(AccessError::LockRequired(..))
+        ));
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), Some(CounterA(3)));
+    }
+
+    #[test]
+    fn untrusted_mode_limits() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap()).untrusted_mode(
+            DeserializationLimits {
+                max_size: 64,
+                ..Default::default()
+            },
+        );
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), StringComponent("A".repeat(128))))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend.get::<&CounterA>(a).unwrap().unwrap().deref(),
+            &CounterA(1)
+        );
+
+        match backend
+            .get::<(&CounterA, &StringComponent)>(a)
+            .as_ref()
+            .map_err(BackendError::root)
+        {
+            Err(BackendError::Access(AccessError::LimitExceeded { component, limit })) => {
+                assert_eq!(component, "StringComponent");
+                assert_eq!(limit, &Limit::Size(64));
+            }
+            other => panic!("expected size limit to be exceeded, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn extract_entity() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend
+                .get::<(Entity, &CounterA)>(a)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (a, &CounterA(1))
+        );
+
+        let mut lock = backend.get::<(&mut CounterA, Entity)>(a).unwrap().unwrap();
+        let (counter, entity) = lock.deref();
+        assert_eq!(entity, a);
+        counter.0 = 2;
+        lock.commit().unwrap();
+
+        assert_eq!(
+            backend.get::<&CounterA>(a).unwrap().unwrap().deref(),
+            &CounterA(2)
+        );
+
+        // Missing components still abort the extraction.
+        assert!(backend.get::<(Entity, &CounterB)>(a).unwrap().is_none());
+    }
+
+    fn lock_counter(backend: &Backend<Json>, entity: Entity) -> Locked<(&mut CounterA,), Json> {
+        backend.get(entity).unwrap().unwrap()
+    }
+
+    fn count<S>(backend: &Backend<Json>, entities: &[Entity]) -> usize
+    where
+        S: Extractor + ReadOnly,
+    {
+        entities
+            .iter()
+            .filter(|entity| backend.peek::<S>(**entity).unwrap().is_some())
+            .count()
+    }
+
+    #[test]
+    fn public_api() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let (a, b) = (Entity::new(), Entity::new());
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(b, (CounterA(1), CounterB(2)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = lock_counter(&backend, a);
+        lock.deref().0 .0 += 1;
+        lock.commit().unwrap();
+
+        assert_eq!(
+            backend.peek::<(&CounterA,)>(a).unwrap(),
+            Some((CounterA(2),))
+        );
+        assert_eq!(count::<&CounterA>(&backend, &[a, b]), 2);
+        assert_eq!(count::<(&CounterA, &CounterB)>(&backend, &[a, b]), 1);
+    }
+
+    #[test]
+    fn modify_components() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(0), CounterB(1)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        for _ in 0..5 {
+            let result = backend
+                .modify::<(&mut CounterA, &CounterB), _>(a, |(a, b)| {
+                    a.0 += b.0;
+                    a.0
+                })
+                .unwrap();
+            assert!(result.is_some());
+        }
+
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), Some(CounterA(5)));
+        assert_eq!(
+            backend
+                .modify::<&mut CounterC, _>(a, |_| unreachable!())
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn modify_releases_lock_on_panic() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            backend.modify::<&mut CounterA, _>(a, |a| {
+                a.0 = 10;
+                panic!("modification failed");
+            })
+        }));
+        assert!(result.is_err());
+
+        // Nothing was written, and the lock was released.
+        assert_eq!(
+            backend
+                .modify::<&mut CounterA, _>(a, |a| {
+                    a.0 += 1;
+                    a.0
+                })
+                .unwrap(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn peek_write_locked() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(2)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend.get::<&mut CounterA>(a).unwrap().unwrap();
+        lock.deref().0 = 10;
+
+        assert_eq!(
+            backend.peek::<(Entity, &CounterA, &CounterB)>(a).unwrap(),
+            Some((a, CounterA(1), CounterB(2)))
+        );
+
+        lock.commit().unwrap();
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), Some(CounterA(10)));
+        assert_eq!(backend.peek::<&CounterC>(a).unwrap(), None);
+    }
+
+    #[test]
+    fn optional_components() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterC(3)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend
+                .get::<(&CounterA, Option<&CounterB>, Option<&CounterC>)>(a)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (&CounterA(1), None, Some(&CounterC(3)))
+        );
+
+        // A missing required component still aborts the extraction.
+        assert!(backend
+            .get::<(&CounterB, Option<&CounterA>)>(a)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn commit_optional_component() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(1)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let b = Entity::new();
+        backend
+            .put(b, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        for entity in [a, b] {
+            let mut lock = backend
+                .get::<(&CounterA, Option<&mut CounterB>)>(entity)
+                .unwrap()
+                .unwrap();
+
+            if let (counter_a, Some(counter_b)) = lock.deref() {
+                counter_b.0 += counter_a.0;
+            }
+
+            lock.commit().unwrap();
+        }
+
+        assert_eq!(
+            backend
+                .get::<(&CounterA, Option<&CounterB>)>(a)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (&CounterA(1), Some(&CounterB(2)))
+        );
+
+        assert_eq!(
+            backend
+                .get::<(&CounterA, Option<&CounterB>)>(b)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (&CounterA(1), None)
+        );
+    }
+
+    #[test]
+    fn lock_ttl() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap())
+            .with_lock_ttl(Duration::from_secs(1));
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let held = backend.get::<&mut CounterA>(a).unwrap().unwrap();
+        assert!(held.time_remaining() <= Duration::from_secs(1));
+        assert!(backend.get::<&mut CounterA>(a).is_err());
+
+        std::thread::sleep(Duration::from_millis(1100));
+
+        let longer = backend
+            .get_with_ttl::<&mut CounterA>(a, Duration::from_secs(60))
+            .unwrap()
+            .unwrap();
+        assert!(longer.time_remaining() > Duration::from_secs(1));
+    }
+
+    #[test]
+    fn lock_ttl_out_of_range() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let out_of_range = |result: &Result<(), BackendError>| {
+            matches!(
+                result.as_ref().map_err(BackendError::root),
+                Err(BackendError::Locking(LockingError::ExpiryOutOfRange(
+                    Duration::MAX
+                )))
+            )
+        };
+        assert!(out_of_range(
+            &backend
+                .get_with_ttl::<&mut CounterA>(a, Duration::MAX)
+                .map(|_| ())
+        ));
+
+        let forever = backend.clone().with_lock_ttl(Duration::MAX);
+        assert!(out_of_range(
+            &forever
+                .get_pair::<&mut CounterA, &mut CounterA>(a, Entity::new())
+                .map(|_| ())
+        ));
+        assert!(out_of_range(
+            &forever
+                .get_or_insert_with::<&mut CounterA, _>(a, || CounterA(0))
+                .map(|_| ())
+        ));
+
+        let mut lock = backend.get::<&mut CounterA>(a).unwrap().unwrap();
+        assert!(out_of_range(&lock.renew(Duration::MAX)));
+        lock.deref().0 = 2;
+        lock.commit().unwrap();
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), Some(CounterA(2)));
+    }
+
+    #[test]
+    fn renew_locked() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend
+            .get_with_ttl::<&mut CounterA>(a, Duration::from_millis(300))
+            .unwrap()
+            .unwrap();
+
+        lock.renew(Duration::from_secs(2)).unwrap();
+        assert!(lock.time_remaining() > Duration::from_secs(1));
+
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(matches!(
+            backend
+                .get::<&CounterA>(a)
+                .as_ref()
+                .map_err(BackendError::root),
+            Err(BackendError::Locking(LockingError::Conflict(..)))
+        ));
+
+        lock.deref().0 = 2;
+        lock.commit().unwrap();
+        assert_eq!(backend.peek::<&CounterA>(a).unwrap(), Some(CounterA(2)));
+
+        let mut lock = backend
+            .get_with_ttl::<&mut CounterA>(a, Duration::from_millis(300))
+            .unwrap()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+
+        let err = lock.renew(Duration::from_secs(2)).unwrap_err();
+        assert_eq!(
+            err.contexts(),
+            vec![format!("renewing lock on components of {a}")]
+        );
+        assert!(matches!(
+            err.root(),
+            BackendError::Locking(LockingError::Expired(id)) if *id == lock.lock_id()
+        ));
+    }
+
+    #[test]
+    fn upgrade_lock() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(2)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend.get::<(&CounterA, &CounterB)>(a).unwrap().unwrap();
+        let other = backend.get::<&CounterA>(a).unwrap().unwrap();
+
+        let err = lock.upgrade::<CounterA>().unwrap_err();
+        assert_eq!(
+            err.contexts(),
+            vec![format!("upgrading lock on {a}'s CounterA")]
+        );
+        assert!(matches!(
+            err.root(),
+            BackendError::Locking(LockingError::Conflict(_, component, LockingMode::Write, _))
+                if component == "CounterA"
+        ));
+
+        // Writing requires a write lock.
+        assert!(matches!(
+            lock.write(&CounterA(5)).map_err(|err| err.root().to_string()),
+            Err(message) if message.contains("CounterA")
+        ));
+
+        drop(other);
+        let mut counter = lock.upgrade::<CounterA>().unwrap().unwrap();
+
+        // Other readers are now kept out, while the rest stays read-locked.
+        assert!(backend.get::<&CounterA>(a).is_err());
+        assert!(backend.get::<&CounterB>(a).unwrap().is_some());
+        assert!(backend.get::<&mut CounterB>(a).is_err());
+
+        counter.0 += 1;
+        lock.write(&counter).unwrap();
+        lock.unlock().unwrap();
+
+        assert_eq!(
+            backend.peek::<(&CounterA, &CounterB)>(a).unwrap(),
+            Some((CounterA(2), CounterB(2)))
+        );
+    }
+
+    #[test]
+    fn adaptive_lock_ttl() {
+        let clock = ManualClock::default();
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap())
+            .with_clock(clock.clone())
+            .with_lock_ttl(LockTtl::Adaptive {
+                multiplier: 2.0,
+                floor: Duration::from_secs(10),
+                ceiling: Duration::from_secs(100),
+            });
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(2)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        // Holds a lock for the given duration, returning the TTL it was given.
+        let hold = |duration: Duration| {
+            let lock = backend.get::<&mut CounterA>(a).unwrap().unwrap();
+            let ttl = lock.ttl();
+            clock.advance(duration);
+            lock.unlock().unwrap();
+            ttl
+        };
+
+        // Without any history, the floor is used.
+        assert_eq!(hold(Duration::from_secs(20)), Duration::from_secs(10));
+
+        // Below 100 samples, the 99th percentile is the slowest hold.
+        for _ in 0..99 {
+            assert_eq!(hold(Duration::from_secs(1)), Duration::from_secs(40));
+        }
+        assert_eq!(hold(Duration::from_secs(1)), Duration::from_secs(10));
+
+        // Other sets of components keep their own history.
+        let hold = |duration: Duration| {
+            let lock = backend.get::<&mut CounterB>(a).unwrap().unwrap();
+            let ttl = lock.ttl();
+            clock.advance(duration);
+            lock.unlock().unwrap();
+            ttl
+        };
+
+        assert_eq!(hold(Duration::from_secs(60)), Duration::from_secs(10));
+        assert_eq!(hold(Duration::from_secs(60)), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn locked_metadata() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let before = SystemTime::now();
+        let lock = backend
+            .get_with_ttl::<&CounterA>(a, Duration::from_secs(60))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(lock.entity(), a);
+        assert_eq!(lock.ttl(), Duration::from_secs(60));
+        assert!(lock.expires_at() >= before + Duration::from_secs(60));
+        assert!(lock.expires_at() <= SystemTime::now() + Duration::from_secs(60));
+
+        let other = backend.get::<&CounterA>(a).unwrap().unwrap();
+        assert_eq!(lock.lock_id().len(), 36);
+        assert_ne!(lock.lock_id(), other.lock_id());
+    }
+
+    #[test]
+    fn commit_expired_lock() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut lock = backend
+            .get_with_ttl::<&mut CounterA>(a, Duration::from_millis(10))
+            .unwrap()
+            .unwrap();
+        lock.deref().0 = 2;
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(matches!(
+            lock.commit().as_ref().map_err(BackendError::root),
+            Err(BackendError::Locking(LockingError::Expired(_)))
+        ));
+
+        assert_eq!(
+            backend.get::<&CounterA>(a).unwrap().unwrap().deref(),
+            &CounterA(1)
+        );
+    }
+
+    #[test]
+    fn update_component() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(2), CounterC(3)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        backend.update(a, (CounterB(20),)).unwrap();
+
+        assert_eq!(
+            backend
+                .get::<(&CounterA, &CounterB, &CounterC)>(a)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (&CounterA(1), &CounterB(20), &CounterC(3))
         );
     }
 
     #[test]
-    fn component_ordering() {
+    fn fail_update_locked_component() {
         let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
 
         let a = Entity::new();
-        backend.put(a, (CounterA(1),)).unwrap();
-        backend.put(a, (CounterB(2),)).unwrap();
-        backend.put(a, (CounterC(3),)).unwrap();
+        backend
+            .put(a, (CounterA(1), CounterB(2)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let lock = backend.get::<&CounterB>(a).unwrap().unwrap();
+
+        assert!(matches!(
+            backend
+                .update(a, (CounterB(20),))
+                .as_ref()
+                .map_err(BackendError::root),
+            Err(BackendError::Locking(LockingError::Conflict(..)))
+        ));
+
+        backend.update(a, (CounterA(10),)).unwrap();
+        lock.unlock().unwrap();
+        backend.update(a, (CounterB(20),)).unwrap();
+
         assert_eq!(
             backend
-                .get::<(&CounterA, &CounterC, &CounterB)>(a)
+                .get::<(&CounterA, &CounterB)>(a)
                 .unwrap()
                 .unwrap()
                 .deref(),
-            (&CounterA(1), &CounterC(3), &CounterB(2))
+            (&CounterA(10), &CounterB(20))
+        );
+    }
+
+    #[test]
+    fn batched_iteration() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let entities: Vec<_> = (0..1000)
+            .map(|i| {
+                let entity = Entity::new();
+                backend
+                    .put(entity, (CounterA(i),))
+                    .and_then(InsertReport::into_result)
+                    .unwrap();
+                entity
+            })
+            .collect();
+
+        let missing = Entity::new();
+        let locked = entities[500];
+        let held = backend.get::<&mut CounterA>(locked).unwrap().unwrap();
+
+        let mut all = entities.clone();
+        all.push(missing);
+
+        let report = backend
+            .for_each::<&mut CounterA, _>(all, |_, counter| counter.0 += 1)
+            .unwrap();
+
+        assert_eq!(report.visited.len(), 999);
+        assert_eq!(report.skipped, vec![locked]);
+        drop(held);
+
+        let mut total = 0;
+        let report = backend
+            .for_each_batched::<&CounterA, _>(entities, 100, |_, counter| total += counter.0)
+            .unwrap();
+
+        assert_eq!(report.visited.len(), 1000);
+        assert_eq!(total, (0..1000).sum::<usize>() + 999);
+    }
+
+    #[test]
+    fn batched_iteration_retries_conflicts() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let (a, b) = (Entity::new(), Entity::new());
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(b, (CounterA(2),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        // b is locked while visiting the first batch, but released before
+        // the conflicting entities are retried.
+        let mut held = backend.get::<&mut CounterA>(b).unwrap();
+
+        let report = backend
+            .for_each_batched::<&CounterA, _>(vec![a, b], 1, |_, _| {
+                held.take();
+            })
+            .unwrap();
+
+        assert_eq!(report.visited, vec![a, b]);
+        assert!(report.skipped.is_empty());
+    }
+
+    #[test]
+    fn remove_components() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1), CounterB(2)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend.remove::<(CounterA, CounterC)>(a).unwrap(),
+            (Some(CounterA(1)), None)
+        );
+
+        assert!(backend.get::<&CounterA>(a).unwrap().is_none());
+        assert_eq!(
+            backend.get::<&CounterB>(a).unwrap().unwrap().deref(),
+            &CounterB(2)
+        );
+
+        assert_eq!(backend.remove::<(CounterA,)>(a).unwrap(), (None,));
+    }
+
+    #[test]
+    fn fail_remove_locked_component() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let lock = backend.get::<&mut CounterA>(a).unwrap().unwrap();
+        assert!(matches!(
+            backend
+                .remove::<(CounterA,)>(a)
+                .as_ref()
+                .map_err(BackendError::root),
+            Err(BackendError::Locking(LockingError::Conflict(..)))
+        ));
+
+        lock.unlock().unwrap();
+        assert_eq!(
+            backend.remove::<(CounterA,)>(a).unwrap(),
+            (Some(CounterA(1)),)
+        );
+    }
+
+    #[test]
+    fn error_context() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (CounterA(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let _lock = backend.get::<&mut CounterA>(a).unwrap().unwrap();
+
+        let err = backend
+            .update(a, (CounterA(2),))
+            .ctx("resetting counters")
+            .unwrap_err();
+
+        assert_eq!(
+            err.contexts(),
+            vec![
+                "resetting counters".to_string(),
+                format!("locking components of {a} for update"),
+            ]
+        );
+        assert!(matches!(
+            err.root(),
+            BackendError::Locking(LockingError::Conflict(..))
+        ));
+        assert!(err.to_string().starts_with(&format!(
+            "resetting counters: locking components of {a} for update: \
+            locking error write lock requested on {a}'s CounterA but write lock {} held until ",
+            _lock.lock_id()
+        )));
+    }
+
+    #[test]
+    fn deserialization_error_context() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (StringComponent("Hello".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct StringComponentAsNumber(pub usize);
+
+        impl Component for StringComponentAsNumber {
+            const COMPONENT_TYPE: &'static str = "StringComponent";
+        }
+
+        let err = backend.get::<&StringComponentAsNumber>(a).unwrap_err();
+
+        assert_eq!(err.contexts(), vec![format!("reading components of {a}")]);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "reading components of {a}: access error error during serialization: \
+                deserializing StringComponent: invalid type: string \"Hello\", \
+                expected usize at line 1 column 7"
+            )
+        );
+    }
+
+    /// The same component as written by an older and a newer release.
+    mod v1 {
+        use eci_core::Component;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+        #[component(version = "1.0.0")]
+        pub struct Position(pub f32, pub f32);
+    }
+
+    mod v2 {
+        use eci_core::Component;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+        #[component(version = "2.0.0")]
+        pub struct Position {
+            pub x: f32,
+            pub y: f32,
+        }
+    }
+
+    #[test]
+    fn reject_older_component_version() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+        backend
+            .put(entity, (v1::Position(1.0, 2.0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend.peek::<&v1::Position>(entity).unwrap(),
+            Some(v1::Position(1.0, 2.0))
+        );
+
+        match backend
+            .peek::<&v2::Position>(entity)
+            .as_ref()
+            .map_err(BackendError::root)
+        {
+            Err(BackendError::Access(AccessError::VersionMismatch {
+                component,
+                stored,
+                expected,
+            })) => {
+                assert_eq!(component, "Position");
+                assert_eq!(*stored, v1::Position::VERSION);
+                assert_eq!(*expected, v2::Position::VERSION);
+            }
+            other => panic!("expected a version mismatch, got {other:?}"),
+        }
+    }
+
+    /// The v1 position, after its type was renamed.
+    mod renamed {
+        use eci_core::Component;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+        #[component(name = "Position", version = "1.0.0")]
+        pub struct Location(pub f32, pub f32);
+    }
+
+    #[test]
+    fn read_renamed_component() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+        backend
+            .put(entity, (v1::Position(1.0, 2.0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut location = backend
+            .get::<&mut renamed::Location>(entity)
+            .unwrap()
+            .unwrap();
+        assert_eq!(location.deref(), &mut renamed::Location(1.0, 2.0));
+        location.deref().0 = 3.0;
+        location.commit().unwrap();
+
+        assert_eq!(
+            backend.peek::<&v1::Position>(entity).unwrap(),
+            Some(v1::Position(3.0, 2.0))
+        );
+        assert_eq!(
+            backend.list_components(entity).unwrap(),
+            vec!["Position".to_string()]
+        );
+    }
+
+    #[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+    #[component(name = "Tagged")]
+    struct Tagged<T>(T);
+
+    #[test]
+    fn store_generic_component() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+        backend
+            .put(entity, (Tagged(5u32),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let mut tagged = backend.get::<&mut Tagged<u32>>(entity).unwrap().unwrap();
+        tagged.deref().0 += 1;
+        tagged.commit().unwrap();
+
+        assert_eq!(
+            backend.peek::<&Tagged<u32>>(entity).unwrap(),
+            Some(Tagged(6))
+        );
+        assert_eq!(
+            backend.list_components(entity).unwrap(),
+            vec!["Tagged".to_string()]
+        );
+    }
+
+    mod v3 {
+        use eci_core::Component;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Component, Deserialize, Serialize, PartialEq)]
+        #[component(version = "3.0.0")]
+        pub struct Position {
+            pub left: f32,
+            pub top: f32,
+        }
+    }
+
+    /// Upgrades positions from the tuple layout through to the one with
+    /// renamed fields.
+    fn position_migrations() -> MigrationRegistry {
+        MigrationRegistry::new()
+            .register(v1::Position::VERSION, |value| {
+                let v1::Position(x, y) =
+                    serde_json::from_value(value).map_err(AccessError::serialization)?;
+                Ok(v2::Position { x, y })
+            })
+            .register(v2::Position::VERSION, |value| {
+                let v2::Position { x, y } =
+                    serde_json::from_value(value).map_err(AccessError::serialization)?;
+                Ok(v3::Position { left: x, top: y })
+            })
+    }
+
+    fn stored_position(backend: &Backend<Json>, entity: Entity) -> SerializedComponent<Json> {
+        backend
+            .read_components(
+                entity,
+                vec![ExtractionDescriptor {
+                    name: "Position".to_string(),
+                    version: None,
+                    schema: None,
+                }],
+            )
+            .unwrap()
+            .pop()
+            .flatten()
+            .unwrap()
+    }
+
+    #[test]
+    fn migrate_through_chained_versions() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap())
+            .with_migrations(position_migrations());
+        let entity = Entity::new();
+        backend
+            .put(entity, (v1::Position(1.0, 2.0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend.peek::<&v3::Position>(entity).unwrap(),
+            Some(v3::Position {
+                left: 1.0,
+                top: 2.0
+            })
+        );
+        assert_eq!(
+            backend.peek::<&v2::Position>(entity).unwrap(),
+            Some(v2::Position { x: 1.0, y: 2.0 })
+        );
+
+        // Without write-back, the stored component is left as it was.
+        assert_eq!(
+            stored_position(&backend, entity).version,
+            v1::Position::VERSION
+        );
+    }
+
+    #[test]
+    fn write_back_migrated_components() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap())
+            .with_migrations(position_migrations().write_back(true));
+        let entity = Entity::new();
+        backend
+            .put(entity, (v1::Position(1.0, 2.0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        assert_eq!(
+            backend.peek::<&v3::Position>(entity).unwrap(),
+            Some(v3::Position {
+                left: 1.0,
+                top: 2.0
+            })
         );
+
+        let stored = stored_position(&backend, entity);
+        assert_eq!(stored.version, v3::Position::VERSION);
+        assert_eq!(
+            Json::deserialize::<v3::Position>(&stored.contents).unwrap(),
+            v3::Position {
+                left: 1.0,
+                top: 2.0
+            }
+        );
+
+        // There is no way back down, so the older layout can no longer be read.
+        assert!(backend.peek::<&v1::Position>(entity).is_err());
     }
 }
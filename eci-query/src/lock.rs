@@ -1,12 +1,31 @@
-use eci_core::backend::{Lock, LockingBackend, LockingError};
-use std::fmt::Debug;
+use eci_core::backend::{
+    AccessBackend, Backend, BackendError, ExtractionDescriptor, Format, Lock, LockingBackend,
+    LockingError, LockingMode, ReleaseErrorHook, ResultExt, SerializedComponent,
+};
+use eci_core::{Component, Entity};
+use log::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::Debug,
+    time::{Duration, SystemTime},
+};
 
 use crate::{refcast::RefCast, Extractor};
 
+/// Point in time at which a lock held for `ttl` from `now` expires, failing
+/// if it lies too far into the future to represent.
+pub(crate) fn expiry(now: SystemTime, ttl: Duration) -> Result<SystemTime, LockingError> {
+    now.checked_add(ttl)
+        .ok_or(LockingError::ExpiryOutOfRange(ttl))
+}
+
 /// Automatically releases the contained lock upon Drop
-pub(crate) struct DropLock {
+pub struct DropLock {
     lock: Option<Lock>,
     backend: Box<dyn LockingBackend>,
+    /// Entities covered by the lock, for reporting failed releases.
+    entities: Vec<Entity>,
+    on_release_error: Option<ReleaseErrorHook>,
 }
 
 impl DropLock {
@@ -14,6 +33,50 @@ impl DropLock {
         DropLock {
             lock: Some(lock),
             backend,
+            entities: Vec::new(),
+            on_release_error: None,
+        }
+    }
+
+    /// Releases the lock through the backend it was acquired from, reporting
+    /// failures to release it upon drop to the backend's release error hook.
+    pub(crate) fn from_backend<F: Format>(
+        lock: Lock,
+        backend: &Backend<F>,
+        entities: &[Entity],
+    ) -> Self {
+        DropLock {
+            lock: Some(lock),
+            backend: Box::new(backend.clone()),
+            entities: entities.to_vec(),
+            on_release_error: backend.release_error_hook().cloned(),
+        }
+    }
+
+    pub fn id(&self) -> Option<String> {
+        self.lock.as_ref().map(Lock::id)
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.lock.as_ref().and_then(Lock::expires_at)
+    }
+
+    /// The lock, unless it has already been released.
+    pub(crate) fn held(&self) -> Result<&Lock, LockingError> {
+        self.lock.as_ref().ok_or(LockingError::Released)
+    }
+
+    pub fn renew(&self, extend_by: Duration) -> Result<(), LockingError> {
+        match &self.lock {
+            Some(lock) => self.backend.renew_lock(lock, extend_by),
+            None => Err(LockingError::Released),
+        }
+    }
+
+    pub fn upgrade(&self, entity: Entity, component: String) -> Result<(), LockingError> {
+        match &self.lock {
+            Some(lock) => self.backend.upgrade_lock(lock, entity, component),
+            None => Err(LockingError::Released),
         }
     }
 
@@ -29,8 +92,20 @@ impl DropLock {
 impl Drop for DropLock {
     fn drop(&mut self) {
         if let Some(lock) = self.lock.take() {
-            println!("dropped lock: {lock}");
-            self.backend.release_lock(lock).ok();
+            let id = lock.id();
+            debug!("releasing dropped lock {id}");
+
+            if let Err(err) = self.backend.release_lock(lock) {
+                let entities: Vec<_> = self.entities.iter().map(Entity::to_string).collect();
+                error!(
+                    "failed to release dropped lock {id} on {}: {err}",
+                    entities.join(", ")
+                );
+
+                if let Some(hook) = &self.on_release_error {
+                    hook(&id, &err);
+                }
+            }
         }
     }
 }
@@ -44,22 +119,47 @@ impl Debug for DropLock {
 }
 
 /// Represents access to a locked resource.
-pub struct Locked<T>
+pub struct Locked<T, F>
 where
     T: Extractor,
+    F: Format,
 {
+    entity: Entity,
     lock: DropLock,
+    backend: Backend<F>,
+    expires: SystemTime,
+    ttl: Duration,
+    /// Components held under a write lock, including upgraded ones.
+    writable: Vec<String>,
     inner: <T as Extractor>::Owned,
 }
 
-impl<'a, T> Locked<T>
+impl<T, F> Locked<T, F>
 where
     T: Extractor,
-    T: RefCast<'a, Owned = <T as Extractor>::Owned>,
+    T: RefCast<Owned = <T as Extractor>::Owned>,
+    F: Format,
 {
-    pub(crate) fn new(lock: DropLock, components: <T as Extractor>::Owned) -> Self {
+    pub(crate) fn new(
+        entity: Entity,
+        lock: DropLock,
+        backend: Backend<F>,
+        expires: SystemTime,
+        ttl: Duration,
+        components: <T as Extractor>::Owned,
+    ) -> Self {
         Locked {
+            entity,
+            // Prefer the expiry reported by the locking backend, if any.
+            expires: lock.expires_at().unwrap_or(expires),
             lock,
+            backend,
+            ttl,
+            writable: T::describe()
+                .into_iter()
+                .filter(|descriptor| descriptor.mode == LockingMode::Write)
+                .map(|descriptor| descriptor.name)
+                .collect(),
             inner: components,
         }
     }
@@ -68,20 +168,196 @@ where
         self.lock.unlock()
     }
 
-    pub fn deref(&'a mut self) -> T {
+    /// The entity whose components are locked.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Identifies the lock, for instance in logs.
+    pub fn lock_id(&self) -> String {
+        self.lock.id().unwrap_or_default()
+    }
+
+    /// Point in time at which the lock expires, at the earliest.
+    pub fn expires_at(&self) -> SystemTime {
+        self.expires
+    }
+
+    /// Duration the lock was acquired for.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Time left before the lock expires, or zero if it already has.
+    pub fn time_remaining(&self) -> Duration {
+        self.expires
+            .duration_since(self.backend.now())
+            .unwrap_or_default()
+    }
+
+    /// Extends the lock so it expires `extend_by` from now, for work which
+    /// takes longer than the lock was acquired for. Fails with
+    /// [`LockingError::Expired`] if the lock has already expired, in which
+    /// case the locked components may have been changed by someone else.
+    pub fn renew(&mut self, extend_by: Duration) -> Result<(), BackendError> {
+        let entity = self.entity;
+        let context = || format!("renewing lock on components of {entity}");
+        let expires = expiry(self.backend.now(), extend_by).ctx(context())?;
+        self.lock.renew(extend_by).ctx(context())?;
+
+        self.expires = expires;
+        Ok(())
+    }
+
+    /// Upgrades the read lock on `T` to a write lock without releasing it,
+    /// failing with [`LockingError::Conflict`] if anyone else holds a lock
+    /// on it. Returns the component as currently stored, which differs from
+    /// the copy read when locking only if it was written without a lock.
+    /// Changes to it can be written with [`Locked::write`].
+    pub fn upgrade<C>(&mut self) -> Result<Option<C>, BackendError>
+    where
+        C: Component + DeserializeOwned,
+    {
+        let entity = self.entity;
+        let context = format!("upgrading lock on {entity}'s {}", C::COMPONENT_TYPE);
+        if self.backend.now() >= self.expires {
+            return Err(LockingError::Expired(self.lock.id().unwrap_or_default())).ctx(context);
+        }
+
+        self.lock
+            .upgrade(entity, C::COMPONENT_TYPE.to_string())
+            .ctx(context.clone())?;
+        self.writable.push(C::COMPONENT_TYPE.to_string());
+
+        crate::migrate::read_components(
+            &self.backend,
+            entity,
+            vec![ExtractionDescriptor {
+                name: C::COMPONENT_TYPE.to_string(),
+                version: Some(C::VERSION),
+                schema: C::schema(),
+            }],
+        )
+        .and_then(|mut components| {
+            components
+                .pop()
+                .flatten()
+                .map(|component| {
+                    crate::deserialize_component::<F, C>(component, self.backend.limits())
+                })
+                .transpose()
+        })
+        .ctx(context)
+    }
+
+    /// Immediately writes a component which is held under a write lock,
+    /// such as one which was upgraded.
+    pub fn write<C>(&self, component: &C) -> Result<(), BackendError>
+    where
+        C: Component + Serialize,
+    {
+        let entity = self.entity;
+        let context = format!("writing {entity}'s {}", C::COMPONENT_TYPE);
+        if !self.writable.iter().any(|name| name == C::COMPONENT_TYPE) {
+            return Err(BackendError::InvalidSelection {
+                component: C::COMPONENT_TYPE.to_string(),
+                modes: vec![LockingMode::Read],
+            })
+            .ctx(context);
+        }
+
+        if self.backend.now() >= self.expires {
+            return Err(LockingError::Expired(self.lock.id().unwrap_or_default())).ctx(context);
+        }
+
+        let lock = self.lock.held().ctx(context.clone())?;
+        F::serialize(component)
+            .and_then(|contents| {
+                self.backend.update_components_locked(
+                    entity,
+                    vec![SerializedComponent {
+                        contents,
+                        name: C::COMPONENT_TYPE.to_string(),
+                        version: C::VERSION,
+                        schema: C::schema(),
+                        metadata: None,
+                    }],
+                    lock,
+                )
+            })
+            .ctx(context)
+    }
+
+    /// Writes the mutably locked components back to the backend and
+    /// releases the lock. Fails without writing if the lock has expired.
+    pub fn commit(self) -> Result<(), BackendError> {
+        let entity = self.entity;
+        if self.backend.now() >= self.expires {
+            return Err(LockingError::Expired(self.lock.id().unwrap_or_default()))
+                .ctx(format!("committing components of {entity}"));
+        }
+
+        let components =
+            T::serialize::<F>(&self.inner).ctx(format!("serializing components of {entity}"))?;
+        if !components.is_empty() {
+            let context = format!("committing components of {entity}");
+            let lock = self.lock.held().ctx(context.clone())?;
+            self.backend
+                .update_components_locked(entity, components, lock)
+                .ctx(context)?;
+        }
+
+        self.lock
+            .unlock()
+            .ctx(format!("releasing components of {entity} after commit"))
+    }
+
+    pub fn deref(&mut self) -> T::Ref<'_> {
         <T as RefCast>::refcast(&mut self.inner)
     }
 }
 
-impl<T> Debug for Locked<T>
+impl<T, F> Debug for Locked<T, F>
 where
     T: Extractor,
     <T as Extractor>::Owned: Debug,
+    F: Format,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Locked")
+            .field("entity", &self.entity)
             .field("lock", &self.lock)
+            .field("expires", &self.expires)
+            .field("ttl", &self.ttl)
             .field("inner", &self.inner)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use eci_backend_memory::MemoryBackend;
+    use eci_core::{
+        backend::{Lock, LockingError},
+        Entity,
+    };
+    use std::time::Duration;
+
+    use super::DropLock;
+
+    #[test]
+    fn released_lock_is_reported_as_such() {
+        let mut lock = DropLock::new(Lock::new(), Box::new(MemoryBackend::new()));
+        lock.lock = None;
+
+        assert!(matches!(lock.held(), Err(LockingError::Released)));
+        assert!(matches!(
+            lock.renew(Duration::from_secs(1)),
+            Err(LockingError::Released)
+        ));
+        assert!(matches!(
+            lock.upgrade(Entity::new(), "A".to_string()),
+            Err(LockingError::Released)
+        ));
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet},
+    hash::{Hash, Hasher},
+};
+
+use eci_core::{
+    backend::{
+        AccessBackend, Backend, BackendError, ExtractionDescriptor, Format, ResultExt,
+        SerializedComponent,
+    },
+    Entity, Version,
+};
+
+/// All three versions of a component which was changed differently in
+/// both copies of a world. `None` means the component was absent.
+pub struct MergeConflict<'a, F: Format> {
+    pub entity: Entity,
+    pub component: &'a str,
+    pub base: Option<&'a F::Data>,
+    pub ours: Option<&'a F::Data>,
+    pub theirs: Option<&'a F::Data>,
+}
+
+/// Decides the merged value of a conflicting component, or `None` to leave
+/// it out of the merged world.
+pub type MergeCallback<'a, F> =
+    Box<dyn FnMut(MergeConflict<'_, F>) -> Option<<F as Format>::Data> + 'a>;
+
+/// How to resolve components which were changed differently in both copies.
+pub enum MergeStrategy<'a, F: Format> {
+    TakeOurs,
+    TakeTheirs,
+    Callback(MergeCallback<'a, F>),
+}
+
+/// Which version of a component ended up in the merged world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// Neither copy changed the component.
+    Unchanged,
+    /// Both copies made the same change.
+    Agreed,
+    /// Only our copy changed the component.
+    Ours,
+    /// Only their copy changed the component.
+    Theirs,
+    /// Both copies changed the component differently, and ours was kept.
+    ConflictOurs,
+    /// Both copies changed the component differently, and theirs was kept.
+    ConflictTheirs,
+    /// Both copies changed the component differently, and the callback
+    /// decided the merged value.
+    ConflictResolved,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeDecision {
+    pub entity: Entity,
+    pub component: String,
+    pub outcome: MergeOutcome,
+    /// Whether the component is present in the merged world.
+    pub present: bool,
+}
+
+/// Every decision taken during a merge, ordered by entity and component.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub decisions: Vec<MergeDecision>,
+}
+
+impl MergeReport {
+    pub fn conflicts(&self) -> impl Iterator<Item = &MergeDecision> {
+        self.decisions.iter().filter(|decision| {
+            matches!(
+                decision.outcome,
+                MergeOutcome::ConflictOurs
+                    | MergeOutcome::ConflictTheirs
+                    | MergeOutcome::ConflictResolved
+            )
+        })
+    }
+}
+
+fn content_hash<F: Format>(component: Option<&F::Data>) -> Option<u64> {
+    component.map(|data| {
+        let mut hasher = DefaultHasher::new();
+        data.as_ref().hash(&mut hasher);
+        hasher.finish()
+    })
+}
+
+/// Reads the component along with the version of its layout, whichever
+/// version that is.
+fn read_component<F: Format>(
+    backend: &Backend<F>,
+    entity: Entity,
+    component: &str,
+) -> Result<(Option<F::Data>, Option<Version>), BackendError> {
+    let component = backend
+        .read_components(
+            entity,
+            vec![ExtractionDescriptor {
+                name: component.to_string(),
+                version: None,
+                schema: None,
+            }],
+        )
+        .ctx(format!("reading {component} of {entity}"))?
+        .pop()
+        .flatten();
+
+    Ok(match component {
+        Some(component) => (Some(component.contents), Some(component.version)),
+        None => (None, None),
+    })
+}
+
+/// Performs a three-way merge of two copies of a world which diverged from
+/// a common base, writing the merged components into `target`.
+///
+/// Entities and components are compared one component at a time: a
+/// component changed in only one copy takes that copy's version, while
+/// components changed differently in both are resolved using `strategy`.
+/// Only the named components are merged.
+pub fn merge_worlds<F: Format>(
+    base: &Backend<F>,
+    ours: &Backend<F>,
+    theirs: &Backend<F>,
+    target: &Backend<F>,
+    components: &[&str],
+    mut strategy: MergeStrategy<'_, F>,
+) -> Result<MergeReport, BackendError> {
+    let mut merged: BTreeMap<Entity, Vec<SerializedComponent<F>>> = BTreeMap::new();
+    let mut report = MergeReport::default();
+
+    for component in components.iter().copied().collect::<BTreeSet<_>>() {
+        let mut entities = BTreeSet::new();
+        for backend in [base, ours, theirs] {
+            entities.extend(
+                backend
+                    .entities_with(component)
+                    .ctx(format!("listing entities with {component}"))?,
+            );
+        }
+
+        for entity in entities {
+            let (base_contents, base_layout) = read_component(base, entity, component)?;
+            let (our_contents, our_layout) = read_component(ours, entity, component)?;
+            let (their_contents, their_layout) = read_component(theirs, entity, component)?;
+            let versions = (base_contents, our_contents, their_contents);
+
+            let (b, o, t) = (
+                content_hash::<F>(versions.0.as_ref()),
+                content_hash::<F>(versions.1.as_ref()),
+                content_hash::<F>(versions.2.as_ref()),
+            );
+
+            let (outcome, contents) = if o == t {
+                let outcome = if o == b {
+                    MergeOutcome::Unchanged
+                } else {
+                    MergeOutcome::Agreed
+                };
+                (outcome, versions.1)
+            } else if o == b {
+                (MergeOutcome::Theirs, versions.2)
+            } else if t == b {
+                (MergeOutcome::Ours, versions.1)
+            } else {
+                match &mut strategy {
+                    MergeStrategy::TakeOurs => (MergeOutcome::ConflictOurs, versions.1),
+                    MergeStrategy::TakeTheirs => (MergeOutcome::ConflictTheirs, versions.2),
+                    MergeStrategy::Callback(resolve) => (
+                        MergeOutcome::ConflictResolved,
+                        resolve(MergeConflict {
+                            entity,
+                            component,
+                            base: versions.0.as_ref(),
+                            ours: versions.1.as_ref(),
+                            theirs: versions.2.as_ref(),
+                        }),
+                    ),
+                }
+            };
+
+            // The merged component keeps the layout version of the copy it
+            // was taken from, preferring ours for values decided by the
+            // callback.
+            let layout = match outcome {
+                MergeOutcome::Theirs | MergeOutcome::ConflictTheirs => their_layout,
+                _ => our_layout.or(their_layout),
+            }
+            .or(base_layout)
+            .unwrap_or_default();
+
+            report.decisions.push(MergeDecision {
+                entity,
+                component: component.to_string(),
+                outcome,
+                present: contents.is_some(),
+            });
+
+            if let Some(contents) = contents {
+                merged.entry(entity).or_default().push(SerializedComponent {
+                    contents,
+                    name: component.to_string(),
+                    version: layout,
+                    schema: None,
+                    metadata: None,
+                });
+            }
+        }
+    }
+
+    for (entity, components) in merged {
+        target
+            .update_components(entity, components)
+            .ctx(format!("writing merged components of {entity}"))?;
+    }
+
+    report
+        .decisions
+        .sort_by(|a, b| (a.entity, &a.component).cmp(&(b.entity, &b.component)));
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{Backend, Format},
+        Component, Entity,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{InsertReport, TypedBackend};
+
+    use super::{merge_worlds, MergeOutcome, MergeReport, MergeStrategy};
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Name(pub String);
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Health(pub u32);
+
+    const COMPONENTS: &[&str] = &["Name", "Health"];
+
+    fn backend() -> Backend<Json> {
+        Backend::from_joint(SqliteBackend::memory().unwrap())
+    }
+
+    struct Worlds {
+        base: Backend<Json>,
+        ours: Backend<Json>,
+        theirs: Backend<Json>,
+        /// Unchanged in both copies.
+        untouched: Entity,
+        /// Health changed in both copies, name removed in ours.
+        contested: Entity,
+        /// Only present in our copy.
+        added: Entity,
+    }
+
+    fn worlds() -> Worlds {
+        let worlds = Worlds {
+            base: backend(),
+            ours: backend(),
+            theirs: backend(),
+            untouched: Entity::new(),
+            contested: Entity::new(),
+            added: Entity::new(),
+        };
+
+        for world in [&worlds.base, &worlds.ours, &worlds.theirs] {
+            world
+                .put(worlds.untouched, (Name("Tree".to_string()), Health(1)))
+                .and_then(InsertReport::into_result)
+                .unwrap();
+        }
+
+        worlds
+            .base
+            .put(worlds.contested, (Name("Orc".to_string()), Health(10)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        worlds
+            .ours
+            .put(worlds.contested, (Health(20),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        worlds
+            .theirs
+            .put(worlds.contested, (Name("Orc".to_string()), Health(30)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        worlds
+            .ours
+            .put(worlds.added, (Name("Goblin".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        worlds
+    }
+
+    fn merge(worlds: &Worlds, strategy: MergeStrategy<'_, Json>) -> (Backend<Json>, MergeReport) {
+        let target = backend();
+        let report = merge_worlds(
+            &worlds.base,
+            &worlds.ours,
+            &worlds.theirs,
+            &target,
+            COMPONENTS,
+            strategy,
+        )
+        .unwrap();
+
+        (target, report)
+    }
+
+    fn outcome(report: &MergeReport, entity: Entity, component: &str) -> MergeOutcome {
+        report
+            .decisions
+            .iter()
+            .find(|decision| decision.entity == entity && decision.component == component)
+            .unwrap()
+            .outcome
+    }
+
+    fn assert_common(worlds: &Worlds, target: &Backend<Json>, report: &MergeReport) {
+        assert_eq!(report.decisions.len(), 5);
+        assert_eq!(report.conflicts().count(), 1);
+
+        assert_eq!(
+            outcome(report, worlds.untouched, "Name"),
+            MergeOutcome::Unchanged
+        );
+        assert_eq!(
+            outcome(report, worlds.untouched, "Health"),
+            MergeOutcome::Unchanged
+        );
+        assert_eq!(outcome(report, worlds.added, "Name"), MergeOutcome::Ours);
+        assert_eq!(
+            outcome(report, worlds.contested, "Name"),
+            MergeOutcome::Ours
+        );
+
+        assert_eq!(
+            target
+                .get::<(&Name, &Health)>(worlds.untouched)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            (&Name("Tree".to_string()), &Health(1))
+        );
+        assert_eq!(
+            target.get::<&Name>(worlds.added).unwrap().unwrap().deref(),
+            &Name("Goblin".to_string())
+        );
+        assert!(target.get::<&Name>(worlds.contested).unwrap().is_none());
+    }
+
+    #[test]
+    fn take_ours() {
+        let worlds = worlds();
+        let (target, report) = merge(&worlds, MergeStrategy::TakeOurs);
+
+        assert_common(&worlds, &target, &report);
+        assert_eq!(
+            outcome(&report, worlds.contested, "Health"),
+            MergeOutcome::ConflictOurs
+        );
+        assert_eq!(
+            target
+                .get::<&Health>(worlds.contested)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            &Health(20)
+        );
+    }
+
+    #[test]
+    fn take_theirs() {
+        let worlds = worlds();
+        let (target, report) = merge(&worlds, MergeStrategy::TakeTheirs);
+
+        assert_common(&worlds, &target, &report);
+        assert_eq!(
+            outcome(&report, worlds.contested, "Health"),
+            MergeOutcome::ConflictTheirs
+        );
+        assert_eq!(
+            target
+                .get::<&Health>(worlds.contested)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            &Health(30)
+        );
+    }
+
+    #[test]
+    fn callback() {
+        let worlds = worlds();
+        let mut calls = 0;
+
+        let (target, report) = merge(
+            &worlds,
+            MergeStrategy::Callback(Box::new(|conflict| {
+                calls += 1;
+                assert_eq!(conflict.component, "Health");
+
+                let health = |data: Option<&Vec<u8>>| Json::deserialize::<Health>(data?).ok();
+                let (base, ours, theirs) = (
+                    health(conflict.base).unwrap(),
+                    health(conflict.ours).unwrap(),
+                    health(conflict.theirs).unwrap(),
+                );
+
+                // Apply both changes relative to the base.
+                Json::serialize(Health(ours.0 + theirs.0 - base.0)).ok()
+            })),
+        );
+
+        assert_eq!(calls, 1);
+        assert_common(&worlds, &target, &report);
+        assert_eq!(
+            outcome(&report, worlds.contested, "Health"),
+            MergeOutcome::ConflictResolved
+        );
+        assert_eq!(
+            target
+                .get::<&Health>(worlds.contested)
+                .unwrap()
+                .unwrap()
+                .deref(),
+            &Health(40)
+        );
+    }
+}
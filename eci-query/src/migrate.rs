@@ -0,0 +1,100 @@
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, Backend, ExtractionDescriptor, Format, SerializedComponent,
+    },
+    Entity, Version,
+};
+use log::*;
+
+/// Reads the components like [`AccessBackend::read_components`], upgrading
+/// components stored by older versions of their layout through the
+/// backend's migrations.
+pub(crate) fn read_components<F: Format>(
+    backend: &Backend<F>,
+    entity: Entity,
+    descriptors: Vec<ExtractionDescriptor>,
+) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+    let (expected, descriptors) = expecting(backend, descriptors);
+    let components = backend.read_components(entity, descriptors)?;
+    upgrade(backend, entity, expected, components)
+}
+
+/// Splits the versions expected of the described components off, leaving
+/// descriptors which accept any version if there are migrations to upgrade
+/// older ones with.
+///
+/// The backend cannot tell which components are stored with the expected
+/// version once any version is accepted, so their schema hashes are
+/// expected along with the versions, and checked by [`upgrade`] instead.
+pub(crate) fn expecting<F: Format>(
+    backend: &Backend<F>,
+    descriptors: Vec<ExtractionDescriptor>,
+) -> (Vec<Expected>, Vec<ExtractionDescriptor>) {
+    if backend.migrations().is_empty() {
+        let expected = descriptors.iter().map(|d| (d.version, None)).collect();
+        return (expected, descriptors);
+    }
+
+    let expected = descriptors.iter().map(|d| (d.version, d.schema)).collect();
+    let any_version = descriptors
+        .into_iter()
+        .map(|descriptor| ExtractionDescriptor {
+            version: None,
+            schema: None,
+            ..descriptor
+        })
+        .collect();
+    (expected, any_version)
+}
+
+/// The version expected of a component, along with the schema hash left to
+/// check, see [`expecting`].
+pub(crate) type Expected = (Option<Version>, Option<u64>);
+
+/// Upgrades components read through descriptors from [`expecting`] to the
+/// expected versions. Upgraded components are written back if the
+/// migrations say so, on a best effort basis, since failing to do so only
+/// means upgrading them again on the next read.
+pub(crate) fn upgrade<F: Format>(
+    backend: &Backend<F>,
+    entity: Entity,
+    expected: Vec<Expected>,
+    mut components: Vec<Option<SerializedComponent<F>>>,
+) -> Result<Vec<Option<SerializedComponent<F>>>, AccessError> {
+    let migrations = backend.migrations();
+    let mut upgraded = Vec::new();
+    for (component, (expected, schema)) in components.iter_mut().zip(expected) {
+        let (Some(component), Some(expected)) = (component, expected) else {
+            continue;
+        };
+
+        if component.version == expected {
+            if let Some(schema) = schema {
+                backend.check_schema(component, schema)?;
+            }
+        } else {
+            component.contents = migrations.migrate::<F>(
+                &component.name,
+                &component.contents,
+                component.version,
+                expected,
+            )?;
+            component.version = expected;
+            upgraded.push(SerializedComponent {
+                contents: component.contents.as_ref().to_vec().into(),
+                name: component.name.clone(),
+                version: expected,
+                schema,
+                metadata: None,
+            });
+        }
+    }
+
+    if migrations.writes_back() && !upgraded.is_empty() {
+        if let Err(err) = backend.update_components(entity, upgraded) {
+            warn!("failed to write back upgraded components of {entity}: {err}");
+        }
+    }
+
+    Ok(components)
+}
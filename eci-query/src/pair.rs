@@ -0,0 +1,126 @@
+use std::{fmt::Debug, time::SystemTime};
+
+use eci_core::{
+    backend::{AccessBackend, Backend, BackendError, Format, LockingError, ResultExt},
+    Entity,
+};
+
+use crate::{lock::DropLock, refcast::RefCast, Extractor};
+
+/// Components of two entities locked together under a single lock, as
+/// returned by [`crate::TypedBackend::get_pair`].
+pub struct LockedPair<A, B, F>
+where
+    A: Extractor,
+    B: Extractor,
+    F: Format,
+{
+    entities: (Entity, Entity),
+    lock: DropLock,
+    backend: Backend<F>,
+    expires: SystemTime,
+    first: <A as Extractor>::Owned,
+    second: <B as Extractor>::Owned,
+}
+
+impl<A, B, F> LockedPair<A, B, F>
+where
+    A: Extractor + RefCast<Owned = <A as Extractor>::Owned>,
+    B: Extractor + RefCast<Owned = <B as Extractor>::Owned>,
+    F: Format,
+{
+    pub(crate) fn new(
+        entities: (Entity, Entity),
+        lock: DropLock,
+        backend: Backend<F>,
+        expires: SystemTime,
+        components: (<A as Extractor>::Owned, <B as Extractor>::Owned),
+    ) -> Self {
+        LockedPair {
+            entities,
+            // Prefer the expiry reported by the locking backend, if any.
+            expires: lock.expires_at().unwrap_or(expires),
+            lock,
+            backend,
+            first: components.0,
+            second: components.1,
+        }
+    }
+
+    pub fn unlock(self) -> Result<(), LockingError> {
+        self.lock.unlock()
+    }
+
+    /// The two entities whose components are locked, in the order given.
+    pub fn entities(&self) -> (Entity, Entity) {
+        self.entities
+    }
+
+    /// Identifies the lock, for instance in logs.
+    pub fn lock_id(&self) -> String {
+        self.lock.id().unwrap_or_default()
+    }
+
+    /// Point in time at which the lock expires, at the earliest.
+    pub fn expires_at(&self) -> SystemTime {
+        self.expires
+    }
+
+    /// Writes the mutably locked components of both entities back to the
+    /// backend and releases the lock. Fails without writing if the lock
+    /// has expired. The entities are written one at a time, so a failure
+    /// writing the second entity leaves the first one written.
+    pub fn commit(self) -> Result<(), BackendError> {
+        let (a, b) = self.entities;
+        if self.backend.now() >= self.expires {
+            return Err(LockingError::Expired(self.lock.id().unwrap_or_default()))
+                .ctx(format!("committing components of {a} and {b}"));
+        }
+
+        let first = A::serialize::<F>(&self.first).ctx(format!("serializing components of {a}"))?;
+        let second =
+            B::serialize::<F>(&self.second).ctx(format!("serializing components of {b}"))?;
+
+        let lock = self
+            .lock
+            .held()
+            .ctx(format!("committing components of {a} and {b}"))?;
+        for (entity, components) in [(a, first), (b, second)] {
+            if !components.is_empty() {
+                self.backend
+                    .update_components_locked(entity, components, lock)
+                    .ctx(format!("committing components of {entity}"))?;
+            }
+        }
+
+        self.lock
+            .unlock()
+            .ctx(format!("releasing components of {a} and {b} after commit"))
+    }
+
+    pub fn deref(&mut self) -> (A::Ref<'_>, B::Ref<'_>) {
+        (
+            <A as RefCast>::refcast(&mut self.first),
+            <B as RefCast>::refcast(&mut self.second),
+        )
+    }
+}
+
+impl<A, B, F> Debug for LockedPair<A, B, F>
+where
+    A: Extractor,
+    B: Extractor,
+    <A as Extractor>::Owned: Debug,
+    <B as Extractor>::Owned: Debug,
+    F: Format,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LockedPair")
+            .field("entities", &self.entities)
+            .field("lock", &self.lock)
+            .field("expires", &self.expires)
+            .field("first", &self.first)
+            .field("second", &self.second)
+            .finish()
+    }
+}
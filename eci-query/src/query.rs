@@ -0,0 +1,345 @@
+use std::{collections::BTreeSet, marker::PhantomData};
+
+use eci_core::{
+    backend::{AccessBackend, Backend, BackendError, Format, Predicate, ResultExt},
+    Entity,
+};
+
+use crate::{Extractor, ReadOnly, TypedBackend};
+
+/// Finds entities by the values of their components, built with
+/// [`TypedBackend::query`]. Filters are evaluated by the backend where
+/// possible, rather than deserializing every component.
+pub struct Query<'a, F: Format, Select> {
+    backend: &'a Backend<F>,
+    predicates: Vec<Predicate>,
+    _select: PhantomData<Select>,
+}
+
+impl<'a, F: Format, Select: Extractor> Query<'a, F, Select> {
+    pub(crate) fn new(backend: &'a Backend<F>) -> Self {
+        Query {
+            backend,
+            predicates: Vec::new(),
+            _select: PhantomData,
+        }
+    }
+
+    /// Only matches entities for which the predicate holds.
+    pub fn filter(mut self, predicate: Predicate) -> Self {
+        self.predicates.push(predicate);
+        self
+    }
+
+    /// Lists the entities matching every filter, regardless of whether they
+    /// have the selected components. Without any filters, lists every
+    /// entity which has at least one of the selected components.
+    pub fn entities(&self) -> Result<Vec<Entity>, BackendError> {
+        if !self.predicates.is_empty() {
+            return self
+                .backend
+                .find_entities_where(self.predicates.clone())
+                .ctx("finding entities matching the query");
+        }
+
+        let mut entities = BTreeSet::new();
+        for descriptor in Select::extract() {
+            entities.extend(
+                self.backend
+                    .entities_with(&descriptor.name)
+                    .ctx(format!("listing entities with {}", descriptor.name))?,
+            );
+        }
+
+        Ok(entities.into_iter().collect())
+    }
+
+    /// Reads the selected components of every matching entity which has
+    /// them, without locking.
+    pub fn peek(&self) -> Result<Vec<(Entity, Select::Owned)>, BackendError>
+    where
+        Select: ReadOnly,
+    {
+        let mut found = Vec::new();
+        for entity in self.entities()? {
+            if let Some(components) = self.backend.peek::<Select>(entity)? {
+                found.push((entity, components));
+            }
+        }
+
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Display;
+
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{AccessError, Backend, Format},
+        Component, Entity,
+    };
+    use eci_format_json::Json;
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use crate::{InsertReport, TypedBackend};
+
+    /// Stores components as json, without letting the backend know.
+    #[derive(Clone)]
+    struct Opaque;
+
+    impl Format for Opaque {
+        type Data = Vec<u8>;
+
+        fn serialize<T: Serialize>(value: T) -> Result<Self::Data, AccessError> {
+            Json::serialize(value)
+        }
+
+        fn deserialize<T: DeserializeOwned>(value: &Self::Data) -> Result<T, AccessError> {
+            Json::deserialize(value)
+        }
+    }
+
+    impl Display for Opaque {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "opaque")
+        }
+    }
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Health {
+        current: u32,
+        max: f32,
+    }
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Name(pub String);
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Stats {
+        armor: Armor,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Armor {
+        kind: String,
+    }
+
+    struct World<F: Format> {
+        backend: Backend<F>,
+        goblin: Entity,
+        orc: Entity,
+        troll: Entity,
+    }
+
+    fn world<F: Format>() -> World<F> {
+        let world = World {
+            backend: Backend::<F>::from_joint(SqliteBackend::memory().unwrap()),
+            goblin: Entity::new(),
+            orc: Entity::new(),
+            troll: Entity::new(),
+        };
+
+        let stats = |kind: &str| Stats {
+            armor: Armor {
+                kind: kind.to_string(),
+            },
+            tags: vec![kind.to_string()],
+        };
+
+        for (entity, name, current, max) in [
+            (world.goblin, "Goblin", 5, 10.0),
+            (world.orc, "Orc", 20, 25.5),
+            (world.troll, "Troll", 9, 80.0),
+        ] {
+            world
+                .backend
+                .put(entity, (Name(name.to_string()), Health { current, max }))
+                .and_then(InsertReport::into_result)
+                .unwrap();
+        }
+
+        world
+            .backend
+            .put(world.goblin, (stats("leather"),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        world
+            .backend
+            .put(world.troll, (stats("hide"),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        world
+    }
+
+    fn sorted(mut entities: Vec<Entity>) -> Vec<Entity> {
+        entities.sort();
+        entities
+    }
+
+    fn numeric<F: Format>() {
+        let World {
+            backend,
+            goblin,
+            orc,
+            troll,
+        } = world::<F>();
+
+        let wounded = backend
+            .query::<&Health>()
+            .filter(Health::field("current").lt(10))
+            .entities()
+            .unwrap();
+        assert_eq!(wounded, sorted(vec![goblin, troll]));
+
+        let found = backend
+            .query::<&Health>()
+            .filter(Health::field("current").ge(9))
+            .filter(Health::field("max").gt(25))
+            .entities()
+            .unwrap();
+        assert_eq!(found, sorted(vec![orc, troll]));
+
+        // Unsigned values too large for an i64 are compared as floats.
+        let below = |value: u64| {
+            backend
+                .query::<&Health>()
+                .filter(Health::field("current").lt(value))
+                .entities()
+                .unwrap()
+        };
+        assert_eq!(below(10), sorted(vec![goblin, troll]));
+        assert_eq!(below(u64::MAX), sorted(vec![goblin, orc, troll]));
+        assert_eq!(
+            backend
+                .query::<&Health>()
+                .filter(Health::field("current").eq(20usize))
+                .entities()
+                .unwrap(),
+            vec![orc]
+        );
+
+        assert_eq!(
+            backend
+                .query::<&Health>()
+                .filter(Health::field("max").eq(25.5))
+                .entities()
+                .unwrap(),
+            vec![orc]
+        );
+
+        // Fields of another kind, or missing fields, never match.
+        assert!(backend
+            .query::<&Health>()
+            .filter(Health::field("current").ne("5"))
+            .entities()
+            .unwrap()
+            .is_empty());
+        assert!(backend
+            .query::<&Health>()
+            .filter(Health::field("missing").ne(5))
+            .entities()
+            .unwrap()
+            .is_empty());
+    }
+
+    fn strings<F: Format>() {
+        let World {
+            backend,
+            goblin,
+            orc,
+            troll,
+        } = world::<F>();
+
+        let found = backend
+            .query::<(&Name, &Health)>()
+            .filter(Name::field("").gt("Goblin"))
+            .filter(Health::field("current").lt(10))
+            .peek()
+            .unwrap();
+        assert_eq!(
+            found,
+            vec![(
+                troll,
+                (
+                    Name("Troll".to_string()),
+                    Health {
+                        current: 9,
+                        max: 80.0
+                    }
+                )
+            )]
+        );
+
+        assert_eq!(
+            backend
+                .query::<&Name>()
+                .filter(Name::field("").ne("Troll"))
+                .entities()
+                .unwrap(),
+            sorted(vec![goblin, orc])
+        );
+
+        assert_eq!(
+            backend
+                .query::<&Stats>()
+                .filter(Stats::field("armor.kind").eq("hide"))
+                .entities()
+                .unwrap(),
+            vec![troll]
+        );
+        assert_eq!(
+            backend
+                .query::<&Stats>()
+                .filter(Stats::field("tags.0").eq("leather"))
+                .entities()
+                .unwrap(),
+            vec![goblin]
+        );
+    }
+
+    #[test]
+    fn numeric_comparisons() {
+        numeric::<Json>();
+    }
+
+    #[test]
+    fn string_comparisons() {
+        strings::<Json>();
+    }
+
+    #[test]
+    fn fallback_numeric_comparisons() {
+        numeric::<Opaque>();
+    }
+
+    #[test]
+    fn fallback_string_comparisons() {
+        strings::<Opaque>();
+    }
+
+    #[test]
+    fn unfiltered() {
+        let World {
+            backend,
+            goblin,
+            orc,
+            troll,
+        } = world::<Json>();
+
+        assert_eq!(
+            backend.query::<&Health>().entities().unwrap(),
+            sorted(vec![goblin, orc, troll])
+        );
+        assert_eq!(backend.query::<&Stats>().peek().unwrap().len(), 2);
+        assert!(backend
+            .query::<&Armor>()
+            .filter(Armor::field("kind").eq("hide"))
+            .entities()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,46 +1,112 @@
-pub trait RefCast<'a> {
+use eci_core::Entity;
+
+pub trait RefCast {
     type Owned;
-    fn refcast(owned: &'a mut Self::Owned) -> Self;
+    type Ref<'a>
+    where
+        Self: 'a;
+    fn refcast<'a>(owned: &'a mut Self::Owned) -> Self::Ref<'a>
+    where
+        Self: 'a;
 }
 
-impl<'a, A> RefCast<'a> for &'a A {
+impl<A> RefCast for &A {
     type Owned = A;
+    type Ref<'a>
+        = &'a A
+    where
+        Self: 'a;
 
-    fn refcast(a: &'a mut Self::Owned) -> Self {
+    fn refcast<'a>(a: &'a mut Self::Owned) -> Self::Ref<'a>
+    where
+        Self: 'a,
+    {
         &*a
     }
 }
 
-impl<'a, A> RefCast<'a> for &'a mut A {
+impl<A> RefCast for &mut A {
     type Owned = A;
+    type Ref<'a>
+        = &'a mut A
+    where
+        Self: 'a;
 
-    fn refcast(a: &'a mut Self::Owned) -> Self {
+    fn refcast<'a>(a: &'a mut Self::Owned) -> Self::Ref<'a>
+    where
+        Self: 'a,
+    {
         a
     }
 }
 
+impl<A> RefCast for Option<&A> {
+    type Owned = Option<A>;
+    type Ref<'a>
+        = Option<&'a A>
+    where
+        Self: 'a;
+
+    fn refcast<'a>(a: &'a mut Self::Owned) -> Self::Ref<'a>
+    where
+        Self: 'a,
+    {
+        a.as_ref()
+    }
+}
+
+impl<A> RefCast for Option<&mut A> {
+    type Owned = Option<A>;
+    type Ref<'a>
+        = Option<&'a mut A>
+    where
+        Self: 'a;
+
+    fn refcast<'a>(a: &'a mut Self::Owned) -> Self::Ref<'a>
+    where
+        Self: 'a,
+    {
+        a.as_mut()
+    }
+}
+
+impl RefCast for Entity {
+    type Owned = Entity;
+    type Ref<'a>
+        = Entity
+    where
+        Self: 'a;
+
+    fn refcast<'a>(entity: &'a mut Self::Owned) -> Self::Ref<'a>
+    where
+        Self: 'a,
+    {
+        *entity
+    }
+}
+
 macro_rules! borrow_tuple {
     ($vh:ident: $th:ident : $ih:ident) => {
-        impl<'a, $th, $ih> RefCast<'a> for ($th,) where
-            $th: RefCast<'a, Owned = $ih> + 'a {
+        impl<$th, $ih> RefCast for ($th,) where
+            $th: RefCast<Owned = $ih> {
             type Owned = ($ih,);
+            type Ref<'a> = ($th::Ref<'a>,) where Self: 'a;
 
-            fn refcast((ref mut $vh,): &'a mut ($th::Owned,)) -> Self {
+            fn refcast<'a>((ref mut $vh,): &'a mut ($th::Owned,)) -> Self::Ref<'a> where Self: 'a {
                 ($th::refcast($vh),)
             }
         }
     };
 
     (  $vh:ident: $th:ident : $ih:ident, $($v:ident: $t:ident : $i:ident),+) => {
-        impl<'a, $th, $( $t ),*, $ih, $( $i ),*> RefCast<'a> for ($th, $($t),*) where
-            $th: RefCast<'a, Owned = $ih> + 'a,
-            $( $t: RefCast<'a, Owned = $i> + 'a ),* {
+        impl<$th, $( $t ),*, $ih, $( $i ),*> RefCast for ($th, $($t),*) where
+            $th: RefCast<Owned = $ih>,
+            $( $t: RefCast<Owned = $i> ),* {
             type Owned = ($ih, $( $i ),*);
+            type Ref<'a> = ($th::Ref<'a>, $( $t::Ref<'a> ),*) where Self: 'a;
 
-            fn refcast( (ref mut $vh, ref mut $( $v ),*) : &'a mut ($th::Owned, $( $t::Owned),* )) -> Self {
-                (
-                    ($th::refcast($vh), $( $t::refcast($v) ),*)
-                )
+            fn refcast<'a>( (ref mut $vh, ref mut $( $v ),*) : &'a mut ($th::Owned, $( $t::Owned),* )) -> Self::Ref<'a> where Self: 'a {
+                ($th::refcast($vh), $( $t::refcast($v) ),*)
             }
         }
 
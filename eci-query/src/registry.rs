@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use eci_core::{
+    backend::{AccessError, Format},
+    Component, Version,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Converts a component between its stored and its interchange representation.
+struct Registration<F: Format> {
+    version: Version,
+    import: fn(serde_json::Value) -> Result<F::Data, AccessError>,
+    export: fn(&F::Data) -> Result<serde_json::Value, AccessError>,
+}
+
+/// Component types known at runtime, keyed by their `COMPONENT_TYPE`.
+pub struct ComponentRegistry<F: Format> {
+    components: BTreeMap<String, Registration<F>>,
+}
+
+impl<F: Format> Default for ComponentRegistry<F> {
+    fn default() -> Self {
+        ComponentRegistry {
+            components: BTreeMap::new(),
+        }
+    }
+}
+
+impl<F: Format> ComponentRegistry<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T: Component + Serialize + DeserializeOwned>(mut self) -> Self {
+        self.components.insert(
+            T::COMPONENT_TYPE.to_string(),
+            Registration {
+                version: T::VERSION,
+                import: |value| {
+                    F::serialize(
+                        serde_json::from_value::<T>(value).map_err(AccessError::serialization)?,
+                    )
+                },
+                export: |data| {
+                    serde_json::to_value(F::deserialize::<T>(data)?)
+                        .map_err(AccessError::serialization)
+                },
+            },
+        );
+        self
+    }
+
+    pub fn contains(&self, component: &str) -> bool {
+        self.components.contains_key(component)
+    }
+
+    /// Names of all registered components, in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.components.keys().map(String::as_str)
+    }
+
+    /// Names of all registered components along with their versions, in
+    /// sorted order.
+    pub fn versions(&self) -> impl Iterator<Item = (&str, Version)> {
+        self.components
+            .iter()
+            .map(|(name, registration)| (name.as_str(), registration.version))
+    }
+
+    /// Validates an interchange value against the component's type and
+    /// serializes it for storage, along with the component's version.
+    pub fn import(
+        &self,
+        component: &str,
+        value: serde_json::Value,
+    ) -> Result<(F::Data, Version), AccessError> {
+        let registration = self
+            .components
+            .get(component)
+            .ok_or_else(|| AccessError::UnknownComponent(component.to_string()))?;
+
+        Ok(((registration.import)(value)?, registration.version))
+    }
+
+    /// Converts a stored component to its interchange value.
+    pub fn export(
+        &self,
+        component: &str,
+        data: &F::Data,
+    ) -> Result<serde_json::Value, AccessError> {
+        let registration = self
+            .components
+            .get(component)
+            .ok_or_else(|| AccessError::UnknownComponent(component.to_string()))?;
+
+        (registration.export)(data)
+    }
+}
@@ -0,0 +1,80 @@
+use eci_core::backend::{
+    AccessError, DeserializationLimits, ExtractionDescriptor, Format, LockDescriptor, LockingMode,
+    SerializedComponent,
+};
+use eci_core::Component;
+use serde::de::DeserializeOwned;
+
+use crate::{deserialize_component, sealed};
+
+/// Maps tuples of components to the descriptors required for removing
+/// them from an entity, and deserializes the removed values. Sealed like
+/// [`crate::Inserter`].
+pub trait Remover: sealed::Removal {
+    type Removed;
+    fn describe() -> Vec<LockDescriptor>;
+    fn extract() -> Vec<ExtractionDescriptor>;
+
+    fn from<F: Format>(
+        serialized: Vec<Option<SerializedComponent<F>>>,
+        limits: Option<&DeserializationLimits>,
+    ) -> Result<Self::Removed, AccessError>;
+}
+
+macro_rules! impl_remover {
+    ($($T:ident),+) => {
+        impl<$($T: Component + DeserializeOwned),+> sealed::Removal for ($($T,)+) {}
+
+        impl<$($T: Component + DeserializeOwned),+> Remover for ($($T,)+) {
+            type Removed = ($(Option<$T>,)+);
+
+            fn describe() -> Vec<LockDescriptor> {
+                vec![
+                    $(
+                        LockDescriptor {
+                            mode: LockingMode::Write,
+                            name: $T::COMPONENT_TYPE.to_string(),
+                        },
+                    )+
+                ]
+            }
+
+            fn extract() -> Vec<ExtractionDescriptor> {
+                vec![
+                    $( ExtractionDescriptor {
+                            name: $T::COMPONENT_TYPE.to_string(),
+                            version: Some($T::VERSION),
+                            schema: $T::schema(),
+                        }, )+
+                ]
+            }
+
+            fn from<F: Format>(
+                serialized: Vec<Option<SerializedComponent<F>>>,
+                limits: Option<&DeserializationLimits>,
+            ) -> Result<Self::Removed, AccessError> {
+                let mut iter = serialized.into_iter();
+                Ok((
+                    $(
+                        iter.next()
+                            .flatten()
+                            .map(|component| deserialize_component::<F, $T>(component, limits))
+                            .transpose()?,
+                    )+
+                ))
+            }
+        }
+    }
+}
+
+macro_rules! impl_all_remover {
+    ($t:ident) => {
+        impl_remover!($t);
+    };
+    ($th:ident, $($tr:ident),*) => {
+        impl_remover!($th, $($tr),+);
+        impl_all_remover!($($tr),+);
+    };
+}
+
+impl_all_remover!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16);
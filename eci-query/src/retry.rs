@@ -0,0 +1,206 @@
+use std::time::{Duration, Instant};
+
+use eci_core::backend::BackendError;
+use rand::Rng;
+
+/// Governs how often and for how long conflicting lock acquisitions are
+/// retried. The delay between attempts doubles with every attempt, starting
+/// at `base_delay` and capped at `max_delay`, plus a random `jitter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: Duration,
+    /// Gives up once this much time has passed, regardless of attempts.
+    pub deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_secs(1),
+            jitter: Duration::from_millis(10),
+            deadline: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry, counting from zero.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+
+        if self.jitter.is_zero() {
+            backoff
+        } else {
+            backoff + rand::thread_rng().gen_range(Duration::ZERO..self.jitter)
+        }
+    }
+}
+
+/// Runs the operation until it no longer fails due to a conflicting lock,
+/// or the policy gives up. The final error describes how long was spent
+/// waiting, and what the last attempt conflicted with.
+pub(crate) fn retry_conflicts<T>(
+    policy: &RetryPolicy,
+    mut operation: impl FnMut() -> Result<T, BackendError>,
+) -> Result<T, BackendError> {
+    let start = Instant::now();
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        let err = match operation() {
+            // Stale writes conflict as well, but would only go stale again.
+            Err(err) if err.is_conflict() && err.is_transient() => err,
+            result => return result,
+        };
+
+        let delay = policy.delay(attempts - 1);
+        let out_of_time = policy
+            .deadline
+            .map(|deadline| start.elapsed() + delay > deadline)
+            .unwrap_or(false);
+
+        if attempts >= policy.max_attempts || out_of_time {
+            return Err(err.context(format!(
+                "gave up after {attempts} attempts over {:?}",
+                start.elapsed()
+            )));
+        }
+
+        std::thread::sleep(delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{Backend, BackendError, LockingError},
+        Component, Entity,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{InsertReport, TypedBackend};
+
+    use super::RetryPolicy;
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Counter(pub usize);
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+            jitter: Duration::ZERO,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.delay(0), Duration::from_millis(10));
+        assert_eq!(policy.delay(2), Duration::from_millis(40));
+        assert_eq!(policy.delay(3), Duration::from_millis(50));
+        assert_eq!(policy.delay(100), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn wait_for_competing_lock() {
+        // In-memory databases are private to each backend, so the threads
+        // share a file instead.
+        let path = std::env::temp_dir().join(format!("eci-retry-{}.db", Entity::new()));
+        let backend = Backend::<Json>::from_joint(SqliteBackend::file(&path).unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (Counter(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let (locked, released) = (mpsc::channel(), mpsc::channel::<()>());
+        let holder = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let backend = Backend::<Json>::from_joint(SqliteBackend::file(path).unwrap());
+                let mut lock = backend.get::<&mut Counter>(a).unwrap().unwrap();
+                locked.0.send(()).unwrap();
+
+                std::thread::sleep(Duration::from_millis(200));
+                lock.deref().0 = 2;
+                lock.commit().unwrap();
+                released.0.send(()).unwrap();
+            })
+        };
+
+        locked.1.recv().unwrap();
+        let start = Instant::now();
+        let mut lock = backend
+            .get_blocking::<&Counter>(
+                a,
+                &RetryPolicy {
+                    max_attempts: 100,
+                    deadline: Some(Duration::from_secs(5)),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(released.1.try_recv().is_ok());
+        assert_eq!(lock.deref(), &Counter(2));
+
+        drop(lock);
+        holder.join().unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn give_up_on_held_lock() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+
+        let a = Entity::new();
+        backend
+            .put(a, (Counter(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        let _lock = backend.get::<&mut Counter>(a).unwrap().unwrap();
+
+        let err = backend
+            .get_blocking::<&Counter>(
+                a,
+                &RetryPolicy {
+                    max_attempts: 3,
+                    base_delay: Duration::from_millis(1),
+                    jitter: Duration::from_millis(1),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+
+        assert!(matches!(
+            err.root(),
+            BackendError::Locking(LockingError::Conflict(..))
+        ));
+
+        let contexts = err.contexts();
+        assert!(contexts[0].starts_with("gave up after 3 attempts over "));
+        assert_eq!(contexts[1], format!("locking components of {a}"));
+        assert!(err.to_string().contains(&format!(
+            "read lock requested on {a}'s Counter but write lock {} held until ",
+            _lock.lock_id()
+        )));
+    }
+}
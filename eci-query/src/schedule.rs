@@ -0,0 +1,191 @@
+use eci_core::backend::{Backend, BackendError, Format, ResultExt};
+
+use crate::{BatchReport, Extractor, RefCast, TypedBackend};
+
+type System<'a, F> = Box<dyn FnMut(&Backend<F>) -> Result<BatchReport, BackendError> + 'a>;
+
+/// Systems run against every entity with the components they select, in
+/// the order they were added:
+///
+/// ```ignore
+/// let mut schedule = Schedule::new()
+///     .add_system::<(&mut Position, &Velocity), _>(|(position, velocity)| {
+///         position.x += velocity.x * dt;
+///     });
+///
+/// let report = schedule.run(&backend)?;
+/// ```
+///
+/// Each entity is locked on its own while a system runs against it, and
+/// its mutably selected components are written back before the lock is
+/// released. Entities locked by someone else are retried once the system
+/// has visited every other entity, and reported as skipped if they still
+/// are.
+pub struct Schedule<'a, F: Format> {
+    systems: Vec<(&'static str, System<'a, F>)>,
+}
+
+impl<F: Format> Default for Schedule<'_, F> {
+    fn default() -> Self {
+        Schedule {
+            systems: Vec::new(),
+        }
+    }
+}
+
+impl<'a, F: Format> Schedule<'a, F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system<Select, Func>(mut self, mut system: Func) -> Self
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned> + 'a,
+        Func: FnMut(Select::Ref<'_>) + 'a,
+    {
+        self.systems.push((
+            std::any::type_name::<Select>(),
+            Box::new(move |backend: &Backend<F>| {
+                let entities = backend.query::<Select>().entities()?;
+                backend
+                    .for_each_batched::<Select, _>(entities, 1, |_, components| system(components))
+            }),
+        ));
+        self
+    }
+
+    /// Runs every system once, stopping at the first one which fails.
+    pub fn run(&mut self, backend: &Backend<F>) -> Result<ScheduleReport, BackendError> {
+        let mut report = ScheduleReport::default();
+        for (index, (selection, system)) in self.systems.iter_mut().enumerate() {
+            report
+                .systems
+                .push(system(backend).ctx(format!("running system {index} on {selection}"))?);
+        }
+
+        Ok(report)
+    }
+}
+
+/// Outcome of running a [`Schedule`], with the entities each system visited
+/// and skipped, in the order the systems were added.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ScheduleReport {
+    pub systems: Vec<BatchReport>,
+}
+
+impl ScheduleReport {
+    /// Whether every system visited every entity it selected.
+    pub fn is_complete(&self) -> bool {
+        self.systems.iter().all(|system| system.skipped.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{backend::Backend, Component, Entity};
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{InsertReport, TypedBackend};
+
+    use super::Schedule;
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Position {
+        x: f32,
+    }
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Velocity {
+        x: f32,
+    }
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Ticks(pub u32);
+
+    #[test]
+    fn run_systems() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let moving = Entity::new();
+        let still = Entity::new();
+        let unrelated = Entity::new();
+        backend
+            .put(moving, (Position { x: 1.0 }, Velocity { x: 2.0 }, Ticks(0)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(still, (Position { x: 5.0 }, Ticks(0)))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(unrelated, (Velocity { x: 1.0 },))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let dt = 0.5;
+        let mut schedule = Schedule::new()
+            .add_system::<(&mut Position, &Velocity), _>(|(position, velocity)| {
+                position.x += velocity.x * dt;
+            })
+            .add_system::<&mut Ticks, _>(|ticks| ticks.0 += 1);
+
+        let report = schedule.run(&backend).unwrap();
+        assert!(report.is_complete());
+        assert_eq!(report.systems[0].visited, vec![moving]);
+        let mut ticked = report.systems[1].visited.clone();
+        ticked.sort();
+        let mut expected = vec![moving, still];
+        expected.sort();
+        assert_eq!(ticked, expected);
+
+        schedule.run(&backend).unwrap();
+        assert_eq!(
+            backend.peek::<&Position>(moving).unwrap(),
+            Some(Position { x: 3.0 })
+        );
+        assert_eq!(
+            backend.peek::<&Position>(still).unwrap(),
+            Some(Position { x: 5.0 })
+        );
+        assert_eq!(backend.peek::<&Ticks>(still).unwrap(), Some(Ticks(2)));
+        assert_eq!(
+            backend.peek::<&Velocity>(unrelated).unwrap(),
+            Some(Velocity { x: 1.0 })
+        );
+    }
+
+    #[test]
+    fn skip_contended_entities() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let free = Entity::new();
+        let held = Entity::new();
+        backend
+            .put(free, (Ticks(0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(held, (Ticks(0),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let lock = backend.lock_entity(held, Duration::from_secs(60)).unwrap();
+        let mut schedule = Schedule::new().add_system::<&mut Ticks, _>(|ticks| ticks.0 += 1);
+
+        let report = schedule.run(&backend).unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(report.systems[0].visited, vec![free]);
+        assert_eq!(report.systems[0].skipped, vec![held]);
+        assert_eq!(backend.peek::<&Ticks>(free).unwrap(), Some(Ticks(1)));
+        assert_eq!(backend.peek::<&Ticks>(held).unwrap(), Some(Ticks(0)));
+
+        // Skipped entities are picked up by the next run once released.
+        lock.unlock().unwrap();
+        assert!(schedule.run(&backend).unwrap().is_complete());
+        assert_eq!(backend.peek::<&Ticks>(held).unwrap(), Some(Ticks(1)));
+        assert_eq!(backend.peek::<&Ticks>(free).unwrap(), Some(Ticks(2)));
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, Backend, BackendError, ExtractionDescriptor, Format,
+        LockDescriptor, LockingBackend, LockingMode, ResultExt, SerializedComponent,
+    },
+    Entity, Version,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::DropLock;
+
+/// Every component of every entity in a backend, as it was stored, for
+/// fixtures and support bundles. Created with [`export`] and written back
+/// with [`import`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    /// Name of the format the components were serialized with, see
+    /// [`Format::name`].
+    pub format: String,
+    pub entities: BTreeMap<Entity, BTreeMap<String, SnapshotComponent>>,
+}
+
+/// A component in a [`WorldSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotComponent {
+    pub version: Version,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<u64>,
+    pub contents: Vec<u8>,
+}
+
+/// What to do with the entities already in a backend a snapshot is
+/// imported into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Writes the snapshot's components over the ones already stored,
+    /// leaving entities and components which are not in the snapshot as
+    /// they are.
+    Merge,
+    /// Despawns every entity first, so only the snapshot's entities are
+    /// left afterwards.
+    Replace,
+}
+
+/// Reads every component of every entity, along with the resources. Each
+/// entity is read locked while it is read, so the components of an entity
+/// are consistent with each other, though not with those of other entities
+/// written in the meantime.
+pub fn export<F: Format>(backend: &Backend<F>) -> Result<WorldSnapshot, BackendError> {
+    let mut entities = backend.list_entities().ctx("listing entities to export")?;
+    entities.push(Entity::RESOURCES);
+
+    let mut snapshot = WorldSnapshot {
+        format: F::name(),
+        entities: BTreeMap::new(),
+    };
+
+    for entity in entities {
+        let components = export_entity(backend, entity)?;
+        if !components.is_empty() {
+            snapshot.entities.insert(entity, components);
+        }
+    }
+
+    Ok(snapshot)
+}
+
+fn export_entity<F: Format>(
+    backend: &Backend<F>,
+    entity: Entity,
+) -> Result<BTreeMap<String, SnapshotComponent>, BackendError> {
+    let names = backend
+        .list_components(entity)
+        .ctx(format!("listing components of {entity} to export"))?;
+    if names.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    let locks: Vec<_> = names
+        .iter()
+        .map(|name| LockDescriptor {
+            mode: LockingMode::Read,
+            name: name.clone(),
+        })
+        .collect();
+    let ttl = backend.lock_ttl_for(&locks);
+    let lock = DropLock::from_backend(
+        backend
+            .acquire_lock(entity, locks, ttl.into())
+            .ctx(format!("locking components of {entity} to export"))?,
+        backend,
+        &[entity],
+    );
+
+    let descriptors = names
+        .into_iter()
+        .map(|name| ExtractionDescriptor {
+            name,
+            version: None,
+            schema: None,
+        })
+        .collect();
+
+    let components = backend
+        .read_components(entity, descriptors)
+        .ctx(format!("reading components of {entity} to export"))?
+        .into_iter()
+        .flatten()
+        .map(|component| {
+            (
+                component.name,
+                SnapshotComponent {
+                    version: component.version,
+                    schema: component.schema,
+                    contents: component.contents.into(),
+                },
+            )
+        })
+        .collect();
+
+    lock.unlock()
+        .ctx(format!("releasing components of {entity} after export"))?;
+    Ok(components)
+}
+
+/// Writes the snapshot's components into the backend, which must use the
+/// format the snapshot was exported with. Components are write locked
+/// while they are written, so the import fails rather than overwrite
+/// components which someone else holds locks on.
+pub fn import<F: Format>(
+    backend: &Backend<F>,
+    snapshot: WorldSnapshot,
+    mode: ImportMode,
+) -> Result<(), BackendError> {
+    if snapshot.format != F::name() {
+        return Err(AccessError::FormatMismatch {
+            component: "snapshot".to_string(),
+            stored: snapshot.format,
+            expected: F::name(),
+        })
+        .ctx("importing snapshot");
+    }
+
+    if mode == ImportMode::Replace {
+        let mut entities = backend.list_entities().ctx("listing entities to replace")?;
+        entities.push(Entity::RESOURCES);
+
+        for entity in entities {
+            match backend.despawn(entity) {
+                Ok(_) | Err(BackendError::EntityNotFound(_)) => {}
+                Err(err) => return Err(err).ctx(format!("despawning {entity} to replace it")),
+            }
+        }
+    }
+
+    for (entity, components) in snapshot.entities {
+        import_entity(backend, entity, components)?;
+    }
+
+    Ok(())
+}
+
+fn import_entity<F: Format>(
+    backend: &Backend<F>,
+    entity: Entity,
+    components: BTreeMap<String, SnapshotComponent>,
+) -> Result<(), BackendError> {
+    if components.is_empty() {
+        return Ok(());
+    }
+
+    let locks: Vec<_> = components
+        .keys()
+        .map(|name| LockDescriptor {
+            mode: LockingMode::Write,
+            name: name.clone(),
+        })
+        .collect();
+    let ttl = backend.lock_ttl_for(&locks);
+    let lock = DropLock::from_backend(
+        backend
+            .acquire_lock(entity, locks, ttl.into())
+            .ctx(format!("locking components of {entity} to import"))?,
+        backend,
+        &[entity],
+    );
+
+    let components = components
+        .into_iter()
+        .map(|(name, component)| SerializedComponent {
+            contents: component.contents.into(),
+            name,
+            version: component.version,
+            schema: component.schema,
+            metadata: None,
+        })
+        .collect();
+
+    let context = format!("writing imported components of {entity}");
+    let held = lock.held().ctx(context.clone())?;
+    backend
+        .update_components_locked(entity, components, held)
+        .ctx(context)?;
+    lock.unlock()
+        .ctx(format!("releasing components of {entity} after import"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{AccessBackend, AccessError, Backend, BackendError},
+        Component, Entity,
+    };
+    use eci_format_bincode::Bincode;
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{InsertReport, TypedBackend};
+
+    use super::{export, import, ImportMode, WorldSnapshot};
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+    #[component(version = "2.0.0")]
+    struct Name(pub String);
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Settings {
+        tick_rate: u32,
+    }
+
+    fn backend() -> Backend<Json> {
+        Backend::<Json>::from_joint(SqliteBackend::memory().unwrap())
+    }
+
+    /// Two entities and a resource.
+    fn populate(backend: &Backend<Json>) -> (Entity, Entity) {
+        let (goblin, orc) = (Entity::new(), Entity::new());
+        backend
+            .put(
+                goblin,
+                (Position { x: 1.0, y: 2.0 }, Name("Goblin".to_string())),
+            )
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend
+            .put(orc, (Name("Orc".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        backend.put_resource(Settings { tick_rate: 60 }).unwrap();
+        (goblin, orc)
+    }
+
+    #[test]
+    fn export_and_import() {
+        let source = backend();
+        let (goblin, orc) = populate(&source);
+
+        let snapshot = export(&source).unwrap();
+        assert_eq!(snapshot.entities.len(), 3);
+        assert_eq!(
+            snapshot.entities[&goblin]["Name"].version.to_string(),
+            "2.0.0"
+        );
+
+        // Snapshots survive being written out.
+        let snapshot: WorldSnapshot =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+        let destination = backend();
+        import(&destination, snapshot.clone(), ImportMode::Merge).unwrap();
+
+        let mut entities = destination.list_entities().unwrap();
+        entities.sort();
+        let mut expected = vec![goblin, orc];
+        expected.sort();
+        assert_eq!(entities, expected);
+        assert_eq!(
+            destination.peek::<(&Position, &Name)>(goblin).unwrap(),
+            Some((Position { x: 1.0, y: 2.0 }, Name("Goblin".to_string())))
+        );
+        assert_eq!(
+            destination.peek::<&Name>(orc).unwrap(),
+            Some(Name("Orc".to_string()))
+        );
+        assert_eq!(
+            destination.peek_resource::<&Settings>().unwrap(),
+            Some(Settings { tick_rate: 60 })
+        );
+        assert_eq!(export(&destination).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn import_into_populated_world() {
+        let source = backend();
+        let (goblin, orc) = populate(&source);
+        let snapshot = export(&source).unwrap();
+
+        let merged = backend();
+        let troll = Entity::new();
+        merged
+            .put(goblin, (Name("Renamed".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        merged
+            .put(troll, (Name("Troll".to_string()),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        merged.put_resource(Settings { tick_rate: 30 }).unwrap();
+
+        // Merging overwrites the snapshot's components, and keeps the rest.
+        import(&merged, snapshot.clone(), ImportMode::Merge).unwrap();
+        assert_eq!(merged.list_entities().unwrap().len(), 3);
+        assert_eq!(
+            merged.peek::<&Name>(goblin).unwrap(),
+            Some(Name("Goblin".to_string()))
+        );
+        assert_eq!(
+            merged.peek::<&Name>(troll).unwrap(),
+            Some(Name("Troll".to_string()))
+        );
+        assert_eq!(
+            merged.peek_resource::<&Settings>().unwrap(),
+            Some(Settings { tick_rate: 60 })
+        );
+
+        // Replacing leaves nothing but the snapshot.
+        merged
+            .put(troll, (Position { x: 0.0, y: 0.0 },))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        import(&merged, snapshot.clone(), ImportMode::Replace).unwrap();
+        let mut entities = merged.list_entities().unwrap();
+        entities.sort();
+        let mut expected = vec![goblin, orc];
+        expected.sort();
+        assert_eq!(entities, expected);
+        assert!(!merged.exists(troll).unwrap());
+        assert_eq!(export(&merged).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn reject_mismatched_format() {
+        let source = backend();
+        populate(&source);
+        let snapshot = export(&source).unwrap();
+
+        let destination = Backend::<Bincode>::from_joint(SqliteBackend::memory().unwrap());
+        let err = import(&destination, snapshot, ImportMode::Replace).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            BackendError::Access(AccessError::FormatMismatch { .. })
+        ));
+        assert!(destination.list_entities().unwrap().is_empty());
+    }
+
+    #[test]
+    fn respect_locks() {
+        let source = backend();
+        let (goblin, _) = populate(&source);
+
+        let lock = source.lock_entity(goblin, Duration::from_secs(60)).unwrap();
+        assert!(export(&source).is_err());
+        let snapshot = {
+            drop(lock);
+            export(&source).unwrap()
+        };
+
+        let destination = backend();
+        let held = destination
+            .lock_entity(goblin, Duration::from_secs(60))
+            .unwrap();
+        assert!(import(&destination, snapshot.clone(), ImportMode::Merge).is_err());
+        drop(held);
+        import(&destination, snapshot, ImportMode::Merge).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, Backend, BackendError, ExtractionDescriptor, Format, ResultExt,
+        SerializedComponent,
+    },
+    Component, Entity,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Deserializes stored contents into a value which can be serialized by any
+/// other format.
+pub type Decoder = fn(&[u8]) -> Result<Box<dyn erased_serde::Serialize>, AccessError>;
+
+/// Decoders for components stored in the format, keyed by their
+/// `COMPONENT_TYPE`. Re-serializing a component takes its concrete type,
+/// which only the caller knows.
+pub struct DecoderRegistry<F: Format> {
+    decoders: BTreeMap<String, Decoder>,
+    format: PhantomData<F>,
+}
+
+impl<F: Format> Default for DecoderRegistry<F> {
+    fn default() -> Self {
+        DecoderRegistry {
+            decoders: BTreeMap::new(),
+            format: PhantomData,
+        }
+    }
+}
+
+impl<F: Format> DecoderRegistry<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T: Component + Serialize + DeserializeOwned + 'static>(self) -> Self {
+        self.with_decoder(T::COMPONENT_TYPE, |contents| {
+            let value: T = F::deserialize(&contents.to_vec().into())?;
+            Ok(Box::new(value))
+        })
+    }
+
+    /// Decodes components of the given name with a hand-written decoder,
+    /// for components whose type is no longer around.
+    pub fn with_decoder<N: Into<String>>(mut self, component: N, decoder: Decoder) -> Self {
+        self.decoders.insert(component.into(), decoder);
+        self
+    }
+
+    pub fn contains(&self, component: &str) -> bool {
+        self.decoders.contains_key(component)
+    }
+
+    fn decode(
+        &self,
+        component: &str,
+        contents: &[u8],
+    ) -> Result<Box<dyn erased_serde::Serialize>, AccessError> {
+        let decoder = self
+            .decoders
+            .get(component)
+            .ok_or_else(|| AccessError::UnknownComponent(component.to_string()))?;
+
+        decoder(contents)
+    }
+}
+
+/// A component which could not be carried over to the destination.
+#[derive(Debug)]
+pub struct MigrationFailure {
+    pub entity: Entity,
+    pub component: String,
+    pub error: AccessError,
+}
+
+/// Outcome of moving every component from one format to another.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// Number of components written to the destination, by name.
+    pub migrated: BTreeMap<String, u64>,
+    /// Components which were left behind, because they had no decoder or
+    /// could not be converted.
+    pub failures: Vec<MigrationFailure>,
+}
+
+impl MigrationReport {
+    /// Whether every component was carried over.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Copies every component of every entity in the source into the
+/// destination, converting it from the source's format to the
+/// destination's. Components keep the version and schema hash they were
+/// stored with, so the destination's migrations and schema checks still
+/// apply to them.
+///
+/// Components which cannot be converted are listed in the report, while
+/// the other components of the same entity are still written. Failing to
+/// list, read or write entities aborts the migration, leaving the entities
+/// written so far in the destination, which can safely be migrated again.
+pub fn migrate_format<Src: Format, Dst: Format>(
+    source: &Backend<Src>,
+    destination: &Backend<Dst>,
+    decoders: &DecoderRegistry<Src>,
+) -> Result<MigrationReport, BackendError> {
+    let mut report = MigrationReport::default();
+
+    for entity in source.list_entities().ctx("listing entities to migrate")? {
+        let descriptors = source
+            .list_components(entity)
+            .ctx(format!("listing components of {entity}"))?
+            .into_iter()
+            .map(|name| ExtractionDescriptor {
+                name,
+                version: None,
+                schema: None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut converted = Vec::new();
+        for component in source
+            .read_components(entity, descriptors)
+            .ctx(format!("reading components of {entity}"))?
+            .into_iter()
+            .flatten()
+        {
+            let contents = decoders
+                .decode(&component.name, component.contents.as_ref())
+                .and_then(|value| Dst::serialize(value));
+
+            match contents {
+                Ok(contents) => converted.push(SerializedComponent {
+                    contents,
+                    name: component.name,
+                    version: component.version,
+                    schema: component.schema,
+                    metadata: None,
+                }),
+                Err(error) => report.failures.push(MigrationFailure {
+                    entity,
+                    component: component.name,
+                    error,
+                }),
+            }
+        }
+
+        if converted.is_empty() {
+            continue;
+        }
+
+        let names: Vec<_> = converted.iter().map(|c| c.name.clone()).collect();
+        destination
+            .write_components(entity, converted)
+            .ctx(format!("writing migrated components of {entity}"))?;
+
+        for name in names {
+            *report.migrated.entry(name).or_default() += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{AccessError, Backend},
+        Component, Entity,
+    };
+    use eci_format_bincode::Bincode;
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{InsertReport, TypedBackend};
+
+    use super::{migrate_format, DecoderRegistry};
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Name(pub String);
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Health(pub u32);
+
+    #[test]
+    fn json_to_bincode() {
+        let source = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let destination = Backend::<Bincode>::from_joint(SqliteBackend::memory().unwrap());
+
+        let entities: Vec<_> = (0..10).map(|_| Entity::new()).collect();
+        for (index, entity) in entities.iter().enumerate() {
+            let position = Position {
+                x: index as f32,
+                y: -(index as f32),
+            };
+
+            if index % 2 == 0 {
+                source
+                    .put(*entity, (position, Name(format!("entity {index}"))))
+                    .and_then(InsertReport::into_result)
+                    .unwrap();
+            } else {
+                source
+                    .put(*entity, (position,))
+                    .and_then(InsertReport::into_result)
+                    .unwrap();
+            }
+        }
+
+        let decoders = DecoderRegistry::<Json>::new()
+            .register::<Position>()
+            .register::<Name>();
+        let report = migrate_format(&source, &destination, &decoders).unwrap();
+
+        assert!(report.is_complete());
+        assert_eq!(report.migrated.get("Position"), Some(&10));
+        assert_eq!(report.migrated.get("Name"), Some(&5));
+
+        for (index, entity) in entities.iter().enumerate() {
+            assert_eq!(
+                destination.peek::<&Position>(*entity).unwrap(),
+                Some(Position {
+                    x: index as f32,
+                    y: -(index as f32),
+                })
+            );
+            assert_eq!(
+                destination.peek::<&Name>(*entity).unwrap(),
+                (index % 2 == 0).then(|| Name(format!("entity {index}")))
+            );
+        }
+    }
+
+    #[test]
+    fn report_failures() {
+        let source = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let destination = Backend::<Bincode>::from_joint(SqliteBackend::memory().unwrap());
+
+        let entity = Entity::new();
+        source
+            .put(
+                entity,
+                (
+                    Position { x: 1.0, y: 2.0 },
+                    Name("Probe".to_string()),
+                    Health(10),
+                ),
+            )
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        // Health was since removed from the code, and its hand-written
+        // decoder gives up on it.
+        let decoders = DecoderRegistry::<Json>::new()
+            .register::<Position>()
+            .with_decoder("Health", |_| {
+                Err(AccessError::serialization(std::fmt::Error))
+            });
+        let report = migrate_format(&source, &destination, &decoders).unwrap();
+
+        assert!(!report.is_complete());
+        assert_eq!(report.migrated.get("Position"), Some(&1));
+        assert_eq!(report.migrated.len(), 1);
+
+        let mut failures: Vec<_> = report.failures.iter().collect();
+        failures.sort_by(|a, b| a.component.cmp(&b.component));
+        assert_eq!(failures.len(), 2);
+        assert!(failures.iter().all(|failure| failure.entity == entity));
+        assert!(matches!(&failures[0].error, AccessError::Serialization(_)));
+        assert!(matches!(
+            &failures[1].error,
+            AccessError::UnknownComponent(name) if name == "Name"
+        ));
+
+        // The rest of the entity is carried over regardless.
+        assert_eq!(
+            destination.peek::<&Position>(entity).unwrap(),
+            Some(Position { x: 1.0, y: 2.0 })
+        );
+        assert_eq!(destination.peek::<&Name>(entity).unwrap(), None);
+    }
+}
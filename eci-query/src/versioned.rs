@@ -0,0 +1,171 @@
+use eci_core::Component;
+use serde::de::DeserializeOwned;
+
+use crate::sealed;
+
+/// A component along with the revision it was read at, see
+/// [`crate::TypedBackend::get_versioned`]. Writing it back with
+/// [`crate::TypedBackend::put_if_unchanged`] only succeeds if no one else
+/// has written the component in the meantime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Versioned<T> {
+    pub value: T,
+    pub revision: u64,
+}
+
+impl<T> Versioned<T> {
+    /// Replaces the value, keeping the revision it was read at.
+    pub fn map<U, Func: FnOnce(T) -> U>(self, func: Func) -> Versioned<U> {
+        Versioned {
+            value: func(self.value),
+            revision: self.revision,
+        }
+    }
+}
+
+/// Selections of a single component, which can be read along with their
+/// revision. Implemented for `&T`.
+pub trait VersionedSelection: sealed::Selection {
+    type Component: Component + DeserializeOwned;
+}
+
+impl<T> VersionedSelection for &T
+where
+    T: Component + DeserializeOwned,
+{
+    type Component = T;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+
+    use eci_backend_memory::MemoryBackend;
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{AccessError, Backend, BackendError},
+        Component, Entity,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{InsertReport, TypedBackend};
+
+    #[derive(Debug, Clone, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Balance(pub i64);
+
+    #[test]
+    fn write_unless_changed() {
+        let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+        let entity = Entity::new();
+        assert_eq!(backend.get_versioned::<&Balance>(entity).unwrap(), None);
+
+        backend
+            .put(entity, (Balance(10),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+        let read = backend.get_versioned::<&Balance>(entity).unwrap().unwrap();
+        assert_eq!(read.value, Balance(10));
+        assert_eq!(read.revision, 1);
+
+        backend
+            .put_if_unchanged(entity, read.clone().map(|Balance(b)| Balance(b + 5)))
+            .unwrap();
+        let reread = backend.get_versioned::<&Balance>(entity).unwrap().unwrap();
+        assert_eq!(reread.value, Balance(15));
+        assert_eq!(reread.revision, 2);
+
+        // Writing back the first read would lose the change made since.
+        let err = backend.put_if_unchanged(entity, read).unwrap_err();
+        assert!(matches!(
+            err,
+            AccessError::StaleWrite {
+                current: Some(2),
+                ..
+            }
+        ));
+
+        // Plain updates move the revision on as well.
+        backend.update(entity, (Balance(0),)).unwrap();
+        assert!(backend.put_if_unchanged(entity, reread).is_err());
+
+        backend.remove::<(Balance,)>(entity).unwrap();
+        let err = backend
+            .put_if_unchanged(
+                entity,
+                crate::Versioned {
+                    value: Balance(1),
+                    revision: 3,
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, AccessError::StaleWrite { current: None, .. }));
+    }
+
+    #[test]
+    fn single_writer_wins() {
+        // A `Backend` cannot be sent to another thread, so each writer opens
+        // its own on a shared file.
+        let path = std::env::temp_dir().join(format!("eci-versioned-{}.db", Entity::new()));
+        let entity = Entity::new();
+        Backend::<Json>::from_joint(SqliteBackend::file(&path).unwrap())
+            .put(entity, (Balance(100),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let barrier = Arc::new(Barrier::new(2));
+        let writers: Vec<_> = [-30, -80]
+            .into_iter()
+            .map(|amount| {
+                let (path, barrier) = (path.clone(), barrier.clone());
+                std::thread::spawn(move || {
+                    let backend = Backend::<Json>::from_joint(SqliteBackend::file(path).unwrap());
+                    let read = backend.get_versioned::<&Balance>(entity).unwrap().unwrap();
+
+                    // Both writers have read the balance before either writes.
+                    barrier.wait();
+                    backend
+                        .put_if_unchanged(entity, read.map(|Balance(b)| Balance(b + amount)))
+                        .map_err(|err| match err {
+                            AccessError::StaleWrite {
+                                entity, current, ..
+                            } => (entity, current),
+                            other => panic!("expected a stale write, got {other:?}"),
+                        })
+                })
+            })
+            .collect();
+
+        let results: Vec<_> = writers
+            .into_iter()
+            .map(|writer| writer.join().unwrap())
+            .collect();
+
+        let backend = Backend::<Json>::from_joint(SqliteBackend::file(&path).unwrap());
+        let stored = backend.get_versioned::<&Balance>(entity).unwrap().unwrap();
+        assert_eq!(stored.revision, 2);
+        assert!([Balance(70), Balance(20)].contains(&stored.value));
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        let loser = results.into_iter().find_map(Result::err).unwrap();
+        assert_eq!(loser, (entity, Some(2)));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unsupported_backends() {
+        let backend = Backend::<Json>::from_joint(MemoryBackend::default());
+        let entity = Entity::new();
+        backend
+            .put(entity, (Balance(1),))
+            .and_then(InsertReport::into_result)
+            .unwrap();
+
+        let err = backend.get_versioned::<&Balance>(entity).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            BackendError::Access(AccessError::Unsupported(_))
+        ));
+    }
+}
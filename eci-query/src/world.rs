@@ -0,0 +1,224 @@
+use eci_core::{
+    backend::{AccessBackend, AccessError, Backend, BackendError, Format, SerializedComponent},
+    Entity,
+};
+
+use crate::{
+    Extractor, InsertReport, Inserter, Locked, Query, ReadOnly, RefCast, Remover, TypedBackend,
+};
+
+/// A single entry point to a backend, for spawning entities and reading
+/// and writing their components. Everything else [`TypedBackend`] offers is
+/// available through [`World::backend`].
+///
+/// ```
+/// # use eci_backend_sqlite::SqliteBackend;
+/// # use eci_core::{backend::Backend, Component};
+/// # use eci_format_json::Json;
+/// # use eci_query::World;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Component, Serialize, Deserialize)]
+/// struct Health(u32);
+///
+/// let world = World::new(Backend::<Json>::from_joint(SqliteBackend::memory().unwrap()));
+/// let player = world.spawn().insert((Health(100),)).id().unwrap();
+/// assert_eq!(world.peek::<&Health>(player).unwrap().unwrap().0, 100);
+/// ```
+#[derive(Clone)]
+pub struct World<F: Format> {
+    backend: Backend<F>,
+}
+
+impl<F: Format> World<F> {
+    pub fn new(backend: Backend<F>) -> Self {
+        World { backend }
+    }
+
+    pub fn backend(&self) -> &Backend<F> {
+        &self.backend
+    }
+
+    /// Starts building a new entity, created by the backend's generator,
+    /// see [`Backend::with_entity_ids`]. Its components are written
+    /// together once [`EntityBuilder::id`] is called.
+    pub fn spawn(&self) -> EntityBuilder<'_, F> {
+        EntityBuilder {
+            world: self,
+            entity: self.backend.new_entity(),
+            components: Vec::new(),
+        }
+    }
+
+    /// See [`TypedBackend::put`].
+    pub fn insert<T: Inserter>(
+        &self,
+        entity: Entity,
+        components: T,
+    ) -> Result<InsertReport, AccessError> {
+        self.backend.put(entity, components)
+    }
+
+    /// See [`TypedBackend::get`].
+    pub fn get<Select>(&self, entity: Entity) -> Result<Option<Locked<Select, F>>, BackendError>
+    where
+        Select: Extractor + RefCast<Owned = <Select as Extractor>::Owned>,
+    {
+        self.backend.get(entity)
+    }
+
+    /// See [`TypedBackend::peek`].
+    pub fn peek<Select>(&self, entity: Entity) -> Result<Option<Select::Owned>, BackendError>
+    where
+        Select: Extractor + ReadOnly,
+    {
+        self.backend.peek::<Select>(entity)
+    }
+
+    /// See [`TypedBackend::remove`].
+    pub fn remove<T: Remover>(&self, entity: Entity) -> Result<T::Removed, BackendError> {
+        self.backend.remove::<T>(entity)
+    }
+
+    /// See [`TypedBackend::query`].
+    pub fn query<Select: Extractor>(&self) -> Query<'_, F, Select> {
+        self.backend.query::<Select>()
+    }
+}
+
+impl<F: Format> From<Backend<F>> for World<F> {
+    fn from(backend: Backend<F>) -> Self {
+        World::new(backend)
+    }
+}
+
+/// Collects the components of a new entity, see [`World::spawn`]. Nothing
+/// is written until [`EntityBuilder::id`] is called, so dropping the
+/// builder leaves the entity unspawned.
+#[must_use = "the entity is only written once `id` is called"]
+pub struct EntityBuilder<'a, F: Format> {
+    world: &'a World<F>,
+    entity: Entity,
+    components: Vec<SerializedComponent<F>>,
+}
+
+impl<F: Format> EntityBuilder<'_, F> {
+    pub fn insert<T: Inserter>(mut self, components: T) -> Self {
+        self.components.extend(components.insert::<F>());
+        self
+    }
+
+    /// Writes the components inserted so far in a single request, and
+    /// returns the new entity. Fails with [`AccessError::Conflict`] if the
+    /// same component was inserted more than once, in which case backends
+    /// which [support atomic writes] write none of them.
+    ///
+    /// [support atomic writes]: AccessBackend::supports_atomic_writes
+    pub fn id(self) -> Result<Entity, AccessError> {
+        if !self.components.is_empty() {
+            self.world
+                .backend
+                .write_components(self.entity, self.components)?;
+        }
+
+        Ok(self.entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eci_backend_sqlite::SqliteBackend;
+    use eci_core::{
+        backend::{AccessBackend, AccessError, Backend},
+        Component, Entity,
+    };
+    use eci_format_json::Json;
+    use serde::{Deserialize, Serialize};
+
+    use super::World;
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Name(pub String);
+
+    #[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+    struct Health(pub u32);
+
+    fn world() -> World<Json> {
+        World::new(Backend::<Json>::from_joint(
+            SqliteBackend::memory().unwrap(),
+        ))
+    }
+
+    #[test]
+    fn spawn_entities() {
+        let world = world();
+        let builder = world
+            .spawn()
+            .insert((Name("Goblin".to_string()),))
+            .insert((Health(5),));
+        assert!(world.backend().list_entities().unwrap().is_empty());
+
+        let goblin = builder.id().unwrap();
+        assert_eq!(
+            world.peek::<(&Name, &Health)>(goblin).unwrap(),
+            Some((Name("Goblin".to_string()), Health(5)))
+        );
+
+        let empty = world.spawn().id().unwrap();
+        assert_ne!(empty, goblin);
+        assert_eq!(world.backend().list_entities().unwrap(), vec![goblin]);
+
+        let duplicated = world.spawn().insert((Health(1),)).insert((Health(2),)).id();
+        assert!(matches!(duplicated, Err(AccessError::Conflict(..))));
+    }
+
+    #[test]
+    fn insert_get_and_remove() {
+        let world = world();
+        let orc = Entity::new();
+        assert!(world
+            .insert(orc, (Name("Orc".to_string()), Health(20)))
+            .unwrap()
+            .is_complete());
+
+        let mut health = world.get::<&mut Health>(orc).unwrap().unwrap();
+        health.deref().0 -= 5;
+        health.commit().unwrap();
+        assert_eq!(world.peek::<&Health>(orc).unwrap(), Some(Health(15)));
+
+        assert_eq!(world.remove::<(Health,)>(orc).unwrap(), (Some(Health(15)),));
+        assert_eq!(world.peek::<&Health>(orc).unwrap(), None);
+        assert_eq!(
+            world.peek::<&Name>(orc).unwrap(),
+            Some(Name("Orc".to_string()))
+        );
+    }
+
+    #[test]
+    fn query_entities() {
+        let world = world();
+        let mut spawned = Vec::new();
+        for (name, health) in [("Goblin", 5), ("Orc", 20), ("Troll", 9)] {
+            spawned.push(
+                world
+                    .spawn()
+                    .insert((Name(name.to_string()), Health(health)))
+                    .id()
+                    .unwrap(),
+            );
+        }
+
+        let mut wounded = world
+            .query::<&Name>()
+            .filter(Health::field("").lt(10))
+            .peek()
+            .unwrap();
+        wounded.sort_by(|a, b| a.1 .0.cmp(&b.1 .0));
+        assert_eq!(
+            wounded,
+            vec![
+                (spawned[0], Name("Goblin".to_string())),
+                (spawned[2], Name("Troll".to_string())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, SerializedComponent},
+    Component, Entity, Version,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Name(pub String);
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Health(pub u32);
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Mana(pub u32);
+
+#[test]
+fn check_presence() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    backend
+        .put(entity, (Name("Probe".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    // Health cannot be deserialized, which checking for it never tries.
+    backend
+        .write_components(
+            entity,
+            vec![SerializedComponent {
+                contents: b"not json".to_vec(),
+                name: Health::COMPONENT_TYPE.to_string(),
+                version: Version::default(),
+                schema: None,
+                metadata: None,
+            }],
+        )
+        .unwrap();
+    assert!(backend.peek::<&Health>(entity).is_err());
+
+    assert!(backend.exists(entity).unwrap());
+    assert!(backend.has::<Name>(entity).unwrap());
+    assert!(backend.has::<Health>(entity).unwrap());
+    assert!(!backend.has::<Mana>(entity).unwrap());
+    assert_eq!(
+        backend
+            .contains(
+                entity,
+                &["Mana".to_string(), "Name".to_string(), "Health".to_string()]
+            )
+            .unwrap(),
+        vec![false, true, true]
+    );
+
+    let missing = Entity::new();
+    assert!(!backend.exists(missing).unwrap());
+    assert!(!backend.has::<Name>(missing).unwrap());
+}
+
+#[test]
+fn ignore_locks() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    let entity = Entity::new();
+    backend
+        .put(entity, (Name("Probe".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+
+    let locked = backend.get::<&mut Name>(entity).unwrap().unwrap();
+    assert!(backend.has::<Name>(entity).unwrap());
+    assert!(backend.exists(entity).unwrap());
+
+    // Nor do the checks take locks of their own.
+    drop(locked);
+    backend.get::<&mut Name>(entity).unwrap().unwrap();
+}
@@ -0,0 +1,96 @@
+use eci_backend_sqlite::SqliteBackend;
+use eci_core::{
+    backend::{AccessBackend, Backend, BackendError, LockingError},
+    Component, Entity,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+use eci_query::{InsertReport, TypedBackend};
+
+#[derive(Debug, Clone, Component, Serialize, Deserialize, PartialEq)]
+struct SimulationSettings {
+    tick_rate: u32,
+    gravity: f32,
+}
+
+#[derive(Debug, Component, Serialize, Deserialize, PartialEq, Eq)]
+struct Name(pub String);
+
+fn settings(tick_rate: u32) -> SimulationSettings {
+    SimulationSettings {
+        tick_rate,
+        gravity: 9.81,
+    }
+}
+
+#[test]
+fn store_resources() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    assert_eq!(
+        backend.peek_resource::<&SimulationSettings>().unwrap(),
+        None
+    );
+
+    backend.put_resource(settings(60)).unwrap();
+    backend.put_resource(Name("World".to_string())).unwrap();
+    assert_eq!(
+        backend.peek_resource::<&SimulationSettings>().unwrap(),
+        Some(settings(60))
+    );
+
+    backend.put_resource(settings(30)).unwrap();
+    assert_eq!(
+        backend
+            .peek_resource::<(&SimulationSettings, &Name)>()
+            .unwrap(),
+        Some((settings(30), Name("World".to_string())))
+    );
+
+    // Resources are not part of any entity.
+    assert!(backend.list_entities().unwrap().is_empty());
+    let entity = Entity::new();
+    backend
+        .put(entity, (Name("Probe".to_string()),))
+        .and_then(InsertReport::into_result)
+        .unwrap();
+    assert_eq!(backend.list_entities().unwrap(), vec![entity]);
+    assert_eq!(backend.entities_with("Name").unwrap(), vec![entity]);
+    assert_eq!(backend.query::<&Name>().entities().unwrap(), vec![entity]);
+    assert!(backend
+        .query::<&SimulationSettings>()
+        .peek()
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn concurrent_resource_writers_conflict() {
+    let backend = Backend::<Json>::from_joint(SqliteBackend::memory().unwrap());
+    backend.put_resource(settings(60)).unwrap();
+
+    let mut first = backend
+        .get_resource::<&mut SimulationSettings>()
+        .unwrap()
+        .unwrap();
+
+    let second = backend.get_resource::<&mut SimulationSettings>();
+    assert!(matches!(
+        second.err().as_ref().map(BackendError::root),
+        Some(BackendError::Locking(LockingError::Conflict(..)))
+    ));
+    assert!(backend.put_resource(settings(10)).is_err());
+
+    first.deref().tick_rate = 120;
+    first.commit().unwrap();
+    assert_eq!(
+        backend.peek_resource::<&SimulationSettings>().unwrap(),
+        Some(settings(120))
+    );
+
+    backend.put_resource(settings(10)).unwrap();
+    assert_eq!(
+        backend.peek_resource::<&SimulationSettings>().unwrap(),
+        Some(settings(10))
+    );
+}
@@ -0,0 +1,247 @@
+//! Storing, reading and listing components through [`AccessBackend`].
+
+use std::time::Duration;
+
+use eci_core::{
+    backend::{
+        AccessBackend, AccessError, ComponentStats, ExtractionDescriptor, Format, LockDescriptor,
+        LockingBackend, LockingMode, SerializedComponent,
+    },
+    Entity, Version,
+};
+use eci_format_json::Json;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct DebugComponentA {
+    content: String,
+}
+
+fn component(name: &str, content: &str) -> SerializedComponent<Json> {
+    SerializedComponent::<Json> {
+        contents: Json::serialize(DebugComponentA {
+            content: content.to_string(),
+        })
+        .unwrap(),
+        name: name.to_string(),
+        version: Version::default(),
+        schema: None,
+        metadata: None,
+    }
+}
+
+fn extract(name: &str) -> ExtractionDescriptor {
+    ExtractionDescriptor {
+        name: name.to_string(),
+        version: None,
+        schema: None,
+    }
+}
+
+fn content(component: &Option<SerializedComponent<Json>>) -> String {
+    Json::deserialize::<DebugComponentA>(&component.as_ref().unwrap().contents)
+        .unwrap()
+        .content
+}
+
+pub fn fail_on_duplicate_components<B: AccessBackend<Json>>(conn: B) {
+    let entity = Entity::new();
+
+    assert!(matches!(
+        conn.write_components(
+            entity,
+            vec![
+                component("DebugComponentA", "Hello"),
+                component("DebugComponentB", "Hello"),
+                component("DebugComponentA", "Hello"),
+            ],
+        ),
+        Err(AccessError::Conflict(_, name)) if name == "DebugComponentA"
+    ));
+
+    // Nothing from the failed write was stored.
+    assert!(conn.list_components(entity).unwrap().is_empty());
+
+    conn.write_components(entity, vec![component("DebugComponentA", "Hello")])
+        .unwrap();
+    assert!(matches!(
+        conn.write_components(entity, vec![component("DebugComponentA", "World")]),
+        Err(AccessError::Conflict(..))
+    ));
+}
+
+pub fn read_update_and_remove_components<B: AccessBackend<Json>>(conn: B) {
+    let entity = Entity::new();
+
+    conn.write_components(
+        entity,
+        vec![
+            component("DebugComponentA", "Hello"),
+            component("DebugComponentC", "Untouched"),
+        ],
+    )
+    .unwrap();
+    conn.update_components(entity, vec![component("DebugComponentA", "World")])
+        .unwrap();
+
+    let comps: Vec<Option<SerializedComponent<Json>>> = conn
+        .read_components(
+            entity,
+            vec![
+                extract("DebugComponentA"),
+                extract("DebugComponentA"),
+                extract("DebugComponentB"),
+                extract("DebugComponentC"),
+            ],
+        )
+        .unwrap();
+    assert_eq!(content(&comps[0]), "World");
+    assert_eq!(content(&comps[1]), "World");
+    assert!(comps[2].is_none());
+    assert_eq!(content(&comps[3]), "Untouched");
+
+    let removed: Vec<Option<SerializedComponent<Json>>> = conn
+        .remove_components(
+            entity,
+            vec![extract("DebugComponentA"), extract("DebugComponentB")],
+        )
+        .unwrap();
+    assert_eq!(content(&removed[0]), "World");
+    assert!(removed[1].is_none());
+    assert_eq!(
+        conn.list_components(entity).unwrap(),
+        vec!["DebugComponentC"]
+    );
+}
+
+pub fn list_entities_and_components<B: AccessBackend<Json>>(conn: B) {
+    let (first, second) = (Entity::new(), Entity::new());
+    conn.write_components_batch(vec![
+        (
+            first,
+            vec![
+                component("DebugComponentB", "b"),
+                component("DebugComponentA", "a"),
+            ],
+        ),
+        (second, vec![component("DebugComponentB", "b")]),
+    ])
+    .unwrap();
+
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(conn.list_entities().unwrap(), expected);
+    assert_eq!(conn.entities_with("DebugComponentB").unwrap(), expected);
+    assert_eq!(
+        conn.list_components(first).unwrap(),
+        vec!["DebugComponentA", "DebugComponentB"]
+    );
+
+    // Entities without components are gone.
+    conn.remove_components(second, vec![extract("DebugComponentB")])
+        .unwrap();
+    assert_eq!(conn.list_entities().unwrap(), vec![first]);
+}
+
+pub fn component_stats<B: AccessBackend<Json>>(conn: B) {
+    for content in ["a", "bb", "ccc"] {
+        conn.write_components(
+            Entity::new(),
+            vec![
+                component("DebugComponentA", content),
+                component("DebugComponentB", "b"),
+            ],
+        )
+        .unwrap();
+    }
+
+    // {"content":"a"} and so on.
+    let bytes = |content: &str| 14 + content.len() as u64;
+    assert_eq!(
+        conn.stats().unwrap(),
+        vec![
+            ComponentStats {
+                name: "DebugComponentA".to_string(),
+                entity_count: 3,
+                total_bytes: bytes("a") + bytes("bb") + bytes("ccc"),
+            },
+            ComponentStats {
+                name: "DebugComponentB".to_string(),
+                entity_count: 3,
+                total_bytes: 3 * bytes("b"),
+            },
+        ]
+    );
+}
+
+pub fn reject_version_mismatch<B: AccessBackend<Json>>(conn: B) {
+    let entity = Entity::new();
+    conn.write_components(entity, vec![component("DebugComponentA", "Hello")])
+        .unwrap();
+
+    let expecting = |version| {
+        vec![ExtractionDescriptor {
+            name: "DebugComponentA".to_string(),
+            version: Some(version),
+            schema: None,
+        }]
+    };
+
+    assert!(matches!(
+        conn.read_components(entity, expecting(Version::new(2, 0, 0))),
+        Err(AccessError::VersionMismatch { stored, .. }) if stored == Version::default()
+    ));
+    assert!(matches!(
+        conn.remove_components(entity, expecting(Version::new(2, 0, 0))),
+        Err(AccessError::VersionMismatch { .. })
+    ));
+    conn.remove_components(entity, expecting(Version::default()))
+        .unwrap();
+}
+
+pub fn reject_invalid_and_empty_requests<B: AccessBackend<Json>>(conn: B) {
+    let entity = Entity::new();
+
+    assert!(matches!(
+        conn.write_components(entity, vec![component("Debug'Component", "Hello")]),
+        Err(AccessError::InvalidComponentName(name)) if name == "Debug'Component"
+    ));
+    assert!(matches!(
+        conn.read_components(entity, vec![extract("1Component")]),
+        Err(AccessError::InvalidComponentName(_))
+    ));
+    assert!(matches!(
+        conn.write_components(entity, Vec::<SerializedComponent<Json>>::new()),
+        Err(AccessError::EmptyRequest)
+    ));
+    assert!(matches!(
+        conn.read_components(entity, vec![]),
+        Err(AccessError::EmptyRequest)
+    ));
+}
+
+pub fn locked_write_requires_write_lock<B: AccessBackend<Json> + LockingBackend>(conn: B) {
+    let entity = Entity::new();
+    let lock = |mode| {
+        conn.acquire_lock(
+            entity,
+            vec![LockDescriptor {
+                mode,
+                name: "DebugComponentA".to_string(),
+            }],
+            Duration::from_secs(60).into(),
+        )
+        .unwrap()
+    };
+
+    let read = lock(LockingMode::Read);
+    assert!(matches!(
+        conn.write_components_locked(entity, vec![component("DebugComponentA", "Hello")], &read),
+        Err(AccessError::LockRequired(..))
+    ));
+    conn.release_lock(read).unwrap();
+
+    let write = lock(LockingMode::Write);
+    conn.write_components_locked(entity, vec![component("DebugComponentA", "Hello")], &write)
+        .unwrap();
+}
@@ -0,0 +1,60 @@
+//! Tests every backend is expected to pass. Backends instantiate them in
+//! their own test modules with [`locking_conformance!`] and
+//! [`access_conformance!`], passing an expression which creates a fresh,
+//! empty backend for each test.
+
+pub mod access;
+pub mod locking;
+
+/// Runs the [`locking`] tests against the backend created by the given
+/// expression, which must implement [`JointBackend<Json>`](eci_core::backend::JointBackend).
+#[macro_export]
+macro_rules! locking_conformance {
+    ($backend:expr) => {
+        $crate::conformance!(
+            locking,
+            $backend,
+            conflicting_locks,
+            entity_lock_conflicts,
+            locks_expire,
+            renew_lock,
+            upgrade_lock,
+            multi_entity_locks,
+            reject_empty_lock_requests,
+            reject_out_of_range_expiry,
+            never_expiring_lock
+        );
+    };
+}
+
+/// Runs the [`access`] tests against the backend created by the given
+/// expression, which must implement [`JointBackend<Json>`](eci_core::backend::JointBackend).
+#[macro_export]
+macro_rules! access_conformance {
+    ($backend:expr) => {
+        $crate::conformance!(
+            access,
+            $backend,
+            fail_on_duplicate_components,
+            read_update_and_remove_components,
+            list_entities_and_components,
+            component_stats,
+            reject_version_mismatch,
+            reject_invalid_and_empty_requests,
+            locked_write_requires_write_lock
+        );
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! conformance {
+    ($module:ident, $backend:expr, $($test:ident),+) => {
+        $(
+            #[test]
+            fn $test() {
+                $crate::$module::$test($backend);
+            }
+        )+
+    };
+}
@@ -0,0 +1,265 @@
+//! Acquiring, renewing and releasing locks through [`LockingBackend`].
+
+use std::time::Duration;
+
+use eci_core::{
+    backend::{
+        Backend, ConflictingLock, Expiry, JointBackend, LockDescriptor, LockStats, LockingBackend,
+        LockingError, LockingMode,
+    },
+    Entity,
+};
+use eci_format_json::Json;
+use uuid::Uuid;
+
+const LOCK_TIME: Duration = Duration::from_secs(60);
+
+fn lock(mode: LockingMode, name: &str) -> Vec<LockDescriptor> {
+    vec![LockDescriptor {
+        mode,
+        name: name.to_string(),
+    }]
+}
+
+fn write_lock() -> Vec<LockDescriptor> {
+    lock(LockingMode::Write, "DebugComponentA")
+}
+
+fn read_lock() -> Vec<LockDescriptor> {
+    lock(LockingMode::Read, "DebugComponentA")
+}
+
+pub fn conflicting_locks<B: LockingBackend>(conn: B) {
+    let entity = Entity::new();
+
+    let _a = conn
+        .acquire_lock(entity, read_lock(), LOCK_TIME.into())
+        .unwrap();
+    let _b = conn
+        .acquire_lock(entity, read_lock(), LOCK_TIME.into())
+        .unwrap();
+    let _c = conn
+        .acquire_lock(
+            entity,
+            lock(LockingMode::Write, "DebugComponentB"),
+            LOCK_TIME.into(),
+        )
+        .unwrap();
+
+    assert!(matches!(
+        conn.acquire_lock(entity, write_lock(), LOCK_TIME.into()),
+        Err(LockingError::Conflict(_, name, LockingMode::Write, Some(ConflictingLock { mode: LockingMode::Read, .. })))
+            if name == "DebugComponentA"
+    ));
+    assert!(matches!(
+        conn.acquire_lock(entity, lock(LockingMode::Read, "DebugComponentB"), LOCK_TIME.into()),
+        Err(LockingError::Conflict(_, name, LockingMode::Read, Some(ConflictingLock { mode: LockingMode::Write, .. })))
+            if name == "DebugComponentB"
+    ));
+
+    // A failed request leaves none of its locks behind.
+    conn.acquire_lock(
+        entity,
+        vec![
+            LockDescriptor {
+                mode: LockingMode::Write,
+                name: "DebugComponentC".to_string(),
+            },
+            LockDescriptor {
+                mode: LockingMode::Write,
+                name: "DebugComponentB".to_string(),
+            },
+        ],
+        LOCK_TIME.into(),
+    )
+    .unwrap_err();
+    conn.acquire_lock(
+        entity,
+        lock(LockingMode::Write, "DebugComponentC"),
+        LOCK_TIME.into(),
+    )
+    .unwrap();
+}
+
+pub fn entity_lock_conflicts<B: LockingBackend>(conn: B) {
+    for mode in [LockingMode::Read, LockingMode::Write] {
+        let entity = Entity::new();
+        let _entity = conn
+            .acquire_lock(entity, vec![LockDescriptor::entity()], LOCK_TIME.into())
+            .unwrap();
+
+        assert!(matches!(
+            conn.acquire_lock(entity, lock(mode, "DebugComponentA"), LOCK_TIME.into()),
+            Err(LockingError::Conflict(_, _, requested, Some(_))) if requested == mode
+        ));
+    }
+
+    for mode in [LockingMode::Read, LockingMode::Write] {
+        let entity = Entity::new();
+        let _component = conn
+            .acquire_lock(entity, lock(mode, "DebugComponentA"), LOCK_TIME.into())
+            .unwrap();
+
+        assert!(matches!(
+            conn.acquire_lock(entity, vec![LockDescriptor::entity()], LOCK_TIME.into()),
+            Err(LockingError::Conflict(_, name, LockingMode::Exclusive, Some(_)))
+                if name == LockDescriptor::ENTITY
+        ));
+    }
+}
+
+pub fn locks_expire<B: LockingBackend>(conn: B) {
+    let entity = Entity::new();
+    let short = Duration::from_millis(100);
+
+    let lock = conn
+        .acquire_lock(entity, write_lock(), short.into())
+        .unwrap();
+    conn.acquire_lock(entity, write_lock(), LOCK_TIME.into())
+        .unwrap_err();
+
+    std::thread::sleep(short + Duration::from_millis(50));
+    assert_eq!(
+        conn.lock_stats().unwrap(),
+        LockStats {
+            active: 0,
+            expired: 1
+        }
+    );
+    assert!(conn.list_locks(Some(entity)).unwrap().is_empty());
+    assert!(matches!(
+        conn.renew_lock(&lock, LOCK_TIME),
+        Err(LockingError::Expired(id)) if id == lock.id()
+    ));
+
+    let _b = conn
+        .acquire_lock(entity, write_lock(), LOCK_TIME.into())
+        .unwrap();
+}
+
+pub fn renew_lock<B: LockingBackend>(conn: B) {
+    let entity = Entity::new();
+    let short = Duration::from_millis(300);
+
+    let lock = conn
+        .acquire_lock(entity, write_lock(), short.into())
+        .unwrap();
+    conn.renew_lock(&lock, Duration::from_secs(1)).unwrap();
+
+    // The lock would have expired by now, had it not been renewed.
+    std::thread::sleep(Duration::from_millis(500));
+    assert!(matches!(
+        conn.acquire_lock(entity, write_lock(), LOCK_TIME.into()),
+        Err(LockingError::Conflict(..))
+    ));
+}
+
+pub fn upgrade_lock<B: LockingBackend>(conn: B) {
+    let entity = Entity::new();
+
+    let lock = conn
+        .acquire_lock(entity, read_lock(), LOCK_TIME.into())
+        .unwrap();
+    let other = conn
+        .acquire_lock(entity, read_lock(), LOCK_TIME.into())
+        .unwrap();
+
+    assert!(matches!(
+        conn.upgrade_lock(&lock, entity, "DebugComponentA".to_string()),
+        Err(LockingError::Conflict(_, name, LockingMode::Write, Some(_))) if name == "DebugComponentA"
+    ));
+
+    conn.release_lock(other).unwrap();
+    conn.upgrade_lock(&lock, entity, "DebugComponentA".to_string())
+        .unwrap();
+    conn.acquire_lock(entity, read_lock(), LOCK_TIME.into())
+        .unwrap_err();
+
+    // Only held locks can be upgraded.
+    assert!(matches!(
+        conn.upgrade_lock(&lock, entity, "DebugComponentB".to_string()),
+        Err(LockingError::Expired(id)) if id == lock.id()
+    ));
+}
+
+pub fn multi_entity_locks<B: LockingBackend>(conn: B) {
+    let (a, b, c) = (Entity::new(), Entity::new(), Entity::new());
+
+    let _b = conn
+        .acquire_lock(b, write_lock(), LOCK_TIME.into())
+        .unwrap();
+
+    // Either every entity is locked, or none are.
+    assert!(matches!(
+        conn.acquire_locks(vec![(a, write_lock()), (b, write_lock())], LOCK_TIME),
+        Err(LockingError::Conflict(entity, ..)) if entity == b
+    ));
+    assert!(conn.list_locks(Some(a)).unwrap().is_empty());
+
+    let bulk = conn
+        .acquire_locks_bulk(
+            vec![(a, write_lock()), (b, write_lock()), (c, write_lock())],
+            LOCK_TIME,
+        )
+        .unwrap();
+    assert_eq!(bulk.granted, vec![a, c]);
+    assert_eq!(bulk.skipped, vec![b]);
+
+    conn.release_lock(bulk.lock).unwrap();
+    conn.acquire_locks(vec![(a, write_lock()), (c, write_lock())], LOCK_TIME)
+        .unwrap();
+}
+
+pub fn reject_empty_lock_requests<B: LockingBackend>(conn: B) {
+    assert!(matches!(
+        conn.acquire_lock(Entity::new(), vec![], LOCK_TIME.into()),
+        Err(LockingError::EmptyRequest)
+    ));
+    assert!(matches!(
+        conn.acquire_locks(
+            vec![(Entity::new(), write_lock()), (Entity::new(), vec![])],
+            LOCK_TIME
+        ),
+        Err(LockingError::EmptyRequest)
+    ));
+    assert!(conn.list_locks(None).unwrap().is_empty());
+}
+
+pub fn reject_out_of_range_expiry<B: LockingBackend>(conn: B) {
+    assert!(matches!(
+        conn.acquire_lock(Entity::new(), write_lock(), Duration::MAX.into()),
+        Err(LockingError::ExpiryOutOfRange(Duration::MAX))
+    ));
+}
+
+pub fn never_expiring_lock<B: JointBackend<Json> + 'static>(conn: B) {
+    let backend = Backend::<Json>::from_joint(conn);
+    let entity = Entity::new();
+
+    let forever = backend
+        .acquire_lock(entity, write_lock(), Expiry::Never)
+        .unwrap();
+    assert_eq!(forever.expires_at(), None);
+
+    // Renewing does not put an expiry on it either.
+    backend
+        .renew_lock(&forever, Duration::from_millis(10))
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(matches!(
+        backend.acquire_lock(entity, write_lock(), LOCK_TIME.into()),
+        Err(LockingError::Conflict(
+            _,
+            _,
+            _,
+            Some(ConflictingLock { expires: None, .. })
+        ))
+    ));
+
+    let id = Uuid::parse_str(&forever.id()).unwrap();
+    assert_eq!(backend.admin().force_release(id).unwrap(), 1);
+    backend
+        .acquire_lock(entity, read_lock(), LOCK_TIME.into())
+        .unwrap();
+    assert_eq!(backend.admin().force_release_entity(entity).unwrap(), 1);
+}